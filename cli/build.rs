@@ -0,0 +1,36 @@
+use std::process::Command;
+
+/// Record what the binary was built for, shown by `stakpak version --build-info`
+fn main() {
+    let env = |name: &str| std::env::var(name).unwrap_or_default();
+
+    println!("cargo:rustc-env=STAKPAK_BUILD_TARGET={}", env("TARGET"));
+    println!("cargo:rustc-env=STAKPAK_BUILD_PROFILE={}", env("PROFILE"));
+
+    let mut features: Vec<String> = std::env::vars()
+        .filter_map(|(name, _)| {
+            name.strip_prefix("CARGO_FEATURE_")
+                .map(|feature| feature.to_lowercase().replace('_', "-"))
+        })
+        .collect();
+    features.sort();
+    println!(
+        "cargo:rustc-env=STAKPAK_BUILD_FEATURES={}",
+        features.join(",")
+    );
+
+    let static_crt = env("CARGO_CFG_TARGET_FEATURE")
+        .split(',')
+        .any(|feature| feature == "crt-static");
+    println!("cargo:rustc-env=STAKPAK_BUILD_STATIC={}", static_crt);
+
+    let commit = Command::new("git")
+        .args(["rev-parse", "--short", "HEAD"])
+        .output()
+        .ok()
+        .filter(|output| output.status.success())
+        .map(|output| String::from_utf8_lossy(&output.stdout).trim().to_string())
+        .unwrap_or_else(|| "unknown".to_string());
+    println!("cargo:rustc-env=STAKPAK_BUILD_COMMIT={}", commit);
+    println!("cargo:rerun-if-changed=../.git/HEAD");
+}
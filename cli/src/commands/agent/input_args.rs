@@ -0,0 +1,175 @@
+use clap::Args;
+use stakpak_api::models::{AgentID, AgentInput};
+use std::path::Path;
+
+/// Provisioners kevin:v1 knows how to apply configurations with
+const PROVISIONERS: &[&str] = &["terraform", "kubernetes", "dockerfile", "github-actions"];
+
+/// Agent-specific inputs of `stakpak agent run`, filled into the agent's scratchpad before
+/// its first step so it does not have to ask for them
+#[derive(Args, Debug, Default, Clone, PartialEq)]
+pub struct AgentInputArgs {
+    /// [kevin:v1] Name of the infra project being applied
+    #[arg(long)]
+    pub project_name: Option<String>,
+    /// [kevin:v1] Local directory containing the configurations to apply
+    #[arg(long)]
+    pub source_dir: Option<String>,
+    /// [kevin:v1] Tool to apply the configurations with
+    #[arg(long, value_parser = clap::builder::PossibleValuesParser::new(PROVISIONERS))]
+    pub provisioner: Option<String>,
+    /// [kevin:v1] Primary configuration language of the project (e.g. hcl, yaml)
+    #[arg(long)]
+    pub configuration_language: Option<String>,
+    /// [kevin:v1] Environment variables the configurations need at runtime
+    #[arg(long)]
+    pub env_vars: Option<String>,
+    /// [kevin:v1] OS dependencies needed to apply the configurations
+    #[arg(long)]
+    pub runtime_dependencies: Option<String>,
+    /// [kevin:v1] Any other relevant information about the deployment
+    #[arg(long)]
+    pub notes: Option<String>,
+    /// [kevin:v1, stuart:v1] Layout of the files in the project
+    #[arg(long)]
+    pub directory_structure: Option<String>,
+}
+
+impl AgentInputArgs {
+    fn kevin_flags(&self) -> Vec<&'static str> {
+        [
+            ("--project-name", self.project_name.is_some()),
+            ("--source-dir", self.source_dir.is_some()),
+            ("--provisioner", self.provisioner.is_some()),
+            (
+                "--configuration-language",
+                self.configuration_language.is_some(),
+            ),
+            ("--env-vars", self.env_vars.is_some()),
+            (
+                "--runtime-dependencies",
+                self.runtime_dependencies.is_some(),
+            ),
+            ("--notes", self.notes.is_some()),
+        ]
+        .into_iter()
+        .filter(|(_, set)| *set)
+        .map(|(flag, _)| flag)
+        .collect()
+    }
+
+    fn is_empty(&self) -> bool {
+        self.kevin_flags().is_empty() && self.directory_structure.is_none()
+    }
+
+    /// Seed the scratchpad of `input` with the given flags, failing on flags the agent
+    /// does not take instead of silently dropping them
+    pub fn apply(&self, input: &mut AgentInput) -> Result<(), String> {
+        if self.is_empty() {
+            return Ok(());
+        }
+        let agent_id = input.get_agent_id();
+        match input {
+            AgentInput::KevinV1 { scratchpad, .. } => {
+                let source_dir = self
+                    .source_dir
+                    .as_deref()
+                    .map(validate_source_dir)
+                    .transpose()?;
+                let mut state = scratchpad.take().unwrap_or_default();
+                set(&mut state.project_name, &self.project_name);
+                set(&mut state.local_source_directory_path, &source_dir);
+                set(&mut state.configuration_tool, &self.provisioner);
+                set(
+                    &mut state.configuration_language,
+                    &self.configuration_language,
+                );
+                set(&mut state.environment_variables, &self.env_vars);
+                set(&mut state.runtime_dependencies, &self.runtime_dependencies);
+                set(&mut state.additional_notes, &self.notes);
+                set(&mut state.directory_structure, &self.directory_structure);
+                if state.user_os.is_none() {
+                    state.user_os = Some(std::env::consts::OS.to_string());
+                }
+                **scratchpad = Some(state);
+                Ok(())
+            }
+            AgentInput::StuartV1 { scratchpad, .. } => {
+                let flags = self.kevin_flags();
+                if !flags.is_empty() {
+                    return Err(unsupported(&agent_id, &flags));
+                }
+                let mut state = scratchpad.take().unwrap_or_default();
+                set(&mut state.directory_structure, &self.directory_structure);
+                **scratchpad = Some(state);
+                Ok(())
+            }
+            _ => {
+                let mut flags = self.kevin_flags();
+                if self.directory_structure.is_some() {
+                    flags.push("--directory-structure");
+                }
+                Err(unsupported(&agent_id, &flags))
+            }
+        }
+    }
+}
+
+fn set(field: &mut Option<String>, value: &Option<String>) {
+    if let Some(value) = value {
+        *field = Some(value.clone());
+    }
+}
+
+fn unsupported(agent_id: &AgentID, flags: &[&str]) -> String {
+    let agent = serde_json::to_value(agent_id)
+        .ok()
+        .and_then(|value| value.as_str().map(str::to_string))
+        .unwrap_or_else(|| format!("{:?}", agent_id));
+    format!(
+        "{} {} not supported by {}, run `stakpak agent agents` to see the inputs each agent takes",
+        flags.join(", "),
+        if flags.len() == 1 { "is" } else { "are" },
+        agent
+    )
+}
+
+/// Absolute path of the source directory, which has to exist for the agent to read it
+fn validate_source_dir(path: &str) -> Result<String, String> {
+    let dir = Path::new(path);
+    if !dir.is_dir() {
+        return Err(format!("--source-dir {} is not a directory", path));
+    }
+    dir.canonicalize()
+        .map(|dir| dir.to_string_lossy().to_string())
+        .map_err(|e| format!("Failed to resolve --source-dir {}: {}", path, e))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_apply_agent_inputs() {
+        let args = AgentInputArgs {
+            project_name: Some("shop".to_string()),
+            provisioner: Some("terraform".to_string()),
+            ..Default::default()
+        };
+
+        let mut input = AgentInput::new(&AgentID::KevinV1);
+        assert_eq!(args.apply(&mut input), Ok(()));
+        let AgentInput::KevinV1 { scratchpad, .. } = input else {
+            panic!("expected a kevin:v1 input");
+        };
+        let scratchpad = (*scratchpad).unwrap_or_default();
+        assert_eq!(scratchpad.project_name.as_deref(), Some("shop"));
+        assert_eq!(scratchpad.configuration_tool.as_deref(), Some("terraform"));
+
+        let mut input = AgentInput::new(&AgentID::StuartV1);
+        assert_eq!(
+            args.apply(&mut input),
+            Err("--project-name, --provisioner are not supported by stuart:v1, run `stakpak agent agents` to see the inputs each agent takes".to_string())
+        );
+    }
+}
@@ -1,4 +1,5 @@
 use crate::config::AppConfig;
+use crate::utils::checkpoint_labels::{CheckpointLabels, label_checkpoint, resolve_checkpoint_id};
 use clap::Subcommand;
 use regex::Regex;
 use stakpak_api::{
@@ -14,6 +15,9 @@ use uuid::Uuid;
 mod get_next_input;
 pub use get_next_input::*;
 
+mod input_args;
+pub use input_args::*;
+
 mod get_or_create_session;
 pub use get_or_create_session::*;
 
@@ -30,12 +34,30 @@ use super::flow;
 #[derive(Subcommand, PartialEq)]
 pub enum AgentCommands {
     /// List agent sessions
-    List,
+    List {
+        /// Maximum number of sessions to list
+        #[arg(long, short, default_value_t = 20)]
+        limit: usize,
+        /// List all sessions, ignoring --limit
+        #[arg(long, default_value_t = false)]
+        all: bool,
+    },
 
     /// Get agent checkpoint details
     Get {
-        /// Checkpoint ID to inspect
+        /// Checkpoint ID or label to inspect
+        checkpoint_id: String,
+        /// Print the raw checkpoint as JSON even when it has a run summary
+        #[arg(long, default_value_t = false)]
+        json: bool,
+    },
+
+    /// Label a checkpoint so it can be resumed by name
+    Label {
+        /// Checkpoint ID to label
         checkpoint_id: String,
+        /// Label to resume the checkpoint with (e.g. stakpak -c <label>)
+        label: String,
     },
 
     /// List available agents and what they do
@@ -45,15 +67,17 @@ pub enum AgentCommands {
     Run {
         /// Add user prompt to stir the agent
         user_prompt: Option<String>,
-        /// Resume Agent session from checkpoint ID
+        /// Resume Agent session from checkpoint ID or label
         #[arg(long, short)]
         checkpoint_id: Option<String>,
-        /// Agent ID to use (norbert:v1, dave:v1)
+        /// Agent ID to use (norbert:v1, dave:v1, kevin:v1, stuart:v1)
         #[arg(long, short, required_unless_present = "checkpoint_id")]
         agent_id: Option<AgentID>,
         /// Run in interactive mode
         #[arg(long, short, default_value_t = false)]
         interactive: bool,
+        #[command(flatten)]
+        inputs: AgentInputArgs,
     },
 }
 
@@ -74,15 +98,32 @@ impl AgentCommands {
                 println!("\tAn agent that containerizes applications using Docker, creating");
                 println!("\tproduction-ready container images and configurations.");
                 println!();
+                println!();
+                println!("kevin:v1");
+                println!("\tAn agent that applies existing infrastructure configurations.");
+                println!("\tInputs: --project-name, --source-dir, --provisioner,");
+                println!("\t--configuration-language, --env-vars, --runtime-dependencies,");
+                println!("\t--notes, --directory-structure");
+                println!();
+                println!();
+                println!("stuart:v1");
+                println!("\tA conversational agent that works on the files of a project.");
+                println!("\tInputs: --directory-structure");
+                println!();
             }
-            AgentCommands::List => {
+            AgentCommands::List { limit, all } => {
                 let client = Client::new(&ClientConfig {
                     api_key: config.api_key,
                     api_endpoint: config.api_endpoint,
+                    fallback_endpoints: config.fallback_api_endpoints,
                 })
                 .map_err(|e| e.to_string())?;
-                let sessions = client.list_agent_sessions().await?;
-                for session in sessions {
+                let sessions = client
+                    .list_agent_sessions(if all { None } else { Some(limit) })
+                    .await?;
+                let checkpoint_labels = CheckpointLabels::load();
+                let (shown, has_more) = (sessions.items.len(), sessions.has_more);
+                for session in sessions.items {
                     println!("Session ID: {}", session.id);
                     println!("Agent ID: {:?}", session.agent_id);
                     println!("Visibility: {:?}", session.visibility);
@@ -90,6 +131,10 @@ impl AgentCommands {
                     println!("Checkpoints:");
                     for checkpoint in session.checkpoints {
                         println!("  - ID: {}", checkpoint.id);
+                        let labels = checkpoint_labels.labels_for(&checkpoint.id);
+                        if !labels.is_empty() {
+                            println!("    Labels: {}", labels.join(", "));
+                        }
                         if let Some(parent) = checkpoint.parent {
                             println!("    Parent: {}", parent.id);
                         }
@@ -99,14 +144,20 @@ impl AgentCommands {
                     }
                     println!();
                 }
+                super::print_listing_hint("sessions", shown, has_more, all);
             }
             AgentCommands::Run {
                 user_prompt,
                 agent_id,
                 checkpoint_id,
                 interactive,
+                inputs,
             } => {
                 let client = Client::new(&config.clone().into()).map_err(|e| e.to_string())?;
+                let checkpoint_id = checkpoint_id
+                    .as_deref()
+                    .map(resolve_checkpoint_id)
+                    .transpose()?;
 
                 let agent_id = match (checkpoint_id.clone(), agent_id) {
                     (Some(checkpoint_id), _) => {
@@ -123,6 +174,7 @@ impl AgentCommands {
                 let mut input = AgentInput::new(&agent_id);
 
                 input.set_user_prompt(user_prompt);
+                inputs.apply(&mut input)?;
 
                 let (agent_id, session, checkpoint) =
                     get_or_create_session(&client, agent_id, checkpoint_id, Some(input.clone()))
@@ -133,8 +185,9 @@ impl AgentCommands {
                     let client_clone =
                         Client::new(&config.clone().into()).map_err(|e| e.to_string())?;
                     let flow_ref = flow_ref.clone();
+                    // Runs alongside the agent, so conflicts get markers instead of prompts
                     tokio::spawn(async move {
-                        flow::sync(&config_clone, &client_clone, &flow_ref, None).await
+                        flow::sync(&config_clone, &client_clone, &flow_ref, None, true).await
                     });
                 }
 
@@ -150,18 +203,35 @@ impl AgentCommands {
                 )
                 .await?;
             }
-            AgentCommands::Get { checkpoint_id } => {
+            AgentCommands::Get {
+                checkpoint_id,
+                json,
+            } => {
+                let renderer = config.markdown_renderer();
                 let client = Client::new(&ClientConfig {
                     api_key: config.api_key,
                     api_endpoint: config.api_endpoint,
+                    fallback_endpoints: config.fallback_api_endpoints,
                 })
                 .map_err(|e| e.to_string())?;
+                let checkpoint_id = resolve_checkpoint_id(&checkpoint_id)?;
                 let checkpoint_uuid = Uuid::from_str(&checkpoint_id).map_err(|e| e.to_string())?;
                 let output = client.get_agent_checkpoint(checkpoint_uuid).await?;
-                println!(
-                    "{}",
-                    serde_json::to_string_pretty(&output).unwrap_or_default()
-                );
+                match &output.summary {
+                    Some(summary) if !json => renderer.print(&summary.to_markdown()),
+                    _ => println!(
+                        "{}",
+                        serde_json::to_string_pretty(&output).unwrap_or_default()
+                    ),
+                }
+            }
+            AgentCommands::Label {
+                checkpoint_id,
+                label,
+            } => {
+                label_checkpoint(&checkpoint_id, &label)?;
+                println!("Labeled checkpoint {} as '{}'", checkpoint_id, label.trim());
+                println!("To resume, run:\nstakpak -c {}", label.trim());
             }
         }
         Ok(())
@@ -1,4 +1,5 @@
 use crate::commands::agent::run::tui::send_input_event;
+use crate::utils::checkpoint_replay::{CheckpointReplayConfig, replay_messages};
 use stakpak_api::Client;
 use stakpak_api::models::AgentOutput;
 use stakpak_shared::models::integrations::openai::{
@@ -10,6 +11,7 @@ use uuid::Uuid;
 pub async fn get_checkpoint_messages(
     client: &Client,
     checkpoint_id: &String,
+    replay: Option<&CheckpointReplayConfig>,
 ) -> Result<Vec<ChatMessage>, String> {
     let checkpoint_uuid = Uuid::parse_str(checkpoint_id).map_err(|_| {
         format!(
@@ -24,7 +26,10 @@ pub async fn get_checkpoint_messages(
         .map_err(|e| e.to_string())?;
     let checkpoint_output: AgentOutput = checkpoint.output;
 
-    Ok(get_messages_from_checkpoint_output(&checkpoint_output))
+    Ok(replay_messages(
+        get_messages_from_checkpoint_output(&checkpoint_output),
+        replay,
+    ))
 }
 
 pub fn get_messages_from_checkpoint_output(output: &AgentOutput) -> Vec<ChatMessage> {
@@ -3,8 +3,13 @@ pub mod helpers;
 pub mod mode_async;
 pub mod mode_interactive;
 pub mod mode_non_interactive;
+pub mod pipeline;
+pub mod session_env;
+pub mod shell_escape;
 pub mod stream;
+pub mod summary;
 pub mod tooling;
+pub mod triage;
 pub mod tui;
 
 pub use mode_async::{RunAsyncConfig, run_async};
@@ -1,16 +1,30 @@
 use crate::commands::agent::run::checkpoint::get_checkpoint_messages;
 use crate::commands::agent::run::helpers::{
-    add_local_context, convert_tools_map, tool_result, user_message,
+    add_local_context, annotate_tools_for_environment, attribute_file_edit, command_redactor,
+    convert_tools_map, redact_tool_call_commands, tool_result, user_message,
 };
-use crate::commands::agent::run::tooling::run_tool_call;
+use crate::commands::agent::run::pipeline::{execution_batches, run_tool_calls};
+use crate::commands::agent::run::summary::compose_run_summary;
+use crate::commands::agent::run::triage::{extract_failed_command, triage_failed_command};
+use crate::commands::history::save_transcript;
 use crate::config::AppConfig;
+use crate::utils::context_ranking::add_relevant_files;
+use crate::utils::env_fingerprint::{check_checkpoint_environment, pin_checkpoint_environment};
+use crate::utils::history_compaction::compact_history;
 use crate::utils::local_context::LocalContext;
+use crate::utils::mcp_trust::connect_mcp_servers;
 use crate::utils::network;
+use crate::utils::pull_request::{create_pull_request, has_uncommitted_changes};
+use crate::utils::run_metrics::{finish_session_metrics, record_turn, start_session_metrics};
+use crate::utils::slack::{ApprovalDecision, SlackApprover};
+use crate::utils::tool_usage::prioritize_tools;
+use stakpak_api::provider::{LLMProvider, Provider};
 use stakpak_api::{Client, ClientConfig};
 use stakpak_mcp_client::ClientManager;
 use stakpak_mcp_server::{MCPServerConfig, ToolMode};
 use stakpak_shared::local_store::LocalStore;
 use stakpak_shared::models::integrations::openai::ChatMessage;
+use stakpak_shared::session_tmp::cleanup_session_tmp_dirs;
 
 pub struct RunAsyncConfig {
     pub prompt: String,
@@ -18,12 +32,31 @@ pub struct RunAsyncConfig {
     pub local_context: Option<LocalContext>,
     pub verbose: bool,
     pub redact_secrets: bool,
+    pub slack_approval: bool,
+    /// Open a pull request with the changes once the run completes
+    pub create_pr: bool,
+    /// Compact the history like interactive sessions do, with `[history_compaction]`
+    pub compact_history: bool,
+    /// Never summarize the prompt when compacting the history
+    pub pin_prompt: bool,
 }
 
 pub async fn run_async(ctx: AppConfig, config: RunAsyncConfig) -> Result<(), String> {
     let mut chat_messages: Vec<ChatMessage> = Vec::new();
+    let renderer = ctx.markdown_renderer();
 
+    // Changes made before the run would end up in its pull request
+    if config.create_pr && has_uncommitted_changes()? {
+        return Err(
+            "--create-pr needs a clean working tree, commit or stash your changes first".into(),
+        );
+    }
+
+    start_session_metrics("async");
     let ctx_clone = ctx.clone();
+    let sandbox = ctx.sandbox_profile()?;
+    let dev_container = ctx.dev_container()?;
+    let command_policy = ctx.command_policy()?;
     let bind_address = network::find_available_bind_address_descending().await?;
     let local_mcp_server_host = format!("http://{}", bind_address);
     let redact_secrets = config.redact_secrets;
@@ -33,31 +66,55 @@ pub async fn run_async(ctx: AppConfig, config: RunAsyncConfig) -> Result<(), Str
                 api: ClientConfig {
                     api_key: ctx_clone.api_key.clone(),
                     api_endpoint: ctx_clone.api_endpoint.clone(),
+                    fallback_endpoints: ctx_clone.fallback_api_endpoints.clone(),
                 },
                 bind_address,
                 redact_secrets,
                 tool_mode: ToolMode::Combined,
+                sandbox,
+                dev_container,
+                command_policy,
+                access: None,
+                tls: None,
             },
             None,
         )
         .await;
     });
 
-    let clients = ClientManager::new(ctx.mcp_server_host.unwrap_or(local_mcp_server_host), None)
-        .await
-        .map_err(|e| e.to_string())?;
-    let tools_map = clients.get_tools().await.map_err(|e| e.to_string())?;
-    let tools = convert_tools_map(&tools_map);
+    let external_mcp_server_host = ctx.mcp_server_host.clone();
+    let mut clients =
+        ClientManager::new(ctx.mcp_server_host.unwrap_or(local_mcp_server_host), None)
+            .await
+            .map_err(|e| e.to_string())?;
+    let tools_map = connect_mcp_servers(&mut clients, external_mcp_server_host.as_deref()).await?;
+    let command_redactor =
+        command_redactor(config.redact_secrets, external_mcp_server_host.as_deref());
+    let mut tools = convert_tools_map(&tools_map);
+    annotate_tools_for_environment(&mut tools, &config.local_context);
+    prioritize_tools(&mut tools);
 
     let client = Client::new(&ClientConfig {
         api_key: ctx.api_key.clone(),
         api_endpoint: ctx.api_endpoint.clone(),
+        fallback_endpoints: ctx.fallback_api_endpoints.clone(),
     })
     .map_err(|e| e.to_string())?;
+    let provider = Provider::from_model(ctx.model.as_deref(), &ctx.llm_providers, client.clone())?;
+    let summary_provider = match &ctx.history_compaction.summary_model {
+        Some(model) => Provider::from_model(Some(model), &ctx.llm_providers, client.clone())?,
+        None => provider.clone(),
+    };
 
     // Load checkpoint messages if provided
     if let Some(checkpoint_id) = config.checkpoint_id {
-        let mut checkpoint_messages = get_checkpoint_messages(&client, &checkpoint_id).await?;
+        if let Some(warning) = check_checkpoint_environment(&checkpoint_id).await {
+            eprintln!("\n{}\n", warning);
+        }
+
+        let mut checkpoint_messages =
+            get_checkpoint_messages(&client, &checkpoint_id, ctx.checkpoint_replay.as_ref())
+                .await?;
 
         // Append checkpoint_id to the last assistant message if present
         if let Some(last_message) = checkpoint_messages.iter_mut().rev().find(|message| {
@@ -81,13 +138,37 @@ pub async fn run_async(ctx: AppConfig, config: RunAsyncConfig) -> Result<(), Str
         chat_messages.extend(checkpoint_messages);
     }
 
+    // Messages from here on belong to this run and make up its summary
+    let mut run_start = chat_messages.len();
+    // Messages of this run summarized away by compacting the history
+    let mut compacted_run: Vec<ChatMessage> = Vec::new();
+    let mut pinned: Vec<String> = Vec::new();
+
     // Add user prompt if provided
     if !config.prompt.is_empty() {
+        let (user_input, ranking_warning) =
+            add_relevant_files(&chat_messages, &config.prompt, ctx.context_ranking.as_ref()).await;
+        if let Some(warning) = ranking_warning {
+            eprintln!("{}", warning);
+        }
         let (user_input, _local_context) =
-            add_local_context(&chat_messages, &config.prompt, &config.local_context);
+            add_local_context(&chat_messages, &user_input, &config.local_context);
+        if config.pin_prompt {
+            pinned.push(user_input.clone());
+        }
         chat_messages.push(user_message(user_input));
     }
 
+    let approver = if config.slack_approval {
+        let slack_config = ctx
+            .slack
+            .clone()
+            .ok_or("Slack approval requires a [slack] section in ~/.stakpak/config.toml")?;
+        Some(SlackApprover::new(slack_config).await?)
+    } else {
+        None
+    };
+
     let mut step = 0;
     let max_steps = 50; // Safety limit to prevent infinite loops
 
@@ -101,12 +182,50 @@ pub async fn run_async(ctx: AppConfig, config: RunAsyncConfig) -> Result<(), Str
             break;
         }
 
+        if config.compact_history {
+            match compact_history(
+                &summary_provider,
+                &mut chat_messages,
+                &ctx.history_compaction,
+                &pinned,
+                false,
+                ctx.budget.as_ref(),
+            )
+            .await
+            {
+                Ok(Some(compaction)) => {
+                    println!("[{}]", compaction);
+                    // The run summary still lists what the summarized steps did
+                    let skipped = run_start.saturating_sub(compaction.first);
+                    run_start = run_start.min(compaction.first);
+                    compacted_run.extend(compaction.summarized.into_iter().skip(skipped));
+                }
+                Ok(None) => {}
+                // The request may still fit, a failed compaction should not end the run
+                Err(e) => eprintln!("[Failed to compact history: {}]", e),
+            }
+        }
+
+        if let Some(budget) = &ctx.budget {
+            budget.acquire_call()?;
+        }
+
         // Make chat completion request
-        let response = client
+        let mut response = provider
             .chat_completion(chat_messages.clone(), Some(tools.clone()))
             .await
             .map_err(|e| e.to_string())?;
+        record_turn(response.usage.total_tokens as u64);
+        if let Some(budget) = &ctx.budget {
+            budget.consume_tokens(response.usage.total_tokens as u64);
+        }
 
+        if let (Some(redactor), Some(tool_calls)) = (
+            &command_redactor,
+            response.choices[0].message.tool_calls.as_mut(),
+        ) {
+            redact_tool_call_commands(tool_calls, redactor);
+        }
         chat_messages.push(response.choices[0].message.clone());
         println!(
             "--[Step {}]---------------------------------------\n{}Running {} tools\n-------------------------------------------------\n",
@@ -114,10 +233,13 @@ pub async fn run_async(ctx: AppConfig, config: RunAsyncConfig) -> Result<(), Str
             if config.verbose {
                 format!(
                     "{}\n\n",
-                    response.choices[0].message.content.as_ref().unwrap_or(
-                        &stakpak_shared::models::integrations::openai::MessageContent::String(
-                            String::new()
-                        )
+                    renderer.render(
+                        &response.choices[0]
+                            .message
+                            .content
+                            .as_ref()
+                            .map(|c| c.to_string())
+                            .unwrap_or_default()
                     )
                 )
             } else {
@@ -137,30 +259,100 @@ pub async fn run_async(ctx: AppConfig, config: RunAsyncConfig) -> Result<(), Str
                 break;
             }
 
+            // Slack reviewers approve one call at a time, after seeing the previous results
+            let concurrency = if approver.is_some() {
+                1
+            } else {
+                ctx.agent_concurrency
+            };
+
             // Execute all tool calls
-            for (i, tool_call) in tool_calls.iter().enumerate() {
-                let result = run_tool_call(&clients, &tools_map, tool_call).await?;
-                if let Some(result) = result {
-                    let result_content = result
-                        .content
-                        .iter()
-                        .map(|c| match c.raw.as_text() {
-                            Some(text) => text.text.clone(),
-                            None => String::new(),
-                        })
-                        .collect::<Vec<String>>()
-                        .join("\n");
-
-                    if config.verbose {
+            for batch in execution_batches(tool_calls, concurrency) {
+                let mut approved = Vec::new();
+                for (i, tool_call) in tool_calls
+                    .iter()
+                    .enumerate()
+                    .take(batch.end)
+                    .skip(batch.start)
+                {
+                    if let Some(approver) = &approver {
                         println!(
-                            "[{} {} result]--------------------------------\n{}\n--------------------------------",
-                            i + 1,
-                            tool_call.function.name,
-                            result_content
+                            "[Waiting for Slack approval of {}]",
+                            tool_call.function.name
                         );
+                        let rejection = match approver.request_approval(tool_call).await? {
+                            ApprovalDecision::Approved(_) => None,
+                            ApprovalDecision::Denied(user) => {
+                                Some(format!("Tool call was denied by reviewer {}", user))
+                            }
+                            ApprovalDecision::TimedOut => {
+                                Some("Tool call was denied: approval timed out".to_string())
+                            }
+                        };
+                        if let Some(rejection) = rejection {
+                            println!("[{}]", rejection);
+                            chat_messages.push(tool_result(tool_call.id.clone(), rejection));
+                            continue;
+                        }
                     }
+                    approved.push((i, tool_call.clone()));
+                }
+
+                let approved_calls: Vec<_> = approved
+                    .iter()
+                    .map(|(_, tool_call)| tool_call.clone())
+                    .collect();
+                let results =
+                    run_tool_calls(&clients, &tools_map, &approved_calls, concurrency).await?;
+
+                for ((i, tool_call), result) in approved.iter().zip(results) {
+                    if let Some(result) = result {
+                        let result_content = result
+                            .content
+                            .iter()
+                            .map(|c| match c.raw.as_text() {
+                                Some(text) => text.text.clone(),
+                                None => String::new(),
+                            })
+                            .collect::<Vec<String>>()
+                            .join("\n");
 
-                    chat_messages.push(tool_result(tool_call.id.clone(), result_content.clone()));
+                        if config.verbose {
+                            println!(
+                                "[{} {} result]--------------------------------\n{}\n--------------------------------",
+                                i + 1,
+                                tool_call.function.name,
+                                result_content
+                            );
+                        }
+
+                        if ctx.error_triage {
+                            if let Some((command, error_lines)) =
+                                extract_failed_command(tool_call, &result_content)
+                            {
+                                match triage_failed_command(
+                                    &client,
+                                    &command,
+                                    &error_lines,
+                                    ctx.budget.as_ref(),
+                                )
+                                .await
+                                {
+                                    Ok(triage) => println!(
+                                        "[Triage]-----------------------------------------\n{}\n-------------------------------------------------",
+                                        triage
+                                    ),
+                                    Err(e) => eprintln!("Failed to triage command: {}", e),
+                                }
+                            }
+                        }
+
+                        if result.is_error != Some(true) {
+                            attribute_file_edit(&chat_messages, tool_call);
+                        }
+                        chat_messages
+                            .push(tool_result(tool_call.id.clone(), result_content.clone()));
+                    }
                 }
             }
 
@@ -190,8 +382,37 @@ pub async fn run_async(ctx: AppConfig, config: RunAsyncConfig) -> Result<(), Str
 
     println!("Async execution completed after {} steps", step - 1);
 
+    if let Err(e) = save_transcript(&chat_messages) {
+        eprintln!("Failed to save session transcript: {}", e);
+    }
+
+    compacted_run.extend_from_slice(&chat_messages[run_start..]);
+    let summary = compose_run_summary(&compacted_run, step - 1);
+    if let Err(e) = LocalStore::write_session_data("run_summary.md", &summary.to_markdown()) {
+        eprintln!("Failed to write run summary: {}", e);
+    }
+
+    if config.create_pr {
+        match create_pull_request(&ctx.pull_request.clone().unwrap_or_default(), &summary).await {
+            Ok(Some(url)) => println!("Pull request opened: {}", url),
+            Ok(None) => println!("No file changes, no pull request opened"),
+            Err(e) => eprintln!("Failed to create pull request: {}", e),
+        }
+    }
+
     // Save checkpoint to file if available
     if let Some(checkpoint_id) = &latest_checkpoint {
+        if let Err(e) = client
+            .attach_checkpoint_summary(*checkpoint_id, &summary)
+            .await
+        {
+            eprintln!("Failed to attach run summary to checkpoint: {}", e);
+        }
+
+        if let Err(e) = pin_checkpoint_environment(&checkpoint_id.to_string()).await {
+            eprintln!("Failed to pin checkpoint environment: {}", e);
+        }
+
         match LocalStore::write_session_data("checkpoint", checkpoint_id.to_string().as_str()) {
             Ok(path) => {
                 println!("Checkpoint {} saved to {}", checkpoint_id, path);
@@ -202,5 +423,12 @@ pub async fn run_async(ctx: AppConfig, config: RunAsyncConfig) -> Result<(), Str
         }
     }
 
+    if let Err(e) = finish_session_metrics() {
+        eprintln!("Failed to record session metrics: {}", e);
+    }
+    if let Err(e) = cleanup_session_tmp_dirs() {
+        eprintln!("Failed to clean up session scratch directories: {}", e);
+    }
+
     Ok(())
 }
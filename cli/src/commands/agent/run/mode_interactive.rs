@@ -3,37 +3,68 @@ use crate::commands::agent::run::checkpoint::{
     get_messages_from_checkpoint_output,
 };
 use crate::commands::agent::run::helpers::{
-    add_local_context, convert_tools_map, tool_result, user_message,
+    ASK_USER_TOOL_NAME, add_local_context, annotate_tools_for_environment, ask_user_tool,
+    attribute_file_edit, command_redactor, convert_tools_map, redact_tool_call_commands,
+    tool_result, user_message,
 };
+use crate::commands::agent::run::session_env::{apply_session_env, remove_session_env};
+use crate::commands::agent::run::shell_escape::{shared_shell_output_message, spawn_shell_escape};
 use crate::commands::agent::run::stream::process_responses_stream;
 use crate::commands::agent::run::tooling::{list_sessions, run_tool_call};
+use crate::commands::agent::run::triage::{extract_failed_command, triage_failed_command};
 use crate::commands::agent::run::tui::{send_input_event, send_tool_call};
+use crate::commands::history::save_transcript;
 use crate::config::AppConfig;
+use crate::utils::account_cache::get_account;
+use crate::utils::audit_log::{AuditEntry, record_audit_entry};
 use crate::utils::check_update::get_latest_cli_version;
+use crate::utils::checkpoint_labels::label_checkpoint;
+use crate::utils::checkpoint_replay::replay_messages;
+use crate::utils::citations::verify_citations;
+use crate::utils::context_ranking::add_relevant_files;
+use crate::utils::env_fingerprint::{check_checkpoint_environment, pin_checkpoint_environment};
+use crate::utils::history_compaction::compact_history;
 use crate::utils::local_context::LocalContext;
+use crate::utils::mcp_trust::connect_mcp_servers;
 use crate::utils::network;
+use crate::utils::run_metrics::{finish_session_metrics, start_session_metrics};
+use crate::utils::session_mirror::{MirrorEvent, SessionMirror};
+use crate::utils::tool_usage::prioritize_tools;
+use stakpak_api::provider::{LLMProvider, Provider};
 use stakpak_api::{Client, ClientConfig};
 use stakpak_mcp_client::ClientManager;
 use stakpak_mcp_server::{MCPServerConfig, ToolMode};
-use stakpak_shared::models::integrations::openai::{ChatMessage, ToolCall};
+use stakpak_shared::models::integrations::openai::{ChatMessage, Role, ToolCall};
+use stakpak_shared::secrets::redact_secrets;
+use stakpak_shared::session_tmp::cleanup_session_tmp_dirs;
 use stakpak_tui::{InputEvent, OutputEvent};
+use std::collections::HashMap;
 use uuid::Uuid;
 
 pub struct RunInteractiveConfig {
     pub checkpoint_id: Option<String>,
     pub local_context: Option<LocalContext>,
     pub redact_secrets: bool,
+    /// Message sent to the agent as soon as the session starts
+    pub initial_prompt: Option<String>,
 }
 
 pub async fn run_interactive(ctx: AppConfig, config: RunInteractiveConfig) -> Result<(), String> {
     let mut messages: Vec<ChatMessage> = Vec::new();
     let mut tools_queue: Vec<ToolCall> = Vec::new();
+    // What was sent for each prompt typed in the TUI, so it can be pinned
+    let mut sent_prompts: HashMap<String, String> = HashMap::new();
+    let mut pinned: Vec<String> = Vec::new();
     let (input_tx, input_rx) = tokio::sync::mpsc::channel::<InputEvent>(100);
     let (output_tx, mut output_rx) = tokio::sync::mpsc::channel::<OutputEvent>(100);
     let (mcp_progress_tx, mut mcp_progress_rx) = tokio::sync::mpsc::channel(100);
+    start_session_metrics("interactive");
     let (shutdown_tx, shutdown_rx) = tokio::sync::broadcast::channel::<()>(1);
 
     let ctx_clone = ctx.clone();
+    let sandbox = ctx.sandbox_profile()?;
+    let dev_container = ctx.dev_container()?;
+    let command_policy = ctx.command_policy()?;
     let bind_address = network::find_available_bind_address_descending().await?;
     let local_mcp_server_host = format!("http://{}", bind_address);
 
@@ -44,10 +75,16 @@ pub async fn run_interactive(ctx: AppConfig, config: RunInteractiveConfig) -> Re
                 api: ClientConfig {
                     api_key: ctx_clone.api_key.clone(),
                     api_endpoint: ctx_clone.api_endpoint.clone(),
+                    fallback_endpoints: ctx_clone.fallback_api_endpoints.clone(),
                 },
                 redact_secrets: config.redact_secrets,
                 bind_address,
                 tool_mode: ToolMode::Combined,
+                sandbox,
+                dev_container,
+                command_policy,
+                access: None,
+                tls: None,
             },
             Some(shutdown_rx),
         )
@@ -55,21 +92,38 @@ pub async fn run_interactive(ctx: AppConfig, config: RunInteractiveConfig) -> Re
     });
 
     // Initialize clients and tools
-    let clients = ClientManager::new(
+    let external_mcp_server_host = ctx.mcp_server_host.clone();
+    let command_redactor =
+        command_redactor(config.redact_secrets, external_mcp_server_host.as_deref());
+    let mut clients = ClientManager::new(
         ctx.mcp_server_host.unwrap_or(local_mcp_server_host),
         Some(mcp_progress_tx),
     )
     .await
     .map_err(|e| e.to_string())?;
-    let tools_map = clients.get_tools().await.map_err(|e| e.to_string())?;
-    let tools = convert_tools_map(&tools_map);
+    let tools_map = connect_mcp_servers(&mut clients, external_mcp_server_host.as_deref()).await?;
+    let mut tools = convert_tools_map(&tools_map);
+    annotate_tools_for_environment(&mut tools, &config.local_context);
+    prioritize_tools(&mut tools);
+    tools.push(ask_user_tool());
+
+    let initial_output_tx = output_tx.clone();
+    // Viewers can follow the session with `stakpak attach`, mirroring is best effort
+    let mirror = SessionMirror::start(&Uuid::new_v4().to_string());
 
     // Spawn TUI task
+    let confirmation_timeout = ctx.confirmation_timeout;
     let tui_handle = tokio::spawn(async move {
         let latest_version = get_latest_cli_version().await;
-        let _ = stakpak_tui::run_tui(input_rx, output_tx, shutdown_tx, latest_version.ok())
-            .await
-            .map_err(|e| e.to_string());
+        let _ = stakpak_tui::run_tui(
+            input_rx,
+            output_tx,
+            shutdown_tx,
+            latest_version.ok(),
+            confirmation_timeout,
+        )
+        .await
+        .map_err(|e| e.to_string());
     });
 
     let input_tx_clone = input_tx.clone();
@@ -80,26 +134,70 @@ pub async fn run_interactive(ctx: AppConfig, config: RunInteractiveConfig) -> Re
     });
 
     // Spawn client task
-    let client_handle: tokio::task::JoinHandle<Result<Vec<ChatMessage>, String>> =
-        tokio::spawn(async move {
-            let client = Client::new(&ClientConfig {
+    let client_handle: tokio::task::JoinHandle<Result<Vec<ChatMessage>, String>> = tokio::spawn(
+        async move {
+            let client_config = ClientConfig {
                 api_key: ctx.api_key.clone(),
                 api_endpoint: ctx.api_endpoint.clone(),
-            })
-            .map_err(|e| e.to_string())?;
+                fallback_endpoints: ctx.fallback_api_endpoints.clone(),
+            };
+            let client = Client::new(&client_config).map_err(|e| e.to_string())?;
+            let provider =
+                Provider::from_model(ctx.model.as_deref(), &ctx.llm_providers, client.clone())?;
+            let summary_provider = match &ctx.history_compaction.summary_model {
+                Some(model) => {
+                    Provider::from_model(Some(model), &ctx.llm_providers, client.clone())?
+                }
+                None => provider.clone(),
+            };
 
-            let data = client.get_my_account().await?;
-            send_input_event(&input_tx, InputEvent::GetStatus(data.to_text())).await?;
+            let account = get_account(&client, &client_config, false).await?;
+            send_input_event(&input_tx, InputEvent::GetStatus(account.account.to_text())).await?;
+            if let Some(warning) = account.warning {
+                send_input_event(&input_tx, InputEvent::SystemMessage(warning)).await?;
+            }
+
+            let mirror = match mirror {
+                Ok(mirror) => {
+                    send_input_event(
+                        &input_tx,
+                        InputEvent::SystemMessage(format!(
+                            "Watch this session read-only from another terminal with: stakpak attach {}",
+                            mirror.session_id()
+                        )),
+                    )
+                    .await?;
+                    Some(mirror)
+                }
+                Err(_) => None,
+            };
+            let publish = |event: MirrorEvent| {
+                if let Some(mirror) = &mirror {
+                    mirror.publish(event);
+                }
+            };
 
             if let Some(checkpoint_id) = config.checkpoint_id {
-                let checkpoint_messages = get_checkpoint_messages(&client, &checkpoint_id).await?;
+                if let Some(warning) = check_checkpoint_environment(&checkpoint_id).await {
+                    send_input_event(&input_tx, InputEvent::Error(warning)).await?;
+                }
+
+                let checkpoint_messages = get_checkpoint_messages(
+                    &client,
+                    &checkpoint_id,
+                    ctx.checkpoint_replay.as_ref(),
+                )
+                .await?;
 
-                let (chat_messages, tool_calls) = extract_checkpoint_messages_and_tool_calls(
+                let (chat_messages, mut tool_calls) = extract_checkpoint_messages_and_tool_calls(
                     &checkpoint_id,
                     &input_tx,
                     checkpoint_messages,
                 )
                 .await?;
+                if let Some(redactor) = &command_redactor {
+                    redact_tool_call_commands(&mut tool_calls, redactor);
+                }
 
                 tools_queue.extend(tool_calls.clone());
 
@@ -111,9 +209,31 @@ pub async fn run_interactive(ctx: AppConfig, config: RunInteractiveConfig) -> Re
                 messages.extend(chat_messages);
             }
 
+            if let Some(prompt) = config.initial_prompt.clone() {
+                send_input_event(
+                    &input_tx,
+                    InputEvent::SystemMessage(format!("Starting with: {}", prompt)),
+                )
+                .await?;
+                initial_output_tx
+                    .send(OutputEvent::UserMessage(prompt))
+                    .await
+                    .map_err(|e| e.to_string())?;
+            }
+            drop(initial_output_tx);
+
             while let Some(output_event) = output_rx.recv().await {
                 match output_event {
-                    OutputEvent::UserMessage(user_input) => {
+                    OutputEvent::UserMessage(prompt) => {
+                        publish(MirrorEvent::User {
+                            content: prompt.clone(),
+                        });
+                        let (user_input, ranking_warning) =
+                            add_relevant_files(&messages, &prompt, ctx.context_ranking.as_ref())
+                                .await;
+                        if let Some(warning) = ranking_warning {
+                            send_input_event(&input_tx, InputEvent::SystemMessage(warning)).await?;
+                        }
                         let (user_input, local_context) =
                             add_local_context(&messages, &user_input, &config.local_context);
                         if let Some(local_context) = local_context {
@@ -123,6 +243,7 @@ pub async fn run_interactive(ctx: AppConfig, config: RunInteractiveConfig) -> Re
                             )
                             .await?;
                         }
+                        sent_prompts.insert(prompt, user_input.clone());
                         messages.push(user_message(user_input));
                     }
                     OutputEvent::AcceptTool(tool_call) => {
@@ -139,8 +260,21 @@ pub async fn run_interactive(ctx: AppConfig, config: RunInteractiveConfig) -> Re
                                 .collect::<Vec<String>>()
                                 .join("\n");
 
+                            if result.is_error != Some(true) {
+                                attribute_file_edit(&messages, &tool_call);
+                            }
                             messages
                                 .push(tool_result(tool_call.clone().id, result_content.clone()));
+                            publish(MirrorEvent::ToolResult {
+                                name: tool_call.function.name.clone(),
+                                result: result_content.clone(),
+                            });
+
+                            let failed_command = if ctx.error_triage {
+                                extract_failed_command(&tool_call, &result_content)
+                            } else {
+                                None
+                            };
 
                             send_input_event(
                                 &input_tx,
@@ -152,6 +286,21 @@ pub async fn run_interactive(ctx: AppConfig, config: RunInteractiveConfig) -> Re
                                 ),
                             )
                             .await?;
+
+                            if let Some((command, error_lines)) = failed_command {
+                                match triage_failed_command(&client, &command, &error_lines).await {
+                                    Ok(triage) => {
+                                        send_input_event(
+                                            &input_tx,
+                                            InputEvent::ErrorTriage(triage),
+                                        )
+                                        .await?;
+                                    }
+                                    Err(e) => {
+                                        send_input_event(&input_tx, InputEvent::Error(e)).await?;
+                                    }
+                                }
+                            }
                             send_input_event(&input_tx, InputEvent::Loading(false)).await?;
                         }
 
@@ -161,7 +310,26 @@ pub async fn run_interactive(ctx: AppConfig, config: RunInteractiveConfig) -> Re
                             continue;
                         }
                     }
-                    OutputEvent::RejectTool(_tool_call) => {
+                    OutputEvent::SubmitAnswers(tool_call, answers) => {
+                        let answers = serde_json::to_string(&serde_json::json!({
+                            "answers": answers,
+                        }))
+                        .map_err(|e| e.to_string())?;
+                        messages.push(tool_result(tool_call.id, answers));
+
+                        if !tools_queue.is_empty() {
+                            let tool_call = tools_queue.remove(0);
+                            send_tool_call(&input_tx, &tool_call).await?;
+                            continue;
+                        }
+                    }
+                    OutputEvent::RejectTool(tool_call) => {
+                        if tool_call.function.name == ASK_USER_TOOL_NAME {
+                            messages.push(tool_result(
+                                tool_call.id,
+                                "The user dismissed the questions without answering".to_string(),
+                            ));
+                        }
                         if !tools_queue.is_empty() {
                             let tool_call = tools_queue.remove(0);
                             send_tool_call(&input_tx, &tool_call).await?;
@@ -180,19 +348,192 @@ pub async fn run_interactive(ctx: AppConfig, config: RunInteractiveConfig) -> Re
                         }
                         continue;
                     }
+                    OutputEvent::ConfirmationTimedOut {
+                        tool_call,
+                        approved,
+                    } => {
+                        let entry = AuditEntry::new(
+                            &tool_call,
+                            if approved { "approved" } else { "skipped" },
+                            format!(
+                                "confirmation timed out after {}s",
+                                confirmation_timeout.map(|t| t.secs).unwrap_or_default()
+                            ),
+                        );
+                        if let Err(e) = record_audit_entry(&entry) {
+                            send_input_event(
+                                &input_tx,
+                                InputEvent::Error(format!("Failed to write audit log: {}", e)),
+                            )
+                            .await?;
+                        }
+                        continue;
+                    }
+                    OutputEvent::LabelCheckpoint(label) => {
+                        let latest_checkpoint = messages
+                            .iter()
+                            .rev()
+                            .find(|m| m.role == Role::Assistant)
+                            .and_then(|m| {
+                                m.content.as_ref().and_then(|c| c.extract_checkpoint_id())
+                            });
+                        let event = match latest_checkpoint {
+                            Some(checkpoint_id) => {
+                                match label_checkpoint(&checkpoint_id.to_string(), &label) {
+                                    Ok(()) => InputEvent::SystemMessage(format!(
+                                        "Labeled checkpoint {} as '{}', resume with: stakpak -c {}",
+                                        checkpoint_id, label, label
+                                    )),
+                                    Err(e) => InputEvent::Error(e),
+                                }
+                            }
+                            None => InputEvent::Error(
+                                "No checkpoint to label yet, send a message to the agent first"
+                                    .to_string(),
+                            ),
+                        };
+                        send_input_event(&input_tx, event).await?;
+                        continue;
+                    }
+                    OutputEvent::RunShellCommand(id, command) => {
+                        spawn_shell_escape(id, command, input_tx.clone());
+                        continue;
+                    }
+                    OutputEvent::ShareShellOutput(result) => {
+                        let mut message = shared_shell_output_message(&result);
+                        if config.redact_secrets {
+                            message =
+                                redact_secrets(&message, None, &HashMap::new()).redacted_string;
+                        }
+                        // Part of the next request, sharing alone doesn't prompt the agent
+                        messages.push(user_message(message));
+                        continue;
+                    }
+                    OutputEvent::SetSessionEnv {
+                        name,
+                        value,
+                        secret,
+                    } => {
+                        let event = match apply_session_env(&name, value, secret) {
+                            Ok(note) => {
+                                // Part of the next request, like shared shell output
+                                messages.push(user_message(note));
+                                InputEvent::SystemMessage(format!(
+                                    "{} is set for every command the agent runs",
+                                    name
+                                ))
+                            }
+                            Err(e) => InputEvent::Error(e),
+                        };
+                        send_input_event(&input_tx, event).await?;
+                        continue;
+                    }
+                    OutputEvent::UnsetSessionEnv(name) => {
+                        let event = match remove_session_env(&name) {
+                            Ok(note) => {
+                                messages.push(user_message(note));
+                                InputEvent::SystemMessage(format!("Unset {}", name))
+                            }
+                            Err(e) => InputEvent::Error(e),
+                        };
+                        send_input_event(&input_tx, event).await?;
+                        continue;
+                    }
+                    OutputEvent::CompactHistory => {
+                        send_input_event(&input_tx, InputEvent::Loading(true)).await?;
+                        let event = match compact_history(
+                            &summary_provider,
+                            &mut messages,
+                            &ctx.history_compaction,
+                            &pinned,
+                            true,
+                            ctx.budget.as_ref(),
+                        )
+                        .await
+                        {
+                            Ok(Some(compaction)) => {
+                                InputEvent::SystemMessage(compaction.to_string())
+                            }
+                            Ok(None) => InputEvent::SystemMessage(
+                                "Nothing to compact yet, the history fits in the recent messages kept as they are".to_string(),
+                            ),
+                            Err(e) => InputEvent::Error(format!("Failed to compact history: {}", e)),
+                        };
+                        send_input_event(&input_tx, InputEvent::Loading(false)).await?;
+                        send_input_event(&input_tx, event).await?;
+                        continue;
+                    }
+                    OutputEvent::PinMessage(prompt) => {
+                        // The TUI marks the message only once it is confirmed here
+                        match sent_prompts.get(&prompt) {
+                            Some(content) => {
+                                if !pinned.contains(content) {
+                                    pinned.push(content.clone());
+                                }
+                                send_input_event(&input_tx, InputEvent::MessagePinned(prompt))
+                                    .await?;
+                                send_input_event(
+                                    &input_tx,
+                                    InputEvent::SystemMessage(
+                                        "Pinned, compacting the history keeps this message as it is"
+                                            .to_string(),
+                                    ),
+                                )
+                                .await?;
+                            }
+                            None => {
+                                send_input_event(
+                                    &input_tx,
+                                    InputEvent::Error(
+                                        "Only messages sent in this session can be pinned"
+                                            .to_string(),
+                                    ),
+                                )
+                                .await?;
+                            }
+                        }
+                        continue;
+                    }
+                    OutputEvent::UnpinMessage(prompt) => {
+                        if let Some(content) = sent_prompts.get(&prompt) {
+                            pinned.retain(|pin| pin != content);
+                        }
+                        send_input_event(
+                            &input_tx,
+                            InputEvent::SystemMessage("Unpinned".to_string()),
+                        )
+                        .await?;
+                        continue;
+                    }
                     OutputEvent::SwitchToSession(session_id) => {
+                        // Pins belong to the session being left, the TUI drops its own too
+                        pinned.clear();
+                        sent_prompts.clear();
                         send_input_event(&input_tx, InputEvent::Loading(true)).await?;
                         let session_id = Uuid::parse_str(&session_id).map_err(|e| e.to_string())?;
                         match client.get_agent_session_latest_checkpoint(session_id).await {
                             Ok(checkpoint) => {
-                                let (chat_messages, tool_calls) =
+                                let checkpoint_id = checkpoint.checkpoint.id.to_string();
+                                if let Some(warning) =
+                                    check_checkpoint_environment(&checkpoint_id).await
+                                {
+                                    send_input_event(&input_tx, InputEvent::Error(warning)).await?;
+                                }
+
+                                let (chat_messages, mut tool_calls) =
                                     extract_checkpoint_messages_and_tool_calls(
-                                        &checkpoint.checkpoint.id.to_string(),
+                                        &checkpoint_id,
                                         &input_tx,
-                                        get_messages_from_checkpoint_output(&checkpoint.output),
+                                        replay_messages(
+                                            get_messages_from_checkpoint_output(&checkpoint.output),
+                                            ctx.checkpoint_replay.as_ref(),
+                                        ),
                                     )
                                     .await?;
                                 messages.extend(chat_messages);
+                                if let Some(redactor) = &command_redactor {
+                                    redact_tool_call_commands(&mut tool_calls, redactor);
+                                }
 
                                 tools_queue.extend(tool_calls.clone());
                                 if !tools_queue.is_empty() {
@@ -211,11 +552,49 @@ pub async fn run_interactive(ctx: AppConfig, config: RunInteractiveConfig) -> Re
                 }
                 send_input_event(&input_tx, InputEvent::Loading(true)).await?;
 
-                let mut stream = client
+                if ctx.history_compaction.auto {
+                    match compact_history(
+                        &summary_provider,
+                        &mut messages,
+                        &ctx.history_compaction,
+                        &pinned,
+                        false,
+                        ctx.budget.as_ref(),
+                    )
+                    .await
+                    {
+                        Ok(Some(compaction)) => {
+                            send_input_event(
+                                &input_tx,
+                                InputEvent::SystemMessage(compaction.to_string()),
+                            )
+                            .await?;
+                        }
+                        Ok(None) => {}
+                        // The request may still fit, a failed compaction should not end the turn
+                        Err(e) => {
+                            send_input_event(
+                                &input_tx,
+                                InputEvent::Error(format!("Failed to compact history: {}", e)),
+                            )
+                            .await?;
+                        }
+                    }
+                }
+
+                if let Some(budget) = &ctx.budget {
+                    if let Err(e) = budget.acquire_call() {
+                        send_input_event(&input_tx, InputEvent::Loading(false)).await?;
+                        send_input_event(&input_tx, InputEvent::Error(e)).await?;
+                        continue;
+                    }
+                }
+
+                let mut stream = provider
                     .chat_completion_stream(messages.clone(), Some(tools.clone()))
                     .await?;
 
-                let response = match process_responses_stream(&mut stream, &input_tx).await {
+                let mut response = match process_responses_stream(&mut stream, &input_tx).await {
                     Ok(response) => response,
                     Err(e) => {
                         send_input_event(&input_tx, InputEvent::Loading(false)).await?;
@@ -227,8 +606,50 @@ pub async fn run_interactive(ctx: AppConfig, config: RunInteractiveConfig) -> Re
                     }
                 };
                 send_input_event(&input_tx, InputEvent::Loading(false)).await?;
+                if let Some(budget) = &ctx.budget {
+                    budget.consume_tokens(response.usage.total_tokens as u64);
+                }
 
+                if let (Some(redactor), Some(tool_calls)) = (
+                    &command_redactor,
+                    response.choices[0].message.tool_calls.as_mut(),
+                ) {
+                    redact_tool_call_commands(tool_calls, redactor);
+                }
                 messages.push(response.choices[0].message.clone());
+                publish(MirrorEvent::Assistant {
+                    content: response.choices[0]
+                        .message
+                        .content
+                        .as_ref()
+                        .map(|content| content.to_string())
+                        .unwrap_or_default(),
+                    tool_calls: response.choices[0]
+                        .message
+                        .tool_calls
+                        .clone()
+                        .unwrap_or_default(),
+                });
+
+                // Check the workspace files the answer cites and list them under it
+                if let Some(content) = &response.choices[0].message.content {
+                    let citations = verify_citations(&content.to_string());
+                    if !citations.is_empty() {
+                        send_input_event(&input_tx, InputEvent::Citations(citations)).await?;
+                    }
+                }
+
+                // Pin the environment to the new checkpoint in the background
+                if let Some(checkpoint_id) = response.choices[0]
+                    .message
+                    .content
+                    .as_ref()
+                    .and_then(|c| c.extract_checkpoint_id())
+                {
+                    tokio::spawn(async move {
+                        let _ = pin_checkpoint_environment(&checkpoint_id.to_string()).await;
+                    });
+                }
 
                 // Send tool calls to TUI if present
                 if let Some(tool_calls) = &response.choices[0].message.tool_calls {
@@ -242,18 +663,24 @@ pub async fn run_interactive(ctx: AppConfig, config: RunInteractiveConfig) -> Re
             }
 
             Ok(messages)
-        });
+        },
+    );
 
     // Wait for all tasks to finish
     let (client_res, _, _, _) =
         tokio::try_join!(client_handle, tui_handle, mcp_handle, mcp_progress_handle)
             .map_err(|e| e.to_string())?;
 
+    let messages = client_res?;
+    if let Err(e) = save_transcript(&messages) {
+        eprintln!("Failed to save session transcript: {}", e);
+    }
+
     // Get latest checkpoint
-    let latest_checkpoint = client_res?
+    let latest_checkpoint = messages
         .iter()
         .rev()
-        .find(|m| m.role == stakpak_shared::models::integrations::openai::Role::Assistant)
+        .find(|m| m.role == Role::Assistant)
         .and_then(|m| m.content.as_ref().and_then(|c| c.extract_checkpoint_id()));
 
     if let Some(latest_checkpoint) = latest_checkpoint {
@@ -271,5 +698,12 @@ stakpak agent get {}
         );
     }
 
+    if let Err(e) = finish_session_metrics() {
+        eprintln!("Failed to record session metrics: {}", e);
+    }
+    if let Err(e) = cleanup_session_tmp_dirs() {
+        eprintln!("Failed to clean up session scratch directories: {}", e);
+    }
+
     Ok(())
 }
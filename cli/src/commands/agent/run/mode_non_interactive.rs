@@ -1,20 +1,37 @@
 use crate::commands::agent::run::checkpoint::get_checkpoint_messages;
 use crate::commands::agent::run::helpers::{
-    add_local_context, convert_tools_map, tool_result, user_message,
+    add_local_context, annotate_tools_for_environment, attribute_file_edit, command_redactor,
+    convert_tools_map, redact_tool_call_commands, tool_result, user_message,
 };
-use crate::commands::agent::run::tooling::run_tool_call;
+use crate::commands::agent::run::pipeline::run_tool_calls;
+use crate::commands::agent::run::tooling::{audit_policy_decision, check_tool_policy};
+use crate::commands::agent::run::triage::{extract_failed_command, triage_failed_command};
+use crate::commands::history::save_transcript;
 use crate::config::AppConfig;
+use crate::utils::context_ranking::add_relevant_files;
+use crate::utils::env_fingerprint::{check_checkpoint_environment, pin_checkpoint_environment};
 use crate::utils::local_context::LocalContext;
+use crate::utils::mcp_trust::connect_mcp_servers;
 use crate::utils::network;
+use crate::utils::run_metrics::{finish_session_metrics, record_turn, start_session_metrics};
+use crate::utils::tool_usage::prioritize_tools;
+use stakpak_api::provider::{LLMProvider, Provider};
+use stakpak_api::usage::SessionUsage;
 use stakpak_api::{Client, ClientConfig};
 use stakpak_mcp_client::ClientManager;
 use stakpak_mcp_server::{MCPServerConfig, ToolMode};
 use stakpak_shared::models::integrations::openai::ChatMessage;
+use stakpak_shared::policy_rules::PolicyAction;
+use stakpak_shared::session_tmp::cleanup_session_tmp_dirs;
 
 pub struct RunNonInteractiveConfig {
     pub prompt: String,
     pub approve: bool,
     pub verbose: bool,
+    /// Print the token usage of the session to stderr, so stdout stays parseable
+    pub show_usage: bool,
+    /// Render the answer as markdown instead of printing the message as JSON
+    pub markdown: bool,
     pub checkpoint_id: Option<String>,
     pub local_context: Option<LocalContext>,
     pub redact_secrets: bool,
@@ -25,8 +42,13 @@ pub async fn run_non_interactive(
     config: RunNonInteractiveConfig,
 ) -> Result<(), String> {
     let mut chat_messages: Vec<ChatMessage> = Vec::new();
+    let renderer = ctx.markdown_renderer();
+    start_session_metrics("non-interactive");
 
     let ctx_clone = ctx.clone();
+    let sandbox = ctx.sandbox_profile()?;
+    let dev_container = ctx.dev_container()?;
+    let command_policy = ctx.command_policy()?;
     let bind_address = network::find_available_bind_address_descending().await?;
     let local_mcp_server_host = format!("http://{}", bind_address);
 
@@ -36,30 +58,50 @@ pub async fn run_non_interactive(
                 api: ClientConfig {
                     api_key: ctx_clone.api_key.clone(),
                     api_endpoint: ctx_clone.api_endpoint.clone(),
+                    fallback_endpoints: ctx_clone.fallback_api_endpoints.clone(),
                 },
                 redact_secrets: config.redact_secrets,
                 bind_address,
                 tool_mode: ToolMode::Combined,
+                sandbox,
+                dev_container,
+                command_policy,
+                access: None,
+                tls: None,
             },
             None,
         )
         .await;
     });
 
-    let clients = ClientManager::new(ctx.mcp_server_host.unwrap_or(local_mcp_server_host), None)
-        .await
-        .map_err(|e| e.to_string())?;
-    let tools_map = clients.get_tools().await.map_err(|e| e.to_string())?;
-    let tools = convert_tools_map(&tools_map);
+    let external_mcp_server_host = ctx.mcp_server_host.clone();
+    let mut clients =
+        ClientManager::new(ctx.mcp_server_host.unwrap_or(local_mcp_server_host), None)
+            .await
+            .map_err(|e| e.to_string())?;
+    let tools_map = connect_mcp_servers(&mut clients, external_mcp_server_host.as_deref()).await?;
+    let command_redactor =
+        command_redactor(config.redact_secrets, external_mcp_server_host.as_deref());
+    let mut tools = convert_tools_map(&tools_map);
+    annotate_tools_for_environment(&mut tools, &config.local_context);
+    prioritize_tools(&mut tools);
 
     let client = Client::new(&ClientConfig {
         api_key: ctx.api_key.clone(),
         api_endpoint: ctx.api_endpoint.clone(),
+        fallback_endpoints: ctx.fallback_api_endpoints.clone(),
     })
     .map_err(|e| e.to_string())?;
+    let provider = Provider::from_model(ctx.model.as_deref(), &ctx.llm_providers, client.clone())?;
 
     if let Some(checkpoint_id) = config.checkpoint_id {
-        let mut checkpoint_messages = get_checkpoint_messages(&client, &checkpoint_id).await?;
+        if let Some(warning) = check_checkpoint_environment(&checkpoint_id).await {
+            eprintln!("\n{}\n", warning);
+        }
+
+        let mut checkpoint_messages =
+            get_checkpoint_messages(&client, &checkpoint_id, ctx.checkpoint_replay.as_ref())
+                .await?;
 
         // Append checkpoint_id to the last assistant message if present
         if let Some(last_message) = checkpoint_messages.iter_mut().rev().find(|message| {
@@ -86,9 +128,46 @@ pub async fn run_non_interactive(
     if let Some(message) = chat_messages.last() {
         if config.approve && message.tool_calls.is_some() {
             // Clone the tool_calls to avoid borrowing message while mutating chat_messages
-            let tool_calls = message.tool_calls.as_ref().unwrap_or(&vec![]).clone();
-            for tool_call in tool_calls.iter() {
-                let result = run_tool_call(&clients, &tools_map, tool_call).await?;
+            let mut tool_calls = message.tool_calls.as_ref().unwrap_or(&vec![]).clone();
+            if let Some(redactor) = &command_redactor {
+                redact_tool_call_commands(&mut tool_calls, redactor);
+            }
+            // Calls the policy rules do not allow are not run, nobody is there to confirm them
+            let mut refused = Vec::new();
+            for tool_call in &tool_calls {
+                let Some(decision) = check_tool_policy(tool_call)? else {
+                    continue;
+                };
+                if let Err(e) = audit_policy_decision(tool_call, &decision) {
+                    eprintln!("Failed to write audit log: {}", e);
+                }
+                if decision.action != PolicyAction::Allow {
+                    eprintln!(
+                        "Not running {}, {} by policy rule '{}'",
+                        tool_call.function.name,
+                        if decision.action == PolicyAction::Deny {
+                            "denied"
+                        } else {
+                            "needs confirmation"
+                        },
+                        decision.rule
+                    );
+                    refused.push((tool_call.id.clone(), decision.rule));
+                }
+            }
+            tool_calls.retain(|tool_call| !refused.iter().any(|(id, _)| *id == tool_call.id));
+            for (id, rule) in refused {
+                chat_messages.push(tool_result(
+                    id,
+                    format!(
+                        "The tool call was not run, policy rule '{}' does not allow it without confirmation",
+                        rule
+                    ),
+                ));
+            }
+            let results =
+                run_tool_calls(&clients, &tools_map, &tool_calls, ctx.agent_concurrency).await?;
+            for (tool_call, result) in tool_calls.iter().zip(results) {
                 if let Some(result) = result {
                     if !config.verbose {
                         println!(
@@ -107,6 +186,27 @@ pub async fn run_non_interactive(
                         .collect::<Vec<String>>()
                         .join("\n");
 
+                    if ctx.error_triage {
+                        if let Some((command, error_lines)) =
+                            extract_failed_command(tool_call, &result_content)
+                        {
+                            match triage_failed_command(
+                                &client,
+                                &command,
+                                &error_lines,
+                                ctx.budget.as_ref(),
+                            )
+                            .await
+                            {
+                                Ok(triage) => eprintln!("[Triage]\n{}", triage),
+                                Err(e) => eprintln!("Failed to triage command: {}", e),
+                            }
+                        }
+                    }
+
+                    if result.is_error != Some(true) {
+                        attribute_file_edit(&chat_messages, tool_call);
+                    }
                     chat_messages.push(tool_result(tool_call.id.clone(), result_content.clone()));
                 }
             }
@@ -114,18 +214,51 @@ pub async fn run_non_interactive(
     }
 
     if !config.prompt.is_empty() {
+        let (user_input, ranking_warning) =
+            add_relevant_files(&chat_messages, &config.prompt, ctx.context_ranking.as_ref()).await;
+        if let Some(warning) = ranking_warning {
+            eprintln!("{}", warning);
+        }
         let (user_input, _local_context) =
-            add_local_context(&chat_messages, &config.prompt, &config.local_context);
+            add_local_context(&chat_messages, &user_input, &config.local_context);
         chat_messages.push(user_message(user_input));
     }
 
-    let response = client
+    if let Some(budget) = &ctx.budget {
+        budget.acquire_call()?;
+    }
+    let mut response = provider
         .chat_completion(chat_messages.clone(), Some(tools))
         .await
         .map_err(|e| e.to_string())?;
+    record_turn(response.usage.total_tokens as u64);
+    if let Some(budget) = &ctx.budget {
+        budget.consume_tokens(response.usage.total_tokens as u64);
+    }
 
+    if let (Some(redactor), Some(tool_calls)) = (
+        &command_redactor,
+        response.choices[0].message.tool_calls.as_mut(),
+    ) {
+        redact_tool_call_commands(tool_calls, redactor);
+    }
     chat_messages.push(response.choices[0].message.clone());
 
+    if let Err(e) = save_transcript(&chat_messages) {
+        eprintln!("Failed to save session transcript: {}", e);
+    }
+
+    if let Some(checkpoint_id) = response.choices[0]
+        .message
+        .content
+        .as_ref()
+        .and_then(|c| c.extract_checkpoint_id())
+    {
+        if let Err(e) = pin_checkpoint_environment(&checkpoint_id.to_string()).await {
+            eprintln!("Failed to pin checkpoint environment: {}", e);
+        }
+    }
+
     match config.verbose {
         true => {
             println!(
@@ -133,12 +266,38 @@ pub async fn run_non_interactive(
                 serde_json::to_string_pretty(&chat_messages).unwrap_or_default()
             );
         }
-        false => {
+        false if !config.markdown => {
             println!(
                 "{}",
                 serde_json::to_string_pretty(&response.choices[0].message).unwrap_or_default()
             );
         }
+        false => {
+            let message = &response.choices[0].message;
+            if let Some(content) = &message.content {
+                renderer.print(&content.to_string());
+            }
+            for tool_call in message.tool_calls.as_deref().unwrap_or_default() {
+                renderer.print(&format!(
+                    "**Tool call** `{}`\n```json\n{}\n```",
+                    tool_call.function.name, tool_call.function.arguments
+                ));
+            }
+        }
+    }
+
+    if config.show_usage {
+        match SessionUsage::load() {
+            Ok(usage) => eprintln!("{}", usage.report()),
+            Err(e) => eprintln!("Failed to load token usage: {}", e),
+        }
+    }
+
+    if let Err(e) = finish_session_metrics() {
+        eprintln!("Failed to record session metrics: {}", e);
+    }
+    if let Err(e) = cleanup_session_tmp_dirs() {
+        eprintln!("Failed to clean up session scratch directories: {}", e);
     }
 
     Ok(())
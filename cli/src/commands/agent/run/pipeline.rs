@@ -0,0 +1,107 @@
+use crate::commands::agent::run::tooling::run_tool_call;
+use futures_util::future::join_all;
+use rmcp::model::{CallToolResult, Tool};
+use stakpak_mcp_client::ClientManager;
+use stakpak_shared::models::integrations::openai::ToolCall;
+use stakpak_shared::policy::{RiskLevel, classify_tool_call, tool_call_paths};
+use std::collections::HashMap;
+use std::ops::Range;
+
+fn is_read_only(tool_call: &ToolCall) -> bool {
+    let arguments = serde_json::from_str::<serde_json::Value>(&tool_call.function.arguments).ok();
+    let argument = |key: &str| {
+        arguments
+            .as_ref()
+            .and_then(|args| args.get(key))
+            .and_then(|value| value.as_str())
+    };
+    classify_tool_call(
+        &tool_call.function.name,
+        argument("command"),
+        &tool_call_paths(arguments.as_ref().and_then(|args| args.as_object())),
+    )
+    .level
+        == RiskLevel::ReadOnly
+}
+
+/// Split the tool calls of a step into batches that can run concurrently. Consecutive
+/// read-only calls share a batch of up to `concurrency` calls, any other call runs alone
+/// so it never overlaps with the calls the model ordered before or after it.
+pub fn execution_batches(tool_calls: &[ToolCall], concurrency: usize) -> Vec<Range<usize>> {
+    let concurrency = concurrency.max(1);
+    let mut batches: Vec<Range<usize>> = Vec::new();
+    let mut batch_read_only = false;
+
+    for (index, tool_call) in tool_calls.iter().enumerate() {
+        let read_only = is_read_only(tool_call);
+        match batches.last_mut() {
+            Some(batch) if read_only && batch_read_only && batch.len() < concurrency => {
+                batch.end = index + 1;
+            }
+            _ => batches.push(index..index + 1),
+        }
+        batch_read_only = read_only;
+    }
+    batches
+}
+
+/// Run the tool calls of a step, batching independent read-only calls so the next
+/// completion can start as soon as the slowest of them returns. Results keep the
+/// order of `tool_calls`.
+pub async fn run_tool_calls(
+    client_manager: &ClientManager,
+    tools_map: &HashMap<String, Vec<Tool>>,
+    tool_calls: &[ToolCall],
+    concurrency: usize,
+) -> Result<Vec<Option<CallToolResult>>, String> {
+    let mut results = Vec::with_capacity(tool_calls.len());
+    for batch in execution_batches(tool_calls, concurrency) {
+        let batch_results = join_all(
+            tool_calls[batch]
+                .iter()
+                .map(|tool_call| run_tool_call(client_manager, tools_map, tool_call)),
+        )
+        .await;
+        for result in batch_results {
+            results.push(result?);
+        }
+    }
+    Ok(results)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use stakpak_shared::models::integrations::openai::FunctionCall;
+
+    fn tool_call(name: &str, arguments: &str) -> ToolCall {
+        ToolCall {
+            id: name.to_string(),
+            r#type: "function".to_string(),
+            function: FunctionCall {
+                name: name.to_string(),
+                arguments: arguments.to_string(),
+            },
+        }
+    }
+
+    #[test]
+    fn test_execution_batches() {
+        let tool_calls = vec![
+            tool_call("view", r#"{"path":"main.tf"}"#),
+            tool_call("view", r#"{"path":"variables.tf"}"#),
+            tool_call("view", r#"{"path":"outputs.tf"}"#),
+            tool_call("run_command", r#"{"command":"terraform apply"}"#),
+            tool_call("view", r#"{"path":"main.tf"}"#),
+        ];
+
+        assert_eq!(
+            execution_batches(&tool_calls, 2),
+            vec![0..2, 2..3, 3..4, 4..5]
+        );
+        assert_eq!(
+            execution_batches(&tool_calls, 1),
+            vec![0..1, 1..2, 2..3, 3..4, 4..5]
+        );
+    }
+}
@@ -0,0 +1,40 @@
+use stakpak_mcp_server::secret_manager::SecretManager;
+use stakpak_shared::session_env::{
+    SessionEnvVar, env_secret_placeholder, set_session_env, unset_session_env,
+};
+use std::collections::HashMap;
+
+/// Set a session variable from `/env`, returning the note telling the agent about it.
+/// Secret values go to the session redaction map and only their placeholder is kept.
+pub fn apply_session_env(name: &str, value: String, secret: bool) -> Result<String, String> {
+    let value = if secret {
+        let placeholder = env_secret_placeholder(name);
+        SecretManager::new(true)
+            .add_to_session_redaction_map(&HashMap::from([(placeholder.clone(), value)]));
+        placeholder
+    } else {
+        value
+    };
+    set_session_env(
+        name,
+        SessionEnvVar {
+            value: value.clone(),
+            secret,
+        },
+    )?;
+    Ok(format!(
+        "The user set the environment variable {}={} for every following run_command, do not export it in commands.",
+        name, value
+    ))
+}
+
+/// Unset a session variable from `/env unset`, returning the note telling the agent about it
+pub fn remove_session_env(name: &str) -> Result<String, String> {
+    if !unset_session_env(name)? {
+        return Err(format!("{} is not set in this session", name));
+    }
+    Ok(format!(
+        "The user unset the session environment variable {}, following commands no longer get it.",
+        name
+    ))
+}
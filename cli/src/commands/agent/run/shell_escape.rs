@@ -0,0 +1,140 @@
+use crate::commands::agent::run::tui::send_input_event;
+use stakpak_tui::{InputEvent, ShellCommandResult};
+use tokio::io::{AsyncBufReadExt, BufReader};
+use tokio::process::Command;
+use tokio::sync::mpsc::Sender;
+use uuid::Uuid;
+
+/// Output lines of a `!command` kept for sharing with the agent, earlier lines are cut
+const MAX_SHARED_LINES: usize = 200;
+
+/// Run a `!command` typed in the TUI on the host, streaming its output back to the TUI
+/// without blocking the agent loop
+pub fn spawn_shell_escape(id: Uuid, command: String, input_tx: Sender<InputEvent>) {
+    tokio::spawn(async move {
+        let result = run_shell_escape(id, command, &input_tx).await;
+        let _ = send_input_event(&input_tx, InputEvent::ShellFinished(id, result)).await;
+    });
+}
+
+async fn run_shell_escape(
+    id: Uuid,
+    command: String,
+    input_tx: &Sender<InputEvent>,
+) -> ShellCommandResult {
+    let mut child = match Command::new("sh")
+        .arg("-c")
+        .arg(&command)
+        .stdin(std::process::Stdio::null())
+        .stdout(std::process::Stdio::piped())
+        .stderr(std::process::Stdio::piped())
+        .spawn()
+    {
+        Ok(child) => child,
+        Err(e) => {
+            return ShellCommandResult {
+                command,
+                output: format!("Failed to run command: {}", e),
+                exit_code: None,
+            };
+        }
+    };
+
+    let (Some(stdout), Some(stderr)) = (child.stdout.take(), child.stderr.take()) else {
+        return ShellCommandResult {
+            command,
+            output: "Failed to capture command output".to_string(),
+            exit_code: None,
+        };
+    };
+    let mut stdout = BufReader::new(stdout).lines();
+    let mut stderr = BufReader::new(stderr).lines();
+    let mut output: Vec<String> = Vec::new();
+    let (mut stdout_open, mut stderr_open) = (true, true);
+
+    while stdout_open || stderr_open {
+        let line = tokio::select! {
+            line = stdout.next_line(), if stdout_open => line.ok().flatten().or_else(|| {
+                stdout_open = false;
+                None
+            }),
+            line = stderr.next_line(), if stderr_open => line.ok().flatten().or_else(|| {
+                stderr_open = false;
+                None
+            }),
+        };
+        if let Some(line) = line {
+            let _ = send_input_event(input_tx, InputEvent::ShellOutput(id, line.clone())).await;
+            output.push(line);
+            if output.len() > MAX_SHARED_LINES {
+                output.remove(0);
+            }
+        }
+    }
+
+    let exit_code = child.wait().await.ok().and_then(|status| status.code());
+    ShellCommandResult {
+        command,
+        output: output.join("\n"),
+        exit_code,
+    }
+}
+
+/// Message added to the conversation when the user shares a `!command` with the agent
+pub fn shared_shell_output_message(result: &ShellCommandResult) -> String {
+    let exit = match result.exit_code {
+        Some(code) => format!("exit code {}", code),
+        None => "terminated by a signal".to_string(),
+    };
+    format!(
+        "I ran `{}` in my shell ({}), output:\n```\n{}\n```",
+        result.command, exit, result.output
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_shared_shell_output_message() {
+        let mut result = ShellCommandResult {
+            command: "git status".to_string(),
+            output: "nothing to commit".to_string(),
+            exit_code: Some(0),
+        };
+        assert_eq!(
+            shared_shell_output_message(&result),
+            "I ran `git status` in my shell (exit code 0), output:\n```\nnothing to commit\n```"
+        );
+        result.exit_code = None;
+        assert!(shared_shell_output_message(&result).contains("(terminated by a signal)"));
+    }
+
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn test_run_shell_escape_streams_and_keeps_the_last_lines() {
+        let id = Uuid::new_v4();
+        let (input_tx, mut input_rx) = tokio::sync::mpsc::channel(1024);
+        let result = run_shell_escape(
+            id,
+            format!("seq {}; echo failed >&2; exit 3", MAX_SHARED_LINES + 10),
+            &input_tx,
+        )
+        .await;
+        drop(input_tx);
+
+        let mut streamed = 0;
+        while let Some(event) = input_rx.recv().await {
+            if let InputEvent::ShellOutput(event_id, _) = event {
+                assert_eq!(event_id, id);
+                streamed += 1;
+            }
+        }
+        assert_eq!(streamed, MAX_SHARED_LINES + 11);
+        assert_eq!(result.exit_code, Some(3));
+        assert_eq!(result.output.lines().count(), MAX_SHARED_LINES);
+        assert!(result.output.lines().any(|line| line == "failed"));
+        assert!(!result.output.lines().any(|line| line == "1"));
+    }
+}
@@ -1,12 +1,76 @@
 use crate::commands::agent::run::tui::send_input_event;
+use crate::utils::run_metrics::record_turn;
 use futures_util::{Stream, StreamExt};
+use stakpak_api::usage::SessionUsage;
 use stakpak_shared::models::integrations::openai::{
     ChatCompletionChoice, ChatCompletionResponse, ChatCompletionStreamResponse, ChatMessage,
     FinishReason, FunctionCall, FunctionCallDelta, MessageContent, Role, ToolCall, Usage,
 };
-use stakpak_tui::InputEvent;
+use stakpak_tui::{InputEvent, StreamMetrics};
+use std::time::{Duration, Instant};
 use uuid::Uuid;
 
+/// How often the streaming latency readout in the TUI is refreshed
+const METRICS_INTERVAL: Duration = Duration::from_millis(250);
+
+/// Tracks time to first token and throughput of a streamed completion. Token counts
+/// are estimated at ~4 characters per token since usage is only reported at the end.
+struct StreamTimer {
+    started_at: Instant,
+    first_token_at: Option<Instant>,
+    last_reported_at: Option<Instant>,
+    streamed_chars: usize,
+}
+
+impl StreamTimer {
+    fn new() -> Self {
+        Self {
+            started_at: Instant::now(),
+            first_token_at: None,
+            last_reported_at: None,
+            streamed_chars: 0,
+        }
+    }
+
+    fn record(&mut self, text: &str) {
+        if text.is_empty() {
+            return;
+        }
+        self.first_token_at.get_or_insert_with(Instant::now);
+        self.streamed_chars += text.chars().count();
+    }
+
+    fn estimated_tokens(&self) -> usize {
+        self.streamed_chars.div_ceil(4)
+    }
+
+    fn metrics(&self) -> Option<StreamMetrics> {
+        let first_token_at = self.first_token_at?;
+        let generation_secs = first_token_at.elapsed().as_secs_f64();
+        Some(StreamMetrics {
+            time_to_first_token: first_token_at.duration_since(self.started_at),
+            tokens_per_second: if generation_secs > 0.0 {
+                self.estimated_tokens() as f64 / generation_secs
+            } else {
+                0.0
+            },
+        })
+    }
+
+    /// Metrics to send to the TUI, throttled to one update per `METRICS_INTERVAL`
+    fn poll(&mut self) -> Option<StreamMetrics> {
+        if self
+            .last_reported_at
+            .is_some_and(|reported_at| reported_at.elapsed() < METRICS_INTERVAL)
+        {
+            return None;
+        }
+        let metrics = self.metrics()?;
+        self.last_reported_at = Some(Instant::now());
+        Some(metrics)
+    }
+}
+
 pub async fn process_responses_stream(
     stream: impl Stream<Item = Result<ChatCompletionStreamResponse, String>>,
     input_tx: &tokio::sync::mpsc::Sender<InputEvent>,
@@ -35,11 +99,20 @@ pub async fn process_responses_stream(
         tool_call_id: None,
     };
     let message_id = Uuid::new_v4();
+    let mut timer = StreamTimer::new();
+    let mut reported_usage = None;
 
     while let Some(response) = stream.next().await {
         send_input_event(input_tx, InputEvent::Loading(true)).await?;
         if let Ok(response) = response {
-            let delta = &response.choices[0].delta;
+            if let Some(usage) = &response.usage {
+                reported_usage = Some(usage.clone());
+            }
+            // The usage chunk closing the stream has no choices
+            let Some(choice) = response.choices.first() else {
+                continue;
+            };
+            let delta = &choice.delta;
 
             chat_completion_response = ChatCompletionResponse {
                 id: response.id.clone(),
@@ -56,6 +129,7 @@ pub async fn process_responses_stream(
             };
 
             if let Some(content) = &delta.content {
+                timer.record(content);
                 chat_message.content = Some(MessageContent::String(match chat_message.content {
                     Some(MessageContent::String(old_content)) => old_content + content,
                     _ => content.clone(),
@@ -70,6 +144,13 @@ pub async fn process_responses_stream(
 
             if let Some(tool_calls) = &delta.tool_calls {
                 for delta_tool_call in tool_calls {
+                    if let Some(arguments) = delta_tool_call
+                        .function
+                        .as_ref()
+                        .and_then(|f| f.arguments.as_deref())
+                    {
+                        timer.record(arguments);
+                    }
                     if chat_message.tool_calls.is_none() {
                         chat_message.tool_calls = Some(vec![]);
                     }
@@ -125,9 +206,40 @@ pub async fn process_responses_stream(
                     }
                 }
             }
+
+            if let Some(metrics) = timer.poll() {
+                send_input_event(input_tx, InputEvent::StreamMetrics(metrics)).await?;
+            }
         }
     }
 
+    let metrics = timer.metrics();
+    tracing::debug!(
+        model = %chat_completion_response.model,
+        time_to_first_token_ms = metrics.map(|m| m.time_to_first_token.as_millis() as u64),
+        duration_ms = timer.started_at.elapsed().as_millis() as u64,
+        estimated_tokens = timer.estimated_tokens(),
+        tokens_per_second = metrics.map(|m| m.tokens_per_second),
+        "chat completion stream finished"
+    );
+    match reported_usage {
+        Some(usage) => {
+            record_turn(usage.total_tokens as u64);
+            chat_completion_response.usage = usage;
+        }
+        None => {
+            // Estimated, so budgets still count turns of providers that report no usage
+            let tokens = timer.estimated_tokens();
+            record_turn(tokens as u64);
+            chat_completion_response.usage.completion_tokens = tokens as u32;
+            chat_completion_response.usage.total_tokens = tokens as u32;
+        }
+    }
+    match SessionUsage::load() {
+        Ok(usage) => send_input_event(input_tx, InputEvent::UsageReport(usage.report())).await?,
+        Err(e) => tracing::warn!("Failed to load session token usage: {}", e),
+    }
+
     // filter out empty tool calls
     chat_message.tool_calls = Some(
         chat_message
@@ -149,3 +261,28 @@ pub async fn process_responses_stream(
 
     Ok(chat_completion_response)
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_stream_timer() {
+        let mut timer = StreamTimer::new();
+        timer.record("");
+        assert!(timer.metrics().is_none());
+        assert!(timer.poll().is_none());
+
+        timer.record("Hello, wor");
+        timer.record("ld");
+        assert_eq!(timer.estimated_tokens(), 3);
+        let metrics = timer.metrics().unwrap();
+        assert!(metrics.time_to_first_token <= timer.started_at.elapsed());
+
+        // Updates to the TUI are throttled
+        assert!(timer.poll().is_some());
+        assert!(timer.poll().is_none());
+        timer.last_reported_at = Some(Instant::now() - METRICS_INTERVAL);
+        assert!(timer.poll().is_some());
+    }
+}
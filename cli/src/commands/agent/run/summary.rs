@@ -0,0 +1,161 @@
+use stakpak_api::models::{RunSummary, RunSummaryCommand, RunSummaryFileChange};
+use stakpak_shared::command_result::command_failed;
+use stakpak_shared::models::integrations::openai::{ChatMessage, Role};
+use std::collections::HashMap;
+
+/// Diff lines kept per file, longer diffs are cut to keep the summary readable
+const MAX_DIFF_LINES: usize = 200;
+
+/// Compose the summary of a run from the messages it added to the conversation
+pub fn compose_run_summary(messages: &[ChatMessage], steps: usize) -> RunSummary {
+    let results: HashMap<&str, String> = messages
+        .iter()
+        .filter(|m| m.role == Role::Tool)
+        .filter_map(|m| {
+            m.tool_call_id.as_deref().map(|id| {
+                (
+                    id,
+                    m.content
+                        .as_ref()
+                        .map(|c| c.to_string())
+                        .unwrap_or_default(),
+                )
+            })
+        })
+        .collect();
+
+    let mut summary = RunSummary {
+        steps,
+        ..Default::default()
+    };
+
+    for tool_call in messages
+        .iter()
+        .filter(|m| m.role == Role::Assistant)
+        .flat_map(|m| m.tool_calls.iter().flatten())
+    {
+        let arguments: serde_json::Value =
+            serde_json::from_str(&tool_call.function.arguments).unwrap_or_default();
+        let argument = |key: &str| {
+            arguments
+                .get(key)
+                .and_then(|v| v.as_str())
+                .unwrap_or_default()
+                .to_string()
+        };
+
+        let diff = match tool_call.function.name.as_str() {
+            "run_command" => {
+                let failed = results
+                    .get(tool_call.id.as_str())
+                    .is_some_and(|result| command_failed(result));
+                summary.commands.push(RunSummaryCommand {
+                    command: argument("command"),
+                    failed,
+                });
+                continue;
+            }
+            "str_replace" => format!(
+                "{}\n{}",
+                prefix_lines("-", &argument("old_str")),
+                prefix_lines("+", &argument("new_str"))
+            ),
+            "create" => prefix_lines("+", &argument("file_text")),
+            "insert" => prefix_lines("+", &argument("new_str")),
+            "delete_file" => "(deleted)".to_string(),
+            "move_file" => format!("(moved to {})", argument("destination")),
+            _ => continue,
+        };
+
+        let path = match tool_call.function.name.as_str() {
+            "move_file" => argument("source"),
+            _ => argument("path"),
+        };
+        match summary.changed_files.iter_mut().find(|c| c.path == path) {
+            Some(change) => {
+                change.diff.push('\n');
+                change.diff.push_str(&diff);
+            }
+            None => summary
+                .changed_files
+                .push(RunSummaryFileChange { path, diff }),
+        }
+    }
+
+    for change in summary.changed_files.iter_mut() {
+        let lines: Vec<&str> = change.diff.lines().collect();
+        if lines.len() > MAX_DIFF_LINES {
+            change.diff = format!(
+                "{}\n... {} more lines",
+                lines[..MAX_DIFF_LINES].join("\n"),
+                lines.len() - MAX_DIFF_LINES
+            );
+        }
+    }
+
+    summary.conclusion = messages
+        .iter()
+        .rev()
+        .find(|m| m.role == Role::Assistant)
+        .and_then(|m| m.content.as_ref())
+        .map(|c| strip_checkpoint_tag(&c.to_string()))
+        .unwrap_or_default();
+    summary.todos = extract_todos(&summary.conclusion);
+
+    summary
+}
+
+fn prefix_lines(prefix: &str, text: &str) -> String {
+    text.lines()
+        .map(|line| format!("{}{}", prefix, line))
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+fn strip_checkpoint_tag(content: &str) -> String {
+    match content.rfind("<checkpoint_id>") {
+        Some(start) => content[..start].trim().to_string(),
+        None => content.trim().to_string(),
+    }
+}
+
+/// Unchecked task list items and TODO lines of the agent's final answer
+fn extract_todos(conclusion: &str) -> Vec<String> {
+    conclusion
+        .lines()
+        .map(|line| line.trim())
+        .filter_map(|line| {
+            if let Some(task) = line
+                .strip_prefix("- [ ]")
+                .or_else(|| line.strip_prefix("* [ ]"))
+            {
+                return Some(task.trim().to_string());
+            }
+            let item = line
+                .trim_start_matches(['-', '*'])
+                .trim_start()
+                .trim_matches('*');
+            item.strip_prefix("TODO")
+                .map(|todo| todo.trim_start_matches([':', ' ', '*']).trim().to_string())
+        })
+        .filter(|todo| !todo.is_empty())
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_extract_todos() {
+        let conclusion = "Upgraded the provider.\n\n- [x] Bump version\n- [ ] Run terraform plan in prod\n- TODO: rotate the old access key\nTODO remove the legacy module";
+        assert_eq!(
+            extract_todos(conclusion),
+            vec![
+                "Run terraform plan in prod",
+                "rotate the old access key",
+                "remove the legacy module"
+            ]
+        );
+    }
+}
@@ -1,12 +1,44 @@
-use rmcp::model::{CallToolRequestParam, CallToolResult};
+use crate::utils::audit_log::{AuditEntry, record_audit_entry};
+use crate::utils::run_metrics::record_tool_metrics;
+use crate::utils::tool_usage::record_tool_call;
+use rmcp::model::{CallToolRequestParam, CallToolResult, Content};
 use stakpak_api::Client;
 use stakpak_api::models::AgentSession;
 use stakpak_mcp_client::ClientManager;
 use stakpak_shared::models::integrations::openai::ToolCall;
+use stakpak_shared::policy::tool_call_paths;
+use stakpak_shared::policy_rules::{PolicyAction, PolicyDecision, PolicyRules};
 use stakpak_tui::SessionInfo;
+use std::collections::BTreeMap;
+use std::sync::OnceLock;
+use std::time::Duration;
+
+/// Most recent sessions shown in the TUI session picker
+const SESSIONS_LIMIT: usize = 100;
+
+static TOOL_TIMEOUTS: OnceLock<BTreeMap<String, u64>> = OnceLock::new();
+
+/// Abandon calls of the given tools after their number of seconds. `run_command` enforces
+/// its own timeout on the server, which returns the partial output of killed commands.
+pub fn set_tool_timeouts(timeouts: BTreeMap<String, u64>) {
+    let _ = TOOL_TIMEOUTS.set(timeouts);
+}
+
+fn tool_timeout(tool_name: &str) -> Option<Duration> {
+    if tool_name == "run_command" {
+        return None;
+    }
+    TOOL_TIMEOUTS
+        .get()?
+        .get(tool_name)
+        .map(|secs| Duration::from_secs(*secs))
+}
 
 pub async fn list_sessions(client: &Client) -> Result<Vec<SessionInfo>, String> {
-    let sessions: Vec<AgentSession> = client.list_agent_sessions().await?;
+    let sessions: Vec<AgentSession> = client
+        .list_agent_sessions(Some(SESSIONS_LIMIT))
+        .await?
+        .items;
     let session_infos: Vec<SessionInfo> = sessions
         .into_iter()
         .map(|s| {
@@ -23,6 +55,40 @@ pub async fn list_sessions(client: &Client) -> Result<Vec<SessionInfo>, String>
     Ok(session_infos)
 }
 
+/// Consult the rules of `.stakpak/policies.yaml` on `tool_call`, `None` when no rule
+/// matches and the usual confirmation applies
+pub fn check_tool_policy(tool_call: &ToolCall) -> Result<Option<PolicyDecision>, String> {
+    let arguments = serde_json::from_str::<serde_json::Value>(&tool_call.function.arguments).ok();
+    let argument = |key: &str| {
+        arguments
+            .as_ref()
+            .and_then(|args| args.get(key))
+            .and_then(|value| value.as_str())
+    };
+    Ok(PolicyRules::load()?.evaluate(
+        &tool_call.function.name,
+        argument("command"),
+        &tool_call_paths(arguments.as_ref().and_then(|args| args.as_object())),
+    ))
+}
+
+/// Record the decision of a policy rule on `tool_call` in the session audit log
+pub fn audit_policy_decision(
+    tool_call: &ToolCall,
+    decision: &PolicyDecision,
+) -> Result<(), String> {
+    let action = match decision.action {
+        PolicyAction::Allow => "approved",
+        PolicyAction::Prompt => "prompted",
+        PolicyAction::Deny => "denied",
+    };
+    record_audit_entry(&AuditEntry::new(
+        tool_call,
+        action,
+        format!("policy rule '{}'", decision.rule),
+    ))
+}
+
 pub async fn run_tool_call(
     client_manager: &ClientManager,
     tools_map: &std::collections::HashMap<String, Vec<rmcp::model::Tool>>,
@@ -39,16 +105,30 @@ pub async fn run_tool_call(
             .get_client(&client_name)
             .await
             .map_err(|e| e.to_string())?;
-        let result = client
-            .call_tool(CallToolRequestParam {
-                name: tool_name.clone().into(),
-                arguments: Some(
-                    serde_json::from_str(&tool_call.function.arguments)
-                        .map_err(|e| e.to_string())?,
-                ),
-            })
-            .await
-            .map_err(|e| e.to_string())?;
+        let call = client.call_tool(CallToolRequestParam {
+            name: ClientManager::server_tool_name(&client_name, tool_name)
+                .to_string()
+                .into(),
+            arguments: Some(
+                serde_json::from_str(&tool_call.function.arguments).map_err(|e| e.to_string())?,
+            ),
+        });
+        let result = match tool_timeout(tool_name) {
+            Some(timeout) => match tokio::time::timeout(timeout, call).await {
+                Ok(result) => result.map_err(|e| e.to_string())?,
+                Err(_) => CallToolResult::error(vec![
+                    Content::text("TIMEOUT"),
+                    Content::text(format!(
+                        "The {} tool did not answer within {}s, the call was abandoned",
+                        tool_name,
+                        timeout.as_secs()
+                    )),
+                ]),
+            },
+            None => call.await.map_err(|e| e.to_string())?,
+        };
+        record_tool_call(tool_name, result.is_error.unwrap_or(false));
+        record_tool_metrics(tool_name, result.is_error.unwrap_or(false));
 
         return Ok(Some(result));
     }
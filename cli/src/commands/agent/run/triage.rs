@@ -0,0 +1,234 @@
+use crate::utils::run_budget::BudgetConfig;
+use serde::Deserialize;
+use serde_json::json;
+use stakpak_api::Client;
+use stakpak_shared::command_result::{command_failed, strip_command_metadata};
+use stakpak_shared::models::integrations::openai::{ChatMessage, MessageContent, Role, ToolCall};
+
+const MAX_ERROR_LINES: usize = 40;
+const ERROR_KEYWORDS: [&str; 10] = [
+    "error",
+    "fail",
+    "fatal",
+    "denied",
+    "not found",
+    "no such",
+    "panic",
+    "exception",
+    "invalid",
+    "timed out",
+];
+
+const TRIAGE_PROMPT: &str = "You are triaging a failed shell command. Given the command and the error lines from its output, reply with a short list of the most likely causes and a concrete fix suggestion for each. Be concise and do not repeat the error output.";
+const TRIAGE_MAX_ATTEMPTS: usize = 2;
+
+/// Structured reply of the triage completion
+#[derive(Deserialize, Debug)]
+struct Triage {
+    causes: Vec<TriageCause>,
+}
+
+#[derive(Deserialize, Debug)]
+struct TriageCause {
+    cause: String,
+    fix: String,
+}
+
+impl std::fmt::Display for Triage {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        for (index, cause) in self.causes.iter().enumerate() {
+            writeln!(f, "{}. {}", index + 1, cause.cause)?;
+            writeln!(f, "   Fix: {}", cause.fix)?;
+        }
+        Ok(())
+    }
+}
+
+fn triage_schema() -> serde_json::Value {
+    json!({
+        "type": "object",
+        "properties": {
+            "causes": {
+                "type": "array",
+                "items": {
+                    "type": "object",
+                    "properties": {
+                        "cause": {"type": "string"},
+                        "fix": {"type": "string"}
+                    },
+                    "required": ["cause", "fix"],
+                    "additionalProperties": false
+                }
+            }
+        },
+        "required": ["causes"],
+        "additionalProperties": false
+    })
+}
+
+/// Returns the command and its relevant error lines when a run_command tool call exited non-zero
+pub fn extract_failed_command(tool_call: &ToolCall, output: &str) -> Option<(String, Vec<String>)> {
+    if tool_call.function.name != "run_command" {
+        return None;
+    }
+
+    if !command_failed(output) {
+        return None;
+    }
+    let output = strip_command_metadata(output);
+    let lines: Vec<&str> = output.lines().collect();
+
+    let command = serde_json::from_str::<serde_json::Value>(&tool_call.function.arguments)
+        .ok()
+        .and_then(|args| {
+            args.get("command")
+                .and_then(|c| c.as_str())
+                .map(|c| c.to_string())
+        })
+        .unwrap_or_default();
+
+    let mut error_lines: Vec<String> = lines
+        .iter()
+        .filter(|line| {
+            let lower = line.to_lowercase();
+            ERROR_KEYWORDS.iter().any(|keyword| lower.contains(keyword))
+        })
+        .map(|line| line.to_string())
+        .collect();
+
+    // Fall back to the tail of the output when nothing looks like an error
+    if error_lines.len() <= 1 {
+        error_lines = lines
+            .iter()
+            .rev()
+            .take(MAX_ERROR_LINES)
+            .rev()
+            .map(|line| line.to_string())
+            .collect();
+    }
+
+    if error_lines.len() > MAX_ERROR_LINES {
+        error_lines = error_lines.split_off(error_lines.len() - MAX_ERROR_LINES);
+    }
+
+    Some((command, error_lines))
+}
+
+/// Runs a focused completion asking for the likely cause of a failed command and how to fix it,
+/// taking it from `budget` like any other completion
+pub async fn triage_failed_command(
+    client: &Client,
+    command: &str,
+    error_lines: &[String],
+    budget: Option<&BudgetConfig>,
+) -> Result<String, String> {
+    if let Some(budget) = budget {
+        budget.acquire_call()?;
+    }
+    let messages = vec![
+        ChatMessage {
+            role: Role::System,
+            content: Some(MessageContent::String(TRIAGE_PROMPT.to_string())),
+            name: None,
+            tool_calls: None,
+            tool_call_id: None,
+        },
+        ChatMessage {
+            role: Role::User,
+            content: Some(MessageContent::String(format!(
+                "<command>\n{}\n</command>\n<errors>\n{}\n</errors>",
+                command,
+                error_lines.join("\n")
+            ))),
+            name: None,
+            tool_calls: None,
+            tool_call_id: None,
+        },
+    ];
+
+    let triage: Triage = client
+        .structured_completion(messages, "triage", triage_schema(), TRIAGE_MAX_ATTEMPTS)
+        .await?;
+
+    Ok(triage.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use stakpak_shared::command_result::CommandMetadata;
+    use stakpak_shared::models::integrations::openai::FunctionCall;
+
+    fn tool_call(name: &str, arguments: serde_json::Value) -> ToolCall {
+        ToolCall {
+            id: "call_1".to_string(),
+            r#type: "function".to_string(),
+            function: FunctionCall {
+                name: name.to_string(),
+                arguments: arguments.to_string(),
+            },
+        }
+    }
+
+    fn result(output: &str, exit_code: i32) -> String {
+        let metadata = CommandMetadata {
+            cwd: "/src".to_string(),
+            exit_code: Some(exit_code),
+            duration_ms: 10,
+            shell: "sh".to_string(),
+            truncated: false,
+            output_file: None,
+            cached: false,
+            timed_out: false,
+        };
+        format!("{}\n{}", output, metadata.to_tagged())
+    }
+
+    #[test]
+    fn test_extract_failed_command() {
+        let call = tool_call("run_command", json!({"command": "terraform apply"}));
+        let output = "Refreshing state...\nError: creating S3 bucket: access denied\nPlan failed";
+        let (command, error_lines) = extract_failed_command(&call, &result(output, 1)).unwrap();
+        assert_eq!(command, "terraform apply");
+        assert_eq!(
+            error_lines,
+            vec![
+                "Error: creating S3 bucket: access denied".to_string(),
+                "Plan failed".to_string(),
+            ]
+        );
+
+        assert!(extract_failed_command(&call, &result(output, 0)).is_none());
+        let view = tool_call("view", json!({"path": "main.tf"}));
+        assert!(extract_failed_command(&view, &result(output, 1)).is_none());
+    }
+
+    #[test]
+    fn test_extract_falls_back_to_the_output_tail() {
+        let call = tool_call("run_command", json!({"command": "make"}));
+        let output = (0..MAX_ERROR_LINES + 5)
+            .map(|i| format!("line {}", i))
+            .collect::<Vec<_>>()
+            .join("\n");
+        let (_, error_lines) = extract_failed_command(&call, &result(&output, 2)).unwrap();
+        assert_eq!(error_lines.len(), MAX_ERROR_LINES);
+        assert_eq!(error_lines[0], "line 5");
+        assert!(
+            error_lines
+                .iter()
+                .all(|line| !line.contains("command_metadata"))
+        );
+    }
+
+    #[test]
+    fn test_triage_display() {
+        let triage: Triage = serde_json::from_value(json!({
+            "causes": [{"cause": "Missing credentials", "fix": "Run aws sso login"}]
+        }))
+        .unwrap();
+        assert_eq!(
+            triage.to_string(),
+            "1. Missing credentials\n   Fix: Run aws sso login\n"
+        );
+    }
+}
@@ -1,3 +1,5 @@
+use crate::commands::agent::run::helpers::ASK_USER_TOOL_NAME;
+use crate::commands::agent::run::tooling::{audit_policy_decision, check_tool_policy};
 use stakpak_shared::models::integrations::openai::ToolCall;
 use stakpak_tui::InputEvent;
 
@@ -12,6 +14,29 @@ pub async fn send_tool_call(
     input_tx: &tokio::sync::mpsc::Sender<InputEvent>,
     tool_call: &ToolCall,
 ) -> Result<(), String> {
-    send_input_event(input_tx, InputEvent::RunToolCall(tool_call.clone())).await?;
-    Ok(())
+    if tool_call.function.name == ASK_USER_TOOL_NAME {
+        return send_input_event(input_tx, InputEvent::AskUser(tool_call.clone())).await;
+    }
+    // A broken policies file must not let calls run unconfirmed, fall back to asking
+    let decision = match check_tool_policy(tool_call) {
+        Ok(decision) => decision,
+        Err(e) => {
+            send_input_event(input_tx, InputEvent::Error(e)).await?;
+            None
+        }
+    };
+    let event = match decision {
+        Some(decision) => {
+            if let Err(e) = audit_policy_decision(tool_call, &decision) {
+                send_input_event(
+                    input_tx,
+                    InputEvent::Error(format!("Failed to write audit log: {}", e)),
+                )
+                .await?;
+            }
+            InputEvent::ApplyToolPolicy(tool_call.clone(), decision)
+        }
+        None => InputEvent::RunToolCall(tool_call.clone()),
+    };
+    send_input_event(input_tx, event).await
 }
@@ -0,0 +1,46 @@
+use stakpak_shared::file_history::FileHistory;
+use stakpak_shared::workspace::resolve_path;
+
+/// Print every hunk of `path` with the agent step that last changed it
+pub fn blame_file(path: &str) -> Result<(), String> {
+    let resolved = resolve_path(path)?;
+    let resolved = resolved.to_string_lossy();
+    let history = FileHistory::load(&resolved)?
+        .filter(|history| !history.versions.is_empty())
+        .ok_or_else(|| format!("No agent edits recorded for {} in this session", path))?;
+    // A deleted file is blamed as of the last agent edit
+    let current = std::fs::read_to_string(resolved.as_ref()).ok();
+
+    let hunks = history.blame(current.as_deref());
+    let width = hunks
+        .last()
+        .map(|hunk| (hunk.start + hunk.lines.len()).to_string().len())
+        .unwrap_or(1);
+    for hunk in hunks {
+        let end = hunk.start + hunk.lines.len() - 1;
+        println!(
+            "\x1b[1;36m@@ lines {}-{} · {}\x1b[0m",
+            hunk.start,
+            end,
+            history.origin_label(hunk.origin)
+        );
+        if let Some(turn) = history.origin_turn(hunk.origin) {
+            if !turn.prompt.is_empty() {
+                println!(
+                    "\x1b[2m   prompt: {}\x1b[0m",
+                    turn.prompt.replace('\n', " ")
+                );
+            }
+            if !turn.reasoning.is_empty() {
+                println!(
+                    "\x1b[2m   reasoning: {}\x1b[0m",
+                    turn.reasoning.replace('\n', " ")
+                );
+            }
+        }
+        for (offset, line) in hunk.lines.iter().enumerate() {
+            println!("{:>width$} │ {}", hunk.start + offset, line, width = width);
+        }
+    }
+    Ok(())
+}
@@ -0,0 +1,74 @@
+use crate::utils::flow_cache::get_flow_documents_cached;
+use stakpak_api::{
+    Client,
+    models::{Flow, FlowRef},
+};
+use stakpak_shared::uri::uri_to_relative_path;
+use stakpak_tui::{BrowseAction, BrowseDocument, BrowseFlow, BrowseVersion, DocumentsResponse};
+
+fn to_browse_flow(owner_name: &str, flow: Flow) -> BrowseFlow {
+    let mut versions = flow.versions;
+    versions.sort_by(|a, b| b.created_at.cmp(&a.created_at));
+    BrowseFlow {
+        visibility: format!("{:?}", flow.visibility).to_lowercase(),
+        updated_at: flow.updated_at.format("%Y-%m-%d %H:%M").to_string(),
+        versions: versions
+            .into_iter()
+            .map(|version| BrowseVersion {
+                flow_ref: format!("{}/{}/{}", owner_name, flow.name, version.id),
+                id: version.id.to_string(),
+                created_at: version.created_at.format("%Y-%m-%d %H:%M").to_string(),
+                tags: version.tags.into_iter().map(|tag| tag.name).collect(),
+                immutable: version.immutable,
+            })
+            .collect(),
+        name: flow.name,
+    }
+}
+
+async fn fetch_documents(client: &Client, flow_ref: &str) -> Result<Vec<BrowseDocument>, String> {
+    let flow_ref = FlowRef::new(flow_ref.to_string())?;
+    let documents = get_flow_documents_cached(client, &flow_ref).await?;
+    let mut documents: Vec<BrowseDocument> = documents
+        .documents
+        .into_iter()
+        .chain(documents.additional_documents)
+        .map(|document| BrowseDocument {
+            path: uri_to_relative_path(&document.uri).to_string(),
+            provisioner: document.provisioner.to_string(),
+            content: document.content,
+        })
+        .collect();
+    documents.sort_by(|a, b| a.path.cmp(&b.path));
+    Ok(documents)
+}
+
+/// Open the flow browser on the flows of `owner_name`, returning what the user picked
+pub async fn browse(
+    client: &Client,
+    owner_name: &str,
+    flows: Vec<Flow>,
+) -> Result<Option<BrowseAction>, String> {
+    let flows = flows
+        .into_iter()
+        .map(|flow| to_browse_flow(owner_name, flow))
+        .collect();
+
+    let (request_tx, mut request_rx) = tokio::sync::mpsc::channel::<String>(10);
+    let (documents_tx, documents_rx) = tokio::sync::mpsc::channel::<DocumentsResponse>(10);
+    let fetch_client = client.clone();
+    let fetcher = tokio::spawn(async move {
+        while let Some(flow_ref) = request_rx.recv().await {
+            let result = fetch_documents(&fetch_client, &flow_ref).await;
+            if documents_tx.send((flow_ref, result)).await.is_err() {
+                break;
+            }
+        }
+    });
+
+    let action = stakpak_tui::run_browser(flows, request_tx, documents_rx)
+        .await
+        .map_err(|e| format!("Failed to run the flow browser: {}", e));
+    fetcher.abort();
+    action
+}
@@ -0,0 +1,82 @@
+use crate::config::AppConfig;
+use crate::settings::{check_env, env_var_statuses};
+use clap::Subcommand;
+
+#[derive(Subcommand, PartialEq)]
+pub enum ConfigCommands {
+    /// Check the environment variables the CLI honors and show the effective settings
+    Check,
+    /// Upgrade ~/.stakpak/config.toml to the current config version
+    Migrate {
+        /// Print the migrated config without writing it
+        #[arg(long, default_value_t = false)]
+        dry_run: bool,
+    },
+}
+
+impl ConfigCommands {
+    pub async fn run(self, config: AppConfig) -> Result<(), String> {
+        match self {
+            ConfigCommands::Check => {
+                let config_value = serde_json::to_value(&config).map_err(|e| e.to_string())?;
+                let statuses = env_var_statuses(&config_value, &AppConfig::file_settings());
+
+                println!("Config file: {}\n", AppConfig::config_path());
+                println!(
+                    "{:<27} {:<12} {:<32} DESCRIPTION",
+                    "VARIABLE", "SOURCE", "VALUE"
+                );
+                for status in &statuses {
+                    println!(
+                        "{:<27} {:<12} {:<32} {}",
+                        status.name,
+                        status.source,
+                        status.value.as_deref().unwrap_or("-"),
+                        status.description
+                    );
+                }
+
+                let issues = check_env();
+                if issues.is_empty() {
+                    println!("\nNo problems found");
+                    return Ok(());
+                }
+                println!();
+                for issue in &issues {
+                    println!("- {}", issue);
+                }
+                Err(format!(
+                    "{} problems found in the environment",
+                    issues.len()
+                ))
+            }
+            ConfigCommands::Migrate { dry_run } => {
+                let Some(migration) = AppConfig::migrate_file(dry_run)? else {
+                    println!("{} is up to date", AppConfig::config_path());
+                    return Ok(());
+                };
+                if dry_run {
+                    println!(
+                        "Would migrate {} from version {} to {}:\n",
+                        AppConfig::config_path(),
+                        migration.from,
+                        migration.to
+                    );
+                    print!(
+                        "{}",
+                        toml::to_string_pretty(&migration.migrated).map_err(|e| e.to_string())?
+                    );
+                } else {
+                    println!(
+                        "Migrated {} from version {} to {}, backup saved to {}",
+                        AppConfig::config_path(),
+                        migration.from,
+                        migration.to,
+                        migration.backup_path.unwrap_or_default()
+                    );
+                }
+                Ok(())
+            }
+        }
+    }
+}
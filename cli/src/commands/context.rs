@@ -0,0 +1,66 @@
+use crate::utils::context_packs::{CONTEXT_PACKS, WorkspaceContextPacks, find_context_pack};
+use clap::Subcommand;
+
+#[derive(Subcommand, PartialEq)]
+pub enum ContextCommands {
+    /// List the context packs and whether they are enabled in this workspace
+    List,
+    /// Print the guidance a context pack adds to the agent's context
+    Show {
+        /// Context pack ID, e.g. eks-terraform
+        pack: String,
+    },
+    /// Add a context pack to the agent's context in this workspace
+    Enable {
+        /// Context pack ID, e.g. eks-terraform
+        pack: String,
+    },
+    /// Remove a context pack from the agent's context in this workspace
+    Disable {
+        /// Context pack ID, e.g. eks-terraform
+        pack: String,
+    },
+}
+
+impl ContextCommands {
+    pub async fn run(self) -> Result<(), String> {
+        let mut workspace_packs = WorkspaceContextPacks::load()?;
+        match self {
+            ContextCommands::List => {
+                for pack in CONTEXT_PACKS {
+                    let enabled = workspace_packs.packs.iter().any(|id| id == pack.id);
+                    println!(
+                        "{} {:<14} {}",
+                        if enabled { "*" } else { " " },
+                        pack.id,
+                        pack.description
+                    );
+                }
+                println!("\n* enabled in this workspace");
+            }
+            ContextCommands::Show { pack } => {
+                print!("{}", find_context_pack(&pack)?.to_context());
+            }
+            ContextCommands::Enable { pack } => {
+                if workspace_packs.enable(&pack)? {
+                    workspace_packs.save()?;
+                    println!(
+                        "Enabled {}, new agent sessions in this workspace get its guidance",
+                        pack
+                    );
+                } else {
+                    println!("{} is already enabled", pack);
+                }
+            }
+            ContextCommands::Disable { pack } => {
+                if workspace_packs.disable(&pack) {
+                    workspace_packs.save()?;
+                    println!("Disabled {}", pack);
+                } else {
+                    println!("{} is not enabled", pack);
+                }
+            }
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,404 @@
+use chrono::{DateTime, Utc};
+use clap::Subcommand;
+use std::collections::HashSet;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::process::Stdio;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use tokio::io::{AsyncBufReadExt, AsyncWriteExt, BufReader};
+use tokio::process::Command;
+use tokio::sync::Semaphore;
+
+#[derive(Subcommand, PartialEq)]
+pub enum FleetCommands {
+    /// Run the same prompt as independent async agent sessions across directories
+    Run {
+        /// Directories to run in, glob patterns like services/*/infra are expanded
+        #[arg(long, short, num_args = 1.., required = true)]
+        dirs: Vec<String>,
+        /// Prompt to run in every directory
+        #[arg(long, short)]
+        prompt: String,
+        /// Maximum number of sessions running at the same time
+        #[arg(long, short, default_value_t = 4)]
+        concurrency: usize,
+        /// Path of the combined markdown report
+        #[arg(long, short, default_value = "fleet-report.md")]
+        report: String,
+    },
+}
+
+#[derive(Clone, PartialEq)]
+enum TaskStatus {
+    Pending,
+    Running,
+    Succeeded,
+    Failed(String),
+}
+
+impl std::fmt::Display for TaskStatus {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            TaskStatus::Pending => write!(f, "pending"),
+            TaskStatus::Running => write!(f, "running"),
+            TaskStatus::Succeeded => write!(f, "succeeded"),
+            TaskStatus::Failed(reason) => write!(f, "failed ({})", reason),
+        }
+    }
+}
+
+#[derive(Clone)]
+struct FleetTask {
+    dir: String,
+    status: TaskStatus,
+    steps: usize,
+    checkpoint_id: Option<String>,
+    log_path: PathBuf,
+    started_at: Option<DateTime<Utc>>,
+    finished_at: Option<DateTime<Utc>>,
+}
+
+/// Match a single path component against a pattern supporting `*` and `?`
+fn matches_wildcard(pattern: &str, name: &str) -> bool {
+    let pattern: Vec<char> = pattern.chars().collect();
+    let name: Vec<char> = name.chars().collect();
+    let (mut p, mut n) = (0, 0);
+    let (mut star, mut mark) = (None, 0);
+
+    while n < name.len() {
+        if p < pattern.len() && (pattern[p] == '?' || pattern[p] == name[n]) {
+            p += 1;
+            n += 1;
+        } else if p < pattern.len() && pattern[p] == '*' {
+            star = Some(p);
+            mark = n;
+            p += 1;
+        } else if let Some(star_pos) = star {
+            p = star_pos + 1;
+            mark += 1;
+            n = mark;
+        } else {
+            return false;
+        }
+    }
+    pattern[p..].iter().all(|c| *c == '*')
+}
+
+/// Expand glob patterns (when the shell did not) into existing directories
+fn expand_dirs(patterns: &[String]) -> Vec<String> {
+    let mut dirs = Vec::new();
+
+    for pattern in patterns {
+        if !pattern.contains(['*', '?']) {
+            if Path::new(pattern).is_dir() {
+                dirs.push(pattern.clone());
+            }
+            continue;
+        }
+
+        let mut candidates = vec![if pattern.starts_with('/') {
+            PathBuf::from("/")
+        } else {
+            PathBuf::new()
+        }];
+        for component in pattern.split('/').filter(|c| !c.is_empty()) {
+            let mut next = Vec::new();
+            for candidate in &candidates {
+                if !component.contains(['*', '?']) {
+                    next.push(candidate.join(component));
+                    continue;
+                }
+                let read_dir = if candidate.as_os_str().is_empty() {
+                    std::fs::read_dir(".")
+                } else {
+                    std::fs::read_dir(candidate)
+                };
+                if let Ok(entries) = read_dir {
+                    for entry in entries.filter_map(|e| e.ok()) {
+                        let name = entry.file_name().to_string_lossy().to_string();
+                        if matches_wildcard(component, &name) {
+                            next.push(candidate.join(name));
+                        }
+                    }
+                }
+            }
+            candidates = next;
+        }
+
+        let mut matched: Vec<String> = candidates
+            .into_iter()
+            .filter(|c| c.is_dir())
+            .map(|c| c.to_string_lossy().to_string())
+            .collect();
+        matched.sort();
+        dirs.extend(matched);
+    }
+
+    // Patterns may overlap, keep the first occurrence of each directory in pattern order
+    let mut seen = HashSet::new();
+    dirs.retain(|dir| seen.insert(dir.clone()));
+    dirs
+}
+
+fn render_table(tasks: &[FleetTask]) -> Vec<String> {
+    let width = tasks.iter().map(|t| t.dir.len()).max().unwrap_or(0).max(9);
+    let mut lines = vec![format!(
+        "{:<width$}  {:<24}  {:>5}  CHECKPOINT",
+        "DIRECTORY", "STATUS", "STEPS"
+    )];
+    for task in tasks {
+        lines.push(format!(
+            "{:<width$}  {:<24}  {:>5}  {}",
+            task.dir,
+            task.status.to_string(),
+            task.steps,
+            task.checkpoint_id.as_deref().unwrap_or("-")
+        ));
+    }
+    lines
+}
+
+fn render_report(prompt: &str, tasks: &[FleetTask]) -> String {
+    let succeeded = tasks
+        .iter()
+        .filter(|t| t.status == TaskStatus::Succeeded)
+        .count();
+
+    let mut report = format!(
+        "# Fleet run report\n\nPrompt: `{}`\n\n{} of {} sessions succeeded\n\n| Directory | Status | Steps | Duration | Checkpoint | Log |\n|---|---|---|---|---|---|\n",
+        prompt,
+        succeeded,
+        tasks.len()
+    );
+    for task in tasks {
+        let duration = match (task.started_at, task.finished_at) {
+            (Some(start), Some(end)) => format!("{}s", (end - start).num_seconds()),
+            _ => "-".to_string(),
+        };
+        report.push_str(&format!(
+            "| {} | {} | {} | {} | {} | {} |\n",
+            task.dir,
+            task.status,
+            task.steps,
+            duration,
+            task.checkpoint_id.as_deref().unwrap_or("-"),
+            task.log_path.display()
+        ));
+    }
+    report.push_str("\nResume a session with `stakpak -w <directory> -c <checkpoint>`\n");
+    report
+}
+
+async fn run_task(
+    index: usize,
+    prompt: String,
+    tasks: Arc<Mutex<Vec<FleetTask>>>,
+) -> Result<(), String> {
+    let (dir, log_path) = {
+        let mut tasks = tasks.lock().map_err(|e| e.to_string())?;
+        tasks[index].status = TaskStatus::Running;
+        tasks[index].started_at = Some(Utc::now());
+        (tasks[index].dir.clone(), tasks[index].log_path.clone())
+    };
+
+    let exe = std::env::current_exe().map_err(|e| e.to_string())?;
+    let mut log = tokio::fs::File::create(&log_path)
+        .await
+        .map_err(|e| e.to_string())?;
+    let stderr_log =
+        std::fs::File::create(log_path.with_extension("stderr.log")).map_err(|e| e.to_string())?;
+
+    let mut child = Command::new(exe)
+        .args(["-a", "-w", &dir, "--", &prompt])
+        .stdin(Stdio::null())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::from(stderr_log))
+        .kill_on_drop(true)
+        .spawn()
+        .map_err(|e| e.to_string())?;
+
+    if let Some(stdout) = child.stdout.take() {
+        let mut lines = BufReader::new(stdout).lines();
+        while let Ok(Some(line)) = lines.next_line().await {
+            let _ = log.write_all(format!("{}\n", line).as_bytes()).await;
+            if line.starts_with("--[Step ") {
+                if let Ok(mut tasks) = tasks.lock() {
+                    tasks[index].steps += 1;
+                }
+            }
+        }
+    }
+
+    let status = child.wait().await.map_err(|e| e.to_string())?;
+    // Async mode saves the final checkpoint in the session store of its working directory
+    let checkpoint_id =
+        std::fs::read_to_string(Path::new(&dir).join(".stakpak/session/checkpoint"))
+            .ok()
+            .map(|c| c.trim().to_string());
+
+    let mut tasks = tasks.lock().map_err(|e| e.to_string())?;
+    tasks[index].finished_at = Some(Utc::now());
+    tasks[index].checkpoint_id = checkpoint_id;
+    tasks[index].status = if status.success() {
+        TaskStatus::Succeeded
+    } else {
+        TaskStatus::Failed(
+            status
+                .code()
+                .map(|c| format!("exit code {}", c))
+                .unwrap_or_else(|| "killed".to_string()),
+        )
+    };
+
+    Ok(())
+}
+
+impl FleetCommands {
+    pub async fn run(self) -> Result<(), String> {
+        match self {
+            FleetCommands::Run {
+                dirs,
+                prompt,
+                concurrency,
+                report,
+            } => {
+                let dirs = expand_dirs(&dirs);
+                if dirs.is_empty() {
+                    return Err("No directories matched".into());
+                }
+
+                let logs_dir = PathBuf::from(".stakpak/session/fleet")
+                    .join(Utc::now().format("%Y%m%d%H%M%S").to_string());
+                std::fs::create_dir_all(&logs_dir).map_err(|e| e.to_string())?;
+
+                let tasks = Arc::new(Mutex::new(
+                    dirs.iter()
+                        .enumerate()
+                        .map(|(i, dir)| FleetTask {
+                            dir: dir.clone(),
+                            status: TaskStatus::Pending,
+                            steps: 0,
+                            checkpoint_id: None,
+                            log_path: logs_dir.join(format!("{:03}.log", i)),
+                            started_at: None,
+                            finished_at: None,
+                        })
+                        .collect::<Vec<_>>(),
+                ));
+
+                let semaphore = Arc::new(Semaphore::new(concurrency.max(1)));
+                let mut handles = Vec::new();
+                for index in 0..dirs.len() {
+                    let semaphore = semaphore.clone();
+                    let tasks = tasks.clone();
+                    let prompt = prompt.clone();
+                    handles.push(tokio::spawn(async move {
+                        let _permit = semaphore.acquire().await.map_err(|e| e.to_string())?;
+                        let result = run_task(index, prompt, tasks.clone()).await;
+                        if let Err(e) = &result {
+                            if let Ok(mut tasks) = tasks.lock() {
+                                tasks[index].status = TaskStatus::Failed(e.clone());
+                                tasks[index].finished_at = Some(Utc::now());
+                            }
+                        }
+                        result
+                    }));
+                }
+
+                // Live status table, redrawn in place until every session finished
+                let mut printed_lines = 0;
+                loop {
+                    let snapshot = tasks.lock().map_err(|e| e.to_string())?.clone();
+                    let lines = render_table(&snapshot);
+                    if printed_lines > 0 {
+                        print!("\x1b[{}A", printed_lines);
+                    }
+                    for line in &lines {
+                        println!("\x1b[2K{}", line);
+                    }
+                    let _ = std::io::stdout().flush();
+                    printed_lines = lines.len();
+
+                    if handles.iter().all(|h| h.is_finished()) {
+                        break;
+                    }
+                    tokio::time::sleep(Duration::from_millis(500)).await;
+                }
+
+                let snapshot = tasks.lock().map_err(|e| e.to_string())?.clone();
+                std::fs::write(&report, render_report(&prompt, &snapshot))
+                    .map_err(|e| format!("Failed to write report to {}: {}", report, e))?;
+                println!(
+                    "\nFleet report saved to {}, logs in {}",
+                    report,
+                    logs_dir.display()
+                );
+
+                let failed = snapshot
+                    .iter()
+                    .filter(|t| t.status != TaskStatus::Succeeded)
+                    .count();
+                if failed > 0 {
+                    return Err(format!("{} of {} sessions failed", failed, snapshot.len()));
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_matches_wildcard() {
+        assert!(matches_wildcard("*", "infra"));
+        assert!(matches_wildcard("svc-*", "svc-api"));
+        assert!(matches_wildcard("svc-?", "svc-a"));
+        assert!(!matches_wildcard("svc-?", "svc-ab"));
+        assert!(!matches_wildcard("svc-*", "api"));
+    }
+
+    #[test]
+    fn test_expand_dirs_skips_duplicates() {
+        let base = std::env::temp_dir().join(format!("stakpak-fleet-{}", std::process::id()));
+        for service in ["api", "web"] {
+            std::fs::create_dir_all(base.join(service).join("infra")).unwrap();
+        }
+        let path = |relative: &str| base.join(relative).to_string_lossy().to_string();
+
+        // Overlapping patterns that aren't next to each other in the expansion
+        let dirs = expand_dirs(&[
+            path("web/infra"),
+            path("*/infra"),
+            path("api/infra"),
+            path("missing"),
+        ]);
+        assert_eq!(dirs, vec![path("web/infra"), path("api/infra")]);
+
+        let _ = std::fs::remove_dir_all(&base);
+    }
+
+    #[test]
+    fn test_report_lists_every_task() {
+        let task = |dir: &str, status: TaskStatus| FleetTask {
+            dir: dir.to_string(),
+            status,
+            steps: 3,
+            checkpoint_id: None,
+            log_path: PathBuf::from(format!("{}.log", dir)),
+            started_at: None,
+            finished_at: None,
+        };
+        let tasks = vec![
+            task("api", TaskStatus::Succeeded),
+            task("web", TaskStatus::Failed("exit 1".to_string())),
+        ];
+        let report = render_report("upgrade", &tasks);
+        assert!(report.contains("1 of 2 sessions succeeded"));
+        assert!(report.contains("| web | failed (exit 1) | 3 | - | - | web.log |"));
+        assert_eq!(render_table(&tasks).len(), 3);
+    }
+}
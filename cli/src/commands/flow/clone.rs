@@ -5,23 +5,60 @@ use stakpak_api::{
     models::{FlowRef, ProvisionerType},
 };
 
+use stakpak_shared::uri::uri_to_path;
+
+use crate::utils::flow_cache::get_flow_documents_cached;
+
+use super::{find_variables, resolve_variables, substitute_variables};
+
 pub async fn clone(
     client: &Client,
     flow_ref: &FlowRef,
     dir: Option<&str>,
 ) -> Result<HashMap<ProvisionerType, Vec<PathBuf>>, String> {
-    let documents = client.get_flow_documents(flow_ref).await?;
+    clone_with_variables(client, flow_ref, dir, None).await
+}
+
+/// Clone a flow, substituting `{{var}}` placeholders when `variables` is set.
+/// Values not provided are prompted for.
+pub async fn clone_with_variables(
+    client: &Client,
+    flow_ref: &FlowRef,
+    dir: Option<&str>,
+    variables: Option<HashMap<String, String>>,
+) -> Result<HashMap<ProvisionerType, Vec<PathBuf>>, String> {
+    let documents = get_flow_documents_cached(client, flow_ref).await?;
     let base_dir = dir.unwrap_or(".");
 
+    let variables = match variables {
+        Some(provided) => {
+            let found = find_variables(
+                documents
+                    .documents
+                    .iter()
+                    .chain(documents.additional_documents.iter())
+                    .map(|doc| doc.content.as_str()),
+            )?;
+            if !found.is_empty() {
+                println!(
+                    "Flow uses variables: {}",
+                    found.iter().cloned().collect::<Vec<_>>().join(", ")
+                );
+            }
+            Some(resolve_variables(&found, provided)?)
+        }
+        None => None,
+    };
+
     let mut path_map = HashMap::new();
+    let mut unchanged = 0;
 
     for doc in documents
         .documents
         .into_iter()
         .chain(documents.additional_documents)
     {
-        let path = doc.uri.strip_prefix("file:///").unwrap_or(&doc.uri);
-        let full_path = std::path::Path::new(&base_dir).join(path);
+        let full_path = uri_to_path(std::path::Path::new(&base_dir), &doc.uri);
 
         path_map
             .entry(doc.provisioner)
@@ -34,13 +71,27 @@ pub async fn clone(
                 .map_err(|e| format!("Failed to create directory {}: {}", parent.display(), e))?;
         }
 
+        let content = match &variables {
+            Some(variables) => substitute_variables(&doc.content, variables)?,
+            None => doc.content,
+        };
+
+        // Leave files that already match untouched, so syncs don't trigger file watchers
+        if std::fs::read_to_string(&full_path).is_ok_and(|existing| existing == content) {
+            unchanged += 1;
+            continue;
+        }
+
         // Write the files
-        std::fs::write(&full_path, doc.content)
+        std::fs::write(&full_path, content)
             .map_err(|e| format!("Failed to write file {}: {}", full_path.display(), e))?;
 
         println!("Cloned {} -> \"{}\"", doc.uri, full_path.display());
     }
 
+    if unchanged > 0 {
+        println!("{} files already up to date", unchanged);
+    }
     println!("Successfully cloned flow to \"{}\"", base_dir);
 
     Ok(path_map)
@@ -0,0 +1,252 @@
+use similar::{ChangeTag, TextDiff};
+use stakpak_api::{Client, models::FlowRef};
+use stakpak_shared::uri::uri_to_relative_path;
+use std::collections::BTreeMap;
+use std::io::IsTerminal;
+
+use crate::utils::flow_cache::get_flow_documents_cached;
+
+pub struct FileDiff {
+    pub path: String,
+    pub insertions: usize,
+    pub deletions: usize,
+    pub unified: String,
+}
+
+async fn fetch_documents(
+    client: &Client,
+    flow_ref: &FlowRef,
+) -> Result<BTreeMap<String, String>, String> {
+    let documents = get_flow_documents_cached(client, flow_ref).await?;
+    Ok(documents
+        .documents
+        .into_iter()
+        .chain(documents.additional_documents)
+        .map(|doc| {
+            let path = uri_to_relative_path(&doc.uri).to_string();
+            (path, doc.content)
+        })
+        .collect())
+}
+
+/// Resolve the second version, either a bare version id/tag of the same flow or a full flow ref
+fn resolve_other_ref(base: &FlowRef, other: &str) -> Result<FlowRef, String> {
+    if other.contains('/') {
+        return FlowRef::new(other.to_string());
+    }
+    let (owner_name, flow_name) = match base {
+        FlowRef::Version {
+            owner_name,
+            flow_name,
+            ..
+        }
+        | FlowRef::Tag {
+            owner_name,
+            flow_name,
+            ..
+        } => (owner_name, flow_name),
+    };
+    FlowRef::new(format!("{}/{}/{}", owner_name, flow_name, other))
+}
+
+pub fn diff_documents(
+    old: &BTreeMap<String, String>,
+    new: &BTreeMap<String, String>,
+) -> Vec<FileDiff> {
+    let mut paths: Vec<&String> = old.keys().chain(new.keys()).collect();
+    paths.sort();
+    paths.dedup();
+
+    paths
+        .into_iter()
+        .filter_map(|path| {
+            let old_content = old.get(path).map(|c| c.as_str()).unwrap_or("");
+            let new_content = new.get(path).map(|c| c.as_str()).unwrap_or("");
+            if old.contains_key(path) && new.contains_key(path) && old_content == new_content {
+                return None;
+            }
+
+            let diff = TextDiff::from_lines(old_content, new_content);
+            let (mut insertions, mut deletions) = (0, 0);
+            for change in diff.iter_all_changes() {
+                match change.tag() {
+                    ChangeTag::Insert => insertions += 1,
+                    ChangeTag::Delete => deletions += 1,
+                    ChangeTag::Equal => {}
+                }
+            }
+
+            let old_header = if old.contains_key(path) {
+                format!("a/{}", path)
+            } else {
+                "/dev/null".to_string()
+            };
+            let new_header = if new.contains_key(path) {
+                format!("b/{}", path)
+            } else {
+                "/dev/null".to_string()
+            };
+
+            Some(FileDiff {
+                path: path.clone(),
+                insertions,
+                deletions,
+                unified: diff
+                    .unified_diff()
+                    .context_radius(3)
+                    .header(&old_header, &new_header)
+                    .to_string(),
+            })
+        })
+        .collect()
+}
+
+/// Whether diffs printed to stdout should be colored, honoring https://no-color.org
+pub fn diff_colors_enabled() -> bool {
+    std::io::stdout().is_terminal() && std::env::var_os("NO_COLOR").is_none_or(|v| v.is_empty())
+}
+
+/// Color a unified diff like `git diff`: headers bold, hunk headers cyan, removed lines
+/// red and added lines green
+pub fn colorize_diff(unified: &str) -> String {
+    unified
+        .split_inclusive('\n')
+        .map(|line| {
+            let (text, newline) = match line.strip_suffix('\n') {
+                Some(text) => (text, "\n"),
+                None => (line, ""),
+            };
+            let style = if text.starts_with("---") || text.starts_with("+++") {
+                "1"
+            } else if text.starts_with("@@") {
+                "36"
+            } else if text.starts_with('-') {
+                "31"
+            } else if text.starts_with('+') {
+                "32"
+            } else {
+                return line.to_string();
+            };
+            format!("\x1b[{}m{}\x1b[0m{}", style, text, newline)
+        })
+        .collect()
+}
+
+/// A unified diff as printed to stdout, colored when it is a terminal
+pub fn render_diff(unified: &str) -> String {
+    if diff_colors_enabled() {
+        colorize_diff(unified)
+    } else {
+        unified.to_string()
+    }
+}
+
+pub fn format_diff_summary(diffs: &[FileDiff]) -> String {
+    let width = diffs.iter().map(|d| d.path.len()).max().unwrap_or(0);
+    let mut lines: Vec<String> = diffs
+        .iter()
+        .map(|d| {
+            format!(
+                " {:<width$} | {:>4} {}{}",
+                d.path,
+                d.insertions + d.deletions,
+                "+".repeat(d.insertions.min(40)),
+                "-".repeat(d.deletions.min(40)),
+            )
+        })
+        .collect();
+    lines.push(format!(
+        " {} files changed, {} insertions(+), {} deletions(-)",
+        diffs.len(),
+        diffs.iter().map(|d| d.insertions).sum::<usize>(),
+        diffs.iter().map(|d| d.deletions).sum::<usize>()
+    ));
+    lines.join("\n")
+}
+
+/// Print per-file unified diffs between two versions of a flow, followed by a summary
+pub async fn diff_flow_versions(
+    client: &Client,
+    flow_ref: &str,
+    other: &str,
+) -> Result<(), String> {
+    let old_ref = FlowRef::new(flow_ref.to_string())?;
+    let new_ref = resolve_other_ref(&old_ref, other)?;
+
+    let (old_documents, new_documents) = tokio::try_join!(
+        fetch_documents(client, &old_ref),
+        fetch_documents(client, &new_ref)
+    )?;
+
+    let diffs = diff_documents(&old_documents, &new_documents);
+    if diffs.is_empty() {
+        println!("No changes between {} and {}", old_ref, new_ref);
+        return Ok(());
+    }
+
+    for file_diff in &diffs {
+        print!("{}", render_diff(&file_diff.unified));
+    }
+    println!("\n{} -> {}", old_ref, new_ref);
+    println!("{}", format_diff_summary(&diffs));
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn documents(files: &[(&str, &str)]) -> BTreeMap<String, String> {
+        files
+            .iter()
+            .map(|(path, content)| (path.to_string(), content.to_string()))
+            .collect()
+    }
+
+    #[test]
+    fn test_diff_documents() {
+        let old = documents(&[
+            ("main.tf", "region\nold\n"),
+            ("removed.tf", "gone\n"),
+            ("same.tf", "same\n"),
+        ]);
+        let new = documents(&[
+            ("added.tf", "one\ntwo\n"),
+            ("main.tf", "region\nnew\n"),
+            ("same.tf", "same\n"),
+        ]);
+        let diffs = diff_documents(&old, &new);
+        let summary: Vec<(&str, usize, usize)> = diffs
+            .iter()
+            .map(|d| (d.path.as_str(), d.insertions, d.deletions))
+            .collect();
+        assert_eq!(
+            summary,
+            vec![("added.tf", 2, 0), ("main.tf", 1, 1), ("removed.tf", 0, 1)]
+        );
+        assert!(
+            diffs[0]
+                .unified
+                .starts_with("--- /dev/null\n+++ b/added.tf\n")
+        );
+        assert!(
+            diffs[2]
+                .unified
+                .starts_with("--- a/removed.tf\n+++ /dev/null\n")
+        );
+        assert!(
+            format_diff_summary(&diffs)
+                .ends_with(" 3 files changed, 3 insertions(+), 2 deletions(-)")
+        );
+    }
+
+    #[test]
+    fn test_colorize_diff() {
+        let unified = "--- a/main.tf\n+++ b/main.tf\n@@ -1,2 +1,2 @@\n region\n-old\n+new";
+        assert_eq!(
+            colorize_diff(unified),
+            "\x1b[1m--- a/main.tf\x1b[0m\n\x1b[1m+++ b/main.tf\x1b[0m\n\x1b[36m@@ -1,2 +1,2 @@\x1b[0m\n region\n\x1b[31m-old\x1b[0m\n\x1b[32m+new\x1b[0m"
+        );
+    }
+}
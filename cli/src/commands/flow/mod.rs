@@ -1,11 +1,49 @@
+use clap::Subcommand;
+use stakpak_api::Client;
+
+use crate::config::AppConfig;
+
 mod clone;
 pub use clone::*;
 
+mod diff;
+pub use diff::*;
+
 mod get_flow_ref;
 pub use get_flow_ref::*;
 
 mod sync;
 pub use sync::*;
 
+mod sync_state;
+
 mod push;
 pub use push::*;
+
+mod variables;
+pub use variables::*;
+
+#[derive(Subcommand, PartialEq)]
+pub enum FlowCommands {
+    /// Show what changed between two versions of a flow
+    Diff {
+        /// Flow reference in format: <owner_name>/<flow_name>/<version_id_or_tag>
+        #[arg(name = "flow-ref")]
+        flow_ref: String,
+        /// Version id or tag to compare against, or a full flow reference
+        #[arg(name = "other")]
+        other: String,
+    },
+}
+
+impl FlowCommands {
+    pub async fn run(self, config: AppConfig) -> Result<(), String> {
+        match self {
+            FlowCommands::Diff { flow_ref, other } => {
+                let client = Client::new(&config.into()).map_err(|e| e.to_string())?;
+                diff_flow_versions(&client, &flow_ref, &other).await?;
+            }
+        }
+        Ok(())
+    }
+}
@@ -1,13 +1,110 @@
-use std::collections::{HashMap, HashSet};
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::fmt;
+use std::io::{IsTerminal, Write};
+use std::path::{Path, PathBuf};
+use std::time::Duration;
 
 use chrono::Utc;
-use walkdir::WalkDir;
+use futures_util::{StreamExt, stream};
 
 use stakpak_api::{
     Client, Edit, SaveEditsResponse,
     models::{Document, FlowRef},
 };
+use stakpak_shared::uri::{document_uri, uri_to_relative_path};
 
+use crate::utils::flow_cache::get_flow_documents_cached;
+use crate::utils::ignore_rules::walk_files;
+
+use super::{diff_documents, render_diff};
+
+/// Files sent per `save_edits` request unless `--batch-size` is given
+pub const DEFAULT_BATCH_SIZE: usize = 50;
+/// Chunks uploaded at the same time
+const CHUNK_CONCURRENCY: usize = 4;
+/// Attempts per chunk before it is reported as failed
+const CHUNK_ATTEMPTS: u32 = 3;
+/// Delay before retrying a chunk, multiplied by the attempts made so far
+const CHUNK_RETRY_DELAY: Duration = Duration::from_secs(2);
+/// Files read at the same time
+const READ_CONCURRENCY: usize = 16;
+const PROGRESS_BAR_WIDTH: usize = 30;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ChangeKind {
+    Added,
+    Modified,
+    Deleted,
+}
+
+impl fmt::Display for ChangeKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ChangeKind::Added => write!(f, "added"),
+            ChangeKind::Modified => write!(f, "modified"),
+            ChangeKind::Deleted => write!(f, "deleted"),
+        }
+    }
+}
+
+/// A local file that differs from the flow version being pushed to
+pub struct PendingChange {
+    pub uri: String,
+    pub kind: ChangeKind,
+    pub old_content: Option<String>,
+    pub new_content: Option<String>,
+}
+
+impl PendingChange {
+    pub fn path(&self) -> &str {
+        uri_to_relative_path(&self.uri)
+    }
+
+    pub fn edits(&self) -> Vec<Edit> {
+        let mut edits = Vec::new();
+        if let Some(old_content) = &self.old_content {
+            edits.push(create_edit(&self.uri, old_content, "delete"));
+        }
+        if let Some(new_content) = &self.new_content {
+            edits.push(create_edit(&self.uri, new_content, "insert"));
+        }
+        edits
+    }
+
+    pub fn unified_diff(&self) -> String {
+        let old: BTreeMap<String, String> = self
+            .old_content
+            .iter()
+            .map(|content| (self.path().to_string(), content.clone()))
+            .collect();
+        let new: BTreeMap<String, String> = self
+            .new_content
+            .iter()
+            .map(|content| (self.path().to_string(), content.clone()))
+            .collect();
+        diff_documents(&old, &new)
+            .into_iter()
+            .map(|file_diff| file_diff.unified)
+            .collect()
+    }
+}
+
+/// Chunk of files that could not be saved, even after retrying
+pub struct ChunkError {
+    /// Position of the chunk, starting at 1
+    pub chunk: usize,
+    pub files: Vec<String>,
+    pub error: String,
+}
+
+/// Outcome of a push, the responses of all saved chunks merged
+pub struct PushResult {
+    pub saved: SaveEditsResponse,
+    pub chunks: usize,
+    pub chunk_errors: Vec<ChunkError>,
+}
+
+#[allow(clippy::too_many_arguments)]
 pub async fn push(
     client: &Client,
     flow_ref: String,
@@ -15,29 +112,230 @@ pub async fn push(
     dir: Option<String>,
     ignore_delete: bool,
     auto_approve: bool,
-) -> Result<Option<SaveEditsResponse>, String> {
+    interactive: bool,
+    batch_size: usize,
+) -> Result<Option<PushResult>, String> {
     let flow_ref = parse_flow_ref(flow_ref, create, client).await?;
 
     println!("Pushing to flow version: {}\n", flow_ref);
 
     let base_dir = dir.unwrap_or_else(|| ".".into());
     let documents_map = fetch_flow_documents(client, &flow_ref).await?;
-    let (edits, files_synced, files_deleted) =
-        process_directory(&base_dir, &documents_map, ignore_delete).await?;
+    let changes = process_directory(&base_dir, &documents_map, ignore_delete).await?;
 
-    if files_synced + files_deleted == 0 {
+    if changes.is_empty() {
         println!("No changes found");
         return Ok(None);
     }
 
-    println!("\nSyncing {} files", files_synced);
+    for change in &changes {
+        println!("\t{:<8} {}", change.kind, change.path());
+    }
+
+    let changes = if interactive {
+        let accepted = review_changes(changes)?;
+        if accepted.is_empty() {
+            println!("\nNo changes selected");
+            return Ok(None);
+        }
+        accepted
+    } else {
+        changes
+    };
+
+    let files_deleted = changes
+        .iter()
+        .filter(|change| change.kind == ChangeKind::Deleted)
+        .count();
+    println!("\nSyncing {} files", changes.len() - files_deleted);
     println!("Deleting {} files", files_deleted);
 
-    if !auto_approve && !create && !confirm_action()? {
+    if !interactive && !auto_approve && !create && !confirm_action()? {
         return Ok(None);
     }
 
-    Ok(Some(client.save_edits(&flow_ref, edits).await?))
+    Ok(Some(
+        upload_chunks(client, &flow_ref, &changes, batch_size).await,
+    ))
+}
+
+/// Progress of an upload drawn on stderr, only when it is a terminal
+struct ProgressBar {
+    total: usize,
+    done: usize,
+    visible: bool,
+}
+
+fn progress_line(done: usize, total: usize) -> String {
+    let filled = (done * PROGRESS_BAR_WIDTH)
+        .checked_div(total)
+        .unwrap_or(PROGRESS_BAR_WIDTH);
+    format!(
+        "[{}{}] {}/{} files",
+        "#".repeat(filled),
+        "-".repeat(PROGRESS_BAR_WIDTH - filled),
+        done,
+        total
+    )
+}
+
+impl ProgressBar {
+    fn new(total: usize) -> Self {
+        let bar = Self {
+            total,
+            done: 0,
+            visible: std::io::stderr().is_terminal(),
+        };
+        bar.draw();
+        bar
+    }
+
+    fn draw(&self) {
+        if self.visible {
+            eprint!("\r{}", progress_line(self.done, self.total));
+            let _ = std::io::stderr().flush();
+        }
+    }
+
+    fn advance(&mut self, files: usize) {
+        self.done = (self.done + files).min(self.total);
+        self.draw();
+    }
+
+    fn finish(&self) {
+        if self.visible {
+            eprintln!();
+        }
+    }
+}
+
+/// Save one chunk of changes, retrying failed requests
+async fn save_chunk(
+    client: &Client,
+    flow_ref: &FlowRef,
+    chunk: &[PendingChange],
+) -> Result<SaveEditsResponse, String> {
+    let mut attempt = 1;
+    loop {
+        let edits = chunk.iter().flat_map(|change| change.edits()).collect();
+        match client.save_edits(flow_ref, edits).await {
+            Ok(response) => return Ok(response),
+            Err(_) if attempt < CHUNK_ATTEMPTS => {
+                tokio::time::sleep(CHUNK_RETRY_DELAY * attempt).await;
+                attempt += 1;
+            }
+            Err(e) => return Err(format!("{} (after {} attempts)", e, attempt)),
+        }
+    }
+}
+
+/// Send the changes in chunks of `batch_size` files, so large pushes don't hit the API
+/// timeout. A file's delete and insert edits always go in the same chunk.
+async fn upload_chunks(
+    client: &Client,
+    flow_ref: &FlowRef,
+    changes: &[PendingChange],
+    batch_size: usize,
+) -> PushResult {
+    let chunks: Vec<&[PendingChange]> = changes.chunks(batch_size.max(1)).collect();
+    let mut result = PushResult {
+        saved: SaveEditsResponse {
+            created_blocks: Vec::new(),
+            modified_blocks: Vec::new(),
+            errors: Vec::new(),
+        },
+        chunks: chunks.len(),
+        chunk_errors: Vec::new(),
+    };
+
+    let mut progress = ProgressBar::new(changes.len());
+    let mut uploads =
+        stream::iter(chunks.into_iter().enumerate())
+            .map(|(index, chunk)| async move {
+                (index, chunk, save_chunk(client, flow_ref, chunk).await)
+            })
+            .buffer_unordered(CHUNK_CONCURRENCY);
+    while let Some((index, chunk, outcome)) = uploads.next().await {
+        progress.advance(chunk.len());
+        match outcome {
+            Ok(response) => {
+                result.saved.created_blocks.extend(response.created_blocks);
+                result
+                    .saved
+                    .modified_blocks
+                    .extend(response.modified_blocks);
+                result.saved.errors.extend(response.errors);
+            }
+            Err(error) => result.chunk_errors.push(ChunkError {
+                chunk: index + 1,
+                files: chunk
+                    .iter()
+                    .map(|change| change.path().to_string())
+                    .collect(),
+                error,
+            }),
+        }
+    }
+    progress.finish();
+
+    result.chunk_errors.sort_by_key(|error| error.chunk);
+    result
+}
+
+const REVIEW_HELP: &str = "a - push this file
+s - skip this file
+d - show the diff for this file
+A - push this file and all remaining files
+q - quit, skipping this file and all remaining files
+? - print help";
+
+/// Walk through each pending change and let the user pick which ones to push,
+/// similar to `git add -p`
+fn review_changes(changes: Vec<PendingChange>) -> Result<Vec<PendingChange>, String> {
+    let total = changes.len();
+    let mut accepted = Vec::new();
+    let mut remaining = changes.into_iter().enumerate();
+
+    println!();
+    while let Some((index, change)) = remaining.next() {
+        loop {
+            print!(
+                "({}/{}) Push {} {} [a,s,d,A,q,?]? ",
+                index + 1,
+                total,
+                change.kind,
+                change.path()
+            );
+            std::io::stdout().flush().map_err(|e| e.to_string())?;
+            let mut input = String::new();
+            let read = std::io::stdin()
+                .read_line(&mut input)
+                .map_err(|e| format!("Failed to read input: {}", e))?;
+            // Treat end of input like quitting the review
+            if read == 0 {
+                println!();
+                return Ok(accepted);
+            }
+
+            match input.trim() {
+                "a" | "y" => {
+                    accepted.push(change);
+                    break;
+                }
+                "s" | "n" => break,
+                "d" => print!("{}", render_diff(&change.unified_diff())),
+                "A" => {
+                    accepted.push(change);
+                    accepted.extend(remaining.by_ref().map(|(_, change)| change));
+                    return Ok(accepted);
+                }
+                "q" => return Ok(accepted),
+                _ => println!("{}", REVIEW_HELP),
+            }
+        }
+    }
+
+    Ok(accepted)
 }
 
 async fn parse_flow_ref(
@@ -86,7 +384,7 @@ async fn fetch_flow_documents(
     client: &Client,
     flow_ref: &FlowRef,
 ) -> Result<HashMap<String, Document>, String> {
-    let documents = client.get_flow_documents(flow_ref).await?.documents;
+    let documents = get_flow_documents_cached(client, flow_ref).await?.documents;
     Ok(documents
         .into_iter()
         .map(|doc| (doc.uri.clone(), doc))
@@ -118,58 +416,63 @@ async fn process_directory(
     base_dir: &str,
     documents_map: &HashMap<String, Document>,
     ignore_delete: bool,
-) -> Result<(Vec<Edit>, usize, usize), String> {
-    let mut edits = Vec::new();
+) -> Result<Vec<PendingChange>, String> {
+    let mut changes = Vec::new();
     let mut processed_uris = HashSet::new();
-    let mut files_synced = 0;
-    let mut files_deleted = 0;
 
-    for entry in WalkDir::new(base_dir)
+    let paths: Vec<PathBuf> = walk_files(Path::new(base_dir))
         .into_iter()
-        .filter_entry(|e| {
-            // Skip hidden directories and non-supported files
-            let file_name = e.file_name().to_str();
-            is_supported_file(file_name, e.file_type().is_file())
+        .filter(|path| is_supported_file(path.file_name().and_then(|name| name.to_str()), true))
+        .collect();
+
+    // Read files concurrently, keeping the walk order
+    let files: Vec<(PathBuf, std::io::Result<String>)> = stream::iter(paths)
+        .map(|path| async move {
+            let content = tokio::fs::read_to_string(&path).await;
+            (path, content)
         })
-        .filter_map(|e| e.ok())
-    {
-        if !entry.file_type().is_file() {
-            continue;
-        }
+        .buffered(READ_CONCURRENCY)
+        .collect()
+        .await;
 
-        let path = entry.path();
-        let content = std::fs::read_to_string(path).map_err(|_| "Failed to read file")?;
-        let document_uri = format!(
-            "file:///{}",
-            path.strip_prefix(base_dir)
-                .map_err(|e| format!("Failed to strip prefix: {}", e))?
-                .to_string_lossy()
-                .replace('\\', "/")
-        );
+    for (path, content) in files {
+        let content = content.map_err(|e| format!("Failed to read {}: {}", path.display(), e))?;
+        let document_uri = document_uri(Path::new(base_dir), &path)?;
         processed_uris.insert(document_uri.clone());
 
         if let Some(document) = documents_map.get(&document_uri) {
             if content != document.content {
-                edits.push(create_edit(&document_uri, &document.content, "delete"));
-                edits.push(create_edit(&document_uri, &content, "insert"));
-                files_synced += 1;
+                changes.push(PendingChange {
+                    uri: document_uri,
+                    kind: ChangeKind::Modified,
+                    old_content: Some(document.content.clone()),
+                    new_content: Some(content),
+                });
             }
         } else {
-            edits.push(create_edit(&document_uri, &content, "insert"));
-            files_synced += 1;
+            changes.push(PendingChange {
+                uri: document_uri,
+                kind: ChangeKind::Added,
+                old_content: None,
+                new_content: Some(content),
+            });
         }
     }
 
     if !ignore_delete {
         for (uri, document) in documents_map {
             if !processed_uris.contains(uri) {
-                edits.push(create_edit(uri, &document.content, "delete"));
-                files_deleted += 1;
+                changes.push(PendingChange {
+                    uri: uri.clone(),
+                    kind: ChangeKind::Deleted,
+                    old_content: Some(document.content.clone()),
+                    new_content: None,
+                });
             }
         }
     }
 
-    Ok((edits, files_synced, files_deleted))
+    Ok(changes)
 }
 
 pub fn create_edit(document_uri: &str, content: &str, operation: &str) -> Edit {
@@ -196,3 +499,80 @@ fn confirm_action() -> Result<bool, String> {
         .map_err(|e| format!("Failed to read input: {}", e))?;
     Ok(input.trim() == "yes")
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_progress_line() {
+        assert_eq!(
+            progress_line(0, 4),
+            format!("[{}] 0/4 files", "-".repeat(PROGRESS_BAR_WIDTH))
+        );
+        assert_eq!(
+            progress_line(2, 4),
+            format!(
+                "[{}{}] 2/4 files",
+                "#".repeat(PROGRESS_BAR_WIDTH / 2),
+                "-".repeat(PROGRESS_BAR_WIDTH / 2)
+            )
+        );
+        assert_eq!(
+            progress_line(0, 0),
+            format!("[{}] 0/0 files", "#".repeat(PROGRESS_BAR_WIDTH))
+        );
+    }
+
+    fn change(kind: ChangeKind, old: Option<&str>, new: Option<&str>) -> PendingChange {
+        PendingChange {
+            uri: "file:///infra/main.tf".to_string(),
+            kind,
+            old_content: old.map(str::to_string),
+            new_content: new.map(str::to_string),
+        }
+    }
+
+    #[test]
+    fn test_pending_change_edits() {
+        let operations = |change: &PendingChange| {
+            change
+                .edits()
+                .into_iter()
+                .map(|edit| (edit.operation, edit.content))
+                .collect::<Vec<_>>()
+        };
+        let modified = change(ChangeKind::Modified, Some("old\n"), Some("new\n"));
+        assert_eq!(modified.path(), "infra/main.tf");
+        assert_eq!(
+            operations(&modified),
+            vec![
+                ("delete".to_string(), "old\n".to_string()),
+                ("insert".to_string(), "new\n".to_string()),
+            ]
+        );
+        assert_eq!(
+            operations(&change(ChangeKind::Added, None, Some("new\n"))),
+            vec![("insert".to_string(), "new\n".to_string())]
+        );
+        assert_eq!(
+            operations(&change(ChangeKind::Deleted, Some("old\n"), None)),
+            vec![("delete".to_string(), "old\n".to_string())]
+        );
+    }
+
+    #[test]
+    fn test_pending_change_diff() {
+        let modified = change(
+            ChangeKind::Modified,
+            Some("region\nold\n"),
+            Some("region\nnew\n"),
+        );
+        let diff = modified.unified_diff();
+        assert!(diff.starts_with("--- a/infra/main.tf\n+++ b/infra/main.tf\n"));
+        assert!(diff.contains("\n-old\n+new\n"));
+
+        let deleted = change(ChangeKind::Deleted, Some("old\n"), None);
+        assert!(deleted.unified_diff().contains("+++ /dev/null\n"));
+    }
+}
@@ -1,5 +1,5 @@
 use std::{
-    collections::{HashMap, HashSet},
+    collections::{BTreeMap, BTreeSet, HashMap, HashSet},
     hash::{DefaultHasher, Hash, Hasher},
     path::Path,
     sync::{
@@ -18,16 +18,25 @@ use rust_socketio::{
 use serde::{Deserialize, Serialize};
 use serde_json::json;
 use tokio::{sync::mpsc, time::sleep};
-use walkdir::WalkDir;
 
 use crate::{
-    commands::flow::{clone, create_edit, is_supported_file},
+    commands::flow::{create_edit, is_supported_file},
     config::AppConfig,
+    utils::{
+        flow_cache::get_flow_documents_cached,
+        ignore_rules::{IgnoreRules, walk_files},
+    },
 };
 use stakpak_api::{
     Client, Edit,
     models::{Document, FlowRef},
 };
+use stakpak_shared::uri::{document_uri, uri_to_path, uri_to_relative_path};
+
+use super::sync_state::{
+    MergeAction, Resolution, SyncState, can_prompt, conflict_markers, has_conflict_markers,
+    merge_action, resolve_conflict,
+};
 
 #[derive(Deserialize, Serialize, Debug)]
 pub struct DocumentBuffer {
@@ -48,21 +57,180 @@ pub enum Change {
     Remote(DocumentsChange),
 }
 
+/// What sync needs to push and pull changes, and to tell which side changed a file
+struct SyncContext<'a> {
+    client: &'a Client,
+    flow_ref: &'a FlowRef,
+    dir: &'a Path,
+    state: SyncState,
+    /// Ask how to resolve conflicts rather than writing conflict markers
+    prompt: bool,
+}
+
+impl SyncContext<'_> {
+    fn save_state(&self) {
+        if let Err(e) = self.state.save(self.dir) {
+            eprintln!("Failed to save sync state: {}", e);
+        }
+    }
+
+    /// Save `edits` to the flow and record the files they changed as synced
+    async fn push_edits(&mut self, edits: Vec<Edit>) -> Result<(), String> {
+        if edits.is_empty() {
+            return Ok(());
+        }
+        println!("🚀 Pushing changes...");
+        let saved = edits.clone();
+        self.client.save_edits(self.flow_ref, edits).await?;
+        self.state.record_edits(&saved);
+        self.save_state();
+        Ok(())
+    }
+}
+
+/// Edits replacing the flow version of `uri` with the local one, `None` for missing files
+fn replace_edits(uri: &str, remote: Option<&str>, local: Option<&str>) -> Vec<Edit> {
+    remote
+        .map(|content| create_edit(uri, content, "delete"))
+        .into_iter()
+        .chain(local.map(|content| create_edit(uri, content, "insert")))
+        .collect()
+}
+
+/// Write `content` to the local file of `uri`, or delete the file when it is `None`
+fn write_local(dir: &Path, uri: &str, content: Option<&str>) -> Result<(), String> {
+    let path = uri_to_path(dir, uri);
+    match content {
+        Some(content) => {
+            if let Some(parent) = path.parent() {
+                std::fs::create_dir_all(parent).map_err(|e| {
+                    format!("Failed to create directory {}: {}", parent.display(), e)
+                })?;
+            }
+            std::fs::write(&path, content)
+                .map_err(|e| format!("Failed to write file {}: {}", path.display(), e))
+        }
+        None => match std::fs::remove_file(&path) {
+            Err(e) if e.kind() != std::io::ErrorKind::NotFound => {
+                Err(format!("Failed to delete file {}: {}", path.display(), e))
+            }
+            _ => Ok(()),
+        },
+    }
+}
+
+fn local_documents(dir: &Path) -> BTreeMap<String, String> {
+    walk_files(dir)
+        .into_iter()
+        .filter(|path| is_supported_file(path.file_name().and_then(|name| name.to_str()), true))
+        .filter_map(|path| {
+            let uri = document_uri(dir, &path).ok()?;
+            let content = std::fs::read_to_string(&path).ok()?;
+            Some((uri, content))
+        })
+        .collect()
+}
+
+/// Bring the directory and the flow up to date with each other before watching them.
+/// Files changed on one side since the last sync are pulled or pushed, files changed on
+/// both sides are conflicts. Returns the flow version of the files left in conflict.
+async fn reconcile(ctx: &mut SyncContext<'_>) -> Result<HashMap<String, String>, String> {
+    let documents = get_flow_documents_cached(ctx.client, ctx.flow_ref).await?;
+    let remote: BTreeMap<String, String> = documents
+        .documents
+        .into_iter()
+        .chain(documents.additional_documents)
+        .map(|doc| (doc.uri, doc.content))
+        .collect();
+    let local = local_documents(ctx.dir);
+    let uris: BTreeSet<&String> = remote.keys().chain(local.keys()).collect();
+
+    let mut edits = Vec::new();
+    let mut conflicts = HashMap::new();
+    for uri in uris {
+        let local = local.get(uri).map(String::as_str);
+        let remote = remote.get(uri).map(String::as_str);
+        let base = ctx.state.files.get(uri).map(String::as_str);
+        let resolution = match merge_action(base, local, remote) {
+            MergeAction::Unchanged => {
+                if remote.is_some() {
+                    ctx.state.record(uri, remote);
+                }
+                continue;
+            }
+            MergeAction::Pull => Resolution::TakeRemote,
+            MergeAction::Push if local.is_some_and(has_conflict_markers) => {
+                println!(
+                    "⚠️  {} still has conflict markers, resolve them to push it",
+                    uri_to_relative_path(uri)
+                );
+                Resolution::Skip
+            }
+            MergeAction::Push => Resolution::KeepLocal,
+            MergeAction::Conflict => resolve_conflict(uri, local, remote, ctx.prompt)?,
+        };
+
+        match resolution {
+            Resolution::KeepLocal => edits.extend(replace_edits(uri, remote, local)),
+            Resolution::TakeRemote => {
+                write_local(ctx.dir, uri, remote)?;
+                if remote.is_some() {
+                    println!("Pulled {}", uri_to_relative_path(uri));
+                }
+                ctx.state.record(uri, remote);
+            }
+            Resolution::Markers => {
+                if let (Some(local), Some(remote)) = (local, remote) {
+                    let merged = conflict_markers(local, remote, &ctx.flow_ref.to_string());
+                    write_local(ctx.dir, uri, Some(&merged))?;
+                    conflicts.insert(uri.clone(), remote.to_string());
+                }
+            }
+            Resolution::Skip => {
+                if let Some(remote) = remote {
+                    conflicts.insert(uri.clone(), remote.to_string());
+                }
+            }
+        }
+    }
+
+    ctx.push_edits(edits).await?;
+    ctx.save_state();
+    Ok(conflicts)
+}
+
 pub async fn sync(
     config: &AppConfig,
     client: &Client,
     flow_ref: &FlowRef,
     dir: Option<&str>,
+    conflict_markers: bool,
 ) -> Result<(), String> {
-    // Initial setup
-    clone(client, flow_ref, dir).await?;
     #[allow(clippy::unwrap_used)]
     let dir = dir
         .map(|d| Path::new(&d).to_path_buf())
         .unwrap_or_else(|| std::env::current_dir().unwrap());
+    std::fs::create_dir_all(&dir)
+        .map_err(|e| format!("Failed to create directory {}: {}", dir.display(), e))?;
+
+    let mut ctx = SyncContext {
+        client,
+        flow_ref,
+        dir: &dir,
+        state: SyncState::load(&dir, &flow_ref.to_string()),
+        prompt: !conflict_markers && can_prompt(),
+    };
+    let conflicts = reconcile(&mut ctx).await?;
 
-    // Initialize state
+    // Watch the files as they are once reconciled
     let mut watched_files = initialize_watched_files(&dir);
+    // Files left in conflict are compared to the flow version, so resolving them pushes
+    // the result
+    for (uri, remote) in conflicts {
+        if let Some(buffer) = watched_files.get_mut(&uri) {
+            buffer.content = remote;
+        }
+    }
     let (tx, mut rx) = mpsc::channel(32);
 
     // Set up watchers
@@ -77,12 +245,14 @@ pub async fn sync(
     while let Some(change) = rx.recv().await {
         match change {
             Change::Internal(event) => {
-                handle_internal_change(event, &dir, &mut watched_files, client, flow_ref)
+                handle_internal_change(event, &mut ctx, &mut watched_files)
                     .await
                     .ok();
             }
             Change::Remote(change) => {
-                handle_remote_change(change, &dir, &mut watched_files);
+                if let Err(e) = handle_remote_change(change, &mut ctx, &mut watched_files).await {
+                    eprintln!("Failed to sync changes: {}", e);
+                }
             }
         }
     }
@@ -92,17 +262,13 @@ pub async fn sync(
 
 fn initialize_watched_files(dir: &Path) -> HashMap<String, DocumentBuffer> {
     #[allow(clippy::unwrap_used)]
-    WalkDir::new(dir)
+    walk_files(dir)
         .into_iter()
-        .filter_map(Result::ok)
-        .filter(|entry| {
-            entry.path().is_file()
-                && is_supported_file(entry.path().file_name().unwrap().to_str(), true)
-        })
-        .filter_map(|entry| {
-            let path = entry.path();
+        .filter(|path| is_supported_file(path.file_name().unwrap().to_str(), true))
+        .filter_map(|path| {
+            let path = path.as_path();
+            let uri = document_uri(dir, path).ok()?;
             hash_file(path).ok().map(|hash| {
-                let uri = get_uri(dir, path);
                 (
                     uri.clone(),
                     DocumentBuffer {
@@ -130,11 +296,10 @@ fn setup_file_watcher(tx: mpsc::Sender<Change>) -> Result<RecommendedWatcher, St
 
 async fn handle_internal_change(
     event: Event,
-    dir: &Path,
+    ctx: &mut SyncContext<'_>,
     watched_files: &mut HashMap<String, DocumentBuffer>,
-    client: &Client,
-    flow_ref: &FlowRef,
 ) -> Result<(), String> {
+    let dir = ctx.dir;
     let Some(event_path) = event.paths.first() else {
         return Ok(());
     };
@@ -143,7 +308,8 @@ async fn handle_internal_change(
     if !is_supported_file(
         event_path.file_name().unwrap().to_str(),
         event_path.is_file(),
-    ) {
+    ) || IgnoreRules::load(dir).is_ignored(event_path, event_path.is_dir())
+    {
         return Ok(());
     }
 
@@ -160,12 +326,7 @@ async fn handle_internal_change(
     // Handle modifications
     process_modified_files(&event, dir, watched_files, &mut edits);
 
-    if !edits.is_empty() {
-        println!("🚀 Pushing changes...");
-        client.save_edits(flow_ref, edits).await?;
-    }
-
-    Ok(())
+    ctx.push_edits(edits).await
 }
 
 fn process_deleted_files(
@@ -176,7 +337,7 @@ fn process_deleted_files(
     let invalid_paths: Vec<_> = watched_files
         .keys()
         .filter(|path| {
-            let absolute_path = Path::new(dir).join(path.strip_prefix("file:///").unwrap_or(path));
+            let absolute_path = uri_to_path(dir, path);
 
             std::fs::read_to_string(absolute_path).is_err()
         })
@@ -198,12 +359,18 @@ fn process_modified_files(
     edits: &mut Vec<Edit>,
 ) {
     for path in &event.paths {
-        if let Ok(hash) = hash_file(path) {
-            let uri = get_uri(dir, path);
+        if let (Ok(hash), Ok(uri)) = (hash_file(path), document_uri(dir, path)) {
             if let Some(buffer) = watched_files.get(&uri) {
                 if buffer.hash != hash {
                     #[allow(clippy::unwrap_used)]
                     let new_content = std::fs::read_to_string(path).unwrap();
+                    if has_conflict_markers(&new_content) {
+                        println!(
+                            "⚠️  {} has conflict markers, resolve them to push it",
+                            uri_to_relative_path(&uri)
+                        );
+                        continue;
+                    }
                     edits.extend([
                         create_edit(&uri, &buffer.content, "delete"),
                         create_edit(&uri, &new_content, "insert"),
@@ -222,61 +389,101 @@ fn process_modified_files(
     }
 }
 
-fn handle_remote_change(
+/// Apply a change made to the flow. Files also changed locally since they were last
+/// synced, e.g. because pushing them failed, are conflicts rather than overwritten.
+async fn handle_remote_change(
     change: DocumentsChange,
-    dir: &Path,
+    ctx: &mut SyncContext<'_>,
     watched_files: &mut HashMap<String, DocumentBuffer>,
-) {
+) -> Result<(), String> {
     println!("🔄 Syncing changes...");
-    let document_uris: HashSet<String> = change.documents.iter().map(|d| d.uri.clone()).collect();
-    for uri in change.touched_document_uris {
-        if !document_uris.contains(&uri) {
-            let absolute_path = Path::new(dir).join(uri.strip_prefix("file:///").unwrap_or(&uri));
-            if watched_files.contains_key(&uri) {
-                watched_files.remove(&uri);
+    let mut remote: BTreeMap<String, Option<String>> = change
+        .touched_document_uris
+        .into_iter()
+        .map(|uri| (uri, None))
+        .collect();
+    for doc in change.documents {
+        remote.insert(doc.uri, Some(doc.content));
+    }
+
+    let mut edits = Vec::new();
+    for (uri, remote) in remote {
+        let remote = remote.as_deref();
+        let local = std::fs::read_to_string(uri_to_path(ctx.dir, &uri)).ok();
+        let local = local.as_deref();
+        let changed_locally = match watched_files.get(&uri) {
+            Some(buffer) => local.map(hash_content) != Some(buffer.hash),
+            None => local.is_some(),
+        };
+        let resolution = if changed_locally && local != remote {
+            resolve_conflict(&uri, local, remote, ctx.prompt)?
+        } else {
+            Resolution::TakeRemote
+        };
+
+        match resolution {
+            Resolution::TakeRemote => {
+                write_local(ctx.dir, &uri, remote)?;
+                watch(watched_files, &uri, remote, remote);
+                ctx.state.record(&uri, remote);
+            }
+            Resolution::KeepLocal => {
+                edits.extend(replace_edits(&uri, remote, local));
+                watch(watched_files, &uri, local, local);
             }
-            std::fs::remove_file(&absolute_path).ok();
+            Resolution::Markers => {
+                if let (Some(local), Some(remote)) = (local, remote) {
+                    let merged = conflict_markers(local, remote, &ctx.flow_ref.to_string());
+                    write_local(ctx.dir, &uri, Some(&merged))?;
+                    watch(watched_files, &uri, Some(remote), Some(&merged));
+                }
+            }
+            // The next local edit is pushed over the flow version
+            Resolution::Skip => watch(watched_files, &uri, remote, local),
         }
     }
-    for doc in change.documents {
-        let uri = doc.uri.clone();
-        let absolute_path = Path::new(dir).join(uri.strip_prefix("file:///").unwrap_or(&uri));
-        #[allow(clippy::unwrap_used)]
-        std::fs::write(&absolute_path, &doc.content).unwrap();
 
-        if let Ok(hash) = hash_file(&absolute_path) {
+    ctx.state.save(ctx.dir)?;
+    ctx.push_edits(edits).await
+}
+
+/// Track `uri` as having `synced` content in the flow and `on_disk` content locally, so
+/// only later local edits are pushed
+fn watch(
+    watched_files: &mut HashMap<String, DocumentBuffer>,
+    uri: &str,
+    synced: Option<&str>,
+    on_disk: Option<&str>,
+) {
+    match (synced, on_disk) {
+        (Some(synced), Some(on_disk)) => {
             watched_files.insert(
-                uri.clone(),
+                uri.to_string(),
                 DocumentBuffer {
-                    content: doc.content,
-                    uri,
-                    hash,
+                    content: synced.to_string(),
+                    uri: uri.to_string(),
+                    hash: hash_content(on_disk),
                 },
             );
         }
+        _ => {
+            watched_files.remove(uri);
+        }
     }
 }
 
+fn hash_content(content: &str) -> u64 {
+    let mut hasher = DefaultHasher::new();
+    content.hash(&mut hasher);
+    hasher.finish()
+}
+
 fn hash_file(path: &Path) -> Result<u64, String> {
     std::fs::read_to_string(path)
-        .map(|content| {
-            let mut hasher = DefaultHasher::new();
-            content.hash(&mut hasher);
-            hasher.finish()
-        })
+        .map(|content| hash_content(&content))
         .map_err(|_| "Cannot read file".to_string())
 }
 
-fn get_uri(dir: &Path, path: &Path) -> String {
-    #[allow(clippy::unwrap_used)]
-    let path = path
-        .strip_prefix(dir)
-        .unwrap()
-        .to_string_lossy()
-        .replace('\\', "/");
-    format!("file:///{}", path)
-}
-
 async fn subscribe_to_remote_changes(
     config: &AppConfig,
     flow_ref: &FlowRef,
@@ -0,0 +1,267 @@
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::collections::BTreeMap;
+use std::io::{IsTerminal, Write};
+use std::path::{Path, PathBuf};
+
+use stakpak_api::Edit;
+use stakpak_shared::uri::uri_to_relative_path;
+
+use super::{diff_documents, render_diff};
+
+/// Hashes of the files as of the last sync, relative to the synced directory
+const SYNC_STATE_FILE: &str = ".stakpak/sync-state.json";
+const CONFLICT_START: &str = "<<<<<<< local";
+const CONFLICT_SEPARATOR: &str = "=======";
+const CONFLICT_END: &str = ">>>>>>> remote";
+
+/// Content of every file both sides agreed on when they were last synced, used as the
+/// common base to tell which side changed a file
+#[derive(Serialize, Deserialize, Debug, Default)]
+pub struct SyncState {
+    pub flow_ref: String,
+    /// Content hash per document URI
+    pub files: BTreeMap<String, String>,
+}
+
+pub fn content_hash(content: &str) -> String {
+    Sha256::digest(content.as_bytes())
+        .iter()
+        .map(|b| format!("{:02x}", b))
+        .collect()
+}
+
+fn sync_state_path(dir: &Path) -> PathBuf {
+    dir.join(SYNC_STATE_FILE)
+}
+
+impl SyncState {
+    /// State of the last sync of `flow_ref` into `dir`, empty when the directory was
+    /// never synced or was synced with another flow
+    pub fn load(dir: &Path, flow_ref: &str) -> Self {
+        std::fs::read_to_string(sync_state_path(dir))
+            .ok()
+            .and_then(|data| serde_json::from_str::<Self>(&data).ok())
+            .filter(|state| state.flow_ref == flow_ref)
+            .unwrap_or_else(|| Self {
+                flow_ref: flow_ref.to_string(),
+                files: BTreeMap::new(),
+            })
+    }
+
+    pub fn save(&self, dir: &Path) -> Result<(), String> {
+        let path = sync_state_path(dir);
+        if let Some(parent) = path.parent() {
+            std::fs::create_dir_all(parent).map_err(|e| e.to_string())?;
+        }
+        let data = serde_json::to_string_pretty(self).map_err(|e| e.to_string())?;
+        std::fs::write(&path, data).map_err(|e| format!("Failed to write sync state: {}", e))
+    }
+
+    /// Record `content` as the synced content of `uri`, `None` when it exists on neither side
+    pub fn record(&mut self, uri: &str, content: Option<&str>) {
+        match content {
+            Some(content) => {
+                self.files.insert(uri.to_string(), content_hash(content));
+            }
+            None => {
+                self.files.remove(uri);
+            }
+        }
+    }
+
+    /// Record the content of the files changed by `edits` once they were saved to the flow
+    pub fn record_edits(&mut self, edits: &[Edit]) {
+        for edit in edits {
+            match edit.operation.as_str() {
+                "insert" => self.record(&edit.document_uri, Some(&edit.content)),
+                _ => self.record(&edit.document_uri, None),
+            }
+        }
+    }
+}
+
+/// What to do with a file given its content at the last sync (`base`), locally and
+/// remotely, `None` where the file does not exist
+#[derive(Debug, PartialEq)]
+pub enum MergeAction {
+    Unchanged,
+    /// Only the remote side changed, write or delete the local file
+    Pull,
+    /// Only the local side changed, send it to the flow
+    Push,
+    Conflict,
+}
+
+pub fn merge_action(base: Option<&str>, local: Option<&str>, remote: Option<&str>) -> MergeAction {
+    let local = local.map(content_hash);
+    let remote = remote.map(content_hash);
+    if local == remote {
+        return MergeAction::Unchanged;
+    }
+    match base {
+        Some(base) if local.as_deref() == Some(base) => MergeAction::Pull,
+        Some(base) if remote.as_deref() == Some(base) => MergeAction::Push,
+        Some(_) => MergeAction::Conflict,
+        // Never synced: files missing locally are pulled, local-only files are left alone
+        None if local.is_none() => MergeAction::Pull,
+        None if remote.is_none() => MergeAction::Unchanged,
+        None => MergeAction::Conflict,
+    }
+}
+
+/// How a file changed on both sides is resolved
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Resolution {
+    /// Send the local version to the flow
+    KeepLocal,
+    /// Overwrite the local file with the flow version
+    TakeRemote,
+    /// Write both versions in the local file between conflict markers, to edit by hand
+    Markers,
+    /// Leave both sides as they are
+    Skip,
+}
+
+/// Both versions of a file, git style. The file is never pushed while the markers are in it.
+pub fn conflict_markers(local: &str, remote: &str, flow_ref: &str) -> String {
+    let mut merged = format!("{}\n{}", CONFLICT_START, local);
+    if !local.is_empty() && !local.ends_with('\n') {
+        merged.push('\n');
+    }
+    merged.push_str(CONFLICT_SEPARATOR);
+    merged.push('\n');
+    merged.push_str(remote);
+    if !remote.is_empty() && !remote.ends_with('\n') {
+        merged.push('\n');
+    }
+    merged.push_str(&format!("{} ({})\n", CONFLICT_END, flow_ref));
+    merged
+}
+
+pub fn has_conflict_markers(content: &str) -> bool {
+    content.lines().any(|line| line == CONFLICT_START)
+        && content.lines().any(|line| line.starts_with(CONFLICT_END))
+}
+
+/// Whether conflicts can be resolved interactively
+pub fn can_prompt() -> bool {
+    std::io::stdin().is_terminal()
+}
+
+const CONFLICT_HELP: &str = "l - keep the local version and push it
+r - take the flow version, overwriting the local file
+m - write both versions between conflict markers to merge by hand
+d - show the diff between the flow and local versions
+s - skip, leaving both versions as they are
+? - print help";
+
+/// Ask how to resolve a conflict on `uri`, or write conflict markers when `prompt` is
+/// off. Markers need both versions, a file deleted on one side is skipped instead.
+pub fn resolve_conflict(
+    uri: &str,
+    local: Option<&str>,
+    remote: Option<&str>,
+    prompt: bool,
+) -> Result<Resolution, String> {
+    let path = uri_to_relative_path(uri);
+    let state = match (local, remote) {
+        (Some(_), Some(_)) => "changed locally and in the flow",
+        (None, _) => "deleted locally and changed in the flow",
+        (_, None) => "changed locally and deleted from the flow",
+    };
+    if !prompt {
+        let resolution = match (local, remote) {
+            (Some(_), Some(_)) => Resolution::Markers,
+            _ => Resolution::Skip,
+        };
+        println!(
+            "⚠️  Conflict: {} was {}, {}",
+            path,
+            state,
+            match resolution {
+                Resolution::Markers => "wrote conflict markers",
+                _ => "left as is",
+            }
+        );
+        return Ok(resolution);
+    }
+
+    loop {
+        print!(
+            "⚠️  Conflict: {} was {}. Resolve [l,r,m,d,s,?]? ",
+            path, state
+        );
+        std::io::stdout().flush().map_err(|e| e.to_string())?;
+        let mut input = String::new();
+        let read = std::io::stdin()
+            .read_line(&mut input)
+            .map_err(|e| format!("Failed to read input: {}", e))?;
+        if read == 0 {
+            return Ok(Resolution::Skip);
+        }
+        match input.trim() {
+            "l" => return Ok(Resolution::KeepLocal),
+            "r" => return Ok(Resolution::TakeRemote),
+            "m" if local.is_some() && remote.is_some() => return Ok(Resolution::Markers),
+            "m" => {
+                println!("Conflict markers need both versions, the file was deleted on one side")
+            }
+            "d" => {
+                let side = |content: Option<&str>| {
+                    content
+                        .map(|content| (path.to_string(), content.to_string()))
+                        .into_iter()
+                        .collect::<BTreeMap<String, String>>()
+                };
+                for file_diff in diff_documents(&side(remote), &side(local)) {
+                    print!("{}", render_diff(&file_diff.unified));
+                }
+            }
+            "s" => return Ok(Resolution::Skip),
+            _ => println!("{}", CONFLICT_HELP),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_merge_action() {
+        let base = content_hash("replicas: 1");
+        let base = Some(base.as_str());
+        assert_eq!(
+            merge_action(base, Some("replicas: 1"), Some("replicas: 2")),
+            MergeAction::Pull
+        );
+        assert_eq!(
+            merge_action(base, Some("replicas: 3"), Some("replicas: 1")),
+            MergeAction::Push
+        );
+        assert_eq!(
+            merge_action(base, None, Some("replicas: 1")),
+            MergeAction::Push
+        );
+        assert_eq!(
+            merge_action(base, Some("replicas: 3"), Some("replicas: 2")),
+            MergeAction::Conflict
+        );
+        assert_eq!(
+            merge_action(None, Some("replicas: 3"), None),
+            MergeAction::Unchanged
+        );
+        assert_eq!(
+            merge_action(None, Some("replicas: 3"), Some("replicas: 2")),
+            MergeAction::Conflict
+        );
+
+        let merged = conflict_markers("replicas: 3", "replicas: 2\n", "acme/web/v2");
+        assert_eq!(
+            merged,
+            "<<<<<<< local\nreplicas: 3\n=======\nreplicas: 2\n>>>>>>> remote (acme/web/v2)\n"
+        );
+        assert!(has_conflict_markers(&merged));
+    }
+}
@@ -0,0 +1,137 @@
+use regex::Regex;
+use std::collections::{BTreeSet, HashMap};
+use std::io::{IsTerminal, Write};
+
+/// Matches `{{name}}` placeholders. Dotted expressions such as Helm's `{{ .Values.x }}`
+/// or GitHub Actions' `${{ secrets.X }}` are left alone.
+const VARIABLE_PATTERN: &str = r"(\$?)\{\{\s*([A-Za-z_][A-Za-z0-9_]*)\s*\}\}";
+
+fn variable_regex() -> Result<Regex, String> {
+    Regex::new(VARIABLE_PATTERN).map_err(|e| e.to_string())
+}
+
+/// Parse a `key=value` pair passed with `--var`
+pub fn parse_variable(s: &str) -> Result<(String, String), String> {
+    let (key, value) = s
+        .split_once('=')
+        .ok_or_else(|| format!("Invalid variable '{}', expected key=value", s))?;
+    let key = key.trim();
+    if key.is_empty() {
+        return Err(format!("Invalid variable '{}', key is empty", s));
+    }
+    Ok((key.to_string(), value.to_string()))
+}
+
+/// Collect the names of all placeholder variables in the given documents
+pub fn find_variables<'a>(
+    contents: impl IntoIterator<Item = &'a str>,
+) -> Result<BTreeSet<String>, String> {
+    let regex = variable_regex()?;
+    let mut variables = BTreeSet::new();
+    for content in contents {
+        for captures in regex.captures_iter(content) {
+            if captures[1].is_empty() {
+                variables.insert(captures[2].to_string());
+            }
+        }
+    }
+    Ok(variables)
+}
+
+/// Replace placeholders that have a value, leaving unknown ones untouched
+pub fn substitute_variables(
+    content: &str,
+    variables: &HashMap<String, String>,
+) -> Result<String, String> {
+    let regex = variable_regex()?;
+    Ok(regex
+        .replace_all(content, |captures: &regex::Captures| {
+            match variables.get(&captures[2]) {
+                Some(value) if captures[1].is_empty() => value.clone(),
+                _ => captures[0].to_string(),
+            }
+        })
+        .into_owned())
+}
+
+/// Ask for every variable not already provided, erroring out when stdin isn't interactive
+pub fn resolve_variables(
+    found: &BTreeSet<String>,
+    mut provided: HashMap<String, String>,
+) -> Result<HashMap<String, String>, String> {
+    let missing: Vec<&String> = found
+        .iter()
+        .filter(|name| !provided.contains_key(*name))
+        .collect();
+    if missing.is_empty() {
+        return Ok(provided);
+    }
+
+    if !std::io::stdin().is_terminal() {
+        return Err(format!(
+            "Missing values for flow variables: {}. Pass them with --var key=value",
+            missing
+                .iter()
+                .map(|s| s.as_str())
+                .collect::<Vec<_>>()
+                .join(", ")
+        ));
+    }
+
+    for name in missing {
+        print!("Value for {{{{{}}}}}: ", name);
+        std::io::stdout().flush().map_err(|e| e.to_string())?;
+        let mut input = String::new();
+        std::io::stdin()
+            .read_line(&mut input)
+            .map_err(|e| format!("Failed to read input: {}", e))?;
+        provided.insert(
+            name.clone(),
+            input.trim_end_matches(['\r', '\n']).to_string(),
+        );
+    }
+
+    Ok(provided)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_variable() {
+        assert_eq!(
+            parse_variable("region=eu-west-1"),
+            Ok(("region".to_string(), "eu-west-1".to_string()))
+        );
+        assert_eq!(
+            parse_variable("args=a=b"),
+            Ok(("args".to_string(), "a=b".to_string()))
+        );
+        assert!(parse_variable("region").is_err());
+        assert!(parse_variable(" =value").is_err());
+    }
+
+    #[test]
+    fn test_find_and_substitute_variables() {
+        let content = "region = \"{{ region }}\"\nname = \"{{name}}\"\nimage: {{ .Values.image }}\ntoken: ${{ secrets.TOKEN }}\n";
+        let found = find_variables([content]).unwrap();
+        assert_eq!(
+            found.into_iter().collect::<Vec<_>>(),
+            vec!["name".to_string(), "region".to_string()]
+        );
+
+        let variables = HashMap::from([("region".to_string(), "eu-west-1".to_string())]);
+        assert_eq!(
+            substitute_variables(content, &variables).unwrap(),
+            "region = \"eu-west-1\"\nname = \"{{name}}\"\nimage: {{ .Values.image }}\ntoken: ${{ secrets.TOKEN }}\n"
+        );
+    }
+
+    #[test]
+    fn test_resolve_provided_variables() {
+        let found = BTreeSet::from(["region".to_string()]);
+        let provided = HashMap::from([("region".to_string(), "us-east-1".to_string())]);
+        assert_eq!(resolve_variables(&found, provided.clone()), Ok(provided));
+    }
+}
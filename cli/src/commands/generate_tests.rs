@@ -0,0 +1,47 @@
+use stakpak_api::ClientConfig;
+use stakpak_mcp_server::RemoteTools;
+use stakpak_mcp_server::test_generation::TestFramework;
+
+/// Generate tests for the configurations in `paths`, writing the test files unless
+/// `print_only` is set
+pub async fn generate_tests(
+    api_config: ClientConfig,
+    paths: Vec<String>,
+    framework: TestFramework,
+    instructions: Option<String>,
+    print_only: bool,
+) -> Result<(), String> {
+    let remote_tools = RemoteTools::new(api_config, true);
+
+    let (progress_tx, mut progress_rx) = tokio::sync::mpsc::channel::<String>(32);
+    let progress_printer = tokio::spawn(async move {
+        while let Some(message) = progress_rx.recv().await {
+            eprintln!("{}", message.trim_end());
+        }
+    });
+
+    let result = remote_tools
+        .generate_tests_with_progress(
+            paths,
+            framework,
+            instructions,
+            Some(!print_only),
+            Some(progress_tx),
+        )
+        .await
+        .map_err(|e| format!("Failed to generate tests: {}", e))?;
+    let _ = progress_printer.await;
+
+    let text = result
+        .content
+        .iter()
+        .filter_map(|content| content.as_text().map(|t| t.text.clone()))
+        .collect::<Vec<_>>();
+
+    if result.is_error.unwrap_or(false) {
+        // Error results start with an error code, the message follows it
+        return Err(text.last().cloned().unwrap_or_default());
+    }
+    println!("{}", text.join("\n"));
+    Ok(())
+}
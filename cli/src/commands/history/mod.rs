@@ -0,0 +1,48 @@
+use clap::Subcommand;
+
+mod search;
+pub use search::*;
+
+mod transcript;
+pub use transcript::*;
+
+#[derive(Subcommand, PartialEq)]
+pub enum HistoryCommands {
+    /// Search past local session transcripts
+    Search {
+        /// Text to search for in past sessions
+        query: String,
+        /// Maximum number of matching sessions to show
+        #[arg(long, short, default_value_t = 10)]
+        limit: usize,
+        /// Rank sessions by relevance to the query instead of recency
+        #[arg(long, short, default_value_t = false)]
+        rank: bool,
+    },
+}
+
+impl HistoryCommands {
+    pub async fn run(self) -> Result<(), String> {
+        match self {
+            HistoryCommands::Search { query, limit, rank } => {
+                let transcripts = load_transcripts()?;
+                if transcripts.is_empty() {
+                    println!("No local session transcripts found");
+                    return Ok(());
+                }
+
+                let results = search_transcripts(&transcripts, &query, rank);
+                if results.is_empty() {
+                    println!("No sessions matched \"{}\"", query);
+                    return Ok(());
+                }
+
+                for result in results.iter().take(limit) {
+                    println!("{}", result);
+                }
+                println!("To resume a session, run:\nstakpak -c <checkpoint_id>");
+            }
+        }
+        Ok(())
+    }
+}
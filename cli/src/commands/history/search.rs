@@ -0,0 +1,222 @@
+use super::SessionTranscript;
+use chrono::{DateTime, Utc};
+use stakpak_shared::models::integrations::openai::ChatMessage;
+
+const MAX_EXCERPTS: usize = 3;
+const MAX_EXCERPT_LENGTH: usize = 160;
+
+pub struct SearchResult {
+    pub checkpoint_id: String,
+    pub updated_at: DateTime<Utc>,
+    pub score: f64,
+    pub excerpts: Vec<String>,
+}
+
+impl std::fmt::Display for SearchResult {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        writeln!(
+            f,
+            "{} ({})",
+            self.checkpoint_id,
+            self.updated_at.format("%Y-%m-%d %H:%M:%S UTC")
+        )?;
+        for excerpt in &self.excerpts {
+            writeln!(f, "\t{}", excerpt)?;
+        }
+        Ok(())
+    }
+}
+
+fn message_text(message: &ChatMessage) -> String {
+    let mut text = message
+        .content
+        .as_ref()
+        .map(|c| c.to_string())
+        .unwrap_or_default();
+    if let Some(tool_calls) = &message.tool_calls {
+        for tool_call in tool_calls {
+            text.push('\n');
+            text.push_str(&tool_call.function.arguments);
+        }
+    }
+    text
+}
+
+fn truncate_excerpt(line: &str) -> String {
+    let line = line.trim();
+    if line.chars().count() > MAX_EXCERPT_LENGTH {
+        format!(
+            "{}...",
+            line.chars().take(MAX_EXCERPT_LENGTH).collect::<String>()
+        )
+    } else {
+        line.to_string()
+    }
+}
+
+/// Find sessions containing every term of the query. Results are ordered by
+/// recency, or by a tf-idf relevance score when `rank` is set.
+pub fn search_transcripts(
+    transcripts: &[SessionTranscript],
+    query: &str,
+    rank: bool,
+) -> Vec<SearchResult> {
+    let terms: Vec<String> = query
+        .to_lowercase()
+        .split_whitespace()
+        .map(|t| t.to_string())
+        .collect();
+    if terms.is_empty() {
+        return Vec::new();
+    }
+
+    let documents: Vec<String> = transcripts
+        .iter()
+        .map(|t| {
+            t.messages
+                .iter()
+                .map(message_text)
+                .collect::<Vec<_>>()
+                .join("\n")
+                .to_lowercase()
+        })
+        .collect();
+
+    let document_frequency: Vec<usize> = terms
+        .iter()
+        .map(|term| documents.iter().filter(|d| d.contains(term)).count())
+        .collect();
+
+    let mut results = Vec::new();
+    for (transcript, document) in transcripts.iter().zip(documents.iter()) {
+        if !terms.iter().all(|term| document.contains(term)) {
+            continue;
+        }
+
+        let score = terms
+            .iter()
+            .zip(document_frequency.iter())
+            .map(|(term, df)| {
+                let tf = document.matches(term.as_str()).count() as f64;
+                let idf = ((transcripts.len() as f64 + 1.0) / (*df as f64 + 1.0)).ln() + 1.0;
+                (1.0 + tf).ln() * idf
+            })
+            .sum();
+
+        let mut excerpts = Vec::new();
+        'messages: for message in &transcript.messages {
+            for line in message_text(message).lines() {
+                let lower = line.to_lowercase();
+                if terms.iter().any(|term| lower.contains(term)) {
+                    excerpts.push(format!("[{}] {}", message.role, truncate_excerpt(line)));
+                    if excerpts.len() >= MAX_EXCERPTS {
+                        break 'messages;
+                    }
+                    break;
+                }
+            }
+        }
+
+        results.push(SearchResult {
+            checkpoint_id: transcript.checkpoint_id.clone(),
+            updated_at: transcript.updated_at,
+            score,
+            excerpts,
+        });
+    }
+
+    if rank {
+        results.sort_by(|a, b| {
+            b.score
+                .partial_cmp(&a.score)
+                .unwrap_or(std::cmp::Ordering::Equal)
+        });
+    } else {
+        results.sort_by(|a, b| b.updated_at.cmp(&a.updated_at));
+    }
+
+    results
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::TimeZone;
+    use stakpak_shared::models::integrations::openai::{MessageContent, Role};
+
+    fn transcript(checkpoint_id: &str, hour: u32, messages: &[(Role, &str)]) -> SessionTranscript {
+        SessionTranscript {
+            checkpoint_id: checkpoint_id.to_string(),
+            updated_at: Utc.with_ymd_and_hms(2025, 1, 1, hour, 0, 0).unwrap(),
+            messages: messages
+                .iter()
+                .map(|(role, content)| ChatMessage {
+                    role: role.clone(),
+                    content: Some(MessageContent::String(content.to_string())),
+                    name: None,
+                    tool_calls: None,
+                    tool_call_id: None,
+                })
+                .collect(),
+        }
+    }
+
+    fn transcripts() -> Vec<SessionTranscript> {
+        vec![
+            transcript(
+                "older",
+                1,
+                &[
+                    (Role::User, "The terraform plan fails"),
+                    (
+                        Role::Assistant,
+                        "terraform state is locked\nrun terraform force-unlock",
+                    ),
+                ],
+            ),
+            transcript(
+                "newer",
+                2,
+                &[(Role::User, "Terraform plan for the EKS cluster")],
+            ),
+            transcript("other", 3, &[(Role::User, "Fix the helm chart")]),
+        ]
+    }
+
+    #[test]
+    fn test_every_term_must_match() {
+        let results = search_transcripts(&transcripts(), "TERRAFORM plan", false);
+        let ids: Vec<&str> = results.iter().map(|r| r.checkpoint_id.as_str()).collect();
+        assert_eq!(ids, vec!["newer", "older"]);
+        assert!(search_transcripts(&transcripts(), "terraform helm", false).is_empty());
+        assert!(search_transcripts(&transcripts(), "  ", false).is_empty());
+    }
+
+    #[test]
+    fn test_rank_by_relevance() {
+        let results = search_transcripts(&transcripts(), "terraform", true);
+        let ids: Vec<&str> = results.iter().map(|r| r.checkpoint_id.as_str()).collect();
+        assert_eq!(ids, vec!["older", "newer"]);
+        assert!(results[0].score > results[1].score);
+    }
+
+    #[test]
+    fn test_excerpts() {
+        let results = search_transcripts(&transcripts(), "terraform", false);
+        // One excerpt per message, from its first matching line
+        assert_eq!(
+            results[1].excerpts,
+            vec![
+                "[user] The terraform plan fails".to_string(),
+                "[assistant] terraform state is locked".to_string(),
+            ]
+        );
+
+        let long = "x".repeat(MAX_EXCERPT_LENGTH + 10);
+        assert_eq!(
+            truncate_excerpt(&long),
+            format!("{}...", "x".repeat(MAX_EXCERPT_LENGTH))
+        );
+        assert_eq!(truncate_excerpt("  short  "), "short");
+    }
+}
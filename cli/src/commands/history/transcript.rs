@@ -0,0 +1,71 @@
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use stakpak_shared::local_store::LocalStore;
+use stakpak_shared::models::integrations::openai::{ChatMessage, Role};
+
+const HISTORY_DIR: &str = "history";
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct SessionTranscript {
+    pub checkpoint_id: String,
+    pub updated_at: DateTime<Utc>,
+    pub messages: Vec<ChatMessage>,
+}
+
+/// Persist the conversation under `.stakpak/session/history/<checkpoint_id>.json`
+/// so it can later be found with `stakpak history search`
+pub fn save_transcript(messages: &[ChatMessage]) -> Result<Option<String>, String> {
+    let checkpoint_id = messages
+        .iter()
+        .rev()
+        .find(|m| m.role == Role::Assistant)
+        .and_then(|m| m.content.as_ref().and_then(|c| c.extract_checkpoint_id()));
+
+    let checkpoint_id = match checkpoint_id {
+        Some(checkpoint_id) => checkpoint_id.to_string(),
+        None => return Ok(None),
+    };
+
+    let transcript = SessionTranscript {
+        checkpoint_id: checkpoint_id.clone(),
+        updated_at: Utc::now(),
+        messages: messages.to_vec(),
+    };
+    let data = serde_json::to_string_pretty(&transcript).map_err(|e| e.to_string())?;
+
+    LocalStore::write_session_data(&format!("{}/{}.json", HISTORY_DIR, checkpoint_id), &data)
+        .map(Some)
+}
+
+pub fn load_transcripts() -> Result<Vec<SessionTranscript>, String> {
+    let history_dir = LocalStore::get_local_session_store_path().join(HISTORY_DIR);
+    if !history_dir.exists() {
+        return Ok(Vec::new());
+    }
+
+    let entries = std::fs::read_dir(&history_dir).map_err(|e| {
+        format!(
+            "Failed to read history directory {}: {}",
+            history_dir.display(),
+            e
+        )
+    })?;
+
+    let mut transcripts = Vec::new();
+    for entry in entries.filter_map(|e| e.ok()) {
+        let path = entry.path();
+        if path.extension().and_then(|ext| ext.to_str()) != Some("json") {
+            continue;
+        }
+        // Skip unreadable or corrupted transcripts instead of failing the whole search
+        let transcript = std::fs::read_to_string(&path)
+            .ok()
+            .and_then(|content| serde_json::from_str::<SessionTranscript>(&content).ok());
+        if let Some(transcript) = transcript {
+            transcripts.push(transcript);
+        }
+    }
+
+    transcripts.sort_by(|a, b| b.updated_at.cmp(&a.updated_at));
+    Ok(transcripts)
+}
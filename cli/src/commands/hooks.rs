@@ -0,0 +1,134 @@
+use clap::Subcommand;
+use std::path::PathBuf;
+use std::process::Command;
+
+const HOOK_MARKER: &str = "# Installed by stakpak hooks install";
+
+#[derive(Subcommand, PartialEq)]
+pub enum HooksCommands {
+    /// Install a git pre-commit hook that scans staged files for secrets
+    Install {
+        /// Also check formatting and validate staged Terraform files
+        #[arg(long, default_value_t = false)]
+        with_iac: bool,
+        /// Overwrite an existing pre-commit hook not installed by stakpak
+        #[arg(long, short, default_value_t = false)]
+        force: bool,
+    },
+    /// Remove the stakpak pre-commit hook
+    Uninstall,
+}
+
+fn hooks_dir() -> Result<PathBuf, String> {
+    // Respects core.hooksPath and linked worktrees
+    let output = Command::new("git")
+        .args(["rev-parse", "--git-path", "hooks"])
+        .output()
+        .map_err(|e| format!("Failed to run git: {}", e))?;
+    if !output.status.success() {
+        return Err("Not inside a git repository".into());
+    }
+    Ok(PathBuf::from(
+        String::from_utf8_lossy(&output.stdout).trim().to_string(),
+    ))
+}
+
+fn hook_script(with_iac: bool) -> String {
+    let mut script = format!(
+        r#"#!/bin/sh
+{}
+# Scans only the staged content of changed files. Skip with `git commit --no-verify`.
+
+stakpak secrets scan --staged || exit 1
+"#,
+        HOOK_MARKER
+    );
+
+    if with_iac {
+        script.push_str(
+            r#"
+STAGED_TF=$(git diff --cached --name-only --diff-filter=ACM -- '*.tf')
+if [ -n "$STAGED_TF" ] && command -v terraform >/dev/null 2>&1; then
+    terraform fmt -check $STAGED_TF || {
+        echo "Terraform files are not formatted, run: terraform fmt"
+        exit 1
+    }
+    for dir in $(for f in $STAGED_TF; do dirname "$f"; done | sort -u); do
+        if [ -d "$dir/.terraform" ]; then
+            terraform -chdir="$dir" validate -no-color || exit 1
+        fi
+    done
+fi
+"#,
+        );
+    }
+
+    script
+}
+
+impl HooksCommands {
+    pub async fn run(self) -> Result<(), String> {
+        let hook_path = hooks_dir()?.join("pre-commit");
+
+        match self {
+            HooksCommands::Install { with_iac, force } => {
+                if let Ok(existing) = std::fs::read_to_string(&hook_path) {
+                    if !existing.contains(HOOK_MARKER) && !force {
+                        return Err(format!(
+                            "A pre-commit hook already exists at {}, use --force to overwrite it",
+                            hook_path.display()
+                        ));
+                    }
+                }
+
+                if let Some(parent) = hook_path.parent() {
+                    std::fs::create_dir_all(parent).map_err(|e| e.to_string())?;
+                }
+                std::fs::write(&hook_path, hook_script(with_iac))
+                    .map_err(|e| format!("Failed to write {}: {}", hook_path.display(), e))?;
+
+                #[cfg(unix)]
+                {
+                    use std::os::unix::fs::PermissionsExt;
+                    std::fs::set_permissions(&hook_path, std::fs::Permissions::from_mode(0o755))
+                        .map_err(|e| e.to_string())?;
+                }
+
+                println!("Pre-commit hook installed at {}", hook_path.display());
+            }
+            HooksCommands::Uninstall => match std::fs::read_to_string(&hook_path) {
+                Ok(existing) if existing.contains(HOOK_MARKER) => {
+                    std::fs::remove_file(&hook_path).map_err(|e| e.to_string())?;
+                    println!("Pre-commit hook removed from {}", hook_path.display());
+                }
+                Ok(_) => {
+                    return Err(format!(
+                        "The pre-commit hook at {} was not installed by stakpak",
+                        hook_path.display()
+                    ));
+                }
+                Err(_) => println!("No pre-commit hook installed"),
+            },
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_hook_script() {
+        let script = hook_script(false);
+        assert!(script.starts_with("#!/bin/sh\n"));
+        assert!(script.contains(HOOK_MARKER));
+        assert!(script.contains("stakpak secrets scan --staged || exit 1"));
+        assert!(!script.contains("terraform"));
+
+        let script = hook_script(true);
+        assert!(script.contains("terraform fmt -check"));
+        assert!(script.contains("terraform -chdir=\"$dir\" validate"));
+    }
+}
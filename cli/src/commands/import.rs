@@ -0,0 +1,146 @@
+/// Cloud an import session reads existing resources from
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ImportProvider {
+    Aws,
+    Gcp,
+    Azure,
+}
+
+impl std::fmt::Display for ImportProvider {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let s = match self {
+            ImportProvider::Aws => "aws",
+            ImportProvider::Gcp => "gcp",
+            ImportProvider::Azure => "azure",
+        };
+        write!(f, "{}", s)
+    }
+}
+
+impl std::str::FromStr for ImportProvider {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "aws" => Ok(ImportProvider::Aws),
+            "gcp" | "google" => Ok(ImportProvider::Gcp),
+            "azure" => Ok(ImportProvider::Azure),
+            _ => Err(format!(
+                "Invalid provider: {}, expected one of: aws, gcp, azure",
+                s
+            )),
+        }
+    }
+}
+
+impl ImportProvider {
+    /// Read-only command listing resources, suggested to the agent as a starting point
+    fn listing_hint(&self) -> &'static str {
+        match self {
+            ImportProvider::Aws => {
+                "aws resourcegroupstaggingapi get-resources (with --tag-filters for tag filters) and the read-only describe/list commands of each service"
+            }
+            ImportProvider::Gcp => {
+                "gcloud asset search-all-resources (with --query for label filters) and the read-only describe/list commands of each service"
+            }
+            ImportProvider::Azure => {
+                "az resource list (with --tag for tag filters) and the read-only show/list commands of each service"
+            }
+        }
+    }
+}
+
+/// Which resources to import
+#[derive(Debug, Clone, PartialEq)]
+pub enum ImportFilter {
+    Tag { key: String, value: String },
+    ResourceType(String),
+    Region(String),
+}
+
+impl std::fmt::Display for ImportFilter {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ImportFilter::Tag { key, value } => write!(f, "tag {} = {}", key, value),
+            ImportFilter::ResourceType(resource_type) => {
+                write!(f, "resource type {}", resource_type)
+            }
+            ImportFilter::Region(region) => write!(f, "region {}", region),
+        }
+    }
+}
+
+/// Parse `tag:KEY=VALUE`, `type:RESOURCE_TYPE` or `region:REGION`
+pub fn parse_import_filter(s: &str) -> Result<ImportFilter, String> {
+    let (kind, value) = s
+        .split_once(':')
+        .ok_or_else(|| format!("Invalid filter '{}', expected <tag|type|region>:<value>", s))?;
+    let value = value.trim();
+    if value.is_empty() {
+        return Err(format!("Invalid filter '{}', the value is empty", s));
+    }
+    match kind.trim() {
+        "tag" => {
+            let (key, value) = value
+                .split_once('=')
+                .ok_or_else(|| format!("Invalid tag filter '{}', expected tag:KEY=VALUE", s))?;
+            Ok(ImportFilter::Tag {
+                key: key.trim().to_string(),
+                value: value.trim().to_string(),
+            })
+        }
+        "type" => Ok(ImportFilter::ResourceType(value.to_string())),
+        "region" => Ok(ImportFilter::Region(value.to_string())),
+        _ => Err(format!(
+            "Invalid filter kind '{}', expected one of: tag, type, region",
+            kind
+        )),
+    }
+}
+
+/// Kickoff prompt of the agent session that brings the matching resources under Terraform
+pub fn import_prompt(provider: ImportProvider, filters: &[ImportFilter]) -> String {
+    let filters = if filters.is_empty() {
+        "all resources the credentials can see".to_string()
+    } else {
+        filters
+            .iter()
+            .map(|filter| format!("- {}", filter))
+            .collect::<Vec<_>>()
+            .join("\n")
+    };
+    format!(
+        "Import existing {provider} resources into Terraform in the current directory. Resources to import (all filters must match):\n{filters}\n\n\
+Work through these steps, and stop to ask me before moving on if anything is ambiguous:\n\
+1. Check the {provider} credentials with check_cloud_access, then list the matching resources using only read-only calls, e.g. {hint}. Show me the list before writing anything.\n\
+2. Write Terraform 1.5+ import blocks for them in imports.tf, and a resource skeleton for each in HCL grouped by service (you may use `terraform plan -generate-config-out=generated.tf` and clean the output up). Reuse the provider and backend configuration already in the directory.\n\
+3. Run `terraform init` and `terraform plan` and iterate on the configuration until the plan only imports the resources, with no changes, replacements or destroys.\n\n\
+Never run `terraform apply`, `terraform import` or any command that changes cloud resources or remote state; leave applying the imports to me.",
+        provider = provider,
+        filters = filters,
+        hint = provider.listing_hint(),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_import_filter() {
+        assert_eq!(
+            parse_import_filter("tag:Team=платформа"),
+            Ok(ImportFilter::Tag {
+                key: "Team".to_string(),
+                value: "платформа".to_string(),
+            })
+        );
+        assert_eq!(
+            parse_import_filter("type:aws_s3_bucket"),
+            Ok(ImportFilter::ResourceType("aws_s3_bucket".to_string()))
+        );
+        assert!(parse_import_filter("tag:Team").is_err());
+        assert!(parse_import_filter("owner:me").is_err());
+        assert!(parse_import_filter("region:").is_err());
+    }
+}
@@ -0,0 +1,509 @@
+use futures_util::future::join_all;
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+use std::path::Path;
+use tokio::process::Command;
+use walkdir::WalkDir;
+
+const SKIPPED_DIRS: [&str; 6] = [
+    ".git",
+    ".stakpak",
+    ".terraform",
+    "node_modules",
+    "target",
+    "vendor",
+];
+/// Findings listed in the prompt of a fixing session, the agent re-runs the lint for the rest
+const MAX_PROMPT_FINDINGS: usize = 100;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum LintFormat {
+    Text,
+    Json,
+}
+
+impl std::fmt::Display for LintFormat {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let s = match self {
+            LintFormat::Text => "text",
+            LintFormat::Json => "json",
+        };
+        write!(f, "{}", s)
+    }
+}
+
+impl std::str::FromStr for LintFormat {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "text" => Ok(LintFormat::Text),
+            "json" => Ok(LintFormat::Json),
+            _ => Err(format!("Invalid lint format: {}", s)),
+        }
+    }
+}
+
+/// Severity every linter's levels are mapped to
+#[derive(Serialize, Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+#[serde(rename_all = "lowercase")]
+pub enum Severity {
+    Error,
+    Warning,
+    Info,
+}
+
+impl Severity {
+    fn from_level(level: &str) -> Self {
+        match level.to_lowercase().as_str() {
+            "error" | "fatal" => Severity::Error,
+            "warning" | "warn" => Severity::Warning,
+            _ => Severity::Info,
+        }
+    }
+}
+
+#[derive(Serialize, Debug, Clone, PartialEq)]
+pub struct LintFinding {
+    pub linter: &'static str,
+    pub file: String,
+    pub line: usize,
+    pub column: usize,
+    pub severity: Severity,
+    pub rule: String,
+    pub message: String,
+}
+
+impl std::fmt::Display for LintFinding {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let severity = match self.severity {
+            Severity::Error => "error",
+            Severity::Warning => "warning",
+            Severity::Info => "info",
+        };
+        write!(
+            f,
+            "{}:{}:{}: {} [{}/{}] {}",
+            self.file, self.line, self.column, severity, self.linter, self.rule, self.message
+        )
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Linter {
+    Tflint,
+    Hadolint,
+    Yamllint,
+    Actionlint,
+    Kubeconform,
+}
+
+impl Linter {
+    pub fn name(&self) -> &'static str {
+        match self {
+            Linter::Tflint => "tflint",
+            Linter::Hadolint => "hadolint",
+            Linter::Yamllint => "yamllint",
+            Linter::Actionlint => "actionlint",
+            Linter::Kubeconform => "kubeconform",
+        }
+    }
+
+    fn command(&self, dir: &str, files: &[String]) -> Command {
+        let mut command = Command::new(self.name());
+        command.current_dir(dir);
+        match self {
+            Linter::Tflint => command.args(["--recursive", "--format", "json"]),
+            Linter::Hadolint => command.args(["--format", "json"]).args(files),
+            Linter::Yamllint => command.args(["--format", "parsable"]).args(files),
+            Linter::Actionlint => command.args(["-format", "{{json .}}"]),
+            Linter::Kubeconform => command
+                .args([
+                    "-output",
+                    "json",
+                    "-summary=false",
+                    "-ignore-missing-schemas",
+                ])
+                .args(files),
+        };
+        command
+    }
+
+    fn parse(&self, stdout: &str) -> Result<Vec<LintFinding>, String> {
+        match self {
+            Linter::Tflint => parse_tflint(stdout),
+            Linter::Hadolint => parse_hadolint(stdout),
+            Linter::Yamllint => Ok(parse_yamllint(stdout)),
+            Linter::Actionlint => parse_actionlint(stdout),
+            Linter::Kubeconform => parse_kubeconform(stdout),
+        }
+    }
+}
+
+#[derive(Deserialize)]
+struct TflintOutput {
+    #[serde(default)]
+    issues: Vec<TflintIssue>,
+}
+
+#[derive(Deserialize)]
+struct TflintIssue {
+    rule: TflintRule,
+    message: String,
+    range: TflintRange,
+}
+
+#[derive(Deserialize)]
+struct TflintRule {
+    name: String,
+    severity: String,
+}
+
+#[derive(Deserialize)]
+struct TflintRange {
+    filename: String,
+    start: TflintPos,
+}
+
+#[derive(Deserialize)]
+struct TflintPos {
+    line: usize,
+    column: usize,
+}
+
+fn parse_tflint(stdout: &str) -> Result<Vec<LintFinding>, String> {
+    let output: TflintOutput = serde_json::from_str(stdout)
+        .map_err(|e| format!("Failed to parse tflint output: {}", e))?;
+    Ok(output
+        .issues
+        .into_iter()
+        .map(|issue| LintFinding {
+            linter: "tflint",
+            file: issue.range.filename,
+            line: issue.range.start.line,
+            column: issue.range.start.column,
+            severity: Severity::from_level(&issue.rule.severity),
+            rule: issue.rule.name,
+            message: issue.message,
+        })
+        .collect())
+}
+
+#[derive(Deserialize)]
+struct HadolintIssue {
+    file: String,
+    line: usize,
+    column: usize,
+    code: String,
+    level: String,
+    message: String,
+}
+
+fn parse_hadolint(stdout: &str) -> Result<Vec<LintFinding>, String> {
+    let issues: Vec<HadolintIssue> = serde_json::from_str(stdout)
+        .map_err(|e| format!("Failed to parse hadolint output: {}", e))?;
+    Ok(issues
+        .into_iter()
+        .map(|issue| LintFinding {
+            linter: "hadolint",
+            file: issue.file,
+            line: issue.line,
+            column: issue.column,
+            severity: Severity::from_level(&issue.level),
+            rule: issue.code,
+            message: issue.message,
+        })
+        .collect())
+}
+
+/// Parse `file:line:column: [level] message (rule)` lines
+fn parse_yamllint(stdout: &str) -> Vec<LintFinding> {
+    stdout
+        .lines()
+        .filter_map(|line| {
+            let mut parts = line.splitn(4, ':');
+            let file = parts.next()?.to_string();
+            let line_number = parts.next()?.parse().ok()?;
+            let column = parts.next()?.parse().ok()?;
+            let rest = parts.next()?.trim();
+            let (level, message) = rest.strip_prefix('[')?.split_once(']')?;
+            let message = message.trim();
+            let (message, rule) = match message.rsplit_once(" (") {
+                Some((message, rule)) => (message, rule.trim_end_matches(')')),
+                None => (message, "yamllint"),
+            };
+            Some(LintFinding {
+                linter: "yamllint",
+                file,
+                line: line_number,
+                column,
+                severity: Severity::from_level(level),
+                rule: rule.to_string(),
+                message: message.to_string(),
+            })
+        })
+        .collect()
+}
+
+#[derive(Deserialize)]
+struct ActionlintIssue {
+    message: String,
+    filepath: String,
+    line: usize,
+    column: usize,
+    kind: String,
+}
+
+fn parse_actionlint(stdout: &str) -> Result<Vec<LintFinding>, String> {
+    if stdout.trim().is_empty() {
+        return Ok(Vec::new());
+    }
+    let issues: Vec<ActionlintIssue> = serde_json::from_str(stdout)
+        .map_err(|e| format!("Failed to parse actionlint output: {}", e))?;
+    Ok(issues
+        .into_iter()
+        .map(|issue| LintFinding {
+            linter: "actionlint",
+            file: issue.filepath,
+            line: issue.line,
+            column: issue.column,
+            severity: Severity::Error,
+            rule: issue.kind,
+            message: issue.message,
+        })
+        .collect())
+}
+
+#[derive(Deserialize)]
+struct KubeconformOutput {
+    #[serde(default)]
+    resources: Vec<KubeconformResource>,
+}
+
+#[derive(Deserialize)]
+struct KubeconformResource {
+    filename: String,
+    #[serde(default)]
+    kind: String,
+    #[serde(default)]
+    name: String,
+    status: String,
+    #[serde(default)]
+    msg: String,
+}
+
+fn parse_kubeconform(stdout: &str) -> Result<Vec<LintFinding>, String> {
+    let output: KubeconformOutput = serde_json::from_str(stdout)
+        .map_err(|e| format!("Failed to parse kubeconform output: {}", e))?;
+    Ok(output
+        .resources
+        .into_iter()
+        .filter(|resource| matches!(resource.status.as_str(), "statusInvalid" | "statusError"))
+        .map(|resource| LintFinding {
+            linter: "kubeconform",
+            file: resource.filename,
+            line: 1,
+            column: 1,
+            severity: Severity::Error,
+            rule: "schema".to_string(),
+            message: format!("{} {}: {}", resource.kind, resource.name, resource.msg),
+        })
+        .collect())
+}
+
+/// Files of the workspace each applicable linter runs on, linters without files are left out
+pub fn discover_linters(dir: &str) -> Result<Vec<(Linter, Vec<String>)>, String> {
+    if !Path::new(dir).is_dir() {
+        return Err(format!("{} is not a directory", dir));
+    }
+
+    let mut terraform = Vec::new();
+    let mut dockerfiles = Vec::new();
+    let mut yaml = Vec::new();
+    let mut workflows = Vec::new();
+    let mut manifests = Vec::new();
+    for entry in WalkDir::new(dir)
+        .follow_links(false)
+        .into_iter()
+        .filter_entry(|e| {
+            !(e.file_type().is_dir()
+                && e.file_name()
+                    .to_str()
+                    .is_some_and(|name| SKIPPED_DIRS.contains(&name)))
+        })
+        .filter_map(|e| e.ok())
+    {
+        if !entry.file_type().is_file() {
+            continue;
+        }
+        let path = entry.path();
+        let file = path
+            .strip_prefix(dir)
+            .unwrap_or(path)
+            .to_string_lossy()
+            .replace('\\', "/");
+        let name = entry.file_name().to_string_lossy();
+
+        if name.ends_with(".tf") {
+            terraform.push(file);
+        } else if name == "Dockerfile"
+            || name.starts_with("Dockerfile.")
+            || name.ends_with(".Dockerfile")
+        {
+            dockerfiles.push(file);
+        } else if name.ends_with(".yml") || name.ends_with(".yaml") {
+            if file.starts_with(".github/workflows/") {
+                workflows.push(file.clone());
+            } else if std::fs::read_to_string(path).is_ok_and(|content| {
+                content.lines().any(|line| line.starts_with("apiVersion:"))
+                    && content.lines().any(|line| line.starts_with("kind:"))
+            }) {
+                manifests.push(file.clone());
+            }
+            yaml.push(file);
+        }
+    }
+
+    Ok([
+        (Linter::Tflint, terraform),
+        (Linter::Hadolint, dockerfiles),
+        (Linter::Yamllint, yaml),
+        (Linter::Actionlint, workflows),
+        (Linter::Kubeconform, manifests),
+    ]
+    .into_iter()
+    .filter(|(_, files)| !files.is_empty())
+    .collect())
+}
+
+/// Result of one linter, `Err` when it is not installed or its output could not be read
+struct LinterRun {
+    linter: Linter,
+    result: Result<Vec<LintFinding>, String>,
+}
+
+async fn run_linter(dir: &str, linter: Linter, files: &[String]) -> LinterRun {
+    let result = match linter.command(dir, files).output().await {
+        Ok(output) => {
+            let stdout = String::from_utf8_lossy(&output.stdout);
+            // Linters exit with an error when they find issues, only fail without any output
+            if stdout.trim().is_empty() && !output.status.success() {
+                Err(String::from_utf8_lossy(&output.stderr).trim().to_string())
+            } else {
+                linter.parse(&stdout)
+            }
+        }
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => Err("not installed".to_string()),
+        Err(e) => Err(e.to_string()),
+    };
+    LinterRun { linter, result }
+}
+
+/// Kickoff prompt of the agent session fixing the findings
+pub fn lint_fix_prompt(findings: &[LintFinding]) -> String {
+    let mut listed = findings
+        .iter()
+        .take(MAX_PROMPT_FINDINGS)
+        .map(|finding| format!("- {}", finding))
+        .collect::<Vec<_>>()
+        .join("\n");
+    if findings.len() > MAX_PROMPT_FINDINGS {
+        listed.push_str(&format!(
+            "\n- ... and {} more",
+            findings.len() - MAX_PROMPT_FINDINGS
+        ));
+    }
+    format!(
+        "Fix these linter findings in the current workspace, errors first:\n{}\n\n\
+Keep the changes minimal and do not change behavior beyond what a finding requires. If a finding is a false positive, tell me instead of silencing the rule. When done, run `stakpak lint` again and show me the findings left.",
+        listed
+    )
+}
+
+/// Run the linters that apply to `dir` concurrently and print one merged report, returning
+/// the findings
+pub async fn lint_workspace(dir: &str, format: LintFormat) -> Result<Vec<LintFinding>, String> {
+    let linters = discover_linters(dir)?;
+    if linters.is_empty() {
+        println!("No Terraform, Dockerfile, YAML, GitHub Actions or Kubernetes files to lint");
+        return Ok(Vec::new());
+    }
+
+    let runs = join_all(
+        linters
+            .iter()
+            .map(|(linter, files)| run_linter(dir, *linter, files)),
+    )
+    .await;
+
+    let mut findings = Vec::new();
+    let mut skipped = Vec::new();
+    for run in runs {
+        match run.result {
+            Ok(linter_findings) => findings.extend(linter_findings),
+            Err(reason) => skipped.push((run.linter.name(), reason)),
+        }
+    }
+    findings.sort_by(|a, b| {
+        (a.severity, &a.file, a.line, a.column).cmp(&(b.severity, &b.file, b.line, b.column))
+    });
+
+    match format {
+        LintFormat::Json => {
+            let report = json!({
+                "findings": findings,
+                "skipped": skipped
+                    .iter()
+                    .map(|(linter, reason)| json!({ "linter": linter, "reason": reason }))
+                    .collect::<Vec<_>>(),
+            });
+            println!(
+                "{}",
+                serde_json::to_string_pretty(&report).map_err(|e| e.to_string())?
+            );
+        }
+        LintFormat::Text => {
+            for finding in &findings {
+                println!("{}", finding);
+            }
+            let count = |severity| findings.iter().filter(|f| f.severity == severity).count();
+            println!(
+                "\n{} errors, {} warnings, {} info",
+                count(Severity::Error),
+                count(Severity::Warning),
+                count(Severity::Info)
+            );
+            for (linter, reason) in &skipped {
+                println!("Skipped {}: {}", linter, reason);
+            }
+        }
+    }
+    Ok(findings)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_linter_output() {
+        let yamllint = parse_yamllint(
+            "k8s/app.yaml:3:1: [warning] missing document start \"---\" (document-start)\n\
+             k8s/app.yaml:9:81: [error] line too long (92 > 80 characters) (line-length)\n",
+        );
+        assert_eq!(yamllint.len(), 2);
+        assert_eq!(yamllint[1].severity, Severity::Error);
+        assert_eq!(yamllint[1].rule, "line-length");
+        assert_eq!(yamllint[1].message, "line too long (92 > 80 characters)");
+
+        let hadolint = parse_hadolint(
+            r#"[{"code":"DL3008","column":1,"file":"Dockerfile","level":"style","line":4,"message":"Pin versions in apt get install"}]"#,
+        )
+        .unwrap_or_default();
+        assert_eq!(hadolint[0].severity, Severity::Info);
+        assert_eq!(
+            hadolint[0].to_string(),
+            "Dockerfile:4:1: info [hadolint/DL3008] Pin versions in apt get install"
+        );
+    }
+}
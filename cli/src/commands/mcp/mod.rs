@@ -0,0 +1,59 @@
+use crate::config::AppConfig;
+use clap::Subcommand;
+use stakpak_mcp_server::ToolMode;
+
+mod schema;
+pub use schema::*;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum SchemaFormat {
+    JsonSchema,
+    OpenApi,
+}
+
+impl std::fmt::Display for SchemaFormat {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let s = match self {
+            SchemaFormat::JsonSchema => "json-schema",
+            SchemaFormat::OpenApi => "openapi",
+        };
+        write!(f, "{}", s)
+    }
+}
+
+impl std::str::FromStr for SchemaFormat {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "json-schema" | "jsonschema" => Ok(SchemaFormat::JsonSchema),
+            "openapi" => Ok(SchemaFormat::OpenApi),
+            _ => Err(format!("Invalid schema format: {}", s)),
+        }
+    }
+}
+
+#[derive(Subcommand, PartialEq)]
+pub enum McpCommands {
+    /// Print the schemas and descriptions of all exposed tools
+    Schema {
+        /// Output format (json-schema, openapi)
+        #[arg(long, short, default_value_t = SchemaFormat::JsonSchema)]
+        format: SchemaFormat,
+        /// Tool mode to export (local, remote, combined)
+        #[arg(long, default_value_t = ToolMode::Combined)]
+        tool_mode: ToolMode,
+    },
+}
+
+impl McpCommands {
+    pub async fn run(self, config: AppConfig) -> Result<(), String> {
+        match self {
+            McpCommands::Schema { format, tool_mode } => {
+                let tools = list_server_tools(config, tool_mode).await?;
+                println!("{}", format_tool_schemas(&tools, format)?);
+            }
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,167 @@
+use super::SchemaFormat;
+use crate::config::AppConfig;
+use crate::utils::network;
+use rmcp::model::Tool;
+use serde_json::{Map, Value, json};
+use stakpak_mcp_client::ClientManager;
+use stakpak_mcp_server::{MCPServerConfig, ToolMode};
+
+/// Start a throwaway MCP server and list the tools it exposes, sorted by name
+pub async fn list_server_tools(
+    config: AppConfig,
+    tool_mode: ToolMode,
+) -> Result<Vec<Tool>, String> {
+    let (shutdown_tx, shutdown_rx) = tokio::sync::broadcast::channel::<()>(1);
+    let bind_address = network::find_available_bind_address_descending().await?;
+    let server_host = format!("http://{}", bind_address);
+
+    let server_handle = tokio::spawn(async move {
+        let _ = stakpak_mcp_server::start_server(
+            MCPServerConfig {
+                api: config.into(),
+                redact_secrets: true,
+                bind_address,
+                tool_mode,
+                sandbox: None,
+                dev_container: None,
+                command_policy: None,
+                access: None,
+                tls: None,
+            },
+            Some(shutdown_rx),
+        )
+        .await;
+    });
+
+    let tools = async {
+        let mut clients = ClientManager::new(server_host, None)
+            .await
+            .map_err(|e| e.to_string())?;
+        let tools_map = clients.get_tools().await.map_err(|e| e.to_string())?;
+        let _ = clients.close_clients().await;
+        Ok::<_, String>(tools_map)
+    }
+    .await;
+
+    let _ = shutdown_tx.send(());
+    let _ = server_handle.await;
+
+    let mut tools: Vec<Tool> = tools?.into_values().flatten().collect();
+    tools.sort_by(|a, b| a.name.cmp(&b.name));
+    Ok(tools)
+}
+
+fn tool_json(tool: &Tool) -> Result<Map<String, Value>, String> {
+    match serde_json::to_value(tool).map_err(|e| e.to_string())? {
+        Value::Object(object) => Ok(object),
+        _ => Err(format!("Unexpected schema for tool {}", tool.name)),
+    }
+}
+
+fn to_json_schema(tools: &[Tool]) -> Result<Value, String> {
+    let tools = tools
+        .iter()
+        .map(|tool| tool_json(tool).map(Value::Object))
+        .collect::<Result<Vec<_>, _>>()?;
+    Ok(json!({
+        "version": env!("CARGO_PKG_VERSION"),
+        "tools": tools,
+    }))
+}
+
+/// Describe each tool as a `POST /tools/<name>` operation taking its input schema
+fn to_openapi(tools: &[Tool]) -> Result<Value, String> {
+    let mut paths = Map::new();
+    for tool in tools {
+        let object = tool_json(tool)?;
+        let description = tool.description.as_deref().unwrap_or_default();
+        paths.insert(
+            format!("/tools/{}", tool.name),
+            json!({
+                "post": {
+                    "operationId": tool.name,
+                    "summary": description.lines().next().unwrap_or_default(),
+                    "description": description,
+                    "requestBody": {
+                        "required": true,
+                        "content": {
+                            "application/json": {
+                                "schema": object.get("inputSchema").cloned().unwrap_or(json!({})),
+                            }
+                        }
+                    },
+                    "responses": {
+                        "200": {
+                            "description": "Tool call result",
+                        }
+                    }
+                }
+            }),
+        );
+    }
+
+    Ok(json!({
+        "openapi": "3.1.0",
+        "info": {
+            "title": "Stakpak MCP tools",
+            "version": env!("CARGO_PKG_VERSION"),
+        },
+        "paths": paths,
+    }))
+}
+
+pub fn format_tool_schemas(tools: &[Tool], format: SchemaFormat) -> Result<String, String> {
+    let document = match format {
+        SchemaFormat::JsonSchema => to_json_schema(tools)?,
+        SchemaFormat::OpenApi => to_openapi(tools)?,
+    };
+    serde_json::to_string_pretty(&document).map_err(|e| e.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn tools() -> Vec<Tool> {
+        vec![
+            serde_json::from_value(json!({
+                "name": "view",
+                "description": "View a file\nSupports line ranges",
+                "inputSchema": {
+                    "type": "object",
+                    "properties": {"path": {"type": "string"}},
+                    "required": ["path"],
+                },
+            }))
+            .unwrap(),
+        ]
+    }
+
+    #[test]
+    fn test_json_schema() {
+        let document: Value =
+            serde_json::from_str(&format_tool_schemas(&tools(), SchemaFormat::JsonSchema).unwrap())
+                .unwrap();
+        assert_eq!(document["tools"][0]["name"], "view");
+        assert_eq!(
+            document["tools"][0]["inputSchema"]["required"],
+            json!(["path"])
+        );
+    }
+
+    #[test]
+    fn test_openapi() {
+        let document: Value =
+            serde_json::from_str(&format_tool_schemas(&tools(), SchemaFormat::OpenApi).unwrap())
+                .unwrap();
+        let operation = &document["paths"]["/tools/view"]["post"];
+        assert_eq!(document["openapi"], "3.1.0");
+        assert_eq!(operation["operationId"], "view");
+        assert_eq!(operation["summary"], "View a file");
+        assert_eq!(
+            operation["requestBody"]["content"]["application/json"]["schema"]["properties"]["path"]
+                ["type"],
+            "string"
+        );
+    }
+}
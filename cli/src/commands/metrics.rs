@@ -0,0 +1,74 @@
+use crate::utils::run_metrics::{
+    load_session_metrics, metrics_path, parse_period, summarize_metrics,
+};
+use chrono::Utc;
+use clap::Subcommand;
+use std::cmp::Reverse;
+
+#[derive(Subcommand, PartialEq)]
+pub enum MetricsCommands {
+    /// Summarize the metrics of the agent sessions run in this workspace
+    Summarize {
+        /// Only include sessions started in this period, e.g. 12h, 30d or 2w
+        #[arg(long, default_value = "30d")]
+        last: String,
+    },
+}
+
+impl MetricsCommands {
+    pub async fn run(self) -> Result<(), String> {
+        match self {
+            MetricsCommands::Summarize { last } => {
+                let since = Utc::now() - parse_period(&last)?;
+                let sessions = load_session_metrics(since)?;
+                if sessions.is_empty() {
+                    println!(
+                        "No sessions recorded in {} over the last {}",
+                        metrics_path().display(),
+                        last
+                    );
+                    return Ok(());
+                }
+
+                let summary = summarize_metrics(&sessions);
+                let per_session = |total: f64| total / summary.sessions as f64;
+                println!("Sessions over the last {}: {}", last, summary.sessions);
+                println!(
+                    "Turns per session:      {:.1}",
+                    per_session(summary.turns as f64)
+                );
+                println!(
+                    "Tool calls per session: {:.1}",
+                    per_session(summary.total_tool_calls() as f64)
+                );
+                println!(
+                    "Tool failure rate:      {:.1}%",
+                    summary.failure_rate() * 100.0
+                );
+                println!(
+                    "Wall time per session:  {:.0}s",
+                    per_session(summary.wall_time_secs)
+                );
+                println!(
+                    "Tokens per session:     {:.0}",
+                    per_session(summary.tokens as f64)
+                );
+
+                let mut tools: Vec<_> = summary.tool_calls.iter().collect();
+                tools.sort_by_key(|(_, stats)| Reverse(stats.calls));
+                if !tools.is_empty() {
+                    println!("\nTool calls by type:");
+                }
+                for (tool, stats) in tools {
+                    println!(
+                        "  {:<28} {:>6} calls  {:>5.1}% failed",
+                        tool,
+                        stats.calls,
+                        stats.failures as f64 * 100.0 / stats.calls.max(1) as f64
+                    );
+                }
+            }
+        }
+        Ok(())
+    }
+}
@@ -1,22 +1,65 @@
-use crate::{config::AppConfig, utils::network};
-use agent::{AgentCommands, get_or_create_session, run_agent};
+use self::config::ConfigCommands;
+use crate::{
+    config::AppConfig,
+    utils::{
+        account_cache, ignore_rules::walk_files, local_context::analyze_local_context, network,
+        session_mirror,
+    },
+};
+use agent::{AgentCommands, get_or_create_session, run::RunInteractiveConfig, run_agent};
 use clap::Subcommand;
-use flow::{clone, get_flow_ref, push, sync};
+use context::ContextCommands;
+use fleet::FleetCommands;
+use flow::{FlowCommands, clone, clone_with_variables, get_flow_ref, parse_variable, push, sync};
+use history::HistoryCommands;
+use hooks::HooksCommands;
+use import::{ImportFilter, ImportProvider, parse_import_filter};
+use lint::{LintFormat, Severity};
+use mcp::McpCommands;
+use metrics::MetricsCommands;
+use policy::PolicyCommands;
+use secrets::SecretsCommands;
 use stakpak_api::{
-    Client,
-    models::{AgentID, Document, ProvisionerType, TranspileTargetProvisionerType},
+    Client, ClientConfig, GetFlowsResponse, MAX_PAGES,
+    models::{AgentID, Document, FlowRef, ProvisionerType, TranspileTargetProvisionerType},
 };
-use stakpak_mcp_server::{MCPServerConfig, ToolMode};
-use termimad::MadSkin;
-use walkdir::WalkDir;
+use stakpak_mcp_server::test_generation::TestFramework;
+use stakpak_mcp_server::{AccessConfig, MCPServerConfig, TlsConfig, TokenAccess, ToolMode};
+use stakpak_shared::session_tmp::cleanup_session_tmp_dirs;
+use stakpak_shared::uri::document_uri;
+use stakpak_tui::BrowseAction;
+use std::path::Path;
+use usage::UsageFormat;
 
 pub mod agent;
+pub mod blame;
+pub mod browse;
+pub mod config;
+pub mod context;
+pub mod fleet;
 pub mod flow;
+pub mod generate_tests;
+pub mod history;
+pub mod hooks;
+pub mod import;
+pub mod lint;
+pub mod mcp;
+pub mod metrics;
+pub mod new;
+pub mod policy;
+pub mod replay;
+pub mod report;
+pub mod secrets;
+pub mod usage;
 
 #[derive(Subcommand, PartialEq)]
 pub enum Commands {
     /// Get CLI Version
-    Version,
+    Version {
+        /// Also print the target, features and linking the binary was built with
+        #[arg(long = "build-info", default_value_t = false)]
+        build_info: bool,
+    },
     /// Login to Stakpak
     Login {
         /// API key for authentication
@@ -28,10 +71,28 @@ pub enum Commands {
     Logout,
 
     /// Get current account
-    Account,
+    Account {
+        /// Revalidate the API key instead of using cached account info
+        #[arg(long, default_value_t = false)]
+        refresh: bool,
+    },
+
+    /// Show the consumption of the account quotas in the current billing period
+    Usage {
+        /// Output format (text, json)
+        #[arg(long, short, default_value_t = UsageFormat::Text)]
+        format: UsageFormat,
+    },
 
     /// List my flows
-    List,
+    List {
+        /// Maximum number of flows to list
+        #[arg(long, short, default_value_t = 20)]
+        limit: usize,
+        /// List all flows, ignoring --limit
+        #[arg(long, default_value_t = false)]
+        all: bool,
+    },
 
     /// Get a flow
     Get {
@@ -39,6 +100,13 @@ pub enum Commands {
         flow_ref: String,
     },
 
+    /// Explore my flows, their versions and documents in a read-only TUI
+    Browse {
+        /// Directory to clone the selected flow version to, defaults to the current one
+        #[arg(long, short)]
+        dir: Option<String>,
+    },
+
     /// Clone configurations from a flow
     Clone {
         /// Flow reference in format: <owner_name>/<flow_name>(/<version_id_or_tag>)?
@@ -47,6 +115,39 @@ pub enum Commands {
         /// Destination directory
         #[arg(long, short)]
         dir: Option<String>,
+        /// Value for a `{{var}}` placeholder in the flow documents (repeatable), implies --template
+        #[arg(long = "var", value_parser = parse_variable)]
+        vars: Vec<(String, String)>,
+        /// Treat the flow as a template, prompting for `{{var}}` values not given with --var
+        #[arg(long, default_value_t = false)]
+        template: bool,
+    },
+
+    /// Create a project from a flow or git template
+    New {
+        /// Template: a flow reference (<owner_name>/<flow_name>) or a git repository URL
+        template: String,
+        /// Destination directory, defaults to the template name
+        dir: Option<String>,
+        /// Value for a `{{var}}` placeholder in the template (repeatable)
+        #[arg(long = "var", value_parser = parse_variable)]
+        vars: Vec<(String, String)>,
+        /// Start an agent session in the new project with the template kickoff prompt
+        #[arg(long, default_value_t = false)]
+        start: bool,
+    },
+
+    /// Start an agent session importing existing cloud resources into Terraform
+    Import {
+        /// Cloud provider (aws, gcp, azure)
+        #[arg(long, short)]
+        provider: ImportProvider,
+        /// Resources to import: tag:KEY=VALUE, type:RESOURCE_TYPE or region:REGION (repeatable)
+        #[arg(long = "filter", value_parser = parse_import_filter)]
+        filters: Vec<ImportFilter>,
+        /// Directory to write the Terraform configuration to, defaults to the current one
+        #[arg(long, short)]
+        dir: Option<String>,
     },
 
     /// Sync configurations from and to a flow
@@ -57,6 +158,9 @@ pub enum Commands {
         /// Source/Destination directory
         #[arg(long, short)]
         dir: Option<String>,
+        /// Write conflict markers in files changed both locally and in the flow instead of asking how to resolve them
+        #[arg(long, default_value_t = false)]
+        conflict_markers: bool,
     },
 
     /// Query your configurations
@@ -91,6 +195,12 @@ pub enum Commands {
         /// Auto approve all changes
         #[arg(long, short = 'y', default_value_t = false)]
         auto_approve: bool,
+        /// Review each changed file before pushing (accept, skip or show its diff)
+        #[arg(long, short, default_value_t = false, conflicts_with = "auto_approve")]
+        interactive: bool,
+        /// Files saved per request, lower it if pushes time out
+        #[arg(long, default_value_t = flow::DEFAULT_BATCH_SIZE)]
+        batch_size: usize,
     },
 
     /// Apply configurations
@@ -123,6 +233,40 @@ pub enum Commands {
         target_provisioner: TranspileTargetProvisionerType,
     },
 
+    /// Generate tests for existing configurations
+    GenerateTests {
+        /// Configuration files or directories to test
+        #[arg(required = true)]
+        paths: Vec<String>,
+
+        /// Test framework (terratest, terraform-test, kuttl, kubeconform, container-structure-test)
+        #[arg(long, short)]
+        framework: TestFramework,
+
+        /// Extra instructions, e.g. which behaviors or edge cases to cover
+        #[arg(long, short)]
+        instructions: Option<String>,
+
+        /// Print the generated tests instead of writing them
+        #[arg(long, default_value_t = false)]
+        print: bool,
+    },
+
+    /// Run the linters that apply to the workspace and merge their findings into one report
+    Lint {
+        /// Directory to lint
+        #[arg(long, short)]
+        dir: Option<String>,
+
+        /// Output format (text, json)
+        #[arg(long, short, default_value_t = LintFormat::Text)]
+        format: LintFormat,
+
+        /// Start an agent session fixing the findings
+        #[arg(long, default_value_t = false)]
+        fix: bool,
+    },
+
     /// Start the MCP server
     Mcp {
         /// Disable secret redaction (WARNING: this will print secrets to the console)
@@ -132,8 +276,103 @@ pub enum Commands {
         /// Tool mode to use (local, remote, combined)
         #[arg(long, short = 'm', default_value_t = ToolMode::Combined)]
         tool_mode: ToolMode,
+
+        /// Access config mapping bearer tokens to tool modes and roles (viewer, operator)
+        #[arg(long = "access-config")]
+        access_config: Option<String>,
+
+        /// Shared secret clients must send as a bearer token, granting every tool of the tool mode
+        #[arg(
+            long = "auth-token",
+            env = "STAKPAK_MCP_AUTH_TOKEN",
+            hide_env_values = true
+        )]
+        auth_token: Option<String>,
+
+        /// PEM certificate chain to serve HTTPS with, requires --tls-key
+        #[arg(long = "tls-cert", requires = "tls_key")]
+        tls_cert: Option<String>,
+
+        /// PEM private key of the --tls-cert certificate
+        #[arg(long = "tls-key", requires = "tls_cert")]
+        tls_key: Option<String>,
+
+        #[command(subcommand)]
+        command: Option<McpCommands>,
     },
 
+    /// Follow a running local agent session read-only from another terminal
+    Attach {
+        /// Session ID shown in the session being watched, optional when only one is running
+        session_id: Option<String>,
+    },
+
+    /// Generate a standalone HTML report of an agent session
+    Report {
+        /// Agent session ID to report on
+        session_id: String,
+        /// Output file path
+        #[arg(long, short, default_value = "report.html")]
+        out: String,
+    },
+
+    /// Re-run the tool calls of a recorded session step by step against the current workspace
+    Replay {
+        /// Session transcript file, or the checkpoint ID of a session in the local history
+        session: String,
+        /// Step to start from, earlier steps are skipped
+        #[arg(long, default_value_t = 1)]
+        from: usize,
+        /// Run every step without asking for confirmation
+        #[arg(long, short, default_value_t = false)]
+        yes: bool,
+    },
+
+    /// Show which agent step last changed each hunk of a file edited in this session
+    Blame {
+        /// File to blame, may be a root://<name>/ path
+        path: String,
+    },
+
+    /// Inspect the CLI configuration
+    #[command(subcommand)]
+    Config(ConfigCommands),
+
+    /// Scan the workspace for secrets
+    #[command(subcommand)]
+    Secrets(SecretsCommands),
+
+    /// Manage git hooks
+    #[command(subcommand)]
+    Hooks(HooksCommands),
+
+    /// Manage the commands this workspace runs without confirmation
+    #[command(subcommand)]
+    Policy(PolicyCommands),
+
+    /// Manage the stack-specific context packs given to the agent in this workspace
+    #[command(subcommand)]
+    Context(ContextCommands),
+
+    /// Summarize the metrics recorded for agent sessions in this workspace
+    #[command(subcommand)]
+    Metrics(MetricsCommands),
+
+    /// Show which API endpoint is active and the health of the configured endpoints
+    Status,
+
+    /// Search past local agent sessions
+    #[command(subcommand)]
+    History(HistoryCommands),
+
+    /// Inspect flows
+    #[command(subcommand)]
+    Flow(FlowCommands),
+
+    /// Run agent sessions across many directories in parallel
+    #[command(subcommand)]
+    Fleet(FleetCommands),
+
     /// Stakpak Agent (WARNING: These agents are in early alpha development and may be unstable)
     #[command(subcommand)]
     Agent(AgentCommands),
@@ -142,23 +381,66 @@ pub enum Commands {
 impl Commands {
     pub async fn run(self, config: AppConfig) -> Result<(), String> {
         match self {
+            Commands::Mcp {
+                command: Some(mcp_commands),
+                ..
+            } => {
+                mcp_commands.run(config).await?;
+            }
             Commands::Mcp {
                 disable_secret_redaction,
                 tool_mode,
+                access_config,
+                auth_token,
+                tls_cert,
+                tls_key,
+                command: None,
             } => {
+                let sandbox = config.sandbox_profile()?;
+                let dev_container = config.dev_container()?;
+                let command_policy = config.command_policy()?;
+                let mut access = access_config
+                    .map(|path| AccessConfig::load(Path::new(&path)))
+                    .transpose()?;
+                if let Some(token) = auth_token {
+                    if token.trim().is_empty() {
+                        return Err("The auth token is empty".to_string());
+                    }
+                    access
+                        .get_or_insert_default()
+                        .tokens
+                        .push(TokenAccess::shared(token));
+                }
+                let tls = match (tls_cert, tls_key) {
+                    (Some(cert_path), Some(key_path)) => Some(TlsConfig {
+                        cert_path: cert_path.into(),
+                        key_path: key_path.into(),
+                    }),
+                    _ => None,
+                };
                 let bind_address = network::find_available_bind_address_descending().await?;
-                println!("MCP server started at http://{}", bind_address);
+                println!(
+                    "MCP server started at {}://{}",
+                    if tls.is_some() { "https" } else { "http" },
+                    bind_address
+                );
                 stakpak_mcp_server::start_server(
                     MCPServerConfig {
                         api: config.into(),
                         redact_secrets: !disable_secret_redaction,
                         bind_address: bind_address.clone(),
                         tool_mode,
+                        sandbox,
+                        dev_container,
+                        command_policy,
+                        access,
+                        tls,
                     },
                     None,
                 )
                 .await
                 .map_err(|e| e.to_string())?;
+                cleanup_session_tmp_dirs()?;
             }
             Commands::Login { api_key } => {
                 let mut updated_config = config.clone();
@@ -167,6 +449,7 @@ impl Commands {
                 updated_config
                     .save()
                     .map_err(|e| format!("Failed to save config: {}", e))?;
+                account_cache::AccountCache::clear();
             }
             Commands::Logout => {
                 let mut updated_config = config.clone();
@@ -175,17 +458,36 @@ impl Commands {
                 updated_config
                     .save()
                     .map_err(|e| format!("Failed to save config: {}", e))?;
+                account_cache::AccountCache::clear();
             }
-            Commands::Account => {
-                let client = Client::new(&(config.into())).map_err(|e| e.to_string())?;
-                let data = client.get_my_account().await?;
-                println!("{}", data.to_text());
+            Commands::Account { refresh } => {
+                let client_config: ClientConfig = config.into();
+                let client = Client::new(&client_config).map_err(|e| e.to_string())?;
+                let info = account_cache::get_account(&client, &client_config, refresh).await?;
+                if let Some(warning) = &info.warning {
+                    eprintln!("Warning: {}", warning);
+                }
+                println!("{}", info.to_text());
             }
-            Commands::List => {
+            Commands::Usage { format } => {
                 let client = Client::new(&config.into()).map_err(|e| e.to_string())?;
-                let owner_name = client.get_my_account().await?.username;
-                let data = client.list_flows(&owner_name).await?;
+                usage::show_usage(&client, format).await?;
+            }
+            Commands::List { limit, all } => {
+                let client_config: ClientConfig = config.into();
+                let client = Client::new(&client_config).map_err(|e| e.to_string())?;
+                let owner_name = account_cache::get_account(&client, &client_config, false)
+                    .await?
+                    .account
+                    .username;
+                let flows = client
+                    .list_flows(&owner_name, if all { None } else { Some(limit) })
+                    .await?;
+                let data = GetFlowsResponse {
+                    results: flows.items,
+                };
                 println!("{}", data.to_text(&owner_name));
+                print_listing_hint("flows", data.results.len(), flows.has_more, all);
             }
             Commands::Get { flow_ref } => {
                 let client = Client::new(&config.into()).map_err(|e| e.to_string())?;
@@ -200,10 +502,107 @@ impl Commands {
                 let data = client.get_flow(owner_name, flow_name).await?;
                 println!("{}", data.to_text(owner_name));
             }
-            Commands::Clone { flow_ref, dir } => {
+            Commands::Browse { dir } => {
+                let client_config: ClientConfig = config.clone().into();
+                let client = Client::new(&client_config).map_err(|e| e.to_string())?;
+                let owner_name = account_cache::get_account(&client, &client_config, false)
+                    .await?
+                    .account
+                    .username;
+                let flows = client.list_flows(&owner_name, None).await?.items;
+                if flows.is_empty() {
+                    return Err("No flows found, push one with `stakpak push` first".into());
+                }
+
+                match browse::browse(&client, &owner_name, flows).await? {
+                    Some(BrowseAction::Clone(flow_ref)) => {
+                        let flow_ref = FlowRef::new(flow_ref)?;
+                        clone(&client, &flow_ref, dir.as_deref()).await?;
+                    }
+                    Some(BrowseAction::StartSession(flow_ref)) => {
+                        let flow_ref = FlowRef::new(flow_ref)?;
+                        clone(&client, &flow_ref, dir.as_deref()).await?;
+                        if let Some(dir) = dir {
+                            std::env::set_current_dir(&dir)
+                                .map_err(|e| format!("Failed to enter {}: {}", dir, e))?;
+                        }
+                        let local_context = analyze_local_context().await.ok();
+                        agent::run::run_interactive(
+                            config,
+                            RunInteractiveConfig {
+                                checkpoint_id: None,
+                                local_context,
+                                redact_secrets: true,
+                                initial_prompt: None,
+                            },
+                        )
+                        .await?;
+                    }
+                    None => {}
+                }
+            }
+            Commands::Clone {
+                flow_ref,
+                dir,
+                vars,
+                template,
+            } => {
                 let client = Client::new(&config.into()).map_err(|e| e.to_string())?;
                 let flow_ref = get_flow_ref(&client, flow_ref).await?;
-                clone(&client, &flow_ref, dir.as_deref()).await?;
+                // Plain clones keep `{{...}}` as is, it may belong to another templating tool
+                let variables = (template || !vars.is_empty()).then(|| vars.into_iter().collect());
+                clone_with_variables(&client, &flow_ref, dir.as_deref(), variables).await?;
+            }
+            Commands::New {
+                template,
+                dir,
+                vars,
+                start,
+            } => {
+                let client = Client::new(&config.clone().into()).map_err(|e| e.to_string())?;
+                let (dir, manifest) =
+                    new::new_project(&client, &template, dir, vars.into_iter().collect()).await?;
+
+                if start {
+                    std::env::set_current_dir(&dir)
+                        .map_err(|e| format!("Failed to enter {}: {}", dir.display(), e))?;
+                    let local_context = analyze_local_context().await.ok();
+                    agent::run::run_interactive(
+                        config,
+                        RunInteractiveConfig {
+                            checkpoint_id: None,
+                            local_context,
+                            redact_secrets: true,
+                            initial_prompt: manifest.kickoff_prompt,
+                        },
+                    )
+                    .await?;
+                } else {
+                    println!("Start working on it with: cd {} && stakpak", dir.display());
+                }
+            }
+            Commands::Import {
+                provider,
+                filters,
+                dir,
+            } => {
+                if let Some(dir) = dir {
+                    std::fs::create_dir_all(&dir)
+                        .map_err(|e| format!("Failed to create {}: {}", dir, e))?;
+                    std::env::set_current_dir(&dir)
+                        .map_err(|e| format!("Failed to enter {}: {}", dir, e))?;
+                }
+                let local_context = analyze_local_context().await.ok();
+                agent::run::run_interactive(
+                    config,
+                    RunInteractiveConfig {
+                        checkpoint_id: None,
+                        local_context,
+                        redact_secrets: true,
+                        initial_prompt: Some(import::import_prompt(provider, &filters)),
+                    },
+                )
+                .await?;
             }
             Commands::Query {
                 query,
@@ -211,6 +610,7 @@ impl Commands {
                 generate_query,
                 synthesize_output,
             } => {
+                let renderer = config.markdown_renderer();
                 let client = Client::new(&config.into()).map_err(|e| e.to_string())?;
                 let data = client
                     .query_blocks(
@@ -221,13 +621,23 @@ impl Commands {
                     )
                     .await?;
 
-                let skin = MadSkin::default();
-                println!("{}", skin.inline(&data.to_text(synthesize_output)));
+                renderer.print(&data.to_text(synthesize_output));
             }
-            Commands::Sync { flow_ref, dir } => {
+            Commands::Sync {
+                flow_ref,
+                dir,
+                conflict_markers,
+            } => {
                 let client = Client::new(&config.clone().into()).map_err(|e| e.to_string())?;
                 let flow_ref = get_flow_ref(&client, flow_ref).await?;
-                sync(&config, &client, &flow_ref, dir.as_deref()).await?;
+                sync(
+                    &config,
+                    &client,
+                    &flow_ref,
+                    dir.as_deref(),
+                    conflict_markers,
+                )
+                .await?;
             }
             Commands::Push {
                 flow_ref,
@@ -235,13 +645,25 @@ impl Commands {
                 dir,
                 ignore_delete,
                 auto_approve,
+                interactive,
+                batch_size,
             } => {
                 let client = Client::new(&config.into()).map_err(|e| e.to_string())?;
 
-                let save_result =
-                    push(&client, flow_ref, create, dir, ignore_delete, auto_approve).await?;
+                let push_result = push(
+                    &client,
+                    flow_ref,
+                    create,
+                    dir,
+                    ignore_delete,
+                    auto_approve,
+                    interactive,
+                    batch_size,
+                )
+                .await?;
 
-                if let Some(save_result) = save_result {
+                if let Some(push_result) = push_result {
+                    let save_result = push_result.saved;
                     if !save_result.errors.is_empty() {
                         println!("\nSave errors:");
                         for error in save_result.errors {
@@ -261,6 +683,70 @@ impl Commands {
                             total_blocks as f64 * 1.5 / 60.0
                         );
                     }
+
+                    if !push_result.chunk_errors.is_empty() {
+                        println!(
+                            "\nFailed to push {} of {} chunks:",
+                            push_result.chunk_errors.len(),
+                            push_result.chunks
+                        );
+                        for chunk_error in &push_result.chunk_errors {
+                            println!(
+                                "\tChunk {} ({} files): {}",
+                                chunk_error.chunk,
+                                chunk_error.files.len(),
+                                chunk_error.error
+                            );
+                            for file in &chunk_error.files {
+                                println!("\t\t{}", file);
+                            }
+                        }
+                        return Err(
+                            "Some files were not pushed, run push again to retry them".into()
+                        );
+                    }
+                }
+            }
+            Commands::GenerateTests {
+                paths,
+                framework,
+                instructions,
+                print,
+            } => {
+                generate_tests::generate_tests(
+                    config.into(),
+                    paths,
+                    framework,
+                    instructions,
+                    print,
+                )
+                .await?;
+            }
+            Commands::Lint { dir, format, fix } => {
+                let dir = dir.unwrap_or_else(|| ".".into());
+                let findings = lint::lint_workspace(&dir, format).await?;
+                if fix && !findings.is_empty() {
+                    std::env::set_current_dir(&dir)
+                        .map_err(|e| format!("Failed to enter {}: {}", dir, e))?;
+                    let local_context = analyze_local_context().await.ok();
+                    agent::run::run_interactive(
+                        config,
+                        RunInteractiveConfig {
+                            checkpoint_id: None,
+                            local_context,
+                            redact_secrets: true,
+                            initial_prompt: Some(lint::lint_fix_prompt(&findings)),
+                        },
+                    )
+                    .await?;
+                } else {
+                    let errors = findings
+                        .iter()
+                        .filter(|finding| finding.severity == Severity::Error)
+                        .count();
+                    if errors > 0 {
+                        return Err(format!("Found {} lint errors", errors));
+                    }
                 }
             }
             Commands::Transpile {
@@ -282,50 +768,19 @@ impl Commands {
 
                 let mut documents = Vec::new();
 
-                for entry in WalkDir::new(&base_dir)
-                    .follow_links(false)
+                // Only terraform files, skipping hidden and ignored ones
+                for path in walk_files(Path::new(&base_dir))
                     .into_iter()
-                    .filter_entry(|e| {
-                        // Skip hidden directories and non-supported files
-                        let file_name = e.file_name().to_str();
-                        match file_name {
-                            Some(name) => {
-                                // Skip hidden files/dirs that aren't just "."
-                                if name.starts_with('.') && name.len() > 1 {
-                                    return false;
-                                }
-                                // Only allow terraform files when from is terraform
-                                if e.file_type().is_file() {
-                                    name.ends_with(".tf")
-                                } else {
-                                    true // Allow directories to be traversed
-                                }
-                            }
-                            None => false,
-                        }
-                    })
-                    .filter_map(|e| e.ok())
+                    .filter(|path| path.extension().is_some_and(|ext| ext == "tf"))
                 {
-                    // Skip directories
-                    if !entry.file_type().is_file() {
-                        continue;
-                    }
-
-                    let path = entry.path();
+                    let path = path.as_path();
                     // Skip binary files by attempting to read as UTF-8 and checking for errors
                     let content = match std::fs::read_to_string(path) {
                         Ok(content) => content,
                         Err(_) => continue, // Skip file if it can't be read as valid UTF-8
                     };
 
-                    // Convert path to URI format
-                    #[allow(clippy::unwrap_used)]
-                    let document_path = path
-                        .strip_prefix(&base_dir)
-                        .unwrap()
-                        .to_string_lossy()
-                        .replace('\\', "/");
-                    let document_uri = format!("file:///{}", document_path);
+                    let document_uri = document_uri(Path::new(&base_dir), path)?;
 
                     documents.push(Document {
                         content,
@@ -356,7 +811,7 @@ impl Commands {
                 );
             }
             Commands::Agent(agent_commands) => {
-                if let AgentCommands::Get { .. } = agent_commands {
+                if let AgentCommands::Get { .. } | AgentCommands::Label { .. } = agent_commands {
                 } else {
                     println!();
                     println!(
@@ -367,11 +822,89 @@ impl Commands {
 
                 AgentCommands::run(agent_commands, config, false).await?;
             }
-            Commands::Version => {
+            Commands::Attach { session_id } => {
+                let session_id = match session_id {
+                    Some(session_id) => session_id,
+                    None => {
+                        let mut running = session_mirror::running_sessions();
+                        match running.len() {
+                            0 => return Err("No running sessions to attach to".to_string()),
+                            1 => running.remove(0),
+                            _ => {
+                                return Err(format!(
+                                    "Several sessions are running, pick one: {}",
+                                    running.join(", ")
+                                ));
+                            }
+                        }
+                    }
+                };
+                session_mirror::attach(&session_id, &config.markdown_renderer()).await?;
+            }
+            Commands::Report { session_id, out } => {
+                let client = Client::new(&config.into()).map_err(|e| e.to_string())?;
+                report::generate_report(&client, &session_id, &out).await?;
+            }
+            Commands::Replay { session, from, yes } => {
+                replay::replay_session(config, &session, from, yes).await?;
+            }
+            Commands::Blame { path } => {
+                blame::blame_file(&path)?;
+            }
+            Commands::Config(config_commands) => {
+                config_commands.run(config).await?;
+            }
+            Commands::Secrets(secrets_commands) => {
+                secrets_commands.run().await?;
+            }
+            Commands::Hooks(hooks_commands) => {
+                hooks_commands.run().await?;
+            }
+            Commands::Policy(policy_commands) => {
+                policy_commands.run().await?;
+            }
+            Commands::Context(context_commands) => {
+                context_commands.run().await?;
+            }
+            Commands::Metrics(metrics_commands) => {
+                metrics_commands.run().await?;
+            }
+            Commands::Status => {
+                let client = Client::new(&config.into()).map_err(|e| e.to_string())?;
+                let health = client.endpoint_health().await;
+                // A fresh client starts on the primary, requests fail over to the first
+                // healthy endpoint
+                let active = health
+                    .iter()
+                    .find(|(_, healthy)| *healthy)
+                    .map(|(endpoint, _)| endpoint.clone())
+                    .unwrap_or_else(|| client.active_endpoint());
+                for (endpoint, healthy) in &health {
+                    println!(
+                        "{} {:<48} {}",
+                        if *endpoint == active { "*" } else { " " },
+                        endpoint,
+                        if *healthy { "healthy" } else { "unreachable" }
+                    );
+                }
+            }
+            Commands::History(history_commands) => {
+                history_commands.run().await?;
+            }
+            Commands::Flow(flow_commands) => {
+                flow_commands.run(config).await?;
+            }
+            Commands::Fleet(fleet_commands) => {
+                fleet_commands.run().await?;
+            }
+            Commands::Version { build_info } => {
                 println!(
                     "stakpak v{} (https://github.com/stakpak/cli)",
                     env!("CARGO_PKG_VERSION")
                 );
+                if build_info {
+                    println!("{}", format_build_info());
+                }
             }
             Commands::Apply {
                 flow_ref,
@@ -393,12 +926,14 @@ impl Commands {
                     Client::new(&config_clone.clone().into()).map_err(|e| e.to_string())?;
                 let flow_ref_clone = flow_ref.clone();
                 let dir_clone = dir.clone();
+                // Runs alongside the agent, so conflicts get markers instead of prompts
                 tokio::spawn(async move {
                     flow::sync(
                         &config_clone,
                         &client_clone,
                         &flow_ref_clone,
                         dir_clone.as_deref(),
+                        true,
                     )
                     .await
                 });
@@ -459,3 +994,81 @@ impl Commands {
         Ok(())
     }
 }
+
+/// Tell the user a listing left items out, pointing to --all unless it was already given
+pub fn print_listing_hint(what: &str, shown: usize, has_more: bool, all: bool) {
+    if !has_more {
+        return;
+    }
+    if all {
+        eprintln!(
+            "Warning: stopped after {} {} ({} pages), more are available",
+            shown, what, MAX_PAGES
+        );
+    } else {
+        eprintln!(
+            "Showing the first {} {}, there are more results. Use --all to list them all",
+            shown, what
+        );
+    }
+}
+
+/// Build metadata recorded by the build script
+fn format_build_info() -> String {
+    let features = env!("STAKPAK_BUILD_FEATURES");
+    let tls = if features.split(',').any(|feature| feature == "rustls") {
+        "rustls"
+    } else {
+        "native-tls"
+    };
+    let linking = if env!("STAKPAK_BUILD_STATIC") == "true" {
+        "static"
+    } else {
+        "dynamic"
+    };
+    [
+        ("target", env!("STAKPAK_BUILD_TARGET")),
+        ("profile", env!("STAKPAK_BUILD_PROFILE")),
+        ("features", features),
+        ("tls", tls),
+        ("linking", linking),
+        ("commit", env!("STAKPAK_BUILD_COMMIT")),
+    ]
+    .iter()
+    .map(|(name, value)| {
+        let label = format!("{}:", name);
+        format!("{:<9} {}", label, value)
+    })
+    .collect::<Vec<_>>()
+    .join("\n")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_format_build_info() {
+        let info = format_build_info();
+        let fields: Vec<(&str, &str)> = info
+            .lines()
+            .filter_map(|line| line.split_once(':'))
+            .map(|(name, value)| (name, value.trim()))
+            .collect();
+        let names: Vec<&str> = fields.iter().map(|(name, _)| *name).collect();
+        assert_eq!(
+            names,
+            vec!["target", "profile", "features", "tls", "linking", "commit"]
+        );
+        let field = |name: &str| {
+            fields
+                .iter()
+                .find(|(field, _)| *field == name)
+                .map(|(_, value)| *value)
+        };
+        assert_eq!(field("target"), Some(env!("STAKPAK_BUILD_TARGET")));
+        assert!(matches!(field("tls"), Some("rustls" | "native-tls")));
+        assert!(matches!(field("linking"), Some("static" | "dynamic")));
+        assert!(!field("commit").unwrap_or_default().is_empty());
+    }
+}
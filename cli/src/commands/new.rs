@@ -0,0 +1,251 @@
+use crate::commands::flow::{
+    clone, find_variables, get_flow_ref, resolve_variables, substitute_variables,
+};
+use serde::{Deserialize, Serialize};
+use stakpak_api::Client;
+use stakpak_shared::local_store::LocalStore;
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+use walkdir::WalkDir;
+
+/// Optional file at the root of a template describing how to bootstrap it
+pub const TEMPLATE_MANIFEST: &str = "stakpak-template.toml";
+
+/// ```toml
+/// description = "EKS cluster with a managed node group"
+/// kickoff_prompt = "Review the generated Terraform and plan it against {{aws_region}}"
+///
+/// [variables]
+/// aws_region = "us-east-1"
+/// ```
+#[derive(Deserialize, Serialize, Debug, Default, Clone)]
+pub struct TemplateManifest {
+    pub description: Option<String>,
+    /// First message sent to the agent when the project is started with `--start`
+    pub kickoff_prompt: Option<String>,
+    /// Default values for `{{var}}` placeholders
+    #[serde(default)]
+    pub variables: HashMap<String, String>,
+}
+
+/// Record of the template a project was created from, kept in `.stakpak/template.toml`
+#[derive(Serialize, Debug)]
+struct TemplateRecord<'a> {
+    source: &'a str,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    kickoff_prompt: Option<&'a str>,
+}
+
+#[derive(Debug, PartialEq)]
+pub enum TemplateSource {
+    /// Git repository URL or local repository path
+    Git(String),
+    /// Flow reference in format: <owner_name>/<flow_name>(/<version_id_or_tag>)?
+    Flow(String),
+}
+
+impl TemplateSource {
+    pub fn parse(template: &str) -> Self {
+        let is_git = ["https://", "http://", "ssh://", "git@", "file://"]
+            .iter()
+            .any(|prefix| template.starts_with(prefix))
+            || template.ends_with(".git")
+            || Path::new(template).is_dir();
+        if is_git {
+            TemplateSource::Git(template.to_string())
+        } else {
+            TemplateSource::Flow(template.to_string())
+        }
+    }
+
+    /// Directory name used when none is given: the repository or flow name
+    pub fn default_dir(&self) -> String {
+        let name = match self {
+            TemplateSource::Git(url) => url
+                .trim_end_matches('/')
+                .rsplit(['/', ':'])
+                .next()
+                .unwrap_or_default()
+                .trim_end_matches(".git"),
+            TemplateSource::Flow(flow_ref) => flow_ref.split('/').nth(1).unwrap_or_default(),
+        };
+        if name.is_empty() {
+            "new-project".to_string()
+        } else {
+            name.to_string()
+        }
+    }
+}
+
+/// Materialize `template` into `dir`, substitute its variables and initialize `.stakpak/`.
+/// Returns the project directory and the template manifest.
+pub async fn new_project(
+    client: &Client,
+    template: &str,
+    dir: Option<String>,
+    variables: HashMap<String, String>,
+) -> Result<(PathBuf, TemplateManifest), String> {
+    let source = TemplateSource::parse(template);
+    let dir = PathBuf::from(dir.unwrap_or_else(|| source.default_dir()));
+    let is_empty_dir = dir
+        .read_dir()
+        .map(|mut entries| entries.next().is_none())
+        .unwrap_or(true);
+    if !is_empty_dir {
+        return Err(format!("{} already exists and is not empty", dir.display()));
+    }
+
+    match &source {
+        TemplateSource::Git(url) => clone_git_template(url, &dir)?,
+        TemplateSource::Flow(flow_ref) => {
+            let flow_ref = get_flow_ref(client, flow_ref.clone()).await?;
+            clone(client, &flow_ref, dir.to_str()).await?;
+        }
+    }
+
+    let manifest_path = dir.join(TEMPLATE_MANIFEST);
+    let manifest = match std::fs::read_to_string(&manifest_path) {
+        Ok(content) => {
+            std::fs::remove_file(&manifest_path)
+                .map_err(|e| format!("Failed to remove {}: {}", manifest_path.display(), e))?;
+            toml::from_str::<TemplateManifest>(&content)
+                .map_err(|e| format!("Failed to parse {}: {}", TEMPLATE_MANIFEST, e))?
+        }
+        Err(_) => TemplateManifest::default(),
+    };
+    if let Some(description) = &manifest.description {
+        println!("{}", description);
+    }
+
+    let mut provided = manifest.variables.clone();
+    provided.extend(variables);
+    let resolved = substitute_template_variables(&dir, provided)?;
+
+    let manifest = TemplateManifest {
+        kickoff_prompt: manifest
+            .kickoff_prompt
+            .map(|prompt| substitute_variables(&prompt, &resolved))
+            .transpose()?,
+        ..manifest
+    };
+    init_stakpak_dir(&dir, template, &manifest)?;
+
+    println!("Created project in \"{}\"", dir.display());
+    Ok((dir, manifest))
+}
+
+fn clone_git_template(url: &str, dir: &Path) -> Result<(), String> {
+    let output = Command::new("git")
+        .args(["clone", "--depth", "1", url])
+        .arg(dir)
+        .output()
+        .map_err(|e| format!("Failed to run git: {}", e))?;
+    if !output.status.success() {
+        return Err(format!(
+            "Failed to clone {}: {}",
+            url,
+            String::from_utf8_lossy(&output.stderr).trim()
+        ));
+    }
+
+    // The new project starts its own history
+    let git_dir = dir.join(".git");
+    std::fs::remove_dir_all(&git_dir)
+        .map_err(|e| format!("Failed to remove {}: {}", git_dir.display(), e))?;
+    println!("Cloned {} -> \"{}\"", url, dir.display());
+    Ok(())
+}
+
+/// Replace `{{var}}` placeholders in every text file of `dir`, prompting for values that
+/// have neither a template default nor a `--var`
+fn substitute_template_variables(
+    dir: &Path,
+    provided: HashMap<String, String>,
+) -> Result<HashMap<String, String>, String> {
+    let mut files = Vec::new();
+    for entry in WalkDir::new(dir)
+        .into_iter()
+        .filter_entry(|e| e.file_name() != ".git" && e.file_name() != ".stakpak")
+        .filter_map(|e| e.ok())
+        .filter(|e| e.file_type().is_file())
+    {
+        // Skip binary files
+        if let Ok(content) = std::fs::read_to_string(entry.path()) {
+            files.push((entry.into_path(), content));
+        }
+    }
+
+    let found = find_variables(files.iter().map(|(_, content)| content.as_str()))?;
+    if found.is_empty() {
+        return Ok(provided);
+    }
+    println!(
+        "Template uses variables: {}",
+        found.iter().cloned().collect::<Vec<_>>().join(", ")
+    );
+    let variables = resolve_variables(&found, provided)?;
+
+    for (path, content) in files {
+        let substituted = substitute_variables(&content, &variables)?;
+        if substituted != content {
+            std::fs::write(&path, substituted)
+                .map_err(|e| format!("Failed to write file {}: {}", path.display(), e))?;
+        }
+    }
+    Ok(variables)
+}
+
+fn init_stakpak_dir(dir: &Path, source: &str, manifest: &TemplateManifest) -> Result<(), String> {
+    let stakpak_dir = dir.join(".stakpak");
+    std::fs::create_dir_all(stakpak_dir.join("session"))
+        .map_err(|e| format!("Failed to create {}: {}", stakpak_dir.display(), e))?;
+
+    let record = toml::to_string(&TemplateRecord {
+        source,
+        kickoff_prompt: manifest.kickoff_prompt.as_deref(),
+    })
+    .map_err(|e| e.to_string())?;
+    std::fs::write(stakpak_dir.join("template.toml"), record)
+        .map_err(|e| format!("Failed to write template record: {}", e))?;
+
+    LocalStore::ensure_gitignored_in(dir)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_template_source() {
+        assert_eq!(
+            TemplateSource::parse("https://github.com/acme/eks-template.git"),
+            TemplateSource::Git("https://github.com/acme/eks-template.git".to_string())
+        );
+        assert_eq!(
+            TemplateSource::parse("git@github.com:acme/eks-template"),
+            TemplateSource::Git("git@github.com:acme/eks-template".to_string())
+        );
+        assert_eq!(
+            TemplateSource::parse("acme/eks-cluster"),
+            TemplateSource::Flow("acme/eks-cluster".to_string())
+        );
+    }
+
+    #[test]
+    fn test_default_dir() {
+        assert_eq!(
+            TemplateSource::Git("https://github.com/acme/eks-template.git".to_string())
+                .default_dir(),
+            "eks-template"
+        );
+        assert_eq!(
+            TemplateSource::Git("git@github.com:acme/eks-template".to_string()).default_dir(),
+            "eks-template"
+        );
+        assert_eq!(
+            TemplateSource::Flow("acme/eks-cluster/v1".to_string()).default_dir(),
+            "eks-cluster"
+        );
+    }
+}
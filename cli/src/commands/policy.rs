@@ -0,0 +1,109 @@
+use clap::Subcommand;
+use stakpak_shared::workspace_policy::{
+    PatternApprovals, WorkspacePolicy, dismiss_suggestion, policy_suggestions,
+};
+use std::io::Write;
+
+#[derive(Subcommand, PartialEq)]
+pub enum PolicyCommands {
+    /// List the command patterns the workspace policy runs without confirmation
+    List,
+    /// Review command patterns you approved repeatedly and add them to the workspace policy
+    Review,
+    /// Remove a command pattern from the workspace policy
+    Remove {
+        /// Pattern exactly as listed by `stakpak policy list`
+        pattern: String,
+    },
+}
+
+enum ReviewAnswer {
+    Add(String),
+    Dismiss,
+    Skip,
+}
+
+fn ask_review(pattern: &str, approvals: &PatternApprovals) -> Result<ReviewAnswer, String> {
+    println!("\n{}  (approved {} times)", pattern, approvals.count);
+    for example in &approvals.examples {
+        println!("    > {}", example);
+    }
+    print!("Add to the workspace policy? [yes/edit/no/skip] (skip): ");
+    std::io::stdout().flush().map_err(|e| e.to_string())?;
+    let mut input = String::new();
+    std::io::stdin()
+        .read_line(&mut input)
+        .map_err(|e| format!("Failed to read input: {}", e))?;
+
+    Ok(match input.trim().to_lowercase().as_str() {
+        "yes" | "y" => ReviewAnswer::Add(pattern.to_string()),
+        "edit" | "e" => {
+            print!("Pattern: ");
+            std::io::stdout().flush().map_err(|e| e.to_string())?;
+            let mut edited = String::new();
+            std::io::stdin()
+                .read_line(&mut edited)
+                .map_err(|e| format!("Failed to read input: {}", e))?;
+            match edited.trim() {
+                "" => ReviewAnswer::Skip,
+                edited => ReviewAnswer::Add(edited.to_string()),
+            }
+        }
+        "no" | "n" => ReviewAnswer::Dismiss,
+        _ => ReviewAnswer::Skip,
+    })
+}
+
+impl PolicyCommands {
+    pub async fn run(self) -> Result<(), String> {
+        let mut policy = WorkspacePolicy::load()?;
+        match self {
+            PolicyCommands::List => {
+                if policy.allow.is_empty() {
+                    println!("The workspace policy is empty, every command asks for confirmation");
+                }
+                for pattern in &policy.allow {
+                    println!("{}", pattern);
+                }
+            }
+            PolicyCommands::Review => {
+                let suggestions = policy_suggestions()?;
+                if suggestions.is_empty() {
+                    println!("Nothing to review, no command pattern was approved often enough");
+                    return Ok(());
+                }
+                println!(
+                    "Commands matching the patterns in {} run without confirmation, unless they need a typed confirmation.",
+                    WorkspacePolicy::path().display()
+                );
+                for (pattern, approvals) in suggestions {
+                    match ask_review(&pattern, &approvals)? {
+                        ReviewAnswer::Add(added) => {
+                            if !policy.allow.contains(&added) {
+                                policy.allow.push(added.clone());
+                                policy.save()?;
+                            }
+                            // An edited pattern replaces the suggestion
+                            if added != pattern {
+                                dismiss_suggestion(&pattern)?;
+                            }
+                            println!("Added {}", added);
+                        }
+                        ReviewAnswer::Dismiss => dismiss_suggestion(&pattern)?,
+                        ReviewAnswer::Skip => {}
+                    }
+                }
+            }
+            PolicyCommands::Remove { pattern } => {
+                let before = policy.allow.len();
+                policy.allow.retain(|allowed| allowed != &pattern);
+                if policy.allow.len() == before {
+                    return Err(format!("{} is not in the workspace policy", pattern));
+                }
+                policy.save()?;
+                println!("Removed {}", pattern);
+            }
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,277 @@
+use crate::commands::agent::run::helpers::ASK_USER_TOOL_NAME;
+use crate::commands::agent::run::tooling::run_tool_call;
+use crate::commands::history::SessionTranscript;
+use crate::config::AppConfig;
+use crate::utils::network;
+use stakpak_api::ClientConfig;
+use stakpak_mcp_client::ClientManager;
+use stakpak_mcp_server::{MCPServerConfig, ToolMode};
+use stakpak_shared::local_store::LocalStore;
+use stakpak_shared::models::integrations::openai::{ChatMessage, Role, ToolCall};
+use std::io::Write;
+use std::path::Path;
+
+const REPLAY_HELP: &str = "y - run this step
+n - skip this step
+o - show the output recorded in the session
+a - run this step and all remaining steps
+q - quit, skipping this step and all remaining steps
+? - print help";
+
+/// A tool call of the recorded session together with the result it had back then
+pub struct ReplayStep {
+    pub tool_call: ToolCall,
+    pub recorded_output: Option<String>,
+}
+
+/// Load a session from a transcript file, a bare list of messages, or the checkpoint ID
+/// of a transcript saved under `.stakpak/session/history/`
+pub fn load_session_messages(session: &str) -> Result<Vec<ChatMessage>, String> {
+    let path = if Path::new(session).exists() {
+        Path::new(session).to_path_buf()
+    } else {
+        LocalStore::get_local_session_store_path()
+            .join("history")
+            .join(format!("{}.json", session))
+    };
+    let data = std::fs::read_to_string(&path)
+        .map_err(|e| format!("Failed to read session {}: {}", path.display(), e))?;
+
+    if let Ok(transcript) = serde_json::from_str::<SessionTranscript>(&data) {
+        return Ok(transcript.messages);
+    }
+    serde_json::from_str::<Vec<ChatMessage>>(&data)
+        .map_err(|e| format!("Failed to parse session {}: {}", path.display(), e))
+}
+
+/// Tool calls of the session in the order the agent made them, questions to the user
+/// are left out since they only make sense in the original conversation
+pub fn replay_steps(messages: &[ChatMessage]) -> Vec<ReplayStep> {
+    messages
+        .iter()
+        .filter(|message| message.role == Role::Assistant)
+        .flat_map(|message| message.tool_calls.iter().flatten())
+        .filter(|tool_call| tool_call.function.name != ASK_USER_TOOL_NAME)
+        .map(|tool_call| ReplayStep {
+            tool_call: tool_call.clone(),
+            recorded_output: messages
+                .iter()
+                .find(|message| {
+                    message.role == Role::Tool
+                        && message.tool_call_id.as_deref() == Some(tool_call.id.as_str())
+                })
+                .and_then(|message| message.content.as_ref())
+                .map(|content| content.to_string()),
+        })
+        .collect()
+}
+
+fn describe_step(tool_call: &ToolCall) -> String {
+    let arguments: serde_json::Value =
+        serde_json::from_str(&tool_call.function.arguments).unwrap_or_default();
+    // Commands are the common case, show them as they would be typed
+    if let Some(command) = arguments.get("command").and_then(|c| c.as_str()) {
+        return format!("{}: {}", tool_call.function.name, command);
+    }
+    format!(
+        "{} {}",
+        tool_call.function.name,
+        serde_json::to_string_pretty(&arguments).unwrap_or_default()
+    )
+}
+
+/// Re-run the tool calls of a recorded session against the current workspace, asking
+/// before each step, starting at step `from` (1-based)
+pub async fn replay_session(
+    config: AppConfig,
+    session: &str,
+    from: usize,
+    run_all: bool,
+) -> Result<(), String> {
+    let messages = load_session_messages(session)?;
+    let steps = replay_steps(&messages);
+    if steps.is_empty() {
+        println!("The session has no tool calls to replay");
+        return Ok(());
+    }
+    let total = steps.len();
+    let skip = from.saturating_sub(1);
+    if skip >= total {
+        return Err(format!(
+            "The session only has {} steps, cannot start at step {}",
+            total, from
+        ));
+    }
+
+    let sandbox = config.sandbox_profile()?;
+    let dev_container = config.dev_container()?;
+    let command_policy = config.command_policy()?;
+    let bind_address = network::find_available_bind_address_descending().await?;
+    let local_mcp_server_host = format!("http://{}", bind_address);
+    let api_config = ClientConfig {
+        api_key: config.api_key.clone(),
+        api_endpoint: config.api_endpoint.clone(),
+        fallback_endpoints: config.fallback_api_endpoints.clone(),
+    };
+    tokio::spawn(async move {
+        let _ = stakpak_mcp_server::start_server(
+            MCPServerConfig {
+                api: api_config,
+                redact_secrets: true,
+                bind_address,
+                tool_mode: ToolMode::Combined,
+                sandbox,
+                dev_container,
+                command_policy,
+                access: None,
+                tls: None,
+            },
+            None,
+        )
+        .await;
+    });
+
+    let clients = ClientManager::new(local_mcp_server_host, None)
+        .await
+        .map_err(|e| e.to_string())?;
+    let tools_map = clients.get_tools().await.map_err(|e| e.to_string())?;
+
+    let (mut ran, mut skipped, mut failed) = (0, skip, 0);
+    let mut run_all = run_all;
+    for (index, step) in steps.iter().enumerate().skip(skip) {
+        println!(
+            "\n({}/{}) {}",
+            index + 1,
+            total,
+            describe_step(&step.tool_call)
+        );
+
+        if !run_all {
+            let run = loop {
+                print!("Run step {} [y,n,o,a,q,?]? ", index + 1);
+                std::io::stdout().flush().map_err(|e| e.to_string())?;
+                let mut input = String::new();
+                let read = std::io::stdin()
+                    .read_line(&mut input)
+                    .map_err(|e| format!("Failed to read input: {}", e))?;
+                // Treat end of input like quitting the replay
+                if read == 0 {
+                    println!();
+                    break None;
+                }
+
+                match input.trim() {
+                    "y" => break Some(true),
+                    "n" => break Some(false),
+                    "o" => println!(
+                        "{}",
+                        step.recorded_output
+                            .as_deref()
+                            .unwrap_or("No output was recorded for this step")
+                    ),
+                    "a" => {
+                        run_all = true;
+                        break Some(true);
+                    }
+                    "q" => break None,
+                    _ => println!("{}", REPLAY_HELP),
+                }
+            };
+            match run {
+                Some(true) => {}
+                Some(false) => {
+                    skipped += 1;
+                    continue;
+                }
+                None => {
+                    skipped += total - index;
+                    break;
+                }
+            }
+        }
+
+        match run_tool_call(&clients, &tools_map, &step.tool_call).await? {
+            Some(result) => {
+                let output = result
+                    .content
+                    .iter()
+                    .filter_map(|content| content.as_text().map(|t| t.text.clone()))
+                    .collect::<Vec<_>>()
+                    .join("\n");
+                println!("{}", output);
+                ran += 1;
+                if result.is_error.unwrap_or(false) {
+                    failed += 1;
+                }
+            }
+            None => {
+                eprintln!(
+                    "Warning: tool {} is not available, skipping",
+                    step.tool_call.function.name
+                );
+                skipped += 1;
+            }
+        }
+    }
+
+    println!(
+        "\nReplayed {} of {} steps ({} skipped, {} failed)",
+        ran, total, skipped, failed
+    );
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use stakpak_shared::models::integrations::openai::{FunctionCall, MessageContent};
+
+    fn tool_call(id: &str, name: &str) -> ToolCall {
+        ToolCall {
+            id: id.to_string(),
+            r#type: "function".to_string(),
+            function: FunctionCall {
+                name: name.to_string(),
+                arguments: "{}".to_string(),
+            },
+        }
+    }
+
+    #[test]
+    fn test_replay_steps() {
+        let message =
+            |role, content: Option<&str>, tool_calls, tool_call_id: Option<&str>| ChatMessage {
+                role,
+                content: content.map(|c| MessageContent::String(c.to_string())),
+                name: None,
+                tool_calls,
+                tool_call_id: tool_call_id.map(|id| id.to_string()),
+            };
+        let messages = vec![
+            message(Role::User, Some("deploy it"), None, None),
+            message(
+                Role::Assistant,
+                None,
+                Some(vec![
+                    tool_call("1", "run_command"),
+                    tool_call("2", ASK_USER_TOOL_NAME),
+                ]),
+                None,
+            ),
+            message(Role::Tool, Some("applied"), None, Some("1")),
+            message(
+                Role::Assistant,
+                None,
+                Some(vec![tool_call("3", "view")]),
+                None,
+            ),
+        ];
+
+        let steps = replay_steps(&messages);
+        assert_eq!(steps.len(), 2);
+        assert_eq!(steps[0].tool_call.id, "1");
+        assert_eq!(steps[0].recorded_output.as_deref(), Some("applied"));
+        assert_eq!(steps[1].tool_call.id, "3");
+        assert!(steps[1].recorded_output.is_none());
+    }
+}
@@ -0,0 +1,387 @@
+use chrono::{DateTime, Utc};
+use stakpak_shared::command_result::command_failed;
+use stakpak_shared::models::integrations::openai::{ChatMessage, Role, ToolCall};
+use std::collections::HashMap;
+use std::fmt::Write;
+
+pub struct ReportInput {
+    pub session_id: String,
+    pub title: String,
+    pub checkpoint_id: String,
+    pub status: String,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+    pub messages: Vec<ChatMessage>,
+}
+
+const STYLE: &str = r#"
+body { font-family: -apple-system, BlinkMacSystemFont, "Segoe UI", Helvetica, Arial, sans-serif; margin: 0; background: #f6f8fa; color: #1f2328; }
+main { max-width: 980px; margin: 0 auto; padding: 32px 24px; }
+h1 { margin-bottom: 4px; }
+.meta { color: #59636e; font-size: 14px; }
+.cards { display: flex; flex-wrap: wrap; gap: 12px; margin: 24px 0; }
+.card { background: #fff; border: 1px solid #d1d9e0; border-radius: 8px; padding: 12px 16px; min-width: 140px; }
+.card .value { font-size: 22px; font-weight: 600; }
+.card .label { color: #59636e; font-size: 13px; }
+.step { background: #fff; border: 1px solid #d1d9e0; border-radius: 8px; padding: 12px 16px; margin-bottom: 12px; }
+.step .role { font-size: 12px; font-weight: 600; text-transform: uppercase; color: #59636e; }
+.step.user { border-left: 4px solid #0969da; }
+.step.assistant { border-left: 4px solid #8250df; }
+.tool { margin-top: 12px; }
+.tool .name { font-family: monospace; font-weight: 600; }
+.tool.failed .name { color: #d1242f; }
+pre { background: #f6f8fa; border-radius: 6px; padding: 8px 12px; overflow-x: auto; white-space: pre-wrap; word-break: break-word; font-size: 13px; }
+.text { white-space: pre-wrap; }
+.add { color: #116329; background: #dafbe1; display: block; }
+.del { color: #82071e; background: #ffebe9; display: block; }
+ul.findings li { margin-bottom: 6px; }
+"#;
+
+fn escape_html(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    for c in text.chars() {
+        match c {
+            '&' => escaped.push_str("&amp;"),
+            '<' => escaped.push_str("&lt;"),
+            '>' => escaped.push_str("&gt;"),
+            '"' => escaped.push_str("&quot;"),
+            '\'' => escaped.push_str("&#39;"),
+            _ => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+fn tool_arguments(tool_call: &ToolCall) -> serde_json::Value {
+    serde_json::from_str(&tool_call.function.arguments).unwrap_or(serde_json::Value::Null)
+}
+
+fn argument<'a>(arguments: &'a serde_json::Value, key: &str) -> &'a str {
+    arguments.get(key).and_then(|v| v.as_str()).unwrap_or("")
+}
+
+/// Render a file change made by one of the editing tools as a diff, if the tool call is one
+fn render_diff(tool_call: &ToolCall) -> Option<(String, String)> {
+    let arguments = tool_arguments(tool_call);
+    let path = argument(&arguments, "path").to_string();
+
+    let diff_lines = |prefix: &str, class: &str, text: &str| {
+        text.lines()
+            .map(|line| {
+                format!(
+                    r#"<span class="{}">{} {}</span>"#,
+                    class,
+                    prefix,
+                    escape_html(line)
+                )
+            })
+            .collect::<Vec<_>>()
+            .join("")
+    };
+
+    let diff = match tool_call.function.name.as_str() {
+        "str_replace" => format!(
+            "{}{}",
+            diff_lines("-", "del", argument(&arguments, "old_str")),
+            diff_lines("+", "add", argument(&arguments, "new_str"))
+        ),
+        "create" => diff_lines("+", "add", argument(&arguments, "file_text")),
+        "insert" => diff_lines("+", "add", argument(&arguments, "new_str")),
+        _ => return None,
+    };
+
+    Some((path, diff))
+}
+
+fn estimate_tokens(text: &str) -> usize {
+    // Rough estimate of ~4 characters per token, the API does not return usage per checkpoint
+    text.chars().count().div_ceil(4)
+}
+
+pub fn render_html_report(input: &ReportInput) -> String {
+    let results: HashMap<&str, String> = input
+        .messages
+        .iter()
+        .filter(|m| m.role == Role::Tool)
+        .filter_map(|m| {
+            m.tool_call_id.as_deref().map(|id| {
+                (
+                    id,
+                    m.content
+                        .as_ref()
+                        .map(|c| c.to_string())
+                        .unwrap_or_default(),
+                )
+            })
+        })
+        .collect();
+
+    let mut timeline = String::new();
+    let mut findings: Vec<String> = Vec::new();
+    let mut changed_files: Vec<String> = Vec::new();
+    let mut commands = 0;
+    let mut steps = 0;
+    let mut input_tokens = 0;
+    let mut output_tokens = 0;
+
+    for message in &input.messages {
+        let content = message
+            .content
+            .as_ref()
+            .map(|c| c.to_string())
+            .unwrap_or_default();
+
+        match message.role {
+            Role::Assistant => {
+                output_tokens += estimate_tokens(&content);
+                for tool_call in message.tool_calls.as_deref().unwrap_or_default() {
+                    output_tokens += estimate_tokens(&tool_call.function.arguments);
+                }
+            }
+            _ => input_tokens += estimate_tokens(&content),
+        }
+
+        let class = match message.role {
+            Role::User => "user",
+            Role::Assistant => "assistant",
+            // Tool results are rendered next to the tool call that produced them
+            _ => continue,
+        };
+        steps += 1;
+
+        let _ = write!(
+            timeline,
+            r#"<div class="step {}"><div class="role">{} · step {}</div>"#,
+            class, message.role, steps
+        );
+        if !content.trim().is_empty() {
+            let _ = write!(
+                timeline,
+                r#"<div class="text">{}</div>"#,
+                escape_html(content.trim())
+            );
+        }
+
+        for tool_call in message.tool_calls.as_deref().unwrap_or_default() {
+            let result = results.get(tool_call.id.as_str());
+            let failed = result.is_some_and(|r| command_failed(r));
+
+            let _ = write!(
+                timeline,
+                r#"<div class="tool{}"><div class="name">{}</div>"#,
+                if failed { " failed" } else { "" },
+                escape_html(&tool_call.function.name)
+            );
+
+            if tool_call.function.name == "run_command" {
+                commands += 1;
+                let command = argument(&tool_arguments(tool_call), "command").to_string();
+                let _ = write!(timeline, "<pre>$ {}</pre>", escape_html(&command));
+                if failed {
+                    findings.push(format!(
+                        "Command failed: <code>{}</code>",
+                        escape_html(&command)
+                    ));
+                }
+            } else if let Some((path, diff)) = render_diff(tool_call) {
+                if !changed_files.contains(&path) {
+                    changed_files.push(path.clone());
+                }
+                let _ = write!(
+                    timeline,
+                    "<div>{}</div><pre>{}</pre>",
+                    escape_html(&path),
+                    diff
+                );
+            } else {
+                let _ = write!(
+                    timeline,
+                    "<pre>{}</pre>",
+                    escape_html(&tool_call.function.arguments)
+                );
+            }
+
+            match result {
+                Some(result) => {
+                    let _ = write!(timeline, "<pre>{}</pre>", escape_html(result.trim()));
+                }
+                None => {
+                    findings.push(format!(
+                        "Tool call <code>{}</code> has no recorded result (pending or rejected)",
+                        escape_html(&tool_call.function.name)
+                    ));
+                }
+            }
+            timeline.push_str("</div>");
+        }
+        timeline.push_str("</div>");
+    }
+
+    let findings_html = if findings.is_empty() {
+        "<p>No failed commands or pending tool calls.</p>".to_string()
+    } else {
+        format!(
+            r#"<ul class="findings">{}</ul>"#,
+            findings
+                .iter()
+                .map(|f| format!("<li>{}</li>", f))
+                .collect::<String>()
+        )
+    };
+
+    let files_html = if changed_files.is_empty() {
+        "<p>No files changed.</p>".to_string()
+    } else {
+        format!(
+            "<ul>{}</ul>",
+            changed_files
+                .iter()
+                .map(|f| format!("<li><code>{}</code></li>", escape_html(f)))
+                .collect::<String>()
+        )
+    };
+
+    let card = |value: String, label: &str| {
+        format!(
+            r#"<div class="card"><div class="value">{}</div><div class="label">{}</div></div>"#,
+            value, label
+        )
+    };
+
+    format!(
+        r#"<!DOCTYPE html>
+<html lang="en">
+<head>
+<meta charset="utf-8">
+<title>{title} · Stakpak session report</title>
+<style>{style}</style>
+</head>
+<body>
+<main>
+<h1>{title}</h1>
+<div class="meta">Session {session_id} · Checkpoint {checkpoint_id} · Status {status}</div>
+<div class="meta">Started {created_at} · Last updated {updated_at} · Generated by stakpak v{version}</div>
+<div class="cards">{cards}</div>
+<h2>Findings</h2>
+{findings}
+<h2>Files changed</h2>
+{files}
+<h2>Timeline</h2>
+{timeline}
+</main>
+</body>
+</html>
+"#,
+        title = escape_html(&input.title),
+        style = STYLE,
+        session_id = escape_html(&input.session_id),
+        checkpoint_id = escape_html(&input.checkpoint_id),
+        status = escape_html(&input.status),
+        created_at = input.created_at.format("%Y-%m-%d %H:%M:%S UTC"),
+        updated_at = input.updated_at.format("%Y-%m-%d %H:%M:%S UTC"),
+        version = env!("CARGO_PKG_VERSION"),
+        cards = [
+            card(steps.to_string(), "steps"),
+            card(commands.to_string(), "commands run"),
+            card(changed_files.len().to_string(), "files changed"),
+            card(format!("~{}", input_tokens), "input tokens (est.)"),
+            card(format!("~{}", output_tokens), "output tokens (est.)"),
+        ]
+        .join(""),
+        findings = findings_html,
+        files = files_html,
+        timeline = timeline,
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use stakpak_shared::models::integrations::openai::{FunctionCall, MessageContent};
+
+    fn message(role: Role, content: &str) -> ChatMessage {
+        ChatMessage {
+            role,
+            content: Some(MessageContent::String(content.to_string())),
+            name: None,
+            tool_calls: None,
+            tool_call_id: None,
+        }
+    }
+
+    fn tool_call(id: &str, name: &str, arguments: serde_json::Value) -> ToolCall {
+        ToolCall {
+            id: id.to_string(),
+            r#type: "function".to_string(),
+            function: FunctionCall {
+                name: name.to_string(),
+                arguments: arguments.to_string(),
+            },
+        }
+    }
+
+    fn tool_result(id: &str, content: &str) -> ChatMessage {
+        ChatMessage {
+            tool_call_id: Some(id.to_string()),
+            ..message(Role::Tool, content)
+        }
+    }
+
+    #[test]
+    fn test_escape_html() {
+        assert_eq!(
+            escape_html(r#"<a href="x">Tom & 'Jerry'</a>"#),
+            "&lt;a href=&quot;x&quot;&gt;Tom &amp; &#39;Jerry&#39;&lt;/a&gt;"
+        );
+    }
+
+    #[test]
+    fn test_render_report() {
+        let mut assistant = message(Role::Assistant, "Fixing the <bucket> policy");
+        assistant.tool_calls = Some(vec![
+            tool_call(
+                "call_1",
+                "run_command",
+                serde_json::json!({"command": "terraform plan"}),
+            ),
+            tool_call(
+                "call_2",
+                "str_replace",
+                serde_json::json!({"path": "main.tf", "old_str": "acl = \"public\"", "new_str": "acl = \"private\""}),
+            ),
+            tool_call(
+                "call_3",
+                "create",
+                serde_json::json!({"path": "outputs.tf", "file_text": "output \"id\" {}"}),
+            ),
+        ]);
+        let messages = vec![
+            message(Role::User, "Make the bucket private"),
+            assistant,
+            tool_result("call_1", "Error: access denied\nCommand exited with code 1"),
+            tool_result("call_2", "Replaced 1 occurrence"),
+        ];
+        let report = render_html_report(&ReportInput {
+            session_id: "session".to_string(),
+            title: "Private <bucket>".to_string(),
+            checkpoint_id: "checkpoint".to_string(),
+            status: "COMPLETE".to_string(),
+            created_at: Utc::now(),
+            updated_at: Utc::now(),
+            messages,
+        });
+
+        assert!(report.contains("<title>Private &lt;bucket&gt; · Stakpak session report</title>"));
+        assert!(report.contains("Fixing the &lt;bucket&gt; policy"));
+        assert!(report.contains("Command failed: <code>terraform plan</code>"));
+        assert!(report.contains(r#"<div class="tool failed"><div class="name">run_command</div>"#));
+        assert!(report.contains("Tool call <code>create</code> has no recorded result"));
+        assert!(report.contains(r#"<span class="del">- acl = &quot;public&quot;</span>"#));
+        assert!(report.contains(r#"<span class="add">+ acl = &quot;private&quot;</span>"#));
+        assert!(report.contains("<li><code>main.tf</code></li><li><code>outputs.tf</code></li>"));
+        assert!(report.contains(r#"<div class="value">2</div><div class="label">steps</div>"#));
+        assert!(
+            report.contains(r#"<div class="value">1</div><div class="label">commands run</div>"#)
+        );
+    }
+}
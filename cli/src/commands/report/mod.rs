@@ -0,0 +1,37 @@
+use crate::commands::agent::run::checkpoint::get_messages_from_checkpoint_output;
+use stakpak_api::Client;
+use uuid::Uuid;
+
+mod html;
+pub use html::*;
+
+/// Generate a standalone HTML report for an agent session and write it to `out`
+pub async fn generate_report(client: &Client, session_id: &str, out: &str) -> Result<(), String> {
+    let session_id = Uuid::parse_str(session_id)
+        .map_err(|_| format!("Invalid session ID '{}' - must be a valid UUID", session_id))?;
+
+    let session = client.get_agent_session(session_id).await?;
+    let checkpoint = client
+        .get_agent_session_latest_checkpoint(session_id)
+        .await?;
+    let messages = get_messages_from_checkpoint_output(&checkpoint.output);
+
+    if messages.is_empty() {
+        return Err("Session has no conversation messages to report".into());
+    }
+
+    let report = render_html_report(&ReportInput {
+        session_id: session.id.to_string(),
+        title: session.title,
+        checkpoint_id: checkpoint.checkpoint.id.to_string(),
+        status: checkpoint.checkpoint.status.to_string(),
+        created_at: session.created_at,
+        updated_at: session.updated_at,
+        messages,
+    });
+
+    std::fs::write(out, report).map_err(|e| format!("Failed to write report to {}: {}", out, e))?;
+    println!("Session report saved to {}", out);
+
+    Ok(())
+}
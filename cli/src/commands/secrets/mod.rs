@@ -0,0 +1,223 @@
+use crate::utils::checkpoint_labels::resolve_checkpoint_id;
+use clap::{ArgGroup, Subcommand};
+use stakpak_mcp_server::secret_manager::SecretManager;
+use stakpak_shared::secrets::redaction_bundle::RedactionBundle;
+use stakpak_shared::secrets::vault::{SecretVault, secret_placeholder, stored_secret_names};
+use std::io::Read;
+
+const PASSPHRASE_ENV: &str = "STAKPAK_SECRETS_PASSPHRASE";
+
+mod scan;
+pub use scan::*;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ScanFormat {
+    Text,
+    Json,
+    Sarif,
+}
+
+impl std::fmt::Display for ScanFormat {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let s = match self {
+            ScanFormat::Text => "text",
+            ScanFormat::Json => "json",
+            ScanFormat::Sarif => "sarif",
+        };
+        write!(f, "{}", s)
+    }
+}
+
+impl std::str::FromStr for ScanFormat {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "text" => Ok(ScanFormat::Text),
+            "json" => Ok(ScanFormat::Json),
+            "sarif" => Ok(ScanFormat::Sarif),
+            _ => Err(format!("Invalid scan format: {}", s)),
+        }
+    }
+}
+
+#[derive(Subcommand, PartialEq)]
+pub enum SecretsCommands {
+    /// Scan the workspace for secrets
+    Scan {
+        /// Directory to scan
+        #[arg(long, short)]
+        dir: Option<String>,
+        /// Output format (text, json, sarif)
+        #[arg(long, short, default_value_t = ScanFormat::Text)]
+        format: ScanFormat,
+        /// Number of findings allowed before exiting with an error
+        #[arg(long, default_value_t = 0)]
+        max_findings: usize,
+        /// Only scan the staged content of files in the git index
+        #[arg(long, default_value_t = false)]
+        staged: bool,
+    },
+    /// Store a secret locally, the agent only ever sees it as a [SECRET:NAME] placeholder
+    #[command(group(ArgGroup::new("source").required(true).args(["from_stdin", "from_file"])))]
+    Add {
+        /// Secret name, referenced as [SECRET:NAME]
+        #[arg(long)]
+        name: String,
+        /// Read the secret value from stdin
+        #[arg(long, default_value_t = false)]
+        from_stdin: bool,
+        /// Read the secret value from a file
+        #[arg(long)]
+        from_file: Option<String>,
+    },
+    /// List the names of stored secrets
+    List,
+    /// Remove a stored secret
+    Remove {
+        /// Secret name
+        name: String,
+    },
+    /// Export the session redaction map for a checkpoint, encrypted with a passphrase, so the
+    /// checkpoint can be resumed on another machine
+    ExportSession {
+        /// Checkpoint ID or label the session is resumed from
+        checkpoint_id: String,
+        /// File to write, defaults to <checkpoint ID>.secrets.json
+        #[arg(long, short)]
+        output: Option<String>,
+    },
+    /// Import a redaction map exported with export-session into the session of this workspace
+    ImportSession {
+        /// File written by export-session
+        file: String,
+    },
+}
+
+/// Passphrase from STAKPAK_SECRETS_PASSPHRASE, or asked for on the terminal
+fn read_passphrase(confirm: bool) -> Result<String, String> {
+    if let Ok(passphrase) = std::env::var(PASSPHRASE_ENV) {
+        return Ok(passphrase);
+    }
+    let passphrase = rpassword::prompt_password("Passphrase: ")
+        .map_err(|e| format!("Failed to read passphrase: {}", e))?;
+    if confirm {
+        let confirmation = rpassword::prompt_password("Repeat passphrase: ")
+            .map_err(|e| format!("Failed to read passphrase: {}", e))?;
+        if confirmation != passphrase {
+            return Err("Passphrases do not match".to_string());
+        }
+    }
+    Ok(passphrase)
+}
+
+/// Drop the trailing newline left by `echo` or editors, keeping any other whitespace
+fn trim_secret_value(value: &str) -> &str {
+    value.trim_end_matches(['\n', '\r'])
+}
+
+impl SecretsCommands {
+    pub async fn run(self) -> Result<(), String> {
+        match self {
+            SecretsCommands::Scan {
+                dir,
+                format,
+                max_findings,
+                staged,
+            } => {
+                let findings = if staged {
+                    scan_staged(dir.as_deref())?
+                } else {
+                    scan_directory(&dir.unwrap_or_else(|| ".".into()))?
+                };
+
+                println!("{}", format_findings(&findings, format)?);
+
+                if findings.len() > max_findings {
+                    return Err(format!(
+                        "Found {} secrets (allowed: {})",
+                        findings.len(),
+                        max_findings
+                    ));
+                }
+            }
+            SecretsCommands::Add {
+                name,
+                from_stdin,
+                from_file,
+            } => {
+                let value = match from_file {
+                    Some(path) => std::fs::read_to_string(&path)
+                        .map_err(|e| format!("Failed to read {}: {}", path, e))?,
+                    None if from_stdin => {
+                        let mut value = String::new();
+                        std::io::stdin()
+                            .read_to_string(&mut value)
+                            .map_err(|e| format!("Failed to read secret from stdin: {}", e))?;
+                        value
+                    }
+                    None => return Err("Provide --from-stdin or --from-file".to_string()),
+                };
+
+                let mut vault = SecretVault::open()?;
+                vault.add(&name, trim_secret_value(&value))?;
+                println!(
+                    "Stored secret {}, reference it as {}",
+                    name,
+                    secret_placeholder(&name)
+                );
+            }
+            SecretsCommands::List => {
+                let names = stored_secret_names()?;
+                if names.is_empty() {
+                    println!("No secrets stored");
+                }
+                for name in names {
+                    println!("{}", secret_placeholder(&name));
+                }
+            }
+            SecretsCommands::Remove { name } => {
+                if !SecretVault::open()?.remove(&name)? {
+                    return Err(format!("No secret named {}", name));
+                }
+                println!("Removed secret {}", name);
+            }
+            SecretsCommands::ExportSession {
+                checkpoint_id,
+                output,
+            } => {
+                let checkpoint_id = resolve_checkpoint_id(&checkpoint_id)?;
+                let redaction_map = SecretManager::new(true).load_session_redaction_map();
+                if redaction_map.is_empty() {
+                    return Err("The session of this workspace has no redacted secrets".to_string());
+                }
+                let bundle =
+                    RedactionBundle::seal(&redaction_map, &checkpoint_id, &read_passphrase(true)?)?;
+                let output = output.unwrap_or_else(|| format!("{}.secrets.json", checkpoint_id));
+                let content = serde_json::to_string_pretty(&bundle).map_err(|e| e.to_string())?;
+                std::fs::write(&output, content)
+                    .map_err(|e| format!("Failed to write {}: {}", output, e))?;
+                println!(
+                    "Exported {} redacted secrets to {}, resume the checkpoint elsewhere after `stakpak secrets import-session {}`",
+                    redaction_map.len(),
+                    output,
+                    output
+                );
+            }
+            SecretsCommands::ImportSession { file } => {
+                let content = std::fs::read_to_string(&file)
+                    .map_err(|e| format!("Failed to read {}: {}", file, e))?;
+                let bundle: RedactionBundle = serde_json::from_str(&content)
+                    .map_err(|e| format!("Failed to parse {}: {}", file, e))?;
+                let redaction_map = bundle.open(&read_passphrase(false)?)?;
+                SecretManager::new(true).add_to_session_redaction_map(&redaction_map);
+                println!(
+                    "Imported {} redacted secrets, resume with `stakpak -c {}`",
+                    redaction_map.len(),
+                    bundle.checkpoint_id
+                );
+            }
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,132 @@
+use stakpak_api::{Client, GetUsageResponse, UsageQuota};
+
+const BAR_WIDTH: usize = 30;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum UsageFormat {
+    Text,
+    Json,
+}
+
+impl std::fmt::Display for UsageFormat {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let s = match self {
+            UsageFormat::Text => "text",
+            UsageFormat::Json => "json",
+        };
+        write!(f, "{}", s)
+    }
+}
+
+impl std::str::FromStr for UsageFormat {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "text" => Ok(UsageFormat::Text),
+            "json" => Ok(UsageFormat::Json),
+            _ => Err(format!("Invalid usage format: {}", s)),
+        }
+    }
+}
+
+fn quota_label(name: &str) -> String {
+    match name {
+        "api_calls" => "API calls".to_string(),
+        "tokens" => "Tokens".to_string(),
+        "agent_minutes" => "Agent minutes".to_string(),
+        _ => name.replace('_', " "),
+    }
+}
+
+/// Format `n` with thousands separators
+fn format_count(n: u64) -> String {
+    let digits = n.to_string();
+    let mut output = String::new();
+    for (i, c) in digits.chars().enumerate() {
+        if i > 0 && (digits.len() - i) % 3 == 0 {
+            output.push(',');
+        }
+        output.push(c);
+    }
+    output
+}
+
+fn render_quota(quota: &UsageQuota) -> String {
+    let label = quota_label(&quota.name);
+    match quota.limit {
+        Some(limit) if limit > 0 => {
+            let ratio = (quota.used as f64 / limit as f64).min(1.0);
+            let filled = (ratio * BAR_WIDTH as f64).round() as usize;
+            format!(
+                "{:<14} [{}{}] {:>3}%  {} / {}{}",
+                label,
+                "█".repeat(filled),
+                "░".repeat(BAR_WIDTH - filled),
+                (quota.used as f64 / limit as f64 * 100.0).floor() as u64,
+                format_count(quota.used),
+                format_count(limit),
+                if quota.used >= limit {
+                    "  (limit reached)"
+                } else {
+                    ""
+                }
+            )
+        }
+        _ => format!("{:<14} {}  (unlimited)", label, format_count(quota.used)),
+    }
+}
+
+pub fn render_usage(usage: &GetUsageResponse) -> String {
+    let mut output = format!(
+        "Plan: {} ({} to {})\n\n",
+        usage.plan,
+        usage.period_start.format("%Y-%m-%d"),
+        usage.period_end.format("%Y-%m-%d")
+    );
+    for quota in &usage.quotas {
+        output.push_str(&render_quota(quota));
+        output.push('\n');
+    }
+    output
+}
+
+pub async fn show_usage(client: &Client, format: UsageFormat) -> Result<(), String> {
+    let usage = client.get_usage().await?;
+    match format {
+        UsageFormat::Text => print!("{}", render_usage(&usage)),
+        UsageFormat::Json => println!(
+            "{}",
+            serde_json::to_string_pretty(&usage).map_err(|e| e.to_string())?
+        ),
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_render_quota() {
+        let quota = |used, limit| UsageQuota {
+            name: "api_calls".to_string(),
+            used,
+            limit,
+        };
+
+        assert_eq!(
+            render_quota(&quota(2500, Some(10000))),
+            format!(
+                "API calls      [{}{}]  25%  2,500 / 10,000",
+                "█".repeat(8),
+                "░".repeat(22)
+            )
+        );
+        assert!(render_quota(&quota(12000, Some(10000))).ends_with("(limit reached)"));
+        assert_eq!(
+            render_quota(&quota(1234567, None)),
+            "API calls      1,234,567  (unlimited)"
+        );
+    }
+}
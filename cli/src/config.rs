@@ -1,14 +1,152 @@
 use config::{Config, ConfigError, Environment, File};
 use serde::{Deserialize, Serialize};
 use stakpak_api::ClientConfig;
+use stakpak_api::provider::LLMProviderConfig;
+use stakpak_mcp_server::{CommandPolicySource, DevContainer, SandboxProfile};
+use stakpak_shared::workspace::WorkspaceRoot;
+use stakpak_tui::ConfirmationTimeout;
+
+use crate::utils::checkpoint_replay::CheckpointReplayConfig;
+use crate::utils::context_ranking::ContextRankingConfig;
+use crate::utils::history_compaction::HistoryCompactionConfig;
+use crate::utils::markdown::{MarkdownRenderer, RenderMode};
+use crate::utils::pull_request::PullRequestConfig;
+use crate::utils::run_budget::BudgetConfig;
+use crate::utils::slack::SlackConfig;
+use std::collections::BTreeMap;
 use std::fs::{create_dir_all, write};
 use std::path::Path;
 
+/// Layout version of the config file written by this CLI
+pub const CONFIG_VERSION: u32 = 1;
+
+/// `MIGRATIONS[n]` upgrades a config file from version n to n + 1
+const MIGRATIONS: [fn(&mut toml::Table); CONFIG_VERSION as usize] = [migrate_v0_to_v1];
+
+/// Configs written before versioning only need the version recorded
+fn migrate_v0_to_v1(_config: &mut toml::Table) {}
+
+/// Result of upgrading a config file to [`CONFIG_VERSION`]
+pub struct ConfigMigration {
+    pub from: u32,
+    pub to: u32,
+    pub migrated: toml::Table,
+    /// Copy of the file before the migration, not set for dry runs
+    pub backup_path: Option<String>,
+}
+
+/// Upgrade a parsed config file, `None` when it is already current
+pub fn migrate_config_table(config: &toml::Table) -> Result<Option<ConfigMigration>, String> {
+    let from = match config.get("version") {
+        None => 0,
+        Some(version) => version
+            .as_integer()
+            .and_then(|version| u32::try_from(version).ok())
+            .ok_or_else(|| format!("Invalid config version: {}", version))?,
+    };
+    if from > CONFIG_VERSION {
+        return Err(format!(
+            "{} was written by a newer version of stakpak (config version {}, this version supports up to {}), update stakpak to use it",
+            get_config_path(),
+            from,
+            CONFIG_VERSION
+        ));
+    }
+    if from == CONFIG_VERSION {
+        return Ok(None);
+    }
+
+    let mut migrated = config.clone();
+    for migration in &MIGRATIONS[from as usize..] {
+        migration(&mut migrated);
+    }
+    migrated.insert(
+        "version".to_string(),
+        toml::Value::Integer(CONFIG_VERSION.into()),
+    );
+    Ok(Some(ConfigMigration {
+        from,
+        to: CONFIG_VERSION,
+        migrated,
+        backup_path: None,
+    }))
+}
+
 #[derive(Serialize, Deserialize, Clone, Debug)]
 pub struct AppConfig {
+    /// Layout version of the config file, see [`CONFIG_VERSION`]
+    #[serde(default = "default_config_version")]
+    pub version: u32,
     pub api_endpoint: String,
+    /// Endpoints requests fail over to, in order, when `api_endpoint` is unreachable
+    #[serde(default)]
+    pub fallback_api_endpoints: Vec<String>,
     pub api_key: Option<String>,
     pub mcp_server_host: Option<String>,
+    /// Run an extra completion to triage failed commands (costs additional tokens)
+    #[serde(default)]
+    pub error_triage: bool,
+    /// Add `.stakpak/` to the repository .gitignore when session data is first written
+    #[serde(default = "default_manage_gitignore")]
+    pub manage_gitignore: bool,
+    /// How markdown output is rendered (styled, no-color, raw)
+    #[serde(default)]
+    pub render_mode: RenderMode,
+    /// Slack channel used to approve tool calls in async mode
+    pub slack: Option<SlackConfig>,
+    /// Branch and remote used by `--create-pr`
+    pub pull_request: Option<PullRequestConfig>,
+    /// Name of the sandbox profile applied to commands run by the agent
+    pub sandbox: Option<String>,
+    /// Custom sandbox profiles, `workspace` and `workspace-network` are built in
+    #[serde(default)]
+    pub sandbox_profiles: BTreeMap<String, SandboxProfile>,
+    /// Where commands run by the agent are executed, `local` or `devcontainer`
+    pub target: Option<String>,
+    /// TOML file with the allow, deny and blocked variable rules of `run_command`,
+    /// reloaded when it changes
+    pub command_policy: Option<String>,
+    /// Track tool usage per workspace and list the most used tools first to the model
+    #[serde(default = "default_tool_usage_hints")]
+    pub tool_usage_hints: bool,
+    /// Upload redacted command outputs too long for the model so the agent can search them
+    #[serde(default)]
+    pub upload_large_outputs: bool,
+    /// Answer repeated identical read-only commands from a short-lived cache by default
+    #[serde(default)]
+    pub cache_command_results: bool,
+    /// What file-writing tools do when they would write new plaintext secrets to disk,
+    /// `warn` (the default), `block` or `off`
+    pub secret_writes: Option<String>,
+    /// Fail on API responses with fields the CLI does not know and save them for debugging
+    #[serde(default)]
+    pub strict_api: bool,
+    /// Take a default action on tool calls left unconfirmed in the TUI for too long
+    pub confirmation_timeout: Option<ConfirmationTimeout>,
+    /// Seconds after which a call of the tool is abandoned, by tool name. For `run_command`
+    /// it is the default timeout of commands, which are killed and return their partial output.
+    #[serde(default)]
+    pub tool_timeouts: BTreeMap<String, u64>,
+    /// Read-only tool calls of one step run concurrently, up to this many at a time
+    #[serde(default = "default_agent_concurrency")]
+    pub agent_concurrency: usize,
+    /// Directories tools can address as `root://<name>/...`, replaced by roots given with `-w`
+    #[serde(default)]
+    pub workspace_roots: Vec<WorkspaceRoot>,
+    /// Model the agent runs on as `<provider>/<model>`, the Stakpak API when unset
+    pub model: Option<String>,
+    /// LLM endpoints models can be selected from, `openai`, `anthropic` and `ollama` are built in
+    #[serde(default)]
+    pub llm_providers: BTreeMap<String, LLMProviderConfig>,
+    /// Send the workspace files a local embedding model ranks most relevant with the first prompt
+    pub context_ranking: Option<ContextRankingConfig>,
+    /// Messages replayed into context when resuming from a checkpoint, all when unset
+    pub checkpoint_replay: Option<CheckpointReplayConfig>,
+    /// When older turns of interactive sessions are summarized to stay within the context
+    #[serde(default)]
+    pub history_compaction: HistoryCompactionConfig,
+    /// Token and API call budgets shared by every run of the user
+    pub budget: Option<BudgetConfig>,
 }
 
 impl From<AppConfig> for ClientConfig {
@@ -16,10 +154,27 @@ impl From<AppConfig> for ClientConfig {
         ClientConfig {
             api_key: config.api_key.clone(),
             api_endpoint: config.api_endpoint.clone(),
+            fallback_endpoints: config.fallback_api_endpoints.clone(),
         }
     }
 }
 
+fn default_config_version() -> u32 {
+    CONFIG_VERSION
+}
+
+fn default_manage_gitignore() -> bool {
+    true
+}
+
+fn default_tool_usage_hints() -> bool {
+    true
+}
+
+fn default_agent_concurrency() -> usize {
+    1
+}
+
 fn get_config_path() -> String {
     format!(
         "{}/.stakpak/config.toml",
@@ -32,7 +187,7 @@ impl AppConfig {
         let config_path: String = get_config_path();
 
         let config = Config::builder()
-            .set_default("api_endpoint", "https://apiv2.stakpak.dev")?
+            .set_default("api_endpoint", stakpak_api::builder::DEFAULT_API_ENDPOINT)?
             .add_source(Environment::with_prefix("STAKPAK"))
             .add_source(File::with_name(&config_path).required(false))
             .build()
@@ -41,6 +196,100 @@ impl AppConfig {
         config.try_deserialize()
     }
 
+    /// Upgrade `~/.stakpak/config.toml` to the current version, keeping a backup of the
+    /// original next to it. Nothing is written when `dry_run` is set.
+    pub fn migrate_file(dry_run: bool) -> Result<Option<ConfigMigration>, String> {
+        let config_path = get_config_path();
+        let Ok(content) = std::fs::read_to_string(&config_path) else {
+            return Ok(None);
+        };
+        let config = content
+            .parse::<toml::Table>()
+            .map_err(|e| format!("Failed to parse {}: {}", config_path, e))?;
+        let Some(mut migration) = migrate_config_table(&config)? else {
+            return Ok(None);
+        };
+        if dry_run {
+            return Ok(Some(migration));
+        }
+
+        let backup_path = format!("{}.v{}.bak", config_path, migration.from);
+        std::fs::copy(&config_path, &backup_path)
+            .map_err(|e| format!("Failed to back up {}: {}", config_path, e))?;
+        let migrated = toml::to_string_pretty(&migration.migrated).map_err(|e| e.to_string())?;
+        write(&config_path, migrated)
+            .map_err(|e| format!("Failed to write {}: {}", config_path, e))?;
+        migration.backup_path = Some(backup_path);
+        Ok(Some(migration))
+    }
+
+    /// Settings written in `~/.stakpak/config.toml`, without defaults or environment overrides
+    pub fn file_settings() -> toml::Table {
+        std::fs::read_to_string(get_config_path())
+            .ok()
+            .and_then(|content| content.parse::<toml::Table>().ok())
+            .unwrap_or_default()
+    }
+
+    pub fn config_path() -> String {
+        get_config_path()
+    }
+
+    /// Resolve the selected sandbox profile, if any
+    pub fn sandbox_profile(&self) -> Result<Option<SandboxProfile>, String> {
+        let Some(name) = &self.sandbox else {
+            return Ok(None);
+        };
+        if let Some(profile) = self.sandbox_profiles.get(name) {
+            return Ok(Some(profile.clone()));
+        }
+        match name.as_str() {
+            "workspace" => Ok(Some(SandboxProfile::default())),
+            "workspace-network" => Ok(Some(SandboxProfile {
+                allow_network: true,
+                ..Default::default()
+            })),
+            _ => Err(format!(
+                "Unknown sandbox profile '{}', define it under [sandbox_profiles.{}] in ~/.stakpak/config.toml",
+                name, name
+            )),
+        }
+    }
+
+    /// Find the dev container commands run in when the `devcontainer` target is selected
+    pub fn dev_container(&self) -> Result<Option<DevContainer>, String> {
+        match self.target.as_deref() {
+            None | Some("local") => Ok(None),
+            Some("devcontainer") => {
+                if self.sandbox.is_some() {
+                    return Err(
+                        "Sandbox profiles only apply to local commands, drop --sandbox to use the devcontainer target"
+                            .to_string(),
+                    );
+                }
+                let workspace = std::env::current_dir()
+                    .map_err(|e| format!("Failed to get current directory: {}", e))?;
+                DevContainer::detect(&workspace).map(Some)
+            }
+            Some(target) => Err(format!(
+                "Unknown target '{}', use local or devcontainer",
+                target
+            )),
+        }
+    }
+
+    /// Load the command policy file, if one is configured
+    pub fn command_policy(&self) -> Result<Option<CommandPolicySource>, String> {
+        self.command_policy
+            .as_ref()
+            .map(CommandPolicySource::load)
+            .transpose()
+    }
+
+    pub fn markdown_renderer(&self) -> MarkdownRenderer {
+        MarkdownRenderer::new(self.render_mode)
+    }
+
     pub fn save(&self) -> Result<(), String> {
         let config_path: String = get_config_path();
 
@@ -53,3 +302,32 @@ impl AppConfig {
         Ok(())
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_migrate_config_table() {
+        let unversioned: toml::Table = "api_key = \"key\"".parse().unwrap();
+        let migration = migrate_config_table(&unversioned).unwrap().unwrap();
+        assert_eq!((migration.from, migration.to), (0, CONFIG_VERSION));
+        assert_eq!(
+            migration
+                .migrated
+                .get("version")
+                .and_then(|v| v.as_integer()),
+            Some(CONFIG_VERSION.into())
+        );
+        assert_eq!(
+            migration.migrated.get("api_key").and_then(|v| v.as_str()),
+            Some("key")
+        );
+
+        let current: toml::Table = format!("version = {}", CONFIG_VERSION).parse().unwrap();
+        assert!(migrate_config_table(&current).unwrap().is_none());
+
+        let newer: toml::Table = format!("version = {}", CONFIG_VERSION + 1).parse().unwrap();
+        assert!(migrate_config_table(&newer).is_err());
+    }
+}
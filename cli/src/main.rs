@@ -1,21 +1,38 @@
 use clap::Parser;
-use std::{env, io::Write, path::Path};
+use std::{env, io::Write};
 
 mod commands;
 mod config;
+mod settings;
 mod utils;
 
 use commands::{
     Commands,
     agent::{
         self,
-        run::{RunAsyncConfig, RunInteractiveConfig, RunNonInteractiveConfig},
+        run::{
+            RunAsyncConfig, RunInteractiveConfig, RunNonInteractiveConfig,
+            tooling::set_tool_timeouts,
+        },
     },
+    config::ConfigCommands,
 };
 use config::AppConfig;
+use stakpak_api::decode::set_strict_mode;
+use stakpak_mcp_server::command_cache::set_command_cache;
+use stakpak_mcp_server::command_timeout::set_command_timeout;
+use stakpak_mcp_server::output_upload::set_output_uploads;
+use stakpak_mcp_server::secret_write_guard::set_secret_write_policy;
+use stakpak_shared::local_store::LocalStore;
+use stakpak_shared::models::integrations::openai::Role;
+use stakpak_shared::workspace::{WorkspaceRoot, parse_workspace_root, set_workspace_roots};
 use tracing_subscriber::{layer::SubscriberExt, util::SubscriberInitExt};
 use utils::check_update::check_update;
+use utils::checkpoint_labels::resolve_checkpoint_id;
+use utils::checkpoint_replay::parse_role;
 use utils::local_context::analyze_local_context;
+use utils::markdown::RenderMode;
+use utils::tool_usage::set_tool_usage_hints;
 
 #[derive(Parser, PartialEq)]
 #[command(name = "stakpak")]
@@ -29,22 +46,55 @@ struct Cli {
     #[arg(short = 'a', long = "async", default_value_t = false)]
     r#async: bool,
 
-    /// Resume agent session at a specific checkpoint
+    /// Resume agent session at a specific checkpoint ID or label
     #[arg(short = 'c', long = "checkpoint")]
     checkpoint_id: Option<String>,
 
-    /// Run the agent in a specific directory
-    #[arg(short = 'w', long = "workdir")]
-    workdir: Option<String>,
+    /// Roles whose messages are replayed when resuming a checkpoint, e.g. user,assistant
+    #[arg(long = "replay-roles", value_delimiter = ',', value_parser = parse_role)]
+    replay_roles: Option<Vec<Role>>,
+
+    /// Tool outputs replayed in full when resuming a checkpoint, counted from the most recent
+    #[arg(long = "replay-tool-outputs")]
+    replay_tool_outputs: Option<usize>,
+
+    /// Run the agent in a specific directory, repeat to add workspace roots tools address
+    /// as root://<name>/... ([NAME=]PATH, append :ro to forbid edits)
+    #[arg(short = 'w', long = "workdir", value_parser = parse_workspace_root)]
+    workdir: Vec<WorkspaceRoot>,
 
     /// Approve the tool call in non-interactive mode
     #[arg(long = "approve", default_value_t = false)]
     approve: bool,
 
+    /// Require approval of tool calls through Slack in async mode
+    #[arg(long = "slack-approval", default_value_t = false)]
+    slack_approval: bool,
+
+    /// Commit the changes of an async run to a new branch and open a pull request for them
+    #[arg(long = "create-pr", default_value_t = false)]
+    create_pr: bool,
+
+    /// Compact the history of an async run once it outgrows [history_compaction] max_tokens
+    #[arg(long = "compact", default_value_t = false, requires = "async")]
+    compact: bool,
+
+    /// Keep the prompt as it is whenever the history of an async run is compacted
+    #[arg(long = "pin", default_value_t = false, requires = "compact")]
+    pin: bool,
+
     /// Enable verbose output in non-interactive mode
     #[arg(long = "verbose", default_value_t = false)]
     verbose: bool,
 
+    /// Render the answer as markdown in non-interactive mode instead of printing it as JSON
+    #[arg(long = "markdown", default_value_t = false)]
+    markdown: bool,
+
+    /// Print the token usage and estimated cost of the session to stderr in non-interactive mode
+    #[arg(long = "show-usage", default_value_t = false)]
+    show_usage: bool,
+
     /// Enable debug output
     #[arg(long = "debug", default_value_t = false)]
     debug: bool,
@@ -53,6 +103,35 @@ struct Cli {
     #[arg(long = "disable-secret-redaction", default_value_t = false)]
     disable_secret_redaction: bool,
 
+    /// Do not track tool usage or reorder tools by how often they are used in this workspace
+    #[arg(long = "disable-tool-hints", global = true, default_value_t = false)]
+    disable_tool_hints: bool,
+
+    /// Upload redacted command outputs too long for the model, so the agent can search them
+    /// with the search_output tool instead of only seeing their tail
+    #[arg(long = "upload-large-outputs", global = true, default_value_t = false)]
+    upload_large_outputs: bool,
+
+    /// Disable colors and text styles in rendered output
+    #[arg(long = "no-color", global = true, default_value_t = false)]
+    no_color: bool,
+
+    /// Print markdown output as-is without rendering it
+    #[arg(long = "raw", global = true, default_value_t = false)]
+    raw: bool,
+
+    /// Sandbox profile confining commands run by the agent (workspace, workspace-network or a custom profile)
+    #[arg(long = "sandbox", global = true)]
+    sandbox: Option<String>,
+
+    /// Where commands run by the agent are executed: local (default) or devcontainer, the running dev container or docker-compose service of the workspace
+    #[arg(long = "target", global = true)]
+    target: Option<String>,
+
+    /// Model the agent runs on as <provider>/<model>, e.g. openai/gpt-4o, anthropic/claude-sonnet-4-0 or ollama/llama3.1
+    #[arg(long = "model", global = true)]
+    model: Option<String>,
+
     /// Prompt to run the agent with in non-interactive mode
     #[clap(required_if_eq("print", "true"))]
     prompt: Option<String>,
@@ -61,13 +140,41 @@ struct Cli {
     command: Option<Commands>,
 }
 
+/// Make root paths absolute, so they still resolve after changing into the first root
+fn absolute_roots(roots: Vec<WorkspaceRoot>) -> Result<Vec<WorkspaceRoot>, String> {
+    let mut absolute: Vec<WorkspaceRoot> = Vec::new();
+    for mut root in roots {
+        if absolute.iter().any(|other| other.name == root.name) {
+            return Err(format!(
+                "Workspace root name '{}' is used more than once",
+                root.name
+            ));
+        }
+        root.path = std::fs::canonicalize(&root.path).map_err(|e| {
+            format!(
+                "Failed to open workspace root {}: {}",
+                root.path.display(),
+                e
+            )
+        })?;
+        absolute.push(root);
+    }
+    Ok(absolute)
+}
+
 #[tokio::main]
 async fn main() {
     let cli = Cli::parse();
 
-    if let Some(workdir) = cli.workdir {
-        let workdir = Path::new(&workdir);
-        if let Err(e) = env::set_current_dir(workdir) {
+    let cli_roots = match absolute_roots(cli.workdir) {
+        Ok(roots) => roots,
+        Err(e) => {
+            eprintln!("{}", e);
+            std::process::exit(1);
+        }
+    };
+    if let Some(root) = cli_roots.first() {
+        if let Err(e) = env::set_current_dir(&root.path) {
             eprintln!("Failed to set current directory: {}", e);
             std::process::exit(1);
         }
@@ -83,6 +190,34 @@ async fn main() {
             .init();
     }
 
+    // `stakpak config check` reports these itself
+    if !matches!(cli.command, Some(Commands::Config(_))) {
+        for issue in settings::check_env() {
+            eprintln!("Warning: {}", issue);
+        }
+    }
+
+    // `stakpak config migrate` migrates, or previews the migration, itself
+    if !matches!(
+        cli.command,
+        Some(Commands::Config(ConfigCommands::Migrate { .. }))
+    ) {
+        match AppConfig::migrate_file(false) {
+            Ok(Some(migration)) => eprintln!(
+                "Migrated {} from version {} to {}, backup saved to {}",
+                AppConfig::config_path(),
+                migration.from,
+                migration.to,
+                migration.backup_path.unwrap_or_default()
+            ),
+            Ok(None) => {}
+            Err(e) => {
+                eprintln!("{}", e);
+                std::process::exit(1);
+            }
+        }
+    }
+
     match AppConfig::load() {
         Ok(mut config) => {
             if config.api_key.is_none() {
@@ -114,6 +249,58 @@ async fn main() {
 
                 config = updated_config;
             }
+            config.render_mode = RenderMode::resolve(config.render_mode, cli.no_color, cli.raw);
+            LocalStore::set_manage_gitignore(config.manage_gitignore);
+            if cli.disable_tool_hints {
+                config.tool_usage_hints = false;
+            }
+            set_tool_usage_hints(config.tool_usage_hints);
+            if cli.upload_large_outputs {
+                config.upload_large_outputs = true;
+            }
+            set_output_uploads(config.upload_large_outputs);
+            set_command_cache(config.cache_command_results);
+            if let Some(secs) = config.tool_timeouts.get("run_command") {
+                set_command_timeout(*secs);
+            }
+            set_tool_timeouts(config.tool_timeouts.clone());
+            if let Some(policy) = &config.secret_writes {
+                match policy.parse() {
+                    Ok(policy) => set_secret_write_policy(policy),
+                    Err(e) => eprintln!("Warning: {}", e),
+                }
+            }
+            set_strict_mode(config.strict_api);
+            if cli.sandbox.is_some() {
+                config.sandbox = cli.sandbox;
+            }
+            if cli.target.is_some() {
+                config.target = cli.target;
+            }
+            if cli.model.is_some() {
+                config.model = cli.model;
+            }
+            if cli.replay_roles.is_some() || cli.replay_tool_outputs.is_some() {
+                let replay = config.checkpoint_replay.get_or_insert_default();
+                if cli.replay_roles.is_some() {
+                    replay.roles = cli.replay_roles;
+                }
+                if cli.replay_tool_outputs.is_some() {
+                    replay.tool_outputs = cli.replay_tool_outputs;
+                }
+            }
+            let workspace_roots = if cli_roots.is_empty() {
+                absolute_roots(config.workspace_roots.clone())
+            } else {
+                Ok(cli_roots)
+            };
+            match workspace_roots {
+                Ok(roots) => set_workspace_roots(roots),
+                Err(e) => {
+                    eprintln!("{}", e);
+                    std::process::exit(1);
+                }
+            }
             match cli.command {
                 Some(command) => {
                     let _ = check_update(format!("v{}", env!("CARGO_PKG_VERSION")).as_str()).await;
@@ -126,6 +313,15 @@ async fn main() {
                     }
                 }
                 None => {
+                    let checkpoint_id =
+                        match cli.checkpoint_id.as_deref().map(resolve_checkpoint_id) {
+                            Some(Ok(checkpoint_id)) => Some(checkpoint_id),
+                            Some(Err(e)) => {
+                                eprintln!("{}", e);
+                                std::process::exit(1);
+                            }
+                            None => None,
+                        };
                     let local_context = analyze_local_context().await.ok();
 
                     match (cli.r#async, cli.print || cli.approve) {
@@ -135,7 +331,11 @@ async fn main() {
                             RunAsyncConfig {
                                 prompt: cli.prompt.unwrap_or_default(),
                                 verbose: cli.verbose,
-                                checkpoint_id: cli.checkpoint_id,
+                                slack_approval: cli.slack_approval,
+                                create_pr: cli.create_pr,
+                                compact_history: cli.compact,
+                                pin_prompt: cli.pin,
+                                checkpoint_id,
                                 local_context,
                                 redact_secrets: !cli.disable_secret_redaction,
                             },
@@ -156,7 +356,9 @@ async fn main() {
                                 prompt: cli.prompt.unwrap_or_default(),
                                 approve: cli.approve,
                                 verbose: cli.verbose,
-                                checkpoint_id: cli.checkpoint_id,
+                                show_usage: cli.show_usage,
+                                markdown: cli.markdown,
+                                checkpoint_id,
                                 local_context,
                                 redact_secrets: !cli.disable_secret_redaction,
                             },
@@ -174,9 +376,10 @@ async fn main() {
                         (false, false) => match agent::run::run_interactive(
                             config,
                             RunInteractiveConfig {
-                                checkpoint_id: cli.checkpoint_id,
+                                checkpoint_id,
                                 local_context,
                                 redact_secrets: !cli.disable_secret_redaction,
+                                initial_prompt: None,
                             },
                         )
                         .await
@@ -0,0 +1,410 @@
+use serde_json::Value;
+use stakpak_mcp_server::secret_write_guard::SecretWritePolicy;
+
+/// Prefix of the environment variables overriding `~/.stakpak/config.toml` settings
+const CONFIG_ENV_PREFIX: &str = "STAKPAK_";
+
+/// Environment variable the CLI honors
+pub struct EnvVar {
+    pub name: &'static str,
+    pub description: &'static str,
+    /// AppConfig field the variable overrides, if it is a config setting
+    pub config_key: Option<&'static str>,
+    /// Never print the value, only whether it is set
+    pub secret: bool,
+    pub validate: fn(&str) -> Result<(), String>,
+}
+
+fn any_value(_: &str) -> Result<(), String> {
+    Ok(())
+}
+
+fn url(value: &str) -> Result<(), String> {
+    reqwest::Url::parse(value)
+        .map(|_| ())
+        .map_err(|e| format!("expected a URL ({})", e))
+}
+
+fn boolean(value: &str) -> Result<(), String> {
+    match value.to_lowercase().as_str() {
+        "true" | "false" | "1" | "0" | "yes" | "no" | "on" | "off" => Ok(()),
+        _ => Err("expected true or false".to_string()),
+    }
+}
+
+fn positive_integer(value: &str) -> Result<(), String> {
+    match value.parse::<usize>() {
+        Ok(n) if n > 0 => Ok(()),
+        _ => Err("expected a positive integer".to_string()),
+    }
+}
+
+fn render_mode(value: &str) -> Result<(), String> {
+    match value {
+        "styled" | "no-color" | "raw" => Ok(()),
+        _ => Err("expected one of: styled, no-color, raw".to_string()),
+    }
+}
+
+fn secret_writes(value: &str) -> Result<(), String> {
+    value
+        .parse::<SecretWritePolicy>()
+        .map(|_| ())
+        .map_err(|_| "expected one of: warn, block, off".to_string())
+}
+
+fn target(value: &str) -> Result<(), String> {
+    match value {
+        "local" | "devcontainer" => Ok(()),
+        _ => Err("expected one of: local, devcontainer".to_string()),
+    }
+}
+
+pub const ENV_VARS: &[EnvVar] = &[
+    EnvVar {
+        name: "STAKPAK_API_KEY",
+        description: "API key used to authenticate with Stakpak",
+        config_key: Some("api_key"),
+        secret: true,
+        validate: any_value,
+    },
+    EnvVar {
+        name: "STAKPAK_API_ENDPOINT",
+        description: "Stakpak API endpoint",
+        config_key: Some("api_endpoint"),
+        secret: false,
+        validate: url,
+    },
+    EnvVar {
+        name: "STAKPAK_MCP_SERVER_HOST",
+        description: "External MCP server used instead of the built-in one",
+        config_key: Some("mcp_server_host"),
+        secret: false,
+        validate: url,
+    },
+    EnvVar {
+        name: "STAKPAK_ERROR_TRIAGE",
+        description: "Triage failed commands with an extra completion",
+        config_key: Some("error_triage"),
+        secret: false,
+        validate: boolean,
+    },
+    EnvVar {
+        name: "STAKPAK_MANAGE_GITIGNORE",
+        description: "Add .stakpak/ to the repository .gitignore",
+        config_key: Some("manage_gitignore"),
+        secret: false,
+        validate: boolean,
+    },
+    EnvVar {
+        name: "STAKPAK_RENDER_MODE",
+        description: "How markdown output is rendered (styled, no-color, raw)",
+        config_key: Some("render_mode"),
+        secret: false,
+        validate: render_mode,
+    },
+    EnvVar {
+        name: "STAKPAK_SANDBOX",
+        description: "Sandbox profile applied to commands run by the agent",
+        config_key: Some("sandbox"),
+        secret: false,
+        validate: any_value,
+    },
+    EnvVar {
+        name: "STAKPAK_TARGET",
+        description: "Where commands run by the agent are executed (local, devcontainer)",
+        config_key: Some("target"),
+        secret: false,
+        validate: target,
+    },
+    EnvVar {
+        name: "STAKPAK_COMMAND_POLICY",
+        description: "TOML file with the allow and deny rules of commands run by the agent",
+        config_key: Some("command_policy"),
+        secret: false,
+        validate: any_value,
+    },
+    EnvVar {
+        name: "STAKPAK_TOOL_USAGE_HINTS",
+        description: "Order tools by how often they are used in the workspace",
+        config_key: Some("tool_usage_hints"),
+        secret: false,
+        validate: boolean,
+    },
+    EnvVar {
+        name: "STAKPAK_UPLOAD_LARGE_OUTPUTS",
+        description: "Upload redacted outputs too long for the model for search_output",
+        config_key: Some("upload_large_outputs"),
+        secret: false,
+        validate: boolean,
+    },
+    EnvVar {
+        name: "STAKPAK_CACHE_COMMAND_RESULTS",
+        description: "Reuse recent outputs of identical read-only commands",
+        config_key: Some("cache_command_results"),
+        secret: false,
+        validate: boolean,
+    },
+    EnvVar {
+        name: "STAKPAK_SECRET_WRITES",
+        description: "Warn, block or allow file writes adding plaintext secrets",
+        config_key: Some("secret_writes"),
+        secret: false,
+        validate: secret_writes,
+    },
+    EnvVar {
+        name: "STAKPAK_STRICT_API",
+        description: "Fail on unexpected API responses and save them under .stakpak/session/debug",
+        config_key: Some("strict_api"),
+        secret: false,
+        validate: boolean,
+    },
+    EnvVar {
+        name: "STAKPAK_AGENT_CONCURRENCY",
+        description: "Read-only tool calls of one step run at the same time",
+        config_key: Some("agent_concurrency"),
+        secret: false,
+        validate: positive_integer,
+    },
+    EnvVar {
+        name: "STAKPAK_SECRETS_PASSPHRASE",
+        description: "Passphrase of session redaction maps exported for checkpoints, asked for when unset",
+        config_key: None,
+        secret: true,
+        validate: any_value,
+    },
+    EnvVar {
+        name: "STAKPAK_MCP_AUTH_TOKEN",
+        description: "Bearer token required by `stakpak mcp`, see --auth-token",
+        config_key: None,
+        secret: true,
+        validate: any_value,
+    },
+    EnvVar {
+        name: "GITHUB_TOKEN",
+        description: "GitHub token used by --create-pr to open pull requests",
+        config_key: None,
+        secret: true,
+        validate: any_value,
+    },
+    EnvVar {
+        name: "GH_TOKEN",
+        description: "Fallback for GITHUB_TOKEN",
+        config_key: None,
+        secret: true,
+        validate: any_value,
+    },
+    EnvVar {
+        name: "GITLAB_TOKEN",
+        description: "GitLab token used by --create-pr to open merge requests",
+        config_key: None,
+        secret: true,
+        validate: any_value,
+    },
+    EnvVar {
+        name: "HTTPS_PROXY",
+        description: "Proxy for HTTPS requests to the API",
+        config_key: None,
+        secret: true,
+        validate: url,
+    },
+    EnvVar {
+        name: "HTTP_PROXY",
+        description: "Proxy for HTTP requests",
+        config_key: None,
+        secret: true,
+        validate: url,
+    },
+    EnvVar {
+        name: "ALL_PROXY",
+        description: "Proxy for requests not covered by HTTP(S)_PROXY",
+        config_key: None,
+        secret: true,
+        validate: url,
+    },
+    EnvVar {
+        name: "NO_PROXY",
+        description: "Hosts requests go to without a proxy",
+        config_key: None,
+        secret: false,
+        validate: any_value,
+    },
+    EnvVar {
+        name: "NO_COLOR",
+        description: "Disable colors in rendered output",
+        config_key: None,
+        secret: false,
+        validate: any_value,
+    },
+    EnvVar {
+        name: "PAGER",
+        description: "Pager used to show long output in the TUI",
+        config_key: None,
+        secret: false,
+        validate: any_value,
+    },
+    EnvVar {
+        name: "RUST_LOG",
+        description: "Log filter used with --debug",
+        config_key: None,
+        secret: false,
+        validate: any_value,
+    },
+];
+
+fn edit_distance(a: &str, b: &str) -> usize {
+    let b: Vec<char> = b.chars().collect();
+    let mut previous: Vec<usize> = (0..=b.len()).collect();
+    for (i, ca) in a.chars().enumerate() {
+        let mut current = vec![i + 1];
+        for (j, cb) in b.iter().enumerate() {
+            let substitution = previous[j] + usize::from(ca != *cb);
+            current.push(substitution.min(previous[j + 1] + 1).min(current[j] + 1));
+        }
+        previous = current;
+    }
+    previous[b.len()]
+}
+
+/// Known variable an unknown `STAKPAK_*` variable was most likely meant to be
+fn closest_env_var(name: &str) -> Option<&'static str> {
+    ENV_VARS
+        .iter()
+        .filter(|var| var.config_key.is_some())
+        .map(|var| (edit_distance(name, var.name), var.name))
+        .filter(|(distance, _)| *distance <= 3)
+        .min_by_key(|(distance, _)| *distance)
+        .map(|(_, name)| name)
+}
+
+/// Problems with the environment variables set for `vars`: invalid values and unknown
+/// `STAKPAK_*` variables, which are most likely misspelled
+pub fn check_env_vars(vars: &[(String, String)]) -> Vec<String> {
+    let mut issues = Vec::new();
+    for (name, value) in vars {
+        match ENV_VARS.iter().find(|var| var.name == name.to_uppercase()) {
+            Some(var) => {
+                if let Err(e) = (var.validate)(value) {
+                    issues.push(format!("{} has an invalid value: {}", name, e));
+                }
+            }
+            None if name.starts_with(CONFIG_ENV_PREFIX) => {
+                issues.push(match closest_env_var(name) {
+                    Some(suggestion) => {
+                        format!("Unknown variable {}, did you mean {}?", name, suggestion)
+                    }
+                    None => format!("Unknown variable {}", name),
+                })
+            }
+            None => {}
+        }
+    }
+    issues
+}
+
+/// Check the variables of the current process, see [`check_env_vars`]
+pub fn check_env() -> Vec<String> {
+    check_env_vars(&std::env::vars().collect::<Vec<_>>())
+}
+
+/// Effective value of a variable and where it comes from
+pub struct EnvVarStatus {
+    pub name: &'static str,
+    pub description: &'static str,
+    pub value: Option<String>,
+    pub source: &'static str,
+}
+
+fn display_value(var: &EnvVar, value: String) -> String {
+    if var.secret && !value.is_empty() {
+        "(set)".to_string()
+    } else {
+        value
+    }
+}
+
+/// Effective value and source of every known variable, `config` being the loaded config
+/// and `config_file` the keys set in `~/.stakpak/config.toml`
+pub fn env_var_statuses(config: &Value, config_file: &toml::Table) -> Vec<EnvVarStatus> {
+    ENV_VARS
+        .iter()
+        .map(|var| {
+            let env_value = std::env::var(var.name)
+                .or_else(|_| std::env::var(var.name.to_lowercase()))
+                .ok();
+            let (value, source) = match var.config_key {
+                // Settings in the config file take precedence over the environment
+                Some(key) => {
+                    let value = config
+                        .get(key)
+                        .filter(|value| !value.is_null())
+                        .map(|value| {
+                            value
+                                .as_str()
+                                .map(|s| s.to_string())
+                                .unwrap_or_else(|| value.to_string())
+                        });
+                    let source = if config_file.contains_key(key) {
+                        "config file"
+                    } else if env_value.is_some() {
+                        "environment"
+                    } else if value.is_some() {
+                        "default"
+                    } else {
+                        "unset"
+                    };
+                    (value, source)
+                }
+                None => {
+                    let source = if env_value.is_some() {
+                        "environment"
+                    } else {
+                        "unset"
+                    };
+                    (env_value, source)
+                }
+            };
+            EnvVarStatus {
+                name: var.name,
+                description: var.description,
+                value: value.map(|value| display_value(var, value)),
+                source,
+            }
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_check_env_vars() {
+        let vars = |pairs: &[(&str, &str)]| {
+            pairs
+                .iter()
+                .map(|(name, value)| (name.to_string(), value.to_string()))
+                .collect::<Vec<_>>()
+        };
+
+        assert!(
+            check_env_vars(&vars(&[
+                ("STAKPAK_API_KEY", "key"),
+                ("HTTPS_PROXY", "http://proxy:3128"),
+                ("HOME", "/root"),
+            ]))
+            .is_empty()
+        );
+        assert_eq!(
+            check_env_vars(&vars(&[(
+                "STAKPAK_API_ENDPIONT",
+                "https://api.stakpak.dev"
+            )])),
+            vec!["Unknown variable STAKPAK_API_ENDPIONT, did you mean STAKPAK_API_ENDPOINT?"]
+        );
+        assert_eq!(
+            check_env_vars(&vars(&[("STAKPAK_AGENT_CONCURRENCY", "0")])),
+            vec!["STAKPAK_AGENT_CONCURRENCY has an invalid value: expected a positive integer"]
+        );
+    }
+}
@@ -0,0 +1,196 @@
+use chrono::{DateTime, Duration, Utc};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use stakpak_api::{AccountError, Client, ClientConfig, GetMyAccountResponse};
+use std::path::PathBuf;
+
+/// How long account info is trusted before it is fetched again
+const ACCOUNT_CACHE_TTL_HOURS: i64 = 24;
+
+/// Account info of the last API key that was validated, kept in `~/.stakpak/account_cache.json`
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct AccountCache {
+    /// Hash of the API key and endpoint the account belongs to, so the cache never
+    /// answers for a different key
+    pub key_hash: String,
+    pub account: GetMyAccountResponse,
+    pub verified_at: DateTime<Utc>,
+}
+
+/// Account info along with where it came from
+#[derive(Debug, Clone)]
+pub struct AccountInfo {
+    pub account: GetMyAccountResponse,
+    pub verified_at: DateTime<Utc>,
+    /// Set when the info comes from the cache because the API could not be reached
+    pub warning: Option<String>,
+}
+
+impl AccountInfo {
+    pub fn to_text(&self) -> String {
+        let mut text = self.account.to_text();
+        let age = Utc::now() - self.verified_at;
+        if age >= Duration::minutes(1) {
+            text.push_str(&format!("\nLast verified: {} ago", format_age(age)));
+        }
+        text
+    }
+}
+
+fn account_cache_path() -> PathBuf {
+    PathBuf::from(format!(
+        "{}/.stakpak/account_cache.json",
+        std::env::var("HOME").unwrap_or_default()
+    ))
+}
+
+fn key_hash(config: &ClientConfig) -> String {
+    let mut hasher = Sha256::new();
+    hasher.update(config.api_endpoint.as_bytes());
+    hasher.update([0]);
+    hasher.update(config.api_key.as_deref().unwrap_or_default().as_bytes());
+    hasher
+        .finalize()
+        .iter()
+        .map(|b| format!("{:02x}", b))
+        .collect()
+}
+
+impl AccountCache {
+    pub fn load() -> Option<Self> {
+        std::fs::read_to_string(account_cache_path())
+            .ok()
+            .and_then(|data| serde_json::from_str(&data).ok())
+    }
+
+    pub fn save(&self) -> Result<(), String> {
+        let path = account_cache_path();
+        if let Some(parent) = path.parent() {
+            std::fs::create_dir_all(parent).map_err(|e| e.to_string())?;
+        }
+        let data = serde_json::to_string_pretty(self).map_err(|e| e.to_string())?;
+        std::fs::write(&path, data).map_err(|e| format!("Failed to write account cache: {}", e))
+    }
+
+    pub fn clear() {
+        let _ = std::fs::remove_file(account_cache_path());
+    }
+
+    fn is_fresh(&self, now: DateTime<Utc>) -> bool {
+        now - self.verified_at < Duration::hours(ACCOUNT_CACHE_TTL_HOURS)
+    }
+}
+
+/// Cached entry for the API key in `config`, if any
+fn cached_for(cache: Option<AccountCache>, config: &ClientConfig) -> Option<AccountCache> {
+    let key_hash = key_hash(config);
+    cache.filter(|cache| cache.key_hash == key_hash)
+}
+
+/// Account of the current API key. Answers from the cache while it is fresh, unless
+/// `refresh` is set, and falls back to it when the API cannot be reached. A key rejected
+/// by the API is reported as an error so expired keys surface before the first prompt.
+pub async fn get_account(
+    client: &Client,
+    config: &ClientConfig,
+    refresh: bool,
+) -> Result<AccountInfo, String> {
+    let cached = cached_for(AccountCache::load(), config);
+    if !refresh {
+        if let Some(cache) = cached.as_ref().filter(|cache| cache.is_fresh(Utc::now())) {
+            return Ok(AccountInfo {
+                account: cache.account.clone(),
+                verified_at: cache.verified_at,
+                warning: None,
+            });
+        }
+    }
+
+    match client.check_account().await {
+        Ok(account) => {
+            let cache = AccountCache {
+                key_hash: key_hash(config),
+                account,
+                verified_at: Utc::now(),
+            };
+            // Not being able to cache only costs a round-trip next time
+            let _ = cache.save();
+            Ok(AccountInfo {
+                account: cache.account,
+                verified_at: cache.verified_at,
+                warning: None,
+            })
+        }
+        Err(AccountError::Rejected(message)) => {
+            AccountCache::clear();
+            Err(format!(
+                "Your API key was rejected ({}). It may have expired or been revoked, create a new one and run `stakpak login --api-key <key>`",
+                message
+            ))
+        }
+        Err(AccountError::Unreachable(message)) => match cached {
+            Some(cache) => Ok(AccountInfo {
+                warning: Some(format!(
+                    "Could not reach Stakpak ({}), using account info verified {} ago",
+                    message,
+                    format_age(Utc::now() - cache.verified_at)
+                )),
+                account: cache.account,
+                verified_at: cache.verified_at,
+            }),
+            None => Err(message),
+        },
+    }
+}
+
+fn format_age(age: Duration) -> String {
+    if age.num_days() > 0 {
+        format!("{}d", age.num_days())
+    } else if age.num_hours() > 0 {
+        format!("{}h", age.num_hours())
+    } else {
+        format!("{}m", age.num_minutes().max(0))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn config(api_key: &str) -> ClientConfig {
+        ClientConfig {
+            api_key: Some(api_key.to_string()),
+            api_endpoint: "https://apiv2.stakpak.dev".to_string(),
+            fallback_endpoints: Vec::new(),
+        }
+    }
+
+    fn cache(config: &ClientConfig, verified_at: DateTime<Utc>) -> AccountCache {
+        AccountCache {
+            key_hash: key_hash(config),
+            account: GetMyAccountResponse {
+                username: "jane".to_string(),
+                id: "1".to_string(),
+                first_name: "Jane".to_string(),
+                last_name: "Doe".to_string(),
+            },
+            verified_at,
+        }
+    }
+
+    #[test]
+    fn test_cache_only_answers_for_the_same_key() {
+        let entry = cache(&config("key-a"), Utc::now());
+        assert!(cached_for(Some(entry.clone()), &config("key-a")).is_some());
+        assert!(cached_for(Some(entry), &config("key-b")).is_none());
+    }
+
+    #[test]
+    fn test_cache_expires_after_ttl() {
+        let now = Utc::now();
+        assert!(cache(&config("key"), now - Duration::hours(2)).is_fresh(now));
+        assert!(!cache(&config("key"), now - Duration::hours(25)).is_fresh(now));
+        assert_eq!(format_age(Duration::hours(25)), "1d");
+        assert_eq!(format_age(Duration::minutes(90)), "1h");
+    }
+}
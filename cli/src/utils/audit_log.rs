@@ -0,0 +1,69 @@
+use chrono::{DateTime, Utc};
+use serde::Serialize;
+use stakpak_shared::local_store::LocalStore;
+use stakpak_shared::models::integrations::openai::ToolCall;
+
+const AUDIT_LOG_FILE: &str = "audit.jsonl";
+
+/// Decision taken on a tool call without the user, one JSON document per line
+#[derive(Serialize, Debug)]
+pub struct AuditEntry {
+    pub timestamp: DateTime<Utc>,
+    pub tool_call_id: String,
+    pub tool: String,
+    pub arguments: String,
+    pub decision: String,
+    pub reason: String,
+}
+
+impl AuditEntry {
+    pub fn new(tool_call: &ToolCall, decision: &str, reason: String) -> Self {
+        Self {
+            timestamp: Utc::now(),
+            tool_call_id: tool_call.id.clone(),
+            tool: tool_call.function.name.clone(),
+            arguments: tool_call.function.arguments.clone(),
+            decision: decision.to_string(),
+            reason,
+        }
+    }
+}
+
+/// Append `entry` to the session audit log
+pub fn record_audit_entry(entry: &AuditEntry) -> Result<(), String> {
+    let line = serde_json::to_string(entry).map_err(|e| e.to_string())?;
+    LocalStore::update_session_data(AUDIT_LOG_FILE, |log| {
+        let mut log = log.unwrap_or_default();
+        log.push_str(&line);
+        log.push('\n');
+        Ok((log, ()))
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use stakpak_shared::models::integrations::openai::FunctionCall;
+
+    #[test]
+    fn test_audit_entry() {
+        let tool_call = ToolCall {
+            id: "call_1".to_string(),
+            r#type: "function".to_string(),
+            function: FunctionCall {
+                name: "run_command".to_string(),
+                arguments: r#"{"command":"ls"}"#.to_string(),
+            },
+        };
+        let entry = AuditEntry::new(&tool_call, "skipped", "No answer within 30s".to_string());
+        let line: serde_json::Value =
+            serde_json::from_str(&serde_json::to_string(&entry).unwrap()).unwrap();
+
+        assert_eq!(line["tool_call_id"], "call_1");
+        assert_eq!(line["tool"], "run_command");
+        assert_eq!(line["arguments"], r#"{"command":"ls"}"#);
+        assert_eq!(line["decision"], "skipped");
+        assert_eq!(line["reason"], "No answer within 30s");
+        assert!(line["timestamp"].is_string());
+    }
+}
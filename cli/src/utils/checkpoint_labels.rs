@@ -0,0 +1,130 @@
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::path::PathBuf;
+use uuid::Uuid;
+
+#[derive(Serialize, Deserialize, Default, Debug)]
+pub struct CheckpointLabels {
+    /// Label to the checkpoint ID it points at
+    pub labels: BTreeMap<String, Uuid>,
+}
+
+fn labels_path() -> PathBuf {
+    PathBuf::from(format!(
+        "{}/.stakpak/checkpoint_labels.json",
+        std::env::var("HOME").unwrap_or_default()
+    ))
+}
+
+fn validate_label(label: &str) -> Result<(), String> {
+    if label.is_empty() {
+        return Err("Checkpoint label cannot be empty".to_string());
+    }
+    if label.chars().any(char::is_whitespace) {
+        return Err(format!(
+            "Invalid checkpoint label '{}' - labels cannot contain whitespace",
+            label
+        ));
+    }
+    if Uuid::parse_str(label).is_ok() {
+        return Err(format!(
+            "Invalid checkpoint label '{}' - labels cannot be UUIDs",
+            label
+        ));
+    }
+    Ok(())
+}
+
+impl CheckpointLabels {
+    pub fn load() -> Self {
+        std::fs::read_to_string(labels_path())
+            .ok()
+            .and_then(|data| serde_json::from_str(&data).ok())
+            .unwrap_or_default()
+    }
+
+    pub fn save(&self) -> Result<(), String> {
+        let path = labels_path();
+        if let Some(parent) = path.parent() {
+            std::fs::create_dir_all(parent).map_err(|e| e.to_string())?;
+        }
+        let data = serde_json::to_string_pretty(self).map_err(|e| e.to_string())?;
+        std::fs::write(&path, data).map_err(|e| format!("Failed to write checkpoint labels: {}", e))
+    }
+
+    /// Labels pointing at the given checkpoint
+    pub fn labels_for(&self, checkpoint_id: &Uuid) -> Vec<&str> {
+        self.labels
+            .iter()
+            .filter(|(_, id)| *id == checkpoint_id)
+            .map(|(label, _)| label.as_str())
+            .collect()
+    }
+}
+
+/// Point `label` at a checkpoint, moving it if it was already used for another one
+pub fn label_checkpoint(checkpoint_id: &str, label: &str) -> Result<(), String> {
+    let label = label.trim();
+    validate_label(label)?;
+    let checkpoint_uuid = Uuid::parse_str(checkpoint_id).map_err(|_| {
+        format!(
+            "Invalid checkpoint ID '{}' - must be a valid UUID",
+            checkpoint_id
+        )
+    })?;
+
+    let mut store = CheckpointLabels::load();
+    store.labels.insert(label.to_string(), checkpoint_uuid);
+    store.save()
+}
+
+/// Resolve a checkpoint reference that is either a checkpoint ID or a label
+pub fn resolve_checkpoint_id(checkpoint_ref: &str) -> Result<String, String> {
+    if Uuid::parse_str(checkpoint_ref).is_ok() {
+        return Ok(checkpoint_ref.to_string());
+    }
+    CheckpointLabels::load()
+        .labels
+        .get(checkpoint_ref)
+        .map(|id| id.to_string())
+        .ok_or_else(|| {
+            format!(
+                "Unknown checkpoint '{}' - expected a checkpoint ID or a label set with `stakpak agent label`",
+                checkpoint_ref
+            )
+        })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_validate_label() {
+        assert!(validate_label("before-upgrade").is_ok());
+        assert!(validate_label("").is_err());
+        assert!(validate_label("before upgrade").is_err());
+        assert!(validate_label(&Uuid::new_v4().to_string()).is_err());
+    }
+
+    #[test]
+    fn test_labels_for() {
+        let (first, second) = (Uuid::new_v4(), Uuid::new_v4());
+        let store = CheckpointLabels {
+            labels: BTreeMap::from([
+                ("stable".to_string(), first),
+                ("before-upgrade".to_string(), first),
+                ("latest".to_string(), second),
+            ]),
+        };
+        assert_eq!(store.labels_for(&first), vec!["before-upgrade", "stable"]);
+        assert_eq!(store.labels_for(&second), vec!["latest"]);
+        assert!(store.labels_for(&Uuid::new_v4()).is_empty());
+    }
+
+    #[test]
+    fn test_checkpoint_ids_resolve_to_themselves() {
+        let id = Uuid::new_v4().to_string();
+        assert_eq!(resolve_checkpoint_id(&id), Ok(id));
+    }
+}
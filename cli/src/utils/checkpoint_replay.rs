@@ -0,0 +1,141 @@
+use serde::{Deserialize, Serialize};
+use stakpak_shared::models::integrations::openai::{ChatMessage, MessageContent, Role};
+
+const OMITTED_OUTPUT: &str = "[Output omitted when resuming from the checkpoint]";
+
+/// Which messages of a checkpoint are replayed into context when resuming it, configured
+/// under `[checkpoint_replay]`. Long sessions are cheaper to resume without old tool outputs.
+#[derive(Serialize, Deserialize, Clone, Debug, Default, PartialEq)]
+pub struct CheckpointReplayConfig {
+    /// Roles whose messages are replayed, all when unset
+    pub roles: Option<Vec<Role>>,
+    /// Tool outputs replayed in full, counted from the most recent, all when unset
+    pub tool_outputs: Option<usize>,
+}
+
+/// Parse a message role given on the command line, e.g. `assistant`
+pub fn parse_role(role: &str) -> Result<Role, String> {
+    serde_json::from_value(serde_json::Value::String(role.to_lowercase())).map_err(|_| {
+        format!(
+            "Unknown role '{}', use system, user, assistant or tool",
+            role
+        )
+    })
+}
+
+impl CheckpointReplayConfig {
+    fn replays(&self, role: &Role) -> bool {
+        self.roles.as_ref().is_none_or(|roles| roles.contains(role))
+    }
+
+    /// Drop the messages of roles not replayed and elide old tool outputs. Tool calls and
+    /// their results are always kept, with their text omitted, so every call still has a
+    /// result. The last message is kept as is, it is what the resumed session continues.
+    pub fn filter(&self, messages: Vec<ChatMessage>) -> Vec<ChatMessage> {
+        let tool_results = messages
+            .iter()
+            .filter(|message| message.role == Role::Tool)
+            .count();
+        let first_full_output = self
+            .tool_outputs
+            .map_or(0, |kept| tool_results.saturating_sub(kept));
+
+        let last = messages.len().saturating_sub(1);
+        let mut tool_result = 0;
+        let mut replayed = Vec::new();
+        for (index, mut message) in messages.into_iter().enumerate() {
+            if index == last {
+                replayed.push(message);
+                break;
+            }
+            match message.role {
+                Role::Tool => {
+                    if !self.replays(&Role::Tool) || tool_result < first_full_output {
+                        message.content = Some(MessageContent::String(OMITTED_OUTPUT.to_string()));
+                    }
+                    tool_result += 1;
+                }
+                _ if self.replays(&message.role) => {}
+                _ if message
+                    .tool_calls
+                    .as_ref()
+                    .is_some_and(|calls| !calls.is_empty()) =>
+                {
+                    message.content = None;
+                }
+                _ => continue,
+            }
+            replayed.push(message);
+        }
+        replayed
+    }
+}
+
+/// Messages of a checkpoint to replay, all of them without a replay config
+pub fn replay_messages(
+    messages: Vec<ChatMessage>,
+    replay: Option<&CheckpointReplayConfig>,
+) -> Vec<ChatMessage> {
+    match replay {
+        Some(replay) => replay.filter(messages),
+        None => messages,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use stakpak_shared::models::integrations::openai::{FunctionCall, ToolCall};
+
+    fn message(role: Role, content: &str) -> ChatMessage {
+        ChatMessage {
+            role,
+            content: Some(MessageContent::String(content.to_string())),
+            name: None,
+            tool_calls: None,
+            tool_call_id: None,
+        }
+    }
+
+    #[test]
+    fn test_filter_keeps_tool_calls_paired() {
+        let mut call = message(Role::Assistant, "checking the pods");
+        call.tool_calls = Some(vec![ToolCall {
+            id: "call_1".to_string(),
+            r#type: "function".to_string(),
+            function: FunctionCall {
+                name: "run_command".to_string(),
+                arguments: "{}".to_string(),
+            },
+        }]);
+        let messages = vec![
+            message(Role::User, "fix the deployment"),
+            call,
+            message(Role::Tool, "pod-0 CrashLoopBackOff"),
+            message(Role::Assistant, "the image tag is wrong"),
+            message(Role::Tool, "deployment patched"),
+            message(Role::User, "thanks"),
+        ];
+        let config = CheckpointReplayConfig {
+            roles: Some(vec![Role::User, Role::Tool]),
+            tool_outputs: Some(1),
+        };
+
+        let replayed = config.filter(messages);
+        let contents: Vec<Option<String>> = replayed
+            .iter()
+            .map(|message| message.content.as_ref().map(|c| c.to_string()))
+            .collect();
+        assert_eq!(
+            contents,
+            vec![
+                Some("fix the deployment".to_string()),
+                None,
+                Some(OMITTED_OUTPUT.to_string()),
+                Some("deployment patched".to_string()),
+                Some("thanks".to_string()),
+            ]
+        );
+        assert_eq!(parse_role("Assistant"), Ok(Role::Assistant));
+    }
+}
@@ -0,0 +1,110 @@
+use regex::Regex;
+use stakpak_shared::workspace::resolve_path;
+use stakpak_tui::Citation;
+
+/// Matches `[path:line]` and `[path:start-end]`, paths may be `root://<name>/<path>`
+const CITATION_PATTERN: &str = r"\[([^\[\]\s]+?):(\d+)(?:-(\d+))?\]";
+
+/// File span cited by an answer
+#[derive(Debug, PartialEq)]
+struct Span {
+    label: String,
+    path: String,
+    start: usize,
+    end: usize,
+}
+
+fn parse_citations(text: &str) -> Result<Vec<Span>, String> {
+    let regex = Regex::new(CITATION_PATTERN).map_err(|e| e.to_string())?;
+    let mut spans: Vec<Span> = Vec::new();
+    for captures in regex.captures_iter(text) {
+        let label = captures[0].trim_matches(['[', ']']).to_string();
+        if spans.iter().any(|span| span.label == label) {
+            continue;
+        }
+        let start = captures[2].parse().unwrap_or(0);
+        let end = captures
+            .get(3)
+            .and_then(|end| end.as_str().parse().ok())
+            .unwrap_or(start);
+        spans.push(Span {
+            label,
+            path: captures[1].to_string(),
+            start,
+            end,
+        });
+    }
+    Ok(spans)
+}
+
+/// Check that the cited file exists and has the cited lines
+fn verify(span: Span) -> Citation {
+    let unverified = |problem: String| Citation {
+        label: span.label.clone(),
+        url: None,
+        problem: Some(problem),
+    };
+    let path = match resolve_path(&span.path) {
+        Ok(path) => path,
+        Err(e) => return unverified(e),
+    };
+    let content = match std::fs::read_to_string(&path) {
+        Ok(content) => content,
+        Err(e) => return unverified(format!("cannot read {}: {}", span.path, e)),
+    };
+    let line_count = content.lines().count();
+    if span.start == 0 || span.end < span.start {
+        return unverified(format!("invalid line range {}-{}", span.start, span.end));
+    }
+    if span.end > line_count {
+        return unverified(format!(
+            "{} has {} lines, line {} does not exist",
+            span.path, line_count, span.end
+        ));
+    }
+    let absolute = path.canonicalize().unwrap_or(path);
+    Citation {
+        url: Some(format!("file://{}", absolute.display())),
+        label: span.label,
+        problem: None,
+    }
+}
+
+/// Workspace spans cited in an answer, each checked against the file it refers to
+pub fn verify_citations(text: &str) -> Vec<Citation> {
+    parse_citations(text)
+        .unwrap_or_default()
+        .into_iter()
+        .map(verify)
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_citations() {
+        let spans = parse_citations(
+            "The listener uses port 8080 [alb.tf:12-14], see [root://infra/vars.tf:3] and [alb.tf:12-14] [not a citation]",
+        )
+        .unwrap_or_default();
+        assert_eq!(
+            spans,
+            vec![
+                Span {
+                    label: "alb.tf:12-14".to_string(),
+                    path: "alb.tf".to_string(),
+                    start: 12,
+                    end: 14,
+                },
+                Span {
+                    label: "root://infra/vars.tf:3".to_string(),
+                    path: "root://infra/vars.tf".to_string(),
+                    start: 3,
+                    end: 3,
+                },
+            ]
+        );
+    }
+}
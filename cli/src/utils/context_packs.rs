@@ -0,0 +1,220 @@
+use serde::{Deserialize, Serialize};
+use std::fmt::Write;
+use std::path::PathBuf;
+
+/// Stack-specific guidance added to the agent's system context when enabled in a workspace
+pub struct ContextPack {
+    pub id: &'static str,
+    pub description: &'static str,
+    pub guidance: &'static [&'static str],
+    /// Sources the agent should prefer over writing resources by hand
+    pub module_sources: &'static [&'static str],
+    /// Commands validating changes before they are applied, run in this order
+    pub validation_commands: &'static [&'static str],
+}
+
+pub const CONTEXT_PACKS: &[ContextPack] = &[
+    ContextPack {
+        id: "eks-terraform",
+        description: "Amazon EKS clusters managed with Terraform",
+        guidance: &[
+            "Manage the cluster, node groups and add-ons in Terraform, never with eksctl or the console, so the state stays the source of truth",
+            "Prefer managed node groups or Karpenter over self-managed node groups, and pin the Kubernetes version explicitly",
+            "Grant pods AWS access with EKS Pod Identity or IRSA, never with node instance role permissions or static keys",
+            "Keep the cluster endpoint private or restrict public_access_cidrs, and enable control plane logging",
+            "Run `aws eks update-kubeconfig --name <cluster>` before kubectl commands and check the current context first",
+        ],
+        module_sources: &[
+            "terraform-aws-modules/eks/aws",
+            "terraform-aws-modules/vpc/aws",
+            "terraform-aws-modules/iam/aws//modules/iam-role-for-service-accounts-eks",
+        ],
+        validation_commands: &[
+            "terraform fmt -check -recursive",
+            "terraform validate",
+            "terraform plan -out=tfplan",
+            "kubectl get nodes",
+        ],
+    },
+    ContextPack {
+        id: "ecs-fargate",
+        description: "Amazon ECS services running on Fargate",
+        guidance: &[
+            "Run services on Fargate in private subnets behind an Application Load Balancer, with assign_public_ip disabled",
+            "Separate the task execution role (pulling images, writing logs) from the task role (what the application may call)",
+            "Read secrets from Secrets Manager or SSM Parameter Store through the task definition `secrets` block, never plain environment variables",
+            "Send container logs to CloudWatch with the awslogs driver and set a retention on the log group",
+            "Enable the deployment circuit breaker with rollback so failed deployments revert on their own",
+        ],
+        module_sources: &[
+            "terraform-aws-modules/ecs/aws",
+            "terraform-aws-modules/alb/aws",
+            "terraform-aws-modules/vpc/aws",
+        ],
+        validation_commands: &[
+            "terraform fmt -check -recursive",
+            "terraform validate",
+            "terraform plan -out=tfplan",
+            "aws ecs describe-services --cluster <cluster> --services <service>",
+        ],
+    },
+    ContextPack {
+        id: "gke",
+        description: "Google Kubernetes Engine clusters",
+        guidance: &[
+            "Prefer Autopilot clusters unless the workload needs node-level control, and use release channels instead of pinned versions",
+            "Create VPC-native clusters with private nodes, and authorize control plane access with master authorized networks",
+            "Grant pods Google Cloud access with Workload Identity, never with exported service account keys",
+            "Run `gcloud container clusters get-credentials <cluster> --region <region>` before kubectl commands and check the current context first",
+        ],
+        module_sources: &[
+            "terraform-google-modules/kubernetes-engine/google",
+            "terraform-google-modules/network/google",
+        ],
+        validation_commands: &[
+            "terraform fmt -check -recursive",
+            "terraform validate",
+            "terraform plan -out=tfplan",
+            "kubectl get nodes",
+        ],
+    },
+    ContextPack {
+        id: "k3s",
+        description: "k3s clusters on bare-metal or self-managed hosts",
+        guidance: &[
+            "Install and upgrade k3s with the official install script pinned to INSTALL_K3S_VERSION, or the system-upgrade-controller",
+            "Keep server options in /etc/rancher/k3s/config.yaml instead of command line flags, so upgrades keep them",
+            "Use the bundled Traefik, ServiceLB and local-path provisioner unless the cluster needs something else, and disable the ones that are replaced",
+            "Back up the datastore (etcd snapshots with `k3s etcd-snapshot`) before upgrades and node changes",
+            "The kubeconfig is at /etc/rancher/k3s/k3s.yaml on server nodes, copy it and replace the server address to use it remotely",
+        ],
+        module_sources: &[
+            "Helm charts from the vendor's repository, installed with k3s HelmChart manifests in /var/lib/rancher/k3s/server/manifests",
+        ],
+        validation_commands: &[
+            "kubectl get nodes -o wide",
+            "kubectl get pods -A --field-selector=status.phase!=Running",
+            "kubectl apply --dry-run=server -f <manifest>",
+        ],
+    },
+];
+
+pub fn find_context_pack(id: &str) -> Result<&'static ContextPack, String> {
+    CONTEXT_PACKS
+        .iter()
+        .find(|pack| pack.id == id)
+        .ok_or_else(|| {
+            format!(
+                "Unknown context pack '{}', available packs: {}",
+                id,
+                CONTEXT_PACKS
+                    .iter()
+                    .map(|pack| pack.id)
+                    .collect::<Vec<_>>()
+                    .join(", ")
+            )
+        })
+}
+
+impl ContextPack {
+    /// Section of the system context describing the stack
+    pub fn to_context(&self) -> String {
+        let mut context = format!("## {} ({})\n", self.description, self.id);
+        for line in self.guidance {
+            let _ = writeln!(context, "- {}", line);
+        }
+        if !self.module_sources.is_empty() {
+            let _ = writeln!(context, "Preferred module sources:");
+            for source in self.module_sources {
+                let _ = writeln!(context, "- {}", source);
+            }
+        }
+        if !self.validation_commands.is_empty() {
+            let _ = writeln!(context, "Validate changes with, in order:");
+            for command in self.validation_commands {
+                let _ = writeln!(context, "- `{}`", command);
+            }
+        }
+        context
+    }
+}
+
+/// Context packs enabled in the current workspace
+#[derive(Serialize, Deserialize, Default, Debug, PartialEq)]
+pub struct WorkspaceContextPacks {
+    #[serde(default)]
+    pub packs: Vec<String>,
+}
+
+impl WorkspaceContextPacks {
+    pub fn path() -> PathBuf {
+        PathBuf::from(".stakpak").join("context.toml")
+    }
+
+    pub fn load() -> Result<Self, String> {
+        let path = Self::path();
+        match std::fs::read_to_string(&path) {
+            Ok(content) => toml::from_str(&content)
+                .map_err(|e| format!("Failed to parse {}: {}", path.display(), e)),
+            Err(_) => Ok(Self::default()),
+        }
+    }
+
+    pub fn save(&self) -> Result<(), String> {
+        let path = Self::path();
+        if let Some(dir) = path.parent() {
+            std::fs::create_dir_all(dir)
+                .map_err(|e| format!("Failed to create {}: {}", dir.display(), e))?;
+        }
+        let content = toml::to_string(self)
+            .map_err(|e| format!("Failed to serialize context packs: {}", e))?;
+        std::fs::write(&path, content)
+            .map_err(|e| format!("Failed to write {}: {}", path.display(), e))
+    }
+
+    /// Enable `id`, returning false when it already was
+    pub fn enable(&mut self, id: &str) -> Result<bool, String> {
+        find_context_pack(id)?;
+        if self.packs.iter().any(|pack| pack == id) {
+            return Ok(false);
+        }
+        self.packs.push(id.to_string());
+        Ok(true)
+    }
+
+    /// Disable `id`, returning false when it was not enabled
+    pub fn disable(&mut self, id: &str) -> bool {
+        let before = self.packs.len();
+        self.packs.retain(|pack| pack != id);
+        self.packs.len() != before
+    }
+
+    /// The enabled packs, skipping ids of packs that no longer exist
+    pub fn enabled(&self) -> Vec<&'static ContextPack> {
+        self.packs
+            .iter()
+            .filter_map(|id| find_context_pack(id).ok())
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_enable_context_packs() {
+        let mut packs = WorkspaceContextPacks::default();
+        assert_eq!(packs.enable("eks-terraform"), Ok(true));
+        assert_eq!(packs.enable("eks-terraform"), Ok(false));
+        assert!(packs.enable("nomad").is_err());
+        assert_eq!(packs.enabled().len(), 1);
+        assert!(
+            packs.enabled()[0]
+                .to_context()
+                .contains("terraform validate")
+        );
+        assert!(packs.disable("eks-terraform"));
+        assert!(packs.enabled().is_empty());
+    }
+}
@@ -0,0 +1,204 @@
+use chrono::{DateTime, Utc};
+use futures_util::future::join_all;
+use serde::{Deserialize, Serialize};
+use stakpak_shared::local_store::LocalStore;
+use std::collections::BTreeMap;
+use std::time::Duration;
+use tokio::process::Command;
+
+const ENVIRONMENTS_DIR: &str = "environments";
+const PROBE_TIMEOUT: Duration = Duration::from_secs(3);
+
+/// (key, program, args) of each environment probe. Probes whose program is
+/// missing or fails are left out of the fingerprint.
+const PROBES: [(&str, &str, &[&str]); 6] = [
+    ("git.commit", "git", &["rev-parse", "HEAD"]),
+    ("git.branch", "git", &["rev-parse", "--abbrev-ref", "HEAD"]),
+    ("terraform.version", "terraform", &["version"]),
+    ("terraform.workspace", "terraform", &["workspace", "show"]),
+    ("kubectl.context", "kubectl", &["config", "current-context"]),
+    (
+        "docker.version",
+        "docker",
+        &["version", "--format", "{{.Client.Version}}"],
+    ),
+];
+
+const ENV_VARS: [&str; 4] = [
+    "AWS_PROFILE",
+    "AWS_REGION",
+    "KUBECONFIG",
+    "CLOUDSDK_CORE_PROJECT",
+];
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct EnvironmentFingerprint {
+    pub captured_at: DateTime<Utc>,
+    pub entries: BTreeMap<String, String>,
+}
+
+async fn probe(program: &str, args: &[&str]) -> Option<String> {
+    let output = tokio::time::timeout(
+        PROBE_TIMEOUT,
+        Command::new(program)
+            .args(args)
+            // Keep terraform from checking for updates over the network
+            .env("CHECKPOINT_DISABLE", "1")
+            .kill_on_drop(true)
+            .output(),
+    )
+    .await
+    .ok()?
+    .ok()?;
+
+    if !output.status.success() {
+        return None;
+    }
+    // Only keep the first line, e.g. "Terraform v1.9.0" out of the full version banner
+    String::from_utf8_lossy(&output.stdout)
+        .lines()
+        .next()
+        .map(|line| line.trim().to_string())
+        .filter(|line| !line.is_empty())
+}
+
+impl EnvironmentFingerprint {
+    pub async fn capture() -> Self {
+        let results = join_all(PROBES.iter().map(|(key, program, args)| async move {
+            probe(program, args)
+                .await
+                .map(|value| (key.to_string(), value))
+        }))
+        .await;
+
+        let mut entries: BTreeMap<String, String> = results.into_iter().flatten().collect();
+
+        if entries.contains_key("git.commit") {
+            let dirty = probe("git", &["status", "--porcelain"]).await.is_some();
+            entries.insert("git.dirty".to_string(), dirty.to_string());
+        }
+        for var in ENV_VARS {
+            if let Ok(value) = std::env::var(var) {
+                entries.insert(format!("env.{}", var), value);
+            }
+        }
+        if let Ok(cwd) = std::env::current_dir() {
+            entries.insert("cwd".to_string(), cwd.display().to_string());
+        }
+
+        Self {
+            captured_at: Utc::now(),
+            entries,
+        }
+    }
+
+    pub fn save(&self, checkpoint_id: &str) -> Result<String, String> {
+        let data = serde_json::to_string_pretty(self).map_err(|e| e.to_string())?;
+        LocalStore::write_session_data(
+            &format!("{}/{}.json", ENVIRONMENTS_DIR, checkpoint_id),
+            &data,
+        )
+    }
+
+    pub fn load(checkpoint_id: &str) -> Option<Self> {
+        LocalStore::read_session_data(&format!("{}/{}.json", ENVIRONMENTS_DIR, checkpoint_id))
+            .ok()
+            .and_then(|data| serde_json::from_str(&data).ok())
+    }
+
+    /// Describe every entry that differs between this pinned fingerprint and `current`
+    pub fn mismatches(&self, current: &EnvironmentFingerprint) -> Vec<String> {
+        let mut keys: Vec<&String> = self.entries.keys().chain(current.entries.keys()).collect();
+        keys.sort();
+        keys.dedup();
+
+        keys.into_iter()
+            .filter_map(|key| {
+                let pinned = self.entries.get(key);
+                let now = current.entries.get(key);
+                if pinned == now {
+                    return None;
+                }
+                Some(format!(
+                    "{}: {} -> {}",
+                    key,
+                    pinned.map(|v| v.as_str()).unwrap_or("<unset>"),
+                    now.map(|v| v.as_str()).unwrap_or("<unset>")
+                ))
+            })
+            .collect()
+    }
+}
+
+/// Capture the current environment and pin it to the checkpoint
+pub async fn pin_checkpoint_environment(checkpoint_id: &str) -> Result<String, String> {
+    EnvironmentFingerprint::capture().await.save(checkpoint_id)
+}
+
+/// Compare the environment pinned to a checkpoint with the current one, returning
+/// a warning when they differ. Checkpoints without a pinned environment are skipped.
+pub async fn check_checkpoint_environment(checkpoint_id: &str) -> Option<String> {
+    let pinned = EnvironmentFingerprint::load(checkpoint_id)?;
+    let current = EnvironmentFingerprint::capture().await;
+    let mismatches = pinned.mismatches(&current);
+    if mismatches.is_empty() {
+        return None;
+    }
+
+    Some(format!(
+        "WARNING: the environment changed since checkpoint {} was created ({}). Resumed actions may be unsafe:\n  {}",
+        checkpoint_id,
+        pinned.captured_at.format("%Y-%m-%d %H:%M:%S UTC"),
+        mismatches.join("\n  ")
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn fingerprint(entries: &[(&str, &str)]) -> EnvironmentFingerprint {
+        EnvironmentFingerprint {
+            captured_at: Utc::now(),
+            entries: entries
+                .iter()
+                .map(|(key, value)| (key.to_string(), value.to_string()))
+                .collect(),
+        }
+    }
+
+    #[test]
+    fn test_mismatches() {
+        let pinned = fingerprint(&[
+            ("git.branch", "main"),
+            ("kubectl.context", "prod"),
+            ("env.AWS_PROFILE", "admin"),
+        ]);
+        let current = fingerprint(&[
+            ("git.branch", "main"),
+            ("kubectl.context", "staging"),
+            ("terraform.workspace", "default"),
+        ]);
+        assert_eq!(
+            pinned.mismatches(&current),
+            vec![
+                "env.AWS_PROFILE: admin -> <unset>".to_string(),
+                "kubectl.context: prod -> staging".to_string(),
+                "terraform.workspace: <unset> -> default".to_string(),
+            ]
+        );
+        assert!(pinned.mismatches(&pinned).is_empty());
+    }
+
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn test_probe() {
+        assert_eq!(
+            probe("sh", &["-c", "printf ' v1.9.0 \\nmore'"]).await,
+            Some("v1.9.0".to_string())
+        );
+        assert_eq!(probe("sh", &["-c", "exit 1"]).await, None);
+        assert_eq!(probe("sh", &["-c", "true"]).await, None);
+        assert_eq!(probe("stakpak-missing-program", &[]).await, None);
+    }
+}
@@ -0,0 +1,117 @@
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use stakpak_api::Client;
+use stakpak_api::models::{FlowDocumentsFetch, FlowRef, GetFlowDocumentsResponse};
+use std::path::PathBuf;
+
+/// Documents of a flow ref as of the last fetch, kept in `~/.stakpak/flow_cache/` so
+/// repeated clones and syncs only download documents when the flow changed
+#[derive(Serialize, Deserialize, Debug)]
+pub struct FlowDocumentsCache {
+    pub etag: String,
+    pub documents: GetFlowDocumentsResponse,
+}
+
+fn flow_cache_path(flow_ref: &FlowRef) -> PathBuf {
+    let hash: String = Sha256::digest(flow_ref.to_string().as_bytes())
+        .iter()
+        .map(|b| format!("{:02x}", b))
+        .collect();
+    PathBuf::from(format!(
+        "{}/.stakpak/flow_cache/{}.json",
+        std::env::var("HOME").unwrap_or_default(),
+        hash
+    ))
+}
+
+impl FlowDocumentsCache {
+    pub fn load(flow_ref: &FlowRef) -> Option<Self> {
+        std::fs::read_to_string(flow_cache_path(flow_ref))
+            .ok()
+            .and_then(|data| serde_json::from_str(&data).ok())
+    }
+
+    pub fn save(&self, flow_ref: &FlowRef) -> Result<(), String> {
+        let path = flow_cache_path(flow_ref);
+        if let Some(parent) = path.parent() {
+            std::fs::create_dir_all(parent).map_err(|e| e.to_string())?;
+        }
+        let data = serde_json::to_string(self).map_err(|e| e.to_string())?;
+        std::fs::write(&path, data).map_err(|e| format!("Failed to write flow cache: {}", e))
+    }
+
+    pub fn clear(flow_ref: &FlowRef) {
+        let _ = std::fs::remove_file(flow_cache_path(flow_ref));
+    }
+}
+
+/// Fetch the documents of a flow, sending the cached ETag so unchanged flows are served
+/// from the local cache instead of being downloaded again
+pub async fn get_flow_documents_cached(
+    client: &Client,
+    flow_ref: &FlowRef,
+) -> Result<GetFlowDocumentsResponse, String> {
+    let cache = FlowDocumentsCache::load(flow_ref);
+    let etag = cache.as_ref().map(|cache| cache.etag.as_str());
+
+    match client.get_flow_documents_if_changed(flow_ref, etag).await? {
+        FlowDocumentsFetch::NotModified => match cache {
+            Some(cache) => Ok(cache.documents),
+            // Only reachable if the API answers 304 without being asked to
+            None => client.get_flow_documents(flow_ref).await,
+        },
+        FlowDocumentsFetch::Modified {
+            documents,
+            etag: Some(etag),
+        } => {
+            let cache = FlowDocumentsCache { etag, documents };
+            if let Err(e) = cache.save(flow_ref) {
+                eprintln!("Warning: {}", e);
+            }
+            Ok(cache.documents)
+        }
+        FlowDocumentsFetch::Modified {
+            documents,
+            etag: None,
+        } => {
+            FlowDocumentsCache::clear(flow_ref);
+            Ok(documents)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn flow_ref(tag: &str) -> FlowRef {
+        FlowRef::new(format!("stakpak/web-app/{}", tag)).unwrap()
+    }
+
+    #[test]
+    fn test_each_flow_ref_has_its_own_cache_file() {
+        let path = flow_cache_path(&flow_ref("latest"));
+        assert_eq!(path, flow_cache_path(&flow_ref("latest")));
+        assert_ne!(path, flow_cache_path(&flow_ref("stable")));
+        assert!(path.to_string_lossy().contains("/.stakpak/flow_cache/"));
+        assert_eq!(
+            path.file_name().map(|name| name.len()),
+            Some(64 + ".json".len())
+        );
+    }
+
+    #[test]
+    fn test_cache_round_trip() {
+        let cache = FlowDocumentsCache {
+            etag: "\"v42\"".to_string(),
+            documents: GetFlowDocumentsResponse {
+                documents: Vec::new(),
+                additional_documents: Vec::new(),
+            },
+        };
+        let cache: FlowDocumentsCache =
+            serde_json::from_str(&serde_json::to_string(&cache).unwrap()).unwrap();
+        assert_eq!(cache.etag, "\"v42\"");
+        assert!(cache.documents.documents.is_empty());
+    }
+}
@@ -0,0 +1,341 @@
+use crate::utils::run_budget::BudgetConfig;
+use serde::{Deserialize, Serialize};
+use stakpak_api::provider::{LLMProvider, Provider};
+use stakpak_shared::models::integrations::openai::{ChatMessage, MessageContent, Role};
+use std::fmt::Write;
+
+const SUMMARY_PROMPT: &str = "You are compacting the history of a session between a user and a DevOps agent, so the agent can continue it with less context. Summarize the conversation you are given: the user's goals and requests, decisions made, commands run and what they showed, files created or changed, errors hit and how they were resolved, and the work still outstanding. Keep exact names, paths, resource identifiers and versions. Never include secret values. Reply with the summary only.";
+
+/// Starts the message replacing the compacted turns
+const SUMMARY_HEADER: &str = "Summary of the earlier conversation, compacted to save context:";
+
+/// Ends the head of a trimmed tool result, also marking it as already trimmed
+const TRIMMED_OUTPUT: &str = "characters of this old tool result were trimmed";
+
+/// The history size is estimated at ~4 characters per token
+const CHARS_PER_TOKEN: usize = 4;
+
+/// Characters of each message sent to the summarization call
+const SUMMARY_INPUT_CHARS: usize = 4000;
+
+/// When and how the history of interactive sessions and async runs is compacted,
+/// configured under `[history_compaction]`
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+#[serde(default)]
+pub struct HistoryCompactionConfig {
+    /// Compact automatically before a request once the history grows past `max_tokens`
+    pub auto: bool,
+    /// Estimated tokens the history may grow to before it is compacted
+    pub max_tokens: usize,
+    /// Most recent messages always kept as they are
+    pub keep_recent: usize,
+    /// Characters kept of tool results older than the recent messages
+    pub tool_output_chars: usize,
+    /// Model summarizing the older turns as `<provider>/<model>`, the session's when unset
+    pub summary_model: Option<String>,
+}
+
+impl Default for HistoryCompactionConfig {
+    fn default() -> Self {
+        Self {
+            auto: true,
+            max_tokens: 120_000,
+            keep_recent: 12,
+            tool_output_chars: 4000,
+            summary_model: None,
+        }
+    }
+}
+
+/// What a compaction did
+#[derive(Debug, PartialEq)]
+pub struct Compaction {
+    /// Index of the first compacted message, now holding the pinned ones and then the summary
+    pub first: usize,
+    /// Messages replaced by the summary
+    pub summarized: Vec<ChatMessage>,
+    pub pinned_messages: usize,
+    pub trimmed_tool_results: usize,
+    pub tokens_before: usize,
+    pub tokens_after: usize,
+}
+
+impl std::fmt::Display for Compaction {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "Compacted the history from ~{} to ~{} tokens: summarized {} older messages and trimmed {} tool results",
+            self.tokens_before,
+            self.tokens_after,
+            self.summarized.len(),
+            self.trimmed_tool_results
+        )?;
+        if self.pinned_messages > 0 {
+            write!(f, ", kept {} pinned messages", self.pinned_messages)?;
+        }
+        Ok(())
+    }
+}
+
+fn message_chars(message: &ChatMessage) -> usize {
+    let content = message
+        .content
+        .as_ref()
+        .map_or(0, |content| content.to_string().chars().count());
+    let tool_calls: usize = message
+        .tool_calls
+        .iter()
+        .flatten()
+        .map(|call| call.function.name.len() + call.function.arguments.chars().count())
+        .sum();
+    content + tool_calls
+}
+
+/// Approximate tokens `messages` take in the model context
+pub fn estimate_tokens(messages: &[ChatMessage]) -> usize {
+    messages
+        .iter()
+        .map(message_chars)
+        .sum::<usize>()
+        .div_ceil(CHARS_PER_TOKEN)
+}
+
+/// Index of the first message kept as is. Tool results stay with the call they answer, and
+/// the latest checkpoint is kept since the session continues from it.
+fn recent_start(messages: &[ChatMessage], keep_recent: usize) -> usize {
+    let mut start = messages.len().saturating_sub(keep_recent);
+    while start > 0 && messages[start].role == Role::Tool {
+        start -= 1;
+    }
+    let latest_checkpoint = messages.iter().rposition(|message| {
+        message.role == Role::Assistant
+            && message
+                .content
+                .as_ref()
+                .and_then(|content| content.extract_checkpoint_id())
+                .is_some()
+    });
+    match latest_checkpoint {
+        Some(checkpoint) => start.min(checkpoint),
+        None => start,
+    }
+}
+
+/// Keep the head and tail of tool results longer than `max_chars`, returning how many
+/// were trimmed
+fn trim_tool_results(messages: &mut [ChatMessage], max_chars: usize) -> usize {
+    let mut trimmed = 0;
+    for message in messages
+        .iter_mut()
+        .filter(|message| message.role == Role::Tool)
+    {
+        let Some(MessageContent::String(output)) = &message.content else {
+            continue;
+        };
+        let chars = output.chars().count();
+        if chars <= max_chars || output.contains(TRIMMED_OUTPUT) {
+            continue;
+        }
+        let head: String = output.chars().take(max_chars / 2).collect();
+        let tail: String = output.chars().skip(chars - max_chars / 2).collect();
+        message.content = Some(MessageContent::String(format!(
+            "{}\n[... {} {} ...]\n{}",
+            head,
+            chars - head.chars().count() - tail.chars().count(),
+            TRIMMED_OUTPUT,
+            tail
+        )));
+        trimmed += 1;
+    }
+    trimmed
+}
+
+/// User messages whose content was pinned, never summarized
+fn is_pinned(message: &ChatMessage, pinned: &[String]) -> bool {
+    message.role == Role::User
+        && message
+            .content
+            .as_ref()
+            .is_some_and(|content| pinned.contains(&content.to_string()))
+}
+
+/// Messages to summarize as one transcript, long ones cut short
+fn transcript(messages: &[ChatMessage]) -> String {
+    let mut transcript = String::new();
+    for message in messages {
+        let content = message
+            .content
+            .as_ref()
+            .map(|content| content.to_string())
+            .unwrap_or_default();
+        let content: String = content.chars().take(SUMMARY_INPUT_CHARS).collect();
+        let _ = writeln!(transcript, "[{}] {}", message.role, content);
+        for call in message.tool_calls.iter().flatten() {
+            let arguments: String = call
+                .function
+                .arguments
+                .chars()
+                .take(SUMMARY_INPUT_CHARS)
+                .collect();
+            let _ = writeln!(
+                transcript,
+                "[{} called {}] {}",
+                message.role, call.function.name, arguments
+            );
+        }
+    }
+    transcript
+}
+
+/// Trim old tool results and, once the history is over its budget or when `force`d,
+/// replace the turns before the recent messages with a summary written by `provider`.
+/// User messages with `pinned` content are kept as they are, ahead of the summary, and the
+/// summarization call is taken from `budget` like any other completion.
+/// Returns `None` when nothing was summarized.
+pub async fn compact_history(
+    provider: &Provider,
+    messages: &mut Vec<ChatMessage>,
+    config: &HistoryCompactionConfig,
+    pinned: &[String],
+    force: bool,
+    budget: Option<&BudgetConfig>,
+) -> Result<Option<Compaction>, String> {
+    let tokens_before = estimate_tokens(messages);
+    let start = recent_start(messages, config.keep_recent);
+    let trimmed_tool_results = trim_tool_results(&mut messages[..start], config.tool_output_chars);
+    if !force && estimate_tokens(messages) <= config.max_tokens {
+        return Ok(None);
+    }
+
+    let first = messages
+        .iter()
+        .position(|message| message.role != Role::System)
+        .unwrap_or(messages.len())
+        .min(start);
+    let (kept, summarized): (Vec<ChatMessage>, Vec<ChatMessage>) = messages[first..start]
+        .iter()
+        .cloned()
+        .partition(|message| is_pinned(message, pinned));
+    // A lone earlier summary is not worth summarizing again
+    if summarized.len() < 2 {
+        return Ok(None);
+    }
+
+    let request = vec![
+        ChatMessage {
+            role: Role::System,
+            content: Some(MessageContent::String(SUMMARY_PROMPT.to_string())),
+            name: None,
+            tool_calls: None,
+            tool_call_id: None,
+        },
+        ChatMessage {
+            role: Role::User,
+            content: Some(MessageContent::String(transcript(&summarized))),
+            name: None,
+            tool_calls: None,
+            tool_call_id: None,
+        },
+    ];
+    if let Some(budget) = budget {
+        budget.acquire_call()?;
+    }
+    let response = provider.chat_completion(request, None).await?;
+    if let Some(budget) = budget {
+        budget.consume_tokens(response.usage.total_tokens as u64);
+    }
+    let summary = response
+        .choices
+        .first()
+        .and_then(|choice| choice.message.content.as_ref())
+        .map(|content| content.to_string())
+        .filter(|summary| !summary.trim().is_empty())
+        .ok_or_else(|| "The summarization call returned no summary".to_string())?;
+
+    let pinned_messages = kept.len();
+    let summary = ChatMessage {
+        role: Role::User,
+        content: Some(MessageContent::String(format!(
+            "{}\n\n{}",
+            SUMMARY_HEADER,
+            summary.trim()
+        ))),
+        name: None,
+        tool_calls: None,
+        tool_call_id: None,
+    };
+    messages.splice(first..start, kept.into_iter().chain([summary]));
+    Ok(Some(Compaction {
+        first,
+        summarized,
+        pinned_messages,
+        trimmed_tool_results,
+        tokens_before,
+        tokens_after: estimate_tokens(messages),
+    }))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use stakpak_shared::models::integrations::openai::{FunctionCall, ToolCall};
+
+    fn message(role: Role, content: &str) -> ChatMessage {
+        ChatMessage {
+            role,
+            content: Some(MessageContent::String(content.to_string())),
+            name: None,
+            tool_calls: None,
+            tool_call_id: None,
+        }
+    }
+
+    #[test]
+    fn test_recent_start_keeps_tool_calls_and_trims_old_results() {
+        let mut call = message(Role::Assistant, "checking the logs");
+        call.tool_calls = Some(vec![ToolCall {
+            id: "call_1".to_string(),
+            r#type: "function".to_string(),
+            function: FunctionCall {
+                name: "run_command".to_string(),
+                arguments: r#"{"command":"kubectl logs api"}"#.to_string(),
+            },
+        }]);
+        let mut messages = vec![
+            message(Role::System, "You are a DevOps agent"),
+            message(Role::User, "why does the api crash?"),
+            call,
+            message(Role::Tool, &"panic: missing DATABASE_URL\n".repeat(100)),
+            message(Role::Assistant, "DATABASE_URL is not set"),
+        ];
+
+        // Keeping the last two would split the tool result from its call
+        let start = recent_start(&messages, 2);
+        assert_eq!(start, 2);
+        assert_eq!(estimate_tokens(&messages[..1]), 6);
+
+        assert_eq!(trim_tool_results(&mut messages, 100), 1);
+        let output = messages[3]
+            .content
+            .as_ref()
+            .map(|content| content.to_string())
+            .unwrap_or_default();
+        assert!(output.starts_with("panic: missing DATABASE_URL"));
+        assert!(output.contains(TRIMMED_OUTPUT));
+        assert_eq!(trim_tool_results(&mut messages, 100), 0);
+    }
+
+    #[test]
+    fn test_only_pinned_user_messages_are_kept() {
+        let pinned = vec!["migrate the api to eks".to_string()];
+        assert!(is_pinned(
+            &message(Role::User, "migrate the api to eks"),
+            &pinned
+        ));
+        assert!(!is_pinned(&message(Role::User, "now the workers"), &pinned));
+        assert!(!is_pinned(
+            &message(Role::Assistant, "migrate the api to eks"),
+            &pinned
+        ));
+    }
+}
@@ -0,0 +1,102 @@
+use ignore::WalkBuilder;
+use ignore::gitignore::{Gitignore, GitignoreBuilder};
+use std::path::{Path, PathBuf};
+
+/// Patterns of files never pushed, synced or transpiled, in `.gitignore` syntax
+pub const STAKPAK_IGNORE: &str = ".stakpakignore";
+/// Directories skipped even when no ignore file lists them
+const ALWAYS_IGNORED: &[&str] = &["node_modules", ".terraform"];
+
+/// Files under `dir` not excluded by `.gitignore`, `.ignore` or `.stakpakignore` files, in
+/// walk order. Hidden files and directories are skipped, ignore files apply even outside
+/// of a git repository.
+pub fn walk_files(dir: &Path) -> Vec<PathBuf> {
+    WalkBuilder::new(dir)
+        .hidden(true)
+        .require_git(false)
+        .add_custom_ignore_filename(STAKPAK_IGNORE)
+        .sort_by_file_name(|a, b| a.cmp(b))
+        .filter_entry(|entry| {
+            !ALWAYS_IGNORED.contains(&entry.file_name().to_string_lossy().as_ref())
+        })
+        .build()
+        .filter_map(|entry| entry.ok())
+        .filter(|entry| {
+            entry
+                .file_type()
+                .is_some_and(|file_type| file_type.is_file())
+        })
+        .map(|entry| entry.into_path())
+        .collect()
+}
+
+/// Ignore patterns at the root of a directory, to check paths reported by the file watcher
+pub struct IgnoreRules {
+    root: PathBuf,
+    matcher: Gitignore,
+}
+
+impl IgnoreRules {
+    pub fn load(dir: &Path) -> Self {
+        let root = dir.canonicalize().unwrap_or_else(|_| dir.to_path_buf());
+        let mut builder = GitignoreBuilder::new(&root);
+        for name in [".gitignore", ".ignore", STAKPAK_IGNORE] {
+            // A missing or malformed ignore file only loses its patterns
+            let _ = builder.add(root.join(name));
+        }
+        for dir_name in ALWAYS_IGNORED {
+            let _ = builder.add_line(None, &format!("{}/", dir_name));
+        }
+        let matcher = builder.build().unwrap_or_else(|_| Gitignore::empty());
+        Self { root, matcher }
+    }
+
+    /// Whether `path`, or a directory containing it, is ignored. Paths outside the
+    /// directory are never ignored.
+    pub fn is_ignored(&self, path: &Path, is_dir: bool) -> bool {
+        let path = path.canonicalize().unwrap_or_else(|_| path.to_path_buf());
+        match path.strip_prefix(&self.root) {
+            Ok(relative) => self
+                .matcher
+                .matched_path_or_any_parents(relative, is_dir)
+                .is_ignore(),
+            Err(_) => false,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_ignore_files_are_applied() {
+        let dir = std::env::temp_dir().join(format!("stakpak-ignore-{}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&dir);
+        for file in [
+            "main.tf",
+            "charts/vendored/values.yaml",
+            "node_modules/pkg/a.yaml",
+        ] {
+            let path = dir.join(file);
+            if let Some(parent) = path.parent() {
+                std::fs::create_dir_all(parent).unwrap();
+            }
+            std::fs::write(path, "").unwrap();
+        }
+        std::fs::write(dir.join(STAKPAK_IGNORE), "charts/vendored/\n").unwrap();
+
+        let files: Vec<PathBuf> = walk_files(&dir)
+            .into_iter()
+            .filter_map(|path| path.strip_prefix(&dir).ok().map(Path::to_path_buf))
+            .collect();
+        assert_eq!(files, vec![PathBuf::from("main.tf")]);
+
+        let rules = IgnoreRules::load(&dir);
+        assert!(rules.is_ignored(&dir.join("charts/vendored/values.yaml"), false));
+        assert!(rules.is_ignored(&dir.join("node_modules/pkg/a.yaml"), false));
+        assert!(!rules.is_ignored(&dir.join("main.tf"), false));
+
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+}
@@ -1,5 +1,7 @@
+use crate::utils::context_packs::WorkspaceContextPacks;
 use serde::{Deserialize, Serialize};
-use std::collections::HashMap;
+use stakpak_shared::secrets::vault::{secret_placeholder, stored_secret_names};
+use stakpak_shared::workspace::{WorkspaceRoot, workspace_roots};
 use std::env;
 use std::fmt;
 use std::fs;
@@ -12,16 +14,34 @@ pub struct LocalContext {
     pub shell_type: String,
     pub is_container: bool,
     pub working_directory: String,
-    pub file_structure: HashMap<String, FileInfo>,
     pub git_info: Option<GitInfo>,
+    /// Infrastructure CLIs found on the PATH
+    #[serde(default)]
+    pub available_clis: Vec<String>,
+    /// Infrastructure CLIs that are not installed
+    #[serde(default)]
+    pub missing_clis: Vec<String>,
+    /// Names of secrets stored with `stakpak secrets add`
+    #[serde(default)]
+    pub stored_secrets: Vec<String>,
+    /// Roots of a multi-root workspace, set with `-w` or `workspace_roots` in the config
+    #[serde(default)]
+    pub workspace_roots: Vec<WorkspaceRoot>,
+    /// IDs of the context packs enabled with `stakpak context enable`
+    #[serde(default)]
+    pub context_packs: Vec<String>,
 }
 
-#[derive(Serialize, Deserialize, Debug, Clone)]
-pub struct FileInfo {
-    pub is_directory: bool,
-    pub size: Option<u64>,
-    pub children: Option<Vec<String>>,
-}
+/// Infrastructure CLIs the agent commonly reaches for
+const PROBED_CLIS: &[&str] = &[
+    "terraform",
+    "kubectl",
+    "docker",
+    "helm",
+    "aws",
+    "gcloud",
+    "az",
+];
 
 #[derive(Serialize, Deserialize, Debug, Clone)]
 pub struct GitInfo {
@@ -66,80 +86,116 @@ impl fmt::Display for LocalContext {
             }
         }
 
+        if !self.available_clis.is_empty() {
+            writeln!(f, "Available CLIs: {}", self.available_clis.join(", "))?;
+        }
+        if !self.missing_clis.is_empty() {
+            writeln!(
+                f,
+                "Missing CLIs (not installed, do not run commands that need them): {}",
+                self.missing_clis.join(", ")
+            )?;
+        }
+        if !self.stored_secrets.is_empty() {
+            let placeholders: Vec<String> = self
+                .stored_secrets
+                .iter()
+                .map(|name| secret_placeholder(name))
+                .collect();
+            writeln!(
+                f,
+                "Stored Secrets (write these placeholders verbatim in commands and files, they are replaced with the real values when tools run): {}",
+                placeholders.join(", ")
+            )?;
+        }
+
+        if !self.workspace_roots.is_empty() {
+            writeln!(
+                f,
+                "# Workspace Roots (address files in them as root://<name>/<path>)"
+            )?;
+            for root in &self.workspace_roots {
+                writeln!(
+                    f,
+                    "- {}: {} ({})",
+                    root.name,
+                    root.path.display(),
+                    root.access
+                )?;
+            }
+        }
+
+        // The workspace itself is summarized on demand by the get_workspace_context tool
         writeln!(
             f,
             "# Current Working Directory ({})",
             self.working_directory
         )?;
-        if self.file_structure.is_empty() {
-            writeln!(f, "(No files or directories found)")?;
-        } else {
-            // Sort entries for consistent output
-            let mut entries: Vec<_> = self.file_structure.iter().collect();
-            entries.sort_by_key(|(name, info)| (info.is_directory, name.to_lowercase()));
-
-            // Display as tree structure like ls output
-            for (i, (name, info)) in entries.iter().enumerate() {
-                let is_last = i == entries.len() - 1;
-                let prefix = if is_last { "└── " } else { "├── " };
-
-                write!(f, "{}", prefix)?;
-
-                if info.is_directory {
-                    write!(f, "{}/", name)?;
-                    if let Some(children) = &info.children {
-                        if !children.is_empty() {
-                            write!(f, " ({} items)", children.len())?;
-                        } else {
-                            write!(f, " (empty)")?;
-                        }
-                    }
-                } else {
-                    write!(f, "{}", name)?;
-                    if let Some(size) = info.size {
-                        write!(f, " ({})", format_file_size(size))?;
-                    }
-                }
-                writeln!(f)?;
+        writeln!(
+            f,
+            "Use the get_workspace_context tool for a structured summary of the file tree, detected provisioners, entry points and tool versions."
+        )?;
+        let packs = WorkspaceContextPacks {
+            packs: self.context_packs.clone(),
+        };
+        let packs = packs.enabled();
+        if !packs.is_empty() {
+            writeln!(
+                f,
+                "# Stack Guidance (follow it unless the user asks otherwise)"
+            )?;
+            for pack in packs {
+                write!(f, "{}", pack.to_context())?;
             }
         }
+        writeln!(f, "# Citations")?;
+        writeln!(
+            f,
+            "When a claim in your answer is based on a workspace file, cite the lines it comes from as [path:line] or [path:start-end], e.g. [infra/alb.tf:12-18]. Only cite lines you have read, citations are checked against the files."
+        )?;
 
         Ok(())
     }
 }
 
-fn format_file_size(size: u64) -> String {
-    const UNITS: &[&str] = &["B", "KB", "MB", "GB", "TB"];
-    let mut size_f = size as f64;
-    let mut unit_index = 0;
-
-    while size_f >= 1024.0 && unit_index < UNITS.len() - 1 {
-        size_f /= 1024.0;
-        unit_index += 1;
-    }
-
-    if unit_index == 0 {
-        format!("{} {}", size, UNITS[unit_index])
-    } else {
-        format!("{:.1} {}", size_f, UNITS[unit_index])
-    }
-}
-
 pub async fn analyze_local_context() -> Result<LocalContext, Box<dyn std::error::Error>> {
     let operating_system = get_operating_system();
     let shell_type = get_shell_type();
     let is_container = detect_container_environment();
     let working_directory = get_working_directory()?;
-    let file_structure = get_file_structure(&working_directory)?;
     let git_info = Some(get_git_info(&working_directory));
+    let (available_clis, missing_clis): (Vec<String>, Vec<String>) = PROBED_CLIS
+        .iter()
+        .map(|cli| cli.to_string())
+        .partition(|cli| is_on_path(cli));
+    // A broken vault should not prevent the agent from starting
+    let stored_secrets = stored_secret_names().unwrap_or_default();
 
     Ok(LocalContext {
         operating_system,
         shell_type,
         is_container,
         working_directory,
-        file_structure,
         git_info,
+        available_clis,
+        missing_clis,
+        stored_secrets,
+        workspace_roots: workspace_roots(),
+        // A broken file should not prevent the agent from starting either
+        context_packs: WorkspaceContextPacks::load()
+            .map(|packs| packs.packs)
+            .unwrap_or_default(),
+    })
+}
+
+/// Check whether `program` is an executable file in one of the PATH directories
+fn is_on_path(program: &str) -> bool {
+    let Some(path) = env::var_os("PATH") else {
+        return false;
+    };
+    env::split_paths(&path).any(|dir| {
+        let candidate = dir.join(program);
+        candidate.is_file() || (cfg!(windows) && candidate.with_extension("exe").is_file())
     })
 }
 
@@ -328,60 +384,6 @@ fn get_working_directory() -> Result<String, Box<dyn std::error::Error>> {
     Ok(cwd.to_string_lossy().to_string())
 }
 
-fn get_file_structure(
-    dir_path: &str,
-) -> Result<HashMap<String, FileInfo>, Box<dyn std::error::Error>> {
-    let mut file_structure = HashMap::new();
-    let path = Path::new(dir_path);
-
-    if !path.exists() {
-        return Ok(file_structure);
-    }
-
-    // Read the current directory
-    let entries = fs::read_dir(path)?;
-
-    for entry in entries {
-        let entry = entry?;
-        let file_name = entry.file_name().to_string_lossy().to_string();
-        let file_path = entry.path();
-        let metadata = entry.metadata()?;
-
-        let is_directory = metadata.is_dir();
-        let size = if is_directory {
-            None
-        } else {
-            Some(metadata.len())
-        };
-
-        // For directories, get immediate children (non-recursive to avoid deep trees)
-        let children = if is_directory {
-            match fs::read_dir(&file_path) {
-                Ok(dir_entries) => {
-                    let child_names: Result<Vec<String>, _> = dir_entries
-                        .map(|entry| entry.map(|e| e.file_name().to_string_lossy().to_string()))
-                        .collect();
-                    child_names.ok()
-                }
-                Err(_) => None,
-            }
-        } else {
-            None
-        };
-
-        file_structure.insert(
-            file_name,
-            FileInfo {
-                is_directory,
-                size,
-                children,
-            },
-        );
-    }
-
-    Ok(file_structure)
-}
-
 fn get_git_info(dir_path: &str) -> GitInfo {
     let path = Path::new(dir_path);
 
@@ -472,3 +474,45 @@ fn get_git_info(dir_path: &str) -> GitInfo {
 
     git_info
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[cfg(unix)]
+    #[test]
+    fn test_is_on_path() {
+        assert!(is_on_path("sh"));
+        assert!(!is_on_path("stakpak-missing-program"));
+    }
+
+    #[test]
+    fn test_clis_are_listed() {
+        let context = LocalContext {
+            operating_system: "linux".to_string(),
+            shell_type: "bash".to_string(),
+            is_container: false,
+            working_directory: "/src".to_string(),
+            git_info: None,
+            available_clis: vec!["terraform".to_string(), "kubectl".to_string()],
+            missing_clis: vec!["helm".to_string()],
+            stored_secrets: Vec::new(),
+            workspace_roots: Vec::new(),
+            context_packs: Vec::new(),
+        };
+        let text = context.to_string();
+        assert!(text.contains("Available CLIs: terraform, kubectl\n"));
+        assert!(
+            text.contains(
+                "Missing CLIs (not installed, do not run commands that need them): helm\n"
+            )
+        );
+
+        let context = LocalContext {
+            available_clis: Vec::new(),
+            missing_clis: Vec::new(),
+            ..context
+        };
+        assert!(!context.to_string().contains("CLIs"));
+    }
+}
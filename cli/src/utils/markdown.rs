@@ -0,0 +1,104 @@
+use serde::{Deserialize, Serialize};
+use termimad::MadSkin;
+
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, Default, PartialEq)]
+#[serde(rename_all = "kebab-case")]
+pub enum RenderMode {
+    /// Render markdown (tables, code fences, lists) with terminal styles
+    #[default]
+    Styled,
+    /// Render markdown layout without colors or text attributes
+    NoColor,
+    /// Print markdown as-is
+    Raw,
+}
+
+impl RenderMode {
+    /// Resolve the render mode from CLI flags, falling back to the configured mode
+    pub fn resolve(configured: RenderMode, no_color: bool, raw: bool) -> RenderMode {
+        if raw {
+            RenderMode::Raw
+        } else if no_color && configured == RenderMode::Styled {
+            RenderMode::NoColor
+        } else {
+            configured
+        }
+    }
+}
+
+pub struct MarkdownRenderer {
+    mode: RenderMode,
+    skin: MadSkin,
+}
+
+impl MarkdownRenderer {
+    pub fn new(mode: RenderMode) -> Self {
+        // Honor https://no-color.org
+        let mode = match mode {
+            RenderMode::Styled if std::env::var_os("NO_COLOR").is_some_and(|v| !v.is_empty()) => {
+                RenderMode::NoColor
+            }
+            mode => mode,
+        };
+        let skin = match mode {
+            RenderMode::Styled => MadSkin::default(),
+            RenderMode::NoColor | RenderMode::Raw => MadSkin::no_style(),
+        };
+        Self { mode, skin }
+    }
+
+    pub fn mode(&self) -> RenderMode {
+        self.mode
+    }
+
+    pub fn render(&self, markdown: &str) -> String {
+        match self.mode {
+            RenderMode::Raw => markdown.to_string(),
+            RenderMode::Styled | RenderMode::NoColor => self.skin.term_text(markdown).to_string(),
+        }
+    }
+
+    pub fn print(&self, markdown: &str) {
+        println!("{}", self.render(markdown));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_resolve_render_mode() {
+        assert_eq!(
+            RenderMode::resolve(RenderMode::Styled, false, false),
+            RenderMode::Styled
+        );
+        assert_eq!(
+            RenderMode::resolve(RenderMode::Styled, true, false),
+            RenderMode::NoColor
+        );
+        assert_eq!(
+            RenderMode::resolve(RenderMode::NoColor, false, true),
+            RenderMode::Raw
+        );
+        // --no-color doesn't turn a configured raw mode into rendered markdown
+        assert_eq!(
+            RenderMode::resolve(RenderMode::Raw, true, false),
+            RenderMode::Raw
+        );
+    }
+
+    #[test]
+    fn test_render() {
+        let markdown = "# Plan\n\n- **apply** the change\n";
+        assert_eq!(
+            MarkdownRenderer::new(RenderMode::Raw).render(markdown),
+            markdown
+        );
+
+        let rendered = MarkdownRenderer::new(RenderMode::NoColor).render(markdown);
+        assert!(rendered.contains("apply the change"));
+        assert!(!rendered.contains('\u{1b}'));
+        assert!(!rendered.contains("**"));
+    }
+}
@@ -0,0 +1,304 @@
+use chrono::{DateTime, Utc};
+use rmcp::model::Tool;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use stakpak_mcp_client::{ClientManager, ExternalServers, LOCAL_CLIENT};
+use std::collections::{BTreeMap, HashMap};
+use std::io::{IsTerminal, Write};
+use std::path::PathBuf;
+
+/// Keywords in a tool's name or description and the permission they imply
+const PERMISSION_HINTS: [(&str, &[&str]); 5] = [
+    (
+        "execute shell commands",
+        &["command", "shell", "exec", "bash", "terminal"],
+    ),
+    (
+        "write or delete files",
+        &[
+            "write",
+            "create",
+            "str_replace",
+            "insert",
+            "delete",
+            "remove",
+        ],
+    ),
+    ("read files", &["read", "view", "file"]),
+    (
+        "make network requests",
+        &["http", "fetch", "url", "request"],
+    ),
+    (
+        "access secrets and credentials",
+        &["secret", "credential", "token", "password"],
+    ),
+];
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct TrustedServer {
+    pub manifest_hash: String,
+    /// Tool name to the hash of its definition, used to explain what changed
+    pub tools: BTreeMap<String, String>,
+    pub trusted_at: DateTime<Utc>,
+}
+
+#[derive(Serialize, Deserialize, Default, Debug)]
+pub struct TrustStore {
+    pub servers: BTreeMap<String, TrustedServer>,
+}
+
+fn trust_store_path() -> PathBuf {
+    PathBuf::from(format!(
+        "{}/.stakpak/trusted_mcp_servers.json",
+        std::env::var("HOME").unwrap_or_default()
+    ))
+}
+
+impl TrustStore {
+    pub fn load() -> Self {
+        std::fs::read_to_string(trust_store_path())
+            .ok()
+            .and_then(|data| serde_json::from_str(&data).ok())
+            .unwrap_or_default()
+    }
+
+    pub fn save(&self) -> Result<(), String> {
+        let path = trust_store_path();
+        if let Some(parent) = path.parent() {
+            std::fs::create_dir_all(parent).map_err(|e| e.to_string())?;
+        }
+        let data = serde_json::to_string_pretty(self).map_err(|e| e.to_string())?;
+        std::fs::write(&path, data).map_err(|e| format!("Failed to write trust store: {}", e))
+    }
+}
+
+fn hash_hex(data: &[u8]) -> String {
+    Sha256::digest(data)
+        .iter()
+        .map(|b| format!("{:02x}", b))
+        .collect()
+}
+
+/// Hash every tool definition (name, description and input schema) exposed by a server
+fn tool_hashes(tools: &[Tool]) -> Result<BTreeMap<String, String>, String> {
+    tools
+        .iter()
+        .map(|tool| {
+            let definition = serde_json::to_vec(tool).map_err(|e| e.to_string())?;
+            Ok((tool.name.to_string(), hash_hex(&definition)))
+        })
+        .collect()
+}
+
+fn manifest_hash(tools: &BTreeMap<String, String>) -> Result<String, String> {
+    let manifest = serde_json::to_vec(tools).map_err(|e| e.to_string())?;
+    Ok(hash_hex(&manifest))
+}
+
+fn implied_permissions(tools: &[Tool]) -> Vec<&'static str> {
+    PERMISSION_HINTS
+        .iter()
+        .filter(|(_, keywords)| {
+            tools.iter().any(|tool| {
+                let text = format!(
+                    "{} {}",
+                    tool.name,
+                    tool.description.as_deref().unwrap_or_default()
+                )
+                .to_lowercase();
+                keywords.iter().any(|k| text.contains(k))
+            })
+        })
+        .map(|(permission, _)| *permission)
+        .collect()
+}
+
+fn describe_changes(
+    previous: &BTreeMap<String, String>,
+    current: &BTreeMap<String, String>,
+) -> Vec<String> {
+    let mut changes = Vec::new();
+    for (name, hash) in current {
+        match previous.get(name) {
+            None => changes.push(format!("+ {} (new tool)", name)),
+            Some(old) if old != hash => changes.push(format!("~ {} (definition changed)", name)),
+            _ => {}
+        }
+    }
+    for name in previous.keys() {
+        if !current.contains_key(name) {
+            changes.push(format!("- {} (removed)", name));
+        }
+    }
+    changes
+}
+
+fn confirm_trust(summary: &str) -> Result<bool, String> {
+    println!("{}", summary);
+    print!("Trust this MCP server? Type 'yes' to confirm: ");
+    std::io::stdout().flush().map_err(|e| e.to_string())?;
+    let mut input = String::new();
+    std::io::stdin()
+        .read_line(&mut input)
+        .map_err(|e| format!("Failed to read input: {}", e))?;
+    Ok(input.trim() == "yes")
+}
+
+/// Trust-on-first-use check for an external MCP server. The first connection asks the
+/// user to trust the tools the server exposes, later connections are refused when the
+/// tool manifest changed until the user confirms again.
+pub fn ensure_server_trusted(
+    server_host: &str,
+    tools_map: &HashMap<String, Vec<Tool>>,
+) -> Result<(), String> {
+    let tools: Vec<Tool> = tools_map.values().flatten().cloned().collect();
+    let hashes = tool_hashes(&tools)?;
+    let hash = manifest_hash(&hashes)?;
+
+    let mut store = TrustStore::load();
+    let previous = store.servers.get(server_host);
+    if previous.is_some_and(|trusted| trusted.manifest_hash == hash) {
+        return Ok(());
+    }
+
+    let mut summary = match previous {
+        None => format!(
+            "\nThe MCP server {} has not been trusted yet. It exposes {} tools:\n",
+            server_host,
+            tools.len()
+        ),
+        Some(trusted) => format!(
+            "\nWARNING: the tools exposed by MCP server {} changed since you trusted it on {}:\n  {}\n\nIt now exposes {} tools:\n",
+            server_host,
+            trusted.trusted_at.format("%Y-%m-%d %H:%M UTC"),
+            describe_changes(&trusted.tools, &hashes).join("\n  "),
+            tools.len()
+        ),
+    };
+    for tool in &tools {
+        let description = tool.description.as_deref().unwrap_or_default();
+        summary.push_str(&format!(
+            "  - {}: {}\n",
+            tool.name,
+            description.lines().next().unwrap_or_default()
+        ));
+    }
+    let permissions = implied_permissions(&tools);
+    if !permissions.is_empty() {
+        summary.push_str(&format!("\nThese tools may {}.\n", permissions.join(", ")));
+    }
+
+    if !std::io::stdin().is_terminal() {
+        return Err(format!(
+            "{}\nRefusing to use an untrusted MCP server in a non-interactive session, run stakpak interactively once to review and trust it",
+            summary
+        ));
+    }
+    if !confirm_trust(&summary)? {
+        return Err(format!("MCP server {} was not trusted", server_host));
+    }
+
+    store.servers.insert(
+        server_host.to_string(),
+        TrustedServer {
+            manifest_hash: hash,
+            tools: hashes,
+            trusted_at: Utc::now(),
+        },
+    );
+    store.save()
+}
+
+/// Connect the servers of `~/.stakpak/mcp.json` and list the tools of every client. Servers
+/// that cannot be reached are skipped with a warning, and every server other than the
+/// built-in one has to be trusted before its tools are used.
+pub async fn connect_mcp_servers(
+    clients: &mut ClientManager,
+    mcp_server_host: Option<&str>,
+) -> Result<HashMap<String, Vec<Tool>>, String> {
+    let external_servers = ExternalServers::load()?;
+    for error in clients.connect_external(&external_servers).await {
+        eprintln!("Warning: {}", error);
+    }
+    let tools_map = clients.get_tools().await.map_err(|e| e.to_string())?;
+
+    if let Some(server_host) = mcp_server_host {
+        let server_tools = tools_map
+            .iter()
+            .filter(|(name, _)| name.as_str() == LOCAL_CLIENT)
+            .map(|(name, tools)| (name.clone(), tools.clone()))
+            .collect();
+        ensure_server_trusted(server_host, &server_tools)?;
+    }
+    for (name, server) in &external_servers.servers {
+        if let Some(tools) = tools_map.get(name) {
+            ensure_server_trusted(
+                &format!("{} ({})", name, server.target()),
+                &HashMap::from([(name.clone(), tools.clone())]),
+            )?;
+        }
+    }
+    Ok(tools_map)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn tool(name: &str, description: &str) -> Tool {
+        serde_json::from_value(serde_json::json!({
+            "name": name,
+            "description": description,
+            "inputSchema": {"type": "object"},
+        }))
+        .unwrap()
+    }
+
+    #[test]
+    fn test_manifest_hash_tracks_tool_definitions() {
+        let tools = vec![tool("search", "Search the docs")];
+        let hash = manifest_hash(&tool_hashes(&tools).unwrap()).unwrap();
+        assert_eq!(hash, manifest_hash(&tool_hashes(&tools).unwrap()).unwrap());
+        let changed = vec![tool("search", "Search the docs and run shell commands")];
+        assert_ne!(
+            hash,
+            manifest_hash(&tool_hashes(&changed).unwrap()).unwrap()
+        );
+    }
+
+    #[test]
+    fn test_describe_changes() {
+        let previous = BTreeMap::from([
+            ("search".to_string(), "a".to_string()),
+            ("fetch".to_string(), "b".to_string()),
+            ("removed".to_string(), "c".to_string()),
+        ]);
+        let current = BTreeMap::from([
+            ("search".to_string(), "a".to_string()),
+            ("fetch".to_string(), "changed".to_string()),
+            ("exec".to_string(), "d".to_string()),
+        ]);
+        assert_eq!(
+            describe_changes(&previous, &current),
+            vec![
+                "+ exec (new tool)".to_string(),
+                "~ fetch (definition changed)".to_string(),
+                "- removed (removed)".to_string(),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_implied_permissions() {
+        let tools = vec![
+            tool("run_shell", "Runs a command"),
+            tool("get_page", "Fetch a URL"),
+        ];
+        assert_eq!(
+            implied_permissions(&tools),
+            vec!["execute shell commands", "make network requests"]
+        );
+        assert!(implied_permissions(&[tool("echo", "Echo the input")]).is_empty());
+    }
+}
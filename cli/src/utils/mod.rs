@@ -1,4 +1,23 @@
+pub mod account_cache;
+pub mod audit_log;
 pub mod check_update;
+pub mod checkpoint_labels;
+pub mod checkpoint_replay;
+pub mod citations;
+pub mod context_packs;
+pub mod context_ranking;
+pub mod env_fingerprint;
+pub mod flow_cache;
+pub mod history_compaction;
+pub mod ignore_rules;
 pub mod local_context;
+pub mod markdown;
+pub mod mcp_trust;
 pub mod network;
 pub mod output;
+pub mod pull_request;
+pub mod run_budget;
+pub mod run_metrics;
+pub mod session_mirror;
+pub mod slack;
+pub mod tool_usage;
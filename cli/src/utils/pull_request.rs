@@ -0,0 +1,303 @@
+use reqwest::header::{ACCEPT, AUTHORIZATION, USER_AGENT};
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+use stakpak_api::models::RunSummary;
+use std::process::Command;
+
+const GITHUB_API_URL: &str = "https://api.github.com";
+/// Longest pull request title and commit subject, longer ones are cut
+const TITLE_MAX_LEN: usize = 72;
+const DEFAULT_TITLE: &str = "Apply changes from Stakpak agent run";
+
+fn default_branch_prefix() -> String {
+    "stakpak/".to_string()
+}
+
+fn default_remote() -> String {
+    "origin".to_string()
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct PullRequestConfig {
+    /// Branch the pull request targets, defaults to the branch the run started on
+    pub base_branch: Option<String>,
+    /// Prefix of the branches created for runs
+    #[serde(default = "default_branch_prefix")]
+    pub branch_prefix: String,
+    /// Git remote the branch is pushed to
+    #[serde(default = "default_remote")]
+    pub remote: String,
+    /// Open the pull request as a draft
+    #[serde(default)]
+    pub draft: bool,
+}
+
+impl Default for PullRequestConfig {
+    fn default() -> Self {
+        Self {
+            base_branch: None,
+            branch_prefix: default_branch_prefix(),
+            remote: default_remote(),
+            draft: false,
+        }
+    }
+}
+
+#[derive(Debug, PartialEq)]
+pub enum GitHost {
+    GitHub,
+    GitLab { api_url: String },
+}
+
+/// Repository a git remote points to
+#[derive(Debug, PartialEq)]
+pub struct RemoteRepo {
+    pub host: GitHost,
+    /// `<owner>/<repo>`, GitLab groups may add more segments
+    pub path: String,
+}
+
+/// Parse `https://host/owner/repo.git`, `ssh://git@host:22/owner/repo.git` and
+/// `git@host:owner/repo.git` remote URLs
+pub fn parse_remote_url(url: &str) -> Result<RemoteRepo, String> {
+    let trimmed = url.trim().trim_end_matches('/');
+    let trimmed = trimmed.strip_suffix(".git").unwrap_or(trimmed);
+    let (host, path) = match trimmed.split_once("://") {
+        Some((_, rest)) => {
+            let rest = rest.rsplit_once('@').map(|(_, r)| r).unwrap_or(rest);
+            let (host, path) = rest
+                .split_once('/')
+                .ok_or_else(|| format!("Invalid remote URL: {}", url))?;
+            (host.split(':').next().unwrap_or(host), path)
+        }
+        None => {
+            let rest = trimmed.split_once('@').map(|(_, r)| r).unwrap_or(trimmed);
+            rest.split_once(':')
+                .ok_or_else(|| format!("Invalid remote URL: {}", url))?
+        }
+    };
+    if !path.contains('/') {
+        return Err(format!("Invalid remote URL: {}", url));
+    }
+
+    let host = if host == "github.com" {
+        GitHost::GitHub
+    } else if host.contains("gitlab") {
+        GitHost::GitLab {
+            api_url: format!("https://{}/api/v4", host),
+        }
+    } else {
+        return Err(format!(
+            "Unsupported git host {}, pull requests can be opened on GitHub and GitLab",
+            host
+        ));
+    };
+    Ok(RemoteRepo {
+        host,
+        path: path.to_string(),
+    })
+}
+
+fn git(args: &[&str]) -> Result<String, String> {
+    let output = Command::new("git")
+        .args(args)
+        .output()
+        .map_err(|e| format!("Failed to run git: {}", e))?;
+    if !output.status.success() {
+        return Err(format!(
+            "git {} failed: {}",
+            args.join(" "),
+            String::from_utf8_lossy(&output.stderr).trim()
+        ));
+    }
+    Ok(String::from_utf8_lossy(&output.stdout).trim().to_string())
+}
+
+pub fn has_uncommitted_changes() -> Result<bool, String> {
+    Ok(!git(&["status", "--porcelain"])?.is_empty())
+}
+
+/// Pull request title from the first line of the agent's final answer
+fn pull_request_title(summary: &RunSummary) -> String {
+    let line = summary
+        .conclusion
+        .lines()
+        .map(|line| line.trim().trim_start_matches('#').trim().replace("**", ""))
+        .find(|line| !line.is_empty())
+        .unwrap_or_else(|| DEFAULT_TITLE.to_string());
+    if line.chars().count() <= TITLE_MAX_LEN {
+        return line;
+    }
+    let cut: String = line.chars().take(TITLE_MAX_LEN - 3).collect();
+    format!("{}...", cut.trim_end())
+}
+
+fn commit_message(title: &str, summary: &RunSummary) -> String {
+    let mut message = format!("{}\n", title);
+    if !summary.changed_files.is_empty() {
+        message.push_str("\nChanged files:\n");
+        for change in &summary.changed_files {
+            message.push_str(&format!("- {}\n", change.path));
+        }
+    }
+    message
+}
+
+fn branch_name(prefix: &str, title: &str) -> String {
+    let mut slug = String::new();
+    for c in title.chars().flat_map(char::to_lowercase) {
+        if c.is_ascii_alphanumeric() {
+            slug.push(c);
+        } else if !slug.is_empty() && !slug.ends_with('-') {
+            slug.push('-');
+        }
+        if slug.len() >= 40 {
+            break;
+        }
+    }
+    let id = uuid::Uuid::new_v4().simple().to_string();
+    format!("{}{}-{}", prefix, slug.trim_end_matches('-'), &id[..8])
+}
+
+fn host_token(host: &GitHost) -> Result<String, String> {
+    let names: &[&str] = match host {
+        GitHost::GitHub => &["GITHUB_TOKEN", "GH_TOKEN"],
+        GitHost::GitLab { .. } => &["GITLAB_TOKEN"],
+    };
+    names
+        .iter()
+        .find_map(|name| std::env::var(name).ok().filter(|token| !token.is_empty()))
+        .ok_or_else(|| format!("Set {} to open pull requests", names.join(" or ")))
+}
+
+#[derive(Deserialize)]
+struct CreatedPullRequest {
+    #[serde(alias = "web_url")]
+    html_url: String,
+}
+
+async fn open_pull_request(
+    repo: &RemoteRepo,
+    token: &str,
+    branch: &str,
+    base: &str,
+    title: &str,
+    body: &str,
+    draft: bool,
+) -> Result<String, String> {
+    let client = reqwest::Client::new();
+    let request = match &repo.host {
+        GitHost::GitHub => client
+            .post(format!("{}/repos/{}/pulls", GITHUB_API_URL, repo.path))
+            .header(AUTHORIZATION, format!("Bearer {}", token))
+            .header(ACCEPT, "application/vnd.github+json")
+            .header(USER_AGENT, "stakpak-cli")
+            .json(&json!({
+                "title": title,
+                "head": branch,
+                "base": base,
+                "body": body,
+                "draft": draft,
+            })),
+        GitHost::GitLab { api_url } => client
+            .post(format!(
+                "{}/projects/{}/merge_requests",
+                api_url,
+                repo.path.replace('/', "%2F")
+            ))
+            .header("PRIVATE-TOKEN", token)
+            .json(&json!({
+                "title": if draft { format!("Draft: {}", title) } else { title.to_string() },
+                "source_branch": branch,
+                "target_branch": base,
+                "description": body,
+            })),
+    };
+
+    let response = request.send().await.map_err(|e| e.to_string())?;
+    if !response.status().is_success() {
+        let status = response.status();
+        let body = response.text().await.unwrap_or_default();
+        return Err(format!(
+            "Failed to open pull request ({}): {}",
+            status, body
+        ));
+    }
+    let created: CreatedPullRequest = response.json().await.map_err(|e| e.to_string())?;
+    Ok(created.html_url)
+}
+
+/// Commit the changes of a run to a new branch, push it and open a pull request with the
+/// run summary as description. Returns the pull request URL, or `None` without changes.
+pub async fn create_pull_request(
+    config: &PullRequestConfig,
+    summary: &RunSummary,
+) -> Result<Option<String>, String> {
+    if !has_uncommitted_changes()? {
+        return Ok(None);
+    }
+
+    // Fail before touching the repository if the pull request cannot be opened
+    let repo = parse_remote_url(&git(&["remote", "get-url", &config.remote])?)?;
+    let token = host_token(&repo.host)?;
+    let base = match &config.base_branch {
+        Some(base) => base.clone(),
+        None => git(&["rev-parse", "--abbrev-ref", "HEAD"])?,
+    };
+    if base == "HEAD" {
+        return Err("The run started on a detached HEAD, set pull_request.base_branch".into());
+    }
+
+    let title = pull_request_title(summary);
+    let branch = branch_name(&config.branch_prefix, &title);
+    git(&["checkout", "-b", &branch])?;
+    git(&["add", "-A"])?;
+    git(&["commit", "-m", &commit_message(&title, summary)])?;
+    git(&["push", "-u", &config.remote, &branch])?;
+
+    let url = open_pull_request(
+        &repo,
+        &token,
+        &branch,
+        &base,
+        &title,
+        &summary.to_markdown(),
+        config.draft,
+    )
+    .await?;
+    Ok(Some(url))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_remote_url() {
+        let github = |path: &str| RemoteRepo {
+            host: GitHost::GitHub,
+            path: path.to_string(),
+        };
+        assert_eq!(
+            parse_remote_url("git@github.com:stakpak/cli.git"),
+            Ok(github("stakpak/cli"))
+        );
+        assert_eq!(
+            parse_remote_url("https://github.com/stakpak/cli"),
+            Ok(github("stakpak/cli"))
+        );
+        assert_eq!(
+            parse_remote_url("ssh://git@gitlab.example.com:2222/infra/platform/network.git"),
+            Ok(RemoteRepo {
+                host: GitHost::GitLab {
+                    api_url: "https://gitlab.example.com/api/v4".to_string()
+                },
+                path: "infra/platform/network".to_string(),
+            })
+        );
+        assert!(parse_remote_url("https://bitbucket.org/acme/infra.git").is_err());
+
+        let branch = branch_name("stakpak/", "Upgrade the AWS provider to 5.x!");
+        assert!(branch.starts_with("stakpak/upgrade-the-aws-provider-to-5-x-"));
+    }
+}
@@ -0,0 +1,207 @@
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use stakpak_shared::local_store::LocalStore;
+
+/// What is left of the budgets, in the user store so every run of the user shares it
+const BUDGET_FILE: &str = "budget.json";
+
+/// Token bucket holding up to `capacity`, refilled at `refill_per_hour`
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct BucketConfig {
+    pub capacity: u64,
+    pub refill_per_hour: u64,
+}
+
+/// Budgets shared by every run of the user, interactive or not, including the runs of
+/// `stakpak fleet`, scheduled jobs and the summaries of history compaction, configured
+/// under `[budget]`. A run stops once one of them is exhausted.
+#[derive(Serialize, Deserialize, Clone, Debug, Default, PartialEq)]
+pub struct BudgetConfig {
+    /// Tokens of model responses
+    pub tokens: Option<BucketConfig>,
+    /// Chat completion requests
+    pub api_calls: Option<BucketConfig>,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+struct Bucket {
+    /// Goes below zero when a response uses more tokens than were left
+    available: f64,
+    updated_at: DateTime<Utc>,
+}
+
+impl Bucket {
+    /// The bucket in `state` refilled up to `now`, a full one the first time
+    fn refilled<'a>(
+        state: &'a mut Option<Bucket>,
+        config: &BucketConfig,
+        now: DateTime<Utc>,
+    ) -> &'a mut Bucket {
+        let bucket = state.get_or_insert_with(|| Bucket {
+            available: config.capacity as f64,
+            updated_at: now,
+        });
+        let hours = (now - bucket.updated_at).num_milliseconds().max(0) as f64 / 3_600_000.0;
+        bucket.available =
+            (bucket.available + hours * config.refill_per_hour as f64).min(config.capacity as f64);
+        bucket.updated_at = now;
+        bucket
+    }
+
+    fn exhausted_error(
+        &self,
+        key: &str,
+        label: &str,
+        config: &BucketConfig,
+        needed: f64,
+    ) -> String {
+        let refill = if config.refill_per_hour == 0 {
+            "it is not refilled".to_string()
+        } else {
+            let minutes =
+                ((needed - self.available) / config.refill_per_hour as f64 * 60.0).ceil() as u64;
+            format!("enough refills in ~{} min", minutes.max(1))
+        };
+        format!(
+            "The shared {} budget is exhausted ({} of {} left), {}. Raise [budget.{}] in ~/.stakpak/config.toml to continue sooner",
+            label,
+            self.available.max(0.0) as u64,
+            config.capacity,
+            refill,
+            key
+        )
+    }
+}
+
+#[derive(Serialize, Deserialize, Default, Debug, PartialEq)]
+struct BudgetState {
+    tokens: Option<Bucket>,
+    api_calls: Option<Bucket>,
+}
+
+impl BudgetState {
+    /// State stored by earlier runs, a full budget only when there is none yet. A corrupt
+    /// state is an error so it never hands out a full budget again
+    fn parse(content: Option<&str>) -> Result<Self, serde_json::Error> {
+        match content {
+            Some(content) => serde_json::from_str(content),
+            None => Ok(Self::default()),
+        }
+    }
+
+    /// Take an API call, as long as neither budget is exhausted
+    fn acquire_call(&mut self, config: &BudgetConfig, now: DateTime<Utc>) -> Result<(), String> {
+        if let Some(tokens) = &config.tokens {
+            let bucket = Bucket::refilled(&mut self.tokens, tokens, now);
+            if bucket.available <= 0.0 {
+                return Err(bucket.exhausted_error("tokens", "token", tokens, 1.0));
+            }
+        }
+        if let Some(api_calls) = &config.api_calls {
+            let bucket = Bucket::refilled(&mut self.api_calls, api_calls, now);
+            if bucket.available < 1.0 {
+                return Err(bucket.exhausted_error("api_calls", "API call", api_calls, 1.0));
+            }
+            bucket.available -= 1.0;
+        }
+        Ok(())
+    }
+
+    /// Take the tokens a response used, known only once it arrived
+    fn consume_tokens(&mut self, config: &BudgetConfig, tokens: u64, now: DateTime<Utc>) {
+        if let Some(config) = &config.tokens {
+            Bucket::refilled(&mut self.tokens, config, now).available -= tokens as f64;
+        }
+    }
+}
+
+impl BudgetConfig {
+    /// Read-modify-write the shared state, locked against the other runs
+    fn update<T>(
+        &self,
+        update: impl FnOnce(&mut BudgetState, DateTime<Utc>) -> Result<T, String>,
+    ) -> Result<T, String> {
+        let store = LocalStore::user()?;
+        let path = store.path(BUDGET_FILE);
+        store.update(BUDGET_FILE, |content| {
+            let mut state = BudgetState::parse(content.as_deref()).map_err(|e| {
+                format!(
+                    "The shared budget in {} is corrupt ({}), fix or remove it to continue",
+                    path.display(),
+                    e
+                )
+            })?;
+            let value = update(&mut state, Utc::now())?;
+            let content = serde_json::to_string_pretty(&state)
+                .map_err(|e| format!("Failed to serialize budget: {}", e))?;
+            Ok((content, value))
+        })
+    }
+
+    /// Take an API call from the shared budget before a completion request
+    pub fn acquire_call(&self) -> Result<(), String> {
+        self.update(|state, now| state.acquire_call(self, now))
+    }
+
+    /// Take the tokens of a completion from the shared budget. The response already
+    /// arrived, so failing to record it is logged rather than failing the run
+    pub fn consume_tokens(&self, tokens: u64) {
+        let result = self.update(|state, now| {
+            state.consume_tokens(self, tokens, now);
+            Ok(())
+        });
+        if let Err(e) = result {
+            tracing::warn!(
+                "Failed to take {} tokens from the shared budget: {}",
+                tokens,
+                e
+            );
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::TimeDelta;
+
+    #[test]
+    fn test_buckets_refill_over_time() {
+        let config = BudgetConfig {
+            tokens: Some(BucketConfig {
+                capacity: 1000,
+                refill_per_hour: 500,
+            }),
+            api_calls: Some(BucketConfig {
+                capacity: 2,
+                refill_per_hour: 1,
+            }),
+        };
+        let mut state = BudgetState::default();
+        let now = Utc::now();
+
+        assert!(state.acquire_call(&config, now).is_ok());
+        state.consume_tokens(&config, 1500, now);
+        let error = state.acquire_call(&config, now).err().unwrap_or_default();
+        assert!(error.contains("token budget is exhausted"));
+        assert!(error.contains("~61 min"));
+
+        // Two hours later the tokens are back above zero and both calls are refilled
+        let later = now + TimeDelta::hours(2);
+        assert!(state.acquire_call(&config, later).is_ok());
+        assert!(state.acquire_call(&config, later).is_ok());
+        assert!(state.acquire_call(&config, later).is_err());
+    }
+
+    #[test]
+    fn test_corrupt_state_is_an_error() {
+        assert_eq!(BudgetState::parse(None).unwrap(), BudgetState::default());
+        assert!(BudgetState::parse(Some("{\"tokens\": ")).is_err());
+        assert!(BudgetState::parse(Some("")).is_err());
+        let state = BudgetState::parse(Some(
+            r#"{"tokens": {"available": 12.5, "updated_at": "2026-01-01T00:00:00Z"}, "api_calls": null}"#,
+        ))
+        .unwrap();
+        assert_eq!(state.tokens.map(|bucket| bucket.available), Some(12.5));
+    }
+}
@@ -0,0 +1,210 @@
+use crate::utils::tool_usage::ToolStats;
+use chrono::{DateTime, TimeDelta, Utc};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::io::Write;
+use std::path::PathBuf;
+use std::sync::Mutex;
+use std::time::Instant;
+
+/// Metrics of the running session, one session runs per process
+static SESSION_METRICS: Mutex<Option<(Instant, SessionMetrics)>> = Mutex::new(None);
+
+/// Efficiency metrics of one agent session, one JSON document per line in the metrics file
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct SessionMetrics {
+    pub started_at: DateTime<Utc>,
+    /// interactive, async or non-interactive
+    pub mode: String,
+    /// Model responses
+    pub turns: u64,
+    pub tool_calls: BTreeMap<String, ToolStats>,
+    pub wall_time_secs: f64,
+    /// Tokens reported by the API, estimated from the streamed output when it reports none
+    pub tokens: u64,
+}
+
+pub fn metrics_path() -> PathBuf {
+    PathBuf::from(".stakpak").join("metrics.jsonl")
+}
+
+/// Start recording the metrics of a session run in `mode`
+pub fn start_session_metrics(mode: &str) {
+    if let Ok(mut metrics) = SESSION_METRICS.lock() {
+        *metrics = Some((
+            Instant::now(),
+            SessionMetrics {
+                started_at: Utc::now(),
+                mode: mode.to_string(),
+                turns: 0,
+                tool_calls: BTreeMap::new(),
+                wall_time_secs: 0.0,
+                tokens: 0,
+            },
+        ));
+    }
+}
+
+fn update_session_metrics(update: impl FnOnce(&mut SessionMetrics)) {
+    if let Ok(mut metrics) = SESSION_METRICS.lock() {
+        if let Some((_, metrics)) = metrics.as_mut() {
+            update(metrics);
+        }
+    }
+}
+
+/// Count a model response and the tokens it used
+pub fn record_turn(tokens: u64) {
+    update_session_metrics(|metrics| {
+        metrics.turns += 1;
+        metrics.tokens += tokens;
+    });
+}
+
+pub fn record_tool_metrics(tool_name: &str, failed: bool) {
+    update_session_metrics(|metrics| {
+        let stats = metrics.tool_calls.entry(tool_name.to_string()).or_default();
+        stats.calls += 1;
+        if failed {
+            stats.failures += 1;
+        }
+    });
+}
+
+/// Append the metrics of the session to the metrics file. Sessions without any model
+/// response are not worth keeping.
+pub fn finish_session_metrics() -> Result<(), String> {
+    let Some((started, mut metrics)) = SESSION_METRICS.lock().map_err(|e| e.to_string())?.take()
+    else {
+        return Ok(());
+    };
+    if metrics.turns == 0 {
+        return Ok(());
+    }
+    metrics.wall_time_secs = started.elapsed().as_secs_f64();
+
+    let path = metrics_path();
+    if let Some(dir) = path.parent() {
+        std::fs::create_dir_all(dir)
+            .map_err(|e| format!("Failed to create {}: {}", dir.display(), e))?;
+    }
+    let line = serde_json::to_string(&metrics).map_err(|e| e.to_string())?;
+    let mut file = std::fs::OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(&path)
+        .map_err(|e| format!("Failed to open {}: {}", path.display(), e))?;
+    writeln!(file, "{}", line).map_err(|e| format!("Failed to write {}: {}", path.display(), e))
+}
+
+/// Parse a period like `30d`, `12h` or `2w`
+pub fn parse_period(period: &str) -> Result<TimeDelta, String> {
+    let invalid = || format!("Invalid period '{}', expected e.g. 12h, 30d or 2w", period);
+    let unit_start = period.char_indices().last().map_or(0, |(index, _)| index);
+    let (amount, unit) = period.split_at(unit_start);
+    let amount: i64 = amount.parse().map_err(|_| invalid())?;
+    match unit {
+        "h" => TimeDelta::try_hours(amount),
+        "d" => TimeDelta::try_days(amount),
+        "w" => TimeDelta::try_weeks(amount),
+        _ => None,
+    }
+    .ok_or_else(invalid)
+}
+
+/// Sessions recorded in the metrics file that started after `since`
+pub fn load_session_metrics(since: DateTime<Utc>) -> Result<Vec<SessionMetrics>, String> {
+    let path = metrics_path();
+    let content = match std::fs::read_to_string(&path) {
+        Ok(content) => content,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(Vec::new()),
+        Err(e) => return Err(format!("Failed to read {}: {}", path.display(), e)),
+    };
+    Ok(content
+        .lines()
+        .filter_map(|line| serde_json::from_str::<SessionMetrics>(line).ok())
+        .filter(|metrics| metrics.started_at >= since)
+        .collect())
+}
+
+/// Totals over many sessions
+#[derive(Debug, Default, PartialEq)]
+pub struct MetricsSummary {
+    pub sessions: usize,
+    pub turns: u64,
+    pub tokens: u64,
+    pub wall_time_secs: f64,
+    pub tool_calls: BTreeMap<String, ToolStats>,
+}
+
+impl MetricsSummary {
+    pub fn total_tool_calls(&self) -> u64 {
+        self.tool_calls.values().map(|stats| stats.calls).sum()
+    }
+
+    pub fn failure_rate(&self) -> f64 {
+        let calls = self.total_tool_calls();
+        if calls == 0 {
+            return 0.0;
+        }
+        self.tool_calls
+            .values()
+            .map(|stats| stats.failures)
+            .sum::<u64>() as f64
+            / calls as f64
+    }
+}
+
+pub fn summarize_metrics(sessions: &[SessionMetrics]) -> MetricsSummary {
+    let mut summary = MetricsSummary {
+        sessions: sessions.len(),
+        ..Default::default()
+    };
+    for session in sessions {
+        summary.turns += session.turns;
+        summary.tokens += session.tokens;
+        summary.wall_time_secs += session.wall_time_secs;
+        for (tool, stats) in &session.tool_calls {
+            let total = summary.tool_calls.entry(tool.clone()).or_default();
+            total.calls += stats.calls;
+            total.failures += stats.failures;
+        }
+    }
+    summary
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_summarize_metrics() {
+        assert_eq!(parse_period("30d"), Ok(TimeDelta::days(30)));
+        assert_eq!(parse_period("12h"), Ok(TimeDelta::hours(12)));
+        assert!(parse_period("30").is_err());
+        assert!(parse_period("").is_err());
+
+        let session = |run_command: ToolStats| SessionMetrics {
+            started_at: Utc::now(),
+            mode: "interactive".to_string(),
+            turns: 4,
+            tool_calls: BTreeMap::from([("run_command".to_string(), run_command)]),
+            wall_time_secs: 60.0,
+            tokens: 1000,
+        };
+        let summary = summarize_metrics(&[
+            session(ToolStats {
+                calls: 3,
+                failures: 1,
+            }),
+            session(ToolStats {
+                calls: 1,
+                failures: 0,
+            }),
+        ]);
+        assert_eq!(summary.sessions, 2);
+        assert_eq!(summary.turns, 8);
+        assert_eq!(summary.total_tool_calls(), 4);
+        assert_eq!(summary.failure_rate(), 0.25);
+    }
+}
@@ -0,0 +1,317 @@
+use crate::utils::markdown::MarkdownRenderer;
+use serde::{Deserialize, Serialize};
+use stakpak_shared::models::integrations::openai::ToolCall;
+use std::collections::VecDeque;
+use std::path::PathBuf;
+
+/// Tool results longer than this are cut when shown to attached viewers
+const MAX_RESULT_LINES: usize = 40;
+/// Events replayed to viewers that attach late, older events are dropped
+const MAX_BACKLOG_EVENTS: usize = 500;
+
+/// Conversation update streamed to `stakpak attach` viewers, one JSON document per line
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum MirrorEvent {
+    User {
+        content: String,
+    },
+    Assistant {
+        content: String,
+        tool_calls: Vec<ToolCall>,
+    },
+    ToolResult {
+        name: String,
+        result: String,
+    },
+}
+
+fn sockets_dir() -> PathBuf {
+    PathBuf::from(format!(
+        "{}/.stakpak/sessions",
+        std::env::var("HOME").unwrap_or_default()
+    ))
+}
+
+fn socket_path(session_id: &str) -> PathBuf {
+    sockets_dir().join(format!("{}.sock", session_id))
+}
+
+/// IDs of the local sessions currently accepting viewers
+pub fn running_sessions() -> Vec<String> {
+    let Ok(entries) = std::fs::read_dir(sockets_dir()) else {
+        return Vec::new();
+    };
+    let mut sessions: Vec<String> = entries
+        .filter_map(|entry| entry.ok())
+        .filter_map(|entry| {
+            entry
+                .file_name()
+                .to_str()
+                .and_then(|name| name.strip_suffix(".sock"))
+                .map(|name| name.to_string())
+        })
+        .collect();
+    sessions.sort();
+    sessions
+}
+
+fn render_event(event: &MirrorEvent) -> String {
+    match event {
+        MirrorEvent::User { content } => format!("**User**\n\n{}", content),
+        MirrorEvent::Assistant {
+            content,
+            tool_calls,
+        } => {
+            let mut markdown = content.clone();
+            for tool_call in tool_calls {
+                markdown.push_str(&format!(
+                    "\n\n**Tool call** `{}`\n```json\n{}\n```",
+                    tool_call.function.name, tool_call.function.arguments
+                ));
+            }
+            markdown
+        }
+        MirrorEvent::ToolResult { name, result } => {
+            format!("**Tool result** `{}`\n```\n{}\n```", name, result)
+        }
+    }
+}
+
+/// The event as published to viewers, long tool results are cut before they are sent or
+/// kept in the backlog
+fn viewer_event(event: MirrorEvent) -> MirrorEvent {
+    let MirrorEvent::ToolResult { name, result } = event else {
+        return event;
+    };
+    let lines: Vec<&str> = result.lines().collect();
+    if lines.len() <= MAX_RESULT_LINES {
+        return MirrorEvent::ToolResult { name, result };
+    }
+    let mut shown = lines[..MAX_RESULT_LINES].join("\n");
+    shown.push_str(&format!(
+        "\n... {} more lines",
+        lines.len() - MAX_RESULT_LINES
+    ));
+    MirrorEvent::ToolResult {
+        name,
+        result: shown,
+    }
+}
+
+/// Add a published line to the backlog, dropping the oldest past [`MAX_BACKLOG_EVENTS`]
+fn push_backlog(backlog: &mut VecDeque<String>, line: String) {
+    backlog.push_back(line);
+    if backlog.len() > MAX_BACKLOG_EVENTS {
+        backlog.pop_front();
+    }
+}
+
+#[cfg(unix)]
+mod host {
+    use super::{MirrorEvent, push_backlog, socket_path, sockets_dir, viewer_event};
+    use std::collections::VecDeque;
+    use std::path::PathBuf;
+    use std::sync::{Arc, Mutex};
+    use tokio::io::AsyncWriteExt;
+    use tokio::net::UnixListener;
+    use tokio::sync::broadcast;
+
+    /// Publishes the conversation of an interactive session on a per-session Unix socket.
+    /// Viewers first receive the latest events published so far, then live updates.
+    #[derive(Clone)]
+    pub struct SessionMirror {
+        session_id: String,
+        backlog: Arc<Mutex<VecDeque<String>>>,
+        tx: broadcast::Sender<String>,
+        _socket: Arc<SocketFile>,
+    }
+
+    /// Removes the socket file once the last mirror handle is dropped
+    struct SocketFile(PathBuf);
+
+    impl Drop for SocketFile {
+        fn drop(&mut self) {
+            let _ = std::fs::remove_file(&self.0);
+        }
+    }
+
+    impl SessionMirror {
+        pub fn start(session_id: &str) -> Result<Self, String> {
+            let dir = sockets_dir();
+            std::fs::create_dir_all(&dir)
+                .map_err(|e| format!("Failed to create {}: {}", dir.display(), e))?;
+            {
+                // Only the current user may watch their sessions
+                use std::os::unix::fs::PermissionsExt;
+                std::fs::set_permissions(&dir, std::fs::Permissions::from_mode(0o700))
+                    .map_err(|e| e.to_string())?;
+            }
+
+            let path = socket_path(session_id);
+            let _ = std::fs::remove_file(&path);
+            let listener = UnixListener::bind(&path)
+                .map_err(|e| format!("Failed to listen on {}: {}", path.display(), e))?;
+
+            let (tx, _) = broadcast::channel::<String>(256);
+            let backlog = Arc::new(Mutex::new(VecDeque::<String>::new()));
+
+            let accept_tx = tx.clone();
+            let accept_backlog = backlog.clone();
+            tokio::spawn(async move {
+                while let Ok((mut stream, _)) = listener.accept().await {
+                    // Snapshot and subscribe under the lock so no event is missed or repeated
+                    let (history, mut rx) = match accept_backlog.lock() {
+                        Ok(backlog) => (backlog.clone(), accept_tx.subscribe()),
+                        Err(_) => break,
+                    };
+                    tokio::spawn(async move {
+                        for line in history {
+                            if stream.write_all(line.as_bytes()).await.is_err() {
+                                return;
+                            }
+                        }
+                        loop {
+                            match rx.recv().await {
+                                Ok(line) => {
+                                    if stream.write_all(line.as_bytes()).await.is_err() {
+                                        return;
+                                    }
+                                }
+                                Err(broadcast::error::RecvError::Lagged(_)) => continue,
+                                Err(broadcast::error::RecvError::Closed) => return,
+                            }
+                        }
+                    });
+                }
+            });
+
+            Ok(Self {
+                session_id: session_id.to_string(),
+                backlog,
+                tx,
+                _socket: Arc::new(SocketFile(path)),
+            })
+        }
+
+        pub fn session_id(&self) -> &str {
+            &self.session_id
+        }
+
+        pub fn publish(&self, event: MirrorEvent) {
+            let Ok(mut line) = serde_json::to_string(&viewer_event(event)) else {
+                return;
+            };
+            line.push('\n');
+            if let Ok(mut backlog) = self.backlog.lock() {
+                push_backlog(&mut backlog, line.clone());
+                // No receivers just means nobody is watching
+                let _ = self.tx.send(line);
+            }
+        }
+    }
+}
+
+#[cfg(unix)]
+pub use host::SessionMirror;
+
+#[cfg(not(unix))]
+#[derive(Clone)]
+pub struct SessionMirror;
+
+#[cfg(not(unix))]
+impl SessionMirror {
+    pub fn start(_session_id: &str) -> Result<Self, String> {
+        Err("Session mirroring is only supported on Unix systems".to_string())
+    }
+
+    pub fn session_id(&self) -> &str {
+        ""
+    }
+
+    pub fn publish(&self, _event: MirrorEvent) {}
+}
+
+/// Follow a running local session read-only until it ends
+#[cfg(unix)]
+pub async fn attach(session_id: &str, renderer: &MarkdownRenderer) -> Result<(), String> {
+    use tokio::io::{AsyncBufReadExt, BufReader};
+    use tokio::net::UnixStream;
+
+    let stream = UnixStream::connect(socket_path(session_id))
+        .await
+        .map_err(|e| format!("No running session {} to attach to: {}", session_id, e))?;
+    println!(
+        "Attached to session {} (read-only, press Ctrl+C to detach)\n",
+        session_id
+    );
+
+    let mut lines = BufReader::new(stream).lines();
+    while let Some(line) = lines
+        .next_line()
+        .await
+        .map_err(|e| format!("Lost connection to session: {}", e))?
+    {
+        if let Ok(event) = serde_json::from_str::<MirrorEvent>(&line) {
+            renderer.print(&render_event(&event));
+        }
+    }
+
+    println!("Session {} ended", session_id);
+    Ok(())
+}
+
+#[cfg(not(unix))]
+pub async fn attach(_session_id: &str, _renderer: &MarkdownRenderer) -> Result<(), String> {
+    Err("Attaching to sessions is only supported on Unix systems".to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_mirror_event_wire_format() {
+        let event = MirrorEvent::ToolResult {
+            name: "run_command".to_string(),
+            result: "ok".to_string(),
+        };
+        let line = serde_json::to_string(&event).unwrap();
+        assert_eq!(
+            line,
+            r#"{"type":"tool_result","name":"run_command","result":"ok"}"#
+        );
+        assert_eq!(serde_json::from_str::<MirrorEvent>(&line).unwrap(), event);
+    }
+
+    #[test]
+    fn test_long_tool_results_are_cut() {
+        let result = (0..50)
+            .map(|i| i.to_string())
+            .collect::<Vec<_>>()
+            .join("\n");
+        let rendered = render_event(&viewer_event(MirrorEvent::ToolResult {
+            name: "run_command".to_string(),
+            result,
+        }));
+        assert!(rendered.contains("\n39\n"));
+        assert!(!rendered.contains("\n40\n"));
+        assert!(rendered.contains("... 10 more lines"));
+
+        let short = MirrorEvent::ToolResult {
+            name: "run_command".to_string(),
+            result: "ok".to_string(),
+        };
+        assert_eq!(viewer_event(short.clone()), short);
+    }
+
+    #[test]
+    fn test_backlog_keeps_the_latest_events() {
+        let mut backlog = VecDeque::new();
+        for i in 0..MAX_BACKLOG_EVENTS + 10 {
+            push_backlog(&mut backlog, i.to_string());
+        }
+        assert_eq!(backlog.len(), MAX_BACKLOG_EVENTS);
+        assert_eq!(backlog.front().map(String::as_str), Some("10"));
+    }
+}
@@ -0,0 +1,273 @@
+use reqwest::header::{AUTHORIZATION, HeaderMap, HeaderValue};
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+use stakpak_shared::models::integrations::openai::ToolCall;
+use std::time::{Duration, Instant};
+
+const SLACK_API_URL: &str = "https://slack.com/api";
+const APPROVE_REACTION: &str = "white_check_mark";
+const DENY_REACTION: &str = "x";
+const POLL_INTERVAL: Duration = Duration::from_secs(5);
+/// Longest wait between polls while Slack keeps failing
+const MAX_POLL_BACKOFF: Duration = Duration::from_secs(60);
+/// Slack API errors worth retrying, anything else (revoked token, missing scope, unknown
+/// channel) won't fix itself while we wait
+const TRANSIENT_ERRORS: &[&str] = &[
+    "ratelimited",
+    "internal_error",
+    "fatal_error",
+    "service_unavailable",
+    "request_timeout",
+];
+
+fn default_approval_timeout_secs() -> u64 {
+    900
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct SlackConfig {
+    /// Bot token with chat:write, reactions:read and reactions:write scopes
+    pub bot_token: String,
+    /// Channel ID to post approval requests to
+    pub channel: String,
+    /// Seconds to wait for a decision before denying the tool call
+    #[serde(default = "default_approval_timeout_secs")]
+    pub approval_timeout_secs: u64,
+}
+
+#[derive(Debug, PartialEq)]
+pub enum ApprovalDecision {
+    Approved(String),
+    Denied(String),
+    TimedOut,
+}
+
+/// Why polling for reactions failed
+#[derive(Debug)]
+enum PollError {
+    /// Network failures, unreadable responses and Slack errors worth retrying
+    Transient(String),
+    Fatal(String),
+}
+
+fn is_transient_error(error: &str) -> bool {
+    TRANSIENT_ERRORS.contains(&error)
+}
+
+/// Wait before the next poll after a failed one, doubling up to MAX_POLL_BACKOFF
+fn next_backoff(backoff: Duration) -> Duration {
+    (backoff * 2).min(MAX_POLL_BACKOFF)
+}
+
+#[derive(Deserialize)]
+struct SlackResponse {
+    ok: bool,
+    error: Option<String>,
+    ts: Option<String>,
+    user_id: Option<String>,
+    message: Option<SlackMessage>,
+}
+
+#[derive(Deserialize)]
+struct SlackMessage {
+    #[serde(default)]
+    reactions: Vec<SlackReaction>,
+}
+
+#[derive(Deserialize)]
+struct SlackReaction {
+    name: String,
+    #[serde(default)]
+    users: Vec<String>,
+}
+
+/// Delegates tool call approvals to a Slack channel. Reviewers approve or deny
+/// by reacting to the posted message, which is polled so no public callback is needed.
+pub struct SlackApprover {
+    client: reqwest::Client,
+    config: SlackConfig,
+    bot_user_id: String,
+}
+
+impl SlackApprover {
+    pub async fn new(config: SlackConfig) -> Result<Self, String> {
+        let mut headers = HeaderMap::new();
+        headers.insert(
+            AUTHORIZATION,
+            HeaderValue::from_str(&format!("Bearer {}", config.bot_token))
+                .map_err(|e| e.to_string())?,
+        );
+        let client = reqwest::Client::builder()
+            .default_headers(headers)
+            .build()
+            .map_err(|e| e.to_string())?;
+
+        let mut approver = Self {
+            client,
+            config,
+            bot_user_id: String::new(),
+        };
+        let auth = approver.call("auth.test", json!({})).await?;
+        approver.bot_user_id = auth.user_id.unwrap_or_default();
+
+        Ok(approver)
+    }
+
+    async fn call(&self, method: &str, body: serde_json::Value) -> Result<SlackResponse, String> {
+        let response: SlackResponse = self
+            .client
+            .post(format!("{}/{}", SLACK_API_URL, method))
+            .json(&body)
+            .send()
+            .await
+            .map_err(|e| e.to_string())?
+            .json()
+            .await
+            .map_err(|e| e.to_string())?;
+
+        if !response.ok {
+            return Err(format!(
+                "Slack {} failed: {}",
+                method,
+                response
+                    .error
+                    .unwrap_or_else(|| "unknown error".to_string())
+            ));
+        }
+        Ok(response)
+    }
+
+    async fn get_reactions(&self, ts: &str) -> Result<Vec<SlackReaction>, PollError> {
+        let response: SlackResponse = self
+            .client
+            .get(format!("{}/reactions.get", SLACK_API_URL))
+            .query(&[("channel", self.config.channel.as_str()), ("timestamp", ts)])
+            .send()
+            .await
+            .map_err(|e| PollError::Transient(e.to_string()))?
+            .json()
+            .await
+            .map_err(|e| PollError::Transient(e.to_string()))?;
+
+        if !response.ok {
+            let error = response
+                .error
+                .unwrap_or_else(|| "unknown error".to_string());
+            let message = format!("Slack reactions.get failed: {}", error);
+            return Err(if is_transient_error(&error) {
+                PollError::Transient(message)
+            } else {
+                PollError::Fatal(message)
+            });
+        }
+        Ok(response.message.map(|m| m.reactions).unwrap_or_default())
+    }
+
+    /// Post the pending tool call to Slack and wait until a reviewer reacts or the timeout expires
+    pub async fn request_approval(&self, tool_call: &ToolCall) -> Result<ApprovalDecision, String> {
+        let text = format!(
+            "*Stakpak agent is waiting for approval*\nTool: `{}`\n```{}```\nReact with :{}: to approve or :{}: to deny.",
+            tool_call.function.name, tool_call.function.arguments, APPROVE_REACTION, DENY_REACTION
+        );
+        let posted = self
+            .call(
+                "chat.postMessage",
+                json!({ "channel": self.config.channel, "text": text }),
+            )
+            .await?;
+        let ts = posted
+            .ts
+            .ok_or("Slack chat.postMessage did not return a message timestamp")?;
+
+        // Seed both reactions so reviewers only need a single click
+        for reaction in [APPROVE_REACTION, DENY_REACTION] {
+            let _ = self
+                .call(
+                    "reactions.add",
+                    json!({ "channel": self.config.channel, "timestamp": ts, "name": reaction }),
+                )
+                .await;
+        }
+
+        let deadline = Instant::now() + Duration::from_secs(self.config.approval_timeout_secs);
+        let mut wait = POLL_INTERVAL;
+        let decision = loop {
+            let now = Instant::now();
+            if now >= deadline {
+                break ApprovalDecision::TimedOut;
+            }
+            tokio::time::sleep(wait.min(deadline - now)).await;
+
+            // A hiccup while polling must not deny a call a reviewer may be approving, keep
+            // retrying with backoff until the approval timeout
+            let reactions = match self.get_reactions(&ts).await {
+                Ok(reactions) => {
+                    wait = POLL_INTERVAL;
+                    reactions
+                }
+                Err(PollError::Transient(error)) => {
+                    wait = next_backoff(wait);
+                    tracing::warn!("{}, retrying in {}s", error, wait.as_secs());
+                    continue;
+                }
+                Err(PollError::Fatal(error)) => return Err(error),
+            };
+            let reviewer = |name: &str| {
+                reactions
+                    .iter()
+                    .find(|r| r.name == name)
+                    .and_then(|r| r.users.iter().find(|u| **u != self.bot_user_id))
+                    .cloned()
+            };
+
+            // Denials win over approvals when both are present
+            if let Some(user) = reviewer(DENY_REACTION) {
+                break ApprovalDecision::Denied(user);
+            }
+            if let Some(user) = reviewer(APPROVE_REACTION) {
+                break ApprovalDecision::Approved(user);
+            }
+        };
+
+        let outcome = match &decision {
+            ApprovalDecision::Approved(user) => {
+                format!(":{}: Approved by <@{}>", APPROVE_REACTION, user)
+            }
+            ApprovalDecision::Denied(user) => format!(":{}: Denied by <@{}>", DENY_REACTION, user),
+            ApprovalDecision::TimedOut => {
+                "Timed out waiting for approval, tool call denied".to_string()
+            }
+        };
+        let _ = self
+            .call(
+                "chat.postMessage",
+                json!({ "channel": self.config.channel, "thread_ts": ts, "text": outcome }),
+            )
+            .await;
+
+        Ok(decision)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_poll_backoff() {
+        assert_eq!(next_backoff(POLL_INTERVAL), Duration::from_secs(10));
+        let mut wait = POLL_INTERVAL;
+        for _ in 0..10 {
+            wait = next_backoff(wait);
+        }
+        assert_eq!(wait, MAX_POLL_BACKOFF);
+    }
+
+    #[test]
+    fn test_transient_errors() {
+        assert!(is_transient_error("ratelimited"));
+        assert!(is_transient_error("internal_error"));
+        assert!(!is_transient_error("invalid_auth"));
+        assert!(!is_transient_error("channel_not_found"));
+    }
+}
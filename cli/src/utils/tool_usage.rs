@@ -0,0 +1,160 @@
+use serde::{Deserialize, Serialize};
+use stakpak_shared::local_store::LocalStore;
+use stakpak_shared::models::integrations::openai::Tool;
+use std::cmp::Reverse;
+use std::collections::BTreeMap;
+use std::sync::atomic::{AtomicBool, Ordering};
+
+const TOOL_USAGE_FILE: &str = "tool_usage.json";
+/// Calls recorded in a workspace before its statistics are trusted to reorder tools
+const MIN_CALLS_FOR_HINTS: u64 = 30;
+/// Tools used for less than this share of all calls are marked as rarely useful
+const RARELY_USED_RATIO: f64 = 0.01;
+
+const RARELY_USED_NOTE: &str = "\n\nRarely useful in this workspace, prefer other tools unless this one is clearly the right fit.";
+
+static TOOL_USAGE_ENABLED: AtomicBool = AtomicBool::new(true);
+
+/// Enable or disable tracking tool usage and reordering tools based on it
+pub fn set_tool_usage_hints(enabled: bool) {
+    TOOL_USAGE_ENABLED.store(enabled, Ordering::SeqCst);
+}
+
+#[derive(Serialize, Deserialize, Default, Debug, Clone, PartialEq)]
+pub struct ToolStats {
+    pub calls: u64,
+    pub failures: u64,
+}
+
+/// Tool calls made by the agent in the current workspace
+#[derive(Serialize, Deserialize, Default, Debug)]
+pub struct ToolUsage {
+    pub tools: BTreeMap<String, ToolStats>,
+}
+
+impl ToolUsage {
+    pub fn load() -> Self {
+        LocalStore::read_session_data(TOOL_USAGE_FILE)
+            .ok()
+            .and_then(|content| serde_json::from_str(&content).ok())
+            .unwrap_or_default()
+    }
+
+    pub fn record(&mut self, tool_name: &str, failed: bool) {
+        let stats = self.tools.entry(tool_name.to_string()).or_default();
+        stats.calls += 1;
+        if failed {
+            stats.failures += 1;
+        }
+    }
+
+    pub fn total_calls(&self) -> u64 {
+        self.tools.values().map(|stats| stats.calls).sum()
+    }
+
+    fn calls(&self, tool_name: &str) -> u64 {
+        self.tools.get(tool_name).map_or(0, |stats| stats.calls)
+    }
+}
+
+/// Count a tool call in the workspace statistics. Failing to persist them is not worth
+/// interrupting the session for.
+pub fn record_tool_call(tool_name: &str, failed: bool) {
+    if !TOOL_USAGE_ENABLED.load(Ordering::SeqCst) {
+        return;
+    }
+    let _ = LocalStore::update_session_data(TOOL_USAGE_FILE, |content| {
+        let mut usage: ToolUsage = content
+            .and_then(|content| serde_json::from_str(&content).ok())
+            .unwrap_or_default();
+        usage.record(tool_name, failed);
+        let content = serde_json::to_string_pretty(&usage).map_err(|e| e.to_string())?;
+        Ok((content, ()))
+    });
+}
+
+/// Order tools by how often they were used in this workspace and mark the rarely used ones,
+/// so the model looks at the most relevant tools first in long tool lists
+pub fn prioritize_tools(tools: &mut [Tool]) {
+    if !TOOL_USAGE_ENABLED.load(Ordering::SeqCst) {
+        return;
+    }
+    apply_usage_hints(tools, &ToolUsage::load());
+}
+
+fn apply_usage_hints(tools: &mut [Tool], usage: &ToolUsage) {
+    let total = usage.total_calls();
+    if total < MIN_CALLS_FOR_HINTS {
+        return;
+    }
+
+    // Stable sort keeps the server order between tools used equally often
+    tools.sort_by_key(|tool| Reverse(usage.calls(&tool.function.name)));
+
+    for tool in tools.iter_mut() {
+        let share = usage.calls(&tool.function.name) as f64 / total as f64;
+        if share < RARELY_USED_RATIO {
+            tool.function
+                .description
+                .get_or_insert_with(String::new)
+                .push_str(RARELY_USED_NOTE);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use stakpak_shared::models::integrations::openai::FunctionDefinition;
+
+    fn tool(name: &str) -> Tool {
+        Tool {
+            r#type: "function".to_string(),
+            function: FunctionDefinition {
+                name: name.to_string(),
+                description: Some(name.to_string()),
+                parameters: serde_json::json!({}),
+            },
+        }
+    }
+
+    fn usage(calls: &[(&str, u64)]) -> ToolUsage {
+        let mut usage = ToolUsage::default();
+        for (name, count) in calls {
+            for _ in 0..*count {
+                usage.record(name, false);
+            }
+        }
+        usage
+    }
+
+    #[test]
+    fn test_most_used_tools_first_and_unused_marked() {
+        let mut tools = vec![tool("view"), tool("search_docs"), tool("run_command")];
+        apply_usage_hints(&mut tools, &usage(&[("run_command", 80), ("view", 20)]));
+
+        let names: Vec<&str> = tools.iter().map(|t| t.function.name.as_str()).collect();
+        assert_eq!(names, vec!["run_command", "view", "search_docs"]);
+        assert_eq!(
+            tools[0].function.description.as_deref(),
+            Some("run_command")
+        );
+        assert!(
+            tools[2]
+                .function
+                .description
+                .as_deref()
+                .is_some_and(|d| d.ends_with(RARELY_USED_NOTE))
+        );
+    }
+
+    #[test]
+    fn test_no_hints_without_enough_history() {
+        let mut tools = vec![tool("view"), tool("run_command")];
+        apply_usage_hints(&mut tools, &usage(&[("run_command", 5)]));
+
+        let names: Vec<&str> = tools.iter().map(|t| t.function.name.as_str()).collect();
+        assert_eq!(names, vec!["view", "run_command"]);
+        assert_eq!(tools[0].function.description.as_deref(), Some("view"));
+    }
+}
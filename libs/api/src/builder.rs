@@ -0,0 +1,385 @@
+use crate::Client;
+use crate::failover::EndpointPool;
+use reqwest::{Client as ReqwestClient, Error as ReqwestError, RequestBuilder, Response, header};
+use serde::Serialize;
+use std::sync::Arc;
+use std::time::Duration;
+
+pub const DEFAULT_API_ENDPOINT: &str = "https://apiv2.stakpak.dev";
+
+/// How failed requests are retried. Only connection errors, timeouts and
+/// 429/502/503/504 responses are retried.
+#[derive(Clone, Debug, PartialEq)]
+pub struct RetryPolicy {
+    /// Retries after the first attempt, 0 disables retrying
+    pub max_retries: u32,
+    /// Delay before the first retry, doubled for every following one
+    pub initial_backoff: Duration,
+    pub max_backoff: Duration,
+}
+
+impl RetryPolicy {
+    pub fn none() -> Self {
+        Self::exponential(0)
+    }
+
+    pub fn exponential(max_retries: u32) -> Self {
+        Self {
+            max_retries,
+            initial_backoff: Duration::from_millis(500),
+            max_backoff: Duration::from_secs(10),
+        }
+    }
+
+    fn backoff(&self, attempt: u32) -> Duration {
+        self.initial_backoff
+            .saturating_mul(2u32.saturating_pow(attempt))
+            .min(self.max_backoff)
+    }
+}
+
+impl Default for RetryPolicy {
+    fn default() -> Self {
+        Self::none()
+    }
+}
+
+/// Overrides applied to the requests of a client, see [`Client::with_options`]
+#[derive(Clone, Debug, Default)]
+pub struct RequestOptions {
+    pub timeout: Option<Duration>,
+    /// Extra headers sent with every request
+    pub headers: Vec<(String, String)>,
+    pub retry: Option<RetryPolicy>,
+    /// API endpoint to send requests to instead of the client's
+    pub api_endpoint: Option<String>,
+}
+
+impl RequestOptions {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn timeout(mut self, timeout: Duration) -> Self {
+        self.timeout = Some(timeout);
+        self
+    }
+
+    pub fn header(mut self, name: impl Into<String>, value: impl Into<String>) -> Self {
+        self.headers.push((name.into(), value.into()));
+        self
+    }
+
+    pub fn retry(mut self, retry: RetryPolicy) -> Self {
+        self.retry = Some(retry);
+        self
+    }
+
+    pub fn api_endpoint(mut self, api_endpoint: impl Into<String>) -> Self {
+        self.api_endpoint = Some(api_endpoint.into());
+        self
+    }
+}
+
+/// Builds a [`Client`].
+///
+/// ```no_run
+/// use stakpak_api::{Client, RetryPolicy};
+/// use std::time::Duration;
+///
+/// let client = Client::builder()
+///     .api_key("sk-...")
+///     .timeout(Duration::from_secs(60))
+///     .header("x-request-source", "ci")
+///     .retry(RetryPolicy::exponential(3))
+///     .build()?;
+/// # Ok::<(), String>(())
+/// ```
+#[derive(Clone)]
+pub struct ClientBuilder {
+    api_key: Option<String>,
+    api_endpoint: String,
+    fallback_endpoints: Vec<String>,
+    connect_timeout: Option<Duration>,
+    options: RequestOptions,
+}
+
+impl Default for ClientBuilder {
+    fn default() -> Self {
+        Self {
+            api_key: None,
+            api_endpoint: DEFAULT_API_ENDPOINT.to_string(),
+            fallback_endpoints: Vec::new(),
+            connect_timeout: None,
+            options: RequestOptions::default(),
+        }
+    }
+}
+
+impl ClientBuilder {
+    pub fn api_key(mut self, api_key: impl Into<String>) -> Self {
+        self.api_key = Some(api_key.into());
+        self
+    }
+
+    pub fn api_endpoint(mut self, api_endpoint: impl Into<String>) -> Self {
+        self.api_endpoint = api_endpoint.into();
+        self
+    }
+
+    /// Endpoint requests fail over to when the ones before it are unreachable or answer
+    /// with 502, 503 or 504, tried in the order they were added
+    pub fn fallback_endpoint(mut self, endpoint: impl Into<String>) -> Self {
+        self.fallback_endpoints.push(endpoint.into());
+        self
+    }
+
+    /// Timeout of a whole request, including reading the response body. Streamed chat
+    /// completions are cut off when it expires, so keep it generous.
+    pub fn timeout(mut self, timeout: Duration) -> Self {
+        self.options.timeout = Some(timeout);
+        self
+    }
+
+    pub fn connect_timeout(mut self, connect_timeout: Duration) -> Self {
+        self.connect_timeout = Some(connect_timeout);
+        self
+    }
+
+    /// Header sent with every request
+    pub fn header(mut self, name: impl Into<String>, value: impl Into<String>) -> Self {
+        self.options.headers.push((name.into(), value.into()));
+        self
+    }
+
+    pub fn retry(mut self, retry: RetryPolicy) -> Self {
+        self.options.retry = Some(retry);
+        self
+    }
+
+    pub fn build(self) -> Result<Client, String> {
+        let api_key = self
+            .api_key
+            .ok_or_else(|| "API Key not found, please login".to_string())?;
+
+        let mut headers = header::HeaderMap::new();
+        headers.insert(
+            header::AUTHORIZATION,
+            header::HeaderValue::from_str(&format!("Bearer {}", api_key))
+                .map_err(|_| "Invalid API key format".to_string())?,
+        );
+        for (name, value) in &self.options.headers {
+            headers.insert(
+                header::HeaderName::from_bytes(name.as_bytes())
+                    .map_err(|e| format!("Invalid header name {}: {}", name, e))?,
+                header::HeaderValue::from_str(value)
+                    .map_err(|e| format!("Invalid value for header {}: {}", name, e))?,
+            );
+        }
+
+        let mut builder = ReqwestClient::builder().default_headers(headers);
+        if let Some(connect_timeout) = self.connect_timeout {
+            builder = builder.connect_timeout(connect_timeout);
+        }
+        let client = builder
+            .build()
+            .map_err(|e| format!("Failed to create HTTP client: {}", e))?;
+
+        let endpoints = (!self.fallback_endpoints.is_empty()).then(|| {
+            let mut endpoints = vec![self.api_endpoint.clone()];
+            endpoints.extend(self.fallback_endpoints);
+            Arc::new(EndpointPool::new(endpoints))
+        });
+
+        Ok(Client {
+            client,
+            base_url: self.api_endpoint.trim_end_matches('/').to_string() + "/v1",
+            endpoints,
+            options: RequestOptions {
+                headers: Vec::new(),
+                ..self.options
+            },
+        })
+    }
+}
+
+/// Request that applies the client's timeout, headers and retry policy when sent
+pub(crate) struct ApiRequest {
+    inner: RequestBuilder,
+    retry: RetryPolicy,
+    endpoints: Option<Arc<EndpointPool>>,
+}
+
+impl ApiRequest {
+    pub(crate) fn new(inner: RequestBuilder, options: &RequestOptions) -> Self {
+        let mut inner = inner;
+        if let Some(timeout) = options.timeout {
+            inner = inner.timeout(timeout);
+        }
+        for (name, value) in &options.headers {
+            inner = inner.header(name.as_str(), value.as_str());
+        }
+        Self {
+            inner,
+            retry: options.retry.clone().unwrap_or_default(),
+            endpoints: None,
+        }
+    }
+
+    /// Fail over to the other endpoints of `endpoints` when the active one is down
+    pub(crate) fn failover(self, endpoints: Option<Arc<EndpointPool>>) -> Self {
+        Self { endpoints, ..self }
+    }
+
+    pub(crate) fn json<T: Serialize + ?Sized>(self, json: &T) -> Self {
+        Self {
+            inner: self.inner.json(json),
+            ..self
+        }
+    }
+
+    pub(crate) fn header(self, name: header::HeaderName, value: &str) -> Self {
+        Self {
+            inner: self.inner.header(name, value),
+            ..self
+        }
+    }
+
+    pub(crate) fn query<T: Serialize + ?Sized>(self, query: &T) -> Self {
+        Self {
+            inner: self.inner.query(query),
+            ..self
+        }
+    }
+
+    pub(crate) async fn send(self) -> Result<Response, ReqwestError> {
+        let Some(endpoints) = self.endpoints.clone() else {
+            return send_with_retry(self.inner, &self.retry).await;
+        };
+
+        let mut tried = 0;
+        loop {
+            let active = endpoints.active();
+            // A request whose body cannot be replayed is only sent to the active endpoint
+            let Some((client, request)) = self.for_endpoint(&endpoints, active) else {
+                return send_with_retry(self.inner, &self.retry).await;
+            };
+            let result = send_with_retry(request, &self.retry).await;
+            tried += 1;
+            if !is_endpoint_failure(&result) || tried >= endpoints.len() {
+                return result;
+            }
+            if endpoints.fail_over(&client, active).await.is_none() {
+                return result;
+            }
+        }
+    }
+
+    fn for_endpoint(
+        &self,
+        endpoints: &EndpointPool,
+        index: usize,
+    ) -> Option<(ReqwestClient, RequestBuilder)> {
+        let (client, request) = self.inner.try_clone()?.build_split();
+        let mut request = request.ok()?;
+        let url = endpoints.rewrite_url(request.url().as_str(), index)?;
+        *request.url_mut() = reqwest::Url::parse(&url).ok()?;
+        Some((client.clone(), RequestBuilder::from_parts(client, request)))
+    }
+}
+
+async fn send_with_retry(
+    inner: RequestBuilder,
+    retry: &RetryPolicy,
+) -> Result<Response, ReqwestError> {
+    let mut attempt = 0;
+    loop {
+        // The last attempt, or a request whose body cannot be replayed, is sent as is
+        let Some(request) = inner.try_clone().filter(|_| attempt < retry.max_retries) else {
+            return inner.send().await;
+        };
+
+        match request.send().await {
+            Ok(response) if !is_retryable_status(response.status()) => return Ok(response),
+            Err(e) if !(e.is_connect() || e.is_timeout()) => return Err(e),
+            _ => {}
+        }
+        tokio::time::sleep(retry.backoff(attempt)).await;
+        attempt += 1;
+    }
+}
+
+/// Whether the endpoint looks down rather than the request being refused
+fn is_endpoint_failure(result: &Result<Response, ReqwestError>) -> bool {
+    match result {
+        Ok(response) => matches!(response.status().as_u16(), 502..=504),
+        Err(e) => e.is_connect() || e.is_timeout(),
+    }
+}
+
+fn is_retryable_status(status: reqwest::StatusCode) -> bool {
+    matches!(status.as_u16(), 429 | 502 | 503 | 504)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_backoff_doubles_up_to_the_maximum() {
+        let retry = RetryPolicy::exponential(5);
+        assert_eq!(retry.backoff(0), Duration::from_millis(500));
+        assert_eq!(retry.backoff(2), Duration::from_secs(2));
+        assert_eq!(retry.backoff(10), Duration::from_secs(10));
+        assert_eq!(retry.backoff(u32::MAX), Duration::from_secs(10));
+        assert_eq!(RetryPolicy::default().max_retries, 0);
+    }
+
+    #[test]
+    fn test_retryable_statuses() {
+        for status in [429, 502, 503, 504] {
+            assert!(is_retryable_status(
+                reqwest::StatusCode::from_u16(status).unwrap()
+            ));
+        }
+        for status in [400, 401, 404, 500] {
+            assert!(!is_retryable_status(
+                reqwest::StatusCode::from_u16(status).unwrap()
+            ));
+        }
+    }
+
+    #[test]
+    fn test_build() {
+        assert!(ClientBuilder::default().build().is_err());
+        assert!(
+            ClientBuilder::default()
+                .api_key("key")
+                .header("bad header", "value")
+                .build()
+                .is_err()
+        );
+
+        let client = ClientBuilder::default()
+            .api_key("key")
+            .api_endpoint("http://localhost:8080/")
+            .timeout(Duration::from_secs(60))
+            .header("x-request-source", "ci")
+            .retry(RetryPolicy::exponential(3))
+            .build()
+            .unwrap();
+        assert_eq!(client.base_url, "http://localhost:8080/v1");
+        assert!(client.endpoints.is_none());
+        assert_eq!(client.options.timeout, Some(Duration::from_secs(60)));
+        assert_eq!(client.options.retry, Some(RetryPolicy::exponential(3)));
+        // Client headers are default headers of the HTTP client, not added per request
+        assert!(client.options.headers.is_empty());
+
+        let client = ClientBuilder::default()
+            .api_key("key")
+            .fallback_endpoint("http://localhost:8081")
+            .build()
+            .unwrap();
+        assert_eq!(client.endpoints.map(|endpoints| endpoints.len()), Some(2));
+    }
+}
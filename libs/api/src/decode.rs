@@ -0,0 +1,142 @@
+use serde::de::DeserializeOwned;
+use serde_json::Value;
+use stakpak_shared::local_store::LocalStore;
+use std::sync::atomic::{AtomicBool, Ordering};
+
+const DEBUG_DIR: &str = "debug";
+
+static STRICT_MODE: AtomicBool = AtomicBool::new(false);
+
+/// Reject API responses with fields the CLI does not know and save mismatched payloads
+pub fn set_strict_mode(enabled: bool) {
+    STRICT_MODE.store(enabled, Ordering::SeqCst);
+}
+
+pub fn strict_mode_enabled() -> bool {
+    STRICT_MODE.load(Ordering::SeqCst)
+}
+
+/// API response that does not match the type the CLI expects
+#[derive(Debug, Clone, PartialEq)]
+pub struct DecodeError {
+    pub type_name: String,
+    /// Path of the mismatched field, `.` for the response itself
+    pub path: String,
+    pub message: String,
+    /// Where the payload was saved in strict mode
+    pub payload_file: Option<String>,
+}
+
+impl std::fmt::Display for DecodeError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "Failed to deserialize {} response at '{}': {}",
+            self.type_name, self.path, self.message
+        )?;
+        match &self.payload_file {
+            Some(file) => write!(f, " (payload saved to {})", file),
+            None => write!(
+                f,
+                " (set STAKPAK_STRICT_API=true to save the payload for debugging)"
+            ),
+        }
+    }
+}
+
+impl std::error::Error for DecodeError {}
+
+impl From<DecodeError> for String {
+    fn from(error: DecodeError) -> Self {
+        error.to_string()
+    }
+}
+
+/// `stakpak_api::models::AgentSession` -> `AgentSession`, generics included
+fn short_type_name<T>() -> String {
+    let name = std::any::type_name::<T>();
+    let base = name.split('<').next().unwrap_or(name);
+    let short = base.rsplit("::").next().unwrap_or(base);
+    match name.find('<') {
+        Some(generics) => format!("{}{}", short, &name[generics..]),
+        None => short.to_string(),
+    }
+}
+
+fn save_payload(type_name: &str, value: &Value) -> Option<String> {
+    let file_name: String = type_name
+        .chars()
+        .take_while(|c| c.is_ascii_alphanumeric() || *c == '_')
+        .collect();
+    let path = format!(
+        "{}/{}-{}.json",
+        DEBUG_DIR,
+        file_name,
+        chrono::Utc::now().format("%Y%m%dT%H%M%S%.3f")
+    );
+    let content = serde_json::to_string_pretty(value).ok()?;
+    LocalStore::write_session_data(&path, &content).ok()
+}
+
+/// Deserialize an API response, reporting the path of the first mismatched field. In
+/// strict mode fields unknown to `T` are mismatches too and the payload is saved under
+/// `.stakpak/session/debug/`.
+pub fn decode<T: DeserializeOwned>(value: Value) -> Result<T, DecodeError> {
+    let strict = strict_mode_enabled();
+    let mut ignored = Vec::new();
+    let result = {
+        let deserializer =
+            serde_ignored::Deserializer::new(&value, |path| ignored.push(path.to_string()));
+        serde_path_to_error::deserialize::<_, T>(deserializer)
+    };
+
+    let (path, message) = match result {
+        Ok(decoded) if !strict || ignored.is_empty() => return Ok(decoded),
+        Ok(_) => (ignored.join(", "), "unknown field".to_string()),
+        Err(e) => (e.path().to_string(), e.into_inner().to_string()),
+    };
+    let type_name = short_type_name::<T>();
+    let payload_file = if strict {
+        save_payload(&type_name, &value)
+    } else {
+        None
+    };
+    Err(DecodeError {
+        type_name,
+        path,
+        message,
+        payload_file,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde::Deserialize;
+    use serde_json::json;
+
+    #[derive(Deserialize, Debug)]
+    #[allow(dead_code)]
+    struct Flow {
+        name: String,
+        versions: Vec<Version>,
+    }
+
+    #[derive(Deserialize, Debug)]
+    #[allow(dead_code)]
+    struct Version {
+        id: u32,
+    }
+
+    #[test]
+    fn test_decode_reports_mismatched_field() {
+        let error = decode::<Flow>(json!({"name": "infra", "versions": [{"id": "v1"}]}))
+            .err()
+            .map(|error| (error.type_name, error.path));
+        assert_eq!(
+            error,
+            Some(("Flow".to_string(), "versions[0].id".to_string()))
+        );
+        assert!(decode::<Flow>(json!({"name": "infra", "versions": [], "extra": 1})).is_ok());
+    }
+}
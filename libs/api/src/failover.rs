@@ -0,0 +1,100 @@
+use reqwest::Client as ReqwestClient;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::time::Duration;
+
+const HEALTH_CHECK_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// API endpoints of a client, the primary first. Once requests failed over to an endpoint
+/// they stick to it, even after the primary recovers, so one run doesn't flap between
+/// regions.
+#[derive(Debug)]
+pub struct EndpointPool {
+    endpoints: Vec<String>,
+    active: AtomicUsize,
+}
+
+impl EndpointPool {
+    pub fn new(endpoints: Vec<String>) -> Self {
+        Self {
+            endpoints: endpoints
+                .into_iter()
+                .map(|endpoint| endpoint.trim_end_matches('/').to_string())
+                .collect(),
+            active: AtomicUsize::new(0),
+        }
+    }
+
+    pub fn endpoints(&self) -> &[String] {
+        &self.endpoints
+    }
+
+    pub fn len(&self) -> usize {
+        self.endpoints.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.endpoints.is_empty()
+    }
+
+    pub fn active(&self) -> usize {
+        self.active.load(Ordering::SeqCst)
+    }
+
+    pub fn active_endpoint(&self) -> &str {
+        self.endpoints
+            .get(self.active())
+            .map(String::as_str)
+            .unwrap_or_default()
+    }
+
+    /// Point `url`, built against the primary endpoint, to the endpoint at `index`
+    pub fn rewrite_url(&self, url: &str, index: usize) -> Option<String> {
+        let rest = url.strip_prefix(self.endpoints.first()?.as_str())?;
+        Some(format!("{}{}", self.endpoints.get(index)?, rest))
+    }
+
+    /// Make the first healthy endpoint after `failed` the active one, returning it, or
+    /// `None` when no other endpoint is healthy
+    pub async fn fail_over(&self, client: &ReqwestClient, failed: usize) -> Option<usize> {
+        for offset in 1..self.endpoints.len() {
+            let index = (failed + offset) % self.endpoints.len();
+            if is_healthy(client, &self.endpoints[index]).await {
+                // A concurrent request may have failed over already, its choice is kept
+                let _ =
+                    self.active
+                        .compare_exchange(failed, index, Ordering::SeqCst, Ordering::SeqCst);
+                return Some(self.active());
+            }
+        }
+        None
+    }
+}
+
+/// Whether `endpoint` answers at all, any response but a server error counts
+pub async fn is_healthy(client: &ReqwestClient, endpoint: &str) -> bool {
+    client
+        .get(endpoint)
+        .timeout(HEALTH_CHECK_TIMEOUT)
+        .send()
+        .await
+        .is_ok_and(|response| !response.status().is_server_error())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_rewrite_url() {
+        let pool = EndpointPool::new(vec![
+            "https://apiv2.stakpak.dev".to_string(),
+            "https://eu.apiv2.stakpak.dev/".to_string(),
+        ]);
+        assert_eq!(
+            pool.rewrite_url("https://apiv2.stakpak.dev/v1/account", 1),
+            Some("https://eu.apiv2.stakpak.dev/v1/account".to_string())
+        );
+        assert_eq!(pool.rewrite_url("https://example.com/v1/account", 1), None);
+        assert_eq!(pool.active_endpoint(), "https://apiv2.stakpak.dev");
+    }
+}
@@ -1,28 +1,70 @@
+use builder::ApiRequest;
 use chrono::{DateTime, Utc};
 use eventsource_stream::Eventsource;
-use reqwest::{Client as ReqwestClient, Error as ReqwestError, header};
+use reqwest::{Client as ReqwestClient, Error as ReqwestError};
 use rmcp::model::Content;
 use rmcp::model::JsonRpcResponse;
-use serde::{Deserialize, Serialize};
+use serde::{Deserialize, Serialize, de::DeserializeOwned};
 pub mod models;
 use futures_util::Stream;
 use futures_util::StreamExt;
 use models::*;
 use serde_json::Value;
 use serde_json::json;
+use stakpak_shared::json_schema;
 use stakpak_shared::models::integrations::openai::{
-    ChatCompletionRequest, ChatCompletionResponse, ChatCompletionStreamResponse, ChatMessage, Tool,
+    ChatCompletionRequest, ChatCompletionResponse, ChatCompletionStreamResponse, ChatMessage,
+    MessageContent, ResponseFormat, Role, Tool,
 };
+use stakpak_shared::uri::uri_to_relative_path;
+use std::sync::Arc;
 use uuid::Uuid;
+pub mod builder;
 pub mod dave_v1;
+pub mod decode;
+pub mod failover;
 pub mod kevin_v1;
 pub mod norbert_v1;
+pub mod provider;
 pub mod stuart_v1;
+pub mod usage;
+pub use builder::{ClientBuilder, RequestOptions, RetryPolicy};
+use decode::decode;
+use failover::{EndpointPool, is_healthy};
 pub use models::Block;
 
+#[derive(Clone)]
 pub struct Client {
     client: ReqwestClient,
+    /// Requests are built against the primary endpoint, see [`EndpointPool::rewrite_url`]
     base_url: String,
+    /// Set when fallback endpoints are configured
+    endpoints: Option<Arc<EndpointPool>>,
+    options: RequestOptions,
+}
+
+const PAGE_SIZE: usize = 50;
+/// Most pages fetched for one listing, even when all items were asked for
+pub const MAX_PAGES: usize = 100;
+
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum Page<T> {
+    Paged {
+        results: Vec<T>,
+        #[serde(default)]
+        next_cursor: Option<String>,
+    },
+    Unpaged(Vec<T>),
+}
+
+/// Items of a paginated list endpoint
+#[derive(Debug)]
+pub struct Listing<T> {
+    pub items: Vec<T>,
+    /// The server has more items than were fetched, because of the limit or because
+    /// the listing stopped after [`MAX_PAGES`] requests
+    pub has_more: bool,
 }
 
 #[derive(Clone, Debug)]
@@ -30,6 +72,26 @@ pub struct Client {
 pub struct ClientConfig {
     pub api_key: Option<String>,
     pub api_endpoint: String,
+    /// Endpoints to fail over to when `api_endpoint` is down, in order
+    pub fallback_endpoints: Vec<String>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum AccountError {
+    /// The API could not be reached or failed to answer
+    Unreachable(String),
+    /// The API refused the request, e.g. because the API key expired or was revoked
+    Rejected(String),
+}
+
+impl std::fmt::Display for AccountError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            AccountError::Unreachable(message) | AccountError::Rejected(message) => {
+                write!(f, "{}", message)
+            }
+        }
+    }
 }
 
 #[derive(Deserialize)]
@@ -45,59 +107,126 @@ struct ApiErrorDetail {
 
 impl Client {
     pub fn new(config: &ClientConfig) -> Result<Self, String> {
-        if config.api_key.is_none() {
-            return Err("API Key not found, please login".into());
+        let mut builder = Client::builder().api_endpoint(&config.api_endpoint);
+        if let Some(api_key) = &config.api_key {
+            builder = builder.api_key(api_key);
         }
+        for endpoint in &config.fallback_endpoints {
+            builder = builder.fallback_endpoint(endpoint);
+        }
+        builder.build()
+    }
 
-        let mut headers = header::HeaderMap::new();
-        headers.insert(
-            header::AUTHORIZATION,
-            header::HeaderValue::from_str(&format!("Bearer {}", config.api_key.clone().unwrap()))
-                .expect("Invalid API key format"),
-        );
+    pub fn builder() -> ClientBuilder {
+        ClientBuilder::default()
+    }
+
+    /// Copy of this client sending its requests with `options` applied on top of its own.
+    /// The copy shares the connection pool, so it is cheap to create for a single call:
+    ///
+    /// ```no_run
+    /// # async fn example(client: &stakpak_api::Client) -> Result<(), String> {
+    /// use stakpak_api::RequestOptions;
+    /// use std::time::Duration;
+    ///
+    /// let flows = client
+    ///     .with_options(RequestOptions::new().timeout(Duration::from_secs(5)))
+    ///     .list_flows("acme", None)
+    ///     .await?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn with_options(&self, options: RequestOptions) -> Self {
+        let mut merged = self.options.clone();
+        merged.timeout = options.timeout.or(merged.timeout);
+        merged.retry = options.retry.or(merged.retry);
+        merged.headers.extend(options.headers);
+        // An explicit endpoint opts out of failing over
+        let endpoints = match options.api_endpoint {
+            Some(_) => None,
+            None => self.endpoints.clone(),
+        };
+        Self {
+            client: self.client.clone(),
+            base_url: options
+                .api_endpoint
+                .map(|endpoint| endpoint + "/v1")
+                .unwrap_or_else(|| self.base_url.clone()),
+            endpoints,
+            options: merged,
+        }
+    }
 
-        let client = ReqwestClient::builder()
-            .default_headers(headers)
-            .build()
-            .expect("Failed to create HTTP client");
+    fn get(&self, url: &str) -> ApiRequest {
+        ApiRequest::new(self.client.get(url), &self.options).failover(self.endpoints.clone())
+    }
 
-        Ok(Self {
-            client,
-            base_url: config.api_endpoint.clone() + "/v1",
-        })
+    fn post(&self, url: &str) -> ApiRequest {
+        ApiRequest::new(self.client.post(url), &self.options).failover(self.endpoints.clone())
+    }
+
+    /// Endpoint requests are currently sent to
+    pub fn active_endpoint(&self) -> String {
+        match &self.endpoints {
+            Some(endpoints) => endpoints.active_endpoint().to_string(),
+            None => self.base_url.trim_end_matches("/v1").to_string(),
+        }
+    }
+
+    /// Health of every configured endpoint, the primary first
+    pub async fn endpoint_health(&self) -> Vec<(String, bool)> {
+        let endpoints = match &self.endpoints {
+            Some(endpoints) => endpoints.endpoints().to_vec(),
+            None => vec![self.active_endpoint()],
+        };
+        let mut health = Vec::new();
+        for endpoint in endpoints {
+            let healthy = is_healthy(&self.client, &endpoint).await;
+            health.push((endpoint, healthy));
+        }
+        health
     }
 
     pub async fn get_my_account(&self) -> Result<GetMyAccountResponse, String> {
+        self.check_account().await.map_err(|e| e.to_string())
+    }
+
+    /// Fetch the account of the current API key, telling apart an unreachable API from a
+    /// rejected key
+    pub async fn check_account(&self) -> Result<GetMyAccountResponse, AccountError> {
         let url = format!("{}/account", self.base_url);
 
         let response = self
-            .client
             .get(&url)
             .send()
             .await
-            .map_err(|e: ReqwestError| e.to_string())?;
+            .map_err(|e: ReqwestError| AccountError::Unreachable(e.to_string()))?;
 
         if !response.status().is_success() {
-            let error: ApiError = response.json().await.map_err(|e| e.to_string())?;
-            return Err(error.error.message);
+            let status = response.status();
+            let message = match response.json::<ApiError>().await {
+                Ok(error) => error.error.message,
+                Err(_) => status.to_string(),
+            };
+            return Err(if status.is_server_error() {
+                AccountError::Unreachable(message)
+            } else {
+                AccountError::Rejected(message)
+            });
         }
 
-        let value: serde_json::Value = response.json().await.map_err(|e| e.to_string())?;
-        match serde_json::from_value::<GetMyAccountResponse>(value.clone()) {
-            Ok(response) => Ok(response),
-            Err(e) => {
-                eprintln!("Failed to deserialize response: {}", e);
-                eprintln!("Raw response: {}", value);
-                Err("Failed to deserialize response:".into())
-            }
-        }
+        let value: serde_json::Value = response
+            .json()
+            .await
+            .map_err(|e| AccountError::Unreachable(e.to_string()))?;
+        decode::<GetMyAccountResponse>(value).map_err(|e| AccountError::Unreachable(e.to_string()))
     }
 
-    pub async fn list_flows(&self, owner_name: &str) -> Result<GetFlowsResponse, String> {
-        let url = format!("{}/flows/{}", self.base_url, owner_name);
+    /// Consumption of the account quotas in the current billing period
+    pub async fn get_usage(&self) -> Result<GetUsageResponse, String> {
+        let url = format!("{}/account/usage", self.base_url);
 
         let response = self
-            .client
             .get(&url)
             .send()
             .await
@@ -108,15 +237,19 @@ impl Client {
             return Err(error.error.message);
         }
 
-        let value: serde_json::Value = response.json().await.map_err(|e| e.to_string())?;
-        match serde_json::from_value::<GetFlowsResponse>(value.clone()) {
-            Ok(response) => Ok(response),
-            Err(e) => {
-                eprintln!("Failed to deserialize response: {}", e);
-                eprintln!("Raw response: {}", value);
-                Err("Failed to deserialize response:".into())
-            }
-        }
+        response
+            .json::<GetUsageResponse>()
+            .await
+            .map_err(|e| format!("Failed to deserialize usage response: {}", e))
+    }
+
+    pub async fn list_flows(
+        &self,
+        owner_name: &str,
+        limit: Option<usize>,
+    ) -> Result<Listing<Flow>, String> {
+        let url = format!("{}/flows/{}", self.base_url, owner_name);
+        self.fetch_pages(&url, limit).await
     }
 
     pub async fn get_flow(
@@ -127,7 +260,6 @@ impl Client {
         let url = format!("{}/flows/{}/{}", self.base_url, owner_name, flow_name);
 
         let response = self
-            .client
             .get(&url)
             .send()
             .await
@@ -139,14 +271,7 @@ impl Client {
         }
 
         let value: serde_json::Value = response.json().await.map_err(|e| e.to_string())?;
-        match serde_json::from_value::<GetFlowResponse>(value.clone()) {
-            Ok(response) => Ok(response),
-            Err(e) => {
-                eprintln!("Failed to deserialize response: {}", e);
-                eprintln!("Raw response: {}", value);
-                Err("Failed to deserialize response:".into())
-            }
-        }
+        Ok(decode::<GetFlowResponse>(value)?)
     }
 
     pub async fn create_flow(
@@ -162,7 +287,6 @@ impl Client {
         };
 
         let response = self
-            .client
             .post(&url)
             .json(&input)
             .send()
@@ -175,14 +299,7 @@ impl Client {
         }
 
         let value: serde_json::Value = response.json().await.map_err(|e| e.to_string())?;
-        match serde_json::from_value::<CreateFlowResponse>(value.clone()) {
-            Ok(response) => Ok(response),
-            Err(e) => {
-                eprintln!("Failed to deserialize response: {}", e);
-                eprintln!("Raw response: {}", value);
-                Err("Failed to deserialize response:".into())
-            }
-        }
+        Ok(decode::<CreateFlowResponse>(value)?)
     }
 
     pub async fn save_edits(
@@ -195,7 +312,6 @@ impl Client {
         let input = SaveEditsInput { edits };
 
         let response = self
-            .client
             .post(&url)
             .json(&input)
             .send()
@@ -208,43 +324,55 @@ impl Client {
         }
 
         let value: serde_json::Value = response.json().await.map_err(|e| e.to_string())?;
-        match serde_json::from_value::<SaveEditsResponse>(value.clone()) {
-            Ok(response) => Ok(response),
-            Err(e) => {
-                eprintln!("Failed to deserialize response: {}", e);
-                eprintln!("Raw response: {}", value);
-                Err("Failed to deserialize response:".into())
-            }
-        }
+        Ok(decode::<SaveEditsResponse>(value)?)
     }
 
     pub async fn get_flow_documents(
         &self,
         flow_ref: &FlowRef,
     ) -> Result<GetFlowDocumentsResponse, String> {
+        match self.get_flow_documents_if_changed(flow_ref, None).await? {
+            FlowDocumentsFetch::Modified { documents, .. } => Ok(documents),
+            FlowDocumentsFetch::NotModified => Err("Unexpected 304 Not Modified response".into()),
+        }
+    }
+
+    /// Fetch the documents of a flow unless they still match `etag`, in which case
+    /// nothing is downloaded and [`FlowDocumentsFetch::NotModified`] is returned
+    pub async fn get_flow_documents_if_changed(
+        &self,
+        flow_ref: &FlowRef,
+        etag: Option<&str>,
+    ) -> Result<FlowDocumentsFetch, String> {
         let url = format!("{}/flows/{}/documents", self.base_url, flow_ref);
 
-        let response = self
-            .client
-            .get(&url)
+        let mut request = self.get(&url);
+        if let Some(etag) = etag {
+            request = request.header(reqwest::header::IF_NONE_MATCH, etag);
+        }
+        let response = request
             .send()
             .await
             .map_err(|e: ReqwestError| e.to_string())?;
 
+        if response.status() == reqwest::StatusCode::NOT_MODIFIED {
+            return Ok(FlowDocumentsFetch::NotModified);
+        }
+
         if !response.status().is_success() {
             let error: ApiError = response.json().await.map_err(|e| e.to_string())?;
             return Err(error.error.message);
         }
 
+        let etag = response
+            .headers()
+            .get(reqwest::header::ETAG)
+            .and_then(|value| value.to_str().ok())
+            .map(|value| value.to_string());
+
         let value: serde_json::Value = response.json().await.map_err(|e| e.to_string())?;
-        match serde_json::from_value::<GetFlowDocumentsResponse>(value.clone()) {
-            Ok(response) => Ok(response),
-            Err(e) => {
-                eprintln!("Failed to deserialize response: {}", e);
-                eprintln!("Raw response: {}", value);
-                Err("Failed to deserialize response:".into())
-            }
-        }
+        let documents = decode::<GetFlowDocumentsResponse>(value)?;
+        Ok(FlowDocumentsFetch::Modified { documents, etag })
     }
 
     pub async fn query_blocks(
@@ -271,7 +399,6 @@ impl Client {
         };
 
         let response = self
-            .client
             .post(&url)
             .json(&input)
             .send()
@@ -284,47 +411,21 @@ impl Client {
         }
 
         let value: serde_json::Value = response.json().await.map_err(|e| e.to_string())?;
-        match serde_json::from_value::<QueryBlocksResponse>(value.clone()) {
-            Ok(response) => Ok(response),
-            Err(e) => {
-                eprintln!("Failed to deserialize response: {}", e);
-                eprintln!("Raw response: {}", value);
-                Err("Failed to deserialize response:".into())
-            }
-        }
+        Ok(decode::<QueryBlocksResponse>(value)?)
     }
 
-    pub async fn list_agent_sessions(&self) -> Result<Vec<AgentSession>, String> {
+    pub async fn list_agent_sessions(
+        &self,
+        limit: Option<usize>,
+    ) -> Result<Listing<AgentSession>, String> {
         let url = format!("{}/agents/sessions", self.base_url);
-
-        let response = self
-            .client
-            .get(&url)
-            .send()
-            .await
-            .map_err(|e: ReqwestError| e.to_string())?;
-
-        if !response.status().is_success() {
-            let error: ApiError = response.json().await.map_err(|e| e.to_string())?;
-            return Err(error.error.message);
-        }
-
-        let value: serde_json::Value = response.json().await.map_err(|e| e.to_string())?;
-        match serde_json::from_value::<Vec<AgentSession>>(value.clone()) {
-            Ok(response) => Ok(response),
-            Err(e) => {
-                eprintln!("Failed to deserialize response: {}", e);
-                eprintln!("Raw response: {}", value);
-                Err("Failed to deserialize response:".into())
-            }
-        }
+        self.fetch_pages(&url, limit).await
     }
 
     pub async fn get_agent_session(&self, session_id: Uuid) -> Result<AgentSession, String> {
         let url = format!("{}/agents/sessions/{}", self.base_url, session_id);
 
         let response = self
-            .client
             .get(&url)
             .send()
             .await
@@ -337,14 +438,7 @@ impl Client {
 
         let value: serde_json::Value = response.json().await.map_err(|e| e.to_string())?;
 
-        match serde_json::from_value::<AgentSession>(value.clone()) {
-            Ok(response) => Ok(response),
-            Err(e) => {
-                eprintln!("Failed to deserialize response: {}", e);
-                eprintln!("Raw response: {}", value);
-                Err("Failed to deserialize response:".into())
-            }
-        }
+        Ok(decode::<AgentSession>(value)?)
     }
 
     pub async fn create_agent_session(
@@ -362,7 +456,6 @@ impl Client {
         });
 
         let response = self
-            .client
             .post(&url)
             .json(&input)
             .send()
@@ -375,21 +468,13 @@ impl Client {
         }
 
         let value: serde_json::Value = response.json().await.map_err(|e| e.to_string())?;
-        match serde_json::from_value::<AgentSession>(value.clone()) {
-            Ok(response) => Ok(response),
-            Err(e) => {
-                eprintln!("Failed to deserialize response: {}", e);
-                eprintln!("Raw response: {}", value);
-                Err("Failed to deserialize response:".into())
-            }
-        }
+        Ok(decode::<AgentSession>(value)?)
     }
 
     pub async fn run_agent(&self, input: &RunAgentInput) -> Result<RunAgentOutput, String> {
         let url = format!("{}/agents/run", self.base_url);
 
         let response = self
-            .client
             .post(&url)
             .json(&input)
             .send()
@@ -402,14 +487,7 @@ impl Client {
         }
 
         let value: serde_json::Value = response.json().await.map_err(|e| e.to_string())?;
-        match serde_json::from_value::<RunAgentOutput>(value.clone()) {
-            Ok(response) => Ok(response),
-            Err(e) => {
-                eprintln!("Failed to deserialize response: {}", e);
-                eprintln!("Raw response: {}", value);
-                Err("Failed to deserialize response:".into())
-            }
-        }
+        Ok(decode::<RunAgentOutput>(value)?)
     }
 
     pub async fn get_agent_checkpoint(
@@ -419,7 +497,6 @@ impl Client {
         let url = format!("{}/agents/checkpoints/{}", self.base_url, checkpoint_id);
 
         let response = self
-            .client
             .get(&url)
             .send()
             .await
@@ -431,14 +508,81 @@ impl Client {
         }
 
         let value: serde_json::Value = response.json().await.map_err(|e| e.to_string())?;
-        match serde_json::from_value::<RunAgentOutput>(value.clone()) {
-            Ok(response) => Ok(response),
-            Err(e) => {
-                eprintln!("Failed to deserialize response: {}", e);
-                eprintln!("Raw response: {}", value);
-                Err("Failed to deserialize response:".into())
-            }
+        Ok(decode::<RunAgentOutput>(value)?)
+    }
+
+    /// Attach the summary of the run that produced a checkpoint, shown instead of the raw
+    /// message history by the web UI and `stakpak agent get`
+    pub async fn attach_checkpoint_summary(
+        &self,
+        checkpoint_id: Uuid,
+        summary: &RunSummary,
+    ) -> Result<(), String> {
+        let url = format!(
+            "{}/agents/checkpoints/{}/summary",
+            self.base_url, checkpoint_id
+        );
+
+        let response = self
+            .post(&url)
+            .json(summary)
+            .send()
+            .await
+            .map_err(|e: ReqwestError| e.to_string())?;
+
+        if !response.status().is_success() {
+            let error: ApiError = response.json().await.map_err(|e| e.to_string())?;
+            return Err(error.error.message);
+        }
+
+        Ok(())
+    }
+
+    /// Register a command output about to be uploaded with [`Client::upload_output_chunk`]
+    pub async fn create_output_artifact(
+        &self,
+        input: &CreateOutputArtifactInput,
+    ) -> Result<OutputArtifact, String> {
+        let url = format!("{}/agents/artifacts/outputs", self.base_url);
+
+        let response = self
+            .post(&url)
+            .json(input)
+            .send()
+            .await
+            .map_err(|e: ReqwestError| e.to_string())?;
+
+        if !response.status().is_success() {
+            let error: ApiError = response.json().await.map_err(|e| e.to_string())?;
+            return Err(error.error.message);
         }
+
+        response.json().await.map_err(|e| e.to_string())
+    }
+
+    pub async fn upload_output_chunk(
+        &self,
+        artifact_id: Uuid,
+        chunk: &OutputChunkInput,
+    ) -> Result<(), String> {
+        let url = format!(
+            "{}/agents/artifacts/outputs/{}/chunks",
+            self.base_url, artifact_id
+        );
+
+        let response = self
+            .post(&url)
+            .json(chunk)
+            .send()
+            .await
+            .map_err(|e: ReqwestError| e.to_string())?;
+
+        if !response.status().is_success() {
+            let error: ApiError = response.json().await.map_err(|e| e.to_string())?;
+            return Err(error.error.message);
+        }
+
+        Ok(())
     }
 
     pub async fn get_agent_session_latest_checkpoint(
@@ -451,7 +595,6 @@ impl Client {
         );
 
         let response = self
-            .client
             .get(&url)
             .send()
             .await
@@ -463,14 +606,7 @@ impl Client {
         }
 
         let value: serde_json::Value = response.json().await.map_err(|e| e.to_string())?;
-        match serde_json::from_value::<RunAgentOutput>(value.clone()) {
-            Ok(response) => Ok(response),
-            Err(e) => {
-                eprintln!("Failed to deserialize response: {}", e);
-                eprintln!("Raw response: {}", value);
-                Err("Failed to deserialize response:".into())
-            }
-        }
+        Ok(decode::<RunAgentOutput>(value)?)
     }
 
     pub async fn transpile(
@@ -494,7 +630,6 @@ impl Client {
         };
 
         let response = self
-            .client
             .post(&url)
             .json(&input)
             .send()
@@ -507,14 +642,7 @@ impl Client {
         }
 
         let value: serde_json::Value = response.json().await.map_err(|e| e.to_string())?;
-        match serde_json::from_value::<TranspileOutput>(value.clone()) {
-            Ok(response) => Ok(response),
-            Err(e) => {
-                eprintln!("Failed to deserialize response: {}", e);
-                eprintln!("Raw response: {}", value);
-                Err("Failed to deserialize response:".into())
-            }
-        }
+        Ok(decode::<TranspileOutput>(value)?)
     }
 
     pub async fn get_agent_tasks(
@@ -530,7 +658,6 @@ impl Client {
         );
 
         let response = self
-            .client
             .get(&url)
             .send()
             .await
@@ -542,27 +669,31 @@ impl Client {
         }
 
         let value: serde_json::Value = response.json().await.map_err(|e| e.to_string())?;
-        match serde_json::from_value::<AgentTaskOutput>(value.clone()) {
-            Ok(response) => Ok(response.results),
-            Err(e) => {
-                eprintln!("Failed to deserialize response: {}", e);
-                eprintln!("Raw response: {}", value);
-                Err("Failed to deserialize response:".into())
-            }
-        }
+        let response = decode::<AgentTaskOutput>(value)?;
+        Ok(response.results)
     }
 
     pub async fn chat_completion(
         &self,
         messages: Vec<ChatMessage>,
         tools: Option<Vec<Tool>>,
+    ) -> Result<ChatCompletionResponse, String> {
+        self.chat_completion_with_format(messages, tools, None)
+            .await
+    }
+
+    pub async fn chat_completion_with_format(
+        &self,
+        messages: Vec<ChatMessage>,
+        tools: Option<Vec<Tool>>,
+        response_format: Option<ResponseFormat>,
     ) -> Result<ChatCompletionResponse, String> {
         let url = format!("{}/agents/openai/v1/chat/completions", self.base_url);
 
-        let input = ChatCompletionRequest::new(messages, tools, None);
+        let mut input = ChatCompletionRequest::new(messages, tools, None);
+        input.response_format = response_format;
 
         let response = self
-            .client
             .post(&url)
             .json(&input)
             .send()
@@ -575,15 +706,75 @@ impl Client {
         }
 
         let value: serde_json::Value = response.json().await.map_err(|e| e.to_string())?;
+        let response = decode::<ChatCompletionResponse>(value)?;
+        // Failing to record usage should never fail the completion
+        let _ = usage::record_usage(&response.model, &response.usage);
 
-        match serde_json::from_value::<ChatCompletionResponse>(value.clone()) {
-            Ok(response) => Ok(response),
-            Err(e) => {
-                eprintln!("Failed to deserialize response: {}", e);
-                eprintln!("Raw response: {}", value);
-                Err("Failed to deserialize response:".into())
-            }
+        Ok(response)
+    }
+
+    /// Ask for a JSON response matching `schema` and parse it into `T`.
+    ///
+    /// Responses that are not valid JSON or violate the schema are sent back to the model
+    /// together with the violations, up to `max_attempts` completions in total.
+    pub async fn structured_completion<T: DeserializeOwned>(
+        &self,
+        mut messages: Vec<ChatMessage>,
+        name: &str,
+        schema: Value,
+        max_attempts: usize,
+    ) -> Result<T, String> {
+        let response_format = ResponseFormat::json_schema(name, schema.clone());
+        let mut last_error = String::new();
+
+        for _ in 0..max_attempts.max(1) {
+            let response = self
+                .chat_completion_with_format(messages.clone(), None, Some(response_format.clone()))
+                .await?;
+            let content = response
+                .choices
+                .first()
+                .and_then(|choice| choice.message.content.as_ref())
+                .map(|content| content.to_string())
+                .unwrap_or_default();
+
+            let violations = match serde_json::from_str::<Value>(strip_code_fence(&content)) {
+                Ok(value) => match json_schema::validate(&value, &schema) {
+                    Ok(()) => {
+                        return serde_json::from_value(value)
+                            .map_err(|e| format!("Failed to parse {} response: {}", name, e));
+                    }
+                    Err(violations) => violations,
+                },
+                Err(e) => vec![format!("response is not valid JSON: {}", e)],
+            };
+
+            last_error = violations.join("; ");
+            messages.push(ChatMessage {
+                role: Role::Assistant,
+                content: Some(MessageContent::String(content)),
+                name: None,
+                tool_calls: None,
+                tool_call_id: None,
+            });
+            messages.push(ChatMessage {
+                role: Role::User,
+                content: Some(MessageContent::String(format!(
+                    "Your response does not match the required JSON schema:\n- {}\nReply again with only a JSON document matching the schema.",
+                    violations.join("\n- ")
+                ))),
+                name: None,
+                tool_calls: None,
+                tool_call_id: None,
+            });
         }
+
+        Err(format!(
+            "Model did not return a valid {} response after {} attempts: {}",
+            name,
+            max_attempts.max(1),
+            last_error
+        ))
     }
 
     pub async fn chat_completion_stream(
@@ -596,7 +787,6 @@ impl Client {
         let input = ChatCompletionRequest::new(messages, tools, Some(true));
 
         let response = self
-            .client
             .post(&url)
             .json(&input)
             .send()
@@ -616,7 +806,7 @@ impl Client {
                 })
         });
 
-        Ok(stream)
+        Ok(usage::track_stream_usage(stream))
     }
 
     pub async fn generate_code(
@@ -626,7 +816,6 @@ impl Client {
         let url = format!("{}/commands/{}/generate", self.base_url, input.provisioner);
 
         let response = self
-            .client
             .post(&url)
             .json(&input)
             .send()
@@ -639,18 +828,23 @@ impl Client {
         }
 
         let value: serde_json::Value = response.json().await.map_err(|e| e.to_string())?;
-        match serde_json::from_value::<GenerateCodeOutput>(value.clone()) {
-            Ok(response) => Ok(response),
-            Err(e) => {
-                eprintln!("Failed to deserialize response: {}", e);
-                eprintln!("Raw response: {}", value);
-                Err("Failed to deserialize response:".into())
-            }
-        }
+        Ok(decode::<GenerateCodeOutput>(value)?)
     }
 
     pub async fn call_mcp_tool(&self, input: &ToolsCallParams) -> Result<Vec<Content>, String> {
+        self.call_mcp_tool_with_progress(input, None).await
+    }
+
+    /// Call a remote MCP tool, asking the API to stream the call so its progress
+    /// notifications are forwarded to `progress` while the tool runs. Responses that are
+    /// not streamed are handled as a plain JSON-RPC response.
+    pub async fn call_mcp_tool_with_progress(
+        &self,
+        input: &ToolsCallParams,
+        progress: Option<tokio::sync::mpsc::Sender<String>>,
+    ) -> Result<Vec<Content>, String> {
         let url = format!("{}/mcp", self.base_url);
+        let request_id = Uuid::new_v4().to_string();
 
         let payload = json!({
             "jsonrpc": "2.0",
@@ -658,14 +852,78 @@ impl Client {
             "params": {
                 "name": input.name,
                 "arguments": input.arguments,
+                "_meta": { "progressToken": request_id },
             },
-            "id": Uuid::new_v4().to_string(),
+            "id": request_id,
         });
 
+        let mut request = self.post(&url).json(&payload);
+        if progress.is_some() {
+            request = request.header(
+                reqwest::header::ACCEPT,
+                "application/json, text/event-stream",
+            );
+        }
+        let response = request
+            .send()
+            .await
+            .map_err(|e: ReqwestError| e.to_string())?;
+
+        if !response.status().is_success() {
+            let error: ApiError = response.json().await.map_err(|e| e.to_string())?;
+            return Err(error.error.message);
+        }
+
+        let is_event_stream = response
+            .headers()
+            .get(reqwest::header::CONTENT_TYPE)
+            .and_then(|value| value.to_str().ok())
+            .is_some_and(|value| value.starts_with("text/event-stream"));
+
+        let value: serde_json::Value = if is_event_stream {
+            let mut events = response.bytes_stream().eventsource();
+            let mut result = None;
+            while let Some(event) = events.next().await {
+                let event = event.map_err(|e| format!("Failed to read response: {}", e))?;
+                let Ok(message) = serde_json::from_str::<serde_json::Value>(&event.data) else {
+                    continue;
+                };
+                if message.get("method").and_then(|m| m.as_str()) == Some("notifications/progress")
+                {
+                    if let (Some(progress), Some(update)) =
+                        (&progress, progress_update(&message["params"]))
+                    {
+                        let _ = progress.send(update).await;
+                    }
+                } else if message.get("id").and_then(|id| id.as_str()) == Some(request_id.as_str())
+                {
+                    result = Some(message);
+                    break;
+                }
+            }
+            result.ok_or("Stream ended before the tool call completed")?
+        } else {
+            response.json().await.map_err(|e| e.to_string())?
+        };
+
+        let response = decode::<JsonRpcResponse<ToolsCallResponse>>(value)?;
+        Ok(response.result.content)
+    }
+
+    async fn fetch_page<T: DeserializeOwned>(
+        &self,
+        url: &str,
+        cursor: Option<&str>,
+        page_size: usize,
+    ) -> Result<Page<T>, String> {
+        let mut query = vec![("limit", page_size.to_string())];
+        if let Some(cursor) = cursor {
+            query.push(("cursor", cursor.to_string()));
+        }
+
         let response = self
-            .client
-            .post(&url)
-            .json(&payload)
+            .get(url)
+            .query(&query)
             .send()
             .await
             .map_err(|e: ReqwestError| e.to_string())?;
@@ -676,18 +934,84 @@ impl Client {
         }
 
         let value: serde_json::Value = response.json().await.map_err(|e| e.to_string())?;
+        Ok(decode::<Page<T>>(value)?)
+    }
+
+    /// Iterate over the pages of a list endpoint until `limit` items are collected
+    /// (all of them when `None`), see [`collect_pages`]
+    async fn fetch_pages<T: DeserializeOwned>(
+        &self,
+        url: &str,
+        limit: Option<usize>,
+    ) -> Result<Listing<T>, String> {
+        collect_pages(limit, |cursor, page_size| async move {
+            self.fetch_page::<T>(url, cursor.as_deref(), page_size)
+                .await
+        })
+        .await
+    }
+}
 
-        match serde_json::from_value::<JsonRpcResponse<ToolsCallResponse>>(value.clone()) {
-            Ok(response) => Ok(response.result.content),
-            Err(e) => {
-                eprintln!("Failed to deserialize response: {}", e);
-                eprintln!("Raw response: {}", value);
-                Err("Failed to deserialize response:".into())
+/// Collect the items of a paginated listing, capped at MAX_PAGES requests. Endpoints
+/// that don't paginate return everything in the first page.
+async fn collect_pages<T, F, Fut>(limit: Option<usize>, mut fetch: F) -> Result<Listing<T>, String>
+where
+    F: FnMut(Option<String>, usize) -> Fut,
+    Fut: std::future::Future<Output = Result<Page<T>, String>>,
+{
+    let mut items = Vec::new();
+    let mut cursor: Option<String> = None;
+    let mut has_more = false;
+
+    for _ in 0..MAX_PAGES {
+        let remaining = limit.map(|l| l.saturating_sub(items.len()));
+        let page_size = remaining.unwrap_or(PAGE_SIZE).min(PAGE_SIZE);
+        if page_size == 0 {
+            break;
+        }
+
+        let (results, next_cursor) = match fetch(cursor.clone(), page_size).await? {
+            Page::Paged {
+                results,
+                next_cursor,
+            } => (results, next_cursor),
+            Page::Unpaged(results) => (results, None),
+        };
+        let received = results.len();
+        items.extend(results);
+
+        match next_cursor {
+            // Stop on an empty page or a cursor that doesn't advance
+            Some(next) if received > 0 && cursor.as_deref() != Some(next.as_str()) => {
+                cursor = Some(next);
+                has_more = true;
+            }
+            _ => {
+                has_more = false;
+                break;
             }
         }
     }
+
+    if let Some(limit) = limit {
+        has_more |= items.len() > limit;
+        items.truncate(limit);
+    }
+    Ok(Listing { items, has_more })
 }
-#[derive(Serialize, Deserialize, Clone, Debug)]
+
+/// Models occasionally wrap JSON responses in a markdown code fence
+fn strip_code_fence(content: &str) -> &str {
+    let content = content.trim();
+    content
+        .strip_prefix("```json")
+        .or_else(|| content.strip_prefix("```"))
+        .and_then(|inner| inner.strip_suffix("```"))
+        .map(|inner| inner.trim())
+        .unwrap_or(content)
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
 pub struct GetMyAccountResponse {
     pub username: String,
     pub id: String,
@@ -704,6 +1028,23 @@ impl GetMyAccountResponse {
     }
 }
 
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct UsageQuota {
+    /// Quota name, e.g. api_calls, tokens or agent_minutes
+    pub name: String,
+    pub used: u64,
+    /// None when the plan does not limit this quota
+    pub limit: Option<u64>,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct GetUsageResponse {
+    pub plan: String,
+    pub period_start: DateTime<Utc>,
+    pub period_end: DateTime<Utc>,
+    pub quotas: Vec<UsageQuota>,
+}
+
 #[derive(Deserialize, Serialize, Debug)]
 pub struct GetFlowsResponse {
     pub results: Vec<Flow>,
@@ -817,7 +1158,7 @@ Score: {:.2}%
             "#,
                     result.flow_version.flow_name,
                     result.flow_version.version_id,
-                    result.block.document_uri.strip_prefix("file:///").unwrap(),
+                    uri_to_relative_path(&result.block.document_uri),
                     result.block.start_point.row,
                     result.block.start_point.column,
                     result.similarity * 100.0,
@@ -858,7 +1199,7 @@ pub struct SaveEditsInput {
     pub edits: Vec<Edit>,
 }
 
-#[derive(Serialize, Debug)]
+#[derive(Serialize, Debug, Clone)]
 pub struct Edit {
     pub content: String,
     pub document_uri: String,
@@ -963,7 +1304,7 @@ impl std::fmt::Display for EditInfo {
 >>>>>>> REPLACE
 ```"#,
                     self.reasoning,
-                    self.document_uri.strip_prefix("file:///").unwrap(),
+                    uri_to_relative_path(&self.document_uri),
                     self.old_str,
                     self.new_str
                 )
@@ -978,7 +1319,7 @@ impl std::fmt::Display for EditInfo {
 {}
 ```"#,
                     self.reasoning,
-                    self.document_uri.strip_prefix("file:///").unwrap(),
+                    uri_to_relative_path(&self.document_uri),
                     self.new_str
                 )
             }
@@ -995,7 +1336,7 @@ impl std::fmt::Display for EditInfo {
 >>>>>>> REPLACE
 ```"#,
                     self.reasoning,
-                    self.document_uri.strip_prefix("file:///").unwrap(),
+                    uri_to_relative_path(&self.document_uri),
                     self.old_str
                 )
             }
@@ -1014,3 +1355,82 @@ pub struct ToolsCallParams {
 pub struct ToolsCallResponse {
     pub content: Vec<Content>,
 }
+
+/// Text of an MCP progress notification, its message or else "progress/total"
+fn progress_update(params: &serde_json::Value) -> Option<String> {
+    if let Some(message) = params.get("message").and_then(|m| m.as_str()) {
+        return Some(message.to_string());
+    }
+    let progress = params.get("progress")?.as_f64()?;
+    Some(match params.get("total").and_then(|t| t.as_f64()) {
+        Some(total) => format!("{}/{}", progress, total),
+        None => progress.to_string(),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Serve `total` numbered items in pages, the cursor is the index of the next item
+    async fn list(total: usize, limit: Option<usize>) -> Listing<usize> {
+        collect_pages(limit, |cursor, page_size| async move {
+            let start = cursor.map_or(0, |cursor| cursor.parse().unwrap());
+            let end = (start + page_size).min(total);
+            Ok(Page::Paged {
+                results: (start..end).collect(),
+                next_cursor: (end < total).then(|| end.to_string()),
+            })
+        })
+        .await
+        .unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_collect_pages_reports_more_results() {
+        let listing = list(30, Some(20)).await;
+        assert_eq!(listing.items, (0..20).collect::<Vec<_>>());
+        assert!(listing.has_more);
+
+        let listing = list(20, Some(20)).await;
+        assert_eq!(listing.items.len(), 20);
+        assert!(!listing.has_more);
+
+        let listing = list(120, None).await;
+        assert_eq!(listing.items.len(), 120);
+        assert!(!listing.has_more);
+    }
+
+    #[tokio::test]
+    async fn test_collect_pages_stops_at_max_pages() {
+        let listing = list(PAGE_SIZE * MAX_PAGES + 1, None).await;
+        assert_eq!(listing.items.len(), PAGE_SIZE * MAX_PAGES);
+        assert!(listing.has_more);
+    }
+
+    #[tokio::test]
+    async fn test_collect_pages_unpaged() {
+        let listing = collect_pages(Some(2), |_, _| async { Ok(Page::Unpaged(vec![1, 2, 3])) })
+            .await
+            .unwrap();
+        assert_eq!(listing.items, vec![1, 2]);
+        assert!(listing.has_more);
+    }
+
+    #[test]
+    fn test_progress_update() {
+        assert_eq!(
+            progress_update(&json!({"progress": 3, "message": "Writing main.tf"})),
+            Some("Writing main.tf".to_string())
+        );
+        assert_eq!(
+            progress_update(&json!({"progress": 3, "total": 4})),
+            Some("3/4".to_string())
+        );
+        assert_eq!(
+            progress_update(&json!({"progress": 0.5})),
+            Some("0.5".to_string())
+        );
+        assert_eq!(progress_update(&json!({})), None);
+    }
+}
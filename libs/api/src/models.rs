@@ -53,6 +53,18 @@ pub struct GetFlowDocumentsResponse {
     pub additional_documents: Vec<Document>,
 }
 
+/// Result of fetching flow documents with a known ETag
+#[derive(Debug)]
+pub enum FlowDocumentsFetch {
+    /// The documents did not change since the ETag was issued
+    NotModified,
+    Modified {
+        documents: GetFlowDocumentsResponse,
+        /// ETag to send with the next fetch, if the API returned one
+        etag: Option<String>,
+    },
+}
+
 #[derive(Deserialize, Serialize, Debug)]
 pub struct Document {
     pub content: String,
@@ -266,6 +278,9 @@ pub enum APIGroupVersionStatus {
     Pending,
     #[serde(rename = "AVAILABLE")]
     Available,
+    /// Status added to the API after this CLI was released
+    #[serde(other, rename = "UNKNOWN")]
+    Unknown,
 }
 
 #[derive(Deserialize, Serialize, Debug)]
@@ -459,6 +474,9 @@ pub enum AgentStatus {
     Blocked,
     #[serde(rename = "FAILED")]
     Failed,
+    /// Status added to the API after this CLI was released
+    #[serde(other, rename = "UNKNOWN")]
+    Unknown,
 }
 impl std::fmt::Display for AgentStatus {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
@@ -467,6 +485,7 @@ impl std::fmt::Display for AgentStatus {
             AgentStatus::Complete => write!(f, "COMPLETE"),
             AgentStatus::Blocked => write!(f, "BLOCKED"),
             AgentStatus::Failed => write!(f, "FAILED"),
+            AgentStatus::Unknown => write!(f, "UNKNOWN"),
         }
     }
 }
@@ -569,6 +588,9 @@ pub enum ActionStatus {
     Failed,
     Aborted,
     PendingHumanReview,
+    /// Status added to the API after this CLI was released
+    #[serde(other)]
+    Unknown,
 }
 
 impl std::fmt::Display for ActionStatus {
@@ -580,6 +602,7 @@ impl std::fmt::Display for ActionStatus {
             ActionStatus::Failed => write!(f, "FAILED"),
             ActionStatus::Aborted => write!(f, "ABORTED"),
             ActionStatus::PendingHumanReview => write!(f, "PENDING_HUMAN_REVIEW"),
+            ActionStatus::Unknown => write!(f, "UNKNOWN"),
         }
     }
 }
@@ -714,6 +737,93 @@ pub struct RunAgentOutput {
     pub checkpoint: AgentCheckpointListItem,
     pub session: AgentSessionListItem,
     pub output: AgentOutput,
+    /// Conclusion of the run that produced this checkpoint, attached by async runs
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub summary: Option<RunSummary>,
+}
+
+/// Human-readable conclusion of an agent run
+#[derive(Debug, Deserialize, Serialize, Clone, Default, PartialEq)]
+pub struct RunSummary {
+    pub steps: usize,
+    /// Final answer of the agent
+    pub conclusion: String,
+    pub commands: Vec<RunSummaryCommand>,
+    pub changed_files: Vec<RunSummaryFileChange>,
+    /// Work the agent left open, as listed in its final answer
+    pub todos: Vec<String>,
+}
+
+/// Command output uploaded in chunks so it can be searched instead of truncated
+#[derive(Debug, Deserialize, Serialize, Clone, PartialEq)]
+pub struct OutputArtifact {
+    pub id: Uuid,
+}
+
+#[derive(Debug, Deserialize, Serialize, Clone, PartialEq)]
+pub struct CreateOutputArtifactInput {
+    /// Command that produced the output
+    pub command: String,
+    pub total_lines: usize,
+    pub chunk_count: usize,
+}
+
+#[derive(Debug, Deserialize, Serialize, Clone, PartialEq)]
+pub struct OutputChunkInput {
+    /// Position of the chunk, starting at 0
+    pub index: usize,
+    /// Line number of the first line of the chunk, starting at 1
+    pub first_line: usize,
+    pub content: String,
+}
+
+#[derive(Debug, Deserialize, Serialize, Clone, PartialEq)]
+pub struct RunSummaryCommand {
+    pub command: String,
+    pub failed: bool,
+}
+
+#[derive(Debug, Deserialize, Serialize, Clone, PartialEq)]
+pub struct RunSummaryFileChange {
+    pub path: String,
+    /// Changed lines prefixed with `-` and `+`
+    pub diff: String,
+}
+
+impl RunSummary {
+    pub fn to_markdown(&self) -> String {
+        let mut markdown = format!("## Run summary ({} steps)\n\n", self.steps);
+        if !self.conclusion.is_empty() {
+            markdown.push_str(&format!("{}\n\n", self.conclusion));
+        }
+        if !self.commands.is_empty() {
+            markdown.push_str("### Commands\n\n");
+            for command in &self.commands {
+                markdown.push_str(&format!(
+                    "- `{}`{}\n",
+                    command.command,
+                    if command.failed { " (failed)" } else { "" }
+                ));
+            }
+            markdown.push('\n');
+        }
+        if !self.changed_files.is_empty() {
+            markdown.push_str("### Changed files\n\n");
+            for change in &self.changed_files {
+                markdown.push_str(&format!(
+                    "`{}`\n```diff\n{}\n```\n\n",
+                    change.path, change.diff
+                ));
+            }
+        }
+        if !self.todos.is_empty() {
+            markdown.push_str("### Outstanding TODOs\n\n");
+            for todo in &self.todos {
+                markdown.push_str(&format!("- {}\n", todo));
+            }
+        }
+        markdown.trim_end().to_string()
+    }
 }
 
 #[derive(Debug, Deserialize, Serialize, Clone, PartialEq)]
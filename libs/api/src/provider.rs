@@ -0,0 +1,678 @@
+use crate::Client;
+use crate::usage::{record_usage, track_stream_usage};
+use eventsource_stream::Eventsource;
+use futures_util::{Stream, StreamExt, future};
+use reqwest::{Client as ReqwestClient, RequestBuilder, Response};
+use serde::{Deserialize, Serialize};
+use serde_json::{Value, json};
+use stakpak_shared::models::integrations::openai::{
+    ChatCompletionChoice, ChatCompletionRequest, ChatCompletionResponse,
+    ChatCompletionStreamChoice, ChatCompletionStreamResponse, ChatMessage, ChatMessageDelta,
+    FinishReason, FunctionCall, FunctionCallDelta, MessageContent, Role, Tool, ToolCall,
+    ToolCallDelta, Usage,
+};
+use std::collections::{BTreeMap, HashMap};
+use std::future::Future;
+use std::pin::Pin;
+
+const OPENAI_ENDPOINT: &str = "https://api.openai.com/v1";
+const ANTHROPIC_ENDPOINT: &str = "https://api.anthropic.com/v1";
+const OLLAMA_ENDPOINT: &str = "http://localhost:11434/v1";
+const ANTHROPIC_VERSION: &str = "2023-06-01";
+/// Anthropic requires a response length limit, OpenAI compatible APIs default to the model's
+const ANTHROPIC_MAX_TOKENS: u32 = 8192;
+
+pub type ChatCompletionStream =
+    Pin<Box<dyn Stream<Item = Result<ChatCompletionStreamResponse, String>> + Send>>;
+
+/// Chat completion API the agent loop runs against
+pub trait LLMProvider {
+    fn chat_completion(
+        &self,
+        messages: Vec<ChatMessage>,
+        tools: Option<Vec<Tool>>,
+    ) -> impl Future<Output = Result<ChatCompletionResponse, String>> + Send;
+
+    fn chat_completion_stream(
+        &self,
+        messages: Vec<ChatMessage>,
+        tools: Option<Vec<Tool>>,
+    ) -> impl Future<Output = Result<ChatCompletionStream, String>> + Send;
+}
+
+impl LLMProvider for Client {
+    async fn chat_completion(
+        &self,
+        messages: Vec<ChatMessage>,
+        tools: Option<Vec<Tool>>,
+    ) -> Result<ChatCompletionResponse, String> {
+        Client::chat_completion(self, messages, tools).await
+    }
+
+    async fn chat_completion_stream(
+        &self,
+        messages: Vec<ChatMessage>,
+        tools: Option<Vec<Tool>>,
+    ) -> Result<ChatCompletionStream, String> {
+        let stream = Client::chat_completion_stream(self, messages, tools).await?;
+        Ok(Box::pin(stream))
+    }
+}
+
+/// API an LLM endpoint speaks
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum ProviderKind {
+    Stakpak,
+    /// OpenAI and compatible APIs, e.g. Ollama, vLLM or LiteLLM
+    OpenAI,
+    Anthropic,
+}
+
+/// LLM endpoint configured under `[llm_providers.<name>]`. `openai`, `anthropic` and
+/// `ollama` work without any configuration.
+#[derive(Serialize, Deserialize, Clone, Debug, Default, PartialEq)]
+pub struct LLMProviderConfig {
+    /// API the endpoint speaks, only needed for providers without a built-in default
+    pub kind: Option<ProviderKind>,
+    pub endpoint: Option<String>,
+    pub api_key: Option<String>,
+}
+
+/// Kind, endpoint and API key environment variable of the built-in providers
+fn builtin_provider(name: &str) -> Option<(ProviderKind, &'static str, Option<&'static str>)> {
+    match name {
+        "openai" => Some((
+            ProviderKind::OpenAI,
+            OPENAI_ENDPOINT,
+            Some("OPENAI_API_KEY"),
+        )),
+        "anthropic" => Some((
+            ProviderKind::Anthropic,
+            ANTHROPIC_ENDPOINT,
+            Some("ANTHROPIC_API_KEY"),
+        )),
+        "ollama" => Some((ProviderKind::OpenAI, OLLAMA_ENDPOINT, None)),
+        _ => None,
+    }
+}
+
+/// Provider the agent talks to, selected with `--model <provider>/<model>`
+#[derive(Clone)]
+pub enum Provider {
+    Stakpak(Client),
+    OpenAI(OpenAIProvider),
+    Anthropic(AnthropicProvider),
+}
+
+impl Provider {
+    /// Resolve a model like `openai/gpt-4o` or `ollama/llama3.1`. Without a model, or with
+    /// `stakpak`, completions go through the Stakpak API.
+    pub fn from_model(
+        model: Option<&str>,
+        providers: &BTreeMap<String, LLMProviderConfig>,
+        stakpak: Client,
+    ) -> Result<Self, String> {
+        let Some(model) = model.filter(|model| *model != "stakpak") else {
+            return Ok(Provider::Stakpak(stakpak));
+        };
+        let (name, model) = model.split_once('/').ok_or_else(|| {
+            format!(
+                "Invalid model '{}', expected <provider>/<model>, e.g. openai/gpt-4o",
+                model
+            )
+        })?;
+        let config = providers.get(name).cloned().unwrap_or_default();
+        let builtin = builtin_provider(name);
+        let kind = config
+            .kind
+            .or(builtin.map(|(kind, _, _)| kind))
+            .ok_or_else(|| {
+                format!(
+                    "Unknown provider '{}', set its kind and endpoint under [llm_providers.{}]",
+                    name, name
+                )
+            })?;
+        let endpoint = config
+            .endpoint
+            .or(builtin.map(|(_, endpoint, _)| endpoint.to_string()));
+        let api_key = config.api_key.or_else(|| {
+            builtin
+                .and_then(|(_, _, env_var)| env_var)
+                .and_then(|env_var| std::env::var(env_var).ok())
+        });
+
+        match kind {
+            ProviderKind::Stakpak => Ok(Provider::Stakpak(stakpak)),
+            ProviderKind::OpenAI => Ok(Provider::OpenAI(OpenAIProvider {
+                client: ReqwestClient::new(),
+                endpoint: endpoint
+                    .ok_or_else(|| format!("No endpoint configured for provider '{}'", name))?,
+                api_key,
+                model: model.to_string(),
+            })),
+            ProviderKind::Anthropic => Ok(Provider::Anthropic(AnthropicProvider {
+                client: ReqwestClient::new(),
+                endpoint: endpoint
+                    .ok_or_else(|| format!("No endpoint configured for provider '{}'", name))?,
+                api_key: api_key
+                    .ok_or_else(|| format!("No API key configured for provider '{}'", name))?,
+                model: model.to_string(),
+            })),
+        }
+    }
+}
+
+impl LLMProvider for Provider {
+    async fn chat_completion(
+        &self,
+        messages: Vec<ChatMessage>,
+        tools: Option<Vec<Tool>>,
+    ) -> Result<ChatCompletionResponse, String> {
+        match self {
+            Provider::Stakpak(client) => {
+                LLMProvider::chat_completion(client, messages, tools).await
+            }
+            Provider::OpenAI(provider) => provider.chat_completion(messages, tools).await,
+            Provider::Anthropic(provider) => provider.chat_completion(messages, tools).await,
+        }
+    }
+
+    async fn chat_completion_stream(
+        &self,
+        messages: Vec<ChatMessage>,
+        tools: Option<Vec<Tool>>,
+    ) -> Result<ChatCompletionStream, String> {
+        match self {
+            Provider::Stakpak(client) => {
+                LLMProvider::chat_completion_stream(client, messages, tools).await
+            }
+            Provider::OpenAI(provider) => provider.chat_completion_stream(messages, tools).await,
+            Provider::Anthropic(provider) => provider.chat_completion_stream(messages, tools).await,
+        }
+    }
+}
+
+/// Send `request`, turning error responses into their status and body
+async fn send_request(request: RequestBuilder) -> Result<Response, String> {
+    let response = request.send().await.map_err(|e| e.to_string())?;
+    if !response.status().is_success() {
+        let status = response.status();
+        let body = response.text().await.unwrap_or_default();
+        return Err(format!("LLM provider returned {}: {}", status, body));
+    }
+    Ok(response)
+}
+
+/// OpenAI chat completions API, also served by Ollama and most local inference servers
+#[derive(Clone)]
+pub struct OpenAIProvider {
+    client: ReqwestClient,
+    endpoint: String,
+    api_key: Option<String>,
+    model: String,
+}
+
+impl OpenAIProvider {
+    fn request(
+        &self,
+        messages: Vec<ChatMessage>,
+        tools: Option<Vec<Tool>>,
+        stream: bool,
+    ) -> RequestBuilder {
+        let mut input = ChatCompletionRequest::new(messages, tools, stream.then_some(true));
+        input.model = self.model.clone();
+        let mut request = self
+            .client
+            .post(format!(
+                "{}/chat/completions",
+                self.endpoint.trim_end_matches('/')
+            ))
+            .json(&input);
+        if let Some(api_key) = &self.api_key {
+            request = request.bearer_auth(api_key);
+        }
+        request
+    }
+}
+
+impl LLMProvider for OpenAIProvider {
+    async fn chat_completion(
+        &self,
+        messages: Vec<ChatMessage>,
+        tools: Option<Vec<Tool>>,
+    ) -> Result<ChatCompletionResponse, String> {
+        let response: ChatCompletionResponse = send_request(self.request(messages, tools, false))
+            .await?
+            .json()
+            .await
+            .map_err(|e| format!("Failed to deserialize response: {}", e))?;
+        // Failing to record usage should never fail the completion
+        let _ = record_usage(&response.model, &response.usage);
+        Ok(response)
+    }
+
+    async fn chat_completion_stream(
+        &self,
+        messages: Vec<ChatMessage>,
+        tools: Option<Vec<Tool>>,
+    ) -> Result<ChatCompletionStream, String> {
+        let response = send_request(self.request(messages, tools, true)).await?;
+        let stream = response.bytes_stream().eventsource().filter_map(|event| {
+            future::ready(match event {
+                Err(_) => Some(Err("Failed to read response".to_string())),
+                Ok(event) if event.data == "[DONE]" => None,
+                Ok(event) => {
+                    match serde_json::from_str::<ChatCompletionStreamResponse>(&event.data) {
+                        // Only the usage chunk is worth passing on without choices
+                        Ok(chunk) if chunk.choices.is_empty() && chunk.usage.is_none() => None,
+                        Ok(chunk) => Some(Ok(chunk)),
+                        Err(e) => Some(Err(format!("Failed to parse response chunk: {}", e))),
+                    }
+                }
+            })
+        });
+        Ok(Box::pin(track_stream_usage(stream)))
+    }
+}
+
+/// Anthropic messages API, translated from and to the OpenAI message format
+#[derive(Clone)]
+pub struct AnthropicProvider {
+    client: ReqwestClient,
+    endpoint: String,
+    api_key: String,
+    model: String,
+}
+
+impl AnthropicProvider {
+    fn request(
+        &self,
+        messages: &[ChatMessage],
+        tools: Option<&[Tool]>,
+        stream: bool,
+    ) -> RequestBuilder {
+        let mut body = anthropic_request(messages, tools);
+        body["model"] = json!(self.model);
+        body["max_tokens"] = json!(ANTHROPIC_MAX_TOKENS);
+        if stream {
+            body["stream"] = json!(true);
+        }
+        self.client
+            .post(format!("{}/messages", self.endpoint.trim_end_matches('/')))
+            .header("x-api-key", &self.api_key)
+            .header("anthropic-version", ANTHROPIC_VERSION)
+            .json(&body)
+    }
+}
+
+impl LLMProvider for AnthropicProvider {
+    async fn chat_completion(
+        &self,
+        messages: Vec<ChatMessage>,
+        tools: Option<Vec<Tool>>,
+    ) -> Result<ChatCompletionResponse, String> {
+        let response: AnthropicResponse =
+            send_request(self.request(&messages, tools.as_deref(), false))
+                .await?
+                .json()
+                .await
+                .map_err(|e| format!("Failed to deserialize response: {}", e))?;
+        let response = response.into_chat_completion();
+        // Failing to record usage should never fail the completion
+        let _ = record_usage(&response.model, &response.usage);
+        Ok(response)
+    }
+
+    async fn chat_completion_stream(
+        &self,
+        messages: Vec<ChatMessage>,
+        tools: Option<Vec<Tool>>,
+    ) -> Result<ChatCompletionStream, String> {
+        let response = send_request(self.request(&messages, tools.as_deref(), true)).await?;
+        let stream = response
+            .bytes_stream()
+            .eventsource()
+            .scan(AnthropicStreamState::default(), |state, event| {
+                future::ready(Some(match event {
+                    Ok(event) => state.chunk(&event.data),
+                    Err(_) => Some(Err("Failed to read response".to_string())),
+                }))
+            })
+            .filter_map(future::ready);
+        Ok(Box::pin(track_stream_usage(stream)))
+    }
+}
+
+fn text_of(content: &Option<MessageContent>) -> String {
+    content
+        .as_ref()
+        .map(|content| content.to_string())
+        .unwrap_or_default()
+}
+
+/// Anthropic request body without model and limits. System messages become the system
+/// prompt, tool results are sent as user messages and consecutive messages of the same
+/// role are merged, as the API requires roles to alternate.
+fn anthropic_request(messages: &[ChatMessage], tools: Option<&[Tool]>) -> Value {
+    let mut system = Vec::new();
+    let mut turns: Vec<(&str, Vec<Value>)> = Vec::new();
+    for message in messages {
+        let (role, blocks) = match message.role {
+            Role::System | Role::Developer => {
+                system.push(text_of(&message.content));
+                continue;
+            }
+            Role::User => (
+                "user",
+                vec![json!({"type": "text", "text": text_of(&message.content)})],
+            ),
+            Role::Tool => (
+                "user",
+                vec![json!({
+                    "type": "tool_result",
+                    "tool_use_id": message.tool_call_id.clone().unwrap_or_default(),
+                    "content": text_of(&message.content),
+                })],
+            ),
+            Role::Assistant => {
+                let text = text_of(&message.content);
+                let mut blocks = Vec::new();
+                if !text.is_empty() {
+                    blocks.push(json!({"type": "text", "text": text}));
+                }
+                for tool_call in message.tool_calls.iter().flatten() {
+                    let input: Value = serde_json::from_str(&tool_call.function.arguments)
+                        .unwrap_or_else(|_| json!({}));
+                    blocks.push(json!({
+                        "type": "tool_use",
+                        "id": tool_call.id,
+                        "name": tool_call.function.name,
+                        "input": input,
+                    }));
+                }
+                ("assistant", blocks)
+            }
+        };
+        if blocks.is_empty() {
+            continue;
+        }
+        match turns.last_mut() {
+            Some((last_role, last_blocks)) if *last_role == role => last_blocks.extend(blocks),
+            _ => turns.push((role, blocks)),
+        }
+    }
+
+    let mut body = json!({
+        "messages": turns
+            .into_iter()
+            .map(|(role, content)| json!({"role": role, "content": content}))
+            .collect::<Vec<_>>(),
+    });
+    if !system.is_empty() {
+        body["system"] = json!(system.join("\n\n"));
+    }
+    if let Some(tools) = tools.filter(|tools| !tools.is_empty()) {
+        body["tools"] = tools
+            .iter()
+            .map(|tool| {
+                json!({
+                    "name": tool.function.name,
+                    "description": tool.function.description,
+                    "input_schema": tool.function.parameters,
+                })
+            })
+            .collect();
+    }
+    body
+}
+
+fn anthropic_finish_reason(stop_reason: Option<&str>) -> FinishReason {
+    match stop_reason {
+        Some("tool_use") => FinishReason::ToolCalls,
+        Some("max_tokens") => FinishReason::Length,
+        _ => FinishReason::Stop,
+    }
+}
+
+#[derive(Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+enum AnthropicBlock {
+    Text {
+        text: String,
+    },
+    ToolUse {
+        id: String,
+        name: String,
+        input: Value,
+    },
+    #[serde(other)]
+    Other,
+}
+
+#[derive(Deserialize, Default)]
+struct AnthropicUsage {
+    #[serde(default)]
+    input_tokens: u32,
+    #[serde(default)]
+    output_tokens: u32,
+}
+
+#[derive(Deserialize)]
+struct AnthropicResponse {
+    id: String,
+    model: String,
+    content: Vec<AnthropicBlock>,
+    stop_reason: Option<String>,
+    #[serde(default)]
+    usage: AnthropicUsage,
+}
+
+impl AnthropicResponse {
+    fn into_chat_completion(self) -> ChatCompletionResponse {
+        let mut text = String::new();
+        let mut tool_calls = Vec::new();
+        for block in self.content {
+            match block {
+                AnthropicBlock::Text { text: block_text } => text.push_str(&block_text),
+                AnthropicBlock::ToolUse { id, name, input } => tool_calls.push(ToolCall {
+                    id,
+                    r#type: "function".to_string(),
+                    function: FunctionCall {
+                        name,
+                        arguments: input.to_string(),
+                    },
+                }),
+                AnthropicBlock::Other => {}
+            }
+        }
+        ChatCompletionResponse {
+            id: self.id,
+            object: "chat.completion".to_string(),
+            created: chrono::Utc::now().timestamp() as u64,
+            model: self.model,
+            choices: vec![ChatCompletionChoice {
+                index: 0,
+                message: ChatMessage {
+                    role: Role::Assistant,
+                    content: Some(MessageContent::String(text)),
+                    name: None,
+                    tool_calls: (!tool_calls.is_empty()).then_some(tool_calls),
+                    tool_call_id: None,
+                },
+                logprobs: None,
+                finish_reason: anthropic_finish_reason(self.stop_reason.as_deref()),
+            }],
+            usage: Usage {
+                prompt_tokens: self.usage.input_tokens,
+                completion_tokens: self.usage.output_tokens,
+                total_tokens: self.usage.input_tokens + self.usage.output_tokens,
+            },
+            system_fingerprint: None,
+        }
+    }
+}
+
+/// Translates Anthropic stream events into OpenAI chunks
+#[derive(Default)]
+struct AnthropicStreamState {
+    id: String,
+    model: String,
+    /// Content block index to tool call index
+    tool_calls: HashMap<u64, usize>,
+    /// Reported when the message starts, output tokens only when it ends
+    input_tokens: u32,
+}
+
+impl AnthropicStreamState {
+    fn chunk(&mut self, data: &str) -> Option<Result<ChatCompletionStreamResponse, String>> {
+        let event: Value = match serde_json::from_str(data) {
+            Ok(event) => event,
+            Err(e) => return Some(Err(format!("Failed to parse response event: {}", e))),
+        };
+        let block_index = event["index"].as_u64().unwrap_or_default();
+        let mut delta = ChatMessageDelta {
+            role: None,
+            content: None,
+            tool_calls: None,
+        };
+        let mut finish_reason = None;
+        let mut usage = None;
+
+        match event["type"].as_str()? {
+            "message_start" => {
+                self.id = event["message"]["id"]
+                    .as_str()
+                    .unwrap_or_default()
+                    .to_string();
+                self.model = event["message"]["model"]
+                    .as_str()
+                    .unwrap_or_default()
+                    .to_string();
+                self.input_tokens = event["message"]["usage"]["input_tokens"]
+                    .as_u64()
+                    .unwrap_or_default() as u32;
+                delta.role = Some(Role::Assistant);
+            }
+            "content_block_start" if event["content_block"]["type"] == "tool_use" => {
+                let index = self.tool_calls.len();
+                self.tool_calls.insert(block_index, index);
+                delta.tool_calls = Some(vec![ToolCallDelta {
+                    index,
+                    id: event["content_block"]["id"].as_str().map(str::to_string),
+                    r#type: Some("function".to_string()),
+                    function: Some(FunctionCallDelta {
+                        name: event["content_block"]["name"].as_str().map(str::to_string),
+                        arguments: None,
+                    }),
+                }]);
+            }
+            "content_block_delta" => match event["delta"]["type"].as_str()? {
+                "text_delta" => {
+                    delta.content = event["delta"]["text"].as_str().map(str::to_string);
+                }
+                "input_json_delta" => {
+                    delta.tool_calls = Some(vec![ToolCallDelta {
+                        index: *self.tool_calls.get(&block_index)?,
+                        id: None,
+                        r#type: None,
+                        function: Some(FunctionCallDelta {
+                            name: None,
+                            arguments: event["delta"]["partial_json"].as_str().map(str::to_string),
+                        }),
+                    }]);
+                }
+                _ => return None,
+            },
+            "message_delta" => {
+                finish_reason = Some(anthropic_finish_reason(
+                    event["delta"]["stop_reason"].as_str(),
+                ));
+                let output_tokens =
+                    event["usage"]["output_tokens"].as_u64().unwrap_or_default() as u32;
+                usage = Some(Usage {
+                    prompt_tokens: self.input_tokens,
+                    completion_tokens: output_tokens,
+                    total_tokens: self.input_tokens + output_tokens,
+                });
+            }
+            "error" => {
+                return Some(Err(event["error"]["message"]
+                    .as_str()
+                    .unwrap_or("LLM provider stream failed")
+                    .to_string()));
+            }
+            _ => return None,
+        }
+
+        Some(Ok(ChatCompletionStreamResponse {
+            id: self.id.clone(),
+            object: "chat.completion.chunk".to_string(),
+            created: chrono::Utc::now().timestamp() as u64,
+            model: self.model.clone(),
+            choices: vec![ChatCompletionStreamChoice {
+                index: 0,
+                delta,
+                finish_reason,
+            }],
+            usage,
+        }))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn message(role: Role, content: &str) -> ChatMessage {
+        ChatMessage {
+            role,
+            content: Some(MessageContent::String(content.to_string())),
+            name: None,
+            tool_calls: None,
+            tool_call_id: None,
+        }
+    }
+
+    #[test]
+    fn test_anthropic_request() {
+        let mut assistant = message(Role::Assistant, "");
+        assistant.tool_calls = Some(vec![ToolCall {
+            id: "call_1".to_string(),
+            r#type: "function".to_string(),
+            function: FunctionCall {
+                name: "run_command".to_string(),
+                arguments: r#"{"command":"ls"}"#.to_string(),
+            },
+        }]);
+        let mut tool_result = message(Role::Tool, "README.md");
+        tool_result.tool_call_id = Some("call_1".to_string());
+        let body = anthropic_request(
+            &[
+                message(Role::System, "Be brief"),
+                message(Role::User, "List files"),
+                assistant,
+                tool_result,
+                message(Role::User, "Thanks"),
+            ],
+            None,
+        );
+
+        assert_eq!(body["system"], "Be brief");
+        let turns = body["messages"].as_array().cloned().unwrap_or_default();
+        assert_eq!(turns.len(), 3);
+        assert_eq!(turns[1]["content"][0]["input"]["command"], "ls");
+        assert_eq!(turns[2]["role"], "user");
+        assert_eq!(turns[2]["content"][0]["type"], "tool_result");
+        assert_eq!(turns[2]["content"][1]["text"], "Thanks");
+
+        let providers = BTreeMap::new();
+        if let Ok(stakpak) = Client::builder().api_key("test").build() {
+            assert!(matches!(
+                Provider::from_model(Some("ollama/llama3.1"), &providers, stakpak.clone()),
+                Ok(Provider::OpenAI(_))
+            ));
+            assert!(Provider::from_model(Some("gpt-4o"), &providers, stakpak).is_err());
+        }
+    }
+}
@@ -0,0 +1,257 @@
+use futures_util::{Stream, StreamExt};
+use serde::{Deserialize, Serialize};
+use stakpak_shared::local_store::LocalStore;
+use stakpak_shared::models::integrations::openai::{ChatCompletionStreamResponse, Usage};
+use std::collections::BTreeMap;
+use std::fmt::Write;
+
+/// Token usage of the session, in the session namespace of the local store
+const USAGE_FILE: &str = "usage.json";
+
+/// Price of a model in USD per million tokens
+struct ModelPrice {
+    /// Model name prefix, so dated versions like `gpt-4o-2024-08-06` are priced too
+    model: &'static str,
+    input: f64,
+    output: f64,
+}
+
+const MODEL_PRICES: &[ModelPrice] = &[
+    ModelPrice {
+        model: "gpt-4o",
+        input: 2.5,
+        output: 10.0,
+    },
+    ModelPrice {
+        model: "gpt-4o-mini",
+        input: 0.15,
+        output: 0.6,
+    },
+    ModelPrice {
+        model: "gpt-4.1",
+        input: 2.0,
+        output: 8.0,
+    },
+    ModelPrice {
+        model: "gpt-4.1-mini",
+        input: 0.4,
+        output: 1.6,
+    },
+    ModelPrice {
+        model: "gpt-4.1-nano",
+        input: 0.1,
+        output: 0.4,
+    },
+    ModelPrice {
+        model: "o3",
+        input: 2.0,
+        output: 8.0,
+    },
+    ModelPrice {
+        model: "o4-mini",
+        input: 1.1,
+        output: 4.4,
+    },
+    ModelPrice {
+        model: "claude-opus-4",
+        input: 15.0,
+        output: 75.0,
+    },
+    ModelPrice {
+        model: "claude-sonnet-4",
+        input: 3.0,
+        output: 15.0,
+    },
+    ModelPrice {
+        model: "claude-3-7-sonnet",
+        input: 3.0,
+        output: 15.0,
+    },
+    ModelPrice {
+        model: "claude-3-5-sonnet",
+        input: 3.0,
+        output: 15.0,
+    },
+    ModelPrice {
+        model: "claude-3-5-haiku",
+        input: 0.8,
+        output: 4.0,
+    },
+];
+
+/// Price of `model`, the one with the longest matching prefix. Models served under a
+/// provider path like `anthropic/claude-sonnet-4` are looked up by their last segment.
+fn model_price(model: &str) -> Option<&'static ModelPrice> {
+    let model = model.rsplit('/').next().unwrap_or(model);
+    MODEL_PRICES
+        .iter()
+        .filter(|price| model.starts_with(price.model))
+        .max_by_key(|price| price.model.len())
+}
+
+#[derive(Serialize, Deserialize, Default, Debug, Clone, PartialEq)]
+pub struct ModelUsage {
+    /// Completions that reported usage
+    pub requests: u64,
+    pub prompt_tokens: u64,
+    pub completion_tokens: u64,
+}
+
+impl ModelUsage {
+    pub fn total_tokens(&self) -> u64 {
+        self.prompt_tokens + self.completion_tokens
+    }
+
+    /// Estimated cost in USD, `None` for models without a known price, e.g. local ones
+    pub fn estimated_cost(&self, model: &str) -> Option<f64> {
+        let price = model_price(model)?;
+        Some(
+            (self.prompt_tokens as f64 * price.input
+                + self.completion_tokens as f64 * price.output)
+                / 1_000_000.0,
+        )
+    }
+}
+
+/// Token usage of every completion in the session, by model
+#[derive(Serialize, Deserialize, Default, Debug, Clone, PartialEq)]
+pub struct SessionUsage {
+    #[serde(default)]
+    pub models: BTreeMap<String, ModelUsage>,
+}
+
+impl SessionUsage {
+    /// Usage of the current session, empty before the first completion
+    pub fn load() -> Result<Self, String> {
+        if !LocalStore::session().path(USAGE_FILE).exists() {
+            return Ok(Self::default());
+        }
+        let content = LocalStore::session().read(USAGE_FILE)?;
+        serde_json::from_str(&content).map_err(|e| format!("Failed to parse token usage: {}", e))
+    }
+
+    pub fn add(&mut self, model: &str, usage: &Usage) {
+        let model_usage = self.models.entry(model.to_string()).or_default();
+        model_usage.requests += 1;
+        model_usage.prompt_tokens += usage.prompt_tokens as u64;
+        model_usage.completion_tokens += usage.completion_tokens as u64;
+    }
+
+    pub fn total(&self) -> ModelUsage {
+        let mut total = ModelUsage::default();
+        for usage in self.models.values() {
+            total.requests += usage.requests;
+            total.prompt_tokens += usage.prompt_tokens;
+            total.completion_tokens += usage.completion_tokens;
+        }
+        total
+    }
+
+    /// Estimated cost in USD of the models with a known price
+    pub fn estimated_cost(&self) -> f64 {
+        self.models
+            .iter()
+            .filter_map(|(model, usage)| usage.estimated_cost(model))
+            .sum()
+    }
+
+    /// Table of the usage and estimated cost of each model
+    pub fn report(&self) -> String {
+        if self.models.is_empty() {
+            return "No token usage recorded in this session yet".to_string();
+        }
+        let mut report = format!(
+            "{:<32} {:>8} {:>12} {:>12} {:>10}\n",
+            "Model", "Requests", "Input", "Output", "Cost"
+        );
+        let format_cost = |cost: Option<f64>| match cost {
+            Some(cost) => format!("${:.4}", cost),
+            None => "-".to_string(),
+        };
+        for (model, usage) in &self.models {
+            let _ = writeln!(
+                report,
+                "{:<32} {:>8} {:>12} {:>12} {:>10}",
+                model,
+                usage.requests,
+                usage.prompt_tokens,
+                usage.completion_tokens,
+                format_cost(usage.estimated_cost(model))
+            );
+        }
+        let total = self.total();
+        let _ = write!(
+            report,
+            "{:<32} {:>8} {:>12} {:>12} {:>10}",
+            "Total",
+            total.requests,
+            total.prompt_tokens,
+            total.completion_tokens,
+            format_cost(Some(self.estimated_cost()))
+        );
+        if self.models.keys().any(|model| model_price(model).is_none()) {
+            report.push_str("\nCosts are estimates, models without a known price are left out");
+        }
+        report
+    }
+}
+
+/// Add the usage of a completion to the session usage
+pub fn record_usage(model: &str, usage: &Usage) -> Result<(), String> {
+    if usage.prompt_tokens == 0 && usage.completion_tokens == 0 {
+        return Ok(());
+    }
+    LocalStore::session().update(USAGE_FILE, |content| {
+        let mut session_usage: SessionUsage = match content {
+            Some(content) => serde_json::from_str(&content).unwrap_or_default(),
+            None => SessionUsage::default(),
+        };
+        session_usage.add(model, usage);
+        let content = serde_json::to_string_pretty(&session_usage)
+            .map_err(|e| format!("Failed to serialize token usage: {}", e))?;
+        Ok((content, ()))
+    })
+}
+
+/// Record the usage reported in the chunks of `stream` as they pass through
+pub fn track_stream_usage<S>(stream: S) -> impl Stream<Item = S::Item>
+where
+    S: Stream<Item = Result<ChatCompletionStreamResponse, String>>,
+{
+    stream.inspect(|chunk| {
+        if let Ok(chunk) = chunk {
+            if let Some(usage) = &chunk.usage {
+                // Failing to record usage should never fail the completion
+                let _ = record_usage(&chunk.model, usage);
+            }
+        }
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_session_usage_cost() {
+        let mut usage = SessionUsage::default();
+        let completion = Usage {
+            prompt_tokens: 1_000_000,
+            completion_tokens: 100_000,
+            total_tokens: 1_100_000,
+        };
+        usage.add("gpt-4o-mini-2024-07-18", &completion);
+        usage.add("anthropic/claude-sonnet-4-20250514", &completion);
+        usage.add("llama3.1", &completion);
+
+        assert_eq!(usage.total().requests, 3);
+        assert_eq!(usage.total().total_tokens(), 3_300_000);
+        let cost = usage.models["gpt-4o-mini-2024-07-18"]
+            .estimated_cost("gpt-4o-mini-2024-07-18")
+            .unwrap_or_default();
+        assert!((cost - 0.21).abs() < 1e-9);
+        assert_eq!(usage.models["llama3.1"].estimated_cost("llama3.1"), None);
+        assert!((usage.estimated_cost() - (0.21 + 4.5)).abs() < 1e-9);
+        assert!(usage.report().contains("without a known price"));
+    }
+}
@@ -0,0 +1,198 @@
+use anyhow::{Result, anyhow};
+use rmcp::{
+    RoleClient, ServiceExt,
+    service::RunningService,
+    transport::{
+        StreamableHttpClientTransport, TokioChildProcess,
+        streamable_http_client::StreamableHttpClientTransportConfig,
+    },
+};
+use serde::Deserialize;
+use std::collections::{BTreeMap, HashMap};
+use std::path::PathBuf;
+
+use crate::local::LocalClientHandler;
+
+/// Separates the server name from the tool name in the tools of external servers
+pub const TOOL_NAMESPACE_SEPARATOR: &str = "__";
+
+/// External MCP server, either a command speaking MCP over stdio or a streamable HTTP URL
+///
+/// ```json
+/// {
+///   "mcpServers": {
+///     "github": {
+///       "url": "https://api.githubcopilot.com/mcp/",
+///       "headers": { "Authorization": "Bearer ${GITHUB_TOKEN}" }
+///     },
+///     "postgres": {
+///       "command": "npx",
+///       "args": ["-y", "@modelcontextprotocol/server-postgres", "postgresql://localhost/app"],
+///       "env": { "PGPASSWORD": "${PGPASSWORD}" }
+///     }
+///   }
+/// }
+/// ```
+///
+/// `${NAME}` in `env` and `headers` values is replaced with the environment variable, so
+/// tokens don't have to be written into the file.
+#[derive(Deserialize, Debug, Clone, PartialEq)]
+pub struct ExternalServerConfig {
+    pub command: Option<String>,
+    #[serde(default)]
+    pub args: Vec<String>,
+    #[serde(default)]
+    pub env: HashMap<String, String>,
+    pub url: Option<String>,
+    #[serde(default)]
+    pub headers: HashMap<String, String>,
+}
+
+impl ExternalServerConfig {
+    /// What the server runs or connects to, identifying it for trust decisions
+    pub fn target(&self) -> String {
+        match (&self.command, &self.url) {
+            (Some(command), _) => std::iter::once(command.as_str())
+                .chain(self.args.iter().map(String::as_str))
+                .collect::<Vec<_>>()
+                .join(" "),
+            (None, Some(url)) => url.clone(),
+            (None, None) => String::new(),
+        }
+    }
+}
+
+#[derive(Deserialize, Debug, Default)]
+pub struct ExternalServers {
+    #[serde(default, rename = "mcpServers")]
+    pub servers: BTreeMap<String, ExternalServerConfig>,
+}
+
+pub fn external_servers_path() -> PathBuf {
+    PathBuf::from(format!(
+        "{}/.stakpak/mcp.json",
+        std::env::var("HOME").unwrap_or_default()
+    ))
+}
+
+impl ExternalServers {
+    /// Servers listed in `~/.stakpak/mcp.json`, none when the file does not exist
+    pub fn load() -> Result<Self, String> {
+        let path = external_servers_path();
+        match std::fs::read_to_string(&path) {
+            Ok(content) => Self::parse(&content)
+                .map_err(|e| format!("Failed to load {}: {}", path.display(), e)),
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(Self::default()),
+            Err(e) => Err(format!("Failed to read {}: {}", path.display(), e)),
+        }
+    }
+
+    pub fn parse(content: &str) -> Result<Self, String> {
+        let servers: Self = serde_json::from_str(content).map_err(|e| e.to_string())?;
+        for (name, server) in &servers.servers {
+            // Server names prefix tool names, which models only accept in this alphabet
+            if name.is_empty()
+                || name.contains(TOOL_NAMESPACE_SEPARATOR)
+                || !name
+                    .chars()
+                    .all(|c| c.is_ascii_alphanumeric() || c == '_' || c == '-')
+            {
+                return Err(format!(
+                    "invalid server name '{}', use letters, digits, '-' and single '_'",
+                    name
+                ));
+            }
+            if server.command.is_some() == server.url.is_some() {
+                return Err(format!(
+                    "server '{}' needs exactly one of \"command\" or \"url\"",
+                    name
+                ));
+            }
+        }
+        Ok(servers)
+    }
+}
+
+/// Replace `${NAME}` with the value of the environment variable `NAME`
+fn expand_env(value: &str) -> String {
+    let mut expanded = String::new();
+    let mut rest = value;
+    while let Some(start) = rest.find("${") {
+        let Some(end) = rest[start..].find('}') else {
+            break;
+        };
+        expanded.push_str(&rest[..start]);
+        expanded.push_str(&std::env::var(&rest[start + 2..start + end]).unwrap_or_default());
+        rest = &rest[start + end + 1..];
+    }
+    expanded.push_str(rest);
+    expanded
+}
+
+/// Name the model sees for `tool` of the external server `server`
+pub fn namespaced_tool_name(server: &str, tool: &str) -> String {
+    format!("{}{}{}", server, TOOL_NAMESPACE_SEPARATOR, tool)
+}
+
+pub async fn external_client(
+    config: &ExternalServerConfig,
+) -> Result<RunningService<RoleClient, LocalClientHandler>> {
+    // Progress notifications of external servers are not in the format the TUI streams
+    let client_handler = LocalClientHandler { progress_tx: None };
+    match (&config.command, &config.url) {
+        (Some(command), _) => {
+            let mut process = tokio::process::Command::new(command);
+            process.args(&config.args);
+            for (name, value) in &config.env {
+                process.env(name, expand_env(value));
+            }
+            let transport = TokioChildProcess::new(process)?;
+            Ok(client_handler.serve(transport).await?)
+        }
+        (None, Some(url)) => {
+            let mut headers = reqwest::header::HeaderMap::new();
+            for (name, value) in &config.headers {
+                headers.insert(
+                    reqwest::header::HeaderName::from_bytes(name.as_bytes())?,
+                    reqwest::header::HeaderValue::from_str(&expand_env(value))?,
+                );
+            }
+            let http_client = reqwest::Client::builder()
+                .default_headers(headers)
+                .build()?;
+            let transport = StreamableHttpClientTransport::with_client(
+                http_client,
+                StreamableHttpClientTransportConfig::with_uri(url.as_str()),
+            );
+            Ok(client_handler.serve(transport).await?)
+        }
+        (None, None) => Err(anyhow!("server has neither a command nor a url")),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_external_servers() {
+        let servers = ExternalServers::parse(
+            r#"{"mcpServers": {
+                "postgres": {"command": "npx", "args": ["-y", "server-postgres"]},
+                "docs": {"url": "https://mcp.example.com/mcp", "headers": {"X-Team": "infra"}}
+            }}"#,
+        )
+        .unwrap_or_default();
+        assert_eq!(servers.servers.len(), 2);
+        assert_eq!(
+            servers.servers["postgres"].target(),
+            "npx -y server-postgres"
+        );
+
+        assert!(ExternalServers::parse(r#"{"mcpServers": {"a__b": {"command": "x"}}}"#).is_err());
+        assert!(
+            ExternalServers::parse(r#"{"mcpServers": {"both": {"command": "x", "url": "y"}}}"#)
+                .is_err()
+        );
+    }
+}
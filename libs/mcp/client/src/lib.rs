@@ -10,9 +10,18 @@ use rmcp::{
 use stakpak_shared::models::integrations::openai::ToolCallResultProgress;
 use tokio::sync::mpsc::Sender;
 
+mod external;
+pub use external::{
+    ExternalServerConfig, ExternalServers, TOOL_NAMESPACE_SEPARATOR, external_servers_path,
+    namespaced_tool_name,
+};
 mod local;
+use crate::external::external_client;
 use crate::local::local_client;
 
+/// Name of the client of the built-in server, whose tools are not namespaced
+pub const LOCAL_CLIENT: &str = "local";
+
 pub struct ClientManager {
     clients: HashMap<String, RunningService<RoleClient, LocalClientHandler>>,
 }
@@ -24,10 +33,41 @@ impl ClientManager {
     ) -> Result<Self> {
         let client1 = local_client(local_server_host, progress_tx).await?;
         Ok(Self {
-            clients: HashMap::from([("local".to_string(), client1)]),
+            clients: HashMap::from([(LOCAL_CLIENT.to_string(), client1)]),
         })
     }
 
+    /// Connect to the external servers, named after their key. Servers that fail to start
+    /// or connect are skipped, their errors are returned so they can be reported.
+    pub async fn connect_external(&mut self, servers: &ExternalServers) -> Vec<String> {
+        let connected =
+            futures::future::join_all(servers.servers.iter().map(|(name, config)| async move {
+                (name.clone(), external_client(config).await)
+            }))
+            .await;
+        let mut errors = Vec::new();
+        for (name, client) in connected {
+            match client {
+                Ok(client) => {
+                    self.clients.insert(name, client);
+                }
+                Err(e) => errors.push(format!("Failed to connect to MCP server {}: {}", name, e)),
+            }
+        }
+        errors
+    }
+
+    /// Name of a tool on its server, external tools are exposed as `<server>__<tool>`
+    pub fn server_tool_name<'a>(client_name: &str, tool_name: &'a str) -> &'a str {
+        if client_name == LOCAL_CLIENT {
+            return tool_name;
+        }
+        tool_name
+            .strip_prefix(client_name)
+            .and_then(|name| name.strip_prefix(TOOL_NAMESPACE_SEPARATOR))
+            .unwrap_or(tool_name)
+    }
+
     pub async fn get_client(
         &self,
         client_name: &str,
@@ -53,7 +93,17 @@ impl ClientManager {
             .await?;
         let tools = tools
             .into_iter()
-            .map(|(name, tools)| (name, tools.tools))
+            .map(|(name, tools)| {
+                let mut tools = tools.tools;
+                // Prefix the tools of external servers so they never shadow built-in tools
+                // or each other
+                if name != LOCAL_CLIENT {
+                    for tool in &mut tools {
+                        tool.name = namespaced_tool_name(&name, &tool.name).into();
+                    }
+                }
+                (name, tools)
+            })
             .collect();
         Ok(tools)
     }
@@ -10,7 +10,7 @@ use tokio::sync::mpsc::Sender;
 
 #[derive(Clone)]
 pub struct LocalClientHandler {
-    progress_tx: Option<Sender<ToolCallResultProgress>>,
+    pub(crate) progress_tx: Option<Sender<ToolCallResultProgress>>,
 }
 
 impl ClientHandler for LocalClientHandler {
@@ -0,0 +1,322 @@
+use rmcp::{Error as McpError, RoleServer, ServerHandler, model::*, service::RequestContext};
+use serde::Deserialize;
+use stakpak_shared::policy::{RiskLevel, classify_tool_call, tool_call_paths};
+use std::path::Path;
+
+use crate::ToolMode;
+
+/// What a token is allowed to do with the tools of its tool mode
+#[derive(Deserialize, Clone, Copy, Debug, PartialEq, Eq, Hash)]
+#[serde(rename_all = "lowercase")]
+pub enum AccessRole {
+    /// Only read-only tools are listed and callable
+    Viewer,
+    /// Every tool of the tool mode
+    Operator,
+}
+
+/// A bearer token accepted by the server
+#[derive(Deserialize, Clone, Debug)]
+pub struct TokenAccess {
+    /// Who the token was handed out to, used in logs
+    pub name: String,
+    pub token: String,
+    pub role: AccessRole,
+    /// Tool mode for this token, defaults to the server tool mode
+    pub tool_mode: Option<String>,
+}
+
+impl TokenAccess {
+    /// A single shared secret granting every tool of the server tool mode
+    pub fn shared(token: String) -> Self {
+        Self {
+            name: "shared".to_string(),
+            token: token.trim().to_string(),
+            role: AccessRole::Operator,
+            tool_mode: None,
+        }
+    }
+}
+
+/// Server-side access configuration mapping bearer tokens to tool modes and roles
+///
+/// ```toml
+/// [[tokens]]
+/// name = "oncall"
+/// token = "..."
+/// role = "viewer"
+/// tool_mode = "local"
+/// ```
+#[derive(Deserialize, Clone, Debug, Default)]
+pub struct AccessConfig {
+    #[serde(default)]
+    pub tokens: Vec<TokenAccess>,
+}
+
+/// Tool mode and role granted to an authenticated request
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub struct AccessScope {
+    pub tool_mode: ToolMode,
+    pub role: AccessRole,
+}
+
+impl AccessConfig {
+    pub fn load(path: &Path) -> Result<Self, String> {
+        let content = std::fs::read_to_string(path)
+            .map_err(|e| format!("Failed to read access config {}: {}", path.display(), e))?;
+        let config: AccessConfig = toml::from_str(&content)
+            .map_err(|e| format!("Failed to parse access config {}: {}", path.display(), e))?;
+        config.validate()?;
+        Ok(config)
+    }
+
+    fn validate(&self) -> Result<(), String> {
+        if self.tokens.is_empty() {
+            return Err("Access config does not define any tokens".to_string());
+        }
+        for token in &self.tokens {
+            if token.token.trim().is_empty() {
+                return Err(format!("Token '{}' is empty", token.name));
+            }
+            if let Some(tool_mode) = &token.tool_mode {
+                tool_mode.parse::<ToolMode>()?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Scopes granted by the configured tokens, without duplicates
+    pub fn scopes(&self, default_tool_mode: &ToolMode) -> Vec<AccessScope> {
+        let mut scopes = Vec::new();
+        for token in &self.tokens {
+            let scope = self.scope_of(token, default_tool_mode);
+            if !scopes.contains(&scope) {
+                scopes.push(scope);
+            }
+        }
+        scopes
+    }
+
+    /// Resolve an `Authorization` header value to the token it carries
+    pub fn authorize(&self, authorization: Option<&str>) -> Option<&TokenAccess> {
+        let token = authorization?.strip_prefix("Bearer ")?.trim();
+        self.tokens
+            .iter()
+            .find(|access| constant_time_eq(access.token.trim().as_bytes(), token.as_bytes()))
+    }
+
+    pub fn scope_of(&self, token: &TokenAccess, default_tool_mode: &ToolMode) -> AccessScope {
+        AccessScope {
+            tool_mode: token
+                .tool_mode
+                .as_deref()
+                .and_then(|tool_mode| tool_mode.parse().ok())
+                .unwrap_or_else(|| default_tool_mode.clone()),
+            role: token.role,
+        }
+    }
+}
+
+/// Compare tokens without leaking how many leading bytes matched through timing
+fn constant_time_eq(a: &[u8], b: &[u8]) -> bool {
+    if a.len() != b.len() {
+        return false;
+    }
+    a.iter().zip(b).fold(0u8, |acc, (x, y)| acc | (x ^ y)) == 0
+}
+
+/// Read-only for the approval flow, but not for a viewer: reading a secret discloses it and
+/// a rollback restores files
+const VIEWER_DENIED_TOOLS: &[&str] = &["k8s_read_secret", "rollback_changes"];
+
+/// Whether a viewer may see `name` at all, before knowing the arguments of a call
+fn is_read_only_tool(name: &str) -> bool {
+    !VIEWER_DENIED_TOOLS.contains(&name)
+        && classify_tool_call(name, None, &[]).level == RiskLevel::ReadOnly
+}
+
+/// Whether a viewer may make this call, classified with its actual arguments
+fn is_read_only_call(name: &str, arguments: Option<&JsonObject>) -> bool {
+    let argument = |key: &str| {
+        arguments
+            .and_then(|arguments| arguments.get(key))
+            .and_then(|value| value.as_str())
+    };
+    let command = argument("command");
+    // Waiting on a command runs it, which viewers never may
+    if name == "wait_for" && command.is_some() {
+        return false;
+    }
+    is_read_only_tool(name)
+        && classify_tool_call(name, command, &tool_call_paths(arguments)).level
+            == RiskLevel::ReadOnly
+}
+
+/// Tool handler exposing only the read-only tools of the wrapped handler
+#[derive(Clone)]
+pub struct ReadOnlyTools<S> {
+    inner: S,
+}
+
+impl<S> ReadOnlyTools<S> {
+    pub fn new(inner: S) -> Self {
+        Self { inner }
+    }
+}
+
+impl<S: ServerHandler> ServerHandler for ReadOnlyTools<S> {
+    fn get_info(&self) -> ServerInfo {
+        self.inner.get_info()
+    }
+
+    async fn initialize(
+        &self,
+        request: InitializeRequestParam,
+        context: RequestContext<RoleServer>,
+    ) -> Result<InitializeResult, McpError> {
+        self.inner.initialize(request, context).await
+    }
+
+    async fn list_tools(
+        &self,
+        request: PaginatedRequestParam,
+        context: RequestContext<RoleServer>,
+    ) -> Result<ListToolsResult, McpError> {
+        let mut result = self.inner.list_tools(request, context).await?;
+        result.tools.retain(|tool| is_read_only_tool(&tool.name));
+        Ok(result)
+    }
+
+    async fn call_tool(
+        &self,
+        request: CallToolRequestParam,
+        context: RequestContext<RoleServer>,
+    ) -> Result<CallToolResult, McpError> {
+        if !is_read_only_call(&request.name, request.arguments.as_ref()) {
+            return Ok(CallToolResult::error(vec![
+                Content::text("PERMISSION_DENIED"),
+                Content::text(format!(
+                    "The {} tool is not available to viewer tokens",
+                    request.name
+                )),
+            ]));
+        }
+        self.inner.call_tool(request, context).await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn config() -> AccessConfig {
+        toml::from_str(
+            r#"
+            [[tokens]]
+            name = "viewer"
+            token = "view-token"
+            role = "viewer"
+            tool_mode = "local"
+
+            [[tokens]]
+            name = "operator"
+            token = "operate-token"
+            role = "operator"
+            "#,
+        )
+        .unwrap()
+    }
+
+    #[test]
+    fn test_authorize_bearer_token() {
+        let config = config();
+        assert_eq!(
+            config
+                .authorize(Some("Bearer view-token"))
+                .map(|t| t.name.as_str()),
+            Some("viewer")
+        );
+        assert!(config.authorize(Some("Bearer wrong")).is_none());
+        assert!(config.authorize(Some("view-token")).is_none());
+        assert!(config.authorize(None).is_none());
+    }
+
+    #[test]
+    fn test_authorize_shared_token() {
+        let config = AccessConfig {
+            tokens: vec![TokenAccess::shared("shared-token\n".to_string())],
+        };
+        // Missing header or scheme
+        assert!(config.authorize(None).is_none());
+        assert!(config.authorize(Some("")).is_none());
+        assert!(config.authorize(Some("shared-token")).is_none());
+        // Wrong token
+        assert!(config.authorize(Some("Bearer ")).is_none());
+        assert!(config.authorize(Some("Bearer shared")).is_none());
+        assert!(config.authorize(Some("Bearer shared-token-2")).is_none());
+        // Valid token, surrounding whitespace on either side is ignored
+        let access = config.authorize(Some("Bearer shared-token")).unwrap();
+        assert_eq!(access.name, "shared");
+        assert_eq!(access.role, AccessRole::Operator);
+        assert!(config.authorize(Some("Bearer  shared-token ")).is_some());
+    }
+
+    #[test]
+    fn test_authorize_trims_configured_tokens() {
+        let config: AccessConfig = toml::from_str(
+            r#"
+            [[tokens]]
+            name = "padded"
+            token = " padded-token\n"
+            role = "viewer"
+            "#,
+        )
+        .unwrap();
+        assert_eq!(
+            config
+                .authorize(Some("Bearer padded-token"))
+                .map(|t| t.name.as_str()),
+            Some("padded")
+        );
+    }
+
+    #[test]
+    fn test_scopes_default_to_server_tool_mode() {
+        let config = config();
+        assert_eq!(
+            config.scopes(&ToolMode::Combined),
+            vec![
+                AccessScope {
+                    tool_mode: ToolMode::LocalOnly,
+                    role: AccessRole::Viewer,
+                },
+                AccessScope {
+                    tool_mode: ToolMode::Combined,
+                    role: AccessRole::Operator,
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn test_viewer_tools_are_read_only() {
+        assert!(is_read_only_tool("view"));
+        assert!(!is_read_only_tool("run_command"));
+        assert!(!is_read_only_tool("str_replace"));
+        assert!(!is_read_only_tool("k8s_read_secret"));
+        assert!(!is_read_only_tool("rollback_changes"));
+    }
+
+    #[test]
+    fn test_viewer_calls_are_classified_with_their_arguments() {
+        let arguments = |value: serde_json::Value| value.as_object().cloned();
+        let url = arguments(serde_json::json!({"url": "http://localhost:8080/health"}));
+        assert!(is_read_only_call("wait_for", url.as_ref()));
+        let command = arguments(serde_json::json!({"command": "curl -sf localhost:8080"}));
+        assert!(!is_read_only_call("wait_for", command.as_ref()));
+        let command = arguments(serde_json::json!({"command": "ls"}));
+        assert!(!is_read_only_call("run_command", command.as_ref()));
+        let path = arguments(serde_json::json!({"path": "README.md"}));
+        assert!(is_read_only_call("view", path.as_ref()));
+    }
+}
@@ -0,0 +1,259 @@
+use serde::Serialize;
+use serde_json::Value;
+use std::time::Duration;
+use tokio::process::Command;
+
+/// Identity calls hit the network, don't let a hanging CLI block the agent
+const CHECK_TIMEOUT: Duration = Duration::from_secs(20);
+/// Exit code of `sh -c` when the command does not exist
+const COMMAND_NOT_FOUND: i32 = 127;
+
+pub const CLOUD_PROVIDERS: [&str; 3] = ["aws", "gcp", "azure"];
+
+#[derive(Serialize, Debug, Clone, Copy, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum AccessStatus {
+    Authenticated,
+    NotAuthenticated,
+    CliNotInstalled,
+}
+
+/// Who the agent acts as on a cloud provider, normalized across CLIs
+#[derive(Serialize, Debug, Clone, PartialEq)]
+pub struct CloudIdentity {
+    pub provider: &'static str,
+    pub status: AccessStatus,
+    /// AWS account ID, GCP project or Azure subscription ID
+    pub account: Option<String>,
+    /// AWS ARN, GCP account email or Azure user
+    pub principal: Option<String>,
+    pub region: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+}
+
+impl CloudIdentity {
+    fn new(provider: &'static str, status: AccessStatus) -> Self {
+        Self {
+            provider,
+            status,
+            account: None,
+            principal: None,
+            region: None,
+            error: None,
+        }
+    }
+
+    fn failed(provider: &'static str, failure: CommandFailure) -> Self {
+        match failure {
+            CommandFailure::NotInstalled => Self::new(provider, AccessStatus::CliNotInstalled),
+            CommandFailure::Failed(error) => Self {
+                error: Some(error),
+                ..Self::new(provider, AccessStatus::NotAuthenticated)
+            },
+        }
+    }
+}
+
+enum CommandFailure {
+    NotInstalled,
+    Failed(String),
+}
+
+/// Builds the `sh -c` command of a check, failing when the command may not run
+pub type ShellCommand<'a> = &'a (dyn Fn(&str) -> Result<Command, String> + Sync);
+
+async fn run(shell: ShellCommand<'_>, command_line: &str) -> Result<String, CommandFailure> {
+    let mut command = shell(command_line).map_err(CommandFailure::Failed)?;
+    command.kill_on_drop(true);
+
+    let output = tokio::time::timeout(CHECK_TIMEOUT, command.output())
+        .await
+        .map_err(|_| {
+            CommandFailure::Failed(format!(
+                "'{}' timed out after {}s",
+                command_line,
+                CHECK_TIMEOUT.as_secs()
+            ))
+        })?
+        .map_err(|e| CommandFailure::Failed(e.to_string()))?;
+
+    match output.status.code() {
+        Some(0) => Ok(String::from_utf8_lossy(&output.stdout).trim().to_string()),
+        Some(COMMAND_NOT_FOUND) => Err(CommandFailure::NotInstalled),
+        _ => Err(CommandFailure::Failed(
+            String::from_utf8_lossy(&output.stderr).trim().to_string(),
+        )),
+    }
+}
+
+/// Optional setting, empty output and failures both mean it is not set
+async fn run_optional(shell: ShellCommand<'_>, command_line: &str) -> Option<String> {
+    run(shell, command_line)
+        .await
+        .ok()
+        .filter(|value| !value.is_empty() && value != "(unset)")
+}
+
+fn env_var(names: &[&str]) -> Option<String> {
+    names
+        .iter()
+        .find_map(|name| std::env::var(name).ok().filter(|value| !value.is_empty()))
+}
+
+fn json_str(value: &Value, pointer: &str) -> Option<String> {
+    value
+        .pointer(pointer)
+        .and_then(|v| v.as_str())
+        .map(|v| v.to_string())
+}
+
+/// Parse `aws sts get-caller-identity --output json`
+fn parse_aws_identity(output: &str) -> Result<CloudIdentity, String> {
+    let value: Value = serde_json::from_str(output).map_err(|e| e.to_string())?;
+    Ok(CloudIdentity {
+        account: json_str(&value, "/Account"),
+        principal: json_str(&value, "/Arn"),
+        ..CloudIdentity::new("aws", AccessStatus::Authenticated)
+    })
+}
+
+/// Parse `gcloud auth list --format=json`, only the active account is relevant
+fn parse_gcloud_accounts(output: &str) -> Result<CloudIdentity, String> {
+    let value: Value = serde_json::from_str(output).map_err(|e| e.to_string())?;
+    let active = value.as_array().and_then(|accounts| {
+        accounts
+            .iter()
+            .find(|account| account.get("status").and_then(|s| s.as_str()) == Some("ACTIVE"))
+    });
+    Ok(match active {
+        Some(account) => CloudIdentity {
+            principal: json_str(account, "/account"),
+            ..CloudIdentity::new("gcp", AccessStatus::Authenticated)
+        },
+        None => CloudIdentity {
+            error: Some("No active gcloud account".to_string()),
+            ..CloudIdentity::new("gcp", AccessStatus::NotAuthenticated)
+        },
+    })
+}
+
+/// Parse `az account show --output json`
+fn parse_azure_account(output: &str) -> Result<CloudIdentity, String> {
+    let value: Value = serde_json::from_str(output).map_err(|e| e.to_string())?;
+    Ok(CloudIdentity {
+        account: json_str(&value, "/id"),
+        principal: json_str(&value, "/user/name"),
+        ..CloudIdentity::new("azure", AccessStatus::Authenticated)
+    })
+}
+
+async fn check_aws(shell: ShellCommand<'_>) -> CloudIdentity {
+    let output = match run(shell, "aws sts get-caller-identity --output json").await {
+        Ok(output) => output,
+        Err(failure) => return CloudIdentity::failed("aws", failure),
+    };
+    let mut identity = parse_aws_identity(&output).unwrap_or_else(|e| CloudIdentity {
+        error: Some(e),
+        ..CloudIdentity::new("aws", AccessStatus::NotAuthenticated)
+    });
+    identity.region = match env_var(&["AWS_REGION", "AWS_DEFAULT_REGION"]) {
+        Some(region) => Some(region),
+        None => run_optional(shell, "aws configure get region").await,
+    };
+    identity
+}
+
+async fn check_gcp(shell: ShellCommand<'_>) -> CloudIdentity {
+    let output = match run(shell, "gcloud auth list --format=json").await {
+        Ok(output) => output,
+        Err(failure) => return CloudIdentity::failed("gcp", failure),
+    };
+    let mut identity = parse_gcloud_accounts(&output).unwrap_or_else(|e| CloudIdentity {
+        error: Some(e),
+        ..CloudIdentity::new("gcp", AccessStatus::NotAuthenticated)
+    });
+    if identity.status == AccessStatus::Authenticated {
+        identity.account = run_optional(shell, "gcloud config get-value project").await;
+        identity.region = run_optional(shell, "gcloud config get-value compute/region").await;
+    }
+    identity
+}
+
+async fn check_azure(shell: ShellCommand<'_>) -> CloudIdentity {
+    let output = match run(shell, "az account show --output json").await {
+        Ok(output) => output,
+        Err(failure) => return CloudIdentity::failed("azure", failure),
+    };
+    let mut identity = parse_azure_account(&output).unwrap_or_else(|e| CloudIdentity {
+        error: Some(e),
+        ..CloudIdentity::new("azure", AccessStatus::NotAuthenticated)
+    });
+    identity.region = run_optional(
+        shell,
+        "az config get defaults.location --query value -o tsv",
+    )
+    .await;
+    identity
+}
+
+/// Check the identity of every requested provider concurrently
+pub async fn check_cloud_access(providers: &[&str], shell: ShellCommand<'_>) -> Vec<CloudIdentity> {
+    let wants = |provider: &str| providers.contains(&provider);
+    let (aws, gcp, azure) = tokio::join!(
+        async {
+            if wants("aws") {
+                Some(check_aws(shell).await)
+            } else {
+                None
+            }
+        },
+        async {
+            if wants("gcp") {
+                Some(check_gcp(shell).await)
+            } else {
+                None
+            }
+        },
+        async {
+            if wants("azure") {
+                Some(check_azure(shell).await)
+            } else {
+                None
+            }
+        },
+    );
+    [aws, gcp, azure].into_iter().flatten().collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_identity_outputs() {
+        let aws = parse_aws_identity(
+            r#"{"UserId": "AIDAEXAMPLE", "Account": "123456789012", "Arn": "arn:aws:iam::123456789012:user/deployer"}"#,
+        )
+        .unwrap();
+        assert_eq!(aws.account.as_deref(), Some("123456789012"));
+        assert_eq!(
+            aws.principal.as_deref(),
+            Some("arn:aws:iam::123456789012:user/deployer")
+        );
+
+        let gcp = parse_gcloud_accounts(
+            r#"[{"account": "old@example.com", "status": ""}, {"account": "ci@example.com", "status": "ACTIVE"}]"#,
+        )
+        .unwrap();
+        assert_eq!(gcp.status, AccessStatus::Authenticated);
+        assert_eq!(gcp.principal.as_deref(), Some("ci@example.com"));
+
+        let azure = parse_azure_account(
+            r#"{"id": "0000-1111", "name": "Production", "user": {"name": "ops@example.com", "type": "user"}}"#,
+        )
+        .unwrap();
+        assert_eq!(azure.account.as_deref(), Some("0000-1111"));
+        assert_eq!(azure.principal.as_deref(), Some("ops@example.com"));
+    }
+}
@@ -3,8 +3,12 @@ use rmcp::{
 };
 use stakpak_api::ClientConfig;
 
+use crate::command_policy::CommandPolicySource;
+use crate::devcontainer::DevContainer;
 use crate::local_tools::LocalTools;
 use crate::remote_tools::{Provisioner, RemoteTools};
+use crate::sandbox::SandboxProfile;
+use crate::test_generation::TestFramework;
 use crate::tool_descriptions::*;
 
 /// Combined tools that include both local and remote functionality
@@ -16,13 +20,30 @@ pub struct CombinedTools {
 
 #[tool(tool_box)]
 impl CombinedTools {
-    pub fn new(api_config: ClientConfig, redact_secrets: bool) -> Self {
+    pub fn new(
+        api_config: ClientConfig,
+        redact_secrets: bool,
+        sandbox: Option<SandboxProfile>,
+    ) -> Self {
         Self {
-            local_tools: LocalTools::new(redact_secrets),
+            local_tools: LocalTools::new(redact_secrets, sandbox)
+                .with_output_uploads(api_config.clone()),
             remote_tools: RemoteTools::new(api_config, redact_secrets),
         }
     }
 
+    /// Run local commands inside `dev_container`, see [`LocalTools::with_dev_container`]
+    pub fn with_dev_container(mut self, dev_container: Option<DevContainer>) -> Self {
+        self.local_tools = self.local_tools.with_dev_container(dev_container);
+        self
+    }
+
+    /// Check commands against `command_policy`, see [`LocalTools::with_command_policy`]
+    pub fn with_command_policy(mut self, command_policy: Option<CommandPolicySource>) -> Self {
+        self.local_tools = self.local_tools.with_command_policy(command_policy);
+        self
+    }
+
     // Local tools delegation
     #[tool(description = RUN_COMMAND_DESCRIPTION)]
     pub async fn run_command(
@@ -34,8 +55,16 @@ impl CombinedTools {
         #[tool(param)]
         #[schemars(description = WORK_DIR_PARAM_DESCRIPTION)]
         work_dir: Option<String>,
+        #[tool(param)]
+        #[schemars(description = CACHE_PARAM_DESCRIPTION)]
+        cache: Option<bool>,
+        #[tool(param)]
+        #[schemars(description = COMMAND_TIMEOUT_PARAM_DESCRIPTION)]
+        timeout: Option<u64>,
     ) -> Result<CallToolResult, McpError> {
-        self.local_tools.run_command(peer, command, work_dir).await
+        self.local_tools
+            .run_command(peer, command, work_dir, cache, timeout)
+            .await
     }
 
     #[tool(description = VIEW_DESCRIPTION)]
@@ -96,10 +125,259 @@ impl CombinedTools {
         self.local_tools.insert(path, insert_line, new_str)
     }
 
+    #[tool(description = DELETE_FILE_DESCRIPTION)]
+    pub fn delete_file(
+        &self,
+        #[tool(param)]
+        #[schemars(description = DELETE_PATH_PARAM_DESCRIPTION)]
+        path: String,
+        #[tool(param)]
+        #[schemars(description = RECURSIVE_PARAM_DESCRIPTION)]
+        recursive: Option<bool>,
+    ) -> Result<CallToolResult, McpError> {
+        self.local_tools.delete_file(path, recursive)
+    }
+
+    #[tool(description = MOVE_FILE_DESCRIPTION)]
+    pub fn move_file(
+        &self,
+        #[tool(param)]
+        #[schemars(description = SOURCE_PATH_PARAM_DESCRIPTION)]
+        source: String,
+        #[tool(param)]
+        #[schemars(description = DESTINATION_PATH_PARAM_DESCRIPTION)]
+        destination: String,
+        #[tool(param)]
+        #[schemars(description = OVERWRITE_PARAM_DESCRIPTION)]
+        overwrite: Option<bool>,
+    ) -> Result<CallToolResult, McpError> {
+        self.local_tools.move_file(source, destination, overwrite)
+    }
+
+    #[tool(description = CHECK_CLOUD_ACCESS_DESCRIPTION)]
+    pub async fn check_cloud_access(
+        &self,
+        #[tool(param)]
+        #[schemars(description = CLOUD_PROVIDERS_PARAM_DESCRIPTION)]
+        providers: Option<Vec<String>>,
+    ) -> Result<CallToolResult, McpError> {
+        self.local_tools.check_cloud_access(providers).await
+    }
+
+    #[tool(description = FORMAT_AND_VALIDATE_DESCRIPTION)]
+    pub fn format_and_validate(
+        &self,
+        #[tool(param)]
+        #[schemars(description = FORMAT_PARAM_DESCRIPTION)]
+        format: Option<String>,
+        #[tool(param)]
+        #[schemars(description = FORMAT_CONTENT_PARAM_DESCRIPTION)]
+        content: Option<String>,
+        #[tool(param)]
+        #[schemars(description = FORMAT_PATH_PARAM_DESCRIPTION)]
+        path: Option<String>,
+    ) -> Result<CallToolResult, McpError> {
+        self.local_tools.format_and_validate(format, content, path)
+    }
+
+    #[tool(description = KUBECTL_PORT_FORWARD_DESCRIPTION)]
+    pub async fn kubectl_port_forward(
+        &self,
+        #[tool(param)]
+        #[schemars(description = PORT_FORWARD_RESOURCE_PARAM_DESCRIPTION)]
+        resource: String,
+        #[tool(param)]
+        #[schemars(description = PORT_FORWARD_REMOTE_PORT_PARAM_DESCRIPTION)]
+        remote_port: u16,
+        #[tool(param)]
+        #[schemars(description = PORT_FORWARD_LOCAL_PORT_PARAM_DESCRIPTION)]
+        local_port: Option<u16>,
+        #[tool(param)]
+        #[schemars(description = NAMESPACE_PARAM_DESCRIPTION)]
+        namespace: Option<String>,
+        #[tool(param)]
+        #[schemars(description = KUBE_CONTEXT_PARAM_DESCRIPTION)]
+        context: Option<String>,
+    ) -> Result<CallToolResult, McpError> {
+        self.local_tools
+            .kubectl_port_forward(resource, remote_port, local_port, namespace, context)
+            .await
+    }
+
+    #[tool(description = K8S_READ_SECRET_DESCRIPTION)]
+    pub async fn k8s_read_secret(
+        &self,
+        #[tool(param)]
+        #[schemars(description = K8S_SECRET_NAME_PARAM_DESCRIPTION)]
+        name: String,
+        #[tool(param)]
+        #[schemars(description = NAMESPACE_PARAM_DESCRIPTION)]
+        namespace: Option<String>,
+        #[tool(param)]
+        #[schemars(description = KUBE_CONTEXT_PARAM_DESCRIPTION)]
+        context: Option<String>,
+        #[tool(param)]
+        #[schemars(description = K8S_SECRET_KEYS_PARAM_DESCRIPTION)]
+        keys: Option<Vec<String>>,
+    ) -> Result<CallToolResult, McpError> {
+        self.local_tools
+            .k8s_read_secret(name, namespace, context, keys)
+            .await
+    }
+
+    #[tool(description = WAIT_FOR_DESCRIPTION)]
+    #[allow(clippy::too_many_arguments)]
+    pub async fn wait_for(
+        &self,
+        peer: rmcp::Peer<RoleServer>,
+        #[tool(param)]
+        #[schemars(description = WAIT_COMMAND_PARAM_DESCRIPTION)]
+        command: Option<String>,
+        #[tool(param)]
+        #[schemars(description = WAIT_URL_PARAM_DESCRIPTION)]
+        url: Option<String>,
+        #[tool(param)]
+        #[schemars(description = WAIT_POD_PARAM_DESCRIPTION)]
+        pod: Option<String>,
+        #[tool(param)]
+        #[schemars(description = NAMESPACE_PARAM_DESCRIPTION)]
+        namespace: Option<String>,
+        #[tool(param)]
+        #[schemars(description = KUBE_CONTEXT_PARAM_DESCRIPTION)]
+        context: Option<String>,
+        #[tool(param)]
+        #[schemars(description = WAIT_TIMEOUT_PARAM_DESCRIPTION)]
+        timeout_seconds: Option<u64>,
+        #[tool(param)]
+        #[schemars(description = WAIT_INTERVAL_PARAM_DESCRIPTION)]
+        interval_seconds: Option<u64>,
+    ) -> Result<CallToolResult, McpError> {
+        self.local_tools
+            .wait_for(
+                peer,
+                command,
+                url,
+                pod,
+                namespace,
+                context,
+                timeout_seconds,
+                interval_seconds,
+            )
+            .await
+    }
+
+    #[tool(description = LIST_BACKGROUND_TASKS_DESCRIPTION)]
+    pub fn list_background_tasks(&self) -> Result<CallToolResult, McpError> {
+        self.local_tools.list_background_tasks()
+    }
+
+    #[tool(description = STOP_BACKGROUND_TASK_DESCRIPTION)]
+    pub async fn stop_background_task(
+        &self,
+        #[tool(param)]
+        #[schemars(description = BACKGROUND_TASK_ID_PARAM_DESCRIPTION)]
+        id: String,
+    ) -> Result<CallToolResult, McpError> {
+        self.local_tools.stop_background_task(id).await
+    }
+
+    #[tool(description = EXPLAIN_CRON_DESCRIPTION)]
+    pub fn explain_cron(
+        &self,
+        #[tool(param)]
+        #[schemars(description = CRON_EXPRESSION_PARAM_DESCRIPTION)]
+        expression: String,
+    ) -> Result<CallToolResult, McpError> {
+        self.local_tools.explain_cron(expression)
+    }
+
+    #[tool(description = LINT_IAM_POLICY_DESCRIPTION)]
+    pub fn lint_iam_policy(
+        &self,
+        #[tool(param)]
+        #[schemars(description = IAM_POLICY_PARAM_DESCRIPTION)]
+        policy: Option<String>,
+        #[tool(param)]
+        #[schemars(description = IAM_POLICY_PATH_PARAM_DESCRIPTION)]
+        path: Option<String>,
+    ) -> Result<CallToolResult, McpError> {
+        self.local_tools.lint_iam_policy(policy, path)
+    }
+
+    #[tool(description = BEGIN_CHANGES_DESCRIPTION)]
+    pub fn begin_changes(&self) -> Result<CallToolResult, McpError> {
+        self.local_tools.begin_changes()
+    }
+
+    #[tool(description = COMMIT_CHANGES_DESCRIPTION)]
+    pub fn commit_changes(&self) -> Result<CallToolResult, McpError> {
+        self.local_tools.commit_changes()
+    }
+
+    #[tool(description = ROLLBACK_CHANGES_DESCRIPTION)]
+    pub fn rollback_changes(&self) -> Result<CallToolResult, McpError> {
+        self.local_tools.rollback_changes()
+    }
+
+    #[tool(description = MKTEMP_SESSION_DESCRIPTION)]
+    pub fn mktemp_session(
+        &self,
+        #[tool(param)]
+        #[schemars(description = MKTEMP_PREFIX_PARAM_DESCRIPTION)]
+        prefix: Option<String>,
+    ) -> Result<CallToolResult, McpError> {
+        self.local_tools.mktemp_session(prefix)
+    }
+
+    #[tool(description = SEARCH_FILES_DESCRIPTION)]
+    pub async fn search_files(
+        &self,
+        #[tool(param)]
+        #[schemars(description = SEARCH_PATTERN_PARAM_DESCRIPTION)]
+        pattern: String,
+        #[tool(param)]
+        #[schemars(description = SEARCH_PATH_PARAM_DESCRIPTION)]
+        path: Option<String>,
+        #[tool(param)]
+        #[schemars(description = SEARCH_GLOBS_PARAM_DESCRIPTION)]
+        globs: Option<Vec<String>>,
+        #[tool(param)]
+        #[schemars(description = CASE_INSENSITIVE_PARAM_DESCRIPTION)]
+        case_insensitive: Option<bool>,
+        #[tool(param)]
+        #[schemars(description = MAX_RESULTS_PARAM_DESCRIPTION)]
+        max_results: Option<u32>,
+        #[tool(param)]
+        #[schemars(description = CONTEXT_LINES_PARAM_DESCRIPTION)]
+        context_lines: Option<u32>,
+    ) -> Result<CallToolResult, McpError> {
+        self.local_tools
+            .search_files(
+                pattern,
+                path,
+                globs,
+                case_insensitive,
+                max_results,
+                context_lines,
+            )
+            .await
+    }
+
+    #[tool(description = GET_WORKSPACE_CONTEXT_DESCRIPTION)]
+    pub async fn get_workspace_context(
+        &self,
+        #[tool(param)]
+        #[schemars(description = WORKSPACE_PATH_PARAM_DESCRIPTION)]
+        path: Option<String>,
+    ) -> Result<CallToolResult, McpError> {
+        self.local_tools.get_workspace_context(path).await
+    }
+
     // Remote tools delegation
     #[tool(description = GENERATE_CODE_DESCRIPTION)]
     pub async fn generate_code(
         &self,
+        peer: rmcp::Peer<RoleServer>,
         #[tool(param)]
         #[schemars(description = GENERATE_PROMPT_PARAM_DESCRIPTION)]
         prompt: String,
@@ -114,7 +392,29 @@ impl CombinedTools {
         context: Option<Vec<String>>,
     ) -> Result<CallToolResult, McpError> {
         self.remote_tools
-            .generate_code(prompt, provisioner, save_files, context)
+            .generate_code(peer, prompt, provisioner, save_files, context)
+            .await
+    }
+
+    #[tool(description = GENERATE_TESTS_DESCRIPTION)]
+    pub async fn generate_tests(
+        &self,
+        peer: rmcp::Peer<RoleServer>,
+        #[tool(param)]
+        #[schemars(description = TEST_TARGET_PATHS_PARAM_DESCRIPTION)]
+        paths: Vec<String>,
+        #[tool(param)]
+        #[schemars(description = TEST_FRAMEWORK_PARAM_DESCRIPTION)]
+        framework: TestFramework,
+        #[tool(param)]
+        #[schemars(description = TEST_INSTRUCTIONS_PARAM_DESCRIPTION)]
+        instructions: Option<String>,
+        #[tool(param)]
+        #[schemars(description = SAVE_FILES_PARAM_DESCRIPTION)]
+        save_files: Option<bool>,
+    ) -> Result<CallToolResult, McpError> {
+        self.remote_tools
+            .generate_tests(peer, paths, framework, instructions, save_files)
             .await
     }
 
@@ -130,6 +430,24 @@ impl CombinedTools {
     ) -> Result<CallToolResult, McpError> {
         self.remote_tools.smart_search_code(query, limit).await
     }
+
+    #[tool(description = SEARCH_OUTPUT_DESCRIPTION)]
+    pub async fn search_output(
+        &self,
+        #[tool(param)]
+        #[schemars(description = OUTPUT_ARTIFACT_ID_PARAM_DESCRIPTION)]
+        artifact_id: String,
+        #[tool(param)]
+        #[schemars(description = OUTPUT_QUERY_PARAM_DESCRIPTION)]
+        query: String,
+        #[tool(param)]
+        #[schemars(description = OUTPUT_CONTEXT_LINES_PARAM_DESCRIPTION)]
+        context_lines: Option<u32>,
+    ) -> Result<CallToolResult, McpError> {
+        self.remote_tools
+            .search_output(artifact_id, query, context_lines)
+            .await
+    }
 }
 
 #[tool(tool_box)]
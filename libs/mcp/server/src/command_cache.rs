@@ -0,0 +1,147 @@
+use serde::{Deserialize, Serialize};
+use stakpak_shared::local_store::LocalStore;
+use stakpak_shared::policy::{RiskLevel, classify_command};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+const CACHE_FILE: &str = "command_cache.json";
+/// Least recently used entries are evicted past this many
+const MAX_ENTRIES: usize = 100;
+/// Cached outputs older than this are stale, cluster state changes quickly
+const TTL_SECS: u64 = 300;
+
+static COMMAND_CACHE: AtomicBool = AtomicBool::new(false);
+
+/// Serve repeated identical read-only `run_command` calls from the cache by default
+pub fn set_command_cache(enabled: bool) {
+    COMMAND_CACHE.store(enabled, Ordering::SeqCst);
+}
+
+pub fn command_cache_enabled() -> bool {
+    COMMAND_CACHE.load(Ordering::SeqCst)
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+struct CacheEntry {
+    command: String,
+    work_dir: String,
+    output: String,
+    cached_at: u64,
+}
+
+/// Results of read-only commands kept under the session directory, most recently used last
+#[derive(Serialize, Deserialize, Debug, Default, PartialEq)]
+pub struct CommandCache {
+    entries: Vec<CacheEntry>,
+}
+
+fn now() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|elapsed| elapsed.as_secs())
+        .unwrap_or_default()
+}
+
+/// Only commands that cannot change anything may be answered from the cache
+pub fn is_cacheable(command: &str) -> bool {
+    classify_command(command).level == RiskLevel::ReadOnly
+}
+
+impl CommandCache {
+    fn to_json(&self) -> Result<String, String> {
+        serde_json::to_string(self).map_err(|e| format!("Failed to serialize command cache: {}", e))
+    }
+
+    /// Load, change and save the session cache while holding the store lock, so concurrent
+    /// tool calls do not overwrite each other's entries
+    fn update<T>(change: impl FnOnce(&mut Self) -> T) -> Result<T, String> {
+        LocalStore::session().update(CACHE_FILE, |content| {
+            let mut cache: Self = content
+                .and_then(|content| serde_json::from_str(&content).ok())
+                .unwrap_or_default();
+            let value = change(&mut cache);
+            Ok((cache.to_json()?, value))
+        })
+    }
+
+    /// The cached output of `command` and its age in seconds, marking it as recently used
+    pub fn get(&mut self, command: &str, work_dir: &str, now: u64) -> Option<(String, u64)> {
+        self.entries
+            .retain(|entry| now.saturating_sub(entry.cached_at) < TTL_SECS);
+        let index = self
+            .entries
+            .iter()
+            .position(|entry| entry.command == command && entry.work_dir == work_dir)?;
+        let entry = self.entries.remove(index);
+        let hit = (entry.output.clone(), now.saturating_sub(entry.cached_at));
+        self.entries.push(entry);
+        Some(hit)
+    }
+
+    pub fn insert(&mut self, command: &str, work_dir: &str, output: &str, now: u64) {
+        self.entries
+            .retain(|entry| entry.command != command || entry.work_dir != work_dir);
+        self.entries.push(CacheEntry {
+            command: command.to_string(),
+            work_dir: work_dir.to_string(),
+            output: output.to_string(),
+            cached_at: now,
+        });
+        if self.entries.len() > MAX_ENTRIES {
+            self.entries.drain(..self.entries.len() - MAX_ENTRIES);
+        }
+    }
+}
+
+/// The cached output of `command` run in `work_dir`, with a marker telling the model it
+/// was not run again
+pub fn cached_output(command: &str, work_dir: &str) -> Option<String> {
+    let (output, age) = match CommandCache::update(|cache| cache.get(command, work_dir, now())) {
+        Ok(hit) => hit?,
+        Err(e) => {
+            tracing::error!("Failed to update command cache: {}", e);
+            return None;
+        }
+    };
+    Some(format!(
+        "[CACHED] Output of an identical run {}s ago, pass cache=false to run the command again\n{}",
+        age, output
+    ))
+}
+
+pub fn cache_output(command: &str, work_dir: &str, output: &str) -> Result<(), String> {
+    CommandCache::update(|cache| cache.insert(command, work_dir, output, now()))
+}
+
+/// Drop every cached output, called whenever a tool call may have changed files
+pub fn invalidate_command_cache() {
+    if let Err(e) = CommandCache::update(|cache| cache.entries.clear()) {
+        tracing::error!("Failed to clear command cache: {}", e);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_cache_expires_and_evicts_least_recently_used() {
+        let mut cache = CommandCache::default();
+        cache.insert("kubectl get pods", ".", "pod-0 Running", 1000);
+        assert_eq!(
+            cache.get("kubectl get pods", ".", 1010),
+            Some(("pod-0 Running".to_string(), 10))
+        );
+        assert_eq!(cache.get("kubectl get pods", "infra", 1010), None);
+        assert_eq!(cache.get("kubectl get pods", ".", 1000 + TTL_SECS), None);
+
+        for i in 0..MAX_ENTRIES {
+            cache.insert(&format!("cat file-{}", i), ".", "content", 2000);
+        }
+        assert!(cache.get("cat file-0", ".", 2001).is_some());
+        cache.insert("ls", ".", "file-0", 2002);
+        assert_eq!(cache.entries.len(), MAX_ENTRIES);
+        assert!(cache.get("cat file-0", ".", 2003).is_some());
+        assert_eq!(cache.get("cat file-1", ".", 2003), None);
+    }
+}
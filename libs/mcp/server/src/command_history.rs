@@ -0,0 +1,125 @@
+use sha2::{Digest, Sha256};
+use similar::TextDiff;
+use stakpak_shared::local_store::LocalStore;
+
+const COMMAND_HISTORY_DIR: &str = "command_history";
+/// Outputs shorter than this are always shown in full
+const MIN_LINES_FOR_DIFF: usize = 10;
+
+/// File of a command in a directory, named after a stable hash so it is found again
+/// whatever Rust version built the server
+fn history_file(command: &str, work_dir: &str) -> String {
+    let hash: String = Sha256::new()
+        .chain_update(command.as_bytes())
+        .chain_update([0])
+        .chain_update(work_dir.as_bytes())
+        .finalize()
+        .iter()
+        .take(8)
+        .map(|b| format!("{:02x}", b))
+        .collect();
+    format!("{}/{}.txt", COMMAND_HISTORY_DIR, hash)
+}
+
+/// Remember `output` as the latest output of `command` and, when the command ran before
+/// in this session, return a condensed result showing only what changed since that run.
+/// `output` is written to disk, so it must already be redacted.
+///
+/// Returns `None` when the full output should be shown: first runs, short outputs and
+/// outputs that changed so much that a diff would not be smaller.
+pub fn condense_repeated_output(
+    command: &str,
+    work_dir: &str,
+    output: &str,
+) -> Result<Option<String>, String> {
+    let file = history_file(command, work_dir);
+    let store = LocalStore::session();
+    let previous = store.update(&file, |previous| Ok((output.to_string(), previous)))?;
+    let saved_path = store.path(&file).to_string_lossy().to_string();
+
+    Ok(previous.and_then(|previous| condense(&previous, output, &saved_path)))
+}
+
+fn condense(previous: &str, output: &str, saved_path: &str) -> Option<String> {
+    let line_count = output.lines().count();
+    if line_count < MIN_LINES_FOR_DIFF {
+        return None;
+    }
+
+    if previous == output {
+        return Some(format!(
+            "Output unchanged since the last run of this command ({} lines). Full output saved to {}",
+            line_count, saved_path
+        ));
+    }
+
+    let diff = TextDiff::from_lines(previous, output)
+        .unified_diff()
+        .context_radius(1)
+        .header("previous run", "this run")
+        .to_string();
+    if diff.lines().count() * 2 > line_count {
+        return None;
+    }
+
+    Some(format!(
+        "Output changed since the last run of this command, showing only the differences ({} lines in total). Full output saved to {}\n{}",
+        line_count,
+        saved_path,
+        diff.trim_end()
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn pods(statuses: &[&str]) -> String {
+        statuses
+            .iter()
+            .enumerate()
+            .map(|(i, status)| format!("pod-{}   1/1   {}\n", i, status))
+            .collect()
+    }
+
+    #[test]
+    fn test_history_file_is_stable() {
+        assert_eq!(
+            history_file("kubectl get pods", "/src"),
+            "command_history/aa54dca6d76abfb9.txt"
+        );
+        assert_ne!(
+            history_file("kubectl get pods", "/src"),
+            history_file("kubectl get pods", "/src/app")
+        );
+    }
+
+    #[test]
+    fn test_unchanged_output_is_summarized() {
+        let output = pods(&["Running"; 12]);
+        let condensed = condense(&output, &output, "out.txt").unwrap();
+        assert!(condensed.starts_with("Output unchanged since the last run"));
+    }
+
+    #[test]
+    fn test_changed_output_shows_only_the_diff() {
+        let mut statuses = ["Running"; 20];
+        let previous = pods(&statuses);
+        statuses[7] = "CrashLoopBackOff";
+        let condensed = condense(&previous, &pods(&statuses), "out.txt").unwrap();
+
+        assert!(condensed.contains("-pod-7   1/1   Running"));
+        assert!(condensed.contains("+pod-7   1/1   CrashLoopBackOff"));
+        assert!(!condensed.contains("pod-15"));
+    }
+
+    #[test]
+    fn test_short_or_rewritten_output_is_shown_in_full() {
+        assert_eq!(condense("a\n", "b\n", "out.txt"), None);
+        let previous = pods(&["Pending"; 12]);
+        assert_eq!(
+            condense(&previous, &pods(&["Running"; 12]), "out.txt"),
+            None
+        );
+    }
+}
@@ -0,0 +1,294 @@
+use regex::Regex;
+use serde::Deserialize;
+use stakpak_shared::policy::split_commands;
+use std::path::PathBuf;
+use std::sync::{Arc, Mutex};
+use std::time::SystemTime;
+
+/// Prefix of patterns that are regular expressions instead of globs
+const REGEX_PREFIX: &str = "re:";
+/// Commands printing the whole environment, blocked when any variable is
+const ENV_DUMP_COMMANDS: &[&str] = &[
+    "env",
+    "printenv",
+    "set",
+    "export",
+    "export -p",
+    "declare -x",
+];
+
+/// Commands `run_command` may spawn, enforced by the server whatever the client allows
+///
+/// ```toml
+/// # Every part of a pipeline or command list has to match, any command when empty
+/// allow = ["kubectl get *", "terraform plan*", "re:^git (status|diff|log)( |$)"]
+/// # Matched against the whole command and each of its parts
+/// deny = ["*--force*", "re:curl .*\\|\\s*(ba)?sh"]
+/// # Variables commands may not read or set
+/// blocked_env = ["AWS_SECRET_*", "*_TOKEN"]
+/// ```
+///
+/// Patterns are globs over the command string (`*` matches anything, `?` one character)
+/// unless prefixed with `re:`, which makes them unanchored regular expressions.
+#[derive(Deserialize, Debug, Default)]
+struct PolicyFile {
+    #[serde(default)]
+    allow: Vec<String>,
+    #[serde(default)]
+    deny: Vec<String>,
+    #[serde(default)]
+    blocked_env: Vec<String>,
+}
+
+#[derive(Debug)]
+struct Pattern {
+    source: String,
+    regex: Regex,
+}
+
+impl Pattern {
+    fn new(source: &str) -> Result<Self, String> {
+        let expression = match source.strip_prefix(REGEX_PREFIX) {
+            Some(expression) => expression.to_string(),
+            None => glob_to_regex(source),
+        };
+        let regex =
+            Regex::new(&expression).map_err(|e| format!("Invalid pattern '{}': {}", source, e))?;
+        Ok(Self {
+            source: source.to_string(),
+            regex,
+        })
+    }
+
+    fn matches(&self, text: &str) -> bool {
+        self.regex.is_match(text)
+    }
+}
+
+fn glob_to_regex(glob: &str) -> String {
+    let mut expression = String::from("^");
+    for c in glob.chars() {
+        match c {
+            '*' => expression.push_str(".*"),
+            '?' => expression.push('.'),
+            c => expression.push_str(&regex::escape(&c.to_string())),
+        }
+    }
+    expression.push('$');
+    expression
+}
+
+/// Compiled command policy
+#[derive(Debug, Default)]
+pub struct CommandPolicy {
+    allow: Vec<Pattern>,
+    deny: Vec<Pattern>,
+    blocked_env: Vec<Pattern>,
+}
+
+fn compile(patterns: &[String]) -> Result<Vec<Pattern>, String> {
+    patterns
+        .iter()
+        .map(|pattern| Pattern::new(pattern))
+        .collect()
+}
+
+/// Names of the variables a command line references (`$NAME`, `${NAME}`) or assigns
+/// (`NAME=value`, `export NAME`)
+fn env_names(command: &str) -> Result<Vec<String>, String> {
+    let regex = Regex::new(
+        r"\$\{?([A-Za-z_][A-Za-z0-9_]*)|(?:^|[\s;&|(])([A-Za-z_][A-Za-z0-9_]*)=|\b(?:export|unset|printenv)\s+([A-Za-z_][A-Za-z0-9_]*)",
+    )
+    .map_err(|e| e.to_string())?;
+    Ok(regex
+        .captures_iter(command)
+        .filter_map(|captures| {
+            captures
+                .get(1)
+                .or_else(|| captures.get(2))
+                .or_else(|| captures.get(3))
+        })
+        .map(|name| name.as_str().to_string())
+        .collect())
+}
+
+/// First word of a command, the program it runs, shown instead of the full command
+/// which may contain restored secrets
+fn program(segment: &str) -> &str {
+    segment.split_whitespace().next().unwrap_or_default()
+}
+
+impl CommandPolicy {
+    pub fn parse(content: &str) -> Result<Self, String> {
+        let file: PolicyFile = toml::from_str(content)
+            .map_err(|e| format!("Failed to parse command policy: {}", e))?;
+        Ok(Self {
+            allow: compile(&file.allow)?,
+            deny: compile(&file.deny)?,
+            blocked_env: compile(&file.blocked_env)?,
+        })
+    }
+
+    /// Why `command` may not run, `None` when it may
+    pub fn check(&self, command: &str) -> Option<String> {
+        let segments: Vec<&str> = split_commands(command)
+            .into_iter()
+            .map(str::trim)
+            .filter(|segment| !segment.is_empty())
+            .collect();
+
+        for pattern in &self.deny {
+            if pattern.matches(command.trim()) || segments.iter().any(|s| pattern.matches(s)) {
+                return Some(format!(
+                    "it matches the denied pattern '{}'",
+                    pattern.source
+                ));
+            }
+        }
+
+        if !self.allow.is_empty() {
+            for segment in &segments {
+                // Substitutions run commands the allow patterns never see
+                if segment.contains('`') || segment.contains("$(") {
+                    return Some(format!(
+                        "`{}` uses a command substitution, which is not allowed by the allowlist",
+                        program(segment)
+                    ));
+                }
+                // Redirections write or read files the allow patterns never see
+                if segment.contains(['>', '<']) {
+                    return Some(format!(
+                        "`{}` uses a redirection, which is not allowed by the allowlist",
+                        program(segment)
+                    ));
+                }
+                if !self.allow.iter().any(|pattern| pattern.matches(segment)) {
+                    return Some(format!("`{}` matches no allowed pattern", program(segment)));
+                }
+            }
+        }
+
+        if !self.blocked_env.is_empty() {
+            if let Some(segment) = segments
+                .iter()
+                .find(|segment| ENV_DUMP_COMMANDS.contains(*segment))
+            {
+                return Some(format!(
+                    "`{}` prints environment variables that are blocked",
+                    segment
+                ));
+            }
+            let names = env_names(command).unwrap_or_default();
+            for name in names {
+                if let Some(pattern) = self.blocked_env.iter().find(|p| p.matches(&name)) {
+                    return Some(format!(
+                        "it uses the variable {}, blocked by the pattern '{}'",
+                        name, pattern.source
+                    ));
+                }
+            }
+        }
+        None
+    }
+}
+
+#[derive(Debug)]
+struct Loaded {
+    modified: SystemTime,
+    policy: Result<Arc<CommandPolicy>, String>,
+}
+
+/// Command policy file, reloaded whenever it changes so the rules can be tightened
+/// without restarting the server. Clones share the loaded policy.
+#[derive(Clone, Debug)]
+pub struct CommandPolicySource {
+    path: PathBuf,
+    loaded: Arc<Mutex<Option<Loaded>>>,
+}
+
+impl CommandPolicySource {
+    /// Load the policy at `path`, failing on a missing or invalid file at startup
+    pub fn load(path: impl Into<PathBuf>) -> Result<Self, String> {
+        let source = Self {
+            path: path.into(),
+            loaded: Arc::new(Mutex::new(None)),
+        };
+        source.current()?;
+        Ok(source)
+    }
+
+    /// Policy as of the latest version of the file. A file that became missing or
+    /// invalid blocks every command until it is fixed.
+    pub fn current(&self) -> Result<Arc<CommandPolicy>, String> {
+        let modified = std::fs::metadata(&self.path)
+            .and_then(|metadata| metadata.modified())
+            .map_err(|e| {
+                format!(
+                    "Failed to read command policy {}: {}",
+                    self.path.display(),
+                    e
+                )
+            })?;
+        let mut loaded = self.loaded.lock().map_err(|e| e.to_string())?;
+        if let Some(loaded) = loaded.as_ref() {
+            if loaded.modified == modified {
+                return loaded.policy.clone();
+            }
+        }
+
+        let policy = std::fs::read_to_string(&self.path)
+            .map_err(|e| {
+                format!(
+                    "Failed to read command policy {}: {}",
+                    self.path.display(),
+                    e
+                )
+            })
+            .and_then(|content| CommandPolicy::parse(&content))
+            .map(Arc::new);
+        match &policy {
+            Ok(_) => tracing::info!("Loaded command policy {}", self.path.display()),
+            Err(e) => tracing::error!("{}", e),
+        }
+        *loaded = Some(Loaded {
+            modified,
+            policy: policy.clone(),
+        });
+        policy
+    }
+
+    /// Why `command` may not run, `None` when it may
+    pub fn check(&self, command: &str) -> Option<String> {
+        match self.current() {
+            Ok(policy) => policy.check(command),
+            Err(e) => Some(format!("the policy could not be loaded ({})", e)),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_command_policy() {
+        let policy = CommandPolicy::parse(
+            r#"
+allow = ["kubectl get *", "grep *", "re:^git (status|diff)( |$)"]
+deny = ["*--all-namespaces*"]
+blocked_env = ["*_TOKEN"]
+"#,
+        )
+        .unwrap();
+
+        assert_eq!(policy.check("kubectl get pods | grep web"), None);
+        assert_eq!(policy.check("git status"), None);
+        assert!(policy.check("kubectl get pods && rm -rf /").is_some());
+        assert!(policy.check("kubectl get pods --all-namespaces").is_some());
+        assert!(policy.check("kubectl get $(cat names)").is_some());
+        assert!(policy.check("grep $GITHUB_TOKEN config").is_some());
+        assert!(policy.check("git log").is_some());
+        assert!(policy.check("kubectl get pods > /etc/profile").is_some());
+        assert!(policy.check("grep key < ~/.ssh/id_rsa").is_some());
+    }
+}
@@ -0,0 +1,58 @@
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::time::Duration;
+use tokio::process::Child;
+
+pub const DEFAULT_COMMAND_TIMEOUT_SECS: u64 = 300;
+/// Longer commands belong in the background, e.g. with `nohup` and a log file
+const MAX_COMMAND_TIMEOUT_SECS: u64 = 3600;
+
+static COMMAND_TIMEOUT_SECS: AtomicU64 = AtomicU64::new(DEFAULT_COMMAND_TIMEOUT_SECS);
+
+/// Kill `run_command` children running longer than this unless the call asks otherwise
+pub fn set_command_timeout(secs: u64) {
+    COMMAND_TIMEOUT_SECS.store(secs, Ordering::SeqCst);
+}
+
+/// Timeout of a `run_command` call, the requested one or the server default
+pub fn command_timeout(requested: Option<u64>) -> Duration {
+    let secs = requested.unwrap_or_else(|| COMMAND_TIMEOUT_SECS.load(Ordering::SeqCst));
+    Duration::from_secs(secs.clamp(1, MAX_COMMAND_TIMEOUT_SECS))
+}
+
+/// Kill `child` and everything it started. Children are spawned as the leader of their own
+/// process group, so the whole group is killed and no background job keeps running.
+pub fn kill_process_group(child: &mut Child) {
+    #[cfg(unix)]
+    if let Some(pid) = child.id() {
+        // SAFETY: killpg only sends a signal, the group is the one the child leads
+        if unsafe { libc::killpg(pid as libc::pid_t, libc::SIGKILL) } == 0 {
+            return;
+        }
+    }
+    if let Err(e) = child.start_kill() {
+        tracing::error!("Failed to kill timed out command: {}", e);
+    }
+}
+
+/// Line appended to the partial output of a command killed on timeout
+pub fn timeout_notice(timeout: Duration) -> String {
+    format!(
+        "TIMEOUT: the command was killed after {}s, the output above is partial. Pass a larger timeout, or run it in the background and poll its log, if it needs longer.",
+        timeout.as_secs()
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_command_timeout() {
+        assert_eq!(command_timeout(Some(10)), Duration::from_secs(10));
+        assert_eq!(command_timeout(Some(0)), Duration::from_secs(1));
+        assert_eq!(
+            command_timeout(Some(86_400)),
+            Duration::from_secs(MAX_COMMAND_TIMEOUT_SECS)
+        );
+    }
+}
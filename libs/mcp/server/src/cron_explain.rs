@@ -0,0 +1,476 @@
+use serde::Serialize;
+use std::collections::BTreeSet;
+
+const MONTHS: [&str; 12] = [
+    "JAN", "FEB", "MAR", "APR", "MAY", "JUN", "JUL", "AUG", "SEP", "OCT", "NOV", "DEC",
+];
+const MONTH_NAMES: [&str; 12] = [
+    "January",
+    "February",
+    "March",
+    "April",
+    "May",
+    "June",
+    "July",
+    "August",
+    "September",
+    "October",
+    "November",
+    "December",
+];
+const WEEKDAYS: [&str; 7] = ["SUN", "MON", "TUE", "WED", "THU", "FRI", "SAT"];
+const WEEKDAY_NAMES: [&str; 7] = [
+    "Sunday",
+    "Monday",
+    "Tuesday",
+    "Wednesday",
+    "Thursday",
+    "Friday",
+    "Saturday",
+];
+
+/// Syntax of a cron expression
+#[derive(Serialize, Debug, Clone, Copy, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum CronDialect {
+    /// crontab, Kubernetes CronJobs and GitHub Actions: 5 fields, Sunday is 0 or 7
+    Standard,
+    /// EventBridge and CloudWatch: 6 fields with a year, `?` for the unused day field,
+    /// Sunday is 1
+    Aws,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum FieldKind {
+    Minute,
+    Hour,
+    DayOfMonth,
+    Month,
+    DayOfWeek,
+    Year,
+}
+
+impl FieldKind {
+    fn name(self) -> &'static str {
+        match self {
+            FieldKind::Minute => "minute",
+            FieldKind::Hour => "hour",
+            FieldKind::DayOfMonth => "day of month",
+            FieldKind::Month => "month",
+            FieldKind::DayOfWeek => "day of week",
+            FieldKind::Year => "year",
+        }
+    }
+
+    /// Values the field accepts as written
+    fn bounds(self, dialect: CronDialect) -> (u32, u32) {
+        match (self, dialect) {
+            (FieldKind::Minute, _) => (0, 59),
+            (FieldKind::Hour, _) => (0, 23),
+            (FieldKind::DayOfMonth, _) => (1, 31),
+            (FieldKind::Month, _) => (1, 12),
+            (FieldKind::DayOfWeek, CronDialect::Standard) => (0, 7),
+            (FieldKind::DayOfWeek, CronDialect::Aws) => (1, 7),
+            (FieldKind::Year, _) => (1970, 2199),
+        }
+    }
+
+    fn named_value(self, name: &str, dialect: CronDialect) -> Option<u32> {
+        let name = name.to_uppercase();
+        let position = |names: &[&str]| names.iter().position(|n| *n == name);
+        match self {
+            FieldKind::Month => position(&MONTHS).map(|i| i as u32 + 1),
+            FieldKind::DayOfWeek => position(&WEEKDAYS).map(|i| match dialect {
+                CronDialect::Standard => i as u32,
+                CronDialect::Aws => i as u32 + 1,
+            }),
+            _ => None,
+        }
+    }
+}
+
+/// One field of the expression with every value it matches. Days of the week are
+/// numbered from Sunday = 0 in both dialects.
+#[derive(Serialize, Debug, Clone, PartialEq)]
+pub struct CronField {
+    pub name: &'static str,
+    pub expression: String,
+    /// Empty when the field matches any value
+    pub values: Vec<u32>,
+}
+
+#[derive(Serialize, Debug, Clone, PartialEq)]
+pub struct CronExplanation {
+    pub expression: String,
+    pub dialect: CronDialect,
+    pub summary: String,
+    pub fields: Vec<CronField>,
+    /// Surprising behaviors of the schedule
+    pub warnings: Vec<String>,
+}
+
+struct ParsedField {
+    kind: FieldKind,
+    expression: String,
+    /// `None` for `*` and `?`
+    values: Option<BTreeSet<u32>>,
+    /// Step of a `*/n` field
+    every: Option<u32>,
+}
+
+fn parse_value(kind: FieldKind, dialect: CronDialect, token: &str) -> Result<u32, String> {
+    let (min, max) = kind.bounds(dialect);
+    let value = match token.parse::<u32>() {
+        Ok(value) => value,
+        Err(_) => match kind.named_value(token, dialect) {
+            Some(value) => value,
+            None if token.contains(['L', 'W', '#']) => {
+                return Err(format!(
+                    "'{}' in the {} uses L, W or #, which are not supported",
+                    token,
+                    kind.name()
+                ));
+            }
+            None => return Err(format!("Invalid {} value '{}'", kind.name(), token)),
+        },
+    };
+    if value < min || value > max {
+        return Err(format!(
+            "{} value {} is out of range {}-{}",
+            kind.name(),
+            value,
+            min,
+            max
+        ));
+    }
+    Ok(value)
+}
+
+fn parse_field(
+    kind: FieldKind,
+    dialect: CronDialect,
+    expression: &str,
+) -> Result<ParsedField, String> {
+    let (min, max) = kind.bounds(dialect);
+    let mut field = ParsedField {
+        kind,
+        expression: expression.to_string(),
+        values: None,
+        every: None,
+    };
+    if expression == "*" || expression == "?" {
+        if expression == "?" && !matches!(kind, FieldKind::DayOfMonth | FieldKind::DayOfWeek) {
+            return Err(format!(
+                "'?' is only valid in the day fields, not the {}",
+                kind.name()
+            ));
+        }
+        return Ok(field);
+    }
+
+    let mut values = BTreeSet::new();
+    for part in expression.split(',') {
+        let (range, step) = match part.split_once('/') {
+            Some((range, step)) => {
+                let step = step
+                    .parse::<u32>()
+                    .ok()
+                    .filter(|step| *step > 0)
+                    .ok_or_else(|| format!("Invalid step '{}' in the {}", step, kind.name()))?;
+                (range, step)
+            }
+            None => (part, 1),
+        };
+        let (start, end) = match range.split_once('-') {
+            _ if range == "*" => (min, max),
+            Some((start, end)) => (
+                parse_value(kind, dialect, start)?,
+                parse_value(kind, dialect, end)?,
+            ),
+            // `5/15` runs from 5 to the end of the range
+            None if step > 1 => (parse_value(kind, dialect, range)?, max),
+            None => {
+                let value = parse_value(kind, dialect, range)?;
+                (value, value)
+            }
+        };
+        if start > end {
+            return Err(format!(
+                "Range {} in the {} goes backwards",
+                range,
+                kind.name()
+            ));
+        }
+        values.extend((start..=end).step_by(step as usize));
+        if range == "*" && expression == part {
+            field.every = Some(step);
+        }
+    }
+
+    if kind == FieldKind::DayOfWeek {
+        // Number days from Sunday = 0, AWS counts from Sunday = 1 and crontab allows 7
+        values = values
+            .into_iter()
+            .map(|day| match dialect {
+                CronDialect::Standard => day % 7,
+                CronDialect::Aws => day - 1,
+            })
+            .collect();
+    }
+    field.values = Some(values);
+    Ok(field)
+}
+
+fn expand_macro(expression: &str) -> Option<&'static str> {
+    match expression {
+        "@yearly" | "@annually" => Some("0 0 1 1 *"),
+        "@monthly" => Some("0 0 1 * *"),
+        "@weekly" => Some("0 0 * * 0"),
+        "@daily" | "@midnight" => Some("0 0 * * *"),
+        "@hourly" => Some("0 * * * *"),
+        _ => None,
+    }
+}
+
+/// `1, 2, 3, 5` as `1 through 3 and 5`
+fn describe_values(values: &BTreeSet<u32>, name: impl Fn(u32) -> String) -> String {
+    let mut runs: Vec<(u32, u32)> = Vec::new();
+    for &value in values {
+        match runs.last_mut() {
+            Some((_, end)) if *end + 1 == value => *end = value,
+            _ => runs.push((value, value)),
+        }
+    }
+    let parts: Vec<String> = runs
+        .into_iter()
+        .flat_map(|(start, end)| match end - start {
+            0 => vec![name(start)],
+            1 => vec![name(start), name(end)],
+            _ => vec![format!("{} through {}", name(start), name(end))],
+        })
+        .collect();
+    match parts.split_last() {
+        Some((last, rest)) if !rest.is_empty() => format!("{} and {}", rest.join(", "), last),
+        _ => parts.join(""),
+    }
+}
+
+fn single(field: &ParsedField) -> Option<u32> {
+    field
+        .values
+        .as_ref()
+        .filter(|values| values.len() == 1)
+        .and_then(|values| values.first().copied())
+}
+
+fn summarize(fields: &[ParsedField], dialect: CronDialect) -> String {
+    let [minute, hour, day, month, weekday, ..] = fields else {
+        return String::new();
+    };
+    let number = |value: u32| value.to_string();
+
+    let mut summary = match (single(minute), single(hour), &minute.values, &hour.values) {
+        (Some(m), Some(h), _, _) => format!("At {:02}:{:02}", h, m),
+        (Some(m), None, _, None) => format!("At minute {} of every hour", m),
+        (_, _, None, None) => "Every minute".to_string(),
+        (_, _, Some(_), None) if minute.every.is_some() => {
+            format!("Every {} minutes", minute.every.unwrap_or(1))
+        }
+        (_, _, minutes, hours) => format!(
+            "At minute {} past {}",
+            minutes
+                .as_ref()
+                .map_or("every minute".to_string(), |m| describe_values(m, number)),
+            hours.as_ref().map_or("every hour".to_string(), |h| format!(
+                "hour {}",
+                describe_values(h, number)
+            ))
+        ),
+    };
+
+    let days = day
+        .values
+        .as_ref()
+        .map(|days| format!("on day {} of the month", describe_values(days, number)));
+    let weekdays = weekday.values.as_ref().map(|days| {
+        format!(
+            "on {}",
+            describe_values(days, |d| WEEKDAY_NAMES[d as usize % 7].to_string())
+        )
+    });
+    match (days, weekdays) {
+        (Some(days), Some(weekdays)) => summary.push_str(&format!(" {} or {}", days, weekdays)),
+        (Some(days), None) => summary.push_str(&format!(" {}", days)),
+        (None, Some(weekdays)) => summary.push_str(&format!(" {}", weekdays)),
+        (None, None) => {}
+    }
+    if let Some(months) = &month.values {
+        summary.push_str(&format!(
+            " in {}",
+            describe_values(months, |m| MONTH_NAMES[(m as usize - 1) % 12].to_string())
+        ));
+    }
+    if let (CronDialect::Aws, Some(years)) =
+        (dialect, fields.get(5).and_then(|f| f.values.as_ref()))
+    {
+        summary.push_str(&format!(" in {}", describe_values(years, number)));
+    }
+    summary
+}
+
+fn warnings(fields: &[ParsedField], dialect: CronDialect) -> Vec<String> {
+    let mut warnings = Vec::new();
+    let [minute, hour, day, month, weekday, ..] = fields else {
+        return warnings;
+    };
+
+    if dialect == CronDialect::Standard && day.values.is_some() && weekday.values.is_some() {
+        warnings.push(
+            "Both day of month and day of week are set, so the job runs on days matching either of them, not both".to_string(),
+        );
+    }
+    if minute.values.is_none() && hour.values.is_some() {
+        warnings.push(
+            "The minute is '*', so the job runs every minute of the matching hours".to_string(),
+        );
+    }
+    for field in [minute, hour] {
+        let (min, max) = field.kind.bounds(dialect);
+        if let Some(step) = field.every {
+            if (max - min + 1) % step != 0 {
+                warnings.push(format!(
+                    "A {} step of {} does not divide {}, so the interval is shorter when the {} wraps around",
+                    field.kind.name(),
+                    step,
+                    max - min + 1,
+                    field.kind.name()
+                ));
+            }
+        }
+    }
+    if let Some(days) = &day.values {
+        let longest_month = month.values.as_ref().map_or(31, |months| {
+            months
+                .iter()
+                .map(|m| match m {
+                    2 => 29,
+                    4 | 6 | 9 | 11 => 30,
+                    _ => 31,
+                })
+                .max()
+                .unwrap_or(31)
+        });
+        if days.iter().all(|d| *d > longest_month) && weekday.values.is_none() {
+            warnings.push(
+                "The day of month never occurs in the selected months, so the job never runs"
+                    .to_string(),
+            );
+        } else if days.iter().any(|d| *d > 28) {
+            warnings.push(
+                "Days after the 28th are skipped in months that do not have them".to_string(),
+            );
+        }
+    }
+    warnings.push(match dialect {
+        CronDialect::Standard => "Times are in the scheduler's time zone: the system time zone for crontab, the kube-controller-manager's (usually UTC) for CronJobs without spec.timeZone, and UTC for GitHub Actions".to_string(),
+        CronDialect::Aws => "Times are in UTC unless the EventBridge Scheduler schedule sets a time zone".to_string(),
+    });
+    warnings
+}
+
+/// Parse a crontab, Kubernetes CronJob, GitHub Actions or AWS `cron(...)` expression and
+/// explain when it runs
+pub fn explain_cron(expression: &str) -> Result<CronExplanation, String> {
+    let trimmed = expression.trim();
+    let (source, dialect) = match trimmed
+        .strip_prefix("cron(")
+        .and_then(|rest| rest.strip_suffix(')'))
+    {
+        Some(inner) => (inner.trim(), CronDialect::Aws),
+        None => match expand_macro(trimmed) {
+            Some(expanded) => (expanded, CronDialect::Standard),
+            None if trimmed == "@reboot" => {
+                return Err("@reboot runs once when cron starts, it has no schedule".to_string());
+            }
+            None => (trimmed, CronDialect::Standard),
+        },
+    };
+    let parts: Vec<&str> = source.split_whitespace().collect();
+    let dialect = match (dialect, parts.len()) {
+        (CronDialect::Standard, 5) => CronDialect::Standard,
+        (_, 6) => CronDialect::Aws,
+        (_, count) => {
+            return Err(format!(
+                "Expected 5 fields (minute hour day-of-month month day-of-week) or 6 for AWS (with a year), got {}",
+                count
+            ));
+        }
+    };
+
+    let kinds = [
+        FieldKind::Minute,
+        FieldKind::Hour,
+        FieldKind::DayOfMonth,
+        FieldKind::Month,
+        FieldKind::DayOfWeek,
+        FieldKind::Year,
+    ];
+    let fields = kinds
+        .into_iter()
+        .zip(&parts)
+        .map(|(kind, part)| parse_field(kind, dialect, part))
+        .collect::<Result<Vec<_>, _>>()?;
+
+    if dialect == CronDialect::Aws && (parts[2] == "?") == (parts[4] == "?") {
+        return Err(
+            "AWS cron expressions need '?' in exactly one of day of month and day of week"
+                .to_string(),
+        );
+    }
+
+    Ok(CronExplanation {
+        expression: trimmed.to_string(),
+        dialect,
+        summary: summarize(&fields, dialect),
+        warnings: warnings(&fields, dialect),
+        fields: fields
+            .iter()
+            .map(|field| CronField {
+                name: field.kind.name(),
+                expression: field.expression.clone(),
+                values: field
+                    .values
+                    .as_ref()
+                    .map(|values| values.iter().copied().collect())
+                    .unwrap_or_default(),
+            })
+            .collect(),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_explain_cron() {
+        let weekdays = explain_cron("30 2 * * MON-FRI").unwrap();
+        assert_eq!(weekdays.summary, "At 02:30 on Monday through Friday");
+
+        let every = explain_cron("*/15 * * * *").unwrap();
+        assert_eq!(every.summary, "Every 15 minutes");
+        assert_eq!(every.fields[0].values, vec![0, 15, 30, 45]);
+
+        let either = explain_cron("0 0 13 * 5").unwrap();
+        assert!(either.warnings[0].contains("either"));
+
+        let aws = explain_cron("cron(0 12 ? * 2-6 *)").unwrap();
+        assert_eq!(aws.dialect, CronDialect::Aws);
+        assert_eq!(aws.summary, "At 12:00 on Monday through Friday");
+
+        assert_eq!(explain_cron("@daily").unwrap().summary, "At 00:00");
+        assert!(explain_cron("0 0 30 2 *").unwrap().warnings[0].contains("never runs"));
+        assert!(explain_cron("61 * * * *").is_err());
+        assert!(explain_cron("0 0 * *").is_err());
+    }
+}
@@ -0,0 +1,199 @@
+use serde::Deserialize;
+use std::path::{Path, PathBuf};
+use tokio::process::Command;
+
+/// Labels set on the containers of a workspace by the Dev Containers CLI/VS Code and by
+/// Docker Compose, the compose project usually living in `.devcontainer/`
+const DEVCONTAINER_LABEL: &str = "devcontainer.local_folder";
+const COMPOSE_LABEL: &str = "com.docker.compose.project.working_dir";
+
+#[derive(Deserialize, Debug, Clone, PartialEq)]
+#[serde(rename_all = "PascalCase")]
+struct Mount {
+    #[serde(default)]
+    source: String,
+    #[serde(default)]
+    destination: String,
+}
+
+#[derive(Deserialize, Debug)]
+#[serde(rename_all = "PascalCase")]
+struct ContainerInfo {
+    id: String,
+    name: String,
+    #[serde(default)]
+    mounts: Vec<Mount>,
+}
+
+/// Running dev container or docker-compose service the workspace is mounted in. Commands
+/// run by the agent are executed inside it with `docker exec`, so they use the project's
+/// pinned toolchain instead of whatever is installed on the host.
+#[derive(Clone, Debug, PartialEq)]
+pub struct DevContainer {
+    pub id: String,
+    pub name: String,
+    /// Workspace directory on the host
+    pub host_root: PathBuf,
+    /// Where the workspace is mounted in the container
+    pub container_root: String,
+}
+
+fn docker(args: &[&str]) -> Result<String, String> {
+    let output = std::process::Command::new("docker")
+        .args(args)
+        .output()
+        .map_err(|e| format!("Failed to run docker: {}", e))?;
+    if !output.status.success() {
+        return Err(format!(
+            "docker {} failed: {}",
+            args.first().copied().unwrap_or_default(),
+            String::from_utf8_lossy(&output.stderr).trim()
+        ));
+    }
+    Ok(String::from_utf8_lossy(&output.stdout).to_string())
+}
+
+/// IDs of running containers labeled with `label=value`
+fn containers_labeled(label: &str, value: &Path) -> Result<Vec<String>, String> {
+    let filter = format!("label={}={}", label, value.display());
+    Ok(docker(&["ps", "--quiet", "--filter", &filter])?
+        .lines()
+        .map(|id| id.trim().to_string())
+        .filter(|id| !id.is_empty())
+        .collect())
+}
+
+/// Path of `workspace` inside the container, from the bind mount containing it
+fn workspace_mount(mounts: &[Mount], workspace: &Path) -> Option<String> {
+    mounts
+        .iter()
+        .filter_map(|mount| {
+            let relative = workspace.strip_prefix(&mount.source).ok()?;
+            Some((mount.source.len(), mount, relative))
+        })
+        // The deepest mount wins when the workspace is under several of them
+        .max_by_key(|(depth, _, _)| *depth)
+        .map(|(_, mount, relative)| {
+            let destination = mount.destination.trim_end_matches('/');
+            match relative.to_string_lossy().as_ref() {
+                "" => destination.to_string(),
+                relative => format!("{}/{}", destination, relative),
+            }
+        })
+}
+
+impl DevContainer {
+    /// Find the running container `workspace` is mounted in: the dev container started
+    /// for it, or a service of the compose project in the workspace or its
+    /// `.devcontainer/` directory
+    pub fn detect(workspace: &Path) -> Result<Self, String> {
+        let workspace = workspace
+            .canonicalize()
+            .map_err(|e| format!("Failed to resolve {}: {}", workspace.display(), e))?;
+
+        let mut candidates = containers_labeled(DEVCONTAINER_LABEL, &workspace)?;
+        for compose_dir in [workspace.clone(), workspace.join(".devcontainer")] {
+            candidates.extend(containers_labeled(COMPOSE_LABEL, &compose_dir)?);
+        }
+        if candidates.is_empty() {
+            return Err(format!(
+                "No running dev container or docker-compose service found for {}, start it first (e.g. `devcontainer up` or `docker compose up -d`)",
+                workspace.display()
+            ));
+        }
+
+        let mut args = vec!["inspect"];
+        args.extend(candidates.iter().map(String::as_str));
+        let containers: Vec<ContainerInfo> = serde_json::from_str(&docker(&args)?)
+            .map_err(|e| format!("Failed to parse docker inspect output: {}", e))?;
+
+        containers
+            .into_iter()
+            .find_map(|container| {
+                let container_root = workspace_mount(&container.mounts, &workspace)?;
+                Some(Self {
+                    id: container.id,
+                    name: container.name.trim_start_matches('/').to_string(),
+                    host_root: workspace.clone(),
+                    container_root,
+                })
+            })
+            .ok_or_else(|| {
+                format!(
+                    "Found containers for {}, but none of them mounts the workspace",
+                    workspace.display()
+                )
+            })
+    }
+
+    /// Path of a host directory inside the container
+    pub fn container_path(&self, host_path: &Path) -> Result<String, String> {
+        let host_path = host_path
+            .canonicalize()
+            .unwrap_or_else(|_| host_path.to_path_buf());
+        let relative = host_path.strip_prefix(&self.host_root).map_err(|_| {
+            format!(
+                "{} is outside the workspace mounted in dev container {}",
+                host_path.display(),
+                self.name
+            )
+        })?;
+        Ok(match relative.to_string_lossy().as_ref() {
+            "" => self.container_root.clone(),
+            relative => format!("{}/{}", self.container_root, relative),
+        })
+    }
+
+    /// Build `docker exec ... sh -c <command>` running in the container directory of
+    /// `work_dir`. The variables in `env` are passed through from the docker client, so
+    /// set them on the returned command.
+    pub fn command<'a>(
+        &self,
+        command: &str,
+        work_dir: &Path,
+        env: impl IntoIterator<Item = &'a str>,
+    ) -> Result<Command, String> {
+        let mut cmd = Command::new("docker");
+        cmd.arg("exec")
+            .arg("--interactive")
+            .arg("--workdir")
+            .arg(self.container_path(work_dir)?);
+        for name in env {
+            cmd.arg("--env").arg(name);
+        }
+        cmd.arg(&self.id).arg("sh").arg("-c").arg(command);
+        Ok(cmd)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_workspace_mount() {
+        let mounts = vec![
+            Mount {
+                source: "/home/dev".to_string(),
+                destination: "/home/vscode".to_string(),
+            },
+            Mount {
+                source: "/home/dev/infra".to_string(),
+                destination: "/workspaces/infra/".to_string(),
+            },
+        ];
+        assert_eq!(
+            workspace_mount(&mounts, Path::new("/home/dev/infra")),
+            Some("/workspaces/infra".to_string())
+        );
+        assert_eq!(
+            workspace_mount(&mounts, Path::new("/home/dev/infra/modules/vpc")),
+            Some("/workspaces/infra/modules/vpc".to_string())
+        );
+        assert_eq!(
+            workspace_mount(&mounts, Path::new("/home/dev/app")),
+            Some("/home/vscode/app".to_string())
+        );
+        assert_eq!(workspace_mount(&mounts, Path::new("/srv")), None);
+    }
+}
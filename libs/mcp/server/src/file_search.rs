@@ -0,0 +1,191 @@
+use ignore::WalkBuilder;
+use ignore::overrides::OverrideBuilder;
+use regex::RegexBuilder;
+use serde::Serialize;
+use std::fs;
+use std::io::Read;
+use std::path::Path;
+
+pub const DEFAULT_MAX_RESULTS: usize = 100;
+const MAX_RESULTS_LIMIT: usize = 1000;
+const MAX_CONTEXT_LINES: usize = 10;
+/// Larger files are skipped, they are rarely source and slow the search down
+const MAX_FILE_SIZE: u64 = 2 * 1024 * 1024;
+/// Files with a NUL byte in their first bytes are treated as binary
+const BINARY_SNIFF_SIZE: usize = 8 * 1024;
+const MAX_LINE_CHARS: usize = 300;
+
+#[derive(Debug, Clone)]
+pub struct SearchOptions {
+    pub pattern: String,
+    /// Globs selecting the searched files, e.g. `*.tf` or `modules/**/*.yaml`, a leading
+    /// `!` excludes. Every file not ignored by .gitignore when empty.
+    pub globs: Vec<String>,
+    pub case_insensitive: bool,
+    pub max_results: usize,
+    pub context_lines: usize,
+}
+
+#[derive(Serialize, Debug, PartialEq)]
+pub struct SearchMatch {
+    /// Path relative to the searched directory, with `/` separators
+    pub path: String,
+    /// 1-indexed line number
+    pub line: usize,
+    pub text: String,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub before: Vec<String>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub after: Vec<String>,
+}
+
+#[derive(Serialize, Debug, Default)]
+pub struct SearchResults {
+    pub matches: Vec<SearchMatch>,
+    pub files_searched: usize,
+    pub files_matched: usize,
+    /// True when the search stopped at `max_results`
+    pub truncated: bool,
+}
+
+fn clip(line: &str) -> String {
+    match line.char_indices().nth(MAX_LINE_CHARS) {
+        Some((end, _)) => format!("{}...", &line[..end]),
+        None => line.to_string(),
+    }
+}
+
+/// Content of a text file, `None` for binary, unreadable and oversized files
+fn read_text(path: &Path) -> Option<String> {
+    let metadata = fs::metadata(path).ok()?;
+    if metadata.len() > MAX_FILE_SIZE {
+        return None;
+    }
+    let mut bytes = Vec::new();
+    fs::File::open(path).ok()?.read_to_end(&mut bytes).ok()?;
+    if bytes.iter().take(BINARY_SNIFF_SIZE).any(|b| *b == 0) {
+        return None;
+    }
+    Some(String::from_utf8_lossy(&bytes).to_string())
+}
+
+/// Search the files under `root`, or `root` itself when it is a file, for lines matching
+/// the regex. Files ignored by .gitignore and hidden files are skipped.
+pub fn search_files(root: &Path, options: &SearchOptions) -> Result<SearchResults, String> {
+    let regex = RegexBuilder::new(&options.pattern)
+        .case_insensitive(options.case_insensitive)
+        .build()
+        .map_err(|e| format!("Invalid pattern: {}", e))?;
+    let max_results = options.max_results.clamp(1, MAX_RESULTS_LIMIT);
+    let context_lines = options.context_lines.min(MAX_CONTEXT_LINES);
+
+    let mut walker = WalkBuilder::new(root);
+    walker.sort_by_file_name(|a, b| a.cmp(b));
+    if !options.globs.is_empty() {
+        let mut overrides = OverrideBuilder::new(root);
+        for glob in &options.globs {
+            overrides
+                .add(glob)
+                .map_err(|e| format!("Invalid glob '{}': {}", glob, e))?;
+        }
+        walker.overrides(
+            overrides
+                .build()
+                .map_err(|e| format!("Invalid globs: {}", e))?,
+        );
+    }
+
+    let mut results = SearchResults::default();
+    for entry in walker.build().filter_map(Result::ok) {
+        if !entry
+            .file_type()
+            .is_some_and(|file_type| file_type.is_file())
+        {
+            continue;
+        }
+        let Some(content) = read_text(entry.path()) else {
+            continue;
+        };
+        results.files_searched += 1;
+
+        let relative = entry
+            .path()
+            .strip_prefix(root)
+            .ok()
+            .filter(|relative| !relative.as_os_str().is_empty())
+            .unwrap_or(entry.path());
+        let relative = relative
+            .components()
+            .map(|component| component.as_os_str().to_string_lossy())
+            .collect::<Vec<_>>()
+            .join("/");
+
+        let lines: Vec<&str> = content.lines().collect();
+        let mut matched = false;
+        for (index, line) in lines.iter().enumerate() {
+            if !regex.is_match(line) {
+                continue;
+            }
+            if results.matches.len() == max_results {
+                results.truncated = true;
+                return Ok(results);
+            }
+            matched = true;
+            let context = |range: std::ops::Range<usize>| -> Vec<String> {
+                lines[range].iter().map(|line| clip(line)).collect()
+            };
+            results.matches.push(SearchMatch {
+                path: relative.clone(),
+                line: index + 1,
+                text: clip(line),
+                before: context(index.saturating_sub(context_lines)..index),
+                after: context(index + 1..(index + 1 + context_lines).min(lines.len())),
+            });
+        }
+        if matched {
+            results.files_matched += 1;
+        }
+    }
+    Ok(results)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_search_files() {
+        let dir = std::env::temp_dir().join(format!("stakpak-search-{}", uuid::Uuid::new_v4()));
+        let _ = fs::create_dir_all(dir.join("modules"));
+        let _ = fs::write(
+            dir.join("main.tf"),
+            "resource \"aws_lb\" \"web\" {\n  port = 8080\n}\n",
+        );
+        let _ = fs::write(dir.join("modules/alb.tf"), "port = 443\n");
+        let _ = fs::write(dir.join("notes.md"), "port 8080 is open\n");
+
+        let options = SearchOptions {
+            pattern: r"port\s*=\s*\d+".to_string(),
+            globs: vec!["*.tf".to_string()],
+            case_insensitive: false,
+            max_results: DEFAULT_MAX_RESULTS,
+            context_lines: 1,
+        };
+        let results = search_files(&dir, &options).unwrap_or_default();
+        let _ = fs::remove_dir_all(&dir);
+
+        assert_eq!(results.files_matched, 2);
+        assert_eq!(
+            results.matches[0],
+            SearchMatch {
+                path: "main.tf".to_string(),
+                line: 2,
+                text: "  port = 8080".to_string(),
+                before: vec!["resource \"aws_lb\" \"web\" {".to_string()],
+                after: vec!["}".to_string()],
+            }
+        );
+        assert_eq!(results.matches[1].path, "modules/alb.tf");
+        assert!(!results.truncated);
+    }
+}
@@ -0,0 +1,160 @@
+use serde::Deserialize;
+use std::path::Path;
+
+/// Formats the `format_and_validate` tool can parse
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum DocumentFormat {
+    Json,
+    Yaml,
+    Hcl,
+}
+
+impl std::fmt::Display for DocumentFormat {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            DocumentFormat::Json => write!(f, "JSON"),
+            DocumentFormat::Yaml => write!(f, "YAML"),
+            DocumentFormat::Hcl => write!(f, "HCL"),
+        }
+    }
+}
+
+impl std::str::FromStr for DocumentFormat {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "json" => Ok(DocumentFormat::Json),
+            "yaml" | "yml" => Ok(DocumentFormat::Yaml),
+            "hcl" | "tf" | "tfvars" => Ok(DocumentFormat::Hcl),
+            _ => Err(format!(
+                "Unknown format '{}', expected one of: json, yaml, hcl",
+                s
+            )),
+        }
+    }
+}
+
+impl DocumentFormat {
+    /// Guess the format from the file extension
+    pub fn from_path(path: &str) -> Option<Self> {
+        Path::new(path)
+            .extension()
+            .and_then(|ext| ext.to_str())
+            .and_then(|ext| ext.parse().ok())
+    }
+}
+
+/// Syntax error with its position in the content, when the parser reports one
+#[derive(Debug, Clone, PartialEq)]
+pub struct SyntaxError {
+    pub line: Option<usize>,
+    pub column: Option<usize>,
+    pub message: String,
+}
+
+impl std::fmt::Display for SyntaxError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match (self.line, self.column) {
+            (Some(line), Some(column)) => {
+                write!(f, "line {}, column {}: {}", line, column, self.message)
+            }
+            (Some(line), None) => write!(f, "line {}: {}", line, self.message),
+            _ => write!(f, "{}", self.message),
+        }
+    }
+}
+
+fn format_json(content: &str) -> Result<String, SyntaxError> {
+    let value: serde_json::Value = serde_json::from_str(content).map_err(|e| SyntaxError {
+        line: Some(e.line()),
+        column: Some(e.column()),
+        message: e.to_string(),
+    })?;
+    serde_json::to_string_pretty(&value).map_err(|e| SyntaxError {
+        line: None,
+        column: None,
+        message: e.to_string(),
+    })
+}
+
+fn format_yaml(content: &str) -> Result<String, SyntaxError> {
+    let yaml_error = |e: serde_yaml::Error| SyntaxError {
+        line: e.location().map(|location| location.line()),
+        column: e.location().map(|location| location.column()),
+        message: e.to_string(),
+    };
+    // Kubernetes manifests often hold several documents
+    let mut documents = Vec::new();
+    for document in serde_yaml::Deserializer::from_str(content) {
+        let value = serde_yaml::Value::deserialize(document).map_err(yaml_error)?;
+        documents.push(serde_yaml::to_string(&value).map_err(yaml_error)?);
+    }
+    Ok(documents.join("---\n"))
+}
+
+fn format_hcl(content: &str) -> Result<String, SyntaxError> {
+    let body = hcl::parse(content).map_err(|e| match &e {
+        hcl::Error::Parse(parse_error) => SyntaxError {
+            line: Some(parse_error.location().line()),
+            column: Some(parse_error.location().column()),
+            message: parse_error.message().to_string(),
+        },
+        _ => SyntaxError {
+            line: None,
+            column: None,
+            message: e.to_string(),
+        },
+    })?;
+    hcl::format::to_string(&body).map_err(|e| SyntaxError {
+        line: None,
+        column: None,
+        message: e.to_string(),
+    })
+}
+
+/// Parse `content` and return it pretty-printed, comments are not kept
+pub fn format_document(content: &str, format: DocumentFormat) -> Result<String, SyntaxError> {
+    match format {
+        DocumentFormat::Json => format_json(content),
+        DocumentFormat::Yaml => format_yaml(content),
+        DocumentFormat::Hcl => format_hcl(content),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_format_document() {
+        assert_eq!(
+            format_document(r#"{"a":[1,2]}"#, DocumentFormat::Json),
+            Ok("{\n  \"a\": [\n    1,\n    2\n  ]\n}".to_string())
+        );
+
+        let error = format_document("{\n  \"a\": 1,\n}", DocumentFormat::Json).unwrap_err();
+        assert_eq!((error.line, error.column), (Some(3), Some(1)));
+
+        let manifests = "kind: Namespace\n---\nkind: ConfigMap\ndata: {a: b}\n";
+        assert_eq!(
+            format_document(manifests, DocumentFormat::Yaml),
+            Ok("kind: Namespace\n---\nkind: ConfigMap\ndata:\n  a: b\n".to_string())
+        );
+        let error = format_document("a: [1, 2\nb: 3\n", DocumentFormat::Yaml).unwrap_err();
+        assert!(error.line.is_some());
+
+        assert!(
+            format_document("resource \"a\" \"b\" {\n  x = 1\n}\n", DocumentFormat::Hcl).is_ok()
+        );
+        let error = format_document("resource \"a\" \"b\" {\n  x = \n}\n", DocumentFormat::Hcl)
+            .unwrap_err();
+        assert!(error.line.is_some());
+
+        assert_eq!(
+            DocumentFormat::from_path("main.tf"),
+            Some(DocumentFormat::Hcl)
+        );
+        assert_eq!(DocumentFormat::from_path("Dockerfile"), None);
+    }
+}
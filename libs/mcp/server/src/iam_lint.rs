@@ -0,0 +1,491 @@
+use serde::Serialize;
+use serde_json::Value;
+use std::collections::HashSet;
+
+const POLICY_VERSIONS: [&str; 2] = ["2012-10-17", "2008-10-17"];
+
+/// Actions that let a principal grant itself more permissions
+const PRIVILEGE_ESCALATION_ACTIONS: [&str; 14] = [
+    "iam:AddUserToGroup",
+    "iam:AttachGroupPolicy",
+    "iam:AttachRolePolicy",
+    "iam:AttachUserPolicy",
+    "iam:CreateAccessKey",
+    "iam:CreateLoginProfile",
+    "iam:CreatePolicyVersion",
+    "iam:PassRole",
+    "iam:PutGroupPolicy",
+    "iam:PutRolePolicy",
+    "iam:PutUserPolicy",
+    "iam:SetDefaultPolicyVersion",
+    "iam:UpdateAssumeRolePolicy",
+    "iam:UpdateLoginProfile",
+];
+
+#[derive(Serialize, Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+#[serde(rename_all = "lowercase")]
+pub enum Severity {
+    Info,
+    Warning,
+    Error,
+}
+
+#[derive(Serialize, Debug, Clone, PartialEq)]
+pub struct PolicyFinding {
+    pub severity: Severity,
+    /// `Statement 2 (Sid)`, none for findings about the whole policy
+    pub statement: Option<String>,
+    pub message: String,
+}
+
+/// What a statement grants or denies
+#[derive(Serialize, Debug, Clone, PartialEq)]
+pub struct StatementSummary {
+    pub sid: Option<String>,
+    pub effect: String,
+    pub actions: Vec<String>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub not_actions: Vec<String>,
+    pub resources: Vec<String>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub not_resources: Vec<String>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub principals: Vec<String>,
+    pub conditional: bool,
+}
+
+#[derive(Serialize, Debug, Clone, PartialEq)]
+pub struct PolicyReport {
+    pub statements: Vec<StatementSummary>,
+    /// Most severe first
+    pub findings: Vec<PolicyFinding>,
+}
+
+/// `Action: "s3:GetObject"` and `Action: ["s3:GetObject"]` alike
+fn string_list(value: Option<&Value>) -> Result<Vec<String>, String> {
+    match value {
+        None => Ok(Vec::new()),
+        Some(Value::String(value)) => Ok(vec![value.clone()]),
+        Some(Value::Array(values)) => values
+            .iter()
+            .map(|value| {
+                value
+                    .as_str()
+                    .map(str::to_string)
+                    .ok_or_else(|| format!("expected a string, got {}", value))
+            })
+            .collect(),
+        Some(value) => Err(format!("expected a string or a list, got {}", value)),
+    }
+}
+
+/// `"*"`, `{"AWS": "arn:..."}` or `{"Service": ["ec2.amazonaws.com"]}` as `AWS:arn:...`
+fn principals(value: Option<&Value>) -> Result<Vec<String>, String> {
+    match value {
+        None => Ok(Vec::new()),
+        Some(Value::String(principal)) => Ok(vec![principal.clone()]),
+        Some(Value::Object(principals)) => {
+            let mut all = Vec::new();
+            for (kind, ids) in principals {
+                all.extend(
+                    string_list(Some(ids))?
+                        .into_iter()
+                        .map(|id| format!("{}:{}", kind, id)),
+                );
+            }
+            Ok(all)
+        }
+        Some(value) => Err(format!("expected a string or an object, got {}", value)),
+    }
+}
+
+/// IAM wildcard match, `*` for any characters and `?` for one, ignoring case like IAM
+/// does for actions
+fn wildcard_match(pattern: &str, value: &str) -> bool {
+    let pattern: Vec<char> = pattern.to_lowercase().chars().collect();
+    let value: Vec<char> = value.to_lowercase().chars().collect();
+    let (mut p, mut v) = (0, 0);
+    let (mut star, mut star_v) = (None, 0);
+    while v < value.len() {
+        if p < pattern.len() && (pattern[p] == '?' || pattern[p] == value[v]) {
+            p += 1;
+            v += 1;
+        } else if p < pattern.len() && pattern[p] == '*' {
+            star = Some(p);
+            star_v = v;
+            p += 1;
+        } else if let Some(star) = star {
+            p = star + 1;
+            star_v += 1;
+            v = star_v;
+        } else {
+            return false;
+        }
+    }
+    pattern[p..].iter().all(|c| *c == '*')
+}
+
+fn is_valid_action(action: &str) -> bool {
+    action == "*"
+        || action.split_once(':').is_some_and(|(service, name)| {
+            !service.is_empty()
+                && service
+                    .chars()
+                    .all(|c| c.is_ascii_lowercase() || c.is_ascii_digit() || c == '-')
+                && !name.is_empty()
+                && name
+                    .chars()
+                    .all(|c| c.is_ascii_alphanumeric() || c == '*' || c == '?')
+        })
+}
+
+struct Linter {
+    findings: Vec<PolicyFinding>,
+}
+
+impl Linter {
+    fn add(&mut self, severity: Severity, statement: Option<&str>, message: String) {
+        self.findings.push(PolicyFinding {
+            severity,
+            statement: statement.map(str::to_string),
+            message,
+        });
+    }
+
+    fn lint_statement(&mut self, label: &str, statement: &Value) -> Option<StatementSummary> {
+        let Some(fields) = statement.as_object() else {
+            self.add(
+                Severity::Error,
+                Some(label),
+                "Statement must be an object".to_string(),
+            );
+            return None;
+        };
+        let list = |linter: &mut Self, key: &str| match string_list(fields.get(key)) {
+            Ok(values) => values,
+            Err(e) => {
+                linter.add(Severity::Error, Some(label), format!("{}: {}", key, e));
+                Vec::new()
+            }
+        };
+        let actions = list(self, "Action");
+        let not_actions = list(self, "NotAction");
+        let resources = list(self, "Resource");
+        let not_resources = list(self, "NotResource");
+        let principals = match principals(fields.get("Principal")) {
+            Ok(principals) => principals,
+            Err(e) => {
+                self.add(Severity::Error, Some(label), format!("Principal: {}", e));
+                Vec::new()
+            }
+        };
+        let conditional = fields.get("Condition").is_some_and(|c| !c.is_null());
+
+        let effect = fields
+            .get("Effect")
+            .and_then(Value::as_str)
+            .unwrap_or_default()
+            .to_string();
+        if effect != "Allow" && effect != "Deny" {
+            self.add(
+                Severity::Error,
+                Some(label),
+                format!("Effect must be \"Allow\" or \"Deny\", got {:?}", effect),
+            );
+        }
+        match (actions.is_empty(), not_actions.is_empty()) {
+            (true, true) => self.add(
+                Severity::Error,
+                Some(label),
+                "Statement needs an Action or NotAction".to_string(),
+            ),
+            (false, false) => self.add(
+                Severity::Error,
+                Some(label),
+                "Statement cannot have both Action and NotAction".to_string(),
+            ),
+            _ => {}
+        }
+        if !resources.is_empty() && !not_resources.is_empty() {
+            self.add(
+                Severity::Error,
+                Some(label),
+                "Statement cannot have both Resource and NotResource".to_string(),
+            );
+        }
+        // Trust policies name a principal instead of a resource
+        if resources.is_empty() && not_resources.is_empty() && principals.is_empty() {
+            self.add(
+                Severity::Error,
+                Some(label),
+                "Identity policy statement needs a Resource or NotResource".to_string(),
+            );
+        }
+        for action in actions.iter().chain(&not_actions) {
+            if !is_valid_action(action) {
+                self.add(
+                    Severity::Error,
+                    Some(label),
+                    format!(
+                        "\"{}\" is not a valid action, expected service:Action",
+                        action
+                    ),
+                );
+            }
+        }
+
+        if effect == "Allow" {
+            self.lint_allow(
+                label,
+                &actions,
+                &not_actions,
+                &resources,
+                &not_resources,
+                &principals,
+                conditional,
+            );
+        } else if effect == "Deny" {
+            self.add(
+                Severity::Info,
+                Some(label),
+                "Deny statements override any Allow, including those of other policies".to_string(),
+            );
+        }
+
+        Some(StatementSummary {
+            sid: fields
+                .get("Sid")
+                .and_then(Value::as_str)
+                .map(str::to_string),
+            effect,
+            actions,
+            not_actions,
+            resources,
+            not_resources,
+            principals,
+            conditional,
+        })
+    }
+
+    #[allow(clippy::too_many_arguments)]
+    fn lint_allow(
+        &mut self,
+        label: &str,
+        actions: &[String],
+        not_actions: &[String],
+        resources: &[String],
+        not_resources: &[String],
+        principals: &[String],
+        conditional: bool,
+    ) {
+        let any_resource = resources.iter().any(|r| r == "*");
+        let every_action = actions.iter().any(|a| a == "*" || a == "*:*");
+        if every_action {
+            self.add(
+                Severity::Error,
+                Some(label),
+                if any_resource && !conditional {
+                    "Allows every action on every resource, i.e. full administrator access"
+                        .to_string()
+                } else {
+                    "Allows every action of every service".to_string()
+                },
+            );
+        }
+        for action in actions {
+            if let Some((service, "*")) = action.split_once(':') {
+                self.add(
+                    Severity::Warning,
+                    Some(label),
+                    format!(
+                        "\"{}\" allows every {} action, including deletes and permission changes",
+                        action, service
+                    ),
+                );
+            } else if action.contains(['*', '?']) && action != "*" {
+                self.add(
+                    Severity::Info,
+                    Some(label),
+                    format!("\"{}\" matches every action starting with that prefix, including ones AWS adds later", action),
+                );
+            }
+        }
+        if !not_actions.is_empty() {
+            self.add(
+                Severity::Warning,
+                Some(label),
+                "Allow with NotAction grants every action except the listed ones, including actions of other services".to_string(),
+            );
+        }
+        if !not_resources.is_empty() {
+            self.add(
+                Severity::Warning,
+                Some(label),
+                "Allow with NotResource grants access to every resource except the listed ones"
+                    .to_string(),
+            );
+        }
+
+        // Already reported when every action is allowed
+        let escalations: Vec<&str> = PRIVILEGE_ESCALATION_ACTIONS
+            .iter()
+            .copied()
+            .filter(|_| !every_action)
+            .filter(|sensitive| {
+                actions
+                    .iter()
+                    .any(|action| wildcard_match(action, sensitive))
+            })
+            .collect();
+        if !escalations.is_empty() {
+            self.add(
+                if any_resource {
+                    Severity::Warning
+                } else {
+                    Severity::Info
+                },
+                Some(label),
+                format!(
+                    "Allows {}, which can be used to escalate privileges{}",
+                    escalations.join(", "),
+                    if any_resource { " on any resource" } else { "" }
+                ),
+            );
+        }
+        if any_resource && !actions.is_empty() && !every_action && escalations.is_empty() {
+            self.add(
+                Severity::Info,
+                Some(label),
+                "Resource \"*\" applies the actions to every resource of the account".to_string(),
+            );
+        }
+
+        if principals.iter().any(|p| p == "*" || p == "AWS:*") {
+            self.add(
+                if conditional {
+                    Severity::Warning
+                } else {
+                    Severity::Error
+                },
+                Some(label),
+                if conditional {
+                    "Principal \"*\" allows anyone, only the Condition restricts who".to_string()
+                } else {
+                    "Principal \"*\" without a Condition makes the resource public".to_string()
+                },
+            );
+        }
+    }
+}
+
+/// Parse an IAM identity, resource or trust policy and report what it grants and the
+/// misconfigurations found, most severe first
+pub fn lint_iam_policy(policy: &str) -> Result<PolicyReport, String> {
+    let document: Value =
+        serde_json::from_str(policy).map_err(|e| format!("Policy is not valid JSON: {}", e))?;
+    let Some(fields) = document.as_object() else {
+        return Err("Policy must be a JSON object".to_string());
+    };
+
+    let mut linter = Linter {
+        findings: Vec::new(),
+    };
+    match fields.get("Version").and_then(Value::as_str) {
+        Some("2012-10-17") => {}
+        Some("2008-10-17") => linter.add(
+            Severity::Warning,
+            None,
+            "Version 2008-10-17 does not support policy variables such as ${aws:username}, use 2012-10-17".to_string(),
+        ),
+        Some(version) => linter.add(
+            Severity::Error,
+            None,
+            format!(
+                "Unknown Version \"{}\", expected one of {}",
+                version,
+                POLICY_VERSIONS.join(", ")
+            ),
+        ),
+        None => linter.add(
+            Severity::Warning,
+            None,
+            "Version is missing, so policy variables are not supported, add \"Version\": \"2012-10-17\"".to_string(),
+        ),
+    }
+
+    let statements = match fields.get("Statement") {
+        Some(Value::Array(statements)) => statements.clone(),
+        Some(statement @ Value::Object(_)) => vec![statement.clone()],
+        _ => return Err("Policy has no Statement".to_string()),
+    };
+
+    let mut sids = HashSet::new();
+    let mut summaries = Vec::new();
+    for (index, statement) in statements.iter().enumerate() {
+        let sid = statement.get("Sid").and_then(Value::as_str);
+        let label = match sid {
+            Some(sid) => format!("Statement {} ({})", index + 1, sid),
+            None => format!("Statement {}", index + 1),
+        };
+        if let Some(sid) = sid {
+            if !sids.insert(sid) {
+                linter.add(
+                    Severity::Error,
+                    Some(&label),
+                    format!("Sid \"{}\" is used by more than one statement", sid),
+                );
+            }
+        }
+        summaries.extend(linter.lint_statement(&label, statement));
+    }
+
+    let mut findings = linter.findings;
+    findings.sort_by(|a, b| b.severity.cmp(&a.severity));
+    Ok(PolicyReport {
+        statements: summaries,
+        findings,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_lint_iam_policy() {
+        let report = lint_iam_policy(
+            r#"{
+                "Version": "2012-10-17",
+                "Statement": [
+                    {"Sid": "Read", "Effect": "Allow", "Action": "s3:GetObject", "Resource": "arn:aws:s3:::logs/*"},
+                    {"Sid": "Admin", "Effect": "Allow", "Action": "*", "Resource": "*"},
+                    {"Sid": "Roles", "Effect": "Allow", "Action": "iam:Pass*", "Resource": "*"}
+                ]
+            }"#,
+        )
+        .unwrap();
+        assert_eq!(report.statements.len(), 3);
+        assert_eq!(report.findings[0].severity, Severity::Error);
+        assert!(
+            report.findings[0]
+                .message
+                .contains("full administrator access")
+        );
+        assert!(
+            report
+                .findings
+                .iter()
+                .any(|finding| finding.message.contains("iam:PassRole"))
+        );
+        assert!(
+            report
+                .findings
+                .iter()
+                .all(|finding| finding.statement.as_deref() != Some("Statement 1 (Read)"))
+        );
+
+        assert!(wildcard_match("s3:Get*", "s3:getobject"));
+        assert!(!wildcard_match("s3:Get*", "s3:PutObject"));
+        assert!(lint_iam_policy("{\"Version\": \"2012-10-17\"}").is_err());
+    }
+}
@@ -0,0 +1,142 @@
+use base64::{Engine, engine::general_purpose::STANDARD};
+use serde::Deserialize;
+use std::collections::{BTreeMap, HashMap};
+
+/// Placeholder standing in for one key of a Kubernetes secret
+pub fn k8s_secret_placeholder(namespace: &str, name: &str, key: &str) -> String {
+    format!("[K8S_SECRET:{}/{}/{}]", namespace, name, key)
+}
+
+/// Build the `kubectl get secret` command line. Arguments are restricted to the characters
+/// Kubernetes names can contain since the line is run through a shell.
+pub fn read_secret_command(
+    name: &str,
+    namespace: Option<&str>,
+    context: Option<&str>,
+) -> Result<String, String> {
+    let is_safe = |value: &str| {
+        !value.is_empty()
+            && value
+                .chars()
+                .all(|c| c.is_ascii_alphanumeric() || matches!(c, '-' | '.' | '_' | ':' | '@'))
+    };
+    for (field, value) in [
+        ("name", Some(name)),
+        ("namespace", namespace),
+        ("context", context),
+    ] {
+        if let Some(value) = value.filter(|value| !is_safe(value)) {
+            return Err(format!("Invalid {}: {}", field, value));
+        }
+    }
+
+    let mut command = "kubectl".to_string();
+    if let Some(context) = context {
+        command.push_str(&format!(" --context {}", context));
+    }
+    if let Some(namespace) = namespace {
+        command.push_str(&format!(" --namespace {}", namespace));
+    }
+    command.push_str(&format!(" get secret {} -o json", name));
+    Ok(command)
+}
+
+#[derive(Deserialize)]
+struct SecretMetadata {
+    name: String,
+    namespace: String,
+}
+
+#[derive(Deserialize)]
+struct SecretObject {
+    metadata: SecretMetadata,
+    #[serde(rename = "type", default)]
+    secret_type: String,
+    #[serde(default)]
+    data: BTreeMap<String, String>,
+}
+
+/// Keys of a secret as placeholders, the values only live in `redactions`
+#[derive(Debug, Default)]
+pub struct SecretPlaceholders {
+    pub secret_type: String,
+    /// Key to placeholder
+    pub placeholders: BTreeMap<String, String>,
+    /// Placeholder to value, to add to the session redaction map
+    pub redactions: HashMap<String, String>,
+    /// Keys whose values are not text, they cannot be restored in commands or files
+    pub binary_keys: Vec<String>,
+    /// Requested keys the secret does not have
+    pub missing_keys: Vec<String>,
+}
+
+/// Decode the `kubectl get secret -o json` output into placeholders, keeping only `keys`
+/// when given
+pub fn secret_placeholders(
+    json: &str,
+    keys: Option<&[String]>,
+) -> Result<SecretPlaceholders, String> {
+    let secret: SecretObject =
+        serde_json::from_str(json).map_err(|e| format!("Failed to parse the secret: {}", e))?;
+    let mut result = SecretPlaceholders {
+        secret_type: secret.secret_type,
+        ..Default::default()
+    };
+    if let Some(keys) = keys {
+        result.missing_keys = keys
+            .iter()
+            .filter(|key| !secret.data.contains_key(*key))
+            .cloned()
+            .collect();
+    }
+
+    for (key, encoded) in secret.data {
+        if keys.is_some_and(|keys| !keys.contains(&key)) {
+            continue;
+        }
+        let value = STANDARD
+            .decode(encoded.as_bytes())
+            .ok()
+            .and_then(|bytes| String::from_utf8(bytes).ok());
+        match value {
+            Some(value) => {
+                let placeholder =
+                    k8s_secret_placeholder(&secret.metadata.namespace, &secret.metadata.name, &key);
+                result.redactions.insert(placeholder.clone(), value);
+                result.placeholders.insert(key, placeholder);
+            }
+            None => result.binary_keys.push(key),
+        }
+    }
+    Ok(result)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_secret_placeholders() {
+        let json = r#"{
+            "metadata": {"name": "db", "namespace": "prod"},
+            "type": "Opaque",
+            "data": {"password": "aHVudGVyMg==", "cert": "/w==", "user": "YWRtaW4="}
+        }"#;
+        let secret = secret_placeholders(json, None).unwrap_or_default();
+        assert_eq!(
+            secret.placeholders.get("password"),
+            Some(&"[K8S_SECRET:prod/db/password]".to_string())
+        );
+        assert_eq!(
+            secret.redactions.get("[K8S_SECRET:prod/db/password]"),
+            Some(&"hunter2".to_string())
+        );
+        assert_eq!(secret.binary_keys, vec!["cert".to_string()]);
+
+        let keys = vec!["user".to_string(), "token".to_string()];
+        let secret = secret_placeholders(json, Some(&keys)).unwrap_or_default();
+        assert_eq!(secret.placeholders.len(), 1);
+        assert_eq!(secret.missing_keys, vec!["token".to_string()]);
+        assert!(read_secret_command("db; cat /etc/passwd", None, None).is_err());
+    }
+}
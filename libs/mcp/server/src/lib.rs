@@ -1,19 +1,56 @@
 use anyhow::Result;
+use axum::extract::{Request, State};
+use axum::http::{StatusCode, header};
+use axum::response::{IntoResponse, Response};
+use axum_server::tls_rustls::RustlsConfig;
+use rmcp::ServerHandler;
 use rmcp::transport::streamable_http_server::{
     StreamableHttpService, session::local::LocalSessionManager,
 };
+use std::collections::HashMap;
+use std::future::Future;
+use std::path::PathBuf;
+use std::pin::Pin;
+use std::sync::Arc;
+use tower_service::Service;
 
 use stakpak_api::ClientConfig;
 
+pub mod access;
+pub mod cloud_access;
 pub mod combined_tools;
+pub mod command_cache;
+pub mod command_history;
+pub mod command_policy;
+pub mod command_timeout;
+pub mod cron_explain;
+pub mod devcontainer;
+pub mod file_search;
+pub mod format_validate;
+pub mod iam_lint;
+pub mod k8s_secret;
 pub mod local_tools;
+pub mod output_upload;
+pub mod port_forward;
 pub mod remote_tools;
+pub mod sandbox;
 pub mod secret_manager;
+pub mod secret_write_guard;
+pub mod task_manager;
+pub mod terraform_preflight;
+pub mod test_generation;
 pub mod tool_descriptions;
+pub mod transaction;
+pub mod wait_for;
+pub mod workspace_context;
 
+pub use access::{AccessConfig, AccessRole, ReadOnlyTools, TokenAccess};
 pub use combined_tools::CombinedTools;
+pub use command_policy::CommandPolicySource;
+pub use devcontainer::DevContainer;
 pub use local_tools::LocalTools;
 pub use remote_tools::RemoteTools;
+pub use sandbox::SandboxProfile;
 
 #[derive(Clone, Debug, PartialEq, Eq, Hash)]
 pub enum ToolMode {
@@ -54,6 +91,23 @@ pub struct MCPServerConfig {
     pub bind_address: String,
     pub redact_secrets: bool,
     pub tool_mode: ToolMode,
+    /// Sandbox `run_command` children with the OS-native sandbox
+    pub sandbox: Option<SandboxProfile>,
+    /// Run `run_command` children inside this container instead of on the host
+    pub dev_container: Option<DevContainer>,
+    /// Commands `run_command` may spawn, checked before each of them
+    pub command_policy: Option<CommandPolicySource>,
+    /// Require bearer tokens and scope tools per token, see [`AccessConfig`]
+    pub access: Option<AccessConfig>,
+    /// Serve over HTTPS instead of plain HTTP
+    pub tls: Option<TlsConfig>,
+}
+
+/// Certificate chain and private key, both PEM encoded, the server terminates TLS with
+#[derive(Clone, Debug)]
+pub struct TlsConfig {
+    pub cert_path: PathBuf,
+    pub key_path: PathBuf,
 }
 
 /// Initialize gitleaks configuration if secret redaction is enabled
@@ -131,6 +185,51 @@ async fn create_shutdown_handler(shutdown_rx: Option<tokio::sync::broadcast::Rec
     }
 }
 
+/// Serve `router` on `bind_address` until shutdown, over TLS when `tls` is set
+async fn serve(
+    router: axum::Router,
+    bind_address: &str,
+    tls: Option<&TlsConfig>,
+    shutdown_rx: Option<tokio::sync::broadcast::Receiver<()>>,
+) -> Result<()> {
+    let Some(tls) = tls else {
+        let tcp_listener = tokio::net::TcpListener::bind(bind_address).await?;
+        axum::serve(tcp_listener, router)
+            .with_graceful_shutdown(create_shutdown_handler(shutdown_rx))
+            .await?;
+        return Ok(());
+    };
+
+    // Dependencies may compile in more than one crypto provider, so pick one explicitly
+    let _ = rustls::crypto::ring::default_provider().install_default();
+    let rustls_config = RustlsConfig::from_pem_file(&tls.cert_path, &tls.key_path)
+        .await
+        .map_err(|e| {
+            anyhow::anyhow!(
+                "Failed to load TLS certificate {} and key {}: {}",
+                tls.cert_path.display(),
+                tls.key_path.display(),
+                e
+            )
+        })?;
+    let address = tokio::net::lookup_host(bind_address)
+        .await?
+        .next()
+        .ok_or_else(|| anyhow::anyhow!("Cannot resolve bind address {}", bind_address))?;
+
+    let handle = axum_server::Handle::new();
+    let shutdown_handle = handle.clone();
+    tokio::spawn(async move {
+        create_shutdown_handler(shutdown_rx).await;
+        shutdown_handle.graceful_shutdown(None);
+    });
+    axum_server::bind_rustls(address, rustls_config)
+        .handle(handle)
+        .serve(router.into_make_service())
+        .await?;
+    Ok(())
+}
+
 /// npx @modelcontextprotocol/inspector cargo run mcp
 pub async fn start_server(
     config: MCPServerConfig,
@@ -138,18 +237,29 @@ pub async fn start_server(
 ) -> Result<()> {
     init_gitleaks_if_needed(config.redact_secrets).await;
 
+    if let Some(access) = config.access.clone() {
+        return start_server_with_access(config, access, shutdown_rx).await;
+    }
+
     match config.tool_mode {
         ToolMode::LocalOnly => {
             let service = StreamableHttpService::new(
-                move || LocalTools::new(config.redact_secrets),
+                move || {
+                    LocalTools::new(config.redact_secrets, config.sandbox.clone())
+                        .with_dev_container(config.dev_container.clone())
+                        .with_command_policy(config.command_policy.clone())
+                },
                 LocalSessionManager::default().into(),
                 Default::default(),
             );
             let router = axum::Router::new().nest_service("/mcp", service);
-            let tcp_listener = tokio::net::TcpListener::bind(config.bind_address).await?;
-            axum::serve(tcp_listener, router)
-                .with_graceful_shutdown(create_shutdown_handler(shutdown_rx))
-                .await?;
+            serve(
+                router,
+                &config.bind_address,
+                config.tls.as_ref(),
+                shutdown_rx,
+            )
+            .await?;
         }
         ToolMode::RemoteOnly => {
             let service = StreamableHttpService::new(
@@ -158,28 +268,163 @@ pub async fn start_server(
                 Default::default(),
             );
             let router = axum::Router::new().nest_service("/mcp", service);
-            let tcp_listener = tokio::net::TcpListener::bind(config.bind_address).await?;
-            axum::serve(tcp_listener, router)
-                .with_graceful_shutdown(create_shutdown_handler(shutdown_rx))
-                .await?;
+            serve(
+                router,
+                &config.bind_address,
+                config.tls.as_ref(),
+                shutdown_rx,
+            )
+            .await?;
         }
         ToolMode::Combined => {
             let service = StreamableHttpService::new(
-                move || CombinedTools::new(config.api.clone(), config.redact_secrets),
+                move || {
+                    CombinedTools::new(
+                        config.api.clone(),
+                        config.redact_secrets,
+                        config.sandbox.clone(),
+                    )
+                    .with_dev_container(config.dev_container.clone())
+                    .with_command_policy(config.command_policy.clone())
+                },
                 LocalSessionManager::default().into(),
                 Default::default(),
             );
             let router = axum::Router::new().nest_service("/mcp", service);
-            let tcp_listener = tokio::net::TcpListener::bind(config.bind_address).await?;
-            axum::serve(tcp_listener, router)
-                .with_graceful_shutdown(create_shutdown_handler(shutdown_rx))
-                .await?;
+            serve(
+                router,
+                &config.bind_address,
+                config.tls.as_ref(),
+                shutdown_rx,
+            )
+            .await?;
         }
     }
 
     Ok(())
 }
 
+type ScopedService =
+    Arc<dyn Fn(Request) -> Pin<Box<dyn Future<Output = Response> + Send>> + Send + Sync>;
+
+struct AccessState {
+    access: AccessConfig,
+    default_tool_mode: ToolMode,
+    services: HashMap<access::AccessScope, ScopedService>,
+}
+
+fn http_service<S: ServerHandler>(
+    factory: impl Fn() -> S + Send + Sync + 'static,
+) -> ScopedService {
+    let service = StreamableHttpService::new(
+        factory,
+        LocalSessionManager::default().into(),
+        Default::default(),
+    );
+    Arc::new(move |request| {
+        let mut service = service.clone();
+        Box::pin(async move {
+            match service.call(request).await {
+                Ok(response) => response.into_response(),
+                Err(_) => StatusCode::INTERNAL_SERVER_ERROR.into_response(),
+            }
+        })
+    })
+}
+
+fn scoped_service(config: &MCPServerConfig, scope: &access::AccessScope) -> ScopedService {
+    let api = config.api.clone();
+    let redact_secrets = config.redact_secrets;
+    let sandbox = config.sandbox.clone();
+    let dev_container = config.dev_container.clone();
+    let command_policy = config.command_policy.clone();
+    match (&scope.tool_mode, scope.role) {
+        (ToolMode::LocalOnly, AccessRole::Operator) => http_service(move || {
+            LocalTools::new(redact_secrets, sandbox.clone())
+                .with_dev_container(dev_container.clone())
+                .with_command_policy(command_policy.clone())
+        }),
+        (ToolMode::LocalOnly, AccessRole::Viewer) => http_service(move || {
+            ReadOnlyTools::new(
+                LocalTools::new(redact_secrets, sandbox.clone())
+                    .with_dev_container(dev_container.clone())
+                    .with_command_policy(command_policy.clone()),
+            )
+        }),
+        (ToolMode::RemoteOnly, AccessRole::Operator) => {
+            http_service(move || RemoteTools::new(api.clone(), redact_secrets))
+        }
+        (ToolMode::RemoteOnly, AccessRole::Viewer) => {
+            http_service(move || ReadOnlyTools::new(RemoteTools::new(api.clone(), redact_secrets)))
+        }
+        (ToolMode::Combined, AccessRole::Operator) => http_service(move || {
+            CombinedTools::new(api.clone(), redact_secrets, sandbox.clone())
+                .with_dev_container(dev_container.clone())
+                .with_command_policy(command_policy.clone())
+        }),
+        (ToolMode::Combined, AccessRole::Viewer) => http_service(move || {
+            ReadOnlyTools::new(
+                CombinedTools::new(api.clone(), redact_secrets, sandbox.clone())
+                    .with_dev_container(dev_container.clone())
+                    .with_command_policy(command_policy.clone()),
+            )
+        }),
+    }
+}
+
+/// Route each request to the tools granted to its bearer token
+async fn dispatch(State(state): State<Arc<AccessState>>, request: Request) -> Response {
+    let authorization = request
+        .headers()
+        .get(header::AUTHORIZATION)
+        .and_then(|value| value.to_str().ok());
+    let Some(token) = state.access.authorize(authorization) else {
+        return (StatusCode::UNAUTHORIZED, "Missing or invalid bearer token").into_response();
+    };
+
+    let scope = state.access.scope_of(token, &state.default_tool_mode);
+    tracing::debug!(
+        "Request from {} scoped to {} tools ({:?})",
+        token.name,
+        scope.tool_mode,
+        scope.role
+    );
+    match state.services.get(&scope) {
+        Some(service) => service(request).await,
+        None => StatusCode::FORBIDDEN.into_response(),
+    }
+}
+
+async fn start_server_with_access(
+    config: MCPServerConfig,
+    access: AccessConfig,
+    shutdown_rx: Option<tokio::sync::broadcast::Receiver<()>>,
+) -> Result<()> {
+    let services = access
+        .scopes(&config.tool_mode)
+        .into_iter()
+        .map(|scope| {
+            let service = scoped_service(&config, &scope);
+            (scope, service)
+        })
+        .collect();
+    let state = Arc::new(AccessState {
+        access,
+        default_tool_mode: config.tool_mode.clone(),
+        services,
+    });
+
+    let router =
+        axum::Router::new().nest_service("/mcp", axum::routing::any(dispatch).with_state(state));
+    serve(
+        router,
+        &config.bind_address,
+        config.tls.as_ref(),
+        shutdown_rx,
+    )
+    .await
+}
+
 /// Start server with local tools only (no API key required)
 pub async fn start_local_server(
     bind_address: String,
@@ -191,10 +436,16 @@ pub async fn start_local_server(
             api: ClientConfig {
                 api_key: None,
                 api_endpoint: "".to_string(),
+                fallback_endpoints: Vec::new(),
             },
             bind_address,
             redact_secrets,
             tool_mode: ToolMode::LocalOnly,
+            sandbox: None,
+            dev_container: None,
+            command_policy: None,
+            access: None,
+            tls: None,
         },
         shutdown_rx,
     )
@@ -214,6 +465,11 @@ pub async fn start_remote_server(
             bind_address,
             redact_secrets,
             tool_mode: ToolMode::RemoteOnly,
+            sandbox: None,
+            dev_container: None,
+            command_policy: None,
+            access: None,
+            tls: None,
         },
         shutdown_rx,
     )
@@ -233,6 +489,11 @@ pub async fn start_combined_server(
             bind_address,
             redact_secrets,
             tool_mode: ToolMode::Combined,
+            sandbox: None,
+            dev_container: None,
+            command_policy: None,
+            access: None,
+            tls: None,
         },
         shutdown_rx,
     )
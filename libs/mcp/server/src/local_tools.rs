@@ -4,30 +4,333 @@ use rmcp::{
 };
 
 use serde_json::json;
+use stakpak_api::{Client, ClientConfig};
+use stakpak_shared::command_result::CommandMetadata;
+use stakpak_shared::file_history::record_file_version;
 use stakpak_shared::local_store::LocalStore;
+use stakpak_shared::policy::{RiskLevel, classify_command};
+use stakpak_shared::session_env::load_session_env;
+use stakpak_shared::session_tmp::create_session_tmp_dir;
+use stakpak_shared::workspace::{
+    RootAccess, is_inside_workspace, resolve_path, resolve_writable_path, root_of,
+};
 use std::fs;
 
 use std::path::Path;
+use std::time::{Duration, Instant};
 use tokio::io::{AsyncBufReadExt, BufReader};
 use tokio::process::Command;
 use tracing::error;
 use uuid::Uuid;
 
+use crate::cloud_access::{self, CLOUD_PROVIDERS};
+use crate::command_cache::{
+    cache_output, cached_output, command_cache_enabled, invalidate_command_cache, is_cacheable,
+};
+use crate::command_history::condense_repeated_output;
+use crate::command_policy::CommandPolicySource;
+use crate::command_timeout::{command_timeout, kill_process_group, timeout_notice};
+use crate::cron_explain::explain_cron;
+use crate::devcontainer::DevContainer;
+use crate::file_search::{DEFAULT_MAX_RESULTS, SearchOptions, search_files};
+use crate::format_validate::{DocumentFormat, format_document};
+use crate::iam_lint::lint_iam_policy;
+use crate::k8s_secret::{read_secret_command, secret_placeholders};
+use crate::output_upload::{output_uploads_enabled, upload_output};
+use crate::port_forward::{port_forward_command, start_port_forward};
+use crate::sandbox::SandboxProfile;
 use crate::secret_manager::SecretManager;
+use crate::secret_write_guard::{
+    SecretWritePolicy, describe_secrets, new_secrets, secret_write_policy,
+};
+use crate::task_manager::TaskManager;
+use crate::terraform_preflight::{
+    format_terraform_warnings, remote_lock_warning, terraform_preflight,
+};
 use crate::tool_descriptions::*;
+use crate::transaction::ChangeSet;
+use crate::wait_for::{WaitCondition, wait_bounds};
+use crate::workspace_context::analyze_workspace;
 use stakpak_shared::models::integrations::openai::ToolCallResultProgress;
 
 /// Local tools that work without API access
 #[derive(Clone)]
 pub struct LocalTools {
     secret_manager: SecretManager,
+    sandbox: Option<SandboxProfile>,
+    /// Container commands run in instead of the host, see [`LocalTools::with_dev_container`]
+    dev_container: Option<DevContainer>,
+    /// Server-side allow and deny rules of every shell command the tools spawn
+    command_policy: Option<CommandPolicySource>,
+    tasks: TaskManager,
+    /// API used to upload outputs too long for the model, see [`output_uploads_enabled`]
+    upload_api: Option<ClientConfig>,
+    /// File edits staged by `begin_changes`, written by `commit_changes`
+    changes: ChangeSet,
+}
+
+/// Message of an error result, the text after its error code
+fn error_message(result: &CallToolResult) -> String {
+    result
+        .content
+        .iter()
+        .filter_map(|content| content.as_text())
+        .map(|text| text.text.as_str())
+        .next_back()
+        .unwrap_or_default()
+        .to_string()
 }
 
 #[tool(tool_box)]
 impl LocalTools {
-    pub fn new(redact_secrets: bool) -> Self {
+    pub fn new(redact_secrets: bool, sandbox: Option<SandboxProfile>) -> Self {
         Self {
             secret_manager: SecretManager::new(redact_secrets),
+            sandbox,
+            dev_container: None,
+            command_policy: None,
+            tasks: TaskManager::new(),
+            upload_api: None,
+            changes: ChangeSet::default(),
+        }
+    }
+
+    /// Upload long command outputs through `api_config` when output uploads are enabled
+    pub fn with_output_uploads(mut self, api_config: ClientConfig) -> Self {
+        self.upload_api = Some(api_config);
+        self
+    }
+
+    /// Run commands inside `dev_container` with `docker exec`. Files are still read and
+    /// written on the host, through the workspace mount.
+    pub fn with_dev_container(mut self, dev_container: Option<DevContainer>) -> Self {
+        self.dev_container = dev_container;
+        self
+    }
+
+    /// Refuse shell commands the policy does not allow, before anything is spawned. The
+    /// policy file is reloaded when it changes.
+    pub fn with_command_policy(mut self, command_policy: Option<CommandPolicySource>) -> Self {
+        self.command_policy = command_policy;
+        self
+    }
+
+    /// Refuse deleting or moving `path` while a transaction is open, since staged edits
+    /// cannot follow the file, or when it lies outside the workspace
+    fn check_movable(&self, path: &str) -> Option<CallToolResult> {
+        if self.changes.is_open() {
+            return Some(CallToolResult::error(vec![
+                Content::text("TRANSACTION_OPEN"),
+                Content::text(
+                    "Files cannot be deleted or moved while a transaction is open, commit or roll it back first",
+                ),
+            ]));
+        }
+        if !is_inside_workspace(Path::new(path)) {
+            return Some(CallToolResult::error(vec![
+                Content::text("OUTSIDE_WORKSPACE"),
+                Content::text(format!(
+                    "{} is not inside the working directory or a workspace root",
+                    path
+                )),
+            ]));
+        }
+        None
+    }
+
+    /// Write an edited file, or stage it while a transaction is open. `Ok(true)` when the
+    /// edit was staged.
+    fn write_file(
+        &self,
+        path: &str,
+        tool: &str,
+        before: Option<&str>,
+        after: &str,
+    ) -> std::io::Result<bool> {
+        if self.changes.stage(path, before, after) {
+            return Ok(true);
+        }
+        fs::write(path, after)?;
+        record_version(path, tool, before, after);
+        Ok(false)
+    }
+
+    /// Look for plaintext secrets an edit adds to `path`. Blocked writes get the error to
+    /// return instead of writing, allowed ones a warning to add to the result.
+    fn check_new_secrets(
+        &self,
+        path: &str,
+        before: Option<&str>,
+        after: &str,
+    ) -> Result<String, CallToolResult> {
+        let policy = secret_write_policy();
+        if policy == SecretWritePolicy::Off {
+            return Ok(String::new());
+        }
+        let findings = new_secrets(path, before, after);
+        if findings.is_empty() {
+            return Ok(String::new());
+        }
+        let locations = describe_secrets(path, &findings);
+        if policy == SecretWritePolicy::Block {
+            return Err(CallToolResult::error(vec![
+                Content::text("SECRET_IN_WRITE"),
+                Content::text(format!(
+                    "The file was not written, it would contain {} plaintext secret(s):\n{}\nReference them from a secret store or environment variable instead.",
+                    findings.len(),
+                    locations
+                )),
+            ]));
+        }
+        Ok(format!(
+            "\nWARNING: {} plaintext secret(s) were written to disk:\n{}\nMake sure the file is not committed, or reference the secrets from a secret store or environment variable instead.",
+            findings.len(),
+            locations
+        ))
+    }
+
+    /// Pass the variables set with `/env` to `command`, secret placeholders restored
+    fn apply_session_env(&self, command: &mut Command) {
+        for (name, var) in load_session_env() {
+            command.env(
+                name,
+                self.secret_manager.restore_secrets_in_string(&var.value),
+            );
+        }
+    }
+
+    /// The error to return when the server's command policy does not allow `command_line`
+    fn command_blocked(&self, command_line: &str) -> Option<CallToolResult> {
+        let reason = self.command_policy.as_ref()?.check(command_line)?;
+        Some(CallToolResult::error(vec![
+            Content::text("COMMAND_BLOCKED"),
+            Content::text(format!(
+                "The command was not run, blocked by the server's command policy: {}",
+                reason
+            )),
+        ]))
+    }
+
+    /// `sh -c command_line` in the dev container or the sandbox when there is one, run in
+    /// the current directory, with the session variables
+    fn shell_command(&self, command_line: &str) -> Result<Command, CallToolResult> {
+        let work_dir = std::env::current_dir().unwrap_or_default();
+        self.shell_command_in(command_line, &work_dir)
+    }
+
+    /// `sh -c command_line` run in `work_dir`, inside the dev container when there is one.
+    /// Every shell the tools spawn comes from here or [`LocalTools::host_command`], which
+    /// enforce the command policy.
+    fn shell_command_in(
+        &self,
+        command_line: &str,
+        work_dir: &Path,
+    ) -> Result<Command, CallToolResult> {
+        let Some(dev_container) = &self.dev_container else {
+            let mut command = self.host_command(command_line)?;
+            command.current_dir(work_dir);
+            return Ok(command);
+        };
+        if let Some(blocked) = self.command_blocked(command_line) {
+            return Err(blocked);
+        }
+        let session_env = load_session_env();
+        let mut command = dev_container
+            .command(
+                command_line,
+                work_dir,
+                session_env.keys().map(String::as_str),
+            )
+            .map_err(|e| {
+                CallToolResult::error(vec![Content::text("SANDBOX_ERROR"), Content::text(e)])
+            })?;
+        self.apply_session_env(&mut command);
+        Ok(command)
+    }
+
+    /// `sh -c command_line` on the host, in the sandbox when there is one, with the
+    /// session variables
+    fn host_command(&self, command_line: &str) -> Result<Command, CallToolResult> {
+        if let Some(blocked) = self.command_blocked(command_line) {
+            return Err(blocked);
+        }
+        let mut command = match &self.sandbox {
+            Some(sandbox) => {
+                let workspace = std::env::current_dir().unwrap_or_default();
+                sandbox.command(command_line, &workspace).map_err(|e| {
+                    CallToolResult::error(vec![
+                        Content::text("SANDBOX_ERROR"),
+                        Content::text(format!("Failed to sandbox command: {}", e)),
+                    ])
+                })?
+            }
+            None => {
+                let mut command = Command::new("sh");
+                command.arg("-c").arg(command_line);
+                command
+            }
+        };
+        self.apply_session_env(&mut command);
+        Ok(command)
+    }
+
+    /// Shell `run_command` children run in, reported in the command metadata
+    fn shell_description(&self) -> String {
+        match (&self.dev_container, &self.sandbox) {
+            (Some(dev_container), _) => format!("sh in {}", dev_container.name),
+            (None, Some(_)) => "sh (sandboxed)".to_string(),
+            (None, None) => "sh".to_string(),
+        }
+    }
+
+    /// Check a `wait_for` condition once, returning whether it holds and the last output
+    /// line, or the error to return when the probe may not run
+    async fn probe_condition(
+        &self,
+        probe: &str,
+        probe_timeout: Duration,
+    ) -> Result<(bool, String), CallToolResult> {
+        let mut command = self.shell_command(probe)?;
+        command
+            .stdout(std::process::Stdio::piped())
+            .stderr(std::process::Stdio::piped())
+            .kill_on_drop(true);
+        let output = match tokio::time::timeout(probe_timeout, command.output()).await {
+            Ok(Ok(output)) => output,
+            Ok(Err(e)) => return Ok((false, format!("Failed to run check: {}", e))),
+            Err(_) => return Ok((false, "Check timed out".to_string())),
+        };
+        let text = format!(
+            "{}{}",
+            String::from_utf8_lossy(&output.stdout),
+            String::from_utf8_lossy(&output.stderr)
+        );
+        let last_line = text
+            .lines()
+            .rev()
+            .find(|line| !line.trim().is_empty())
+            .unwrap_or_default()
+            .to_string();
+        Ok((output.status.success(), last_line))
+    }
+
+    /// Upload the full, redacted output of `command`, returning a note on how to search it
+    async fn upload_full_output(&self, command: &str, output: &str) -> Option<String> {
+        let api_config = self
+            .upload_api
+            .as_ref()
+            .filter(|_| output_uploads_enabled())?;
+        let client = Client::new(api_config).ok()?;
+        let redacted = self.secret_manager.redact_and_store_secrets(output, None);
+        match upload_output(&client, command, &redacted).await {
+            Ok(artifact_id) => Some(format!(
+                "The full output (secrets redacted) was uploaded as output artifact {}, use the search_output tool to look for lines in it.",
+                artifact_id
+            )),
+            Err(e) => {
+                error!("Failed to upload command output: {}", e);
+                None
+            }
         }
     }
 
@@ -41,6 +344,12 @@ impl LocalTools {
         #[tool(param)]
         #[schemars(description = WORK_DIR_PARAM_DESCRIPTION)]
         work_dir: Option<String>,
+        #[tool(param)]
+        #[schemars(description = CACHE_PARAM_DESCRIPTION)]
+        cache: Option<bool>,
+        #[tool(param)]
+        #[schemars(description = COMMAND_TIMEOUT_PARAM_DESCRIPTION)]
+        timeout: Option<u64>,
     ) -> Result<CallToolResult, McpError> {
         const MAX_LINES: usize = 300;
 
@@ -49,10 +358,84 @@ impl LocalTools {
         // Restore secrets in the command before execution
         let actual_command = self.secret_manager.restore_secrets_in_string(&command);
 
-        let mut child = Command::new("sh")
-            .arg("-c")
-            .arg(actual_command)
-            .current_dir(work_dir.unwrap_or(".".to_string()))
+        // Checked again when the shell is spawned, but already here so a blocked command
+        // never gets a cached output
+        if let Some(blocked) = self.command_blocked(&actual_command) {
+            return Ok(blocked);
+        }
+
+        let work_dir = match resolve_tool_path(&work_dir.unwrap_or(".".to_string()), false) {
+            Ok(work_dir) => work_dir,
+            Err(error) => return Ok(error),
+        };
+        if let Some(root) = root_of(Path::new(&work_dir)) {
+            if root.access == RootAccess::ReadOnly
+                && classify_command(&actual_command).level > RiskLevel::ReadOnly
+            {
+                return Ok(CallToolResult::error(vec![
+                    Content::text("READ_ONLY_ROOT"),
+                    Content::text(format!(
+                        "The command was not run, it may change files and {} is in the read-only workspace root '{}'",
+                        work_dir, root.name
+                    )),
+                ]));
+            }
+        }
+
+        let mut metadata = CommandMetadata {
+            cwd: Path::new(&work_dir)
+                .canonicalize()
+                .map(|path| path.to_string_lossy().to_string())
+                .unwrap_or_else(|_| work_dir.clone()),
+            exit_code: None,
+            duration_ms: 0,
+            shell: self.shell_description(),
+            truncated: false,
+            output_file: None,
+            cached: false,
+            timed_out: false,
+        };
+
+        let cacheable = is_cacheable(&actual_command);
+        if !cacheable {
+            // The command may change what the cached commands read
+            invalidate_command_cache();
+        }
+        let use_cache = cache.unwrap_or_else(command_cache_enabled) && cacheable;
+        if use_cache {
+            if let Some(output) = cached_output(&command_clone, &work_dir) {
+                metadata.exit_code = Some(0);
+                metadata.cached = true;
+                return Ok(CallToolResult::success(vec![
+                    Content::text(output),
+                    Content::text(metadata.to_tagged()),
+                ]));
+            }
+        }
+
+        let mut terraform_warnings = terraform_preflight(&actual_command, Path::new(&work_dir));
+        if terraform_warnings.iter().any(|warning| warning.blocking) {
+            return Ok(CallToolResult::error(vec![
+                Content::text("TERRAFORM_PREFLIGHT_FAILED"),
+                Content::text(format!(
+                    "The command was not run.\n{}",
+                    format_terraform_warnings(&terraform_warnings)
+                )),
+            ]));
+        }
+
+        let mut command = match self.shell_command_in(&actual_command, Path::new(&work_dir)) {
+            Ok(command) => command,
+            Err(error) => return Ok(error),
+        };
+
+        // Leading its own process group lets a timeout kill everything the command started
+        #[cfg(unix)]
+        command.process_group(0);
+
+        let timeout = command_timeout(timeout);
+        let started = Instant::now();
+        let mut child = command
             .stdout(std::process::Stdio::piped())
             .stderr(std::process::Stdio::piped())
             .spawn()
@@ -80,9 +463,17 @@ impl LocalTools {
         let mut result = String::new();
         let progress_id = Uuid::new_v4();
 
+        let deadline = tokio::time::sleep(timeout);
+        tokio::pin!(deadline);
+
         // Read from both streams concurrently
         loop {
             tokio::select! {
+                _ = &mut deadline => {
+                    metadata.timed_out = true;
+                    kill_process_group(&mut child);
+                    break;
+                }
                 Ok(n) = stderr_reader.read_line(&mut stderr_buf) => {
                     if n == 0 {
                         break;
@@ -128,24 +519,37 @@ impl LocalTools {
         }
 
         // Wait for the process to complete
-        let exit_code = child
-            .wait()
-            .await
-            .map_err(|e| {
-                error!("Failed to wait for command: {}", e);
-                McpError::internal_error(
-                    "Failed to wait for command",
-                    Some(json!({
-                        "command": command_clone,
-                        "error": e.to_string()
-                    })),
-                )
-            })?
-            .code()
-            .unwrap_or(-1);
+        let status = child.wait().await.map_err(|e| {
+            error!("Failed to wait for command: {}", e);
+            McpError::internal_error(
+                "Failed to wait for command",
+                Some(json!({
+                    "command": command_clone,
+                    "error": e.to_string()
+                })),
+            )
+        })?;
+        metadata.exit_code = status.code();
+        metadata.duration_ms = started.elapsed().as_millis() as u64;
+
+        if metadata.failed() {
+            terraform_warnings.extend(remote_lock_warning(&result));
+        }
+        if !terraform_warnings.is_empty() {
+            result.push_str(&format_terraform_warnings(&terraform_warnings));
+        }
 
-        if exit_code != 0 {
-            result.push_str(&format!("Command exited with code {}\n", exit_code));
+        if metadata.timed_out {
+            result.push_str(&timeout_notice(timeout));
+        } else {
+            // Polling the same command again only needs to show what changed. The output is
+            // kept on disk, so only its redacted form is recorded.
+            let redacted = self.secret_manager.redact_and_store_secrets(&result, None);
+            match condense_repeated_output(&command_clone, &work_dir, &redacted) {
+                Ok(Some(condensed)) => result = condensed,
+                Ok(None) => {}
+                Err(e) => error!("Failed to record command output history: {}", e),
+            }
         }
 
         let output_lines = result.lines().collect::<Vec<_>>();
@@ -156,20 +560,32 @@ impl LocalTools {
                 "command.output.{:06x}.txt",
                 rand::rng().random_range(0..=0xFFFFFF)
             );
+            // Kept in the session namespace, other sessions in the workspace write their own
             let output_file_path =
-                LocalStore::write_session_data(&output_file, &result).map_err(|e| {
-                    error!("Failed to write session data to {}: {}", output_file, e);
-                    McpError::internal_error(
-                        "Failed to write session data",
-                        Some(json!({ "error": e.to_string() })),
-                    )
-                })?;
+                LocalStore::session()
+                    .write(&output_file, &result)
+                    .map_err(|e| {
+                        error!("Failed to write session data to {}: {}", output_file, e);
+                        McpError::internal_error(
+                            "Failed to write session data",
+                            Some(json!({ "error": e.to_string() })),
+                        )
+                    })?;
+            metadata.truncated = true;
+            metadata.output_file = Some(output_file_path.clone());
+
+            let upload_note = self
+                .upload_full_output(&command_clone, &result)
+                .await
+                .map(|note| format!("{}\n", note))
+                .unwrap_or_default();
 
             format!(
-                "Showing the last {} / {} output lines. Full output saved to {}\n...\n{}",
+                "Showing the last {} / {} output lines. Full output saved to {}\n{}...\n{}",
                 MAX_LINES,
                 output_lines.len(),
                 output_file_path,
+                upload_note,
                 output_lines
                     .into_iter()
                     .rev()
@@ -183,14 +599,33 @@ impl LocalTools {
         };
 
         if result.is_empty() {
-            return Ok(CallToolResult::success(vec![Content::text("No output")]));
+            return Ok(CallToolResult::success(vec![
+                Content::text("No output"),
+                Content::text(metadata.to_tagged()),
+            ]));
         }
 
         let redacted_output = self.secret_manager.redact_and_store_secrets(&result, None);
 
-        Ok(CallToolResult::success(vec![Content::text(
-            &redacted_output,
-        )]))
+        // Failures are worth running again, only successful outputs are cached
+        if use_cache && !metadata.failed() {
+            if let Err(e) = cache_output(&command_clone, &work_dir, &redacted_output) {
+                error!("Failed to cache command output: {}", e);
+            }
+        }
+
+        if metadata.timed_out {
+            return Ok(CallToolResult::error(vec![
+                Content::text("TIMEOUT"),
+                Content::text(&redacted_output),
+                Content::text(metadata.to_tagged()),
+            ]));
+        }
+
+        Ok(CallToolResult::success(vec![
+            Content::text(&redacted_output),
+            Content::text(metadata.to_tagged()),
+        ]))
     }
 
     #[tool(description = VIEW_DESCRIPTION)]
@@ -205,9 +640,13 @@ impl LocalTools {
     ) -> Result<CallToolResult, McpError> {
         const MAX_LINES: usize = 300;
 
+        let path = match resolve_tool_path(&path, false) {
+            Ok(path) => path,
+            Err(error) => return Ok(error),
+        };
         let path_obj = Path::new(&path);
 
-        if !path_obj.exists() {
+        if !self.changes.exists(&path) {
             return Ok(CallToolResult::error(vec![
                 Content::text("FILE_NOT_FOUND"),
                 Content::text(format!("File or directory not found: {}", path)),
@@ -266,8 +705,8 @@ impl LocalTools {
                 ])),
             }
         } else {
-            // Read file contents
-            match fs::read_to_string(&path) {
+            // Read file contents, staged edits included
+            match self.changes.read(&path) {
                 Ok(content) => {
                     let result = if let Some([start, end]) = view_range {
                         let lines: Vec<&str> = content.lines().collect();
@@ -382,9 +821,15 @@ impl LocalTools {
         #[schemars(description = NEW_STR_PARAM_DESCRIPTION)]
         new_str: String,
     ) -> Result<CallToolResult, McpError> {
+        // Cached outputs of commands reading the files would be stale
+        invalidate_command_cache();
+        let path = match resolve_tool_path(&path, true) {
+            Ok(path) => path,
+            Err(error) => return Ok(error),
+        };
         let path_obj = Path::new(&path);
 
-        if !path_obj.exists() {
+        if !self.changes.exists(&path) {
             return Ok(CallToolResult::error(vec![
                 Content::text("FILE_NOT_FOUND"),
                 Content::text(format!("File not found: {}", path)),
@@ -402,7 +847,7 @@ impl LocalTools {
         let actual_old_str = self.secret_manager.restore_secrets_in_string(&old_str);
         let actual_new_str = self.secret_manager.restore_secrets_in_string(&new_str);
 
-        match fs::read_to_string(&path) {
+        match self.changes.read(&path) {
             Ok(content) => {
                 let matches: Vec<_> = content.match_indices(&actual_old_str).collect();
 
@@ -415,11 +860,20 @@ impl LocalTools {
                     ])),
                     1 => {
                         let new_content = content.replace(&actual_old_str, &actual_new_str);
-                        match fs::write(&path, new_content) {
-                            Ok(_) => Ok(CallToolResult::success(vec![Content::text(format!(
-                                "Successfully replaced text in {}",
-                                path
-                            ))])),
+                        let warning =
+                            match self.check_new_secrets(&path, Some(&content), &new_content) {
+                                Ok(warning) => warning,
+                                Err(blocked) => return Ok(blocked),
+                            };
+                        match self.write_file(&path, "str_replace", Some(&content), &new_content) {
+                            Ok(staged) => {
+                                Ok(CallToolResult::success(vec![Content::text(format!(
+                                    "Successfully replaced text in {}{}{}",
+                                    path,
+                                    staged_note(staged),
+                                    warning
+                                ))]))
+                            }
                             Err(e) => Ok(CallToolResult::error(vec![
                                 Content::text("WRITE_ERROR"),
                                 Content::text(format!("Cannot write to file: {}", e)),
@@ -452,17 +906,30 @@ impl LocalTools {
         #[schemars(description = FILE_TEXT_PARAM_DESCRIPTION)]
         file_text: String,
     ) -> Result<CallToolResult, McpError> {
+        // Cached outputs of commands reading the files would be stale
+        invalidate_command_cache();
+        let path = match resolve_tool_path(&path, true) {
+            Ok(path) => path,
+            Err(error) => return Ok(error),
+        };
         let path_obj = Path::new(&path);
 
-        if path_obj.exists() {
+        if self.changes.exists(&path) {
             return Ok(CallToolResult::error(vec![
                 Content::text("FILE_EXISTS"),
                 Content::text(format!("File already exists: {}", path)),
             ]));
         }
 
-        // Create parent directories if they don't exist
-        if let Some(parent) = path_obj.parent() {
+        // Restore secrets in the file content before writing
+        let actual_file_text = self.secret_manager.restore_secrets_in_string(&file_text);
+        let warning = match self.check_new_secrets(&path, None, &actual_file_text) {
+            Ok(warning) => warning,
+            Err(blocked) => return Ok(blocked),
+        };
+
+        // Create parent directories if they don't exist, commit_changes creates them for staged files
+        if let Some(parent) = path_obj.parent().filter(|_| !self.changes.is_open()) {
             if !parent.exists() {
                 if let Err(e) = fs::create_dir_all(parent) {
                     return Ok(CallToolResult::error(vec![
@@ -473,19 +940,14 @@ impl LocalTools {
             }
         }
 
-        // Restore secrets in the file content before writing
-        let actual_file_text = self.secret_manager.restore_secrets_in_string(&file_text);
-
-        match fs::write(&path, actual_file_text) {
-            Ok(_) => {
-                let lines = fs::read_to_string(&path)
-                    .map(|content| content.lines().count())
-                    .unwrap_or(0);
-                Ok(CallToolResult::success(vec![Content::text(format!(
-                    "Successfully created file {} with {} lines",
-                    path, lines
-                ))]))
-            }
+        match self.write_file(&path, "create", None, &actual_file_text) {
+            Ok(staged) => Ok(CallToolResult::success(vec![Content::text(format!(
+                "Successfully created file {} with {} lines{}{}",
+                path,
+                actual_file_text.lines().count(),
+                staged_note(staged),
+                warning
+            ))])),
             Err(e) => Ok(CallToolResult::error(vec![
                 Content::text("WRITE_ERROR"),
                 Content::text(format!("Cannot create file: {}", e)),
@@ -506,9 +968,15 @@ impl LocalTools {
         #[schemars(description = INSERT_TEXT_PARAM_DESCRIPTION)]
         new_str: String,
     ) -> Result<CallToolResult, McpError> {
+        // Cached outputs of commands reading the files would be stale
+        invalidate_command_cache();
+        let path = match resolve_tool_path(&path, true) {
+            Ok(path) => path,
+            Err(error) => return Ok(error),
+        };
         let path_obj = Path::new(&path);
 
-        if !path_obj.exists() {
+        if !self.changes.exists(&path) {
             return Ok(CallToolResult::error(vec![
                 Content::text("FILE_NOT_FOUND"),
                 Content::text(format!("File not found: {}", path)),
@@ -522,7 +990,7 @@ impl LocalTools {
             ]));
         }
 
-        match fs::read_to_string(&path) {
+        match self.changes.read(&path) {
             Ok(content) => {
                 let mut lines: Vec<&str> = content.lines().collect();
                 let insert_idx = if insert_line == 0 {
@@ -559,12 +1027,19 @@ impl LocalTools {
                     new_content
                 };
 
-                match fs::write(&path, final_content) {
-                    Ok(_) => Ok(CallToolResult::success(vec![Content::text(format!(
-                        "Successfully inserted {} lines at line {} in {}",
+                let warning = match self.check_new_secrets(&path, Some(&content), &final_content) {
+                    Ok(warning) => warning,
+                    Err(blocked) => return Ok(blocked),
+                };
+
+                match self.write_file(&path, "insert", Some(&content), &final_content) {
+                    Ok(staged) => Ok(CallToolResult::success(vec![Content::text(format!(
+                        "Successfully inserted {} lines at line {} in {}{}{}",
                         new_lines.len(),
                         insert_line,
-                        path
+                        path,
+                        staged_note(staged),
+                        warning
                     ))])),
                     Err(e) => Ok(CallToolResult::error(vec![
                         Content::text("WRITE_ERROR"),
@@ -578,6 +1053,804 @@ impl LocalTools {
             ])),
         }
     }
+
+    #[tool(description = DELETE_FILE_DESCRIPTION)]
+    pub fn delete_file(
+        &self,
+        #[tool(param)]
+        #[schemars(description = DELETE_PATH_PARAM_DESCRIPTION)]
+        path: String,
+        #[tool(param)]
+        #[schemars(description = RECURSIVE_PARAM_DESCRIPTION)]
+        recursive: Option<bool>,
+    ) -> Result<CallToolResult, McpError> {
+        // Cached outputs of commands reading the files would be stale
+        invalidate_command_cache();
+        let path = match resolve_tool_path(&path, true) {
+            Ok(path) => path,
+            Err(error) => return Ok(error),
+        };
+        if let Some(error) = self.check_movable(&path) {
+            return Ok(error);
+        }
+        let path_obj = Path::new(&path);
+        let Ok(metadata) = fs::symlink_metadata(path_obj) else {
+            return Ok(CallToolResult::error(vec![
+                Content::text("FILE_NOT_FOUND"),
+                Content::text(format!("File not found: {}", path)),
+            ]));
+        };
+
+        let result = if metadata.is_dir() {
+            if !recursive.unwrap_or(false) {
+                return Ok(CallToolResult::error(vec![
+                    Content::text("IS_DIRECTORY"),
+                    Content::text(format!(
+                        "{} is a directory, set recursive to true to delete it with everything in it",
+                        path
+                    )),
+                ]));
+            }
+            fs::remove_dir_all(path_obj)
+        } else {
+            // Keep text files recoverable from the file history
+            if let Ok(content) = fs::read_to_string(path_obj) {
+                record_version(&path, "delete_file", Some(&content), "");
+            }
+            fs::remove_file(path_obj)
+        };
+
+        match result {
+            Ok(()) => Ok(CallToolResult::success(vec![Content::text(format!(
+                "Successfully deleted {}",
+                path
+            ))])),
+            Err(e) => Ok(CallToolResult::error(vec![
+                Content::text("DELETE_ERROR"),
+                Content::text(format!("Cannot delete {}: {}", path, e)),
+            ])),
+        }
+    }
+
+    #[tool(description = MOVE_FILE_DESCRIPTION)]
+    pub fn move_file(
+        &self,
+        #[tool(param)]
+        #[schemars(description = SOURCE_PATH_PARAM_DESCRIPTION)]
+        source: String,
+        #[tool(param)]
+        #[schemars(description = DESTINATION_PATH_PARAM_DESCRIPTION)]
+        destination: String,
+        #[tool(param)]
+        #[schemars(description = OVERWRITE_PARAM_DESCRIPTION)]
+        overwrite: Option<bool>,
+    ) -> Result<CallToolResult, McpError> {
+        // Cached outputs of commands reading the files would be stale
+        invalidate_command_cache();
+        let source = match resolve_tool_path(&source, true) {
+            Ok(path) => path,
+            Err(error) => return Ok(error),
+        };
+        let destination = match resolve_tool_path(&destination, true) {
+            Ok(path) => path,
+            Err(error) => return Ok(error),
+        };
+        let source_obj = Path::new(&source);
+        let destination_obj = Path::new(&destination);
+
+        if fs::symlink_metadata(source_obj).is_err() {
+            return Ok(CallToolResult::error(vec![
+                Content::text("FILE_NOT_FOUND"),
+                Content::text(format!("File not found: {}", source)),
+            ]));
+        }
+        if let Some(error) = self
+            .check_movable(&source)
+            .or_else(|| self.check_movable(&destination))
+        {
+            return Ok(error);
+        }
+        if let Some(parent) = destination_obj.parent() {
+            if !parent.as_os_str().is_empty() && !parent.exists() {
+                if let Err(e) = fs::create_dir_all(parent) {
+                    return Ok(CallToolResult::error(vec![
+                        Content::text("CREATE_DIR_ERROR"),
+                        Content::text(format!("Cannot create parent directories: {}", e)),
+                    ]));
+                }
+            }
+        }
+        if destination_obj.exists() {
+            if destination_obj.is_dir() || !overwrite.unwrap_or(false) {
+                return Ok(CallToolResult::error(vec![
+                    Content::text("FILE_EXISTS"),
+                    Content::text(format!(
+                        "{} already exists, set overwrite to true to replace a file",
+                        destination
+                    )),
+                ]));
+            }
+            if let Ok(content) = fs::read_to_string(destination_obj) {
+                record_version(&destination, "move_file", Some(&content), "");
+            }
+        }
+
+        match move_path(source_obj, destination_obj) {
+            Ok(()) => Ok(CallToolResult::success(vec![Content::text(format!(
+                "Successfully moved {} to {}",
+                source, destination
+            ))])),
+            Err(e) => Ok(CallToolResult::error(vec![
+                Content::text("MOVE_ERROR"),
+                Content::text(format!("Cannot move {} to {}: {}", source, destination, e)),
+            ])),
+        }
+    }
+
+    #[tool(description = CHECK_CLOUD_ACCESS_DESCRIPTION)]
+    pub async fn check_cloud_access(
+        &self,
+        #[tool(param)]
+        #[schemars(description = CLOUD_PROVIDERS_PARAM_DESCRIPTION)]
+        providers: Option<Vec<String>>,
+    ) -> Result<CallToolResult, McpError> {
+        let providers: Vec<&str> = match &providers {
+            Some(providers) => {
+                if let Some(unknown) = providers
+                    .iter()
+                    .find(|p| !CLOUD_PROVIDERS.contains(&p.as_str()))
+                {
+                    return Ok(CallToolResult::error(vec![
+                        Content::text("INVALID_ARGUMENT"),
+                        Content::text(format!(
+                            "Unknown provider '{}', expected one of: {}",
+                            unknown,
+                            CLOUD_PROVIDERS.join(", ")
+                        )),
+                    ]));
+                }
+                providers.iter().map(|p| p.as_str()).collect()
+            }
+            None => CLOUD_PROVIDERS.to_vec(),
+        };
+
+        // The checks run like any other command, under the command policy
+        let shell = |command_line: &str| {
+            self.shell_command(command_line)
+                .map_err(|blocked| error_message(&blocked))
+        };
+        let identities = cloud_access::check_cloud_access(&providers, &shell).await;
+        match serde_json::to_string_pretty(&identities) {
+            Ok(json) => Ok(CallToolResult::success(vec![Content::text(
+                self.secret_manager.redact_and_store_secrets(&json, None),
+            )])),
+            Err(e) => Ok(CallToolResult::error(vec![
+                Content::text("SERIALIZATION_ERROR"),
+                Content::text(format!("Failed to serialize cloud identities: {}", e)),
+            ])),
+        }
+    }
+
+    #[tool(description = FORMAT_AND_VALIDATE_DESCRIPTION)]
+    pub fn format_and_validate(
+        &self,
+        #[tool(param)]
+        #[schemars(description = FORMAT_PARAM_DESCRIPTION)]
+        format: Option<String>,
+        #[tool(param)]
+        #[schemars(description = FORMAT_CONTENT_PARAM_DESCRIPTION)]
+        content: Option<String>,
+        #[tool(param)]
+        #[schemars(description = FORMAT_PATH_PARAM_DESCRIPTION)]
+        path: Option<String>,
+    ) -> Result<CallToolResult, McpError> {
+        let format = match format
+            .as_deref()
+            .map(str::parse::<DocumentFormat>)
+            .or_else(|| path.as_deref().and_then(DocumentFormat::from_path).map(Ok))
+        {
+            Some(Ok(format)) => format,
+            Some(Err(e)) => {
+                return Ok(CallToolResult::error(vec![
+                    Content::text("INVALID_ARGUMENT"),
+                    Content::text(e),
+                ]));
+            }
+            None => {
+                return Ok(CallToolResult::error(vec![
+                    Content::text("INVALID_ARGUMENT"),
+                    Content::text("Cannot tell the format, pass format as 'json', 'yaml' or 'hcl'"),
+                ]));
+            }
+        };
+
+        let content = match (content, &path) {
+            (Some(content), _) => content,
+            (None, Some(path)) => match resolve_path(path)
+                .and_then(|resolved| fs::read_to_string(resolved).map_err(|e| e.to_string()))
+            {
+                Ok(content) => content,
+                Err(e) => {
+                    return Ok(CallToolResult::error(vec![
+                        Content::text("READ_ERROR"),
+                        Content::text(format!("Failed to read file {}: {}", path, e)),
+                    ]));
+                }
+            },
+            (None, None) => {
+                return Ok(CallToolResult::error(vec![
+                    Content::text("INVALID_ARGUMENT"),
+                    Content::text("Either content or path is required"),
+                ]));
+            }
+        };
+
+        match format_document(&content, format) {
+            Ok(formatted) => Ok(CallToolResult::success(vec![Content::text(format!(
+                "Valid {}\n\n{}",
+                format,
+                self.secret_manager
+                    .redact_and_store_secrets(&formatted, path.as_deref())
+            ))])),
+            Err(e) => Ok(CallToolResult::error(vec![
+                Content::text("SYNTAX_ERROR"),
+                Content::text(format!(
+                    "Invalid {}{} at {}",
+                    format,
+                    path.as_ref()
+                        .map(|path| format!(" in {}", path))
+                        .unwrap_or_default(),
+                    e
+                )),
+            ])),
+        }
+    }
+
+    #[tool(description = KUBECTL_PORT_FORWARD_DESCRIPTION)]
+    pub async fn kubectl_port_forward(
+        &self,
+        #[tool(param)]
+        #[schemars(description = PORT_FORWARD_RESOURCE_PARAM_DESCRIPTION)]
+        resource: String,
+        #[tool(param)]
+        #[schemars(description = PORT_FORWARD_REMOTE_PORT_PARAM_DESCRIPTION)]
+        remote_port: u16,
+        #[tool(param)]
+        #[schemars(description = PORT_FORWARD_LOCAL_PORT_PARAM_DESCRIPTION)]
+        local_port: Option<u16>,
+        #[tool(param)]
+        #[schemars(description = NAMESPACE_PARAM_DESCRIPTION)]
+        namespace: Option<String>,
+        #[tool(param)]
+        #[schemars(description = KUBE_CONTEXT_PARAM_DESCRIPTION)]
+        context: Option<String>,
+    ) -> Result<CallToolResult, McpError> {
+        let command_line = match port_forward_command(
+            &resource,
+            remote_port,
+            local_port,
+            namespace.as_deref(),
+            context.as_deref(),
+        ) {
+            Ok(command_line) => command_line,
+            Err(e) => {
+                return Ok(CallToolResult::error(vec![
+                    Content::text("INVALID_ARGUMENT"),
+                    Content::text(e),
+                ]));
+            }
+        };
+
+        // Forward from the host, a port bound inside a dev container is not reachable here
+        let command = match self.host_command(&command_line) {
+            Ok(command) => command,
+            Err(error) => return Ok(error),
+        };
+
+        let (child, bound_port) = match start_port_forward(command).await {
+            Ok(started) => started,
+            Err(e) => {
+                return Ok(CallToolResult::error(vec![
+                    Content::text("PORT_FORWARD_FAILED"),
+                    Content::text(e),
+                ]));
+            }
+        };
+
+        let target = format!(
+            "{}{}:{}",
+            namespace.map(|ns| format!("{}/", ns)).unwrap_or_default(),
+            resource,
+            remote_port
+        );
+        let description = format!("127.0.0.1:{} -> {}", bound_port, target);
+        match self.tasks.add("port-forward", description.clone(), child) {
+            Ok(id) => Ok(CallToolResult::success(vec![Content::text(format!(
+                "Started port-forward {}: {}\nConnect to 127.0.0.1:{}. It keeps running in the background until stopped with stop_background_task or the session ends.",
+                id, description, bound_port
+            ))])),
+            Err(e) => Err(McpError::internal_error(
+                "Failed to track port-forward",
+                Some(json!({ "error": e })),
+            )),
+        }
+    }
+
+    #[tool(description = K8S_READ_SECRET_DESCRIPTION)]
+    pub async fn k8s_read_secret(
+        &self,
+        #[tool(param)]
+        #[schemars(description = K8S_SECRET_NAME_PARAM_DESCRIPTION)]
+        name: String,
+        #[tool(param)]
+        #[schemars(description = NAMESPACE_PARAM_DESCRIPTION)]
+        namespace: Option<String>,
+        #[tool(param)]
+        #[schemars(description = KUBE_CONTEXT_PARAM_DESCRIPTION)]
+        context: Option<String>,
+        #[tool(param)]
+        #[schemars(description = K8S_SECRET_KEYS_PARAM_DESCRIPTION)]
+        keys: Option<Vec<String>>,
+    ) -> Result<CallToolResult, McpError> {
+        let command_line =
+            match read_secret_command(&name, namespace.as_deref(), context.as_deref()) {
+                Ok(command_line) => command_line,
+                Err(e) => {
+                    return Ok(CallToolResult::error(vec![
+                        Content::text("INVALID_ARGUMENT"),
+                        Content::text(e),
+                    ]));
+                }
+            };
+        let mut command = match self.shell_command(&command_line) {
+            Ok(command) => command,
+            Err(error) => return Ok(error),
+        };
+        let output = match command.output().await {
+            Ok(output) => output,
+            Err(e) => {
+                return Ok(CallToolResult::error(vec![
+                    Content::text("KUBECTL_ERROR"),
+                    Content::text(format!("Failed to run kubectl: {}", e)),
+                ]));
+            }
+        };
+        if !output.status.success() {
+            let stderr = String::from_utf8_lossy(&output.stderr);
+            return Ok(CallToolResult::error(vec![
+                Content::text("KUBECTL_ERROR"),
+                Content::text(
+                    self.secret_manager
+                        .redact_and_store_secrets(stderr.trim(), None),
+                ),
+            ]));
+        }
+
+        // The decoded values go straight to the redaction map, never into the result, and
+        // are redacted from every later output that echoes them
+        let secret =
+            match secret_placeholders(&String::from_utf8_lossy(&output.stdout), keys.as_deref()) {
+                Ok(secret) => secret,
+                Err(e) => {
+                    return Ok(CallToolResult::error(vec![
+                        Content::text("KUBECTL_ERROR"),
+                        Content::text(e),
+                    ]));
+                }
+            };
+        self.secret_manager.add_value_redactions(&secret.redactions);
+
+        let mut result = format!("Secret {} ({})\n", name, secret.secret_type);
+        if secret.placeholders.is_empty() {
+            result.push_str("No readable keys\n");
+        }
+        for (key, placeholder) in &secret.placeholders {
+            result.push_str(&format!("  {}: {}\n", key, placeholder));
+        }
+        if !secret.binary_keys.is_empty() {
+            result.push_str(&format!(
+                "Binary keys, not available as placeholders: {}\n",
+                secret.binary_keys.join(", ")
+            ));
+        }
+        if !secret.missing_keys.is_empty() {
+            result.push_str(&format!(
+                "Keys not in the secret: {}\n",
+                secret.missing_keys.join(", ")
+            ));
+        }
+        result.push_str("Use the placeholders in commands and files, they are replaced with the values when the tools run.");
+        Ok(CallToolResult::success(vec![Content::text(result)]))
+    }
+
+    #[tool(description = WAIT_FOR_DESCRIPTION)]
+    #[allow(clippy::too_many_arguments)]
+    pub async fn wait_for(
+        &self,
+        peer: rmcp::Peer<RoleServer>,
+        #[tool(param)]
+        #[schemars(description = WAIT_COMMAND_PARAM_DESCRIPTION)]
+        command: Option<String>,
+        #[tool(param)]
+        #[schemars(description = WAIT_URL_PARAM_DESCRIPTION)]
+        url: Option<String>,
+        #[tool(param)]
+        #[schemars(description = WAIT_POD_PARAM_DESCRIPTION)]
+        pod: Option<String>,
+        #[tool(param)]
+        #[schemars(description = NAMESPACE_PARAM_DESCRIPTION)]
+        namespace: Option<String>,
+        #[tool(param)]
+        #[schemars(description = KUBE_CONTEXT_PARAM_DESCRIPTION)]
+        context: Option<String>,
+        #[tool(param)]
+        #[schemars(description = WAIT_TIMEOUT_PARAM_DESCRIPTION)]
+        timeout_seconds: Option<u64>,
+        #[tool(param)]
+        #[schemars(description = WAIT_INTERVAL_PARAM_DESCRIPTION)]
+        interval_seconds: Option<u64>,
+    ) -> Result<CallToolResult, McpError> {
+        let command =
+            command.map(|command| self.secret_manager.restore_secrets_in_string(&command));
+        let condition = match WaitCondition::from_args(command, url, pod, namespace, context) {
+            Ok(condition) => condition,
+            Err(e) => {
+                return Ok(CallToolResult::error(vec![
+                    Content::text("INVALID_ARGUMENT"),
+                    Content::text(e),
+                ]));
+            }
+        };
+        let (timeout, interval) = wait_bounds(timeout_seconds, interval_seconds);
+        let description = self
+            .secret_manager
+            .redact_and_store_secrets(&condition.describe(), None);
+
+        let started = Instant::now();
+        let progress_id = Uuid::new_v4();
+        let mut checks = 0;
+        loop {
+            checks += 1;
+            let remaining = timeout.saturating_sub(started.elapsed());
+            // kubectl and curl give up after an interval so progress keeps being reported,
+            // a slow command may use the rest of the wait but not more
+            let probe_timeout = remaining.max(Duration::from_secs(1));
+            let probe = condition.probe_command(interval.min(probe_timeout));
+            let (met, last_line) = match self.probe_condition(&probe, probe_timeout).await {
+                Ok(probed) => probed,
+                Err(error) => return Ok(error),
+            };
+            let last_line = self
+                .secret_manager
+                .redact_and_store_secrets(&last_line, None);
+            let elapsed = started.elapsed().as_secs();
+
+            if met {
+                return Ok(CallToolResult::success(vec![Content::text(format!(
+                    "Done waiting for {} after {}s ({} checks)\n{}",
+                    description, elapsed, checks, last_line
+                ))]));
+            }
+            if started.elapsed() >= timeout {
+                return Ok(CallToolResult::error(vec![
+                    Content::text("WAIT_TIMEOUT"),
+                    Content::text(format!(
+                        "Gave up waiting for {} after {}s ({} checks), last output:\n{}",
+                        description, elapsed, checks, last_line
+                    )),
+                ]));
+            }
+
+            let _ = peer
+                .notify_progress(ProgressNotificationParam {
+                    progress_token: ProgressToken(NumberOrString::Number(0)),
+                    progress: 50,
+                    total: Some(100),
+                    message: Some(
+                        serde_json::to_string(&ToolCallResultProgress {
+                            id: progress_id,
+                            message: format!(
+                                "Waiting for {} ({}s / {}s): {}\n",
+                                description,
+                                elapsed,
+                                timeout.as_secs(),
+                                last_line
+                            ),
+                        })
+                        .unwrap_or_default(),
+                    ),
+                })
+                .await;
+            tokio::time::sleep(interval.min(timeout.saturating_sub(started.elapsed()))).await;
+        }
+    }
+
+    #[tool(description = LIST_BACKGROUND_TASKS_DESCRIPTION)]
+    pub fn list_background_tasks(&self) -> Result<CallToolResult, McpError> {
+        let tasks = self.tasks.list().map_err(|e| {
+            McpError::internal_error(
+                "Failed to list background tasks",
+                Some(json!({ "error": e })),
+            )
+        })?;
+        if tasks.is_empty() {
+            return Ok(CallToolResult::success(vec![Content::text(
+                "No background tasks",
+            )]));
+        }
+        Ok(CallToolResult::success(vec![Content::text(
+            tasks.join("\n"),
+        )]))
+    }
+
+    #[tool(description = STOP_BACKGROUND_TASK_DESCRIPTION)]
+    pub async fn stop_background_task(
+        &self,
+        #[tool(param)]
+        #[schemars(description = BACKGROUND_TASK_ID_PARAM_DESCRIPTION)]
+        id: String,
+    ) -> Result<CallToolResult, McpError> {
+        match self.tasks.stop(&id).await {
+            Ok(task) => Ok(CallToolResult::success(vec![Content::text(format!(
+                "Stopped {} {}: {}",
+                task.kind, id, task.description
+            ))])),
+            Err(e) => Ok(CallToolResult::error(vec![
+                Content::text("TASK_NOT_STOPPED"),
+                Content::text(e),
+            ])),
+        }
+    }
+
+    #[tool(description = EXPLAIN_CRON_DESCRIPTION)]
+    pub fn explain_cron(
+        &self,
+        #[tool(param)]
+        #[schemars(description = CRON_EXPRESSION_PARAM_DESCRIPTION)]
+        expression: String,
+    ) -> Result<CallToolResult, McpError> {
+        let explanation = match explain_cron(&expression) {
+            Ok(explanation) => explanation,
+            Err(e) => {
+                return Ok(CallToolResult::error(vec![
+                    Content::text("INVALID_CRON_EXPRESSION"),
+                    Content::text(e),
+                ]));
+            }
+        };
+        match serde_json::to_string_pretty(&explanation) {
+            Ok(json) => Ok(CallToolResult::success(vec![Content::text(json)])),
+            Err(e) => Ok(CallToolResult::error(vec![
+                Content::text("SERIALIZATION_ERROR"),
+                Content::text(format!("Failed to serialize cron explanation: {}", e)),
+            ])),
+        }
+    }
+
+    #[tool(description = LINT_IAM_POLICY_DESCRIPTION)]
+    pub fn lint_iam_policy(
+        &self,
+        #[tool(param)]
+        #[schemars(description = IAM_POLICY_PARAM_DESCRIPTION)]
+        policy: Option<String>,
+        #[tool(param)]
+        #[schemars(description = IAM_POLICY_PATH_PARAM_DESCRIPTION)]
+        path: Option<String>,
+    ) -> Result<CallToolResult, McpError> {
+        let policy = match (policy, &path) {
+            (Some(policy), _) => policy,
+            (None, Some(path)) => match resolve_path(path)
+                .and_then(|resolved| fs::read_to_string(resolved).map_err(|e| e.to_string()))
+            {
+                Ok(policy) => policy,
+                Err(e) => {
+                    return Ok(CallToolResult::error(vec![
+                        Content::text("READ_ERROR"),
+                        Content::text(format!("Failed to read file {}: {}", path, e)),
+                    ]));
+                }
+            },
+            (None, None) => {
+                return Ok(CallToolResult::error(vec![
+                    Content::text("INVALID_ARGUMENT"),
+                    Content::text("Either policy or path is required"),
+                ]));
+            }
+        };
+
+        let report = match lint_iam_policy(&policy) {
+            Ok(report) => report,
+            Err(e) => {
+                return Ok(CallToolResult::error(vec![
+                    Content::text("INVALID_POLICY"),
+                    Content::text(e),
+                ]));
+            }
+        };
+        match serde_json::to_string_pretty(&report) {
+            Ok(json) => Ok(CallToolResult::success(vec![Content::text(json)])),
+            Err(e) => Ok(CallToolResult::error(vec![
+                Content::text("SERIALIZATION_ERROR"),
+                Content::text(format!("Failed to serialize policy report: {}", e)),
+            ])),
+        }
+    }
+
+    #[tool(description = BEGIN_CHANGES_DESCRIPTION)]
+    pub fn begin_changes(&self) -> Result<CallToolResult, McpError> {
+        match self.changes.begin() {
+            Ok(id) => Ok(CallToolResult::success(vec![Content::text(format!(
+                "Started transaction {}. Edits made with create, str_replace and insert are staged until commit_changes writes them all at once, or rollback_changes discards them.",
+                id
+            ))])),
+            Err(e) => Ok(CallToolResult::error(vec![
+                Content::text("TRANSACTION_OPEN"),
+                Content::text(e),
+            ])),
+        }
+    }
+
+    #[tool(description = COMMIT_CHANGES_DESCRIPTION)]
+    pub fn commit_changes(&self) -> Result<CallToolResult, McpError> {
+        // Cached outputs of commands reading the files would be stale
+        invalidate_command_cache();
+        match self.changes.commit() {
+            Ok(files) => {
+                for file in &files {
+                    record_version(
+                        &file.path,
+                        "commit_changes",
+                        file.original.as_deref(),
+                        &file.content,
+                    );
+                }
+                let paths: Vec<&str> = files.iter().map(|file| file.path.as_str()).collect();
+                Ok(CallToolResult::success(vec![Content::text(format!(
+                    "Committed {} files:\n{}",
+                    paths.len(),
+                    paths.join("\n")
+                ))]))
+            }
+            Err(e) => Ok(CallToolResult::error(vec![
+                Content::text("COMMIT_FAILED"),
+                Content::text(format!("{}. The transaction was rolled back.", e)),
+            ])),
+        }
+    }
+
+    #[tool(description = ROLLBACK_CHANGES_DESCRIPTION)]
+    pub fn rollback_changes(&self) -> Result<CallToolResult, McpError> {
+        // Cached outputs of commands reading the files would be stale
+        invalidate_command_cache();
+        match self.changes.rollback() {
+            Ok(paths) => Ok(CallToolResult::success(vec![Content::text(format!(
+                "Discarded staged edits of {} files",
+                paths.len()
+            ))])),
+            Err(e) => Ok(CallToolResult::error(vec![
+                Content::text("NO_TRANSACTION"),
+                Content::text(e),
+            ])),
+        }
+    }
+
+    #[tool(description = MKTEMP_SESSION_DESCRIPTION)]
+    pub fn mktemp_session(
+        &self,
+        #[tool(param)]
+        #[schemars(description = MKTEMP_PREFIX_PARAM_DESCRIPTION)]
+        prefix: Option<String>,
+    ) -> Result<CallToolResult, McpError> {
+        match create_session_tmp_dir(prefix.as_deref()) {
+            Ok(path) => Ok(CallToolResult::success(vec![Content::text(
+                path.display().to_string(),
+            )])),
+            Err(e) => Ok(CallToolResult::error(vec![
+                Content::text("MKTEMP_FAILED"),
+                Content::text(e),
+            ])),
+        }
+    }
+
+    #[tool(description = SEARCH_FILES_DESCRIPTION)]
+    pub async fn search_files(
+        &self,
+        #[tool(param)]
+        #[schemars(description = SEARCH_PATTERN_PARAM_DESCRIPTION)]
+        pattern: String,
+        #[tool(param)]
+        #[schemars(description = SEARCH_PATH_PARAM_DESCRIPTION)]
+        path: Option<String>,
+        #[tool(param)]
+        #[schemars(description = SEARCH_GLOBS_PARAM_DESCRIPTION)]
+        globs: Option<Vec<String>>,
+        #[tool(param)]
+        #[schemars(description = CASE_INSENSITIVE_PARAM_DESCRIPTION)]
+        case_insensitive: Option<bool>,
+        #[tool(param)]
+        #[schemars(description = MAX_RESULTS_PARAM_DESCRIPTION)]
+        max_results: Option<u32>,
+        #[tool(param)]
+        #[schemars(description = CONTEXT_LINES_PARAM_DESCRIPTION)]
+        context_lines: Option<u32>,
+    ) -> Result<CallToolResult, McpError> {
+        let root = match resolve_tool_path(&path.unwrap_or_else(|| ".".to_string()), false) {
+            Ok(root) => root,
+            Err(error) => return Ok(error),
+        };
+        if !Path::new(&root).exists() {
+            return Ok(CallToolResult::error(vec![
+                Content::text("FILE_NOT_FOUND"),
+                Content::text(format!("File or directory not found: {}", root)),
+            ]));
+        }
+
+        let options = SearchOptions {
+            pattern,
+            globs: globs.unwrap_or_default(),
+            case_insensitive: case_insensitive.unwrap_or(false),
+            max_results: max_results.map_or(DEFAULT_MAX_RESULTS, |max| max as usize),
+            context_lines: context_lines.unwrap_or(0) as usize,
+        };
+        let search = tokio::task::spawn_blocking(move || search_files(Path::new(&root), &options))
+            .await
+            .map_err(|e| format!("Search failed: {}", e))
+            .and_then(|result| result);
+        let results = match search {
+            Ok(results) => results,
+            Err(e) => {
+                return Ok(CallToolResult::error(vec![
+                    Content::text("SEARCH_FAILED"),
+                    Content::text(e),
+                ]));
+            }
+        };
+
+        match serde_json::to_string_pretty(&results) {
+            Ok(json) => Ok(CallToolResult::success(vec![Content::text(
+                self.secret_manager.redact_and_store_secrets(&json, None),
+            )])),
+            Err(e) => Ok(CallToolResult::error(vec![
+                Content::text("SERIALIZATION_ERROR"),
+                Content::text(format!("Failed to serialize search results: {}", e)),
+            ])),
+        }
+    }
+
+    #[tool(description = GET_WORKSPACE_CONTEXT_DESCRIPTION)]
+    pub async fn get_workspace_context(
+        &self,
+        #[tool(param)]
+        #[schemars(description = WORKSPACE_PATH_PARAM_DESCRIPTION)]
+        path: Option<String>,
+    ) -> Result<CallToolResult, McpError> {
+        let root = match resolve_tool_path(&path.unwrap_or_else(|| ".".to_string()), false) {
+            Ok(root) => root,
+            Err(error) => return Ok(error),
+        };
+        let root_path = Path::new(&root);
+        if !root_path.is_dir() {
+            return Ok(CallToolResult::error(vec![
+                Content::text("DIRECTORY_NOT_FOUND"),
+                Content::text(format!("Directory not found: {}", root)),
+            ]));
+        }
+
+        let root_path = root_path
+            .canonicalize()
+            .unwrap_or_else(|_| root_path.to_path_buf());
+        let context = analyze_workspace(&root_path).await;
+
+        match serde_json::to_string_pretty(&context) {
+            Ok(json) => Ok(CallToolResult::success(vec![Content::text(
+                self.secret_manager.redact_and_store_secrets(&json, None),
+            )])),
+            Err(e) => Ok(CallToolResult::error(vec![
+                Content::text("SERIALIZATION_ERROR"),
+                Content::text(format!("Failed to serialize workspace context: {}", e)),
+            ])),
+        }
+    }
 }
 
 #[tool(tool_box)]
@@ -602,3 +1875,259 @@ impl ServerHandler for LocalTools {
         Ok(self.get_info())
     }
 }
+
+/// Rename `source`, falling back to copying files across filesystems
+fn move_path(source: &Path, destination: &Path) -> std::io::Result<()> {
+    match fs::rename(source, destination) {
+        Err(e) if e.kind() == std::io::ErrorKind::CrossesDevices && source.is_file() => {
+            fs::copy(source, destination)?;
+            fs::remove_file(source)
+        }
+        result => result,
+    }
+}
+
+/// Told to the model after an edit that was only staged
+fn staged_note(staged: bool) -> &'static str {
+    if staged {
+        " (staged, written by commit_changes)"
+    } else {
+        ""
+    }
+}
+
+/// Resolve `root://<name>/...` paths against their workspace root, refusing paths in
+/// read-only roots when `write` is set
+fn resolve_tool_path(path: &str, write: bool) -> Result<String, CallToolResult> {
+    let resolved = if write {
+        resolve_writable_path(path)
+    } else {
+        resolve_path(path)
+    };
+    resolved
+        .map(|path| path.to_string_lossy().to_string())
+        .map_err(|e| CallToolResult::error(vec![Content::text("INVALID_PATH"), Content::text(e)]))
+}
+
+/// Keep the new version of an edited file in the session file history, failures are only
+/// logged so they never fail the edit itself
+fn record_version(path: &str, tool: &str, before: Option<&str>, after: &str) {
+    if let Err(e) = record_file_version(path, tool, before, after) {
+        error!("Failed to record file history of {}: {}", path, e);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use stakpak_shared::workspace::{WorkspaceRoot, set_workspace_roots, workspace_roots};
+    use std::path::PathBuf;
+
+    #[test]
+    fn test_command_policy_applies_to_every_shell() {
+        let path = std::env::temp_dir().join(format!(
+            "stakpak-command-policy-{}.toml",
+            std::process::id()
+        ));
+        std::fs::write(&path, "allow = [\"kubectl get *\"]\n").unwrap();
+        let tools = LocalTools::new(false, None)
+            .with_command_policy(Some(CommandPolicySource::load(path.clone()).unwrap()));
+
+        assert!(tools.shell_command("kubectl get pods").is_ok());
+        // wait_for probes and k8s_read_secret go through shell_command, port-forwards
+        // through host_command
+        assert!(
+            tools
+                .shell_command("curl -sf localhost:8080/health")
+                .is_err()
+        );
+        assert!(
+            tools
+                .host_command("kubectl port-forward svc/api 8080:80")
+                .is_err()
+        );
+
+        let _ = std::fs::remove_file(&path);
+    }
+
+    #[tokio::test]
+    async fn test_cloud_access_checks_follow_the_command_policy() {
+        let path = std::env::temp_dir().join(format!(
+            "stakpak-cloud-access-policy-{}.toml",
+            std::process::id()
+        ));
+        std::fs::write(&path, "deny = [\"aws *\"]\n").unwrap();
+        let tools = LocalTools::new(false, None)
+            .with_command_policy(Some(CommandPolicySource::load(path.clone()).unwrap()));
+
+        let result = tools
+            .check_cloud_access(Some(vec!["aws".to_string()]))
+            .await
+            .unwrap();
+        let _ = std::fs::remove_file(&path);
+
+        let text = result
+            .content
+            .first()
+            .and_then(|content| content.as_text())
+            .map(|text| text.text.clone())
+            .unwrap_or_default();
+        let identities: serde_json::Value = serde_json::from_str(&text).unwrap();
+        assert_eq!(identities[0]["status"], "not_authenticated");
+        assert!(
+            identities[0]["error"]
+                .as_str()
+                .is_some_and(|error| error.contains("blocked by the server's command policy"))
+        );
+    }
+
+    fn error_code(result: &CallToolResult) -> Option<String> {
+        if result.is_error != Some(true) {
+            return None;
+        }
+        result
+            .content
+            .first()
+            .and_then(|content| content.as_text())
+            .map(|text| text.text.clone())
+    }
+
+    /// A workspace root of its own holding `data/blob.bin`, which is not text so deleting
+    /// or replacing it records no file history
+    fn workspace(name: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("stakpak-{}-{}", name, std::process::id()));
+        std::fs::create_dir_all(dir.join("data")).unwrap();
+        let dir = dir.canonicalize().unwrap();
+        std::fs::write(dir.join("data/blob.bin"), [0xff, 0xfe, 0x00]).unwrap();
+        let mut roots = workspace_roots();
+        roots.push(WorkspaceRoot {
+            name: format!("{}-{}", name, std::process::id()),
+            path: dir.clone(),
+            access: RootAccess::ReadWrite,
+        });
+        set_workspace_roots(roots);
+        dir
+    }
+
+    fn path(path: PathBuf) -> String {
+        path.to_string_lossy().to_string()
+    }
+
+    #[test]
+    fn test_edits_invalidate_cached_reads() {
+        let dir = workspace("command-cache");
+        let file = path(dir.join("main.tf"));
+        let work_dir = path(dir.clone());
+        let tools = LocalTools::new(false, None);
+        let command = format!("cat {}", file);
+
+        cache_output(&command, &work_dir, "bucket = \"logs\"").unwrap();
+        assert!(cached_output(&command, &work_dir).is_some());
+
+        let result = tools
+            .create(file.clone(), "bucket = \"audit\"\n".to_string())
+            .unwrap();
+        assert_eq!(error_code(&result), None);
+        assert_eq!(cached_output(&command, &work_dir), None);
+    }
+
+    #[test]
+    fn test_delete_file() {
+        let dir = workspace("delete-file");
+        let outside = dir.with_extension("bin");
+        std::fs::write(&outside, [0xff]).unwrap();
+        let tools = LocalTools::new(false, None);
+
+        let result = tools.delete_file(path(dir.join("data")), None).unwrap();
+        assert_eq!(error_code(&result).as_deref(), Some("IS_DIRECTORY"));
+
+        let result = tools
+            .delete_file(path(dir.join("data/blob.bin")), None)
+            .unwrap();
+        assert_eq!(error_code(&result), None);
+        assert!(!dir.join("data/blob.bin").exists());
+        let result = tools
+            .delete_file(path(dir.join("data/blob.bin")), None)
+            .unwrap();
+        assert_eq!(error_code(&result).as_deref(), Some("FILE_NOT_FOUND"));
+
+        let result = tools
+            .delete_file(path(dir.join("data")), Some(true))
+            .unwrap();
+        assert_eq!(error_code(&result), None);
+        assert!(!dir.join("data").exists());
+
+        // Neither directly nor through `..`
+        for target in [
+            outside.clone(),
+            dir.join("..").join(outside.file_name().unwrap()),
+        ] {
+            let result = tools.delete_file(path(target), None).unwrap();
+            assert_eq!(error_code(&result).as_deref(), Some("OUTSIDE_WORKSPACE"));
+        }
+        assert!(outside.exists());
+        let result = tools.delete_file(path(dir.clone()), Some(true)).unwrap();
+        assert_eq!(error_code(&result).as_deref(), Some("OUTSIDE_WORKSPACE"));
+
+        let _ = std::fs::remove_file(&outside);
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn test_move_file() {
+        let dir = workspace("move-file");
+        let outside = dir.with_extension("bin");
+        let tools = LocalTools::new(false, None);
+        let destination = dir.join("moved/nested/blob.bin");
+
+        // Missing parent directories are created
+        let result = tools
+            .move_file(
+                path(dir.join("data/blob.bin")),
+                path(destination.clone()),
+                None,
+            )
+            .unwrap();
+        assert_eq!(error_code(&result), None);
+        assert!(destination.exists());
+        assert!(!dir.join("data/blob.bin").exists());
+
+        let result = tools
+            .move_file(
+                path(dir.join("data/blob.bin")),
+                path(dir.join("b.bin")),
+                None,
+            )
+            .unwrap();
+        assert_eq!(error_code(&result).as_deref(), Some("FILE_NOT_FOUND"));
+
+        // Existing files are only replaced when asked to
+        std::fs::write(dir.join("data/other.bin"), [0xff, 0x01]).unwrap();
+        let result = tools
+            .move_file(
+                path(dir.join("data/other.bin")),
+                path(destination.clone()),
+                None,
+            )
+            .unwrap();
+        assert_eq!(error_code(&result).as_deref(), Some("FILE_EXISTS"));
+        let result = tools
+            .move_file(
+                path(dir.join("data/other.bin")),
+                path(destination.clone()),
+                Some(true),
+            )
+            .unwrap();
+        assert_eq!(error_code(&result), None);
+        assert_eq!(std::fs::read(&destination).unwrap(), vec![0xff, 0x01]);
+
+        let result = tools
+            .move_file(path(destination.clone()), path(outside.clone()), None)
+            .unwrap();
+        assert_eq!(error_code(&result).as_deref(), Some("OUTSIDE_WORKSPACE"));
+        assert!(destination.exists());
+        assert!(!outside.exists());
+
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+}
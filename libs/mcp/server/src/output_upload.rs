@@ -0,0 +1,85 @@
+use stakpak_api::Client;
+use stakpak_api::models::{CreateOutputArtifactInput, OutputChunkInput};
+use std::sync::atomic::{AtomicBool, Ordering};
+use uuid::Uuid;
+
+/// Largest chunk sent in one request
+const CHUNK_BYTES: usize = 256 * 1024;
+
+static OUTPUT_UPLOADS: AtomicBool = AtomicBool::new(false);
+
+/// Upload command outputs too long for the model instead of only keeping their tail
+pub fn set_output_uploads(enabled: bool) {
+    OUTPUT_UPLOADS.store(enabled, Ordering::SeqCst);
+}
+
+pub fn output_uploads_enabled() -> bool {
+    OUTPUT_UPLOADS.load(Ordering::SeqCst)
+}
+
+/// Split `output` into chunks of at most `max_bytes` on line boundaries, returned with the
+/// line number of their first line. Lines longer than `max_bytes` get a chunk of their own.
+fn chunk_lines(output: &str, max_bytes: usize) -> Vec<(usize, String)> {
+    let mut chunks: Vec<(usize, String)> = Vec::new();
+    let mut current = String::new();
+    let mut first_line = 1;
+    for (index, line) in output.lines().enumerate() {
+        if !current.is_empty() && current.len() + line.len() + 1 > max_bytes {
+            chunks.push((first_line, std::mem::take(&mut current)));
+            first_line = index + 1;
+        }
+        current.push_str(line);
+        current.push('\n');
+    }
+    if !current.is_empty() {
+        chunks.push((first_line, current));
+    }
+    chunks
+}
+
+/// Upload an already redacted command output as a session artifact the `search_output`
+/// tool can query, returning its handle
+pub async fn upload_output(client: &Client, command: &str, output: &str) -> Result<Uuid, String> {
+    let chunks = chunk_lines(output, CHUNK_BYTES);
+    let artifact = client
+        .create_output_artifact(&CreateOutputArtifactInput {
+            command: command.to_string(),
+            total_lines: output.lines().count(),
+            chunk_count: chunks.len(),
+        })
+        .await?;
+    for (index, (first_line, content)) in chunks.into_iter().enumerate() {
+        client
+            .upload_output_chunk(
+                artifact.id,
+                &OutputChunkInput {
+                    index,
+                    first_line,
+                    content,
+                },
+            )
+            .await
+            .map_err(|e| format!("Failed to upload output chunk {}: {}", index, e))?;
+    }
+    Ok(artifact.id)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_chunk_lines() {
+        let chunks = chunk_lines("aaaa\nbbbb\ncccc\ndddddddddddd\ne\n", 10);
+        assert_eq!(
+            chunks,
+            vec![
+                (1, "aaaa\nbbbb\n".to_string()),
+                (3, "cccc\n".to_string()),
+                (4, "dddddddddddd\n".to_string()),
+                (5, "e\n".to_string()),
+            ]
+        );
+        assert!(chunk_lines("", 10).is_empty());
+    }
+}
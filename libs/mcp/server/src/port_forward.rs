@@ -0,0 +1,128 @@
+use std::time::Duration;
+use tokio::io::{AsyncBufReadExt, AsyncReadExt, BufReader};
+use tokio::process::{Child, Command};
+
+/// How long kubectl gets to resolve the target and bind the local port
+const STARTUP_TIMEOUT: Duration = Duration::from_secs(20);
+
+/// Build the `kubectl port-forward` command line. Arguments are restricted to the
+/// characters Kubernetes names can contain since the line is run through a shell.
+pub fn port_forward_command(
+    resource: &str,
+    remote_port: u16,
+    local_port: Option<u16>,
+    namespace: Option<&str>,
+    context: Option<&str>,
+) -> Result<String, String> {
+    let is_safe = |value: &str| {
+        !value.is_empty()
+            && value.chars().all(|c| {
+                c.is_ascii_alphanumeric() || matches!(c, '-' | '.' | '_' | '/' | ':' | '@')
+            })
+    };
+    for (name, value) in [
+        ("resource", Some(resource)),
+        ("namespace", namespace),
+        ("context", context),
+    ] {
+        if let Some(value) = value.filter(|value| !is_safe(value)) {
+            return Err(format!("Invalid {}: {}", name, value));
+        }
+    }
+
+    let mut command = "kubectl port-forward --address 127.0.0.1".to_string();
+    if let Some(context) = context {
+        command.push_str(&format!(" --context {}", context));
+    }
+    if let Some(namespace) = namespace {
+        command.push_str(&format!(" --namespace {}", namespace));
+    }
+    // An empty local port lets kubectl pick a free one
+    command.push_str(&format!(
+        " {} {}:{}",
+        resource,
+        local_port.map(|port| port.to_string()).unwrap_or_default(),
+        remote_port
+    ));
+    Ok(command)
+}
+
+/// Local port from kubectl's "Forwarding from 127.0.0.1:54321 -> 8080" line
+pub fn parse_forwarded_port(line: &str) -> Option<u16> {
+    line.strip_prefix("Forwarding from ")?
+        .split(" -> ")
+        .next()?
+        .rsplit(':')
+        .next()?
+        .parse()
+        .ok()
+}
+
+/// Spawn the port-forward and wait until kubectl reports the bound local port
+pub async fn start_port_forward(mut command: Command) -> Result<(Child, u16), String> {
+    let mut child = command
+        .stdout(std::process::Stdio::piped())
+        .stderr(std::process::Stdio::piped())
+        .kill_on_drop(true)
+        .spawn()
+        .map_err(|e| format!("Failed to run kubectl: {}", e))?;
+
+    let (Some(stdout), Some(mut stderr)) = (child.stdout.take(), child.stderr.take()) else {
+        return Err("Failed to capture kubectl output".to_string());
+    };
+    let mut lines = BufReader::new(stdout).lines();
+
+    let local_port = tokio::time::timeout(STARTUP_TIMEOUT, async {
+        while let Ok(Some(line)) = lines.next_line().await {
+            if let Some(port) = parse_forwarded_port(&line) {
+                return Some(port);
+            }
+        }
+        None
+    })
+    .await;
+
+    let local_port = match local_port {
+        Ok(Some(port)) => port,
+        Ok(None) => {
+            let mut error = String::new();
+            let _ = tokio::time::timeout(Duration::from_secs(2), stderr.read_to_string(&mut error))
+                .await;
+            return Err(format!("kubectl port-forward failed: {}", error.trim()));
+        }
+        Err(_) => {
+            return Err(format!(
+                "kubectl port-forward did not report a local port within {}s",
+                STARTUP_TIMEOUT.as_secs()
+            ));
+        }
+    };
+
+    // Keep draining the output so kubectl never blocks on a full pipe
+    tokio::spawn(async move { while let Ok(Some(_)) = lines.next_line().await {} });
+    tokio::spawn(async move {
+        let _ = tokio::io::copy(&mut stderr, &mut tokio::io::sink()).await;
+    });
+
+    Ok((child, local_port))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_port_forward_command_and_output() {
+        assert_eq!(
+            port_forward_command("svc/api", 8080, None, Some("payments"), None).unwrap(),
+            "kubectl port-forward --address 127.0.0.1 --namespace payments svc/api :8080"
+        );
+        assert!(port_forward_command("svc/api; rm -rf /", 8080, None, None, None).is_err());
+
+        assert_eq!(
+            parse_forwarded_port("Forwarding from 127.0.0.1:54321 -> 8080"),
+            Some(54321)
+        );
+        assert_eq!(parse_forwarded_port("Handling connection for 54321"), None);
+    }
+}
@@ -6,6 +6,10 @@ use serde::{Deserialize, Serialize};
 use serde_json::json;
 use stakpak_api::models::SimpleDocument;
 use stakpak_api::{Client, ClientConfig, GenerationResult, ToolsCallParams};
+use stakpak_shared::models::integrations::openai::ToolCallResultProgress;
+use stakpak_shared::uri::uri_to_relative_path;
+use tokio::sync::mpsc;
+use uuid::Uuid;
 
 use std::fs;
 use std::io::Write;
@@ -13,6 +17,7 @@ use std::path::Path;
 use tracing::{error, warn};
 
 use crate::secret_manager::SecretManager;
+use crate::test_generation::{TestFramework, collect_config_files, test_generation_prompt};
 use crate::tool_descriptions::*;
 
 /// Remote tools that require API access
@@ -48,6 +53,32 @@ impl std::fmt::Display for Provisioner {
     }
 }
 
+/// Forward progress updates of a remote tool call to the MCP client, where they are shown
+/// like streamed command output
+fn forward_progress(peer: rmcp::Peer<RoleServer>) -> mpsc::Sender<String> {
+    let (tx, mut rx) = mpsc::channel::<String>(32);
+    let progress_id = Uuid::new_v4();
+    tokio::spawn(async move {
+        while let Some(message) = rx.recv().await {
+            let _ = peer
+                .notify_progress(ProgressNotificationParam {
+                    progress_token: ProgressToken(NumberOrString::Number(0)),
+                    progress: 50,
+                    total: Some(100),
+                    message: Some(
+                        serde_json::to_string(&ToolCallResultProgress {
+                            id: progress_id,
+                            message,
+                        })
+                        .unwrap_or_default(),
+                    ),
+                })
+                .await;
+        }
+    });
+    tx
+}
+
 #[tool(tool_box)]
 impl RemoteTools {
     pub fn new(api_config: ClientConfig, redact_secrets: bool) -> Self {
@@ -60,6 +91,7 @@ impl RemoteTools {
     #[tool(description = GENERATE_CODE_DESCRIPTION)]
     pub async fn generate_code(
         &self,
+        peer: rmcp::Peer<RoleServer>,
         #[tool(param)]
         #[schemars(description = GENERATE_PROMPT_PARAM_DESCRIPTION)]
         prompt: String,
@@ -72,6 +104,25 @@ impl RemoteTools {
         #[tool(param)]
         #[schemars(description = CONTEXT_PARAM_DESCRIPTION)]
         context: Option<Vec<String>>,
+    ) -> Result<CallToolResult, McpError> {
+        self.generate_code_with_progress(
+            prompt,
+            provisioner,
+            save_files,
+            context,
+            Some(forward_progress(peer)),
+        )
+        .await
+    }
+
+    /// Generate code, sending what the generation has produced so far to `progress`
+    pub async fn generate_code_with_progress(
+        &self,
+        prompt: String,
+        provisioner: Provisioner,
+        save_files: Option<bool>,
+        context: Option<Vec<String>>,
+        progress: Option<mpsc::Sender<String>>,
     ) -> Result<CallToolResult, McpError> {
         let client = Client::new(&self.api_config).map_err(|e| {
             error!("Failed to create client: {}", e);
@@ -120,15 +171,18 @@ impl RemoteTools {
         };
 
         let response = match client
-            .call_mcp_tool(&ToolsCallParams {
-                name: "generate_code".to_string(),
-                arguments: json!({
-                    "prompt": prompt,
-                    "provisioner": provisioner.to_string(),
-                    "context": context_documents,
-                    "output_format": output_format,
-                }),
-            })
+            .call_mcp_tool_with_progress(
+                &ToolsCallParams {
+                    name: "generate_code".to_string(),
+                    arguments: json!({
+                        "prompt": prompt,
+                        "provisioner": provisioner.to_string(),
+                        "context": context_documents,
+                        "output_format": output_format,
+                    }),
+                },
+                progress,
+            )
             .await
         {
             Ok(response) => response,
@@ -168,11 +222,7 @@ impl RemoteTools {
             let mut failed_edits = Vec::new();
 
             for edit in generation_result.edits.unwrap_or_default() {
-                let file_path = Path::new(
-                    edit.document_uri
-                        .strip_prefix("file:///")
-                        .unwrap_or(&edit.document_uri),
-                );
+                let file_path = Path::new(uri_to_relative_path(&edit.document_uri));
 
                 // Create parent directories if they don't exist
                 if let Some(parent) = file_path.parent() {
@@ -323,6 +373,64 @@ impl RemoteTools {
         }
     }
 
+    #[tool(description = GENERATE_TESTS_DESCRIPTION)]
+    pub async fn generate_tests(
+        &self,
+        peer: rmcp::Peer<RoleServer>,
+        #[tool(param)]
+        #[schemars(description = TEST_TARGET_PATHS_PARAM_DESCRIPTION)]
+        paths: Vec<String>,
+        #[tool(param)]
+        #[schemars(description = TEST_FRAMEWORK_PARAM_DESCRIPTION)]
+        framework: TestFramework,
+        #[tool(param)]
+        #[schemars(description = TEST_INSTRUCTIONS_PARAM_DESCRIPTION)]
+        instructions: Option<String>,
+        #[tool(param)]
+        #[schemars(description = SAVE_FILES_PARAM_DESCRIPTION)]
+        save_files: Option<bool>,
+    ) -> Result<CallToolResult, McpError> {
+        self.generate_tests_with_progress(
+            paths,
+            framework,
+            instructions,
+            save_files,
+            Some(forward_progress(peer)),
+        )
+        .await
+    }
+
+    pub async fn generate_tests_with_progress(
+        &self,
+        paths: Vec<String>,
+        framework: TestFramework,
+        instructions: Option<String>,
+        save_files: Option<bool>,
+        progress: Option<mpsc::Sender<String>>,
+    ) -> Result<CallToolResult, McpError> {
+        let files = collect_config_files(&paths, framework);
+        if files.is_empty() {
+            return Ok(CallToolResult::error(vec![
+                Content::text("NO_CONFIG_FILES"),
+                Content::text(format!(
+                    "No {} configuration files found in: {}",
+                    framework.provisioner(),
+                    paths.join(", ")
+                )),
+            ]));
+        }
+
+        let prompt = test_generation_prompt(framework, &files, instructions.as_deref());
+        self.generate_code_with_progress(
+            prompt,
+            framework.provisioner(),
+            save_files,
+            Some(files),
+            progress,
+        )
+        .await
+    }
+
     #[tool(description = SMART_SEARCH_CODE_DESCRIPTION)]
     pub async fn smart_search_code(
         &self,
@@ -362,6 +470,50 @@ impl RemoteTools {
 
         Ok(CallToolResult::success(response))
     }
+
+    #[tool(description = SEARCH_OUTPUT_DESCRIPTION)]
+    pub async fn search_output(
+        &self,
+        #[tool(param)]
+        #[schemars(description = OUTPUT_ARTIFACT_ID_PARAM_DESCRIPTION)]
+        artifact_id: String,
+        #[tool(param)]
+        #[schemars(description = OUTPUT_QUERY_PARAM_DESCRIPTION)]
+        query: String,
+        #[tool(param)]
+        #[schemars(description = OUTPUT_CONTEXT_LINES_PARAM_DESCRIPTION)]
+        context_lines: Option<u32>,
+    ) -> Result<CallToolResult, McpError> {
+        let client = Client::new(&self.api_config).map_err(|e| {
+            error!("Failed to create client: {}", e);
+            McpError::internal_error(
+                "Failed to create client",
+                Some(json!({ "error": e.to_string() })),
+            )
+        })?;
+
+        let response = match client
+            .call_mcp_tool(&ToolsCallParams {
+                name: "search_output".to_string(),
+                arguments: json!({
+                    "artifact_id": artifact_id,
+                    "query": query,
+                    "context_lines": context_lines,
+                }),
+            })
+            .await
+        {
+            Ok(response) => response,
+            Err(e) => {
+                return Ok(CallToolResult::error(vec![
+                    Content::text("SEARCH_OUTPUT_ERROR"),
+                    Content::text(format!("Failed to search output {}: {}", artifact_id, e)),
+                ]));
+            }
+        };
+
+        Ok(CallToolResult::success(response))
+    }
 }
 
 #[tool(tool_box)]
@@ -385,3 +537,65 @@ impl ServerHandler for RemoteTools {
         Ok(self.get_info())
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn remote_tools() -> RemoteTools {
+        RemoteTools::new(
+            ClientConfig {
+                api_key: None,
+                api_endpoint: "http://localhost:1".to_string(),
+                fallback_endpoints: Vec::new(),
+            },
+            false,
+        )
+    }
+
+    #[tokio::test]
+    async fn test_generate_tests_without_config_files() {
+        let dir = std::env::temp_dir().join(format!("stakpak-remote-tools-{}", Uuid::new_v4()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let (progress_tx, mut progress_rx) = mpsc::channel(8);
+
+        let result = remote_tools()
+            .generate_tests_with_progress(
+                vec![dir.to_string_lossy().to_string()],
+                TestFramework::TerraformTest,
+                None,
+                None,
+                Some(progress_tx),
+            )
+            .await
+            .unwrap();
+        std::fs::remove_dir_all(&dir).unwrap();
+
+        assert_eq!(result.is_error, Some(true));
+        assert_eq!(
+            result
+                .content
+                .first()
+                .and_then(|content| content.as_text())
+                .map(|text| text.text.as_str()),
+            Some("NO_CONFIG_FILES")
+        );
+        // Nothing was generated, so no progress was reported
+        assert!(progress_rx.recv().await.is_none());
+    }
+
+    #[tokio::test]
+    async fn test_generate_code_requires_an_api_key() {
+        let (progress_tx, _progress_rx) = mpsc::channel(8);
+        let result = remote_tools()
+            .generate_code_with_progress(
+                "an S3 bucket".to_string(),
+                Provisioner::Terraform,
+                None,
+                None,
+                Some(progress_tx),
+            )
+            .await;
+        assert!(result.is_err());
+    }
+}
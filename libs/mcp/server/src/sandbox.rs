@@ -0,0 +1,515 @@
+use serde::{Deserialize, Serialize};
+use std::path::{Path, PathBuf};
+use tokio::process::Command;
+
+/// OS-native sandbox applied to `run_command` children: writes are confined to the
+/// workspace, temp directories and `writable_paths`, and network access is denied
+/// unless `allow_network` is set. Uses sandbox-exec on macOS and Landlock + seccomp
+/// on Linux.
+#[derive(Serialize, Deserialize, Clone, Debug, Default, PartialEq)]
+pub struct SandboxProfile {
+    /// Allow sandboxed commands to open network sockets
+    #[serde(default)]
+    pub allow_network: bool,
+    /// Paths commands may write to besides the workspace and temp directories
+    #[serde(default)]
+    pub writable_paths: Vec<String>,
+}
+
+impl SandboxProfile {
+    /// Directories commands may write to, canonicalized since both sandboxes match
+    /// on resolved paths (e.g. /tmp is /private/tmp on macOS)
+    #[cfg(any(target_os = "macos", target_os = "linux"))]
+    fn writable_dirs(&self, workspace: &Path) -> Vec<PathBuf> {
+        std::iter::once(workspace.to_path_buf())
+            .chain(std::iter::once(std::env::temp_dir()))
+            .chain(std::iter::once(PathBuf::from("/tmp")))
+            .chain(
+                self.writable_paths
+                    .iter()
+                    .map(|path| match path.strip_prefix("~/") {
+                        Some(rest) => {
+                            PathBuf::from(std::env::var("HOME").unwrap_or_default()).join(rest)
+                        }
+                        None => PathBuf::from(path),
+                    }),
+            )
+            .filter_map(|path| path.canonicalize().ok())
+            .collect()
+    }
+
+    /// Build the `sh -c <command>` invocation wrapped in the platform sandbox
+    pub fn command(&self, command: &str, workspace: &Path) -> Result<Command, String> {
+        #[cfg(target_os = "macos")]
+        {
+            let mut cmd = Command::new("/usr/bin/sandbox-exec");
+            cmd.arg("-p")
+                .arg(macos::profile(self, workspace))
+                .arg("sh")
+                .arg("-c")
+                .arg(command);
+            Ok(cmd)
+        }
+
+        #[cfg(target_os = "linux")]
+        {
+            let restrictions = linux::Restrictions::new(self, workspace)?;
+            let mut cmd = Command::new("sh");
+            cmd.arg("-c").arg(command);
+            // SAFETY: the closure only issues raw syscalls on data prepared before
+            // forking, it does not allocate or take locks in the child
+            unsafe {
+                cmd.pre_exec(move || restrictions.apply());
+            }
+            Ok(cmd)
+        }
+
+        #[cfg(not(any(target_os = "macos", target_os = "linux")))]
+        {
+            let _ = (self, command, workspace);
+            Err("Sandboxed commands are only supported on macOS and Linux".to_string())
+        }
+    }
+}
+
+#[cfg(target_os = "macos")]
+mod macos {
+    use super::SandboxProfile;
+    use std::path::Path;
+
+    fn quote(path: &Path) -> String {
+        format!(
+            "\"{}\"",
+            path.display()
+                .to_string()
+                .replace('\\', "\\\\")
+                .replace('"', "\\\"")
+        )
+    }
+
+    /// Seatbelt profile allowing everything except writes outside the writable
+    /// directories and, unless allowed, IP networking
+    pub fn profile(sandbox: &SandboxProfile, workspace: &Path) -> String {
+        let writable = sandbox
+            .writable_dirs(workspace)
+            .iter()
+            .map(|dir| format!("(subpath {})", quote(dir)))
+            .collect::<Vec<_>>()
+            .join(" ");
+
+        let mut profile = format!(
+            "(version 1)\n(allow default)\n(deny file-write*)\n(allow file-write* {} (subpath \"/private/var/folders\") (subpath \"/dev\"))\n",
+            writable
+        );
+        if !sandbox.allow_network {
+            profile
+                .push_str("(deny network-outbound (remote ip))\n(deny network-bind (local ip))\n");
+        }
+        profile
+    }
+}
+
+#[cfg(target_os = "linux")]
+mod linux {
+    use super::SandboxProfile;
+    use std::ffi::CString;
+    use std::io;
+    use std::os::unix::ffi::OsStrExt;
+    use std::path::Path;
+
+    const SYS_LANDLOCK_CREATE_RULESET: libc::c_long = 444;
+    const SYS_LANDLOCK_ADD_RULE: libc::c_long = 445;
+    const SYS_LANDLOCK_RESTRICT_SELF: libc::c_long = 446;
+    const LANDLOCK_CREATE_RULESET_VERSION: u32 = 1 << 0;
+    const LANDLOCK_RULE_PATH_BENEATH: libc::c_int = 1;
+
+    const ACCESS_FS_WRITE_FILE: u64 = 1 << 1;
+    const ACCESS_FS_REMOVE_DIR: u64 = 1 << 4;
+    const ACCESS_FS_REMOVE_FILE: u64 = 1 << 5;
+    const ACCESS_FS_MAKE_CHAR: u64 = 1 << 6;
+    const ACCESS_FS_MAKE_DIR: u64 = 1 << 7;
+    const ACCESS_FS_MAKE_REG: u64 = 1 << 8;
+    const ACCESS_FS_MAKE_SOCK: u64 = 1 << 9;
+    const ACCESS_FS_MAKE_FIFO: u64 = 1 << 10;
+    const ACCESS_FS_MAKE_BLOCK: u64 = 1 << 11;
+    const ACCESS_FS_MAKE_SYM: u64 = 1 << 12;
+    const ACCESS_FS_REFER: u64 = 1 << 13;
+    const ACCESS_FS_TRUNCATE: u64 = 1 << 14;
+    const ACCESS_NET_BIND_TCP: u64 = 1 << 0;
+    const ACCESS_NET_CONNECT_TCP: u64 = 1 << 1;
+
+    #[cfg(target_arch = "x86_64")]
+    const AUDIT_ARCH: u32 = 0xC000_003E;
+    #[cfg(target_arch = "aarch64")]
+    const AUDIT_ARCH: u32 = 0xC000_00B7;
+
+    // Classic BPF opcodes used by the seccomp filter
+    const BPF_LD_W_ABS: u16 = 0x20;
+    const BPF_JMP_JEQ_K: u16 = 0x15;
+    const BPF_JMP_JGE_K: u16 = 0x35;
+    const BPF_RET_K: u16 = 0x06;
+    /// Set in the numbers of x32 syscalls, which pass the x86_64 arch check
+    const X32_SYSCALL_BIT: u32 = 0x4000_0000;
+    const SECCOMP_RET_ALLOW: u32 = 0x7fff_0000;
+    const SECCOMP_RET_ERRNO: u32 = 0x0005_0000;
+    const SECCOMP_RET_KILL_PROCESS: u32 = 0x8000_0000;
+    // Offsets into struct seccomp_data
+    const SECCOMP_DATA_NR: u32 = 0;
+    const SECCOMP_DATA_ARCH: u32 = 4;
+    const SECCOMP_DATA_ARG0: u32 = 16;
+
+    /// Older kernels accept the larger struct as long as the fields they don't know are zero
+    #[repr(C)]
+    struct RulesetAttr {
+        handled_access_fs: u64,
+        handled_access_net: u64,
+    }
+
+    #[repr(C, packed)]
+    struct PathBeneathAttr {
+        allowed_access: u64,
+        parent_fd: i32,
+    }
+
+    /// Everything needed to restrict the child, prepared before forking
+    pub struct Restrictions {
+        handled_access_fs: u64,
+        handled_access_net: u64,
+        writable_dirs: Vec<CString>,
+        seccomp_filter: Option<Vec<libc::sock_filter>>,
+    }
+
+    fn bpf(code: u16, jt: u8, jf: u8, k: u32) -> libc::sock_filter {
+        libc::sock_filter { code, jt, jf, k }
+    }
+
+    /// Seccomp filter failing with EACCES every socket(2) and socketpair(2) that is not
+    /// AF_UNIX, io_uring (its socket operations never reach the filter) and x32 syscalls.
+    /// connect(2) takes the address behind a pointer seccomp can't read, only sockets
+    /// that could be created are left to connect
+    #[cfg(any(target_arch = "x86_64", target_arch = "aarch64"))]
+    pub(super) fn deny_network_filter() -> Result<Vec<libc::sock_filter>, String> {
+        let errno = SECCOMP_RET_ERRNO | libc::EACCES as u32;
+        Ok(vec![
+            bpf(BPF_LD_W_ABS, 0, 0, SECCOMP_DATA_ARCH),
+            bpf(BPF_JMP_JEQ_K, 1, 0, AUDIT_ARCH),
+            bpf(BPF_RET_K, 0, 0, SECCOMP_RET_KILL_PROCESS),
+            bpf(BPF_LD_W_ABS, 0, 0, SECCOMP_DATA_NR),
+            bpf(BPF_JMP_JGE_K, 5, 0, X32_SYSCALL_BIT),
+            bpf(BPF_JMP_JEQ_K, 4, 0, libc::SYS_io_uring_setup as u32),
+            bpf(BPF_JMP_JEQ_K, 1, 0, libc::SYS_socket as u32),
+            bpf(BPF_JMP_JEQ_K, 0, 3, libc::SYS_socketpair as u32),
+            bpf(BPF_LD_W_ABS, 0, 0, SECCOMP_DATA_ARG0),
+            bpf(BPF_JMP_JEQ_K, 1, 0, libc::AF_UNIX as u32),
+            bpf(BPF_RET_K, 0, 0, errno),
+            bpf(BPF_RET_K, 0, 0, SECCOMP_RET_ALLOW),
+        ])
+    }
+
+    #[cfg(not(any(target_arch = "x86_64", target_arch = "aarch64")))]
+    fn deny_network_filter() -> Result<Vec<libc::sock_filter>, String> {
+        Err("Denying network access in the sandbox is only supported on x86_64 and aarch64".into())
+    }
+
+    fn landlock_abi() -> Result<libc::c_long, String> {
+        // SAFETY: querying the ABI version takes no pointers
+        let abi = unsafe {
+            libc::syscall(
+                SYS_LANDLOCK_CREATE_RULESET,
+                std::ptr::null::<RulesetAttr>(),
+                0usize,
+                LANDLOCK_CREATE_RULESET_VERSION,
+            )
+        };
+        if abi < 1 {
+            return Err(format!(
+                "Landlock is not available on this kernel ({}), cannot sandbox commands",
+                io::Error::last_os_error()
+            ));
+        }
+        Ok(abi)
+    }
+
+    impl Restrictions {
+        pub fn new(sandbox: &SandboxProfile, workspace: &Path) -> Result<Self, String> {
+            let abi = landlock_abi()?;
+            let mut handled_access_fs = ACCESS_FS_WRITE_FILE
+                | ACCESS_FS_REMOVE_DIR
+                | ACCESS_FS_REMOVE_FILE
+                | ACCESS_FS_MAKE_CHAR
+                | ACCESS_FS_MAKE_DIR
+                | ACCESS_FS_MAKE_REG
+                | ACCESS_FS_MAKE_SOCK
+                | ACCESS_FS_MAKE_FIFO
+                | ACCESS_FS_MAKE_BLOCK
+                | ACCESS_FS_MAKE_SYM;
+            if abi >= 2 {
+                handled_access_fs |= ACCESS_FS_REFER;
+            }
+            if abi >= 3 {
+                handled_access_fs |= ACCESS_FS_TRUNCATE;
+            }
+            // With no rule allowing them, TCP sockets inherited from the parent can't
+            // connect or bind either
+            let handled_access_net = if abi >= 4 && !sandbox.allow_network {
+                ACCESS_NET_BIND_TCP | ACCESS_NET_CONNECT_TCP
+            } else {
+                0
+            };
+
+            let mut writable_dirs = sandbox.writable_dirs(workspace);
+            // Let commands redirect to /dev/null and friends
+            writable_dirs.push("/dev".into());
+            let writable_dirs = writable_dirs
+                .iter()
+                .map(|dir| CString::new(dir.as_os_str().as_bytes()).map_err(|e| e.to_string()))
+                .collect::<Result<Vec<_>, _>>()?;
+
+            let seccomp_filter = if sandbox.allow_network {
+                None
+            } else {
+                Some(deny_network_filter()?)
+            };
+
+            Ok(Self {
+                handled_access_fs,
+                handled_access_net,
+                writable_dirs,
+                seccomp_filter,
+            })
+        }
+
+        /// Restrict the calling process, runs in the forked child right before exec
+        pub fn apply(&self) -> io::Result<()> {
+            // SAFETY: all pointers passed below point at data owned by `self` or the
+            // stack and outlive the syscalls
+            unsafe {
+                if libc::prctl(libc::PR_SET_NO_NEW_PRIVS, 1, 0, 0, 0) != 0 {
+                    return Err(io::Error::last_os_error());
+                }
+
+                let attr = RulesetAttr {
+                    handled_access_fs: self.handled_access_fs,
+                    handled_access_net: self.handled_access_net,
+                };
+                let ruleset_fd = libc::syscall(
+                    SYS_LANDLOCK_CREATE_RULESET,
+                    &attr as *const RulesetAttr,
+                    std::mem::size_of::<RulesetAttr>(),
+                    0u32,
+                ) as libc::c_int;
+                if ruleset_fd < 0 {
+                    return Err(io::Error::last_os_error());
+                }
+
+                for dir in &self.writable_dirs {
+                    let parent_fd = libc::open(dir.as_ptr(), libc::O_PATH | libc::O_CLOEXEC);
+                    if parent_fd < 0 {
+                        continue;
+                    }
+                    let rule = PathBeneathAttr {
+                        allowed_access: self.handled_access_fs,
+                        parent_fd,
+                    };
+                    let added = libc::syscall(
+                        SYS_LANDLOCK_ADD_RULE,
+                        ruleset_fd,
+                        LANDLOCK_RULE_PATH_BENEATH,
+                        &rule as *const PathBeneathAttr,
+                        0u32,
+                    );
+                    libc::close(parent_fd);
+                    if added != 0 {
+                        let error = io::Error::last_os_error();
+                        libc::close(ruleset_fd);
+                        return Err(error);
+                    }
+                }
+
+                let restricted = libc::syscall(SYS_LANDLOCK_RESTRICT_SELF, ruleset_fd, 0u32);
+                libc::close(ruleset_fd);
+                if restricted != 0 {
+                    return Err(io::Error::last_os_error());
+                }
+
+                if let Some(filter) = &self.seccomp_filter {
+                    let program = libc::sock_fprog {
+                        len: filter.len() as libc::c_ushort,
+                        filter: filter.as_ptr() as *mut libc::sock_filter,
+                    };
+                    if libc::prctl(
+                        libc::PR_SET_SECCOMP,
+                        libc::SECCOMP_MODE_FILTER,
+                        &program as *const libc::sock_fprog,
+                    ) != 0
+                    {
+                        return Err(io::Error::last_os_error());
+                    }
+                }
+            }
+            Ok(())
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[cfg(target_os = "macos")]
+    #[test]
+    fn test_profile_denies_ip_networking() {
+        let workspace = std::env::temp_dir();
+        let profile = macos::profile(&SandboxProfile::default(), &workspace);
+        assert!(profile.contains("(deny network-outbound (remote ip))"));
+        assert!(profile.contains("(deny file-write*)"));
+        let allowed = SandboxProfile {
+            allow_network: true,
+            ..Default::default()
+        };
+        assert!(!macos::profile(&allowed, &workspace).contains("network-outbound"));
+    }
+
+    /// Run `probe` in a forked child restricted by `sandbox`, returning its exit code.
+    /// `None` when the kernel has no Landlock to test with
+    #[cfg(target_os = "linux")]
+    fn run_sandboxed(
+        sandbox: &SandboxProfile,
+        workspace: &Path,
+        probe: impl Fn() -> i32,
+    ) -> Option<i32> {
+        let restrictions = match linux::Restrictions::new(sandbox, workspace) {
+            Ok(restrictions) => restrictions,
+            Err(e) => {
+                eprintln!("Skipping the sandbox test: {}", e);
+                return None;
+            }
+        };
+        // SAFETY: the child only issues raw syscalls on data prepared before forking
+        unsafe {
+            let pid = libc::fork();
+            assert!(pid >= 0);
+            if pid == 0 {
+                let code = match restrictions.apply() {
+                    Ok(()) => probe(),
+                    Err(_) => 100,
+                };
+                libc::_exit(code);
+            }
+            let mut status = 0;
+            assert_eq!(libc::waitpid(pid, &mut status, 0), pid);
+            assert!(libc::WIFEXITED(status));
+            Some(libc::WEXITSTATUS(status))
+        }
+    }
+
+    #[cfg(target_os = "linux")]
+    fn denied(result: libc::c_long) -> bool {
+        result == -1 && std::io::Error::last_os_error().raw_os_error() == Some(libc::EACCES)
+    }
+
+    #[cfg(target_os = "linux")]
+    #[test]
+    fn test_network_is_blocked_inside_the_sandbox() {
+        let workspace = std::env::temp_dir();
+        let code = run_sandboxed(&SandboxProfile::default(), &workspace, || unsafe {
+            if !denied(libc::socket(libc::AF_INET, libc::SOCK_STREAM, 0) as libc::c_long) {
+                return 1;
+            }
+            if !denied(libc::socket(libc::AF_INET6, libc::SOCK_DGRAM, 0) as libc::c_long) {
+                return 2;
+            }
+            if !denied(libc::socket(libc::AF_NETLINK, libc::SOCK_RAW, 0) as libc::c_long) {
+                return 3;
+            }
+            let mut fds = [0; 2];
+            if !denied(
+                libc::socketpair(libc::AF_INET, libc::SOCK_STREAM, 0, fds.as_mut_ptr())
+                    as libc::c_long,
+            ) {
+                return 4;
+            }
+            // Local IPC keeps working
+            if libc::socketpair(libc::AF_UNIX, libc::SOCK_STREAM, 0, fds.as_mut_ptr()) != 0 {
+                return 5;
+            }
+            if !denied(libc::syscall(
+                libc::SYS_io_uring_setup,
+                1,
+                std::ptr::null_mut::<u8>(),
+            )) {
+                return 6;
+            }
+            // The x32 number of socket(2) must not slip past the filter
+            #[cfg(target_arch = "x86_64")]
+            if libc::syscall(
+                0x4000_0000 | libc::SYS_socket,
+                libc::AF_INET,
+                libc::SOCK_STREAM,
+                0,
+            ) != -1
+            {
+                return 7;
+            }
+            0
+        });
+        if let Some(code) = code {
+            assert_eq!(code, 0, "probe {} was not blocked", code);
+        }
+    }
+
+    #[cfg(target_os = "linux")]
+    #[test]
+    fn test_network_can_be_allowed() {
+        let workspace = std::env::temp_dir();
+        let sandbox = SandboxProfile {
+            allow_network: true,
+            ..Default::default()
+        };
+        let code = run_sandboxed(&sandbox, &workspace, || unsafe {
+            let fd = libc::socket(libc::AF_INET, libc::SOCK_STREAM, 0);
+            if fd < 0 {
+                return 1;
+            }
+            libc::close(fd);
+            0
+        });
+        if let Some(code) = code {
+            assert_eq!(code, 0);
+        }
+    }
+
+    #[cfg(target_os = "linux")]
+    #[test]
+    fn test_writes_are_confined_to_writable_dirs() {
+        use std::ffi::CString;
+
+        let workspace =
+            std::env::temp_dir().join(format!("stakpak-sandbox-{}", std::process::id()));
+        let _ = std::fs::create_dir_all(&workspace);
+        let inside = CString::new(workspace.join("allowed").to_string_lossy().as_bytes()).unwrap();
+        let outside = CString::new(
+            Path::new(env!("CARGO_MANIFEST_DIR"))
+                .join("sandbox-probe")
+                .to_string_lossy()
+                .as_bytes(),
+        )
+        .unwrap();
+        let code = run_sandboxed(&SandboxProfile::default(), &workspace, || unsafe {
+            let flags = libc::O_CREAT | libc::O_WRONLY | libc::O_CLOEXEC;
+            let fd = libc::open(inside.as_ptr(), flags, 0o600);
+            if fd < 0 {
+                return 1;
+            }
+            libc::close(fd);
+            if !denied(libc::open(outside.as_ptr(), flags, 0o600) as libc::c_long) {
+                return 2;
+            }
+            0
+        });
+        let _ = std::fs::remove_dir_all(&workspace);
+        if let Some(code) = code {
+            assert_eq!(code, 0, "probe {} was not confined", code);
+        }
+    }
+}
@@ -1,8 +1,55 @@
 use serde_json;
 use stakpak_shared::local_store::LocalStore;
+use stakpak_shared::secrets::vault::stored_secret_placeholders;
 use stakpak_shared::secrets::{redact_secrets, restore_secrets};
 use std::collections::HashMap;
-use tracing::{error, warn};
+use std::time::Instant;
+use tracing::{debug, error, warn};
+
+/// Redactions applied wherever their values appear, for secrets no detector recognizes
+const VALUE_REDACTIONS_FILE: &str = "secret_values.json";
+
+/// Replace every occurrence of the values of `redactions` with their placeholders, longest
+/// values first so a value containing another one is replaced whole
+fn redact_values(content: &str, redactions: &HashMap<String, String>) -> String {
+    let mut redactions: Vec<(&String, &String)> = redactions
+        .iter()
+        .filter(|(_, value)| !value.is_empty())
+        .collect();
+    redactions.sort_by(|a, b| b.1.len().cmp(&a.1.len()));
+    let mut content = content.to_string();
+    for (placeholder, value) in redactions {
+        content = content.replace(value.as_str(), placeholder);
+    }
+    content
+}
+
+/// Merge `new_redactions` into the redaction map in the session file `file`
+fn merge_redactions(file: &str, new_redactions: &HashMap<String, String>) {
+    if new_redactions.is_empty() {
+        return;
+    }
+
+    // Merged under the store lock so redactions added by other processes sharing the
+    // workspace are kept
+    let updated = LocalStore::update_session_data(file, |content| {
+        let mut existing_map = content
+            .filter(|content| !content.trim().is_empty())
+            .and_then(|content| {
+                serde_json::from_str::<HashMap<String, String>>(&content)
+                    .map_err(|e| error!("Failed to parse session redaction map JSON: {}", e))
+                    .ok()
+            })
+            .unwrap_or_default();
+        existing_map.extend(new_redactions.clone());
+        let json_content = serde_json::to_string_pretty(&existing_map)
+            .map_err(|e| format!("Failed to serialize session redaction map to JSON: {}", e))?;
+        Ok((json_content, ()))
+    });
+    if let Err(e) = updated {
+        error!("Failed to save session redaction map: {}", e);
+    }
+}
 
 /// Handles secret redaction and restoration across different tool types
 #[derive(Clone)]
@@ -54,18 +101,42 @@ impl SecretManager {
 
     /// Add new redactions to the session map
     pub fn add_to_session_redaction_map(&self, new_redactions: &HashMap<String, String>) {
-        if new_redactions.is_empty() {
-            return;
+        merge_redactions("secrets.json", new_redactions);
+    }
+
+    /// Add redactions whose values are replaced in every later tool output, like those of
+    /// stored secrets, since a detector would not recognize them
+    pub fn add_value_redactions(&self, new_redactions: &HashMap<String, String>) {
+        self.add_to_session_redaction_map(new_redactions);
+        merge_redactions(VALUE_REDACTIONS_FILE, new_redactions);
+    }
+
+    fn load_value_redactions(&self) -> HashMap<String, String> {
+        if !LocalStore::workspace().path(VALUE_REDACTIONS_FILE).exists() {
+            return HashMap::new();
         }
+        LocalStore::read_session_data(VALUE_REDACTIONS_FILE)
+            .ok()
+            .and_then(|content| {
+                serde_json::from_str(&content)
+                    .map_err(|e| error!("Failed to parse value redactions JSON: {}", e))
+                    .ok()
+            })
+            .unwrap_or_default()
+    }
 
-        let mut existing_map = self.load_session_redaction_map();
-        existing_map.extend(new_redactions.clone());
-        self.save_session_redaction_map(&existing_map);
+    /// Load the `[SECRET:NAME]` placeholders of secrets stored with `stakpak secrets add`
+    fn load_stored_secrets(&self) -> HashMap<String, String> {
+        stored_secret_placeholders().unwrap_or_else(|e| {
+            warn!("Failed to load stored secrets: {}", e);
+            HashMap::new()
+        })
     }
 
-    /// Restore secrets in a string using the session redaction map
+    /// Restore secrets in a string using the session redaction map and stored secrets
     pub fn restore_secrets_in_string(&self, input: &str) -> String {
-        let redaction_map = self.load_session_redaction_map();
+        let mut redaction_map = self.load_session_redaction_map();
+        redaction_map.extend(self.load_stored_secrets());
         if redaction_map.is_empty() {
             return input.to_string();
         }
@@ -74,13 +145,26 @@ impl SecretManager {
 
     /// Redact secrets and add to session map
     pub fn redact_and_store_secrets(&self, content: &str, path: Option<&str>) -> String {
+        // Stored secrets and secrets read by tools never reach the model, even when
+        // redaction is disabled
+        let mut value_redactions = self.load_stored_secrets();
+        value_redactions.extend(self.load_value_redactions());
+        let content = redact_values(content, &value_redactions);
+        let content = content.as_str();
+
         if !self.redact_secrets {
             return content.to_string();
         }
 
         // TODO: this is not thread safe, we need to use a mutex or an actor to protect the redaction map
         let existing_redaction_map = self.load_session_redaction_map();
+        let started = Instant::now();
         let redaction_result = redact_secrets(content, path, &existing_redaction_map);
+        debug!(
+            "Scanned {} bytes for secrets in {:?}",
+            content.len(),
+            started.elapsed()
+        );
 
         // Add new redactions to session map
         self.add_to_session_redaction_map(&redaction_result.redaction_map);
@@ -88,3 +172,30 @@ impl SecretManager {
         redaction_result.redacted_string
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_redact_values() {
+        let redactions = HashMap::from([
+            (
+                "[K8S_SECRET:prod/db/password]".to_string(),
+                "hunter2".to_string(),
+            ),
+            (
+                "[K8S_SECRET:prod/db/url]".to_string(),
+                "postgres://admin:hunter2@db:5432".to_string(),
+            ),
+            ("[K8S_SECRET:prod/db/empty]".to_string(), String::new()),
+        ]);
+        assert_eq!(
+            redact_values(
+                "DB_PASSWORD=hunter2\nDATABASE_URL=postgres://admin:hunter2@db:5432",
+                &redactions
+            ),
+            "DB_PASSWORD=[K8S_SECRET:prod/db/password]\nDATABASE_URL=[K8S_SECRET:prod/db/url]"
+        );
+    }
+}
@@ -0,0 +1,90 @@
+use std::collections::BTreeMap;
+use std::sync::{Arc, Mutex};
+use std::time::Instant;
+use tokio::process::Child;
+
+/// Long-running process started by a tool, e.g. a port-forward
+pub struct BackgroundTask {
+    pub kind: String,
+    pub description: String,
+    pub started_at: Instant,
+    child: Child,
+}
+
+/// Tracks the background processes started in an MCP session. Children are spawned with
+/// `kill_on_drop`, so they are stopped when the session's tools are dropped instead of
+/// outliving the agent.
+#[derive(Clone, Default)]
+pub struct TaskManager {
+    tasks: Arc<Mutex<BTreeMap<String, BackgroundTask>>>,
+}
+
+impl TaskManager {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Track `child` and return the ID used to stop it
+    pub fn add(&self, kind: &str, description: String, child: Child) -> Result<String, String> {
+        let mut tasks = self.tasks.lock().map_err(|e| e.to_string())?;
+        let id = format!("{}-{}", kind, tasks.len() + 1);
+        let id = if tasks.contains_key(&id) {
+            format!("{}-{}", kind, uuid::Uuid::new_v4().simple())
+        } else {
+            id
+        };
+        tasks.insert(
+            id.clone(),
+            BackgroundTask {
+                kind: kind.to_string(),
+                description,
+                started_at: Instant::now(),
+                child,
+            },
+        );
+        Ok(id)
+    }
+
+    /// One line per task: ID, status, uptime and description. Tasks whose process exited
+    /// are reported once and forgotten.
+    pub fn list(&self) -> Result<Vec<String>, String> {
+        let mut tasks = self.tasks.lock().map_err(|e| e.to_string())?;
+        let mut lines = Vec::new();
+        let mut exited = Vec::new();
+        for (id, task) in tasks.iter_mut() {
+            let status = match task.child.try_wait() {
+                Ok(None) => "running".to_string(),
+                Ok(Some(status)) => {
+                    exited.push(id.clone());
+                    format!("exited ({})", status)
+                }
+                Err(e) => format!("unknown ({})", e),
+            };
+            lines.push(format!(
+                "{}  {}  {}s  {}",
+                id,
+                status,
+                task.started_at.elapsed().as_secs(),
+                task.description
+            ));
+        }
+        for id in exited {
+            tasks.remove(&id);
+        }
+        Ok(lines)
+    }
+
+    pub async fn stop(&self, id: &str) -> Result<BackgroundTask, String> {
+        let mut task = self
+            .tasks
+            .lock()
+            .map_err(|e| e.to_string())?
+            .remove(id)
+            .ok_or_else(|| format!("No background task with ID {}", id))?;
+        task.child
+            .kill()
+            .await
+            .map_err(|e| format!("Failed to stop {}: {}", id, e))?;
+        Ok(task)
+    }
+}
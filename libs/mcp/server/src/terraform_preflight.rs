@@ -0,0 +1,264 @@
+use serde::Deserialize;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+const STATE_LOCK_FILE: &str = ".terraform.tfstate.lock.info";
+const PROVIDER_LOCK_FILE: &str = ".terraform.lock.hcl";
+/// Local state locks older than this most likely belong to a run that crashed
+const STALE_LOCK_AGE: Duration = Duration::from_secs(60 * 60);
+
+/// Subcommands that take the state lock or need the installed providers to match the
+/// dependency lock file
+const CHECKED_SUBCOMMANDS: [&str; 7] = [
+    "plan", "apply", "destroy", "import", "refresh", "taint", "untaint",
+];
+
+/// Problem found before running a terraform command
+#[derive(Debug, Clone, PartialEq)]
+pub struct TerraformWarning {
+    pub kind: &'static str,
+    pub message: String,
+    pub remediation: String,
+    /// The command would fail right away, so it is not run
+    pub blocking: bool,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "PascalCase")]
+struct StateLockInfo {
+    #[serde(rename = "ID")]
+    id: String,
+    #[serde(default)]
+    operation: String,
+    #[serde(default)]
+    who: String,
+    #[serde(default)]
+    created: String,
+}
+
+/// Terraform subcommand and working directory of `command`, if it runs terraform or tofu.
+/// `cd` before the terraform call and `-chdir` are followed.
+fn parse_terraform_command(command: &str, work_dir: &Path) -> Option<(String, PathBuf)> {
+    let mut dir = work_dir.to_path_buf();
+    for segment in command.split(['&', ';', '|']) {
+        let words: Vec<&str> = segment.split_whitespace().collect();
+        match words.first() {
+            Some(&"cd") => {
+                if let Some(target) = words.get(1) {
+                    dir = dir.join(target);
+                }
+            }
+            Some(&"terraform") | Some(&"tofu") => {
+                for word in words.iter().skip(1) {
+                    if let Some(chdir) = word.strip_prefix("-chdir=") {
+                        dir = dir.join(chdir);
+                    } else if !word.starts_with('-') {
+                        return Some((word.to_string(), dir));
+                    }
+                }
+                return None;
+            }
+            _ => {}
+        }
+    }
+    None
+}
+
+fn check_state_lock(dir: &Path) -> Option<TerraformWarning> {
+    let path = dir.join(STATE_LOCK_FILE);
+    let content = std::fs::read_to_string(&path).ok()?;
+    let age = std::fs::metadata(&path)
+        .and_then(|metadata| metadata.modified())
+        .ok()
+        .and_then(|modified| modified.elapsed().ok())
+        .unwrap_or_default();
+    let stale = age > STALE_LOCK_AGE;
+
+    let lock = serde_json::from_str::<StateLockInfo>(&content).ok();
+    let details = match &lock {
+        Some(lock) => format!(
+            "held by {} for {} since {} (lock ID {})",
+            if lock.who.is_empty() {
+                "unknown"
+            } else {
+                &lock.who
+            },
+            if lock.operation.is_empty() {
+                "an unknown operation"
+            } else {
+                &lock.operation
+            },
+            lock.created,
+            lock.id
+        ),
+        None => "with unreadable lock info".to_string(),
+    };
+    let unlock = match &lock {
+        Some(lock) => format!("terraform force-unlock {}", lock.id),
+        None => "terraform force-unlock <lock ID>".to_string(),
+    };
+
+    Some(TerraformWarning {
+        kind: if stale {
+            "stale_state_lock"
+        } else {
+            "state_lock"
+        },
+        message: format!(
+            "The local state in {} is locked, {}",
+            dir.display(),
+            details
+        ),
+        remediation: if stale {
+            format!(
+                "The lock is over an hour old and most likely left behind by a crashed run. Check that no terraform process is still running, then ask the user before running `{}`.",
+                unlock
+            )
+        } else {
+            format!(
+                "Another terraform run is probably in progress, wait for it to finish and retry. Only if it is certain that no run holds the lock, ask the user before running `{}`.",
+                unlock
+            )
+        },
+        blocking: true,
+    })
+}
+
+/// Providers pinned in `.terraform.lock.hcl` as (source address, version)
+fn locked_providers(content: &str) -> Vec<(String, String)> {
+    let mut providers = Vec::new();
+    let mut current: Option<String> = None;
+    for line in content.lines().map(str::trim) {
+        if let Some(rest) = line.strip_prefix("provider \"") {
+            current = rest.split('"').next().map(|source| source.to_string());
+        } else if let Some(version) = line.strip_prefix("version") {
+            let version = version.trim_start().trim_start_matches('=').trim();
+            if let Some(source) = current.take() {
+                providers.push((source, version.trim_matches('"').to_string()));
+            }
+        }
+    }
+    providers
+}
+
+fn check_provider_lock(dir: &Path) -> Option<TerraformWarning> {
+    let content = std::fs::read_to_string(dir.join(PROVIDER_LOCK_FILE)).ok()?;
+    let installed_dir = dir.join(".terraform").join("providers");
+    if !installed_dir.exists() {
+        return None;
+    }
+
+    let mismatched: Vec<String> = locked_providers(&content)
+        .into_iter()
+        .filter(|(source, version)| !installed_dir.join(source).join(version).exists())
+        .map(|(source, version)| format!("{} {}", source, version))
+        .collect();
+    if mismatched.is_empty() {
+        return None;
+    }
+
+    Some(TerraformWarning {
+        kind: "provider_lock_mismatch",
+        message: format!(
+            "Providers pinned in {} are not installed: {}",
+            PROVIDER_LOCK_FILE,
+            mismatched.join(", ")
+        ),
+        remediation: "Run `terraform init` to install the pinned versions. Only use `terraform init -upgrade` if the user wants to move to newer provider versions, since it rewrites the lock file.".to_string(),
+        blocking: true,
+    })
+}
+
+/// Look for state locks and provider lock file mismatches that would make `command` fail
+/// as soon as terraform starts
+pub fn terraform_preflight(command: &str, work_dir: &Path) -> Vec<TerraformWarning> {
+    let Some((subcommand, dir)) = parse_terraform_command(command, work_dir) else {
+        return Vec::new();
+    };
+    if !CHECKED_SUBCOMMANDS.contains(&subcommand.as_str()) {
+        return Vec::new();
+    }
+    [check_state_lock(&dir), check_provider_lock(&dir)]
+        .into_iter()
+        .flatten()
+        .collect()
+}
+
+/// Remediation for remote backend locks, which can only be seen in terraform's own output
+pub fn remote_lock_warning(output: &str) -> Option<TerraformWarning> {
+    if !output.contains("Error acquiring the state lock") {
+        return None;
+    }
+    let lock_id = output
+        .lines()
+        .map(str::trim)
+        .find_map(|line| line.strip_prefix("ID:"))
+        .map(|id| id.trim().to_string());
+    Some(TerraformWarning {
+        kind: "backend_state_lock",
+        message: format!(
+            "The backend state is locked{}",
+            lock_id
+                .as_ref()
+                .map(|id| format!(" (lock ID {})", id))
+                .unwrap_or_default()
+        ),
+        remediation: format!(
+            "Retry with `-lock-timeout=5m` if another run is in progress. If the lock was left by a crashed run, ask the user before running `terraform force-unlock {}`.",
+            lock_id.as_deref().unwrap_or("<lock ID>")
+        ),
+        blocking: false,
+    })
+}
+
+pub fn format_terraform_warnings(warnings: &[TerraformWarning]) -> String {
+    let mut output = String::from("Terraform preflight warnings:\n");
+    for warning in warnings {
+        output.push_str(&format!(
+            "- [{}] {}\n  Remediation: {}\n",
+            warning.kind, warning.message, warning.remediation
+        ));
+    }
+    output
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_terraform_preflight() {
+        let dir =
+            std::env::temp_dir().join(format!("stakpak-tf-preflight-{}", uuid::Uuid::new_v4()));
+        std::fs::create_dir_all(
+            dir.join(".terraform/providers/registry.terraform.io/hashicorp/aws/5.31.0"),
+        )
+        .unwrap();
+        std::fs::write(
+            dir.join(PROVIDER_LOCK_FILE),
+            "provider \"registry.terraform.io/hashicorp/aws\" {\n  version     = \"5.31.0\"\n}\n\nprovider \"registry.terraform.io/hashicorp/random\" {\n  version = \"3.6.0\"\n}\n",
+        )
+        .unwrap();
+        std::fs::write(
+            dir.join(STATE_LOCK_FILE),
+            r#"{"ID":"8c1f","Operation":"OperationTypeApply","Who":"ci@runner","Created":"2026-01-01T00:00:00Z"}"#,
+        )
+        .unwrap();
+
+        let warnings = terraform_preflight("terraform apply -auto-approve", &dir);
+        let kinds: Vec<_> = warnings.iter().map(|w| w.kind).collect();
+        assert_eq!(kinds, vec!["state_lock", "provider_lock_mismatch"]);
+        assert!(
+            warnings[0]
+                .remediation
+                .contains("terraform force-unlock 8c1f")
+        );
+        assert!(warnings[1].message.contains("hashicorp/random 3.6.0"));
+        assert!(!warnings[1].message.contains("hashicorp/aws"));
+
+        assert!(terraform_preflight("terraform init", &dir).is_empty());
+        assert!(terraform_preflight("cd modules && terraform plan", &dir).is_empty());
+        assert!(terraform_preflight("ls -la", &dir).is_empty());
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -0,0 +1,202 @@
+use crate::remote_tools::Provisioner;
+use rmcp::schemars;
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use std::path::Path;
+
+/// Directories files are never collected from when a directory is passed as a target
+const SKIPPED_DIRS: [&str; 4] = [".git", ".terraform", "node_modules", "target"];
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq, JsonSchema)]
+#[serde(rename_all = "kebab-case")]
+pub enum TestFramework {
+    /// Go tests using gruntwork-io/terratest
+    Terratest,
+    /// Native `terraform test` files (*.tftest.hcl)
+    TerraformTest,
+    /// kuttl end-to-end test steps
+    Kuttl,
+    /// kubeconform schema validation
+    Kubeconform,
+    /// GoogleContainerTools container-structure-test configs
+    ContainerStructureTest,
+}
+
+impl std::fmt::Display for TestFramework {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            TestFramework::Terratest => write!(f, "terratest"),
+            TestFramework::TerraformTest => write!(f, "terraform-test"),
+            TestFramework::Kuttl => write!(f, "kuttl"),
+            TestFramework::Kubeconform => write!(f, "kubeconform"),
+            TestFramework::ContainerStructureTest => write!(f, "container-structure-test"),
+        }
+    }
+}
+
+impl std::str::FromStr for TestFramework {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "terratest" => Ok(TestFramework::Terratest),
+            "terraform-test" => Ok(TestFramework::TerraformTest),
+            "kuttl" => Ok(TestFramework::Kuttl),
+            "kubeconform" => Ok(TestFramework::Kubeconform),
+            "container-structure-test" => Ok(TestFramework::ContainerStructureTest),
+            _ => Err(format!(
+                "Unknown test framework '{}', expected one of: terratest, terraform-test, kuttl, kubeconform, container-structure-test",
+                s
+            )),
+        }
+    }
+}
+
+impl TestFramework {
+    /// Provisioner of the configurations the framework tests, used to route the generation
+    pub fn provisioner(&self) -> Provisioner {
+        match self {
+            TestFramework::Terratest | TestFramework::TerraformTest => Provisioner::Terraform,
+            TestFramework::Kuttl | TestFramework::Kubeconform => Provisioner::Kubernetes,
+            TestFramework::ContainerStructureTest => Provisioner::Dockerfile,
+        }
+    }
+
+    fn is_config_file(&self, path: &Path) -> bool {
+        let name = path
+            .file_name()
+            .and_then(|name| name.to_str())
+            .unwrap_or_default()
+            .to_lowercase();
+        match self.provisioner() {
+            Provisioner::Terraform => name.ends_with(".tf"),
+            Provisioner::Kubernetes => name.ends_with(".yaml") || name.ends_with(".yml"),
+            Provisioner::Dockerfile => name.contains("dockerfile"),
+            _ => false,
+        }
+    }
+
+    fn instructions(&self) -> &'static str {
+        match self {
+            TestFramework::Terratest => {
+                "Write Terratest tests in Go under a test/ directory next to the module, with a go.mod if none exists. \
+Each test should call t.Parallel(), build terraform.Options pointing at the module with unique resource names (random.UniqueId()), \
+defer terraform.Destroy, run terraform.InitAndApply and assert on the module outputs and the real resources they describe."
+            }
+            TestFramework::TerraformTest => {
+                "Write native Terraform test files (*.tftest.hcl, Terraform 1.6+) under a tests/ directory next to the module. \
+Use run blocks with command = plan wherever the assertion does not need real infrastructure, set the required variables in variables blocks, \
+and assert on resource attributes, outputs and variable validation rules with clear error_message values. Add expect_failures cases for invalid inputs."
+            }
+            TestFramework::Kuttl => {
+                "Write kuttl end-to-end tests: a kuttl-test.yaml TestSuite at the repository root and one directory per scenario under tests/e2e/, \
+each with numbered steps (00-install.yaml, 00-assert.yaml, 01-..., ...). Asserts should check the readiness status of Deployments/StatefulSets, \
+the Services and ConfigMaps created, and use errors files for resources that must not exist."
+            }
+            TestFramework::Kubeconform => {
+                "Write a kubeconform validation setup under tests/kubeconform/: a run.sh script that runs kubeconform -strict -summary \
+against the manifests (rendering Kustomize or Helm first if they are used), with -schema-location entries for the CRDs the manifests reference, \
+and an invalid/ directory with deliberately broken copies of the manifests that the script expects kubeconform to reject."
+            }
+            TestFramework::ContainerStructureTest => {
+                "Write container-structure-test configs (schemaVersion 2.0.0) next to each Dockerfile, named container-structure-test.yaml. \
+Include a metadataTest matching the image's exposed ports, user, workdir, entrypoint and cmd, fileExistenceTests for the files the Dockerfile copies \
+in (with permissions), and commandTests checking the installed tools report the expected versions."
+            }
+        }
+    }
+}
+
+/// Config files to pass as context, directories are searched recursively for the files
+/// the framework tests
+pub fn collect_config_files(paths: &[String], framework: TestFramework) -> Vec<String> {
+    fn walk(dir: &Path, framework: TestFramework, files: &mut Vec<String>) {
+        let Ok(entries) = std::fs::read_dir(dir) else {
+            return;
+        };
+        let mut entries: Vec<_> = entries.flatten().map(|entry| entry.path()).collect();
+        entries.sort();
+        for path in entries {
+            let name = path
+                .file_name()
+                .and_then(|name| name.to_str())
+                .unwrap_or_default();
+            if path.is_dir() {
+                if !SKIPPED_DIRS.contains(&name) {
+                    walk(&path, framework, files);
+                }
+            } else if framework.is_config_file(&path) {
+                files.push(path.to_string_lossy().to_string());
+            }
+        }
+    }
+
+    let mut files = Vec::new();
+    for path in paths {
+        let path = Path::new(path);
+        if path.is_dir() {
+            walk(path, framework, &mut files);
+        } else {
+            files.push(path.to_string_lossy().to_string());
+        }
+    }
+    files.dedup();
+    files
+}
+
+/// Prompt asking `generate_code` for tests of the given configuration files
+pub fn test_generation_prompt(
+    framework: TestFramework,
+    files: &[String],
+    instructions: Option<&str>,
+) -> String {
+    let mut prompt = format!(
+        "Generate {} tests for the existing configurations in these files, without changing the files themselves:\n{}\n\n{}",
+        framework,
+        files
+            .iter()
+            .map(|file| format!("- {}", file))
+            .collect::<Vec<_>>()
+            .join("\n"),
+        framework.instructions()
+    );
+    if let Some(instructions) = instructions.filter(|i| !i.trim().is_empty()) {
+        prompt.push_str(&format!(
+            "\n\nAdditional instructions: {}",
+            instructions.trim()
+        ));
+    }
+    prompt
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_collect_config_files() {
+        let dir = std::env::temp_dir().join(format!("stakpak-test-gen-{}", uuid::Uuid::new_v4()));
+        std::fs::create_dir_all(dir.join("modules/vpc")).unwrap();
+        std::fs::create_dir_all(dir.join(".terraform/modules")).unwrap();
+        std::fs::write(dir.join("main.tf"), "").unwrap();
+        std::fs::write(dir.join("README.md"), "").unwrap();
+        std::fs::write(dir.join("modules/vpc/main.tf"), "").unwrap();
+        std::fs::write(dir.join(".terraform/modules/cached.tf"), "").unwrap();
+
+        let files = collect_config_files(
+            &[dir.to_string_lossy().to_string()],
+            TestFramework::TerraformTest,
+        );
+        std::fs::remove_dir_all(&dir).unwrap();
+
+        assert_eq!(
+            files,
+            vec![
+                dir.join("main.tf").to_string_lossy().to_string(),
+                dir.join("modules/vpc/main.tf")
+                    .to_string_lossy()
+                    .to_string(),
+            ]
+        );
+    }
+}
@@ -6,7 +6,20 @@ SECRET HANDLING:
 - You can use these placeholders in subsequent commands - they will be automatically restored to actual values before execution
 - Example: If you see 'export API_KEY=[REDACTED_SECRET:api-key:abc123]', you can use '[REDACTED_SECRET:api-key:abc123]' in later commands
 
-If the command's output exceeds 300 lines the result will be truncated and the full output will be saved to a file in the current directory";
+If the command's output exceeds 300 lines the result will be truncated and the full output will be saved to a file in the current directory
+
+REPEATED COMMANDS:
+- When the same command is run again in the same directory (e.g. polling 'kubectl get pods'), only the differences from the previous output are shown, or a note that it did not change
+- The full output of every run is still saved to a file whose path is included in the result
+
+CACHED RESULTS:
+- Pass cache=true to reuse the output of an identical read-only command run in the last 5 minutes, the result then starts with [CACHED] and the command is not run again
+- Pass cache=false to always run the command, e.g. when checking whether something changed
+
+TIMEOUTS:
+- Commands are killed, with everything they started, after 300 seconds unless the server sets another default
+- A killed command returns a TIMEOUT error with the output it printed so far
+- Pass timeout (in seconds, at most 3600) for commands known to take longer, or run them in the background and poll their log";
 
 pub const VIEW_DESCRIPTION: &str = "View the contents of a file or list the contents of a directory. Can read entire files or specific line ranges.
 
@@ -31,27 +44,132 @@ pub const CREATE_DESCRIPTION: &str = "Create a new file with the specified conte
 pub const INSERT_DESCRIPTION: &str =
     "Insert text at a specific line number in a file. Line numbers are 1-indexed.";
 
+pub const DELETE_FILE_DESCRIPTION: &str = "Delete a file, or a directory with recursive set to true. Use this instead of running rm. Only paths inside the workspace can be deleted, and the content of deleted text files is kept in the session file history. Not available while a transaction from begin_changes is open.";
+
+pub const MOVE_FILE_DESCRIPTION: &str = "Move or rename a file or directory. Use this instead of running mv. Both paths must be inside the workspace, parent directories of the destination are created automatically, and an existing destination is only replaced with overwrite set to true. Not available while a transaction from begin_changes is open.";
+
+pub const SEARCH_FILES_DESCRIPTION: &str = "Search file contents for lines matching a regular expression. Use this instead of running grep -R or findstr: it skips files ignored by .gitignore, hidden and binary files, works the same on every platform and returns JSON with the matches (path relative to the searched directory, 1-indexed line, text and optional context lines), the number of files searched and matched, and whether the results were cut at max_results.";
+
+pub const GET_WORKSPACE_CONTEXT_DESCRIPTION: &str = "Get a structured JSON summary of the workspace: file tree summary (file counts per top-level entry and by extension), detected provisioners (Terraform, Kubernetes, Helm, Docker, GitHub Actions, ...) and where they live, entry points, pinned and installed tool versions, and git status. Call this before exploring an unfamiliar workspace instead of listing directories one by one.";
+
+pub const CHECK_CLOUD_ACCESS_DESCRIPTION: &str = "Check which cloud identities are active by running lightweight identity calls (aws sts get-caller-identity, gcloud auth list, az account show). Returns a JSON array with one entry per provider: provider, status (authenticated, not_authenticated, cli_not_installed), account (AWS account ID, GCP project or Azure subscription), principal, region and error.
+
+IMPORTANT: Call this before creating, changing or deleting cloud resources and confirm the account and region are the ones the user intends to work in.";
+
+pub const FORMAT_AND_VALIDATE_DESCRIPTION: &str = "Parse JSON, YAML or HCL (Terraform) content or a file and return it pretty-printed, or the syntax error with its line and column. Use this to check generated snippets and files before saving or applying them, without depending on language-specific CLIs being installed. It only checks syntax, not provider or Kubernetes schemas. The formatted output does not keep comments, so do not overwrite commented files with it.";
+
+pub const KUBECTL_PORT_FORWARD_DESCRIPTION: &str = "Start a kubectl port-forward to a pod, service or deployment in the background and return the local port it is bound to on 127.0.0.1. Use this instead of running 'kubectl port-forward' with run_command, which would block until it times out. The port-forward keeps running until it is stopped with stop_background_task or the session ends.";
+
+pub const K8S_READ_SECRET_DESCRIPTION: &str = "Read the keys of a Kubernetes secret without exposing its values. Each value is returned as a placeholder like [K8S_SECRET:<namespace>/<name>/<key>] that is replaced with the real value when used in run_command, create or str_replace. Use this instead of 'kubectl get secret -o yaml' or base64-decoding values with run_command, which would show them in the conversation.";
+
+pub const WAIT_FOR_DESCRIPTION: &str = "Wait until a condition holds, checking it every interval_seconds up to timeout_seconds: a shell command exiting with 0, a URL returning HTTP 200, or a pod (or all pods matching a label selector) being Ready. Pass exactly one of command, url and pod. Use this instead of polling with run_command loops like 'sleep 10 && kubectl get pods'. Returns the last check output once the condition holds, or a WAIT_TIMEOUT error with it when the timeout is reached.";
+
+pub const LIST_BACKGROUND_TASKS_DESCRIPTION: &str = "List the background tasks started in this session (e.g. port-forwards) with their ID, status, uptime and description.";
+
+pub const STOP_BACKGROUND_TASK_DESCRIPTION: &str =
+    "Stop a background task started in this session, such as a port-forward, by its ID.";
+
+pub const MKTEMP_SESSION_DESCRIPTION: &str = "Create an empty scratch directory for this session and return its absolute path. Use it for temporary files such as downloaded Helm charts, rendered manifests or plan files instead of writing them into the workspace or /tmp. The directory is deleted when the session ends and is never pushed or committed.";
+
+pub const EXPLAIN_CRON_DESCRIPTION: &str = "Explain when a cron expression runs. Accepts crontab, Kubernetes CronJob and GitHub Actions expressions (5 fields, @daily and similar macros) and AWS EventBridge expressions (cron(...) with 6 fields). Returns a plain-English summary, the values each field matches and warnings about surprising behavior, such as day-of-month and day-of-week both being set or days that never occur. Use it instead of interpreting schedules yourself.";
+
+pub const LINT_IAM_POLICY_DESCRIPTION: &str = "Parse an AWS IAM identity, resource or trust policy (JSON content or a file) and return what each statement allows or denies together with misconfigurations: invalid structure, wildcard actions and resources, NotAction/NotResource allows, privilege escalation actions such as iam:PassRole and public principals. Findings are sorted most severe first. Use it to check policies you write or review instead of guessing their permission boundaries. It does not evaluate SCPs, permission boundaries or other policies attached to the same principal.";
+
+pub const BEGIN_CHANGES_DESCRIPTION: &str = "Start a transaction for a change spanning several files. Until commit_changes is called, create, str_replace and insert only stage their edits: view shows the staged content, but nothing is written to disk and commands do not see the edits. Use it for multi-file refactors so a failing step never leaves files half-changed.";
+
+pub const COMMIT_CHANGES_DESCRIPTION: &str = "Write all edits staged since begin_changes at once. If any file changed on disk since it was staged or cannot be written, nothing is written and the transaction is rolled back.";
+
+pub const ROLLBACK_CHANGES_DESCRIPTION: &str =
+    "Discard all edits staged since begin_changes, leaving every file as it is on disk.";
+
 pub const GENERATE_CODE_DESCRIPTION: &str = "Advanced Generate/Edit devops configurations and infrastructure as code with suggested file names using a given prompt. This code generation/editing only works for Terraform, Kubernetes, Dockerfile, and Github Actions. If save_files is true, the generated files will be saved to the filesystem. The printed shell output will redact any secrets, will be replaced with a placeholder [REDACTED_SECRET:rule-id:short-hash]
 
 IMPORTANT: When breaking down large projects into multiple generation steps, always include previously generated files in the 'context' parameter to maintain coherent references and consistent structure across all generated files.";
 
+pub const GENERATE_TESTS_DESCRIPTION: &str = "Generate tests for existing infrastructure configurations: Terratest or native terraform test files for Terraform modules, kuttl test cases or kubeconform validation for Kubernetes manifests, and container-structure-test configs for Dockerfiles. Directories are searched for the configuration files the framework tests, which are sent as context to code generation. If save_files is true, the generated test files will be saved to the filesystem.";
+
 pub const SMART_SEARCH_CODE_DESCRIPTION: &str = "Query remote configurations and infrastructure as code indexed in Stakpak using natural language. This function uses a smart retrival system to find relevant code blocks with a relevance score, not just keyword matching. This function is useful for finding code blocks that are not in your local filesystem.";
 
+pub const SEARCH_OUTPUT_DESCRIPTION: &str = "Search the full output of a command that was too long to show, using the output artifact ID given in the truncated command result. Returns the matching lines with their line numbers and surrounding context. Use it instead of re-running the command to find errors or specific entries in huge logs.";
+
 // Parameter descriptions
 pub const COMMAND_PARAM_DESCRIPTION: &str = "The shell command to execute";
-pub const WORK_DIR_PARAM_DESCRIPTION: &str = "Optional working directory for command execution";
+pub const CACHE_PARAM_DESCRIPTION: &str = "Optional, reuse the output of an identical read-only command run recently instead of running it again (default: the server setting, usually false)";
+pub const COMMAND_TIMEOUT_PARAM_DESCRIPTION: &str = "Optional, seconds after which the command is killed and its partial output returned (default: the server setting, usually 300, at most 3600)";
+pub const WORK_DIR_PARAM_DESCRIPTION: &str = "Optional working directory for command execution, may be a root://<name>/<path> path in multi-root workspaces";
 
-pub const PATH_PARAM_DESCRIPTION: &str = "The path to the file or directory to view";
+pub const PATH_PARAM_DESCRIPTION: &str = "The path to the file or directory to view, may be a root://<name>/<path> path in multi-root workspaces";
 pub const VIEW_RANGE_PARAM_DESCRIPTION: &str = "Optional line range to view [start_line, end_line]. Line numbers are 1-indexed. Use -1 for end_line to read to end of file.";
 
-pub const FILE_PATH_PARAM_DESCRIPTION: &str = "The path to the file to modify";
+pub const FILE_PATH_PARAM_DESCRIPTION: &str =
+    "The path to the file to modify, may be a root://<name>/<path> path in multi-root workspaces";
 pub const OLD_STR_PARAM_DESCRIPTION: &str =
     "The exact text to replace (must match exactly, including whitespace and indentation)";
 pub const NEW_STR_PARAM_DESCRIPTION: &str = "The new text to insert in place of the old text. When replacing code, ensure the new text maintains proper syntax, indentation, and follows the codebase style.";
 
-pub const CREATE_PATH_PARAM_DESCRIPTION: &str = "The path where the new file should be created";
+pub const CREATE_PATH_PARAM_DESCRIPTION: &str = "The path where the new file should be created, may be a root://<name>/<path> path in multi-root workspaces";
 pub const FILE_TEXT_PARAM_DESCRIPTION: &str = "The content to write to the new file, when creating code, ensure the new text has proper syntax, indentation, and follows the codebase style.";
 
+pub const DELETE_PATH_PARAM_DESCRIPTION: &str = "The path to the file or directory to delete, may be a root://<name>/<path> path in multi-root workspaces";
+pub const RECURSIVE_PARAM_DESCRIPTION: &str =
+    "Optional, delete a directory with everything in it (default: false, directories are refused)";
+pub const SOURCE_PATH_PARAM_DESCRIPTION: &str = "The path to the file or directory to move, may be a root://<name>/<path> path in multi-root workspaces";
+pub const DESTINATION_PATH_PARAM_DESCRIPTION: &str = "The new path of the file or directory, may be a root://<name>/<path> path in multi-root workspaces";
+pub const OVERWRITE_PARAM_DESCRIPTION: &str =
+    "Optional, replace the destination file if it exists (default: false)";
+
+pub const SEARCH_PATTERN_PARAM_DESCRIPTION: &str =
+    "Regular expression (Rust regex syntax) matched against each line";
+pub const SEARCH_PATH_PARAM_DESCRIPTION: &str = "Optional directory or file to search, may be a root://<name>/<path> path in multi-root workspaces (default: the current working directory)";
+pub const SEARCH_GLOBS_PARAM_DESCRIPTION: &str = "Optional globs selecting the files to search, e.g. ['*.tf', 'modules/**/*.yaml'], prefix a glob with ! to exclude files (default: all files)";
+pub const CASE_INSENSITIVE_PARAM_DESCRIPTION: &str =
+    "Optional, match regardless of case (default: false)";
+pub const MAX_RESULTS_PARAM_DESCRIPTION: &str =
+    "Optional maximum number of matches to return (default: 100, at most 1000)";
+pub const CONTEXT_LINES_PARAM_DESCRIPTION: &str =
+    "Optional number of lines to return before and after each match (default: 0, at most 10)";
+
+pub const WORKSPACE_PATH_PARAM_DESCRIPTION: &str = "Optional workspace root to summarize, e.g. root://<name> (default: the current working directory)";
+
+pub const CLOUD_PROVIDERS_PARAM_DESCRIPTION: &str =
+    "Optional providers to check: any of 'aws', 'gcp' and 'azure' (default: all of them)";
+pub const FORMAT_PARAM_DESCRIPTION: &str = "Format of the content: 'json', 'yaml' or 'hcl' (optional when a path with a known extension is given)";
+pub const FORMAT_CONTENT_PARAM_DESCRIPTION: &str =
+    "Content to check, either this or path is required";
+pub const FORMAT_PATH_PARAM_DESCRIPTION: &str =
+    "Path of the file to check, either this or content is required";
+pub const PORT_FORWARD_RESOURCE_PARAM_DESCRIPTION: &str =
+    "Resource to forward to, e.g. 'svc/api', 'pod/api-7d9f' or 'deployment/api'";
+pub const PORT_FORWARD_REMOTE_PORT_PARAM_DESCRIPTION: &str = "Port on the resource to forward to";
+pub const PORT_FORWARD_LOCAL_PORT_PARAM_DESCRIPTION: &str =
+    "Optional local port to bind (default: a free port picked by kubectl)";
+pub const NAMESPACE_PARAM_DESCRIPTION: &str =
+    "Optional namespace of the resource (default: the namespace of the current context)";
+pub const KUBE_CONTEXT_PARAM_DESCRIPTION: &str =
+    "Optional kubeconfig context to use (default: the current context)";
+pub const K8S_SECRET_NAME_PARAM_DESCRIPTION: &str = "Name of the secret";
+pub const K8S_SECRET_KEYS_PARAM_DESCRIPTION: &str =
+    "Optional keys to read (default: every key of the secret)";
+pub const WAIT_COMMAND_PARAM_DESCRIPTION: &str =
+    "Shell command to wait for, the condition holds once it exits with 0";
+pub const WAIT_URL_PARAM_DESCRIPTION: &str =
+    "http(s) URL to wait for, the condition holds once it returns HTTP 200";
+pub const WAIT_POD_PARAM_DESCRIPTION: &str = "Pod name, or label selector like 'app=api', to wait for, the condition holds once the pod (or every matching pod) is Ready";
+pub const WAIT_TIMEOUT_PARAM_DESCRIPTION: &str =
+    "Optional time to wait before giving up, in seconds (default: 300, max: 1800)";
+pub const WAIT_INTERVAL_PARAM_DESCRIPTION: &str =
+    "Optional time between checks, in seconds (default: 5)";
+pub const CRON_EXPRESSION_PARAM_DESCRIPTION: &str =
+    "Cron expression, e.g. '30 2 * * MON-FRI', '@hourly' or 'cron(0 12 ? * MON-FRI *)'";
+pub const IAM_POLICY_PARAM_DESCRIPTION: &str =
+    "Optional policy JSON to lint, required if path is not provided";
+pub const IAM_POLICY_PATH_PARAM_DESCRIPTION: &str =
+    "Optional path of a policy JSON file to lint, used if policy is not provided";
+pub const MKTEMP_PREFIX_PARAM_DESCRIPTION: &str =
+    "Optional name prefix of the directory, e.g. 'charts' (default: 'tmp')";
+pub const BACKGROUND_TASK_ID_PARAM_DESCRIPTION: &str =
+    "ID of the background task, as returned when it was started";
+
 pub const INSERT_LINE_PARAM_DESCRIPTION: &str =
     "The line number where text should be inserted (1-indexed)";
 pub const INSERT_TEXT_PARAM_DESCRIPTION: &str = "The text to insert";
@@ -62,7 +180,18 @@ pub const PROVISIONER_PARAM_DESCRIPTION: &str =
 pub const SAVE_FILES_PARAM_DESCRIPTION: &str =
     "Whether to save the generated files to the filesystem (default: false)";
 pub const CONTEXT_PARAM_DESCRIPTION: &str = "Optional list of file paths to include as context for the generation. CRITICAL: When generating code in multiple steps (breaking down large projects), always include previously generated files from earlier steps to ensure consistent references, imports, and overall project coherence. Add any files you want to edit, or that you want to use as context for the generation (default: empty)";
+pub const TEST_TARGET_PATHS_PARAM_DESCRIPTION: &str =
+    "Configuration files or directories to generate tests for";
+pub const TEST_FRAMEWORK_PARAM_DESCRIPTION: &str = "Test framework to generate tests for: 'terratest' or 'terraform-test' (Terraform), 'kuttl' or 'kubeconform' (Kubernetes), 'container-structure-test' (Dockerfile)";
+pub const TEST_INSTRUCTIONS_PARAM_DESCRIPTION: &str =
+    "Optional extra instructions, e.g. which behaviors or edge cases to cover";
 
 pub const SEARCH_QUERY_PARAM_DESCRIPTION: &str = "The natural language query to find relevant code blocks, the more detailed the query the better the results will be";
 pub const SEARCH_LIMIT_PARAM_DESCRIPTION: &str =
     "The maximum number of results to return (default: 10)";
+pub const OUTPUT_ARTIFACT_ID_PARAM_DESCRIPTION: &str =
+    "The output artifact ID from the truncated command result";
+pub const OUTPUT_QUERY_PARAM_DESCRIPTION: &str =
+    "Text or regular expression to look for in the output, e.g. 'ERROR|FATAL'";
+pub const OUTPUT_CONTEXT_LINES_PARAM_DESCRIPTION: &str =
+    "Number of lines to show before and after each match (default: 2)";
@@ -0,0 +1,226 @@
+use std::collections::BTreeMap;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex, MutexGuard};
+use uuid::Uuid;
+
+/// Content of a file edited while a transaction is open
+#[derive(Debug, Clone, PartialEq)]
+pub struct StagedFile {
+    pub path: String,
+    /// Content on disk when the file was first staged, `None` when it did not exist
+    pub original: Option<String>,
+    pub content: String,
+}
+
+#[derive(Debug)]
+struct Transaction {
+    id: String,
+    files: BTreeMap<String, StagedFile>,
+}
+
+/// File edits staged between `begin_changes` and `commit_changes`, so a multi-file change
+/// is written all at once or not at all. Clones share the same transaction.
+#[derive(Clone, Default)]
+pub struct ChangeSet {
+    open: Arc<Mutex<Option<Transaction>>>,
+}
+
+impl ChangeSet {
+    fn lock(&self) -> MutexGuard<'_, Option<Transaction>> {
+        self.open
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+    }
+
+    /// Start staging file edits, returning the transaction ID
+    pub fn begin(&self) -> Result<String, String> {
+        let mut open = self.lock();
+        if let Some(transaction) = open.as_ref() {
+            return Err(format!(
+                "Transaction {} is already open with {} staged files, commit or roll it back first",
+                transaction.id,
+                transaction.files.len()
+            ));
+        }
+        let id = Uuid::new_v4().simple().to_string()[..8].to_string();
+        *open = Some(Transaction {
+            id: id.clone(),
+            files: BTreeMap::new(),
+        });
+        Ok(id)
+    }
+
+    pub fn is_open(&self) -> bool {
+        self.lock().is_some()
+    }
+
+    fn staged(&self, path: &str) -> Option<String> {
+        self.lock()
+            .as_ref()
+            .and_then(|transaction| transaction.files.get(path))
+            .map(|file| file.content.clone())
+    }
+
+    /// Content of `path` as edits see it, the staged content first
+    pub fn read(&self, path: &str) -> io::Result<String> {
+        match self.staged(path) {
+            Some(content) => Ok(content),
+            None => fs::read_to_string(path),
+        }
+    }
+
+    pub fn exists(&self, path: &str) -> bool {
+        self.staged(path).is_some() || Path::new(path).exists()
+    }
+
+    /// Stage the new content of `path` when a transaction is open, `false` when the edit
+    /// has to be written right away
+    pub fn stage(&self, path: &str, before: Option<&str>, after: &str) -> bool {
+        let mut open = self.lock();
+        let Some(transaction) = open.as_mut() else {
+            return false;
+        };
+        transaction
+            .files
+            .entry(path.to_string())
+            .or_insert_with(|| StagedFile {
+                path: path.to_string(),
+                original: before.map(str::to_string),
+                content: String::new(),
+            })
+            .content = after.to_string();
+        true
+    }
+
+    /// Discard the staged edits, returning the paths they touched
+    pub fn rollback(&self) -> Result<Vec<String>, String> {
+        match self.lock().take() {
+            Some(transaction) => Ok(transaction.files.into_keys().collect()),
+            None => Err("No transaction is open".to_string()),
+        }
+    }
+
+    /// Write every staged file, or none of them if any write fails. The transaction is
+    /// closed either way.
+    pub fn commit(&self) -> Result<Vec<StagedFile>, String> {
+        let transaction = self
+            .lock()
+            .take()
+            .ok_or_else(|| "No transaction is open".to_string())?;
+        let files: Vec<StagedFile> = transaction.files.into_values().collect();
+        apply_files(&files, &transaction.id)?;
+        Ok(files)
+    }
+}
+
+fn temp_path(path: &Path, id: &str) -> PathBuf {
+    let name = path
+        .file_name()
+        .map(|name| name.to_string_lossy().to_string())
+        .unwrap_or_default();
+    path.with_file_name(format!(".{}.stakpak-{}.tmp", name, id))
+}
+
+/// Put back the files replaced so far, best effort
+fn restore(files: &[StagedFile]) {
+    for file in files {
+        let _ = match &file.original {
+            Some(original) => fs::write(&file.path, original),
+            None => fs::remove_file(&file.path),
+        };
+    }
+}
+
+/// Write `files` next to their targets, then move them into place. Any failure removes
+/// the temporary files and restores the files already replaced.
+fn apply_files(files: &[StagedFile], id: &str) -> Result<(), String> {
+    for file in files {
+        let current = match fs::read_to_string(&file.path) {
+            Ok(content) => Some(content),
+            Err(e) if e.kind() == io::ErrorKind::NotFound => None,
+            Err(e) => return Err(format!("Cannot read {}: {}", file.path, e)),
+        };
+        if current != file.original {
+            return Err(format!(
+                "{} changed on disk since it was staged, nothing was written",
+                file.path
+            ));
+        }
+    }
+
+    let temps: Vec<PathBuf> = files
+        .iter()
+        .map(|file| temp_path(Path::new(&file.path), id))
+        .collect();
+    let remove_temps = || {
+        for temp in &temps {
+            let _ = fs::remove_file(temp);
+        }
+    };
+    for (file, temp) in files.iter().zip(&temps) {
+        let written = match Path::new(&file.path).parent() {
+            Some(parent) if !parent.as_os_str().is_empty() => fs::create_dir_all(parent),
+            _ => Ok(()),
+        }
+        .and_then(|_| fs::write(temp, &file.content));
+        if let Err(e) = written {
+            remove_temps();
+            return Err(format!(
+                "Cannot write {}: {}, nothing was written",
+                file.path, e
+            ));
+        }
+    }
+
+    for (index, (file, temp)) in files.iter().zip(&temps).enumerate() {
+        if let Err(e) = fs::rename(temp, &file.path) {
+            restore(&files[..index]);
+            remove_temps();
+            return Err(format!(
+                "Cannot replace {}: {}, the files already replaced were restored",
+                file.path, e
+            ));
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_commit_is_all_or_nothing() {
+        let dir = std::env::temp_dir().join(format!("stakpak-changes-{}", std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+        let main = dir.join("main.tf").to_string_lossy().to_string();
+        let vars = dir.join("modules/vars.tf").to_string_lossy().to_string();
+        fs::write(&main, "old").unwrap();
+
+        let changes = ChangeSet::default();
+        assert!(!changes.stage(&main, Some("old"), "new"));
+        changes.begin().unwrap();
+        assert!(changes.stage(&main, Some("old"), "new"));
+        assert!(changes.stage(&vars, None, "variable \"region\" {}"));
+        assert_eq!(changes.read(&main).unwrap(), "new");
+        assert_eq!(fs::read_to_string(&main).unwrap(), "old");
+
+        // Edited outside the transaction, so committing must not overwrite it
+        fs::write(&main, "edited by the user").unwrap();
+        assert!(changes.commit().is_err());
+        assert!(!Path::new(&vars).exists());
+
+        fs::write(&main, "old").unwrap();
+        changes.begin().unwrap();
+        changes.stage(&main, Some("old"), "new");
+        changes.stage(&vars, None, "variable \"region\" {}");
+        assert_eq!(changes.commit().map(|files| files.len()), Ok(2));
+        assert_eq!(fs::read_to_string(&main).unwrap(), "new");
+        assert!(Path::new(&vars).exists());
+
+        let _ = fs::remove_dir_all(&dir);
+    }
+}
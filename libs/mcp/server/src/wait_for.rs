@@ -0,0 +1,167 @@
+use std::time::Duration;
+
+pub const DEFAULT_TIMEOUT: Duration = Duration::from_secs(300);
+/// Longest a single wait may block the agent
+pub const MAX_TIMEOUT: Duration = Duration::from_secs(1800);
+pub const DEFAULT_INTERVAL: Duration = Duration::from_secs(5);
+const MIN_INTERVAL: Duration = Duration::from_secs(1);
+
+/// Condition the `wait_for` tool polls until it holds
+#[derive(Debug, PartialEq)]
+pub enum WaitCondition {
+    /// Shell command exiting with 0
+    Command(String),
+    /// URL answering with HTTP 200
+    Url(String),
+    /// Pod name or label selector whose pods are all Ready
+    PodReady {
+        pod: String,
+        namespace: Option<String>,
+        context: Option<String>,
+    },
+}
+
+impl WaitCondition {
+    /// Build the condition from the tool arguments, exactly one of `command`, `url` and
+    /// `pod` must be given
+    pub fn from_args(
+        command: Option<String>,
+        url: Option<String>,
+        pod: Option<String>,
+        namespace: Option<String>,
+        context: Option<String>,
+    ) -> Result<Self, String> {
+        match (command, url, pod) {
+            (Some(command), None, None) => Ok(WaitCondition::Command(command)),
+            (None, Some(url), None) => {
+                let is_http = url.starts_with("http://") || url.starts_with("https://");
+                // The URL ends up single-quoted in a shell command line
+                if !is_http || url.contains(['\'', ' ', '\n']) {
+                    return Err(format!("Invalid URL: {}", url));
+                }
+                Ok(WaitCondition::Url(url))
+            }
+            (None, None, Some(pod)) => {
+                let is_safe = |value: &str| {
+                    !value.is_empty()
+                        && value.chars().all(|c| {
+                            c.is_ascii_alphanumeric()
+                                || matches!(c, '-' | '.' | '_' | '/' | ':' | '@' | '=' | ',')
+                        })
+                };
+                for (name, value) in [
+                    ("pod", Some(pod.as_str())),
+                    ("namespace", namespace.as_deref()),
+                    ("context", context.as_deref()),
+                ] {
+                    if let Some(value) = value.filter(|value| !is_safe(value)) {
+                        return Err(format!("Invalid {}: {}", name, value));
+                    }
+                }
+                Ok(WaitCondition::PodReady {
+                    pod,
+                    namespace,
+                    context,
+                })
+            }
+            _ => Err("Exactly one of command, url and pod is required".to_string()),
+        }
+    }
+
+    /// Shell command line checking the condition once, it holds when the line exits with 0
+    pub fn probe_command(&self, probe_timeout: Duration) -> String {
+        let seconds = probe_timeout.as_secs().max(1);
+        match self {
+            WaitCondition::Command(command) => command.clone(),
+            WaitCondition::Url(url) => format!(
+                "code=$(curl -s -o /dev/null -w '%{{http_code}}' --max-time {} '{}'); echo \"HTTP $code\"; [ \"$code\" = 200 ]",
+                seconds, url
+            ),
+            WaitCondition::PodReady {
+                pod,
+                namespace,
+                context,
+            } => {
+                let mut command = "kubectl".to_string();
+                if let Some(context) = context {
+                    command.push_str(&format!(" --context {}", context));
+                }
+                if let Some(namespace) = namespace {
+                    command.push_str(&format!(" --namespace {}", namespace));
+                }
+                // A name=value pair is a label selector, anything else a pod name
+                let target = if pod.contains('=') {
+                    format!("pod -l {}", pod)
+                } else {
+                    format!("pod/{}", pod.trim_start_matches("pod/"))
+                };
+                format!(
+                    "{} wait --for=condition=Ready {} --timeout={}s",
+                    command, target, seconds
+                )
+            }
+        }
+    }
+
+    pub fn describe(&self) -> String {
+        match self {
+            WaitCondition::Command(command) => format!("'{}' to succeed", command),
+            WaitCondition::Url(url) => format!("{} to return 200", url),
+            WaitCondition::PodReady { pod, .. } => format!("pod {} to be Ready", pod),
+        }
+    }
+}
+
+/// Clamp the requested timeout and check interval to sane bounds
+pub fn wait_bounds(
+    timeout_seconds: Option<u64>,
+    interval_seconds: Option<u64>,
+) -> (Duration, Duration) {
+    let timeout = timeout_seconds
+        .map(Duration::from_secs)
+        .unwrap_or(DEFAULT_TIMEOUT)
+        .min(MAX_TIMEOUT);
+    let interval = interval_seconds
+        .map(Duration::from_secs)
+        .unwrap_or(DEFAULT_INTERVAL)
+        .clamp(MIN_INTERVAL, timeout.max(MIN_INTERVAL));
+    (timeout, interval)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_wait_condition_probe_command() {
+        let pod = WaitCondition::from_args(
+            None,
+            None,
+            Some("app=api".to_string()),
+            Some("prod".to_string()),
+            None,
+        );
+        assert_eq!(
+            pod.map(|condition| condition.probe_command(Duration::from_secs(5))),
+            Ok(
+                "kubectl --namespace prod wait --for=condition=Ready pod -l app=api --timeout=5s"
+                    .to_string()
+            )
+        );
+        assert!(
+            WaitCondition::from_args(
+                None,
+                Some("http://x'; rm -rf /".to_string()),
+                None,
+                None,
+                None
+            )
+            .is_err()
+        );
+        assert!(WaitCondition::from_args(None, None, None, None, None).is_err());
+        assert_eq!(
+            wait_bounds(Some(7200), Some(0)),
+            (MAX_TIMEOUT, MIN_INTERVAL)
+        );
+    }
+}
@@ -0,0 +1,96 @@
+use serde::{Deserialize, Serialize};
+
+const METADATA_START: &str = "<command_metadata>";
+const METADATA_END: &str = "</command_metadata>";
+/// How results of commands run before the metadata existed reported failures
+const LEGACY_EXIT_PREFIX: &str = "Command exited with code ";
+
+/// How and where a `run_command` call ran, appended to its result as JSON between
+/// `<command_metadata>` tags so the TUI, audit log and model read it without parsing prose
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct CommandMetadata {
+    pub cwd: String,
+    /// `None` when the command was killed by a signal
+    pub exit_code: Option<i32>,
+    pub duration_ms: u64,
+    /// Shell the command ran in, e.g. `sh`, `sh (sandboxed)` or `sh in <container>`
+    pub shell: String,
+    /// Whether the output was cut to its last lines
+    pub truncated: bool,
+    /// File the full output was saved to when it was truncated
+    pub output_file: Option<String>,
+    /// Whether the output was served from the command cache instead of running the command
+    #[serde(default)]
+    pub cached: bool,
+    /// Whether the command was killed for running longer than its timeout
+    #[serde(default)]
+    pub timed_out: bool,
+}
+
+impl CommandMetadata {
+    pub fn to_tagged(&self) -> String {
+        format!(
+            "{}{}{}",
+            METADATA_START,
+            serde_json::to_string(self).unwrap_or_default(),
+            METADATA_END
+        )
+    }
+
+    /// Metadata of a `run_command` result, the last one when results were concatenated
+    pub fn parse(result: &str) -> Option<Self> {
+        let start = result.rfind(METADATA_START)? + METADATA_START.len();
+        let end = start + result[start..].find(METADATA_END)?;
+        serde_json::from_str(&result[start..end]).ok()
+    }
+
+    pub fn failed(&self) -> bool {
+        self.exit_code != Some(0)
+    }
+}
+
+/// The output of a `run_command` result without its metadata
+pub fn strip_command_metadata(result: &str) -> String {
+    result
+        .lines()
+        .filter(|line| !(line.starts_with(METADATA_START) && line.ends_with(METADATA_END)))
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+/// Whether a `run_command` result reports a failed command, also for results recorded
+/// before they carried metadata
+pub fn command_failed(result: &str) -> bool {
+    match CommandMetadata::parse(result) {
+        Some(metadata) => metadata.failed(),
+        None => result
+            .lines()
+            .any(|line| line.starts_with(LEGACY_EXIT_PREFIX)),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_command_metadata() {
+        let metadata = CommandMetadata {
+            cwd: "/src/infra".to_string(),
+            exit_code: Some(1),
+            duration_ms: 1200,
+            shell: "sh".to_string(),
+            truncated: false,
+            output_file: None,
+            cached: false,
+            timed_out: false,
+        };
+        let result = format!("Error: no such file\n{}", metadata.to_tagged());
+
+        assert_eq!(CommandMetadata::parse(&result), Some(metadata));
+        assert!(command_failed(&result));
+        assert_eq!(strip_command_metadata(&result), "Error: no such file");
+        assert!(command_failed("boom\nCommand exited with code 2\n"));
+        assert!(!command_failed("ok"));
+    }
+}
@@ -0,0 +1,321 @@
+use crate::local_store::LocalStore;
+use serde::{Deserialize, Serialize};
+use similar::{DiffTag, TextDiff};
+use std::collections::hash_map::DefaultHasher;
+use std::hash::{Hash, Hasher};
+use std::path::Path;
+
+const FILE_HISTORY_DIR: &str = "file_history";
+
+/// A version of a file as left by an agent tool
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct FileVersion {
+    /// Tool that produced this version, `original` for the content before the first edit
+    pub tool: String,
+    /// File content, `None` when the file did not exist
+    pub content: Option<String>,
+    /// Conversation turn the edit was made in, when known
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub turn: Option<EditTurn>,
+}
+
+/// Conversation turn an agent edit was made in
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct EditTurn {
+    /// Number of the user message the edit answered, starting at 1
+    pub turn: usize,
+    pub tool_call_id: String,
+    /// Start of the user message
+    pub prompt: String,
+    /// Start of the assistant message that made the tool call
+    pub reasoning: String,
+}
+
+/// Where a line of a file comes from
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum LineOrigin {
+    /// Present before the first agent edit
+    Original,
+    /// Added by the agent edit producing this version index
+    Version(usize),
+    /// Changed after the last agent edit, e.g. by the user
+    Outside,
+}
+
+/// Consecutive lines of a file sharing one origin
+#[derive(Debug, Clone, PartialEq)]
+pub struct BlameHunk {
+    /// Line number of the first line, starting at 1
+    pub start: usize,
+    pub lines: Vec<String>,
+    pub origin: LineOrigin,
+}
+
+/// Origins of the lines of `new`, carrying over the origins of lines kept from `old`
+fn carry_origins(
+    old: &str,
+    new: &str,
+    origins: &[LineOrigin],
+    edit: LineOrigin,
+) -> Vec<LineOrigin> {
+    let mut carried = Vec::new();
+    for op in TextDiff::from_lines(old, new).ops() {
+        let (tag, old_range, new_range) = op.as_tag_tuple();
+        match tag {
+            DiffTag::Equal => carried.extend(
+                old_range.map(|index| origins.get(index).copied().unwrap_or(LineOrigin::Original)),
+            ),
+            DiffTag::Insert | DiffTag::Replace => {
+                carried.extend(std::iter::repeat_n(edit, new_range.len()))
+            }
+            DiffTag::Delete => {}
+        }
+    }
+    carried
+}
+
+/// Every version of a file touched by agent tools during the current session
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct FileHistory {
+    pub path: String,
+    pub versions: Vec<FileVersion>,
+}
+
+impl FileHistory {
+    pub fn new(path: String) -> Self {
+        Self {
+            path,
+            versions: Vec::new(),
+        }
+    }
+
+    /// Load the history of `path` from the session store, `None` when the file was never
+    /// edited by an agent tool in this session
+    pub fn load(path: &str) -> Result<Option<Self>, String> {
+        let path = normalize_path(path);
+        match LocalStore::read_session_data(&history_file(&path)) {
+            Ok(content) => serde_json::from_str(&content)
+                .map(Some)
+                .map_err(|e| format!("Failed to parse file history of {}: {}", path, e)),
+            Err(_) => Ok(None),
+        }
+    }
+
+    pub fn save(&self) -> Result<(), String> {
+        let content = serde_json::to_string(self).map_err(|e| e.to_string())?;
+        LocalStore::write_session_data(&history_file(&self.path), &content)?;
+        Ok(())
+    }
+
+    /// Add a new version, recording the content before the first edit as the original
+    pub fn push_version(&mut self, tool: &str, before: Option<&str>, after: &str) {
+        if self.versions.is_empty() {
+            self.versions.push(FileVersion {
+                tool: "original".to_string(),
+                content: before.map(|content| content.to_string()),
+                turn: None,
+            });
+        }
+        self.versions.push(FileVersion {
+            tool: tool.to_string(),
+            content: Some(after.to_string()),
+            turn: None,
+        });
+    }
+
+    /// Attribute each line of `current`, the file as it is now, to the agent edit that
+    /// introduced it. Without `current` the latest recorded version is blamed.
+    pub fn blame(&self, current: Option<&str>) -> Vec<BlameHunk> {
+        let Some(first) = self.versions.first() else {
+            return Vec::new();
+        };
+        let mut previous = first.content.as_deref().unwrap_or("");
+        let mut origins = vec![LineOrigin::Original; previous.lines().count()];
+        for (index, version) in self.versions.iter().enumerate().skip(1) {
+            let content = version.content.as_deref().unwrap_or("");
+            origins = carry_origins(previous, content, &origins, LineOrigin::Version(index));
+            previous = content;
+        }
+        let content = match current {
+            Some(current) if current != previous => {
+                origins = carry_origins(previous, current, &origins, LineOrigin::Outside);
+                current
+            }
+            _ => previous,
+        };
+
+        let mut hunks: Vec<BlameHunk> = Vec::new();
+        for (index, (line, origin)) in content.lines().zip(origins).enumerate() {
+            match hunks.last_mut() {
+                Some(hunk) if hunk.origin == origin => hunk.lines.push(line.to_string()),
+                _ => hunks.push(BlameHunk {
+                    start: index + 1,
+                    lines: vec![line.to_string()],
+                    origin,
+                }),
+            }
+        }
+        hunks
+    }
+
+    /// Short description of where lines of `origin` come from, e.g. `turn 3 · str_replace`
+    pub fn origin_label(&self, origin: LineOrigin) -> String {
+        match origin {
+            LineOrigin::Original => "original".to_string(),
+            LineOrigin::Outside => "changed outside the agent".to_string(),
+            LineOrigin::Version(index) => match self.versions.get(index) {
+                Some(FileVersion {
+                    tool,
+                    turn: Some(turn),
+                    ..
+                }) => format!("turn {} · {} ({})", turn.turn, tool, turn.tool_call_id),
+                Some(version) => format!("{} (turn unknown)", version.tool),
+                None => "unknown".to_string(),
+            },
+        }
+    }
+
+    /// Conversation turn behind lines of `origin`, when recorded
+    pub fn origin_turn(&self, origin: LineOrigin) -> Option<&EditTurn> {
+        match origin {
+            LineOrigin::Version(index) => self.versions.get(index)?.turn.as_ref(),
+            _ => None,
+        }
+    }
+}
+
+/// Record a new version of `path` written by `tool` in the session file history
+pub fn record_file_version(
+    path: &str,
+    tool: &str,
+    before: Option<&str>,
+    after: &str,
+) -> Result<(), String> {
+    let mut history =
+        FileHistory::load(path)?.unwrap_or_else(|| FileHistory::new(normalize_path(path)));
+    history.push_version(tool, before, after);
+    history.save()
+}
+
+/// Record the conversation turn behind the latest version of `path`, unless it already has one
+pub fn attribute_latest_version(path: &str, turn: EditTurn) -> Result<(), String> {
+    let Some(mut history) = FileHistory::load(path)? else {
+        return Ok(());
+    };
+    match history.versions.last_mut() {
+        Some(version) if version.turn.is_none() && version.tool != "original" => {
+            version.turn = Some(turn);
+            history.save()
+        }
+        _ => Ok(()),
+    }
+}
+
+/// Paths of the files edited by agent tools in this session, most recently edited first
+pub fn recent_files(limit: usize) -> Vec<String> {
+    let dir = LocalStore::get_local_session_store_path().join(FILE_HISTORY_DIR);
+    let Ok(entries) = std::fs::read_dir(dir) else {
+        return Vec::new();
+    };
+    let mut histories: Vec<_> = entries
+        .flatten()
+        .filter_map(|entry| {
+            let modified = entry.metadata().and_then(|m| m.modified()).ok()?;
+            let content = std::fs::read_to_string(entry.path()).ok()?;
+            let history: FileHistory = serde_json::from_str(&content).ok()?;
+            Some((modified, history.path))
+        })
+        .collect();
+    histories.sort_by(|a, b| b.0.cmp(&a.0));
+    histories
+        .into_iter()
+        .take(limit)
+        .map(|(_, path)| path)
+        .collect()
+}
+
+/// Absolute form of `path` so relative and absolute references share one history
+fn normalize_path(path: &str) -> String {
+    std::fs::canonicalize(path)
+        .or_else(|_| std::env::current_dir().map(|cwd| cwd.join(Path::new(path))))
+        .map(|path| path.to_string_lossy().to_string())
+        .unwrap_or_else(|_| path.to_string())
+}
+
+fn history_file(path: &str) -> String {
+    let mut hasher = DefaultHasher::new();
+    path.hash(&mut hasher);
+    format!("{}/{:016x}.json", FILE_HISTORY_DIR, hasher.finish())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_first_version_records_original() {
+        let mut history = FileHistory::new("/tmp/main.tf".to_string());
+        history.push_version("create", None, "a");
+        history.push_version("str_replace", Some("a"), "b");
+
+        assert_eq!(
+            history.versions,
+            vec![
+                FileVersion {
+                    tool: "original".to_string(),
+                    content: None,
+                    turn: None,
+                },
+                FileVersion {
+                    tool: "create".to_string(),
+                    content: Some("a".to_string()),
+                    turn: None,
+                },
+                FileVersion {
+                    tool: "str_replace".to_string(),
+                    content: Some("b".to_string()),
+                    turn: None,
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn test_blame_attributes_lines_to_versions() {
+        let mut history = FileHistory::new("/tmp/main.tf".to_string());
+        history.push_version("str_replace", Some("a\nb\nc\n"), "a\nB\nc\n");
+        history.push_version("insert", Some("a\nB\nc\n"), "a\nB\nc\nd\ne\n");
+
+        let origins = |hunks: Vec<BlameHunk>| {
+            hunks
+                .into_iter()
+                .map(|hunk| (hunk.start, hunk.lines.len(), hunk.origin))
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(
+            origins(history.blame(None)),
+            vec![
+                (1, 1, LineOrigin::Original),
+                (2, 1, LineOrigin::Version(1)),
+                (3, 1, LineOrigin::Original),
+                (4, 2, LineOrigin::Version(2)),
+            ]
+        );
+        assert_eq!(
+            origins(history.blame(Some("x\na\nB\nc\nd\ne\n"))),
+            vec![
+                (1, 1, LineOrigin::Outside),
+                (2, 1, LineOrigin::Original),
+                (3, 1, LineOrigin::Version(1)),
+                (4, 1, LineOrigin::Original),
+                (5, 2, LineOrigin::Version(2)),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_history_file_is_stable_per_path() {
+        assert_eq!(history_file("/a/main.tf"), history_file("/a/main.tf"));
+        assert_ne!(history_file("/a/main.tf"), history_file("/b/main.tf"));
+    }
+}
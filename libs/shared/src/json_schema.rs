@@ -0,0 +1,153 @@
+use serde_json::Value;
+
+/// Check `value` against the subset of JSON Schema used for structured model outputs:
+/// `type`, `properties`, `required`, `additionalProperties: false`, `items` and `enum`.
+///
+/// Returns every violation found, each prefixed with the JSON path it occurred at.
+pub fn validate(value: &Value, schema: &Value) -> Result<(), Vec<String>> {
+    let mut errors = Vec::new();
+    validate_at("$", value, schema, &mut errors);
+    if errors.is_empty() {
+        Ok(())
+    } else {
+        Err(errors)
+    }
+}
+
+fn validate_at(path: &str, value: &Value, schema: &Value, errors: &mut Vec<String>) {
+    if let Some(expected) = schema.get("type") {
+        let matches = match expected {
+            Value::String(expected) => matches_type(value, expected),
+            Value::Array(expected) => expected
+                .iter()
+                .filter_map(|t| t.as_str())
+                .any(|t| matches_type(value, t)),
+            _ => true,
+        };
+        if !matches {
+            errors.push(format!(
+                "{}: expected {}, got {}",
+                path,
+                expected,
+                type_name(value)
+            ));
+            return;
+        }
+    }
+
+    if let Some(allowed) = schema
+        .get("enum")
+        .and_then(|e| e.as_array())
+        .filter(|allowed| !allowed.contains(value))
+    {
+        errors.push(format!(
+            "{}: {} is not one of {}",
+            path,
+            value,
+            Value::Array(allowed.clone())
+        ));
+    }
+
+    if let Value::Object(object) = value {
+        let properties = schema.get("properties").and_then(|p| p.as_object());
+
+        if let Some(required) = schema.get("required").and_then(|r| r.as_array()) {
+            for key in required.iter().filter_map(|k| k.as_str()) {
+                if !object.contains_key(key) {
+                    errors.push(format!("{}: missing required property '{}'", path, key));
+                }
+            }
+        }
+
+        let closed = schema.get("additionalProperties") == Some(&Value::Bool(false));
+        for (key, field) in object {
+            match properties.and_then(|p| p.get(key)) {
+                Some(field_schema) => {
+                    validate_at(&format!("{}.{}", path, key), field, field_schema, errors)
+                }
+                None if closed => {
+                    errors.push(format!("{}: unexpected property '{}'", path, key));
+                }
+                None => {}
+            }
+        }
+    }
+
+    if let (Value::Array(items), Some(item_schema)) = (value, schema.get("items")) {
+        for (index, item) in items.iter().enumerate() {
+            validate_at(&format!("{}[{}]", path, index), item, item_schema, errors);
+        }
+    }
+}
+
+fn matches_type(value: &Value, expected: &str) -> bool {
+    match expected {
+        "object" => value.is_object(),
+        "array" => value.is_array(),
+        "string" => value.is_string(),
+        "number" => value.is_number(),
+        "integer" => value.is_i64() || value.is_u64(),
+        "boolean" => value.is_boolean(),
+        "null" => value.is_null(),
+        _ => true,
+    }
+}
+
+fn type_name(value: &Value) -> &'static str {
+    match value {
+        Value::Null => "null",
+        Value::Bool(_) => "boolean",
+        Value::Number(_) => "number",
+        Value::String(_) => "string",
+        Value::Array(_) => "array",
+        Value::Object(_) => "object",
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn schema() -> Value {
+        json!({
+            "type": "object",
+            "properties": {
+                "severity": {"type": "string", "enum": ["low", "high"]},
+                "findings": {
+                    "type": "array",
+                    "items": {
+                        "type": "object",
+                        "properties": {"line": {"type": "integer"}},
+                        "required": ["line"],
+                        "additionalProperties": false
+                    }
+                }
+            },
+            "required": ["severity", "findings"],
+            "additionalProperties": false
+        })
+    }
+
+    #[test]
+    fn test_valid_document() {
+        let value = json!({"severity": "low", "findings": [{"line": 3}]});
+        assert_eq!(validate(&value, &schema()), Ok(()));
+    }
+
+    #[test]
+    fn test_reports_every_violation_with_path() {
+        let value = json!({"severity": "medium", "findings": [{"line": "3"}, {}], "extra": 1});
+        let mut errors = validate(&value, &schema()).unwrap_err();
+        errors.sort();
+        assert_eq!(
+            errors,
+            vec![
+                "$.findings[0].line: expected \"integer\", got string".to_string(),
+                "$.findings[1]: missing required property 'line'".to_string(),
+                "$.severity: \"medium\" is not one of [\"low\",\"high\"]".to_string(),
+                "$: unexpected property 'extra'".to_string(),
+            ]
+        );
+    }
+}
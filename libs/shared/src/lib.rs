@@ -1,3 +1,13 @@
+pub mod command_result;
+pub mod file_history;
+pub mod json_schema;
 pub mod local_store;
 pub mod models;
+pub mod policy;
+pub mod policy_rules;
 pub mod secrets;
+pub mod session_env;
+pub mod session_tmp;
+pub mod uri;
+pub mod workspace;
+pub mod workspace_policy;
@@ -1,7 +1,139 @@
 use std::{
-    fs,
+    fs::{self, File, OpenOptions},
+    io::Write,
     path::{Path, PathBuf},
+    sync::{
+        Once, OnceLock,
+        atomic::{AtomicBool, Ordering},
+    },
 };
+use uuid::Uuid;
+
+const GITIGNORE_ENTRY: &str = ".stakpak/";
+/// Directory holding the data private to each session, under the workspace store
+const SESSIONS_DIR: &str = "sessions";
+const LOCK_SUFFIX: &str = ".lock";
+
+static MANAGE_GITIGNORE: AtomicBool = AtomicBool::new(true);
+static GITIGNORE_CHECKED: Once = Once::new();
+static SESSION_ID: OnceLock<String> = OnceLock::new();
+
+/// Who stored data is shared with
+#[derive(Debug, Clone, PartialEq)]
+pub enum Namespace {
+    /// Every stakpak process working in the current directory
+    Workspace,
+    /// A single session, other processes in the workspace never see its files
+    Session(String),
+}
+
+/// Files of one namespace of the local store. Writes are atomic, readers never see a
+/// partially written file, and every access takes an advisory lock on a `<name>.lock`
+/// file next to the data so concurrent processes sharing a workspace do not lose updates.
+#[derive(Debug, Clone)]
+pub struct NamespacedStore {
+    root: PathBuf,
+}
+
+impl NamespacedStore {
+    pub fn root(&self) -> &Path {
+        &self.root
+    }
+
+    pub fn path(&self, name: &str) -> PathBuf {
+        self.root.join(name)
+    }
+
+    pub fn read(&self, name: &str) -> Result<String, String> {
+        let path = self.path(name);
+        let _lock = self.lock(&path, false)?;
+        fs::read_to_string(&path)
+            .map_err(|e| format!("Failed to read session data from {}: {}", path.display(), e))
+    }
+
+    /// Replace the content of `name`, returning the path it was written to
+    pub fn write(&self, name: &str, data: &str) -> Result<String, String> {
+        let path = self.path(name);
+        let _lock = self.lock(&path, true)?;
+        write_atomic(&path, data)?;
+        Ok(path.to_string_lossy().to_string())
+    }
+
+    /// Read-modify-write `name` under an exclusive lock. `update` gets the current content,
+    /// `None` when the file does not exist, and returns the new content along with a value
+    /// passed back to the caller. Nothing is written when `update` fails.
+    pub fn update<T>(
+        &self,
+        name: &str,
+        update: impl FnOnce(Option<String>) -> Result<(String, T), String>,
+    ) -> Result<T, String> {
+        let path = self.path(name);
+        let _lock = self.lock(&path, true)?;
+        let current = match fs::read_to_string(&path) {
+            Ok(content) => Some(content),
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => None,
+            Err(e) => {
+                return Err(format!(
+                    "Failed to read session data from {}: {}",
+                    path.display(),
+                    e
+                ));
+            }
+        };
+        let (content, value) = update(current)?;
+        write_atomic(&path, &content)?;
+        Ok(value)
+    }
+
+    /// Lock guarding `path`, shared for readers and exclusive for writers. The lock is
+    /// released when the returned file is dropped.
+    fn lock(&self, path: &Path, exclusive: bool) -> Result<File, String> {
+        if let Some(dir) = path.parent() {
+            fs::create_dir_all(dir)
+                .map_err(|e| format!("Failed to create session directory: {}", e))?;
+        }
+        let mut lock_path = path.as_os_str().to_owned();
+        lock_path.push(LOCK_SUFFIX);
+        let file = OpenOptions::new()
+            .create(true)
+            .truncate(false)
+            .write(true)
+            .open(&lock_path)
+            .map_err(|e| format!("Failed to open lock file for {}: {}", path.display(), e))?;
+        let locked = if exclusive {
+            file.lock()
+        } else {
+            file.lock_shared()
+        };
+        locked.map_err(|e| format!("Failed to lock {}: {}", path.display(), e))?;
+        Ok(file)
+    }
+}
+
+/// Write to a temporary file in the same directory and rename it over `path`, so the
+/// file is either fully replaced or left untouched
+fn write_atomic(path: &Path, data: &str) -> Result<(), String> {
+    let file_name = path
+        .file_name()
+        .map(|name| name.to_string_lossy().to_string())
+        .unwrap_or_default();
+    let tmp_path = path.with_file_name(format!(".{}.{}.tmp", file_name, Uuid::new_v4()));
+    let written = File::create(&tmp_path)
+        .and_then(|mut file| {
+            file.write_all(data.as_bytes())?;
+            file.sync_all()
+        })
+        .and_then(|_| fs::rename(&tmp_path, path));
+    if let Err(e) = written {
+        let _ = fs::remove_file(&tmp_path);
+        return Err(format!(
+            "Failed to write session data to {}: {}",
+            path.display(),
+            e
+        ));
+    }
+    Ok(())
+}
 
 pub struct LocalStore {}
 
@@ -10,22 +142,173 @@ impl LocalStore {
         Path::new(".stakpak").join("session")
     }
 
-    pub fn write_session_data(path: &str, data: &str) -> Result<String, String> {
-        let session_dir = Self::get_local_session_store_path();
-        if !session_dir.exists() {
-            std::fs::create_dir_all(&session_dir)
-                .map_err(|e| format!("Failed to create session directory: {}", e))?;
+    /// Enable or disable adding `.stakpak/` to the repository `.gitignore` on first write
+    pub fn set_manage_gitignore(enabled: bool) {
+        MANAGE_GITIGNORE.store(enabled, Ordering::SeqCst);
+    }
+
+    /// Id of this process's session namespace, a random one unless set before first use
+    pub fn session_id() -> &'static str {
+        SESSION_ID.get_or_init(|| Uuid::new_v4().to_string())
+    }
+
+    /// Name the session namespace after the agent session, has no effect once it was used
+    pub fn set_session_id(id: &str) {
+        let _ = SESSION_ID.set(id.to_string());
+    }
+
+    pub fn namespace(namespace: Namespace) -> NamespacedStore {
+        if MANAGE_GITIGNORE.load(Ordering::SeqCst) {
+            // Failing to update .gitignore should never block writing session data
+            GITIGNORE_CHECKED.call_once(|| {
+                let _ = Self::ensure_gitignored();
+            });
         }
 
-        let path = Self::get_local_session_store_path().join(path);
-        std::fs::write(&path, data)
-            .map_err(|e| format!("Failed to write session data to {}: {}", path.display(), e))?;
-        Ok(path.to_string_lossy().to_string())
+        let workspace = Self::get_local_session_store_path();
+        let root = match namespace {
+            Namespace::Workspace => workspace,
+            Namespace::Session(id) => workspace.join(SESSIONS_DIR).join(id),
+        };
+        NamespacedStore { root }
+    }
+
+    /// Store shared by every process in the workspace
+    pub fn workspace() -> NamespacedStore {
+        Self::namespace(Namespace::Workspace)
+    }
+
+    /// Store private to the current session
+    pub fn session() -> NamespacedStore {
+        Self::namespace(Namespace::Session(Self::session_id().to_string()))
+    }
+
+    /// Store in `~/.stakpak`, shared by every session of the user whatever directory it
+    /// works in. Fails without a home directory rather than sharing files of the current one
+    pub fn user() -> Result<NamespacedStore, String> {
+        let home = std::env::var("HOME").unwrap_or_default();
+        if home.trim().is_empty() {
+            return Err("HOME is not set, the user store in ~/.stakpak can't be found".to_string());
+        }
+        Ok(NamespacedStore {
+            root: PathBuf::from(home).join(".stakpak"),
+        })
+    }
+
+    pub fn write_session_data(path: &str, data: &str) -> Result<String, String> {
+        Self::workspace().write(path, data)
     }
 
     pub fn read_session_data(path: &str) -> Result<String, String> {
-        let path = Self::get_local_session_store_path().join(path);
-        fs::read_to_string(&path)
-            .map_err(|e| format!("Failed to read session data from {}: {}", path.display(), e))
+        Self::workspace().read(path)
+    }
+
+    /// Read-modify-write a file of the workspace store, see [`NamespacedStore::update`]
+    pub fn update_session_data<T>(
+        path: &str,
+        update: impl FnOnce(Option<String>) -> Result<(String, T), String>,
+    ) -> Result<T, String> {
+        Self::workspace().update(path, update)
+    }
+
+    /// Make sure `.stakpak/` is ignored by the git repository containing the current
+    /// directory. Directories outside a git repository are left untouched.
+    pub fn ensure_gitignored() -> Result<(), String> {
+        let cwd = std::env::current_dir().map_err(|e| e.to_string())?;
+        let Some(repo_root) = cwd.ancestors().find(|dir| dir.join(".git").exists()) else {
+            return Ok(());
+        };
+
+        Self::ensure_gitignored_in(repo_root)
+    }
+
+    /// Add the `.stakpak/` entry to the `.gitignore` of `dir`, creating it if needed
+    pub fn ensure_gitignored_in(dir: &Path) -> Result<(), String> {
+        let gitignore_path = dir.join(".gitignore");
+        let existing = fs::read_to_string(&gitignore_path).ok();
+        if let Some(content) = gitignore_with_stakpak(existing.as_deref()) {
+            fs::write(&gitignore_path, content)
+                .map_err(|e| format!("Failed to update {}: {}", gitignore_path.display(), e))?;
+        }
+        Ok(())
+    }
+}
+
+/// Return the `.gitignore` content with the `.stakpak/` entry appended, or `None` when
+/// the directory is already ignored
+fn gitignore_with_stakpak(existing: Option<&str>) -> Option<String> {
+    let existing = existing.unwrap_or_default();
+    let already_ignored = existing.lines().any(|line| {
+        matches!(
+            line.trim(),
+            ".stakpak" | ".stakpak/" | "/.stakpak" | "/.stakpak/" | ".stakpak/*" | ".stakpak/**"
+        )
+    });
+    if already_ignored {
+        return None;
+    }
+
+    let mut content = existing.to_string();
+    if !content.is_empty() {
+        if !content.ends_with('\n') {
+            content.push('\n');
+        }
+        content.push('\n');
+    }
+    content.push_str("# Stakpak session data (may contain secrets)\n");
+    content.push_str(GITIGNORE_ENTRY);
+    content.push('\n');
+    Some(content)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_gitignore_created_when_missing() {
+        assert_eq!(
+            gitignore_with_stakpak(None),
+            Some("# Stakpak session data (may contain secrets)\n.stakpak/\n".to_string())
+        );
+    }
+
+    #[test]
+    fn test_gitignore_appended_with_comment() {
+        assert_eq!(
+            gitignore_with_stakpak(Some("target")),
+            Some("target\n\n# Stakpak session data (may contain secrets)\n.stakpak/\n".to_string())
+        );
+    }
+
+    #[test]
+    fn test_concurrent_updates_are_not_lost() {
+        let store = NamespacedStore {
+            root: std::env::temp_dir().join(format!("stakpak-store-{}", Uuid::new_v4())),
+        };
+        let handles: Vec<_> = (0..8)
+            .map(|_| {
+                let store = store.clone();
+                std::thread::spawn(move || {
+                    store.update("counter", |current| {
+                        let count = current.and_then(|c| c.parse::<u32>().ok()).unwrap_or(0);
+                        Ok(((count + 1).to_string(), ()))
+                    })
+                })
+            })
+            .collect();
+        for handle in handles {
+            assert!(matches!(handle.join(), Ok(Ok(()))));
+        }
+        let count = store.read("counter");
+        let _ = fs::remove_dir_all(store.root());
+        assert_eq!(count, Ok("8".to_string()));
+    }
+
+    #[test]
+    fn test_gitignore_is_idempotent() {
+        let content = gitignore_with_stakpak(Some("target\n")).unwrap();
+        assert_eq!(gitignore_with_stakpak(Some(&content)), None);
+        assert_eq!(gitignore_with_stakpak(Some("/.stakpak\n")), None);
     }
 }
@@ -50,6 +50,8 @@ pub struct ChatCompletionRequest {
     #[serde(skip_serializing_if = "Option::is_none")]
     pub stream: Option<bool>,
     #[serde(skip_serializing_if = "Option::is_none")]
+    pub stream_options: Option<StreamOptions>,
+    #[serde(skip_serializing_if = "Option::is_none")]
     pub temperature: Option<f32>,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub top_p: Option<f32>,
@@ -77,6 +79,10 @@ impl ChatCompletionRequest {
             response_format: None,
             seed: None,
             stop: None,
+            // Streams only report token usage in a last chunk when asked to
+            stream_options: (stream == Some(true)).then_some(StreamOptions {
+                include_usage: true,
+            }),
             stream,
             temperature: None,
             top_p: None,
@@ -192,9 +198,48 @@ pub struct ImageUrl {
     pub detail: Option<String>,
 }
 
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+pub struct StreamOptions {
+    pub include_usage: bool,
+}
+
 #[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
 pub struct ResponseFormat {
     pub r#type: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub json_schema: Option<JsonSchemaFormat>,
+}
+
+impl ResponseFormat {
+    pub fn json_object() -> Self {
+        Self {
+            r#type: "json_object".to_string(),
+            json_schema: None,
+        }
+    }
+
+    /// Constrain the response to a JSON document matching `schema`
+    pub fn json_schema(name: &str, schema: serde_json::Value) -> Self {
+        Self {
+            r#type: "json_schema".to_string(),
+            json_schema: Some(JsonSchemaFormat {
+                name: name.to_string(),
+                description: None,
+                schema,
+                strict: Some(true),
+            }),
+        }
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+pub struct JsonSchemaFormat {
+    pub name: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub description: Option<String>,
+    pub schema: serde_json::Value,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub strict: Option<bool>,
 }
 
 #[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
@@ -379,6 +424,9 @@ pub struct ChatCompletionStreamResponse {
     pub created: u64,
     pub model: String,
     pub choices: Vec<ChatCompletionStreamChoice>,
+    /// Only set on the last chunk, which has no choices
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub usage: Option<Usage>,
 }
 #[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
 pub struct ChatCompletionStreamChoice {
@@ -512,6 +560,7 @@ mod tests {
             seed: None,
             stop: None,
             stream: None,
+            stream_options: None,
             temperature: Some(0.7),
             top_p: None,
             tools: None,
@@ -621,6 +670,7 @@ mod tests {
             seed: None,
             stop: None,
             stream: None,
+            stream_options: None,
             top_p: None,
             user: None,
             context: None,
@@ -740,9 +790,7 @@ mod tests {
                 tool_calls: None,
                 tool_call_id: None,
             }],
-            response_format: Some(ResponseFormat {
-                r#type: "json_object".to_string(),
-            }),
+            response_format: Some(ResponseFormat::json_object()),
             max_tokens: Some(100),
             temperature: None,
             frequency_penalty: None,
@@ -753,6 +801,7 @@ mod tests {
             seed: None,
             stop: None,
             stream: None,
+            stream_options: None,
             top_p: None,
             tools: None,
             tool_choice: None,
@@ -764,6 +813,27 @@ mod tests {
         assert!(json.contains("\"response_format\":{\"type\":\"json_object\"}"));
     }
 
+    #[test]
+    fn test_json_schema_response_format() {
+        let format = ResponseFormat::json_schema(
+            "triage",
+            serde_json::json!({"type": "object", "properties": {}}),
+        );
+
+        let json = serde_json::to_value(&format).unwrap();
+        assert_eq!(
+            json,
+            serde_json::json!({
+                "type": "json_schema",
+                "json_schema": {
+                    "name": "triage",
+                    "schema": {"type": "object", "properties": {}},
+                    "strict": true
+                }
+            })
+        );
+    }
+
     #[test]
     fn test_llm_message_to_chat_message() {
         // Test simple string content
@@ -0,0 +1,648 @@
+use serde::{Deserialize, Serialize};
+use std::fmt;
+
+/// How much damage a tool call can do, ordered from least to most risky
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum RiskLevel {
+    /// Only reads local or remote state
+    ReadOnly,
+    /// Changes local files or the local environment
+    Write,
+    /// Changes remote infrastructure or services
+    NetworkMutating,
+    /// Deletes data or resources, possibly irreversibly
+    Destructive,
+}
+
+impl RiskLevel {
+    /// High risk actions need an explicit typed confirmation instead of a single key press
+    pub fn requires_typed_confirmation(&self) -> bool {
+        *self >= RiskLevel::NetworkMutating
+    }
+}
+
+impl fmt::Display for RiskLevel {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            RiskLevel::ReadOnly => write!(f, "read-only"),
+            RiskLevel::Write => write!(f, "write"),
+            RiskLevel::NetworkMutating => write!(f, "network-mutating"),
+            RiskLevel::Destructive => write!(f, "destructive"),
+        }
+    }
+}
+
+/// Risk classification of a pending tool call
+#[derive(Debug, Clone, PartialEq)]
+pub struct CommandRisk {
+    pub level: RiskLevel,
+    /// Local paths the call creates, changes or removes
+    pub affected_paths: Vec<String>,
+}
+
+impl CommandRisk {
+    fn new(level: RiskLevel) -> Self {
+        Self {
+            level,
+            affected_paths: Vec::new(),
+        }
+    }
+
+    fn merge(&mut self, other: CommandRisk) {
+        self.level = self.level.max(other.level);
+        for path in other.affected_paths {
+            if !self.affected_paths.contains(&path) {
+                self.affected_paths.push(path);
+            }
+        }
+    }
+}
+
+/// Arguments of the built-in tools naming the paths a call reads, writes or runs in
+pub const PATH_ARGUMENTS: &[&str] = &["path", "source", "destination", "work_dir"];
+
+/// Values of the path arguments of a tool call, see [`PATH_ARGUMENTS`]
+pub fn tool_call_paths(
+    arguments: Option<&serde_json::Map<String, serde_json::Value>>,
+) -> Vec<&str> {
+    PATH_ARGUMENTS
+        .iter()
+        .filter_map(|key| arguments?.get(*key)?.as_str())
+        .collect()
+}
+
+/// Classify a tool call by name, using its `command` argument for shell commands and its
+/// path arguments for file edits
+pub fn classify_tool_call(tool_name: &str, command: Option<&str>, paths: &[&str]) -> CommandRisk {
+    let mut risk = match tool_name {
+        "run_command" => command
+            .map(classify_command)
+            .unwrap_or(CommandRisk::new(RiskLevel::Write)),
+        // Waiting on a URL or pod only reads, waiting on a command runs it
+        "wait_for" => command
+            .map(classify_command)
+            .unwrap_or(CommandRisk::new(RiskLevel::ReadOnly)),
+        "view"
+        | "get_workspace_context"
+        | "smart_search_code"
+        | "read_rulebook"
+        | "list_background_tasks"
+        | "check_cloud_access"
+        | "search_output"
+        | "search_files"
+        | "k8s_read_secret"
+        | "format_and_validate"
+        | "explain_cron"
+        | "lint_iam_policy"
+        | "begin_changes"
+        | "rollback_changes" => CommandRisk::new(RiskLevel::ReadOnly),
+        "delete_file" => CommandRisk::new(RiskLevel::Destructive),
+        _ => CommandRisk::new(RiskLevel::Write),
+    };
+    for path in paths {
+        if risk.level > RiskLevel::ReadOnly && !risk.affected_paths.iter().any(|p| p == path) {
+            risk.affected_paths.push(path.to_string());
+        }
+    }
+    risk
+}
+
+/// Command and process substitutions, which run commands of their own
+const SUBSTITUTIONS: &[&str] = &["$(", "`", "<(", ">("];
+
+/// Classify a shell command, the riskiest command of a pipeline or command list wins
+pub fn classify_command(command: &str) -> CommandRisk {
+    let mut risk = CommandRisk::new(RiskLevel::ReadOnly);
+    let mut command = command.to_string();
+    if SUBSTITUTIONS
+        .iter()
+        .any(|substitution| command.contains(substitution))
+    {
+        // Substituted commands are classified like the others by splitting them out, and
+        // never count as read-only since their output can be run as well
+        risk.merge(CommandRisk::new(RiskLevel::Write));
+        command = SUBSTITUTIONS
+            .iter()
+            .fold(command.replace(')', ";"), |command, substitution| {
+                command.replace(substitution, ";")
+            });
+    }
+    for segment in split_commands(&command) {
+        let tokens: Vec<&str> = segment.split_whitespace().collect();
+        if tokens.is_empty() {
+            continue;
+        }
+        risk.merge(classify_segment(&tokens));
+    }
+    risk
+}
+
+/// Split a command line on `;`, `|`, `&&`, `||`, `&` and newlines, keeping redirections
+/// such as `2>&1` and `&>out.log` intact
+pub fn split_commands(command: &str) -> Vec<&str> {
+    let bytes = command.as_bytes();
+    let mut segments = Vec::new();
+    let mut start = 0;
+    for (i, c) in command.char_indices() {
+        let separator = match c {
+            ';' | '|' | '\n' => true,
+            '&' => {
+                let after_redirect = i > 0 && bytes[i - 1] == b'>';
+                let before_redirect = bytes.get(i + 1) == Some(&b'>');
+                !after_redirect && !before_redirect
+            }
+            _ => false,
+        };
+        if separator {
+            segments.push(&command[start..i]);
+            start = i + 1;
+        }
+    }
+    segments.push(&command[start..]);
+    segments
+}
+
+const READ_ONLY_PROGRAMS: &[&str] = &[
+    "ls",
+    "cat",
+    "head",
+    "tail",
+    "less",
+    "more",
+    "grep",
+    "rg",
+    "ag",
+    "pwd",
+    "echo",
+    "printf",
+    "which",
+    "whereis",
+    "type",
+    "wc",
+    "du",
+    "df",
+    "ps",
+    "top",
+    "stat",
+    "file",
+    "tree",
+    "diff",
+    "jq",
+    "yq",
+    "whoami",
+    "id",
+    "uname",
+    "hostname",
+    "date",
+    "uptime",
+    "free",
+    "dig",
+    "nslookup",
+    "ping",
+    "host",
+    "cd",
+    "true",
+    "false",
+    "test",
+    "sort",
+    "uniq",
+    "cut",
+    "tr",
+    "basename",
+    "dirname",
+    "realpath",
+    "readlink",
+    "md5sum",
+    "sha256sum",
+    "nproc",
+    "lsof",
+    "netstat",
+    "ss",
+    "history",
+];
+
+const WRITE_PROGRAMS: &[&str] = &[
+    "mv", "cp", "mkdir", "touch", "chmod", "chown", "chgrp", "ln", "tee", "install", "patch",
+    "unzip", "tar", "gzip", "gunzip", "zip",
+];
+
+const DESTRUCTIVE_PROGRAMS: &[&str] = &[
+    "rm", "rmdir", "shred", "dd", "wipefs", "fdisk", "parted", "truncate", "kill", "killall",
+    "pkill", "shutdown", "reboot", "halt", "poweroff",
+];
+
+/// Programs whose non-flag arguments are the paths they change
+const PATH_PROGRAMS: &[&str] = &[
+    "rm", "rmdir", "shred", "truncate", "mv", "cp", "mkdir", "touch", "ln", "tee", "install",
+];
+
+/// Global flags of git, kubectl and helm taking the next argument as their value, which is
+/// not the subcommand
+const FLAGS_WITH_VALUE: &[&str] = &[
+    "-n",
+    "--namespace",
+    "--context",
+    "--kube-context",
+    "--kubeconfig",
+    "-C",
+    "-c",
+];
+
+fn classify_segment(tokens: &[&str]) -> CommandRisk {
+    // Skip wrappers and environment assignments in front of the actual program
+    let start = tokens
+        .iter()
+        .position(|token| {
+            !matches!(*token, "sudo" | "env" | "time" | "nohup" | "exec")
+                && (!token.contains('=') || token.starts_with('-'))
+        })
+        .unwrap_or(tokens.len());
+    let (wrappers, tokens) = tokens.split_at(start);
+    let Some(program) = tokens.first() else {
+        // `env` on its own prints every variable, secrets included
+        return CommandRisk::new(if wrappers.contains(&"env") {
+            RiskLevel::Write
+        } else {
+            RiskLevel::ReadOnly
+        });
+    };
+    let program = program.rsplit('/').next().unwrap_or(program);
+    let args = &tokens[1..];
+    let mut subcommand = "";
+    let mut skip_value = false;
+    for arg in args {
+        if skip_value {
+            skip_value = false;
+        } else if FLAGS_WITH_VALUE.contains(arg) {
+            skip_value = true;
+        } else if !arg.starts_with('-') {
+            subcommand = arg;
+            break;
+        }
+    }
+    let has_flag = |flags: &[&str]| args.iter().any(|arg| flags.contains(arg));
+    let has_arg = |needle: &str| args.iter().any(|arg| arg.contains(needle));
+
+    let level = match program {
+        p if DESTRUCTIVE_PROGRAMS.contains(&p) || p.starts_with("mkfs") => RiskLevel::Destructive,
+        p if WRITE_PROGRAMS.contains(&p) => RiskLevel::Write,
+        "sed" | "perl" if has_arg("-i") => RiskLevel::Write,
+        "find" if has_flag(&["-delete"]) => RiskLevel::Destructive,
+        "find" if has_flag(&["-exec", "-execdir"]) => RiskLevel::Write,
+        "git" => match subcommand {
+            "push" if has_arg("--force") || has_flag(&["-f"]) => RiskLevel::Destructive,
+            "reset" if has_flag(&["--hard"]) => RiskLevel::Destructive,
+            "clean" => RiskLevel::Destructive,
+            "branch" if has_flag(&["-D", "--delete"]) => RiskLevel::Destructive,
+            "push" => RiskLevel::NetworkMutating,
+            "status" | "log" | "diff" | "show" | "remote" | "blame" | "rev-parse" | "ls-files" => {
+                RiskLevel::ReadOnly
+            }
+            "branch" | "tag" if args.len() == 1 => RiskLevel::ReadOnly,
+            _ => RiskLevel::Write,
+        },
+        "terraform" | "tofu" | "terragrunt" => match subcommand {
+            "destroy" => RiskLevel::Destructive,
+            "apply" if has_flag(&["-destroy"]) => RiskLevel::Destructive,
+            "apply" | "import" | "state" | "taint" | "untaint" | "force-unlock" => {
+                RiskLevel::NetworkMutating
+            }
+            "plan" | "validate" | "show" | "output" | "version" | "providers" | "graph" => {
+                RiskLevel::ReadOnly
+            }
+            "fmt" if has_flag(&["-check"]) => RiskLevel::ReadOnly,
+            _ => RiskLevel::Write,
+        },
+        "kubectl" | "oc" => match subcommand {
+            "delete" | "drain" => RiskLevel::Destructive,
+            "get" | "describe" | "logs" | "top" | "explain" | "version" | "api-resources"
+            | "cluster-info" | "diff" | "auth" => RiskLevel::ReadOnly,
+            "config" if has_flag(&["view", "get-contexts", "current-context"]) => {
+                RiskLevel::ReadOnly
+            }
+            "config" => RiskLevel::Write,
+            _ => RiskLevel::NetworkMutating,
+        },
+        "helm" => match subcommand {
+            "uninstall" | "delete" => RiskLevel::Destructive,
+            "install" | "upgrade" | "rollback" => RiskLevel::NetworkMutating,
+            "list" | "ls" | "status" | "get" | "template" | "show" | "history" | "search"
+            | "lint" | "version" => RiskLevel::ReadOnly,
+            _ => RiskLevel::Write,
+        },
+        "docker" | "podman" => match subcommand {
+            "rm" | "rmi" | "prune" | "kill" => RiskLevel::Destructive,
+            _ if has_flag(&["prune"]) => RiskLevel::Destructive,
+            "push" | "login" => RiskLevel::NetworkMutating,
+            "ps" | "images" | "logs" | "inspect" | "version" | "info" | "stats" => {
+                RiskLevel::ReadOnly
+            }
+            _ => RiskLevel::Write,
+        },
+        "aws" | "gcloud" | "az" | "doctl" => classify_cloud_cli(args),
+        "curl" | "wget" | "http" => {
+            let mutating_method = args.windows(2).any(|pair| {
+                matches!(pair[0], "-X" | "--request")
+                    && !pair[1].eq_ignore_ascii_case("GET")
+                    && !pair[1].eq_ignore_ascii_case("HEAD")
+            });
+            let sends_data = args.iter().any(|arg| {
+                arg.starts_with("--data")
+                    || arg.starts_with("--post")
+                    || matches!(*arg, "-d" | "-F" | "--form" | "-T" | "--upload-file")
+            });
+            if mutating_method || sends_data {
+                RiskLevel::NetworkMutating
+            } else if has_flag(&["-o", "-O", "--output"]) || program == "wget" {
+                RiskLevel::Write
+            } else {
+                RiskLevel::ReadOnly
+            }
+        }
+        "ssh" => RiskLevel::NetworkMutating,
+        "scp" | "rsync" if args.iter().skip(1).any(|arg| arg.contains(':')) => {
+            RiskLevel::NetworkMutating
+        }
+        p if READ_ONLY_PROGRAMS.contains(&p) => RiskLevel::ReadOnly,
+        _ => RiskLevel::Write,
+    };
+
+    let mut risk = CommandRisk::new(level);
+    if PATH_PROGRAMS.contains(&program) {
+        risk.affected_paths.extend(
+            args.iter()
+                .filter(|arg| !arg.starts_with('-'))
+                .map(|arg| arg.to_string()),
+        );
+    }
+
+    // Output redirections write to the redirected file
+    let mut redirect_next = false;
+    for token in tokens {
+        if redirect_next {
+            risk.merge(redirected(token));
+            redirect_next = false;
+        } else if let Some(target) = redirect_target(token) {
+            if target.is_empty() {
+                redirect_next = true;
+            } else {
+                risk.merge(redirected(target));
+            }
+        }
+    }
+
+    risk
+}
+
+/// Target of an output redirection token such as `>out.txt` or `2>>err.log`, empty when
+/// the target is the next token
+fn redirect_target(token: &str) -> Option<&str> {
+    let start = token.find('>')?;
+    let prefix = &token[..start];
+    if prefix != "&" && !prefix.chars().all(|c| c.is_ascii_digit()) {
+        return None;
+    }
+    let target = token[start..].trim_start_matches('>');
+    // Redirecting into another file descriptor (2>&1) does not touch the filesystem
+    if target.starts_with('&') {
+        return None;
+    }
+    Some(target)
+}
+
+fn redirected(target: &str) -> CommandRisk {
+    let mut risk = CommandRisk::new(RiskLevel::Write);
+    if target != "/dev/null" {
+        risk.affected_paths.push(target.to_string());
+    }
+    risk
+}
+
+fn classify_cloud_cli(args: &[&str]) -> RiskLevel {
+    let operations: Vec<&str> = args
+        .iter()
+        .filter(|arg| !arg.starts_with('-'))
+        .copied()
+        .collect();
+    let is = |prefixes: &[&str]| {
+        operations
+            .iter()
+            .any(|op| prefixes.iter().any(|prefix| op.starts_with(prefix)))
+    };
+
+    if is(&[
+        "delete",
+        "terminate",
+        "destroy",
+        "remove",
+        "rb",
+        "rm",
+        "deregister",
+        "purge",
+    ]) {
+        RiskLevel::Destructive
+    } else if is(&[
+        "create",
+        "put",
+        "update",
+        "run",
+        "start",
+        "stop",
+        "modify",
+        "attach",
+        "detach",
+        "deploy",
+        "set",
+        "add",
+        "associate",
+        "disassociate",
+        "reboot",
+        "cp",
+        "mv",
+        "sync",
+        "apply",
+        "import",
+        "invoke",
+        "publish",
+        "register",
+        "tag",
+        "untag",
+        "enable",
+        "disable",
+        "restore",
+        "resize",
+        "scale",
+    ]) {
+        RiskLevel::NetworkMutating
+    } else if is(&[
+        "describe", "list", "get", "show", "ls", "help", "whoami", "version",
+    ]) {
+        RiskLevel::ReadOnly
+    } else {
+        RiskLevel::NetworkMutating
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_read_only_commands() {
+        assert_eq!(
+            classify_command("ls -la | grep foo").level,
+            RiskLevel::ReadOnly
+        );
+        assert_eq!(
+            classify_command("git status && git diff").level,
+            RiskLevel::ReadOnly
+        );
+        assert_eq!(
+            classify_command("kubectl get pods -A").level,
+            RiskLevel::ReadOnly
+        );
+        assert_eq!(
+            classify_command("aws ec2 describe-instances").level,
+            RiskLevel::ReadOnly
+        );
+        assert_eq!(
+            classify_command("kubectl -n prod get pods").level,
+            RiskLevel::ReadOnly
+        );
+        assert_eq!(
+            classify_command("git -C repo status").level,
+            RiskLevel::ReadOnly
+        );
+        assert_eq!(
+            classify_command("terraform plan 2>&1").level,
+            RiskLevel::ReadOnly
+        );
+        assert_eq!(
+            classify_command("make test &> /dev/null")
+                .affected_paths
+                .len(),
+            0
+        );
+    }
+
+    #[test]
+    fn test_write_commands_report_paths() {
+        let risk = classify_command("mkdir -p build && echo done > build/status.txt");
+        assert_eq!(risk.level, RiskLevel::Write);
+        assert_eq!(risk.affected_paths, vec!["build", "build/status.txt"]);
+        assert_eq!(
+            classify_command("sed -i s/a/b/ main.tf").level,
+            RiskLevel::Write
+        );
+        assert_eq!(
+            classify_command("some-unknown-tool").level,
+            RiskLevel::Write
+        );
+        // Dumping the environment discloses secrets and awk can run commands
+        for command in [
+            "env",
+            "FOO=1 env",
+            "printenv AWS_SECRET_ACCESS_KEY",
+            "awk '{print $1}' access.log",
+        ] {
+            assert_eq!(
+                classify_command(command).level,
+                RiskLevel::Write,
+                "{}",
+                command
+            );
+        }
+    }
+
+    #[test]
+    fn test_substitutions_are_not_read_only() {
+        assert_eq!(
+            classify_command("cat $(rm -rf ~/x)").level,
+            RiskLevel::Destructive
+        );
+        assert_eq!(
+            classify_command("echo `rm -rf x`").level,
+            RiskLevel::Destructive
+        );
+        assert_eq!(
+            classify_command("diff <(ls a) <(ls b)").level,
+            RiskLevel::Write
+        );
+        assert_eq!(
+            classify_command(r#"awk 'BEGIN{system("rm -rf x")}'"#).level,
+            RiskLevel::Write
+        );
+    }
+
+    #[test]
+    fn test_network_mutating_commands() {
+        for command in [
+            "terraform apply -auto-approve",
+            "kubectl apply -f deploy.yaml",
+            "helm upgrade app ./chart",
+            "git push origin main",
+            "curl -X POST https://example.com/api",
+            "aws ec2 run-instances --image-id ami-123",
+        ] {
+            assert_eq!(
+                classify_command(command).level,
+                RiskLevel::NetworkMutating,
+                "{}",
+                command
+            );
+        }
+    }
+
+    #[test]
+    fn test_destructive_commands() {
+        for command in [
+            "sudo rm -rf /var/lib/app",
+            "terraform destroy",
+            "kubectl delete ns prod",
+            "git push --force origin main",
+            "git reset --hard HEAD~3",
+            "find . -name '*.log' -delete",
+            "aws s3 rb s3://bucket --force",
+            "ls; rm notes.txt",
+            "kubectl -n prod delete pod x",
+            "kubectl --context prod delete ns app",
+            "git -C repo push --force",
+            "git -c user.name=ci push -f origin main",
+        ] {
+            assert_eq!(
+                classify_command(command).level,
+                RiskLevel::Destructive,
+                "{}",
+                command
+            );
+        }
+        assert_eq!(
+            classify_command("rm -rf build dist").affected_paths,
+            vec!["build", "dist"]
+        );
+    }
+
+    #[test]
+    fn test_classify_tool_call() {
+        let view = classify_tool_call("view", None, &["main.tf"]);
+        assert_eq!(view.level, RiskLevel::ReadOnly);
+        assert!(view.affected_paths.is_empty());
+
+        let edit = classify_tool_call("str_replace", None, &["main.tf"]);
+        assert_eq!(edit.level, RiskLevel::Write);
+        assert_eq!(edit.affected_paths, vec!["main.tf"]);
+
+        let command = classify_tool_call("run_command", Some("terraform destroy"), &[]);
+        assert!(command.level.requires_typed_confirmation());
+
+        let arguments = serde_json::json!({"source": "notes.txt", "destination": "~/.aws/config"});
+        let paths = tool_call_paths(arguments.as_object());
+        assert_eq!(paths, vec!["notes.txt", "~/.aws/config"]);
+        assert_eq!(
+            classify_tool_call("move_file", None, &paths).affected_paths,
+            vec!["notes.txt", "~/.aws/config"]
+        );
+        assert!(tool_call_paths(None).is_empty());
+    }
+}
@@ -0,0 +1,263 @@
+use crate::policy::{RiskLevel, classify_tool_call, split_commands};
+use serde::{Deserialize, Serialize};
+use std::path::{Path, PathBuf};
+
+/// What to do with a tool call, ordered from least to most restrictive
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+#[serde(rename_all = "lowercase")]
+pub enum PolicyAction {
+    /// Run without asking
+    Allow,
+    /// Always ask, even when the workspace policy would allow the call
+    Prompt,
+    /// Never run
+    Deny,
+}
+
+/// Rule applied to the tool calls matching all of its criteria, a rule without criteria
+/// matches every call
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct PolicyRule {
+    pub name: String,
+    pub action: PolicyAction,
+    /// Tool names, any tool when empty
+    #[serde(default)]
+    pub tools: Vec<String>,
+    /// Command patterns, a trailing `*` matches any remaining arguments. Allow rules need
+    /// every part of a pipeline or command list to match, other rules any part.
+    #[serde(default)]
+    pub commands: Vec<String>,
+    /// Paths the call reads or writes, `~` is the home directory
+    #[serde(default)]
+    pub paths: Vec<String>,
+    /// Riskiest classification of the call, see [`RiskLevel`]
+    pub max_risk: Option<RiskLevel>,
+}
+
+/// Approval rules of the workspace
+///
+/// ```yaml
+/// rules:
+///   - name: read-only commands
+///     max_risk: read-only
+///     action: allow
+///   - name: destructive commands
+///     commands: ["rm *", "terraform apply *", "kubectl delete *"]
+///     action: prompt
+///   - name: AWS credentials
+///     paths: ["~/.aws"]
+///     action: deny
+/// ```
+#[derive(Serialize, Deserialize, Debug, Clone, Default, PartialEq)]
+pub struct PolicyRules {
+    #[serde(default)]
+    pub rules: Vec<PolicyRule>,
+}
+
+/// Action taken on a tool call and the rule that decided it
+#[derive(Debug, Clone, PartialEq)]
+pub struct PolicyDecision {
+    pub action: PolicyAction,
+    pub rule: String,
+}
+
+fn expand_home(path: &str) -> PathBuf {
+    let home = || PathBuf::from(std::env::var("HOME").unwrap_or_default());
+    match path
+        .strip_prefix("~/")
+        .or_else(|| path.strip_prefix("$HOME/"))
+    {
+        Some(rest) => home().join(rest),
+        None if path == "~" || path == "$HOME" => home(),
+        None => PathBuf::from(path),
+    }
+}
+
+fn segment_matches(pattern: &str, segment: &str) -> bool {
+    let tokens: Vec<&str> = segment.split_whitespace().collect();
+    let expected: Vec<&str> = pattern.split_whitespace().collect();
+    match expected.split_last() {
+        Some((&"*", prefix)) => tokens.starts_with(prefix),
+        _ => tokens == expected,
+    }
+}
+
+impl PolicyRule {
+    fn matches_command(&self, command: &str) -> bool {
+        let mut segments = split_commands(command)
+            .into_iter()
+            .filter(|segment| !segment.trim().is_empty());
+        let matches = |segment: &str| {
+            self.commands
+                .iter()
+                .any(|pattern| segment_matches(pattern, segment))
+        };
+        if self.action == PolicyAction::Allow {
+            // Redirections and substitutions can do anything the pattern did not intend
+            !command.contains(['>', '<', '`']) && !command.contains("$(") && segments.all(matches)
+        } else {
+            segments.any(matches)
+        }
+    }
+
+    fn touches_path(&self, command: Option<&str>, paths: &[&str]) -> bool {
+        let mut candidates: Vec<&str> = paths.to_vec();
+        if let Some(command) = command {
+            candidates.extend(
+                command
+                    .split(|c: char| c.is_whitespace() || c == '=')
+                    .map(|token| token.trim_matches(['"', '\''])),
+            );
+        }
+        self.paths.iter().any(|protected| {
+            let protected_path = expand_home(protected);
+            candidates.iter().any(|candidate| {
+                Path::new(&expand_home(candidate)).starts_with(&protected_path)
+                    || candidate.contains(protected.as_str())
+            })
+        })
+    }
+
+    fn matches(
+        &self,
+        tool_name: &str,
+        command: Option<&str>,
+        paths: &[&str],
+        risk: RiskLevel,
+    ) -> bool {
+        (self.tools.is_empty() || self.tools.iter().any(|tool| tool == tool_name))
+            && (self.commands.is_empty() || command.is_some_and(|c| self.matches_command(c)))
+            && (self.paths.is_empty() || self.touches_path(command, paths))
+            && self.max_risk.is_none_or(|max_risk| risk <= max_risk)
+    }
+}
+
+impl PolicyRules {
+    pub fn path() -> PathBuf {
+        PathBuf::from(".stakpak").join("policies.yaml")
+    }
+
+    /// Load the rules of the current workspace, none when there is no policies file
+    pub fn load() -> Result<Self, String> {
+        Self::load_from(&Self::path())
+    }
+
+    /// Load rules from `path`, a missing file means no rules. A file that exists but
+    /// can't be read is an error, ignoring it would silently drop every deny rule.
+    fn load_from(path: &Path) -> Result<Self, String> {
+        match std::fs::read_to_string(path) {
+            Ok(content) => serde_yaml::from_str(&content)
+                .map_err(|e| format!("Failed to parse {}: {}", path.display(), e)),
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(Self::default()),
+            Err(e) => Err(format!("Failed to read {}: {}", path.display(), e)),
+        }
+    }
+
+    /// Decide what to do with a tool call, given its command and every path it reads or
+    /// writes. The most restrictive matching rule wins, so a deny rule cannot be bypassed
+    /// by an allow rule. `None` when no rule matches.
+    pub fn evaluate(
+        &self,
+        tool_name: &str,
+        command: Option<&str>,
+        paths: &[&str],
+    ) -> Option<PolicyDecision> {
+        let risk = classify_tool_call(tool_name, command, paths).level;
+        let mut decision: Option<&PolicyRule> = None;
+        for rule in &self.rules {
+            if rule.matches(tool_name, command, paths, risk)
+                && decision.is_none_or(|decided| rule.action > decided.action)
+            {
+                decision = Some(rule);
+            }
+        }
+        decision.map(|rule| PolicyDecision {
+            action: rule.action,
+            rule: rule.name.clone(),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_most_restrictive_rule_wins() {
+        let rules: PolicyRules = serde_yaml::from_str(
+            r#"
+rules:
+  - name: read-only
+    max_risk: read-only
+    action: allow
+  - name: destructive
+    commands: ["rm *", "kubectl delete *"]
+    action: prompt
+  - name: aws
+    paths: ["~/.aws"]
+    action: deny
+"#,
+        )
+        .unwrap();
+        let action = |tool: &str, command: Option<&str>, paths: &[&str]| {
+            rules
+                .evaluate(tool, command, paths)
+                .map(|decision| decision.action)
+        };
+
+        assert_eq!(
+            action("run_command", Some("kubectl get pods"), &[]),
+            Some(PolicyAction::Allow)
+        );
+        assert_eq!(
+            action("run_command", Some("ls && rm -rf build"), &[]),
+            Some(PolicyAction::Prompt)
+        );
+        assert_eq!(
+            action("run_command", Some("cat ~/.aws/credentials"), &[]),
+            Some(PolicyAction::Deny)
+        );
+        assert_eq!(
+            action("view", None, &["~/.aws/config"]),
+            Some(PolicyAction::Deny)
+        );
+        assert_eq!(
+            action("move_file", None, &["notes.txt", "~/.aws/config"]),
+            Some(PolicyAction::Deny)
+        );
+        assert_eq!(action("run_command", Some("terraform apply"), &[]), None);
+        // Reads hiding a write are not read-only
+        for command in [
+            "cat $(rm -rf ~/x)",
+            "echo `rm -rf x`",
+            r#"awk 'BEGIN{system("rm -rf x")}'"#,
+        ] {
+            assert_ne!(
+                action("run_command", Some(command), &[]),
+                Some(PolicyAction::Allow),
+                "{}",
+                command
+            );
+        }
+    }
+
+    #[test]
+    fn test_load_only_treats_a_missing_file_as_no_rules() {
+        let dir = std::env::temp_dir().join(format!("stakpak-policies-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+
+        let rules = PolicyRules::load_from(&dir.join("missing.yaml")).unwrap();
+        assert!(rules.rules.is_empty());
+
+        // A directory in place of the file can't be read
+        let unreadable = dir.join("policies.yaml");
+        std::fs::create_dir_all(&unreadable).unwrap();
+        assert!(PolicyRules::load_from(&unreadable).is_err());
+
+        let invalid = dir.join("invalid.yaml");
+        std::fs::write(&invalid, "rules: [").unwrap();
+        assert!(PolicyRules::load_from(&invalid).is_err());
+
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+}
@@ -0,0 +1,132 @@
+use base64::{Engine, engine::general_purpose::STANDARD};
+use ring::aead::{AES_256_GCM, Aad, LessSafeKey, NONCE_LEN, Nonce, UnboundKey};
+use ring::pbkdf2;
+use ring::rand::{SecureRandom, SystemRandom};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::num::NonZeroU32;
+
+const BUNDLE_VERSION: u32 = 1;
+const KEY_LEN: usize = 32;
+const SALT_LEN: usize = 16;
+const PBKDF2_ITERATIONS: u32 = 600_000;
+/// Passphrases shorter than this are too easy to guess offline
+pub const MIN_PASSPHRASE_LEN: usize = 8;
+
+/// Session redaction map encrypted under a passphrase, so a checkpoint resumed on another
+/// machine can still restore the placeholders in its transcript.
+///
+/// The key is derived with PBKDF2-HMAC-SHA256 and the map sealed with AES-256-GCM, the
+/// checkpoint ID is bound as associated data so a bundle cannot be passed off as another
+/// checkpoint's.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct RedactionBundle {
+    pub version: u32,
+    pub checkpoint_id: String,
+    salt: String,
+    nonce: String,
+    ciphertext: String,
+}
+
+fn derive_key(passphrase: &str, salt: &[u8]) -> Result<LessSafeKey, String> {
+    let mut key = [0u8; KEY_LEN];
+    let iterations = NonZeroU32::new(PBKDF2_ITERATIONS).ok_or("Invalid iteration count")?;
+    pbkdf2::derive(
+        pbkdf2::PBKDF2_HMAC_SHA256,
+        iterations,
+        salt,
+        passphrase.as_bytes(),
+        &mut key,
+    );
+    let key = UnboundKey::new(&AES_256_GCM, &key).map_err(|_| "Failed to derive key")?;
+    Ok(LessSafeKey::new(key))
+}
+
+impl RedactionBundle {
+    pub fn seal(
+        redaction_map: &HashMap<String, String>,
+        checkpoint_id: &str,
+        passphrase: &str,
+    ) -> Result<Self, String> {
+        if passphrase.chars().count() < MIN_PASSPHRASE_LEN {
+            return Err(format!(
+                "Passphrase must be at least {} characters",
+                MIN_PASSPHRASE_LEN
+            ));
+        }
+
+        let rng = SystemRandom::new();
+        let mut salt = [0u8; SALT_LEN];
+        let mut nonce = [0u8; NONCE_LEN];
+        rng.fill(&mut salt)
+            .and_then(|_| rng.fill(&mut nonce))
+            .map_err(|_| "Failed to generate random bytes".to_string())?;
+
+        let mut in_out = serde_json::to_vec(redaction_map).map_err(|e| e.to_string())?;
+        derive_key(passphrase, &salt)?
+            .seal_in_place_append_tag(
+                Nonce::assume_unique_for_key(nonce),
+                Aad::from(checkpoint_id.as_bytes()),
+                &mut in_out,
+            )
+            .map_err(|_| "Failed to encrypt the redaction map".to_string())?;
+
+        Ok(Self {
+            version: BUNDLE_VERSION,
+            checkpoint_id: checkpoint_id.to_string(),
+            salt: STANDARD.encode(salt),
+            nonce: STANDARD.encode(nonce),
+            ciphertext: STANDARD.encode(in_out),
+        })
+    }
+
+    pub fn open(&self, passphrase: &str) -> Result<HashMap<String, String>, String> {
+        if self.version > BUNDLE_VERSION {
+            return Err(format!(
+                "Redaction bundle version {} is newer than this CLI supports",
+                self.version
+            ));
+        }
+        let decode = |value: &str| {
+            STANDARD
+                .decode(value)
+                .map_err(|e| format!("Corrupted redaction bundle: {}", e))
+        };
+        let nonce: [u8; NONCE_LEN] = decode(&self.nonce)?
+            .try_into()
+            .map_err(|_| "Corrupted redaction bundle: invalid nonce".to_string())?;
+        let mut in_out = decode(&self.ciphertext)?;
+        let plaintext = derive_key(passphrase, &decode(&self.salt)?)?
+            .open_in_place(
+                Nonce::assume_unique_for_key(nonce),
+                Aad::from(self.checkpoint_id.as_bytes()),
+                &mut in_out,
+            )
+            .map_err(|_| "Wrong passphrase or tampered redaction bundle".to_string())?;
+        serde_json::from_slice(plaintext).map_err(|e| format!("Corrupted redaction bundle: {}", e))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_seal_and_open() {
+        let map = HashMap::from([(
+            "[REDACTED_SECRET:aws-key:abc]".to_string(),
+            "AKIAEXAMPLE".to_string(),
+        )]);
+        assert!(RedactionBundle::seal(&map, "checkpoint", "short").is_err());
+
+        let bundle = RedactionBundle::seal(&map, "checkpoint", "correct horse battery");
+        let Ok(mut bundle) = bundle else {
+            panic!("failed to seal the bundle");
+        };
+        assert_eq!(bundle.open("correct horse battery"), Ok(map));
+        assert!(bundle.open("wrong passphrase").is_err());
+
+        bundle.checkpoint_id = "other".to_string();
+        assert!(bundle.open("correct horse battery").is_err());
+    }
+}
@@ -0,0 +1,335 @@
+use base64::{Engine, engine::general_purpose::STANDARD};
+use ring::aead::{AES_256_GCM, Aad, LessSafeKey, NONCE_LEN, Nonce, UnboundKey};
+use ring::rand::{SecureRandom, SystemRandom};
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap};
+use std::io::{ErrorKind, Write};
+use std::path::{Path, PathBuf};
+
+const KEY_LEN: usize = 32;
+const KEY_FILE: &str = "vault.key";
+const VAULT_FILE: &str = "vault.json";
+
+/// A secret value encrypted with the vault key, the secret name is bound as associated data
+#[derive(Serialize, Deserialize, Debug, Clone)]
+struct EncryptedSecret {
+    nonce: String,
+    ciphertext: String,
+}
+
+/// Secrets stored on this machine and handed to the agent only as `[SECRET:NAME]` placeholders.
+///
+/// Values are encrypted with AES-256-GCM under a random key kept next to the vault with
+/// owner-only permissions, and are substituted back only when a tool runs locally.
+pub struct SecretVault {
+    dir: PathBuf,
+    key: LessSafeKey,
+    secrets: BTreeMap<String, EncryptedSecret>,
+}
+
+/// Placeholder the agent uses to reference the stored secret `name`
+pub fn secret_placeholder(name: &str) -> String {
+    format!("[SECRET:{}]", name)
+}
+
+pub fn validate_secret_name(name: &str) -> Result<(), String> {
+    if name.is_empty()
+        || !name
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || c == '_' || c == '-')
+    {
+        return Err(format!(
+            "Invalid secret name '{}': use letters, digits, '_' and '-' only",
+            name
+        ));
+    }
+    Ok(())
+}
+
+impl SecretVault {
+    pub fn default_dir() -> PathBuf {
+        PathBuf::from(std::env::var("HOME").unwrap_or_default())
+            .join(".stakpak")
+            .join("secrets")
+    }
+
+    /// Open the vault in the default location, creating its key on first use
+    pub fn open() -> Result<Self, String> {
+        Self::open_in(&Self::default_dir())
+    }
+
+    pub fn open_in(dir: &Path) -> Result<Self, String> {
+        let key_path = dir.join(KEY_FILE);
+        let vault_path = dir.join(VAULT_FILE);
+        let key_bytes = match std::fs::read(&key_path) {
+            Ok(bytes) => bytes,
+            // A new key could never decrypt the secrets already stored
+            Err(e) if e.kind() == ErrorKind::NotFound && vault_path.exists() => {
+                return Err(format!(
+                    "The vault key {} is missing, the secrets in {} cannot be decrypted without it",
+                    key_path.display(),
+                    vault_path.display()
+                ));
+            }
+            Err(e) if e.kind() == ErrorKind::NotFound => {
+                let mut bytes = vec![0u8; KEY_LEN];
+                SystemRandom::new()
+                    .fill(&mut bytes)
+                    .map_err(|_| "Failed to generate vault key".to_string())?;
+                write_private(&key_path, &bytes)?;
+                bytes
+            }
+            Err(e) => {
+                return Err(format!(
+                    "Failed to read vault key {}: {}",
+                    key_path.display(),
+                    e
+                ));
+            }
+        };
+        let key = UnboundKey::new(&AES_256_GCM, &key_bytes)
+            .map_err(|_| format!("Invalid vault key in {}", key_path.display()))?;
+
+        // Anything but a missing vault has to fail, saving would otherwise wipe the secrets
+        let secrets = match std::fs::read_to_string(&vault_path) {
+            Ok(content) => serde_json::from_str(&content)
+                .map_err(|e| format!("Failed to parse secret vault: {}", e))?,
+            Err(e) if e.kind() == ErrorKind::NotFound => BTreeMap::new(),
+            Err(e) => {
+                return Err(format!(
+                    "Failed to read secret vault {}: {}",
+                    vault_path.display(),
+                    e
+                ));
+            }
+        };
+
+        Ok(Self {
+            dir: dir.to_path_buf(),
+            key: LessSafeKey::new(key),
+            secrets,
+        })
+    }
+
+    fn save(&self) -> Result<(), String> {
+        let content = serde_json::to_string_pretty(&self.secrets).map_err(|e| e.to_string())?;
+        write_private(&self.dir.join(VAULT_FILE), content.as_bytes())
+    }
+
+    /// Store `value` under `name`, replacing any previous value
+    pub fn add(&mut self, name: &str, value: &str) -> Result<(), String> {
+        validate_secret_name(name)?;
+        if value.is_empty() {
+            return Err(format!("Secret '{}' is empty", name));
+        }
+
+        let mut nonce = [0u8; NONCE_LEN];
+        SystemRandom::new()
+            .fill(&mut nonce)
+            .map_err(|_| "Failed to generate nonce".to_string())?;
+        let mut in_out = value.as_bytes().to_vec();
+        self.key
+            .seal_in_place_append_tag(
+                Nonce::assume_unique_for_key(nonce),
+                Aad::from(name.as_bytes()),
+                &mut in_out,
+            )
+            .map_err(|_| format!("Failed to encrypt secret '{}'", name))?;
+
+        self.secrets.insert(
+            name.to_string(),
+            EncryptedSecret {
+                nonce: STANDARD.encode(nonce),
+                ciphertext: STANDARD.encode(in_out),
+            },
+        );
+        self.save()
+    }
+
+    /// Remove `name`, returns whether it was stored
+    pub fn remove(&mut self, name: &str) -> Result<bool, String> {
+        let removed = self.secrets.remove(name).is_some();
+        if removed {
+            self.save()?;
+        }
+        Ok(removed)
+    }
+
+    pub fn names(&self) -> Vec<String> {
+        self.secrets.keys().cloned().collect()
+    }
+
+    pub fn get(&self, name: &str) -> Result<Option<String>, String> {
+        let Some(secret) = self.secrets.get(name) else {
+            return Ok(None);
+        };
+        let decode = |value: &str| {
+            STANDARD
+                .decode(value)
+                .map_err(|e| format!("Corrupted secret '{}': {}", name, e))
+        };
+        let nonce: [u8; NONCE_LEN] = decode(&secret.nonce)?
+            .try_into()
+            .map_err(|_| format!("Corrupted secret '{}': invalid nonce", name))?;
+        let mut in_out = decode(&secret.ciphertext)?;
+        let plaintext = self
+            .key
+            .open_in_place(
+                Nonce::assume_unique_for_key(nonce),
+                Aad::from(name.as_bytes()),
+                &mut in_out,
+            )
+            .map_err(|_| format!("Failed to decrypt secret '{}'", name))?;
+        String::from_utf8(plaintext.to_vec())
+            .map(Some)
+            .map_err(|e| format!("Secret '{}' is not valid UTF-8: {}", name, e))
+    }
+
+    /// Placeholder to value map of every stored secret, in the shape of a redaction map
+    pub fn placeholder_map(&self) -> Result<HashMap<String, String>, String> {
+        let mut map = HashMap::new();
+        for name in self.secrets.keys() {
+            if let Some(value) = self.get(name)? {
+                map.insert(secret_placeholder(name), value);
+            }
+        }
+        Ok(map)
+    }
+}
+
+/// Open the default vault only when a secret was ever stored, so no key is created otherwise
+fn open_existing() -> Result<Option<SecretVault>, String> {
+    let dir = SecretVault::default_dir();
+    if !dir.join(VAULT_FILE).exists() {
+        return Ok(None);
+    }
+    SecretVault::open_in(&dir).map(Some)
+}
+
+/// Names of the secrets stored in the default vault
+pub fn stored_secret_names() -> Result<Vec<String>, String> {
+    Ok(open_existing()?
+        .map(|vault| vault.names())
+        .unwrap_or_default())
+}
+
+/// Placeholder to value map of the default vault, empty when nothing was stored
+pub fn stored_secret_placeholders() -> Result<HashMap<String, String>, String> {
+    match open_existing()? {
+        Some(vault) => vault.placeholder_map(),
+        None => Ok(HashMap::new()),
+    }
+}
+
+/// Write `data` to a temporary file created owner-only, then move it over `path`, so the
+/// data is never readable by others nor half written
+fn write_private(path: &Path, data: &[u8]) -> Result<(), String> {
+    let error = |e: std::io::Error| format!("Failed to write {}: {}", path.display(), e);
+    if let Some(parent) = path.parent() {
+        std::fs::create_dir_all(parent)
+            .map_err(|e| format!("Failed to create {}: {}", parent.display(), e))?;
+    }
+    let file_name = path
+        .file_name()
+        .map(|name| name.to_string_lossy().to_string())
+        .unwrap_or_default();
+    let temp_path = path.with_file_name(format!(".{}.{}.tmp", file_name, std::process::id()));
+    let _ = std::fs::remove_file(&temp_path);
+
+    let mut options = std::fs::OpenOptions::new();
+    options.write(true).create_new(true);
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::OpenOptionsExt;
+        options.mode(0o600);
+    }
+    let written = options.open(&temp_path).and_then(|mut file| {
+        file.write_all(data)?;
+        file.sync_all()
+    });
+    if let Err(e) = written.and_then(|_| std::fs::rename(&temp_path, path)) {
+        let _ = std::fs::remove_file(&temp_path);
+        return Err(error(e));
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn temp_dir() -> PathBuf {
+        std::env::temp_dir().join(format!("stakpak-vault-{}", uuid::Uuid::new_v4()))
+    }
+
+    #[test]
+    fn test_round_trip_without_plaintext_on_disk() {
+        let dir = temp_dir();
+        let mut vault = SecretVault::open_in(&dir).unwrap();
+        vault.add("DB_PASSWORD", "hunter2-very-secret").unwrap();
+
+        let on_disk = std::fs::read_to_string(dir.join(VAULT_FILE)).unwrap();
+        assert!(!on_disk.contains("hunter2-very-secret"));
+
+        let reopened = SecretVault::open_in(&dir).unwrap();
+        assert_eq!(
+            reopened.get("DB_PASSWORD").unwrap(),
+            Some("hunter2-very-secret".to_string())
+        );
+        assert_eq!(
+            reopened
+                .placeholder_map()
+                .unwrap()
+                .get("[SECRET:DB_PASSWORD]"),
+            Some(&"hunter2-very-secret".to_string())
+        );
+
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn test_unreadable_files_are_never_replaced() {
+        let dir = temp_dir();
+        let mut vault = SecretVault::open_in(&dir).unwrap();
+        vault.add("DB_PASSWORD", "hunter2-very-secret").unwrap();
+        let key = std::fs::read(dir.join(KEY_FILE)).unwrap();
+
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::PermissionsExt;
+            let mode = std::fs::metadata(dir.join(KEY_FILE))
+                .unwrap()
+                .permissions()
+                .mode();
+            assert_eq!(mode & 0o777, 0o600);
+        }
+
+        // A vault that cannot be read fails to open instead of starting empty
+        std::fs::rename(dir.join(VAULT_FILE), dir.join("vault.json.bak")).unwrap();
+        std::fs::create_dir(dir.join(VAULT_FILE)).unwrap();
+        assert!(SecretVault::open_in(&dir).is_err());
+        std::fs::remove_dir(dir.join(VAULT_FILE)).unwrap();
+        std::fs::rename(dir.join("vault.json.bak"), dir.join(VAULT_FILE)).unwrap();
+
+        // A missing key is not regenerated while secrets are stored
+        std::fs::remove_file(dir.join(KEY_FILE)).unwrap();
+        assert!(SecretVault::open_in(&dir).is_err());
+        assert!(!dir.join(KEY_FILE).exists());
+
+        // Neither is a key that fails to read
+        std::fs::create_dir(dir.join(KEY_FILE)).unwrap();
+        assert!(SecretVault::open_in(&dir).is_err());
+        std::fs::remove_dir(dir.join(KEY_FILE)).unwrap();
+
+        std::fs::write(dir.join(KEY_FILE), key).unwrap();
+        assert!(SecretVault::open_in(&dir).is_ok());
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn test_rejects_invalid_names() {
+        assert!(validate_secret_name("DB_PASSWORD").is_ok());
+        assert!(validate_secret_name("db password").is_err());
+        assert!(validate_secret_name("").is_err());
+    }
+}
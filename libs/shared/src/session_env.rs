@@ -0,0 +1,84 @@
+use crate::local_store::LocalStore;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+
+const SESSION_ENV_FILE: &str = "session_env.json";
+
+/// Environment variable set with `/env`, passed to every `run_command` of the session
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct SessionEnvVar {
+    /// Value, or for secrets the placeholder restored from the session redaction map
+    pub value: String,
+    #[serde(default)]
+    pub secret: bool,
+}
+
+/// Placeholder standing in for the secret value of the session variable `name`
+pub fn env_secret_placeholder(name: &str) -> String {
+    format!("[ENV_SECRET:{}]", name)
+}
+
+pub fn validate_env_name(name: &str) -> Result<(), String> {
+    let valid = name
+        .chars()
+        .next()
+        .is_some_and(|c| c.is_ascii_alphabetic() || c == '_')
+        && name.chars().all(|c| c.is_ascii_alphanumeric() || c == '_');
+    if !valid {
+        return Err(format!(
+            "Invalid variable name '{}': use letters, digits and '_', not starting with a digit",
+            name
+        ));
+    }
+    Ok(())
+}
+
+/// Variables of the current session, empty when none were set
+pub fn load_session_env() -> BTreeMap<String, SessionEnvVar> {
+    LocalStore::read_session_data(SESSION_ENV_FILE)
+        .ok()
+        .and_then(|content| serde_json::from_str(&content).ok())
+        .unwrap_or_default()
+}
+
+fn save_session_env(env: &BTreeMap<String, SessionEnvVar>) -> Result<(), String> {
+    let content = serde_json::to_string_pretty(env).map_err(|e| e.to_string())?;
+    LocalStore::write_session_data(SESSION_ENV_FILE, &content)?;
+    Ok(())
+}
+
+/// Set `name` for every following command. Secret values must already be placeholders.
+pub fn set_session_env(name: &str, var: SessionEnvVar) -> Result<(), String> {
+    validate_env_name(name)?;
+    let mut env = load_session_env();
+    env.insert(name.to_string(), var);
+    save_session_env(&env)
+}
+
+/// Remove `name`, returning whether it was set
+pub fn unset_session_env(name: &str) -> Result<bool, String> {
+    let mut env = load_session_env();
+    if env.remove(name).is_none() {
+        return Ok(false);
+    }
+    save_session_env(&env)?;
+    Ok(true)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_validate_env_name() {
+        assert!(validate_env_name("AWS_PROFILE").is_ok());
+        assert!(validate_env_name("_private").is_ok());
+        assert!(validate_env_name("1PASSWORD").is_err());
+        assert!(validate_env_name("MY-VAR").is_err());
+        assert!(validate_env_name("").is_err());
+        assert_eq!(
+            env_secret_placeholder("DB_PASSWORD"),
+            "[ENV_SECRET:DB_PASSWORD]"
+        );
+    }
+}
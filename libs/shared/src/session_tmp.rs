@@ -0,0 +1,66 @@
+use crate::local_store::LocalStore;
+use std::path::PathBuf;
+use uuid::Uuid;
+
+const TMP_DIR: &str = "tmp";
+
+/// Scratch directories of this process. Each agent run has its own, so ending one session
+/// never removes the files of another running in the same workspace.
+fn process_tmp_root() -> PathBuf {
+    LocalStore::get_local_session_store_path()
+        .join(TMP_DIR)
+        .join(std::process::id().to_string())
+}
+
+/// Directory name made of `prefix`, restricted to safe characters, and a random suffix
+fn tmp_dir_name(prefix: Option<&str>) -> String {
+    let prefix: String = prefix
+        .unwrap_or_default()
+        .chars()
+        .filter(|c| c.is_ascii_alphanumeric() || matches!(c, '-' | '_' | '.'))
+        .collect();
+    let prefix = match prefix.trim_matches('.') {
+        "" => "tmp",
+        prefix => prefix,
+    };
+    let suffix = Uuid::new_v4().simple().to_string();
+    format!("{}-{}", prefix, &suffix[..8])
+}
+
+/// Create an empty scratch directory under `.stakpak/session/`, returning its absolute
+/// path. It is removed with [`cleanup_session_tmp_dirs`] when the session ends and, being
+/// under `.stakpak/`, never pushed or committed.
+pub fn create_session_tmp_dir(prefix: Option<&str>) -> Result<PathBuf, String> {
+    let path = process_tmp_root().join(tmp_dir_name(prefix));
+    std::fs::create_dir_all(&path)
+        .map_err(|e| format!("Failed to create {}: {}", path.display(), e))?;
+    let cwd = std::env::current_dir().map_err(|e| e.to_string())?;
+    Ok(cwd.join(path))
+}
+
+/// Remove the scratch directories created by this process
+pub fn cleanup_session_tmp_dirs() -> Result<(), String> {
+    let root = process_tmp_root();
+    if root.exists() {
+        std::fs::remove_dir_all(&root)
+            .map_err(|e| format!("Failed to remove {}: {}", root.display(), e))?;
+    }
+    // Only succeeds once no other session has scratch directories left
+    if let Some(tmp_dir) = root.parent() {
+        let _ = std::fs::remove_dir(tmp_dir);
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_tmp_dir_name() {
+        assert!(tmp_dir_name(Some("helm-charts")).starts_with("helm-charts-"));
+        assert!(tmp_dir_name(Some("../../etc")).starts_with("etc-"));
+        assert!(tmp_dir_name(None).starts_with("tmp-"));
+        assert_eq!(tmp_dir_name(None).len(), "tmp-".len() + 8);
+    }
+}
@@ -0,0 +1,105 @@
+use std::path::{Component, Path, PathBuf};
+
+/// Prefix of workspace-relative document URIs, e.g. `file:///modules/vpc/main.tf`
+pub const DOCUMENT_URI_PREFIX: &str = "file:///";
+
+/// Build the document URI of `path` relative to `base_dir`
+///
+/// Path components are joined with `/` regardless of the platform, so a file pushed from
+/// Windows gets the same URI as on Linux or macOS.
+pub fn document_uri(base_dir: &Path, path: &Path) -> Result<String, String> {
+    let relative = path.strip_prefix(base_dir).map_err(|e| {
+        format!(
+            "Failed to make {} relative to {}: {}",
+            path.display(),
+            base_dir.display(),
+            e
+        )
+    })?;
+    let segments: Vec<String> = relative
+        .components()
+        .filter_map(|component| match component {
+            Component::Normal(segment) => Some(segment.to_string_lossy().to_string()),
+            _ => None,
+        })
+        .collect();
+    Ok(relative_path_to_uri(&segments.join("/")))
+}
+
+/// Build the document URI of a workspace-relative path such as `./modules\vpc/main.tf`
+pub fn relative_path_to_uri(relative_path: &str) -> String {
+    let segments: Vec<&str> = relative_path
+        .split(['/', '\\'])
+        .filter(|segment| !segment.is_empty() && *segment != ".")
+        .collect();
+    format!("{}{}", DOCUMENT_URI_PREFIX, segments.join("/"))
+}
+
+/// Workspace-relative path of a document URI, URIs without the `file:///` prefix are
+/// returned as is
+pub fn uri_to_relative_path(uri: &str) -> &str {
+    uri.strip_prefix(DOCUMENT_URI_PREFIX).unwrap_or(uri)
+}
+
+/// Resolve a document URI to a path under `base_dir` using the platform separator
+pub fn uri_to_path(base_dir: &Path, uri: &str) -> PathBuf {
+    uri_to_relative_path(uri)
+        .split(['/', '\\'])
+        .filter(|segment| !segment.is_empty() && *segment != ".")
+        .fold(base_dir.to_path_buf(), |path, segment| path.join(segment))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_document_uri_is_relative_to_base_dir() {
+        let base_dir = Path::new(".");
+        let path = Path::new(".").join("modules").join("vpc").join("main.tf");
+        assert_eq!(
+            document_uri(base_dir, &path),
+            Ok("file:///modules/vpc/main.tf".to_string())
+        );
+        assert!(document_uri(Path::new("other"), &path).is_err());
+    }
+
+    #[test]
+    fn test_relative_path_to_uri_normalizes_separators() {
+        assert_eq!(
+            relative_path_to_uri(r"modules\vpc\main.tf"),
+            "file:///modules/vpc/main.tf"
+        );
+        assert_eq!(
+            relative_path_to_uri("./modules//vpc/main.tf"),
+            "file:///modules/vpc/main.tf"
+        );
+        assert_eq!(relative_path_to_uri("/Dockerfile"), "file:///Dockerfile");
+    }
+
+    #[test]
+    fn test_uri_round_trip() {
+        let base_dir = Path::new("workspace");
+        let uri = relative_path_to_uri("modules/vpc/main.tf");
+        let path = uri_to_path(base_dir, &uri);
+
+        assert_eq!(
+            path,
+            Path::new("workspace")
+                .join("modules")
+                .join("vpc")
+                .join("main.tf")
+        );
+        assert_eq!(document_uri(base_dir, &path), Ok(uri.clone()));
+        assert_eq!(uri_to_relative_path(&uri), "modules/vpc/main.tf");
+    }
+
+    #[test]
+    fn test_uri_without_prefix() {
+        assert_eq!(uri_to_relative_path("main.tf"), "main.tf");
+        assert_eq!(
+            uri_to_path(Path::new("."), "main.tf"),
+            Path::new(".").join("main.tf")
+        );
+    }
+}
@@ -0,0 +1,260 @@
+use serde::{Deserialize, Serialize};
+use std::path::{Component, Path, PathBuf};
+use std::sync::RwLock;
+
+/// Prefix of paths relative to a named workspace root, e.g. `root://infra/main.tf`
+pub const ROOT_URI_PREFIX: &str = "root://";
+
+static WORKSPACE_ROOTS: RwLock<Vec<WorkspaceRoot>> = RwLock::new(Vec::new());
+
+/// What tools may do under a workspace root
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Default)]
+#[serde(rename_all = "kebab-case")]
+pub enum RootAccess {
+    #[default]
+    ReadWrite,
+    /// Files can be viewed but not created or edited
+    ReadOnly,
+}
+
+impl std::fmt::Display for RootAccess {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            RootAccess::ReadWrite => write!(f, "read-write"),
+            RootAccess::ReadOnly => write!(f, "read-only"),
+        }
+    }
+}
+
+/// Directory of a multi-root workspace, addressed by tools as `root://<name>/...`
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct WorkspaceRoot {
+    pub name: String,
+    pub path: PathBuf,
+    #[serde(default)]
+    pub access: RootAccess,
+}
+
+/// Parse `[NAME=]PATH[:ro|:rw]`, the name defaults to the directory name of the path
+pub fn parse_workspace_root(s: &str) -> Result<WorkspaceRoot, String> {
+    let (s, access) = if let Some(path) = s.strip_suffix(":ro") {
+        (path, RootAccess::ReadOnly)
+    } else if let Some(path) = s.strip_suffix(":rw") {
+        (path, RootAccess::ReadWrite)
+    } else {
+        (s, RootAccess::ReadWrite)
+    };
+    let (name, path) = match s.split_once('=') {
+        Some((name, path)) => (name.trim().to_string(), PathBuf::from(path.trim())),
+        None => {
+            let path = PathBuf::from(s.trim());
+            let name = path
+                .components()
+                .filter_map(|component| match component {
+                    Component::Normal(segment) => Some(segment.to_string_lossy().to_string()),
+                    _ => None,
+                })
+                .next_back()
+                .ok_or_else(|| format!("Cannot name the workspace root '{}', use NAME=PATH", s))?;
+            (name, path)
+        }
+    };
+    if name.is_empty() || name.contains(['/', '\\']) {
+        return Err(format!("Invalid workspace root name '{}'", name));
+    }
+    if path.as_os_str().is_empty() {
+        return Err(format!("Workspace root '{}' has no path", name));
+    }
+    Ok(WorkspaceRoot { name, path, access })
+}
+
+/// Make the roots known to tools and the TUI for the rest of the process
+pub fn set_workspace_roots(roots: Vec<WorkspaceRoot>) {
+    if let Ok(mut current) = WORKSPACE_ROOTS.write() {
+        *current = roots;
+    }
+}
+
+pub fn workspace_roots() -> Vec<WorkspaceRoot> {
+    WORKSPACE_ROOTS
+        .read()
+        .map(|roots| roots.clone())
+        .unwrap_or_default()
+}
+
+fn resolve_in(roots: &[WorkspaceRoot], path: &str) -> Result<PathBuf, String> {
+    let Some(rest) = path.strip_prefix(ROOT_URI_PREFIX) else {
+        return Ok(PathBuf::from(path));
+    };
+    let (name, relative) = rest.split_once('/').unwrap_or((rest, ""));
+    let root = roots.iter().find(|root| root.name == name).ok_or_else(|| {
+        format!(
+            "Unknown workspace root '{}', known roots: {}",
+            name,
+            roots
+                .iter()
+                .map(|root| root.name.as_str())
+                .collect::<Vec<_>>()
+                .join(", ")
+        )
+    })?;
+    let relative = Path::new(relative);
+    if relative
+        .components()
+        .any(|component| !matches!(component, Component::Normal(_) | Component::CurDir))
+    {
+        return Err(format!("{} escapes the workspace root '{}'", path, name));
+    }
+    Ok(root.path.join(relative))
+}
+
+/// Resolve `root://<name>/<path>` against its workspace root, other paths are returned as is
+pub fn resolve_path(path: &str) -> Result<PathBuf, String> {
+    resolve_in(&workspace_roots(), path)
+}
+
+fn root_containing<'a>(roots: &'a [WorkspaceRoot], path: &Path) -> Option<&'a WorkspaceRoot> {
+    let absolute = if path.is_absolute() {
+        path.to_path_buf()
+    } else {
+        std::env::current_dir().unwrap_or_default().join(path)
+    };
+    roots
+        .iter()
+        .filter(|root| absolute.starts_with(&root.path))
+        .max_by_key(|root| root.path.components().count())
+}
+
+/// Workspace root `path` lies in, the innermost one for nested roots
+pub fn root_of(path: &Path) -> Option<WorkspaceRoot> {
+    root_containing(&workspace_roots(), path).cloned()
+}
+
+/// Whether `path` lies in the working directory or a workspace root. The path does not
+/// need to exist, and a symlink at `path` itself is not followed. The directories
+/// themselves do not count as inside.
+pub fn is_inside_workspace(path: &Path) -> bool {
+    is_inside(
+        path,
+        std::env::current_dir()
+            .into_iter()
+            .chain(workspace_roots().into_iter().map(|root| root.path)),
+    )
+}
+
+fn is_inside(path: &Path, dirs: impl IntoIterator<Item = PathBuf>) -> bool {
+    let absolute = if path.is_absolute() {
+        path.to_path_buf()
+    } else {
+        std::env::current_dir().unwrap_or_default().join(path)
+    };
+    // Resolve the deepest existing ancestor, the rest may not exist yet
+    let mut existing = absolute.parent();
+    while let Some(dir) = existing.filter(|dir| !dir.exists()) {
+        existing = dir.parent();
+    }
+    let Some(existing) = existing else {
+        return false;
+    };
+    let Ok(rest) = absolute.strip_prefix(existing) else {
+        return false;
+    };
+    if rest
+        .components()
+        .any(|component| !matches!(component, Component::Normal(_)))
+    {
+        return false;
+    }
+    let Ok(resolved) = existing.canonicalize().map(|dir| dir.join(rest)) else {
+        return false;
+    };
+    dirs.into_iter()
+        .filter_map(|dir| dir.canonicalize().ok())
+        .any(|dir| resolved.starts_with(&dir) && resolved != dir)
+}
+
+/// Resolve `path` like [`resolve_path`] and refuse it if it lies in a read-only root
+pub fn resolve_writable_path(path: &str) -> Result<PathBuf, String> {
+    let roots = workspace_roots();
+    let resolved = resolve_in(&roots, path)?;
+    if let Some(root) = root_containing(&roots, &resolved) {
+        if root.access == RootAccess::ReadOnly {
+            return Err(format!(
+                "{} is in the read-only workspace root '{}'",
+                path, root.name
+            ));
+        }
+    }
+    Ok(resolved)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_resolve_root_paths() {
+        let infra = parse_workspace_root("/src/infra:ro").unwrap();
+        assert_eq!(infra.name, "infra");
+        assert_eq!(infra.access, RootAccess::ReadOnly);
+        let app = parse_workspace_root("app=/src/web-app").unwrap();
+        assert_eq!(app.path, PathBuf::from("/src/web-app"));
+        assert!(parse_workspace_root("a/b=/src").is_err());
+
+        let roots = vec![infra.clone(), app];
+        assert_eq!(
+            resolve_in(&roots, "root://infra/modules/vpc/main.tf"),
+            Ok(PathBuf::from("/src/infra/modules/vpc/main.tf"))
+        );
+        assert_eq!(
+            resolve_in(&roots, "root://app"),
+            Ok(PathBuf::from("/src/web-app"))
+        );
+        assert_eq!(resolve_in(&roots, "main.tf"), Ok(PathBuf::from("main.tf")));
+        assert!(resolve_in(&roots, "root://infra/../../etc/passwd").is_err());
+        assert!(resolve_in(&roots, "root://docs/README.md").is_err());
+
+        assert_eq!(
+            root_containing(&roots, Path::new("/src/infra/main.tf")),
+            Some(&infra)
+        );
+        assert_eq!(root_containing(&roots, Path::new("/src/other")), None);
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_is_inside() {
+        let base = std::env::temp_dir().join(format!("stakpak-workspace-{}", std::process::id()));
+        let workspace = base.join("workspace");
+        let outside = base.join("outside");
+        std::fs::create_dir_all(workspace.join("src")).unwrap();
+        std::fs::create_dir_all(&outside).unwrap();
+        std::os::unix::fs::symlink(&outside, workspace.join("linked")).unwrap();
+        std::os::unix::fs::symlink(outside.join("secret"), workspace.join("link")).unwrap();
+        let dirs = || vec![workspace.clone()];
+
+        assert!(is_inside(&workspace.join("src/main.rs"), dirs()));
+        // Paths that don't exist yet are fine
+        assert!(is_inside(&workspace.join("new/dir/file.txt"), dirs()));
+        assert!(!is_inside(&workspace, dirs()));
+        assert!(!is_inside(&outside.join("file.txt"), dirs()));
+
+        // `..` can't climb out, whether the directories it walks through exist or not
+        assert!(!is_inside(
+            &workspace.join("src/../../outside/file.txt"),
+            dirs()
+        ));
+        assert!(!is_inside(
+            &workspace.join("missing/../../outside/file.txt"),
+            dirs()
+        ));
+        assert!(!is_inside(&workspace.join("src/.."), dirs()));
+        assert!(is_inside(&workspace.join("src/../Cargo.toml"), dirs()));
+
+        // Symlinked directories are followed, a symlink at the path itself is not
+        assert!(!is_inside(&workspace.join("linked/file.txt"), dirs()));
+        assert!(is_inside(&workspace.join("link"), dirs()));
+
+        let _ = std::fs::remove_dir_all(&base);
+    }
+}
@@ -0,0 +1,220 @@
+use crate::local_store::LocalStore;
+use crate::policy::{classify_command, split_commands};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::path::PathBuf;
+
+const APPROVALS_FILE: &str = "policy_approvals.json";
+/// Approvals of the same command pattern after which it is suggested for the workspace policy
+pub const SUGGEST_AFTER_APPROVALS: usize = 3;
+/// Approved commands kept per pattern to show when reviewing a suggestion
+const MAX_EXAMPLES: usize = 3;
+
+/// Commands of this workspace that run without asking for confirmation
+///
+/// ```toml
+/// allow = ["terraform plan *", "kubectl get *"]
+/// ```
+#[derive(Serialize, Deserialize, Debug, Clone, Default, PartialEq)]
+pub struct WorkspacePolicy {
+    /// Command patterns, a trailing `*` matches any remaining arguments
+    #[serde(default)]
+    pub allow: Vec<String>,
+}
+
+impl WorkspacePolicy {
+    pub fn path() -> PathBuf {
+        PathBuf::from(".stakpak").join("policy.toml")
+    }
+
+    /// Load the policy of the current workspace, empty when there is none
+    pub fn load() -> Result<Self, String> {
+        let path = Self::path();
+        match std::fs::read_to_string(&path) {
+            Ok(content) => toml::from_str(&content)
+                .map_err(|e| format!("Failed to parse {}: {}", path.display(), e)),
+            Err(_) => Ok(Self::default()),
+        }
+    }
+
+    pub fn save(&self) -> Result<(), String> {
+        let path = Self::path();
+        if let Some(dir) = path.parent() {
+            std::fs::create_dir_all(dir)
+                .map_err(|e| format!("Failed to create {}: {}", dir.display(), e))?;
+        }
+        let content = toml::to_string(self).map_err(|e| e.to_string())?;
+        std::fs::write(&path, content)
+            .map_err(|e| format!("Failed to write {}: {}", path.display(), e))
+    }
+
+    /// Whether `command` may run without confirmation. Every part of a pipeline or command
+    /// list has to match a pattern, and commands needing a typed confirmation never do.
+    pub fn allows_command(&self, command: &str) -> bool {
+        if classify_command(command)
+            .level
+            .requires_typed_confirmation()
+        {
+            return false;
+        }
+        let mut segments = split_commands(command)
+            .into_iter()
+            .filter(|segment| !segment.trim().is_empty())
+            .peekable();
+        segments.peek().is_some()
+            && segments.all(|segment| {
+                self.allow
+                    .iter()
+                    .any(|pattern| pattern_matches(pattern, segment))
+            })
+    }
+}
+
+fn pattern_matches(pattern: &str, segment: &str) -> bool {
+    // Redirections and substitutions can do anything the pattern did not intend
+    if segment.contains(['>', '<', '`']) || segment.contains("$(") {
+        return false;
+    }
+    let tokens: Vec<&str> = segment.split_whitespace().collect();
+    let expected: Vec<&str> = pattern.split_whitespace().collect();
+    match expected.split_last() {
+        Some((&"*", prefix)) => tokens.starts_with(prefix),
+        _ => tokens == expected,
+    }
+}
+
+/// Pattern covering `command` and its variations, e.g. `terraform plan *` for
+/// `terraform plan -out plan.tfplan`. `None` for pipelines, command lists and commands
+/// needing a typed confirmation, which are never learned.
+pub fn command_pattern(command: &str) -> Option<String> {
+    let segments: Vec<&str> = split_commands(command)
+        .into_iter()
+        .filter(|segment| !segment.trim().is_empty())
+        .collect();
+    let [segment] = segments.as_slice() else {
+        return None;
+    };
+    if classify_command(segment)
+        .level
+        .requires_typed_confirmation()
+    {
+        return None;
+    }
+    let tokens: Vec<&str> = segment.split_whitespace().collect();
+    let (program, args) = tokens.split_first()?;
+    if program.contains('=') {
+        return None;
+    }
+    let mut pattern = vec![*program];
+    if let Some(subcommand) = args.first() {
+        let is_subcommand = subcommand
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || c == '-')
+            && !subcommand.starts_with('-');
+        if is_subcommand {
+            pattern.push(subcommand);
+        }
+    }
+    if tokens.len() > pattern.len() {
+        pattern.push("*");
+    }
+    let pattern = pattern.join(" ");
+    pattern_matches(&pattern, segment).then_some(pattern)
+}
+
+/// How often a command pattern was approved by hand
+#[derive(Serialize, Deserialize, Debug, Clone, Default, PartialEq)]
+pub struct PatternApprovals {
+    pub count: usize,
+    /// Latest approved commands matching the pattern
+    pub examples: Vec<String>,
+    /// The user declined adding the pattern to the policy
+    #[serde(default)]
+    pub dismissed: bool,
+}
+
+fn load_approvals() -> BTreeMap<String, PatternApprovals> {
+    LocalStore::read_session_data(APPROVALS_FILE)
+        .ok()
+        .and_then(|content| serde_json::from_str(&content).ok())
+        .unwrap_or_default()
+}
+
+fn save_approvals(approvals: &BTreeMap<String, PatternApprovals>) -> Result<(), String> {
+    let content = serde_json::to_string_pretty(approvals).map_err(|e| e.to_string())?;
+    LocalStore::write_session_data(APPROVALS_FILE, &content)?;
+    Ok(())
+}
+
+/// Count a confirmation of `command` given by the user. Returns the command pattern the
+/// moment it has been approved often enough to be suggested for the workspace policy.
+pub fn record_approval(command: &str) -> Result<Option<String>, String> {
+    let Some(pattern) = command_pattern(command) else {
+        return Ok(None);
+    };
+    if WorkspacePolicy::load()?.allow.contains(&pattern) {
+        return Ok(None);
+    }
+    let mut approvals = load_approvals();
+    let entry = approvals.entry(pattern.clone()).or_default();
+    entry.count += 1;
+    entry.examples.retain(|example| example != command);
+    entry.examples.push(command.to_string());
+    if entry.examples.len() > MAX_EXAMPLES {
+        entry.examples.remove(0);
+    }
+    let suggest = entry.count == SUGGEST_AFTER_APPROVALS && !entry.dismissed;
+    save_approvals(&approvals)?;
+    Ok(suggest.then_some(pattern))
+}
+
+/// Patterns approved often enough to be added to the policy, most approved first
+pub fn policy_suggestions() -> Result<Vec<(String, PatternApprovals)>, String> {
+    let policy = WorkspacePolicy::load()?;
+    let mut suggestions: Vec<_> = load_approvals()
+        .into_iter()
+        .filter(|(pattern, approvals)| {
+            approvals.count >= SUGGEST_AFTER_APPROVALS
+                && !approvals.dismissed
+                && !policy.allow.contains(pattern)
+        })
+        .collect();
+    suggestions.sort_by(|a, b| b.1.count.cmp(&a.1.count));
+    Ok(suggestions)
+}
+
+/// Stop suggesting `pattern` for the workspace policy
+pub fn dismiss_suggestion(pattern: &str) -> Result<(), String> {
+    let mut approvals = load_approvals();
+    if let Some(entry) = approvals.get_mut(pattern) {
+        entry.dismissed = true;
+        save_approvals(&approvals)?;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_command_patterns() {
+        assert_eq!(
+            command_pattern("terraform plan -out plan.tfplan"),
+            Some("terraform plan *".to_string())
+        );
+        assert_eq!(command_pattern("ls -la"), Some("ls *".to_string()));
+        assert_eq!(command_pattern("pwd"), Some("pwd".to_string()));
+        assert_eq!(command_pattern("cat main.tf | grep aws"), None);
+        assert_eq!(command_pattern("terraform apply"), None);
+
+        let policy = WorkspacePolicy {
+            allow: vec!["kubectl get *".to_string(), "grep *".to_string()],
+        };
+        assert!(policy.allows_command("kubectl get pods -A"));
+        assert!(policy.allows_command("kubectl get pods | grep api"));
+        assert!(!policy.allows_command("kubectl get pods > pods.txt"));
+        assert!(!policy.allows_command("kubectl describe pod api"));
+        assert!(!policy.allows_command("kubectl get pods; kubectl delete pod api"));
+    }
+}
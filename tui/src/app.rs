@@ -1,9 +1,17 @@
-use crate::services::message::Message;
+use crate::services::command_palette::CommandPalette;
+use crate::services::file_history::FileHistoryView;
+use crate::services::message::{Message, WrappedLinesCache};
+use crate::services::question_form::{QuestionAnswer, QuestionForm};
 use ratatui::style::Style;
+use serde::{Deserialize, Serialize};
 use stakpak_shared::models::integrations::openai::{
     ToolCall, ToolCallResult, ToolCallResultProgress,
 };
+use stakpak_shared::policy::CommandRisk;
+use stakpak_shared::policy_rules::PolicyDecision;
 use std::collections::HashMap;
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
 use uuid::Uuid;
 
 #[derive(Debug)]
@@ -14,6 +22,52 @@ pub struct SessionInfo {
     pub checkpoints: Vec<String>,
 }
 
+/// Latency of the completion currently being streamed
+#[derive(Debug, Clone, Copy)]
+pub struct StreamMetrics {
+    pub time_to_first_token: Duration,
+    pub tokens_per_second: f64,
+}
+
+/// What happens to a tool call when its confirmation dialog is not answered in time
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum TimeoutAction {
+    #[default]
+    Skip,
+    /// Approve read-only and local write calls, skip the ones that need a typed confirmation
+    ApproveLowRisk,
+}
+
+/// Countdown on the confirmation dialog for semi-attended sessions
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct ConfirmationTimeout {
+    pub secs: u64,
+    #[serde(default)]
+    pub default_action: TimeoutAction,
+}
+
+/// Outcome of a `!command` run from the input box
+#[derive(Debug, Clone, PartialEq)]
+pub struct ShellCommandResult {
+    pub command: String,
+    /// Combined stdout and stderr, possibly cut to its last lines
+    pub output: String,
+    /// `None` when the command was killed by a signal
+    pub exit_code: Option<i32>,
+}
+
+/// File span an answer referred to as `[path:line]` or `[path:start-end]`
+#[derive(Debug, Clone, PartialEq)]
+pub struct Citation {
+    /// The citation as written in the answer, without the brackets
+    pub label: String,
+    /// `file://` link to the cited file, `None` when the span could not be verified
+    pub url: Option<String>,
+    /// Why the span could not be verified
+    pub problem: Option<String>,
+}
+
 #[derive(Debug, PartialEq)]
 pub enum LoadingType {
     Llm,
@@ -35,7 +89,12 @@ pub struct AppState {
     pub show_shortcuts: bool,
     pub is_dialog_open: bool,
     pub dialog_command: Option<ToolCall>,
+    /// Risk classification of the tool call awaiting confirmation
+    pub dialog_risk: Option<CommandRisk>,
     pub dialog_selected: usize,
+    /// When the pending tool call gets the default action, cleared once the user types
+    pub dialog_deadline: Option<Instant>,
+    pub confirmation_timeout: Option<ConfirmationTimeout>,
     pub loading: bool,
     pub loading_type: LoadingType,
     pub spinner_frame: usize,
@@ -43,10 +102,31 @@ pub struct AppState {
     pub show_sessions_dialog: bool,
     pub session_selected: usize,
     pub account_info: String,
+    pub usage_report: String,
+    /// Messages kept as they are when the history is compacted, see `/pin`
+    pub pinned_messages: Vec<String>,
     pub pending_bash_message_id: Option<Uuid>, // New field to track pending bash message
     pub streaming_tool_results: HashMap<Uuid, String>,
     pub streaming_tool_result_id: Option<Uuid>,
     pub is_pasting: bool,
+    pub question_form: Option<QuestionForm>,
+    pub stream_metrics: Option<StreamMetrics>,
+    /// Full output of the last truncated command result, opened with ctrl + o
+    pub saved_output_path: Option<String>,
+    /// File history opened with /filehistory
+    pub file_history: Option<FileHistoryView>,
+    pub command_palette: Option<CommandPalette>,
+    /// Output buffers of running `!command`s
+    pub shell_outputs: HashMap<Uuid, String>,
+    /// Last finished `!command`, until the user decides whether the agent sees it
+    pub pending_shell_share: Option<ShellCommandResult>,
+    /// Session variable whose secret value is being typed, the input is masked meanwhile
+    pub secret_env_name: Option<String>,
+    pub wrapped_lines_cache: Mutex<WrappedLinesCache>,
+    /// Width the scroll position was last computed for, to reflow it on resize
+    pub message_area_width: usize,
+    /// Verified citation labels and the files they link to, hyperlinked wherever shown
+    pub citation_links: Vec<(String, String)>,
 }
 
 #[derive(Debug)]
@@ -56,10 +136,22 @@ pub enum InputEvent {
     RunToolCall(ToolCall),
     ToolResult(ToolCallResult),
     StreamToolResult(ToolCallResultProgress),
+    ShellOutput(Uuid, String),
+    ShellFinished(Uuid, ShellCommandResult),
+    StreamMetrics(StreamMetrics),
+    /// Workspace spans cited by the last answer, checked against the files
+    Citations(Vec<Citation>),
+    ErrorTriage(String),
+    AskUser(ToolCall),
     Loading(bool),
     InputChanged(char),
     GetStatus(String),
+    /// Token usage and estimated cost of the session, shown with `/usage`
+    UsageReport(String),
+    SystemMessage(String),
     Error(String),
+    /// The agent pinned a message, see `/pin`
+    MessagePinned(String),
     SetSessions(Vec<SessionInfo>),
     InputBackspace,
     InputChangedNewline,
@@ -81,11 +173,18 @@ pub enum InputEvent {
     CursorRight,
     ToggleCursorVisible,
     Resized(u16, u16),
+    /// The terminal gained or lost focus
+    FocusChanged(bool),
     ShowConfirmationDialog(ToolCall),
+    /// Tool call decided by a rule of `.stakpak/policies.yaml`
+    ApplyToolPolicy(ToolCall, PolicyDecision),
     DialogConfirm,
     DialogCancel,
     Tab,
+    BackTab,
     HandlePaste(String),
+    OpenPager,
+    OpenCommandPalette,
 }
 
 #[derive(Debug)]
@@ -93,8 +192,30 @@ pub enum OutputEvent {
     UserMessage(String),
     AcceptTool(ToolCall),
     RejectTool(ToolCall),
+    /// Sent before the AcceptTool or RejectTool of a dialog that timed out
+    ConfirmationTimedOut {
+        tool_call: ToolCall,
+        approved: bool,
+    },
+    SubmitAnswers(ToolCall, Vec<QuestionAnswer>),
     ListSessions,
     SwitchToSession(String),
+    LabelCheckpoint(String),
+    RunShellCommand(Uuid, String),
+    /// Add the output of a `!command` to the conversation
+    ShareShellOutput(ShellCommandResult),
+    /// Set a variable for every following `run_command`, see `/env`
+    SetSessionEnv {
+        name: String,
+        value: String,
+        secret: bool,
+    },
+    UnsetSessionEnv(String),
+    /// Summarize older turns now instead of waiting for the history to outgrow its budget
+    CompactHistory,
+    /// Keep a sent message as it is whenever the history is compacted
+    PinMessage(String),
+    UnpinMessage(String),
 }
 
 impl AppState {
@@ -155,7 +276,10 @@ impl AppState {
             show_shortcuts: false,
             is_dialog_open: false,
             dialog_command: None,
+            dialog_risk: None,
             dialog_selected: 0,
+            dialog_deadline: None,
+            confirmation_timeout: None,
             loading: false,
             loading_type: LoadingType::Llm,
             spinner_frame: 0,
@@ -163,10 +287,23 @@ impl AppState {
             show_sessions_dialog: false,
             session_selected: 0,
             account_info: String::new(),
+            usage_report: String::new(),
+            pinned_messages: Vec::new(),
             pending_bash_message_id: None, // Initialize new field
             streaming_tool_results: HashMap::new(),
             streaming_tool_result_id: None,
             is_pasting: false,
+            question_form: None,
+            stream_metrics: None,
+            saved_output_path: None,
+            file_history: None,
+            command_palette: None,
+            shell_outputs: HashMap::new(),
+            pending_shell_share: None,
+            secret_env_name: None,
+            wrapped_lines_cache: Mutex::new(WrappedLinesCache::default()),
+            message_area_width: 0,
+            citation_links: Vec::new(),
         }
     }
 }
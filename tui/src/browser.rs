@@ -0,0 +1,604 @@
+use crate::services::markdown::render_markdown_to_lines;
+use crate::terminal::TerminalGuard;
+use crossterm::event::{Event, KeyCode, KeyEvent, KeyEventKind, KeyModifiers};
+use crossterm::{execute, terminal::EnterAlternateScreen};
+use ratatui::{
+    Frame, Terminal,
+    backend::CrosstermBackend,
+    layout::{Constraint, Direction, Layout},
+    style::{Color, Modifier, Style},
+    text::{Line, Span},
+    widgets::{Block, Borders, List, ListItem, ListState, Paragraph},
+};
+use std::io;
+use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, Ordering};
+use tokio::sync::mpsc::{Receiver, Sender};
+use tokio::time::Duration;
+
+const READER_POLL_INTERVAL: Duration = Duration::from_millis(100);
+const PAGE_LINES: usize = 20;
+
+#[derive(Debug, Clone)]
+pub struct BrowseFlow {
+    pub name: String,
+    pub visibility: String,
+    pub updated_at: String,
+    /// Newest version first
+    pub versions: Vec<BrowseVersion>,
+}
+
+#[derive(Debug, Clone)]
+pub struct BrowseVersion {
+    /// `<owner_name>/<flow_name>/<version_id>`
+    pub flow_ref: String,
+    pub id: String,
+    pub created_at: String,
+    pub tags: Vec<String>,
+    pub immutable: bool,
+}
+
+#[derive(Debug, Clone)]
+pub struct BrowseDocument {
+    pub path: String,
+    pub provisioner: String,
+    pub content: String,
+}
+
+/// Documents of a flow version, sent back for a flow ref requested by the browser
+pub type DocumentsResponse = (String, Result<Vec<BrowseDocument>, String>);
+
+/// What the user picked before leaving the browser, for the flow ref of a version
+#[derive(Debug, Clone, PartialEq)]
+pub enum BrowseAction {
+    Clone(String),
+    StartSession(String),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Screen {
+    Flows,
+    Versions,
+    Documents,
+    Content,
+}
+
+struct BrowserState {
+    flows: Vec<BrowseFlow>,
+    screen: Screen,
+    flow: usize,
+    version: usize,
+    document: usize,
+    documents: Vec<BrowseDocument>,
+    /// Flow ref `documents` belong to, or are being fetched for
+    documents_ref: Option<String>,
+    loading: bool,
+    content: Vec<Line<'static>>,
+    /// Cursor in the filtered list, or first visible line of the content
+    selected: usize,
+    filter: String,
+    searching: bool,
+    status: Option<String>,
+}
+
+enum KeyOutcome {
+    Continue,
+    FetchDocuments(String),
+    Exit(Option<BrowseAction>),
+}
+
+fn code_block_language(path: &str) -> &str {
+    let name = path.rsplit('/').next().unwrap_or(path);
+    if name.starts_with("Dockerfile") {
+        return "dockerfile";
+    }
+    match name.rsplit_once('.').map(|(_, ext)| ext) {
+        Some("tf") | Some("tfvars") | Some("hcl") => "hcl",
+        Some("yml") | Some("yaml") => "yaml",
+        Some("sh") => "bash",
+        Some(ext) => ext,
+        None => "",
+    }
+}
+
+fn line_text(line: &Line) -> String {
+    line.spans
+        .iter()
+        .map(|span| span.content.as_ref())
+        .collect()
+}
+
+impl BrowserState {
+    fn new(flows: Vec<BrowseFlow>) -> Self {
+        Self {
+            flows,
+            screen: Screen::Flows,
+            flow: 0,
+            version: 0,
+            document: 0,
+            documents: Vec::new(),
+            documents_ref: None,
+            loading: false,
+            content: Vec::new(),
+            selected: 0,
+            filter: String::new(),
+            searching: false,
+            status: None,
+        }
+    }
+
+    fn labels(&self) -> Vec<String> {
+        match self.screen {
+            Screen::Flows => self
+                .flows
+                .iter()
+                .map(|flow| {
+                    format!(
+                        "{}  ·  {} versions  ·  updated {}  ·  {}",
+                        flow.name,
+                        flow.versions.len(),
+                        flow.updated_at,
+                        flow.visibility
+                    )
+                })
+                .collect(),
+            Screen::Versions => self
+                .flows
+                .get(self.flow)
+                .map(|flow| {
+                    flow.versions
+                        .iter()
+                        .map(|version| {
+                            let mut label = format!("{}  ·  {}", version.id, version.created_at);
+                            if !version.tags.is_empty() {
+                                label.push_str(&format!("  ·  [{}]", version.tags.join(", ")));
+                            }
+                            if version.immutable {
+                                label.push_str("  ·  immutable");
+                            }
+                            label
+                        })
+                        .collect()
+                })
+                .unwrap_or_default(),
+            Screen::Documents => self
+                .documents
+                .iter()
+                .map(|document| format!("{}  ·  {}", document.path, document.provisioner))
+                .collect(),
+            Screen::Content => Vec::new(),
+        }
+    }
+
+    /// Items of the current list matching the filter, with their index in the unfiltered list
+    fn visible_items(&self) -> Vec<(usize, String)> {
+        let filter = self.filter.to_lowercase();
+        self.labels()
+            .into_iter()
+            .enumerate()
+            .filter(|(_, label)| label.to_lowercase().contains(&filter))
+            .collect()
+    }
+
+    fn selected_index(&self) -> Option<usize> {
+        self.visible_items()
+            .get(self.selected)
+            .map(|(index, _)| *index)
+    }
+
+    /// Version the clone and session actions apply to, the newest one on the flow list
+    fn selected_flow_ref(&self) -> Option<String> {
+        let (flow, version) = match self.screen {
+            Screen::Flows => (self.selected_index()?, 0),
+            Screen::Versions => (self.flow, self.selected_index()?),
+            Screen::Documents | Screen::Content => (self.flow, self.version),
+        };
+        self.flows
+            .get(flow)?
+            .versions
+            .get(version)
+            .map(|version| version.flow_ref.clone())
+    }
+
+    fn enter_screen(&mut self, screen: Screen, selected: usize) {
+        self.screen = screen;
+        self.selected = selected;
+        self.filter.clear();
+        self.searching = false;
+        self.status = None;
+    }
+
+    fn drill_down(&mut self) -> KeyOutcome {
+        match self.screen {
+            Screen::Flows => {
+                if let Some(flow) = self.selected_index() {
+                    self.flow = flow;
+                    self.enter_screen(Screen::Versions, 0);
+                }
+            }
+            Screen::Versions => {
+                let Some(version) = self.selected_index() else {
+                    return KeyOutcome::Continue;
+                };
+                self.version = version;
+                let Some(flow_ref) = self.selected_flow_ref() else {
+                    return KeyOutcome::Continue;
+                };
+                self.enter_screen(Screen::Documents, 0);
+                if self.documents_ref.as_ref() != Some(&flow_ref) {
+                    self.documents.clear();
+                    self.documents_ref = Some(flow_ref.clone());
+                    self.loading = true;
+                    return KeyOutcome::FetchDocuments(flow_ref);
+                }
+            }
+            Screen::Documents => {
+                let Some(index) = self.selected_index() else {
+                    return KeyOutcome::Continue;
+                };
+                if let Some(document) = self.documents.get(index) {
+                    self.document = index;
+                    self.content = render_markdown_to_lines(
+                        &format!(
+                            "```{}\n{}\n```",
+                            code_block_language(&document.path),
+                            document.content
+                        ),
+                        0,
+                    );
+                    self.enter_screen(Screen::Content, 0);
+                }
+            }
+            Screen::Content => {}
+        }
+        KeyOutcome::Continue
+    }
+
+    fn go_back(&mut self) {
+        match self.screen {
+            Screen::Flows => {}
+            Screen::Versions => self.enter_screen(Screen::Flows, self.flow),
+            Screen::Documents => self.enter_screen(Screen::Versions, self.version),
+            Screen::Content => self.enter_screen(Screen::Documents, self.document),
+        }
+    }
+
+    /// Scroll the content to the next line containing the search term, starting at `from`
+    fn find_in_content(&mut self, from: usize) {
+        if self.filter.is_empty() {
+            return;
+        }
+        let term = self.filter.to_lowercase();
+        let found = (from..self.content.len())
+            .chain(0..from.min(self.content.len()))
+            .find(|index| {
+                line_text(&self.content[*index])
+                    .to_lowercase()
+                    .contains(&term)
+            });
+        match found {
+            Some(index) => {
+                self.selected = index;
+                self.status = None;
+            }
+            None => self.status = Some(format!("No match for '{}'", self.filter)),
+        }
+    }
+
+    fn move_cursor(&mut self, delta: isize) {
+        let len = match self.screen {
+            Screen::Content => self.content.len(),
+            _ => self.visible_items().len(),
+        };
+        let max = len.saturating_sub(1) as isize;
+        self.selected = (self.selected as isize + delta).clamp(0, max) as usize;
+    }
+
+    fn handle_key(&mut self, key: KeyEvent) -> KeyOutcome {
+        if key.kind != KeyEventKind::Press {
+            return KeyOutcome::Continue;
+        }
+        if key.modifiers.contains(KeyModifiers::CONTROL) && key.code == KeyCode::Char('c') {
+            return KeyOutcome::Exit(None);
+        }
+
+        if self.searching {
+            match key.code {
+                KeyCode::Char(c) => self.filter.push(c),
+                KeyCode::Backspace => {
+                    self.filter.pop();
+                }
+                KeyCode::Enter => {
+                    self.searching = false;
+                    if self.screen == Screen::Content {
+                        self.find_in_content(self.selected);
+                    }
+                }
+                KeyCode::Esc => {
+                    self.searching = false;
+                    self.filter.clear();
+                }
+                _ => {}
+            }
+            if self.screen != Screen::Content {
+                self.selected = 0;
+            }
+            return KeyOutcome::Continue;
+        }
+
+        match key.code {
+            KeyCode::Char('q') => return KeyOutcome::Exit(None),
+            KeyCode::Up | KeyCode::Char('k') => self.move_cursor(-1),
+            KeyCode::Down | KeyCode::Char('j') => self.move_cursor(1),
+            KeyCode::PageUp => self.move_cursor(-(PAGE_LINES as isize)),
+            KeyCode::PageDown => self.move_cursor(PAGE_LINES as isize),
+            KeyCode::Char('/') => {
+                self.searching = true;
+                self.filter.clear();
+            }
+            KeyCode::Char('n') if self.screen == Screen::Content => {
+                self.find_in_content(self.selected + 1)
+            }
+            KeyCode::Enter | KeyCode::Right | KeyCode::Char('l') => return self.drill_down(),
+            KeyCode::Esc if !self.filter.is_empty() => {
+                self.filter.clear();
+                self.selected = 0;
+            }
+            KeyCode::Esc | KeyCode::Backspace | KeyCode::Left | KeyCode::Char('h') => {
+                self.go_back()
+            }
+            KeyCode::Char('c') => {
+                if let Some(flow_ref) = self.selected_flow_ref() {
+                    return KeyOutcome::Exit(Some(BrowseAction::Clone(flow_ref)));
+                }
+            }
+            KeyCode::Char('s') => {
+                if let Some(flow_ref) = self.selected_flow_ref() {
+                    return KeyOutcome::Exit(Some(BrowseAction::StartSession(flow_ref)));
+                }
+            }
+            _ => {}
+        }
+        KeyOutcome::Continue
+    }
+
+    fn handle_documents(&mut self, (flow_ref, result): DocumentsResponse) {
+        if self.documents_ref.as_ref() != Some(&flow_ref) {
+            return;
+        }
+        self.loading = false;
+        match result {
+            Ok(documents) => self.documents = documents,
+            Err(e) => {
+                self.documents_ref = None;
+                self.status = Some(format!("Failed to load documents: {}", e));
+            }
+        }
+    }
+
+    fn breadcrumb(&self) -> String {
+        let flow = self.flows.get(self.flow);
+        let version = flow.and_then(|flow| flow.versions.get(self.version));
+        match self.screen {
+            Screen::Flows => "Flows".to_string(),
+            Screen::Versions => format!(
+                "Flows › {}",
+                flow.map(|flow| flow.name.as_str()).unwrap_or_default()
+            ),
+            Screen::Documents | Screen::Content => format!(
+                "Flows › {} › {}",
+                flow.map(|flow| flow.name.as_str()).unwrap_or_default(),
+                version
+                    .map(|version| version.id.as_str())
+                    .unwrap_or_default()
+            ),
+        }
+    }
+
+    fn help(&self) -> &'static str {
+        match self.screen {
+            Screen::Flows | Screen::Versions => {
+                "enter open · / search · c clone · s start session · esc back · q quit"
+            }
+            Screen::Documents => {
+                "enter view · / search · c clone · s start session · esc back · q quit"
+            }
+            Screen::Content => {
+                "↑/↓ scroll · / search · n next match · c clone · s start session · esc back · q quit"
+            }
+        }
+    }
+}
+
+fn draw(f: &mut Frame, state: &BrowserState) {
+    let chunks = Layout::default()
+        .direction(Direction::Vertical)
+        .constraints([
+            Constraint::Length(1),
+            Constraint::Min(1),
+            Constraint::Length(1),
+        ])
+        .split(f.area());
+
+    f.render_widget(
+        Paragraph::new(Span::styled(
+            state.breadcrumb(),
+            Style::default()
+                .fg(Color::Yellow)
+                .add_modifier(Modifier::BOLD),
+        )),
+        chunks[0],
+    );
+
+    let block = Block::default()
+        .borders(Borders::ALL)
+        .border_style(Style::default().fg(Color::DarkGray));
+    if state.screen == Screen::Content {
+        let content = Paragraph::new(state.content.clone())
+            .block(block)
+            .scroll((state.selected as u16, 0));
+        f.render_widget(content, chunks[1]);
+    } else if state.loading {
+        f.render_widget(
+            Paragraph::new("Loading documents...")
+                .style(Style::default().fg(Color::Gray))
+                .block(block),
+            chunks[1],
+        );
+    } else {
+        let items: Vec<ListItem> = state
+            .visible_items()
+            .into_iter()
+            .map(|(_, label)| ListItem::new(Line::from(label)))
+            .collect();
+        let mut list_state = ListState::default();
+        list_state.select(Some(state.selected));
+        let list = List::new(items)
+            .highlight_style(
+                Style::default()
+                    .fg(Color::White)
+                    .bg(Color::Cyan)
+                    .add_modifier(Modifier::BOLD),
+            )
+            .style(Style::default().fg(Color::Gray))
+            .block(block);
+        f.render_stateful_widget(list, chunks[1], &mut list_state);
+    }
+
+    let footer = if state.searching {
+        Span::styled(
+            format!("/{}", state.filter),
+            Style::default().fg(Color::White),
+        )
+    } else if let Some(status) = &state.status {
+        Span::styled(status.clone(), Style::default().fg(Color::LightRed))
+    } else {
+        Span::styled(state.help(), Style::default().fg(Color::DarkGray))
+    };
+    f.render_widget(Paragraph::new(footer), chunks[2]);
+}
+
+/// Browse `flows` read-only. Documents of a version are requested through `request_tx`
+/// with the version flow ref and answered on `documents_rx`.
+pub async fn run_browser(
+    flows: Vec<BrowseFlow>,
+    request_tx: Sender<String>,
+    mut documents_rx: Receiver<DocumentsResponse>,
+) -> io::Result<Option<BrowseAction>> {
+    let _guard = TerminalGuard;
+    crossterm::terminal::enable_raw_mode()?;
+    execute!(std::io::stdout(), EnterAlternateScreen)?;
+    let mut terminal = Terminal::new(CrosstermBackend::new(std::io::stdout()))?;
+
+    let (event_tx, mut event_rx) = tokio::sync::mpsc::channel::<Event>(100);
+    // The reader has to stop before a session started from the browser reads the terminal
+    let stop_reader = Arc::new(AtomicBool::new(false));
+    let stop_reader_clone = stop_reader.clone();
+    std::thread::spawn(move || {
+        while !stop_reader_clone.load(Ordering::SeqCst) {
+            if !crossterm::event::poll(READER_POLL_INTERVAL).unwrap_or(false) {
+                continue;
+            }
+            if let Ok(event) = crossterm::event::read() {
+                if event_tx.blocking_send(event).is_err() {
+                    break;
+                }
+            }
+        }
+    });
+
+    let mut state = BrowserState::new(flows);
+    let action = loop {
+        terminal.draw(|f| draw(f, &state))?;
+        tokio::select! {
+            event = event_rx.recv() => {
+                let Some(event) = event else {
+                    break None;
+                };
+                if let Event::Key(key) = event {
+                    match state.handle_key(key) {
+                        KeyOutcome::Continue => {}
+                        KeyOutcome::FetchDocuments(flow_ref) => {
+                            if request_tx.send(flow_ref).await.is_err() {
+                                break None;
+                            }
+                        }
+                        KeyOutcome::Exit(action) => break action,
+                    }
+                }
+            }
+            Some(response) = documents_rx.recv() => state.handle_documents(response),
+        }
+    };
+
+    stop_reader.store(true, Ordering::SeqCst);
+    Ok(action)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn press(state: &mut BrowserState, code: KeyCode) -> KeyOutcome {
+        state.handle_key(KeyEvent::new(code, KeyModifiers::NONE))
+    }
+
+    fn flow(name: &str, versions: &[&str]) -> BrowseFlow {
+        BrowseFlow {
+            name: name.to_string(),
+            visibility: "PRIVATE".to_string(),
+            updated_at: "2026-01-01".to_string(),
+            versions: versions
+                .iter()
+                .map(|id| BrowseVersion {
+                    flow_ref: format!("me/{}/{}", name, id),
+                    id: id.to_string(),
+                    created_at: "2026-01-01".to_string(),
+                    tags: Vec::new(),
+                    immutable: false,
+                })
+                .collect(),
+        }
+    }
+
+    #[test]
+    fn test_browser_navigation() {
+        let mut state = BrowserState::new(vec![
+            flow("network", &["v2", "v1"]),
+            flow("cluster", &["v3"]),
+        ]);
+
+        press(&mut state, KeyCode::Char('/'));
+        for c in "clus".chars() {
+            press(&mut state, KeyCode::Char(c));
+        }
+        press(&mut state, KeyCode::Enter);
+        assert_eq!(state.selected_flow_ref(), Some("me/cluster/v3".to_string()));
+
+        press(&mut state, KeyCode::Enter);
+        assert_eq!(state.screen, Screen::Versions);
+        assert!(matches!(
+            press(&mut state, KeyCode::Enter),
+            KeyOutcome::FetchDocuments(flow_ref) if flow_ref == "me/cluster/v3"
+        ));
+        state.handle_documents((
+            "me/cluster/v3".to_string(),
+            Ok(vec![BrowseDocument {
+                path: "main.tf".to_string(),
+                provisioner: "Terraform".to_string(),
+                content: "resource \"a\" \"b\" {}".to_string(),
+            }]),
+        ));
+        assert!(!state.loading);
+        assert_eq!(state.visible_items().len(), 1);
+
+        press(&mut state, KeyCode::Esc);
+        press(&mut state, KeyCode::Esc);
+        assert_eq!(state.screen, Screen::Flows);
+        press(&mut state, KeyCode::Up);
+        assert!(matches!(
+            press(&mut state, KeyCode::Char('s')),
+            KeyOutcome::Exit(Some(BrowseAction::StartSession(flow_ref))) if flow_ref == "me/network/v2"
+        ));
+    }
+}
@@ -12,6 +12,12 @@ pub fn map_crossterm_event_to_input_event(event: Event) -> Option<InputEvent> {
             KeyCode::Char('j') if modifiers.contains(KeyModifiers::CONTROL) => {
                 Some(InputEvent::InputChangedNewline)
             }
+            KeyCode::Char('o') if modifiers.contains(KeyModifiers::CONTROL) => {
+                Some(InputEvent::OpenPager)
+            }
+            KeyCode::Char('k') if modifiers.contains(KeyModifiers::CONTROL) => {
+                Some(InputEvent::OpenCommandPalette)
+            }
             KeyCode::Char(c) => Some(InputEvent::InputChanged(c)),
             KeyCode::Backspace => Some(InputEvent::InputBackspace),
             KeyCode::Enter => Some(InputEvent::InputSubmitted),
@@ -23,6 +29,7 @@ pub fn map_crossterm_event_to_input_event(event: Event) -> Option<InputEvent> {
             KeyCode::PageUp => Some(InputEvent::PageUp),
             KeyCode::PageDown => Some(InputEvent::PageDown),
             KeyCode::Tab => Some(InputEvent::Tab),
+            KeyCode::BackTab => Some(InputEvent::BackTab),
             _ => None,
         },
         Event::Mouse(me) => match me.kind {
@@ -32,6 +39,7 @@ pub fn map_crossterm_event_to_input_event(event: Event) -> Option<InputEvent> {
         },
         Event::Resize(w, h) => Some(InputEvent::Resized(w, h)),
         Event::Paste(p) => Some(InputEvent::HandlePaste(p)),
-        _ => None,
+        Event::FocusGained => Some(InputEvent::FocusChanged(true)),
+        Event::FocusLost => Some(InputEvent::FocusChanged(false)),
     }
 }
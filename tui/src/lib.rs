@@ -1,38 +1,86 @@
 mod app;
+mod browser;
 mod event;
 mod terminal;
 mod view;
-pub use app::{AppState, InputEvent, OutputEvent, SessionInfo};
+pub use app::{
+    AppState, Citation, ConfirmationTimeout, InputEvent, OutputEvent, SessionInfo,
+    ShellCommandResult, StreamMetrics, TimeoutAction,
+};
+
+pub use browser::{
+    BrowseAction, BrowseDocument, BrowseFlow, BrowseVersion, DocumentsResponse, run_browser,
+};
 
 mod services;
+pub use services::question_form::QuestionAnswer;
 
-use crossterm::{execute, terminal::EnterAlternateScreen};
+use crossterm::{
+    event::{DisableFocusChange, EnableFocusChange},
+    execute,
+    terminal::EnterAlternateScreen,
+};
 pub use event::map_crossterm_event_to_input_event;
 use ratatui::{Terminal, backend::CrosstermBackend};
 use std::io;
+use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, Ordering};
 pub use terminal::TerminalGuard;
 use tokio::sync::mpsc::{Receiver, Sender};
-use tokio::time::{Duration, interval};
+use tokio::time::{Duration, Instant, interval, sleep_until};
 pub use view::view;
 
+const READER_POLL_INTERVAL: Duration = Duration::from_millis(100);
+/// Changes arriving faster than this, e.g. streamed tokens, are drawn together
+const FRAME_INTERVAL: Duration = Duration::from_millis(16);
+const SPINNER_INTERVAL: Duration = Duration::from_millis(100);
+/// Nobody watches a spinner in a background terminal closely
+const UNFOCUSED_SPINNER_INTERVAL: Duration = Duration::from_millis(500);
+
 pub async fn run_tui(
     mut input_rx: Receiver<InputEvent>,
     output_tx: Sender<OutputEvent>,
     shutdown_tx: tokio::sync::broadcast::Sender<()>,
     latest_version: Option<String>,
+    confirmation_timeout: Option<ConfirmationTimeout>,
 ) -> io::Result<()> {
     let _guard = TerminalGuard;
     crossterm::terminal::enable_raw_mode()?;
-    execute!(std::io::stdout(), EnterAlternateScreen)?;
+    execute!(std::io::stdout(), EnterAlternateScreen, EnableFocusChange)?;
     let mut terminal = Terminal::new(CrosstermBackend::new(std::io::stdout()))?;
 
-    let all_helpers = vec!["/help", "/status", "/sessions", "/quit"];
+    let all_helpers = vec![
+        "/help",
+        "/status",
+        "/usage",
+        "/sessions",
+        "/checkpoint",
+        "/filehistory",
+        "/blame",
+        "/env",
+        "/compact",
+        "/pin",
+        "/pins",
+        "/unpin",
+        "/quit",
+    ];
     let mut state = AppState::new(all_helpers.clone(), latest_version);
+    state.confirmation_timeout = confirmation_timeout;
 
     // Internal channel for event handling
     let (internal_tx, mut internal_rx) = tokio::sync::mpsc::channel::<InputEvent>(100);
+    // Stop reading terminal events while a pager owns the terminal
+    let reader_paused = Arc::new(AtomicBool::new(false));
+    let reader_paused_clone = reader_paused.clone();
     std::thread::spawn(move || {
         loop {
+            if reader_paused_clone.load(Ordering::SeqCst) {
+                std::thread::sleep(READER_POLL_INTERVAL);
+                continue;
+            }
+            if !crossterm::event::poll(READER_POLL_INTERVAL).unwrap_or(false) {
+                continue;
+            }
             if let Ok(event) = crossterm::event::read() {
                 if let Some(event) = crate::event::map_crossterm_event_to_input_event(event) {
                     if internal_tx.blocking_send(event).is_err() {
@@ -43,25 +91,26 @@ pub async fn run_tui(
         }
     });
 
-    let mut spinner_interval = interval(Duration::from_millis(100));
-    // get terminal width
-    let terminal_size = terminal.size()?;
+    let mut spinner_interval = interval(SPINNER_INTERVAL);
+    let mut countdown_interval = interval(Duration::from_secs(1));
     // Main async update/view loop
     terminal.draw(|f| view::view(f, &state))?;
+    let mut last_draw = Instant::now();
+    let mut needs_redraw = false;
     let mut should_quit = false;
     loop {
         tokio::select! {
             Some(event) = input_rx.recv() => {
-                if let InputEvent::RunToolCall(tool_call) = &event {
-                    services::update::update(&mut state, InputEvent::ShowConfirmationDialog(tool_call.clone()), 10, 40, &output_tx, terminal_size);
-                    terminal.draw(|f| view::view(f, &state))?;
-                    continue;
-                }
+                let event = match event {
+                    InputEvent::RunToolCall(tool_call) => InputEvent::ShowConfirmationDialog(tool_call),
+                    event => event,
+                };
                 if let InputEvent::ToolResult(ref tool_call_result) = event {
                     let tool_call = tool_call_result.call.clone();
                     let result = tool_call_result.result.clone();
                     services::update::clear_streaming_tool_results(&mut state);
-                    services::bash_block::render_result_block(&tool_call, &result, &mut state, terminal_size);
+                    services::bash_block::render_result_block(&tool_call, &result, &mut state);
+                    services::pager::remember_saved_output(&mut state, &result);
                 }
                 if let InputEvent::Quit = event { should_quit = true; }
                 else {
@@ -89,11 +138,34 @@ pub async fn run_tui(
                         .split(term_rect);
                     let message_area_width = outer_chunks[0].width as usize;
                     let message_area_height = outer_chunks[0].height as usize;
-                    services::update::update(&mut state, event, message_area_height, message_area_width, &output_tx, terminal_size);
+                    services::update::update(&mut state, event, message_area_height, message_area_width, &output_tx);
                 }
             }
             Some(event) = internal_rx.recv() => {
                 if let InputEvent::Quit = event { should_quit = true; }
+                else if let InputEvent::FocusChanged(focused) = event {
+                    spinner_interval = interval(if focused { SPINNER_INTERVAL } else { UNFOCUSED_SPINNER_INTERVAL });
+                }
+                else if let InputEvent::OpenPager = event {
+                    match state.saved_output_path.clone() {
+                        Some(path) => {
+                            reader_paused.store(true, Ordering::SeqCst);
+                            // Let the reader finish its current poll so it can't steal pager input
+                            tokio::time::sleep(READER_POLL_INTERVAL).await;
+                            crossterm::terminal::disable_raw_mode()?;
+                            execute!(std::io::stdout(), crossterm::terminal::LeaveAlternateScreen)?;
+                            let result = services::pager::open_in_pager(&path).await;
+                            execute!(std::io::stdout(), EnterAlternateScreen)?;
+                            crossterm::terminal::enable_raw_mode()?;
+                            reader_paused.store(false, Ordering::SeqCst);
+                            terminal.clear()?;
+                            if let Err(e) = result {
+                                services::helper_block::push_error_message(&mut state, &format!("Failed to open pager: {}", e));
+                            }
+                        }
+                        None => services::helper_block::push_error_message(&mut state, "No truncated output to open"),
+                    }
+                }
                 else {
                     let term_size = terminal.size()?;
                     let term_rect = ratatui::layout::Rect::new(0, 0, term_size.width, term_size.height);
@@ -121,27 +193,40 @@ pub async fn run_tui(
                     let message_area_height = outer_chunks[0].height as usize;
                     if let InputEvent::InputSubmitted = event {
                         // if input starts with / don't submit output event
-                        if !state.input.trim().is_empty() && !state.input.trim().starts_with('/') {
+                        if state.question_form.is_none() && state.command_palette.is_none() && !state.input.trim().is_empty() && !state.input.trim().starts_with('/') && !state.input.trim().starts_with('!') {
                             let _ = output_tx.try_send(OutputEvent::UserMessage(state.input.clone()));
                         }
                     }
-                    services::update::update(&mut state, event, message_area_height, message_area_width, &output_tx, terminal_size);
+                    services::update::update(&mut state, event, message_area_height, message_area_width, &output_tx);
                 }
             }
             _ = spinner_interval.tick(), if state.loading => {
                 state.spinner_frame = state.spinner_frame.wrapping_add(1);
-                terminal.draw(|f| view::view(f, &state))?;
             }
+            _ = countdown_interval.tick(), if state.dialog_deadline.is_some() => {
+                services::confirmation_dialog::apply_confirmation_timeout(&mut state, &output_tx);
+            }
+            // Draws the changes held back by the frame limit below
+            _ = sleep_until(last_draw + FRAME_INTERVAL), if needs_redraw => {}
         }
         if should_quit {
             break;
         }
-        terminal.draw(|f| view::view(f, &state))?;
+        needs_redraw = true;
+        if last_draw.elapsed() >= FRAME_INTERVAL {
+            terminal.draw(|f| view::view(f, &state))?;
+            last_draw = Instant::now();
+            needs_redraw = false;
+        }
     }
 
     println!("Quitting...");
     let _ = shutdown_tx.send(());
     crossterm::terminal::disable_raw_mode()?;
-    execute!(std::io::stdout(), crossterm::terminal::LeaveAlternateScreen)?;
+    execute!(
+        std::io::stdout(),
+        DisableFocusChange,
+        crossterm::terminal::LeaveAlternateScreen
+    )?;
     Ok(())
 }
@@ -1,11 +1,10 @@
 use crate::app::AppState;
 use crate::services::message::{
     BubbleColors, Message, MessageContent, extract_command_purpose, get_command_type_name,
-    wrap_text,
 };
-use ratatui::layout::Size;
 use ratatui::style::{Color, Modifier, Style};
 use ratatui::text::{Line, Span};
+use stakpak_shared::command_result::{CommandMetadata, strip_command_metadata};
 use stakpak_shared::models::integrations::openai::ToolCall;
 use uuid::Uuid;
 
@@ -54,6 +53,12 @@ pub fn extract_bash_block_info(
             content_color: Color::LightRed,
             tool_type: "delete_file".to_string(),
         },
+        "move_file" => BubbleColors {
+            border_color: Color::Blue,
+            title_color: Color::White,
+            content_color: Color::LightBlue,
+            tool_type: "move_file".to_string(),
+        },
         _ => BubbleColors {
             border_color: Color::Cyan,
             title_color: Color::White,
@@ -64,57 +69,15 @@ pub fn extract_bash_block_info(
     (command, outside_title, bubble_title, colors)
 }
 
-#[allow(clippy::too_many_arguments)]
 pub fn render_styled_block(
     content: &str,
     outside_title: &str,
     bubble_title: &str,
     colors: Option<BubbleColors>,
     state: &mut AppState,
-    terminal_size: Size,
     tool_type: &str,
     message_id: Option<Uuid>,
 ) -> Uuid {
-    let terminal_width = terminal_size.width as usize;
-    let content_width = if terminal_width > 4 {
-        terminal_width - 4
-    } else {
-        40
-    };
-    let content_lines = content.split('\n').collect::<Vec<_>>();
-    let inner_width = content_width;
-    let horizontal_line = "─".repeat(inner_width + 2);
-    let bottom_border = format!("╰{}╯", horizontal_line);
-    let title_border = {
-        let title_width = bubble_title.chars().count();
-        if title_width <= inner_width {
-            let remaining_dashes = inner_width + 2 - title_width;
-            format!("╭{}{}", bubble_title, "─".repeat(remaining_dashes)) + "╮"
-        } else {
-            let truncated_title = bubble_title.chars().take(inner_width).collect::<String>();
-            format!("╭{}─╮", truncated_title)
-        }
-    };
-    let mut bubble_lines = vec![];
-    bubble_lines.push(title_border);
-    for line in &content_lines {
-        let trimmed_line = line.trim_end();
-        if trimmed_line.is_empty() {
-            let padding = " ".repeat(inner_width);
-            bubble_lines.push(format!("│ {} │", padding));
-            continue;
-        }
-        let wrapped_lines = wrap_text(trimmed_line, inner_width);
-        for wrapped_line in wrapped_lines {
-            let line_char_count = wrapped_line.chars().count();
-            let padding_needed = inner_width - line_char_count;
-            let padding = " ".repeat(padding_needed);
-            let formatted_line = format!("│ {}{} │", wrapped_line, padding);
-            bubble_lines.push(formatted_line);
-        }
-    }
-    bubble_lines.push(bottom_border);
-
     let default_colors = BubbleColors {
         border_color: Color::Cyan,
         title_color: Color::White,
@@ -127,8 +90,9 @@ pub fn render_styled_block(
         id: message_id,
         content: MessageContent::BashBubble {
             title: outside_title.to_string(),
-            content: bubble_lines,
-            colors: colors.clone().unwrap_or(default_colors),
+            bubble_title: bubble_title.to_string(),
+            content: content.to_string(),
+            colors: colors.unwrap_or(default_colors),
             tool_type: tool_type.to_string(),
         },
     });
@@ -140,7 +104,6 @@ pub fn render_bash_block(
     output: &str,
     _accepted: bool,
     state: &mut AppState,
-    terminal_size: Size,
 ) -> Uuid {
     let (command, outside_title, bubble_title, colors) = extract_bash_block_info(tool_call, output);
     render_styled_block(
@@ -149,25 +112,20 @@ pub fn render_bash_block(
         &bubble_title,
         Some(colors.clone()),
         state,
-        terminal_size,
         &tool_call.function.name,
         None,
     )
 }
 
-pub fn render_result_block(
-    tool_call: &ToolCall,
-    result: &str,
-    state: &mut AppState,
-    terminal_size: Size,
-) {
+pub fn render_result_block(tool_call: &ToolCall, result: &str, state: &mut AppState) {
+    let metadata = CommandMetadata::parse(result);
+    let result = strip_command_metadata(result);
+    let result = match tool_call.function.name.as_str() {
+        "search_files" => compact_search_results(&result).unwrap_or(result),
+        _ => result,
+    };
     let mut lines = Vec::new();
 
-    // Get terminal width for wrapping calculation
-    let terminal_width: usize = terminal_size.width as usize;
-    let prefix_width = 6; // "    └ " or "      "
-    let available_width = terminal_width.saturating_sub(prefix_width);
-
     // Header line with approved colors (green bullet, white text)
     lines.push(Line::from(vec![
         Span::styled(
@@ -188,26 +146,21 @@ pub fn render_result_block(
         ),
     ]));
 
-    // Show the command output with proper wrapping
+    // Output lines are wrapped for the current width when drawn
     let output_pad = "    "; // 4 spaces for indentation
     for (i, line) in result.lines().enumerate() {
         let prefix = if i == 0 { "└ " } else { "  " };
+        lines.push(Line::from(vec![
+            Span::styled(
+                format!("{output_pad}{prefix}"),
+                Style::default().fg(Color::Gray),
+            ),
+            Span::styled(line.to_string(), Style::default().fg(Color::Gray)),
+        ]));
+    }
 
-        // Wrap long lines
-        let wrapped_lines = wrap_text(line, available_width);
-
-        for (j, wrapped_line) in wrapped_lines.iter().enumerate() {
-            let line_prefix = if j == 0 {
-                format!("{output_pad}{prefix}")
-            } else {
-                format!("{output_pad}  ") // Continue indent for wrapped lines
-            };
-
-            lines.push(Line::from(vec![
-                Span::styled(line_prefix, Style::default().fg(Color::Gray)),
-                Span::styled(wrapped_line.clone(), Style::default().fg(Color::Gray)),
-            ]));
-        }
+    if let Some(metadata) = metadata {
+        lines.push(command_metadata_line(&metadata));
     }
 
     let mut owned_lines: Vec<Line<'static>> = lines
@@ -229,6 +182,109 @@ pub fn render_result_block(
     });
 }
 
+/// `search_files` JSON as one `path:line: text` line per match
+fn compact_search_results(result: &str) -> Option<String> {
+    const MAX_SHOWN_MATCHES: usize = 20;
+    let results = serde_json::from_str::<serde_json::Value>(result).ok()?;
+    let matches = results.get("matches")?.as_array()?;
+    let count = |key: &str| results.get(key).and_then(|v| v.as_u64()).unwrap_or(0);
+
+    let mut lines = vec![format!(
+        "{} matches in {} of {} files{}",
+        matches.len(),
+        count("files_matched"),
+        count("files_searched"),
+        if results.get("truncated").and_then(|v| v.as_bool()) == Some(true) {
+            " (cut at max_results)"
+        } else {
+            ""
+        }
+    )];
+    for search_match in matches.iter().take(MAX_SHOWN_MATCHES) {
+        let field = |key: &str| search_match.get(key).cloned().unwrap_or_default();
+        lines.push(format!(
+            "{}:{}: {}",
+            field("path").as_str().unwrap_or_default(),
+            field("line"),
+            field("text").as_str().unwrap_or_default().trim()
+        ));
+    }
+    if matches.len() > MAX_SHOWN_MATCHES {
+        lines.push(format!("... {} more", matches.len() - MAX_SHOWN_MATCHES));
+    }
+    Some(lines.join("\n"))
+}
+
+/// Exit status, duration and shell of a command, under its output
+fn command_metadata_line(metadata: &CommandMetadata) -> Line<'static> {
+    let status = match metadata.exit_code {
+        Some(code) => format!("exit {}", code),
+        None if metadata.timed_out => "timed out".to_string(),
+        None => "killed".to_string(),
+    };
+    let mut details = vec![
+        format!("{:.1}s", metadata.duration_ms as f64 / 1000.0),
+        metadata.shell.clone(),
+    ];
+    if metadata.cached {
+        details.push("cached".to_string());
+    }
+    if metadata.truncated {
+        details.push("truncated".to_string());
+    }
+    Line::from(vec![
+        Span::raw("      "),
+        Span::styled(
+            status,
+            Style::default().fg(if metadata.failed() {
+                Color::LightRed
+            } else {
+                Color::DarkGray
+            }),
+        ),
+        Span::styled(
+            format!(" · {}", details.join(" · ")),
+            Style::default().fg(Color::DarkGray),
+        ),
+    ])
+}
+
+pub fn render_triage_block(triage: &str, state: &mut AppState) {
+    let mut lines = Vec::new();
+
+    lines.push(Line::from(vec![
+        Span::styled(
+            "● ",
+            Style::default()
+                .fg(Color::LightMagenta)
+                .add_modifier(Modifier::BOLD),
+        ),
+        Span::styled(
+            "Triage",
+            Style::default()
+                .fg(Color::White)
+                .add_modifier(Modifier::BOLD),
+        ),
+        Span::styled(
+            " (likely causes and fixes)",
+            Style::default().fg(Color::Gray),
+        ),
+    ]));
+
+    for line in triage.lines() {
+        lines.push(Line::from(vec![
+            Span::styled("  │ ", Style::default().fg(Color::Magenta)),
+            Span::styled(line.to_string(), Style::default().fg(Color::LightMagenta)),
+        ]));
+    }
+    lines.push(Line::from(""));
+
+    state.messages.push(Message {
+        id: Uuid::new_v4(),
+        content: MessageContent::StyledBlock(lines),
+    });
+}
+
 // Function to render a rejected bash command (when user selects "No")
 pub fn render_bash_block_rejected(command_name: &str, state: &mut AppState) {
     let mut lines = Vec::new();
@@ -0,0 +1,123 @@
+use crate::app::{AppState, Citation};
+use crate::services::message::{Message, MessageContent};
+use ratatui::buffer::Buffer;
+use ratatui::layout::Rect;
+use ratatui::style::{Color, Modifier, Style};
+use ratatui::text::{Line, Span};
+use uuid::Uuid;
+
+/// List the files an answer cited, flagging the spans that could not be verified, and
+/// remember the verified ones to hyperlink them
+pub fn push_citations(state: &mut AppState, citations: Vec<Citation>) {
+    if citations.is_empty() {
+        return;
+    }
+    let mut lines = vec![Line::from(Span::styled(
+        "Sources",
+        Style::default()
+            .fg(Color::Cyan)
+            .add_modifier(Modifier::BOLD),
+    ))];
+    for citation in citations {
+        match (&citation.url, &citation.problem) {
+            (Some(url), None) => {
+                lines.push(Line::from(vec![
+                    Span::styled("  ✓ ", Style::default().fg(Color::Green)),
+                    Span::styled(
+                        citation.label.clone(),
+                        Style::default().add_modifier(Modifier::UNDERLINED),
+                    ),
+                ]));
+                if !state
+                    .citation_links
+                    .iter()
+                    .any(|(label, _)| *label == citation.label)
+                {
+                    state.citation_links.push((citation.label, url.clone()));
+                }
+            }
+            (_, problem) => lines.push(Line::from(vec![
+                Span::styled("  ✗ ", Style::default().fg(Color::Red)),
+                Span::raw(citation.label),
+                Span::styled(
+                    format!(
+                        " unverified: {}",
+                        problem.as_deref().unwrap_or("file not found")
+                    ),
+                    Style::default().fg(Color::DarkGray),
+                ),
+            ])),
+        }
+    }
+    lines.push(Line::from(""));
+    state.messages.push(Message {
+        id: Uuid::new_v4(),
+        content: MessageContent::StyledBlock(lines),
+    });
+}
+
+/// Wrap every shown occurrence of a verified citation in an OSC 8 hyperlink. The escape
+/// sequence is written into the cell symbols two characters at a time, skipping the
+/// second cell, so that ratatui's width accounting of the sequence does not shift the row.
+pub fn hyperlink_citations(buf: &mut Buffer, area: Rect, links: &[(String, String)]) {
+    for (label, url) in links {
+        // Cells hold one character each, only ASCII labels map onto them one to one
+        if label.is_empty() || !label.is_ascii() {
+            continue;
+        }
+        let label = label.as_bytes();
+        for y in area.top()..area.bottom() {
+            let mut x = area.left();
+            while x + label.len() as u16 <= area.right() {
+                if !matches_at(buf, x, y, label) {
+                    x += 1;
+                    continue;
+                }
+                for chunk_start in (0..label.len()).step_by(2) {
+                    let chunk_end = (chunk_start + 2).min(label.len());
+                    let chunk = String::from_utf8_lossy(&label[chunk_start..chunk_end]);
+                    let cell_x = x + chunk_start as u16;
+                    buf[(cell_x, y)]
+                        .set_symbol(&format!("\x1b]8;;{}\x1b\\{}\x1b]8;;\x1b\\", url, chunk));
+                    if chunk_end - chunk_start == 2 {
+                        buf[(cell_x + 1, y)].set_skip(true);
+                    }
+                }
+                x += label.len() as u16;
+            }
+        }
+    }
+}
+
+fn matches_at(buf: &Buffer, x: u16, y: u16, label: &[u8]) -> bool {
+    label.iter().enumerate().all(|(i, byte)| {
+        let cell = &buf[(x + i as u16, y)];
+        !cell.skip && cell.symbol().as_bytes() == [*byte]
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_hyperlink_citations() {
+        let area = Rect::new(0, 0, 20, 1);
+        let mut buf = Buffer::empty(area);
+        buf.set_string(0, 0, "see main.tf:3 ok", Style::default());
+        let links = vec![("main.tf:3".to_string(), "file:///src/main.tf".to_string())];
+        hyperlink_citations(&mut buf, area, &links);
+
+        assert_eq!(buf[(3, 0)].symbol(), " ");
+        assert_eq!(
+            buf[(4, 0)].symbol(),
+            "\x1b]8;;file:///src/main.tf\x1b\\ma\x1b]8;;\x1b\\"
+        );
+        assert!(buf[(5, 0)].skip);
+        assert_eq!(
+            buf[(12, 0)].symbol(),
+            "\x1b]8;;file:///src/main.tf\x1b\\3\x1b]8;;\x1b\\"
+        );
+        assert!(!buf[(13, 0)].skip);
+    }
+}
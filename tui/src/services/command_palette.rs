@@ -0,0 +1,298 @@
+use crate::app::AppState;
+use ratatui::{
+    Frame,
+    layout::Rect,
+    style::{Color, Modifier, Style},
+    text::{Line, Span},
+    widgets::{Block, Borders, Clear, List, ListItem, ListState, Paragraph},
+};
+use stakpak_shared::file_history::recent_files;
+
+const MAX_VISIBLE_ITEMS: usize = 10;
+const MAX_RECENT_FILES: usize = 20;
+
+/// What running a palette entry does
+#[derive(Debug, Clone, PartialEq)]
+pub enum PaletteAction {
+    /// Run a slash command as if it was picked from the helper dropdown
+    Command(&'static str),
+    OpenFileHistory(String),
+    SwitchSession {
+        id: String,
+        title: String,
+    },
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct PaletteItem {
+    /// Group shown next to the entry: command, file or session
+    pub kind: &'static str,
+    pub label: String,
+    pub detail: String,
+    pub action: PaletteAction,
+}
+
+/// Ctrl+K palette searching slash commands, recently edited files and sessions
+pub struct CommandPalette {
+    pub query: String,
+    pub items: Vec<PaletteItem>,
+    /// Indexes into `items` matching the query, best match first
+    pub filtered: Vec<usize>,
+    pub selected: usize,
+}
+
+impl CommandPalette {
+    pub fn new(items: Vec<PaletteItem>) -> Self {
+        let mut palette = Self {
+            query: String::new(),
+            items,
+            filtered: Vec::new(),
+            selected: 0,
+        };
+        palette.refilter();
+        palette
+    }
+
+    fn refilter(&mut self) {
+        let mut matches: Vec<(i64, usize)> = self
+            .items
+            .iter()
+            .enumerate()
+            .filter_map(|(i, item)| fuzzy_score(&self.query, &item.label).map(|score| (score, i)))
+            .collect();
+        // Stable, so equally good matches keep the commands, files, sessions order
+        matches.sort_by(|a, b| b.0.cmp(&a.0));
+        self.filtered = matches.into_iter().map(|(_, i)| i).collect();
+        self.selected = 0;
+    }
+
+    pub fn insert_str(&mut self, text: &str) {
+        self.query.push_str(text);
+        self.refilter();
+    }
+
+    pub fn backspace(&mut self) {
+        self.query.pop();
+        self.refilter();
+    }
+
+    pub fn next(&mut self) {
+        if self.selected + 1 < self.filtered.len() {
+            self.selected += 1;
+        }
+    }
+
+    pub fn previous(&mut self) {
+        self.selected = self.selected.saturating_sub(1);
+    }
+
+    pub fn selected_action(&self) -> Option<PaletteAction> {
+        self.filtered
+            .get(self.selected)
+            .and_then(|&i| self.items.get(i))
+            .map(|item| item.action.clone())
+    }
+}
+
+/// Score `candidate` against `query` when every query character appears in it in order.
+/// Consecutive characters and matches at the start of a word score higher.
+pub fn fuzzy_score(query: &str, candidate: &str) -> Option<i64> {
+    let candidate: Vec<char> = candidate.to_lowercase().chars().collect();
+    let mut score = 0;
+    let mut position = 0;
+    let mut previous_match: Option<usize> = None;
+
+    for query_char in query.to_lowercase().chars().filter(|c| !c.is_whitespace()) {
+        let offset = candidate[position..]
+            .iter()
+            .position(|&c| c == query_char)?;
+        let index = position + offset;
+
+        score += 1;
+        if previous_match.is_some_and(|previous| previous + 1 == index) {
+            score += 5;
+        }
+        if index == 0 || matches!(candidate[index - 1], ' ' | '/' | '_' | '-' | '.') {
+            score += 3;
+        }
+        score -= offset as i64;
+
+        previous_match = Some(index);
+        position = index + 1;
+    }
+    Some(score)
+}
+
+fn command_description(command: &str) -> &'static str {
+    match command {
+        "/help" => "Show usage and shortcuts",
+        "/status" => "Show account and session status",
+        "/usage" => "Show token usage and estimated cost of the session",
+        "/sessions" => "Browse and switch sessions",
+        "/checkpoint" => "Label the current checkpoint",
+        "/filehistory" => "Step through agent edits of a file",
+        "/blame" => "Show which agent step changed each hunk of a file",
+        "/env" => "View and set session environment variables",
+        "/compact" => "Summarize older turns to free up context",
+        "/pin" => "Pin your latest message so compaction never summarizes it",
+        "/pins" => "List pinned messages",
+        "/unpin" => "Unpin a message",
+        "/quit" => "Quit Stakpak",
+        _ => "",
+    }
+}
+
+/// Open the palette with everything that can currently be run from it
+pub fn open_command_palette(state: &mut AppState) {
+    let commands = state.helpers.iter().map(|&command| PaletteItem {
+        kind: "command",
+        label: command.to_string(),
+        detail: command_description(command).to_string(),
+        action: PaletteAction::Command(command),
+    });
+
+    let cwd = std::env::current_dir().unwrap_or_default();
+    let files = recent_files(MAX_RECENT_FILES).into_iter().map(|path| {
+        let label = std::path::Path::new(&path)
+            .strip_prefix(&cwd)
+            .map(|relative| relative.to_string_lossy().to_string())
+            .unwrap_or_else(|_| path.clone());
+        PaletteItem {
+            kind: "file",
+            label,
+            detail: "Show edit history".to_string(),
+            action: PaletteAction::OpenFileHistory(path),
+        }
+    });
+
+    // Sessions are only known once they have been listed with /sessions
+    let sessions = state.sessions.iter().map(|session| PaletteItem {
+        kind: "session",
+        label: session.title.clone(),
+        detail: session.updated_at.clone(),
+        action: PaletteAction::SwitchSession {
+            id: session.id.clone(),
+            title: session.title.clone(),
+        },
+    });
+
+    state.command_palette = Some(CommandPalette::new(
+        commands.chain(files).chain(sessions).collect(),
+    ));
+}
+
+pub fn render_command_palette(f: &mut Frame, state: &AppState) {
+    let Some(palette) = &state.command_palette else {
+        return;
+    };
+    let screen = f.area();
+    let visible = palette.filtered.len().clamp(1, MAX_VISIBLE_ITEMS) as u16;
+    let width = screen.width.saturating_sub(4).min(80);
+    let height = (visible + 4).min(screen.height);
+    let area = Rect {
+        x: (screen.width.saturating_sub(width)) / 2,
+        y: screen.height.saturating_sub(height) / 3,
+        width,
+        height,
+    };
+    f.render_widget(Clear, area);
+
+    let block = Block::default()
+        .borders(Borders::ALL)
+        .border_style(Style::default().fg(Color::LightYellow))
+        .title(Span::styled(
+            "Command palette",
+            Style::default()
+                .fg(Color::Yellow)
+                .add_modifier(Modifier::BOLD),
+        ));
+    f.render_widget(block, area);
+
+    let inner_width = area.width.saturating_sub(4);
+    let query_area = Rect {
+        x: area.x + 2,
+        y: area.y + 1,
+        width: inner_width,
+        height: 1,
+    };
+    let query = Paragraph::new(Line::from(vec![
+        Span::styled("> ", Style::default().fg(Color::DarkGray)),
+        Span::raw(palette.query.as_str()),
+        Span::styled(" ", Style::default().bg(Color::Cyan)),
+    ]));
+    f.render_widget(query, query_area);
+
+    let list_area = Rect {
+        x: area.x + 2,
+        y: area.y + 2,
+        width: inner_width,
+        height: visible,
+    };
+    if palette.filtered.is_empty() {
+        f.render_widget(
+            Paragraph::new("No matches").style(Style::default().fg(Color::DarkGray)),
+            list_area,
+        );
+    } else {
+        let items: Vec<ListItem> = palette
+            .filtered
+            .iter()
+            .filter_map(|&i| palette.items.get(i))
+            .map(|item| {
+                ListItem::new(Line::from(vec![
+                    Span::styled(
+                        format!("{:<8} ", item.kind),
+                        Style::default().fg(Color::DarkGray),
+                    ),
+                    Span::raw(item.label.clone()),
+                    Span::styled(
+                        format!("  {}", item.detail),
+                        Style::default().fg(Color::DarkGray),
+                    ),
+                ]))
+            })
+            .collect();
+        let mut list_state = ListState::default();
+        list_state.select(Some(palette.selected));
+        let list = List::new(items).highlight_style(
+            Style::default()
+                .fg(Color::White)
+                .bg(Color::Cyan)
+                .add_modifier(Modifier::BOLD),
+        );
+        f.render_stateful_widget(list, list_area, &mut list_state);
+    }
+
+    let help_area = Rect {
+        x: area.x + 2,
+        y: area.y + area.height.saturating_sub(2),
+        width: inner_width,
+        height: 1,
+    };
+    f.render_widget(
+        Paragraph::new("↑/↓ to select · enter to run · esc to close")
+            .style(Style::default().fg(Color::DarkGray)),
+        help_area,
+    );
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_fuzzy_score() {
+        assert!(fuzzy_score("fh", "/filehistory").is_some());
+        assert!(fuzzy_score("hf", "/filehistory").is_none());
+        assert_eq!(fuzzy_score("", "/help"), Some(0));
+
+        // Word starts and consecutive characters rank first
+        let items = ["modules/vpc/main.tf", "/sessions", "/status"];
+        let mut ranked: Vec<_> = items
+            .iter()
+            .filter_map(|item| fuzzy_score("sta", item).map(|score| (score, *item)))
+            .collect();
+        ranked.sort_by(|a, b| b.0.cmp(&a.0));
+        assert_eq!(ranked.first().map(|(_, item)| *item), Some("/status"));
+    }
+}
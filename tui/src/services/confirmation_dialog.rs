@@ -1,5 +1,7 @@
-use crate::app::AppState;
-use crate::services::message::get_wrapped_message_lines;
+use crate::app::{AppState, OutputEvent, TimeoutAction};
+use crate::services::bash_block::render_bash_block_rejected;
+use crate::services::helper_block::render_system_message;
+use crate::services::message::{extract_truncated_command_arguments, wrapped_line_count};
 use ratatui::{
     Frame,
     layout::Alignment,
@@ -7,14 +9,156 @@ use ratatui::{
     text::{Line, Span},
     widgets::{Block, Borders, Paragraph},
 };
+use serde_json::Value;
+use stakpak_shared::models::integrations::openai::ToolCall;
+use stakpak_shared::policy::{CommandRisk, RiskLevel, classify_tool_call, tool_call_paths};
+use stakpak_shared::workspace_policy::{SUGGEST_AFTER_APPROVALS, WorkspacePolicy, record_approval};
+use std::time::{Duration, Instant};
+use tokio::sync::mpsc::Sender;
+
+/// Text the user has to type before a high risk tool call is accepted
+pub const TYPED_CONFIRMATION: &str = "yes";
+
+/// Classify the pending tool call from its name and arguments
+pub fn assess_tool_call(tool_call: &ToolCall) -> CommandRisk {
+    let arguments = serde_json::from_str::<Value>(&tool_call.function.arguments).ok();
+    let argument = |key: &str| {
+        arguments
+            .as_ref()
+            .and_then(|args| args.get(key))
+            .and_then(|value| value.as_str())
+    };
+    classify_tool_call(
+        &tool_call.function.name,
+        argument("command"),
+        &tool_call_paths(arguments.as_ref().and_then(|args| args.as_object())),
+    )
+}
+
+fn shell_command(tool_call: &ToolCall) -> Option<String> {
+    if tool_call.function.name != "run_command" {
+        return None;
+    }
+    serde_json::from_str::<Value>(&tool_call.function.arguments)
+        .ok()?
+        .get("command")?
+        .as_str()
+        .map(|command| command.to_string())
+}
+
+/// Whether the workspace policy lets the tool call run without asking
+pub fn is_allowed_by_workspace_policy(tool_call: &ToolCall) -> bool {
+    let Some(command) = shell_command(tool_call) else {
+        return false;
+    };
+    WorkspacePolicy::load()
+        .map(|policy| policy.allows_command(&command))
+        .unwrap_or(false)
+}
+
+/// Count a tool call the user accepted, suggesting to add its command pattern to the
+/// workspace policy once it has been approved often enough
+pub fn remember_approval(state: &mut AppState, tool_call: &ToolCall) {
+    let Some(command) = shell_command(tool_call) else {
+        return;
+    };
+    // Learning from approvals is best effort
+    if let Ok(Some(pattern)) = record_approval(&command) {
+        render_system_message(
+            state,
+            &format!(
+                "You approved `{}` {} times, review adding it to the workspace policy with: stakpak policy review",
+                pattern, SUGGEST_AFTER_APPROVALS
+            ),
+        );
+    }
+}
+
+/// Whether Enter may accept the pending tool call with the current input
+pub fn is_confirmation_satisfied(state: &AppState) -> bool {
+    match &state.dialog_risk {
+        Some(risk) if risk.level.requires_typed_confirmation() => {
+            state.input.trim() == TYPED_CONFIRMATION
+        }
+        _ => true,
+    }
+}
+
+/// Start the countdown of a newly opened dialog, if one is configured
+pub fn start_confirmation_countdown(state: &mut AppState) {
+    state.dialog_deadline = state
+        .confirmation_timeout
+        .map(|timeout| Instant::now() + Duration::from_secs(timeout.secs));
+}
+
+/// Take the default action on the pending tool call once its countdown has run out
+pub fn apply_confirmation_timeout(state: &mut AppState, output_tx: &Sender<OutputEvent>) {
+    let (Some(deadline), Some(timeout)) = (state.dialog_deadline, state.confirmation_timeout)
+    else {
+        return;
+    };
+    if !state.is_dialog_open || Instant::now() < deadline {
+        return;
+    }
+    let Some(tool_call) = state.dialog_command.take() else {
+        return;
+    };
+
+    let approved = timeout.default_action == TimeoutAction::ApproveLowRisk
+        && state
+            .dialog_risk
+            .as_ref()
+            .is_some_and(|risk| !risk.level.requires_typed_confirmation());
+    let _ = output_tx.try_send(OutputEvent::ConfirmationTimedOut {
+        tool_call: tool_call.clone(),
+        approved,
+    });
+    if approved {
+        let _ = output_tx.try_send(OutputEvent::AcceptTool(tool_call.clone()));
+    } else {
+        let _ = output_tx.try_send(OutputEvent::RejectTool(tool_call.clone()));
+        render_bash_block_rejected(&extract_truncated_command_arguments(&tool_call), state);
+    }
+    render_system_message(
+        state,
+        &format!(
+            "No answer within {}s, {} {}",
+            timeout.secs,
+            if approved { "approved" } else { "skipped" },
+            tool_call.function.name
+        ),
+    );
+
+    state.is_dialog_open = false;
+    state.dialog_risk = None;
+    state.dialog_deadline = None;
+    state.input.clear();
+    state.cursor_position = 0;
+}
+
+/// Height of the confirmation dialog: borders, the prompt and the affected paths if any
+pub fn confirmation_dialog_height(state: &AppState) -> u16 {
+    let has_paths = state
+        .dialog_risk
+        .as_ref()
+        .is_some_and(|risk| !risk.affected_paths.is_empty());
+    if has_paths { 4 } else { 3 }
+}
+
+fn risk_color(level: RiskLevel) -> Color {
+    match level {
+        RiskLevel::ReadOnly => Color::Green,
+        RiskLevel::Write => Color::LightYellow,
+        RiskLevel::NetworkMutating => Color::Magenta,
+        RiskLevel::Destructive => Color::Red,
+    }
+}
 
 pub fn render_confirmation_dialog(f: &mut Frame, state: &AppState) {
     let screen = f.area();
-    let message_lines = get_wrapped_message_lines(&state.messages, screen.width as usize);
-    let mut last_message_y = message_lines.len() as u16 + 1; // +1 for a gap
+    let mut last_message_y = wrapped_line_count(state, screen.width as usize) as u16 + 1; // +1 for a gap
 
-    // Fixed dialog height: just 3 lines (border, message, border)
-    let dialog_height = 3;
+    let dialog_height = confirmation_dialog_height(state);
 
     // Clamp so dialog fits on screen
     if last_message_y + dialog_height > screen.height {
@@ -28,19 +172,211 @@ pub fn render_confirmation_dialog(f: &mut Frame, state: &AppState) {
         height: dialog_height,
     };
 
-    let line = Line::from(vec![Span::styled(
-        "Press Enter to continue or Esc to cancel and reprompt",
-        Style::default()
-            .fg(Color::White)
-            .add_modifier(Modifier::BOLD),
-    )]);
-    let dialog = Paragraph::new(vec![line])
+    let risk = state.dialog_risk.as_ref();
+    let border_color = risk
+        .map(|risk| risk_color(risk.level))
+        .unwrap_or(Color::LightYellow);
+
+    let mut prompt = Vec::new();
+    if let Some(risk) = risk {
+        prompt.push(Span::styled(
+            format!(" {} ", risk.level.to_string().to_uppercase()),
+            Style::default()
+                .fg(Color::Black)
+                .bg(risk_color(risk.level))
+                .add_modifier(Modifier::BOLD),
+        ));
+        prompt.push(Span::raw(" "));
+    }
+    if risk.is_some_and(|risk| risk.level.requires_typed_confirmation()) {
+        prompt.push(Span::styled(
+            format!(
+                "Type '{}' and press Enter to continue or Esc to cancel: ",
+                TYPED_CONFIRMATION
+            ),
+            Style::default()
+                .fg(Color::White)
+                .add_modifier(Modifier::BOLD),
+        ));
+        prompt.push(Span::styled(
+            state.input.clone(),
+            Style::default().fg(border_color),
+        ));
+    } else {
+        prompt.push(Span::styled(
+            "Press Enter to continue or Esc to cancel and reprompt",
+            Style::default()
+                .fg(Color::White)
+                .add_modifier(Modifier::BOLD),
+        ));
+    }
+
+    if let (Some(deadline), Some(timeout)) = (state.dialog_deadline, state.confirmation_timeout) {
+        let remaining = deadline.saturating_duration_since(Instant::now()).as_secs();
+        let auto_approve = timeout.default_action == TimeoutAction::ApproveLowRisk
+            && risk.is_some_and(|risk| !risk.level.requires_typed_confirmation());
+        prompt.push(Span::styled(
+            format!(
+                "  ({} in {}s)",
+                if auto_approve {
+                    "approving"
+                } else {
+                    "skipping"
+                },
+                remaining
+            ),
+            Style::default().fg(Color::DarkGray),
+        ));
+    }
+
+    let mut lines = vec![Line::from(prompt)];
+    if let Some(risk) = risk.filter(|risk| !risk.affected_paths.is_empty()) {
+        lines.push(Line::from(Span::styled(
+            format!("Affects: {}", risk.affected_paths.join(", ")),
+            Style::default().fg(Color::DarkGray),
+        )));
+    }
+
+    let dialog = Paragraph::new(lines)
         .block(
             Block::default()
                 .borders(Borders::ALL)
-                .border_style(Style::default().fg(Color::LightYellow))
+                .border_style(Style::default().fg(border_color))
                 .title("Confirmation"),
         )
         .alignment(Alignment::Center);
     f.render_widget(dialog, area);
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::app::ConfirmationTimeout;
+    use stakpak_shared::models::integrations::openai::FunctionCall;
+    use tokio::sync::mpsc::Receiver;
+
+    fn run_command(command: &str) -> ToolCall {
+        ToolCall {
+            id: "call_1".to_string(),
+            r#type: "function".to_string(),
+            function: FunctionCall {
+                name: "run_command".to_string(),
+                arguments: serde_json::json!({ "command": command }).to_string(),
+            },
+        }
+    }
+
+    /// State with a dialog open on `command` whose countdown has run out
+    fn timed_out_state(command: &str, default_action: TimeoutAction) -> AppState {
+        let mut state = AppState::new(Vec::new(), None);
+        let tool_call = run_command(command);
+        state.confirmation_timeout = Some(ConfirmationTimeout {
+            secs: 30,
+            default_action,
+        });
+        state.is_dialog_open = true;
+        state.dialog_risk = Some(assess_tool_call(&tool_call));
+        state.dialog_command = Some(tool_call);
+        state.dialog_deadline = Some(Instant::now());
+        state
+    }
+
+    fn timed_out_outcome(output_rx: &mut Receiver<OutputEvent>) -> Option<bool> {
+        match output_rx.try_recv() {
+            Ok(OutputEvent::ConfirmationTimedOut { approved, .. }) => Some(approved),
+            _ => None,
+        }
+    }
+
+    #[test]
+    fn test_typed_confirmation() {
+        let mut state = AppState::new(Vec::new(), None);
+        assert!(is_confirmation_satisfied(&state));
+
+        state.dialog_risk = Some(assess_tool_call(&run_command("rm -rf build")));
+        assert!(!is_confirmation_satisfied(&state));
+        state.input = " yes ".to_string();
+        assert!(is_confirmation_satisfied(&state));
+    }
+
+    #[test]
+    fn test_every_path_argument_is_affected() {
+        let tool_call = ToolCall {
+            id: "call_1".to_string(),
+            r#type: "function".to_string(),
+            function: FunctionCall {
+                name: "move_file".to_string(),
+                arguments: serde_json::json!({
+                    "source": "notes.txt",
+                    "destination": "~/.aws/config",
+                })
+                .to_string(),
+            },
+        };
+        assert_eq!(
+            assess_tool_call(&tool_call).affected_paths,
+            vec!["notes.txt", "~/.aws/config"]
+        );
+    }
+
+    #[test]
+    fn test_countdown_only_runs_when_configured() {
+        let mut state = AppState::new(Vec::new(), None);
+        start_confirmation_countdown(&mut state);
+        assert!(state.dialog_deadline.is_none());
+
+        state.confirmation_timeout = Some(ConfirmationTimeout {
+            secs: 30,
+            default_action: TimeoutAction::Skip,
+        });
+        start_confirmation_countdown(&mut state);
+        assert!(
+            state
+                .dialog_deadline
+                .is_some_and(|deadline| deadline > Instant::now())
+        );
+
+        // Nothing happens before the deadline
+        let (output_tx, mut output_rx) = tokio::sync::mpsc::channel(8);
+        state.is_dialog_open = true;
+        state.dialog_command = Some(run_command("ls"));
+        apply_confirmation_timeout(&mut state, &output_tx);
+        assert!(state.is_dialog_open);
+        assert!(output_rx.try_recv().is_err());
+    }
+
+    #[test]
+    fn test_low_risk_calls_are_approved_on_timeout() {
+        let (output_tx, mut output_rx) = tokio::sync::mpsc::channel(8);
+        let mut state = timed_out_state("ls -la", TimeoutAction::ApproveLowRisk);
+        apply_confirmation_timeout(&mut state, &output_tx);
+
+        assert_eq!(timed_out_outcome(&mut output_rx), Some(true));
+        assert!(matches!(
+            output_rx.try_recv(),
+            Ok(OutputEvent::AcceptTool(_))
+        ));
+        assert!(!state.is_dialog_open);
+        assert!(state.dialog_command.is_none());
+        assert!(state.dialog_deadline.is_none());
+    }
+
+    #[test]
+    fn test_calls_are_skipped_on_timeout() {
+        for (command, default_action) in [
+            ("ls -la", TimeoutAction::Skip),
+            ("rm -rf build", TimeoutAction::ApproveLowRisk),
+        ] {
+            let (output_tx, mut output_rx) = tokio::sync::mpsc::channel(8);
+            let mut state = timed_out_state(command, default_action);
+            apply_confirmation_timeout(&mut state, &output_tx);
+
+            assert_eq!(timed_out_outcome(&mut output_rx), Some(false));
+            assert!(matches!(
+                output_rx.try_recv(),
+                Ok(OutputEvent::RejectTool(_))
+            ));
+            assert!(!state.is_dialog_open);
+        }
+    }
+}
@@ -0,0 +1,237 @@
+use crate::app::AppState;
+use crate::services::message::{Message, MessageContent};
+use ratatui::style::{Color, Modifier, Style};
+use ratatui::text::{Line, Span};
+use similar::TextDiff;
+use stakpak_shared::file_history::FileHistory;
+use stakpak_shared::workspace::resolve_path;
+use uuid::Uuid;
+
+/// File history being stepped through with the left and right arrow keys
+pub struct FileHistoryView {
+    pub history: FileHistory,
+    pub index: usize,
+    pub message_id: Uuid,
+}
+
+fn parse_path_argument(input: &str, command: &str) -> Option<String> {
+    let path = input.trim().strip_prefix(command)?.trim();
+    let path = path.trim_matches('"').trim();
+    if path.is_empty() {
+        return None;
+    }
+    Some(path.to_string())
+}
+
+/// Extract the path out of `/filehistory <path>`
+pub fn parse_file_history_path(input: &str) -> Option<String> {
+    parse_path_argument(input, "/filehistory")
+}
+
+/// Extract the path out of `/blame <path>`
+pub fn parse_blame_path(input: &str) -> Option<String> {
+    parse_path_argument(input, "/blame")
+}
+
+/// Load the session history of `path` and show its latest version
+pub fn open_file_history(state: &mut AppState, path: &str) -> Result<(), String> {
+    let history = FileHistory::load(path)?
+        .filter(|history| !history.versions.is_empty())
+        .ok_or_else(|| format!("No agent edits recorded for {} in this session", path))?;
+
+    let view = FileHistoryView {
+        index: history.versions.len() - 1,
+        history,
+        message_id: Uuid::new_v4(),
+    };
+    state.messages.push(Message {
+        id: view.message_id,
+        content: MessageContent::StyledBlock(render_version(&view)),
+    });
+    state.file_history = Some(view);
+    Ok(())
+}
+
+/// Move `delta` versions back or forward and re-render the history block in place
+pub fn step_file_history(state: &mut AppState, delta: isize) {
+    let Some(view) = &mut state.file_history else {
+        return;
+    };
+    let last = view.history.versions.len().saturating_sub(1);
+    view.index = view.index.saturating_add_signed(delta).min(last);
+
+    let lines = render_version(view);
+    if let Some(message) = state
+        .messages
+        .iter_mut()
+        .find(|message| message.id == view.message_id)
+    {
+        message.content = MessageContent::StyledBlock(lines);
+    }
+}
+
+pub fn close_file_history(state: &mut AppState) {
+    state.file_history = None;
+}
+
+/// Show every hunk of `path` with the agent step that last changed it
+pub fn open_blame(state: &mut AppState, path: &str) -> Result<(), String> {
+    let resolved = resolve_path(path)?;
+    let resolved = resolved.to_string_lossy();
+    let history = FileHistory::load(&resolved)?
+        .filter(|history| !history.versions.is_empty())
+        .ok_or_else(|| format!("No agent edits recorded for {} in this session", path))?;
+    let current = std::fs::read_to_string(resolved.as_ref()).ok();
+
+    let mut lines = vec![
+        Line::from(vec![
+            Span::styled(
+                "🔎 Blame",
+                Style::default()
+                    .fg(Color::Cyan)
+                    .add_modifier(Modifier::BOLD),
+            ),
+            Span::raw(format!(" · {}", history.path)),
+        ]),
+        Line::from(""),
+    ];
+    let hunks = history.blame(current.as_deref());
+    let width = hunks
+        .last()
+        .map(|hunk| (hunk.start + hunk.lines.len()).to_string().len())
+        .unwrap_or(1);
+    for hunk in hunks {
+        lines.push(Line::from(Span::styled(
+            format!(
+                "  @@ lines {}-{} · {}",
+                hunk.start,
+                hunk.start + hunk.lines.len() - 1,
+                history.origin_label(hunk.origin)
+            ),
+            Style::default().fg(Color::Cyan),
+        )));
+        if let Some(turn) = history.origin_turn(hunk.origin) {
+            for (label, text) in [("prompt", &turn.prompt), ("reasoning", &turn.reasoning)] {
+                if !text.is_empty() {
+                    lines.push(Line::from(Span::styled(
+                        format!("     {}: {}", label, text.replace('\n', " ")),
+                        Style::default().fg(Color::DarkGray),
+                    )));
+                }
+            }
+        }
+        for (offset, line) in hunk.lines.iter().enumerate() {
+            lines.push(Line::from(vec![
+                Span::styled(
+                    format!("  {:>width$} │ ", hunk.start + offset, width = width),
+                    Style::default().fg(Color::DarkGray),
+                ),
+                Span::raw(line.clone()),
+            ]));
+        }
+    }
+    lines.push(Line::from(""));
+
+    state.messages.push(Message {
+        id: Uuid::new_v4(),
+        content: MessageContent::StyledBlock(lines),
+    });
+    Ok(())
+}
+
+fn render_version(view: &FileHistoryView) -> Vec<Line<'static>> {
+    let versions = &view.history.versions;
+    let version = &versions[view.index];
+
+    let mut lines = vec![
+        Line::from(vec![
+            Span::styled(
+                "📜 File history",
+                Style::default()
+                    .fg(Color::Cyan)
+                    .add_modifier(Modifier::BOLD),
+            ),
+            Span::raw(format!(" · {}", view.history.path)),
+        ]),
+        Line::from(Span::styled(
+            format!(
+                "  version {}/{} ({}) · ←/→ to step through versions, esc to close",
+                view.index + 1,
+                versions.len(),
+                version.tool
+            ),
+            Style::default().fg(Color::DarkGray),
+        )),
+        Line::from(""),
+    ];
+
+    if view.index == 0 {
+        let summary = match &version.content {
+            Some(content) => format!(
+                "  Content before the first agent edit ({} lines)",
+                content.lines().count()
+            ),
+            None => "  The file did not exist before the first agent edit".to_string(),
+        };
+        lines.push(Line::from(Span::styled(
+            summary,
+            Style::default().fg(Color::DarkGray),
+        )));
+    } else {
+        let old = versions[view.index - 1].content.as_deref().unwrap_or("");
+        let new = version.content.as_deref().unwrap_or("");
+        lines.extend(render_diff(old, new));
+    }
+
+    lines.push(Line::from(""));
+    lines
+}
+
+fn render_diff(old: &str, new: &str) -> Vec<Line<'static>> {
+    let diff = TextDiff::from_lines(old, new);
+    let unified = diff
+        .unified_diff()
+        .context_radius(3)
+        .header("previous", "current")
+        .to_string();
+
+    unified
+        .lines()
+        .map(|line| {
+            let style = if line.starts_with("+++") || line.starts_with("---") {
+                Style::default().add_modifier(Modifier::BOLD)
+            } else if line.starts_with('+') {
+                Style::default().fg(Color::Green)
+            } else if line.starts_with('-') {
+                Style::default().fg(Color::Red)
+            } else if line.starts_with("@@") {
+                Style::default().fg(Color::Cyan)
+            } else {
+                Style::default()
+            };
+            Line::from(Span::styled(format!("  {}", line), style))
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_file_history_path() {
+        assert_eq!(
+            parse_file_history_path("/filehistory src/main.rs"),
+            Some("src/main.rs".to_string())
+        );
+        assert_eq!(
+            parse_file_history_path("/filehistory \"my dir/main.tf\""),
+            Some("my dir/main.tf".to_string())
+        );
+        assert_eq!(parse_file_history_path("/filehistory "), None);
+        assert_eq!(
+            parse_blame_path("/blame main.tf"),
+            Some("main.tf".to_string())
+        );
+    }
+}
@@ -63,6 +63,32 @@ pub fn push_status_message(state: &mut AppState) {
     });
 }
 
+pub fn push_usage_message(state: &mut AppState) {
+    let mut lines = vec![
+        Line::from(vec![Span::styled(
+            "Token Usage",
+            Style::default()
+                .fg(Color::White)
+                .add_modifier(Modifier::BOLD),
+        )]),
+        Line::from(""),
+    ];
+    if state.usage_report.is_empty() {
+        lines.push(Line::from(Span::styled(
+            "  No token usage recorded in this session yet",
+            Style::default().fg(Color::DarkGray),
+        )));
+    }
+    for line in state.usage_report.lines() {
+        lines.push(Line::from(format!("  {}", line)));
+    }
+    lines.push(Line::from(""));
+    state.messages.push(Message {
+        id: Uuid::new_v4(),
+        content: MessageContent::StyledBlock(lines),
+    });
+}
+
 pub fn push_help_message(state: &mut AppState) {
     use ratatui::style::{Color, Modifier, Style};
     use ratatui::text::{Line, Span};
@@ -131,7 +157,19 @@ pub fn push_help_message(state: &mut AppState) {
     let commands = vec![
         ("/help", "show this help overlay"),
         ("/status", "show account status"),
+        ("/usage", "show token usage and estimated cost"),
         ("/sessions", "show list of sessions"),
+        ("/checkpoint label <name>", "label the current checkpoint"),
+        ("/filehistory <path>", "step through agent edits of a file"),
+        ("/blame <path>", "show which agent step changed each hunk"),
+        (
+            "/env [set|secret|unset]",
+            "manage session environment variables",
+        ),
+        ("/compact", "summarize older turns to free up context"),
+        ("/pin", "keep your latest message out of compaction"),
+        ("/pins", "list pinned messages"),
+        ("/unpin <number>", "unpin a message"),
         ("/quit", "quit the app"),
     ];
     for (cmd, desc) in commands {
@@ -155,6 +193,13 @@ pub fn push_help_message(state: &mut AppState) {
         ("Enter", "send message", Color::Yellow),
         ("Ctrl+J or Shift+Enter", "insert newline", Color::Yellow),
         ("Up/Down", "scroll prompt history", Color::Yellow),
+        ("Ctrl+K", "command palette", Color::Yellow),
+        (
+            "!<command>",
+            "run a shell command without the agent",
+            Color::Yellow,
+        ),
+        ("Ctrl+O", "open truncated output in $PAGER", Color::Yellow),
         ("Ctrl+C", "quit Stakpak", Color::Yellow),
     ];
     for (key, desc, color) in shortcuts {
@@ -234,10 +279,57 @@ pub fn render_loading_spinner(state: &AppState) -> Line {
         "Stakpaking..."
     };
 
-    Line::from(vec![Span::styled(
+    let mut spans = vec![Span::styled(
         format!("{} {}", spinner, spinner_text),
         Style::default()
             .fg(Color::LightRed)
             .add_modifier(Modifier::BOLD),
-    )])
+    )];
+    if let Some(metrics) = state
+        .stream_metrics
+        .filter(|_| state.loading_type == LoadingType::Llm)
+    {
+        spans.push(Span::styled(
+            format!(
+                "  first token {:.1}s · {:.0} tok/s",
+                metrics.time_to_first_token.as_secs_f64(),
+                metrics.tokens_per_second
+            ),
+            Style::default().fg(Color::DarkGray),
+        ));
+    }
+    Line::from(spans)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::app::StreamMetrics;
+    use std::time::Duration;
+
+    fn spinner_text(state: &AppState) -> String {
+        render_loading_spinner(state)
+            .spans
+            .iter()
+            .map(|span| span.content.as_ref())
+            .collect()
+    }
+
+    #[test]
+    fn test_loading_spinner_shows_stream_metrics() {
+        let mut state = AppState::new(Vec::new(), None);
+        assert_eq!(spinner_text(&state), "▄▀ Stakpaking...");
+
+        state.stream_metrics = Some(StreamMetrics {
+            time_to_first_token: Duration::from_millis(1250),
+            tokens_per_second: 42.4,
+        });
+        assert_eq!(
+            spinner_text(&state),
+            "▄▀ Stakpaking...  first token 1.2s · 42 tok/s"
+        );
+
+        state.loading_type = LoadingType::Sessions;
+        assert_eq!(spinner_text(&state), "▄▀ Loading sessions...");
+    }
 }
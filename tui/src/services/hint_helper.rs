@@ -11,7 +11,8 @@ pub fn render_hint_or_shortcuts(f: &mut Frame, state: &AppState, area: Rect) {
     if state.show_shortcuts {
         let shortcuts = vec![
             Line::from("/ for commands       shift + enter or ctrl + j to insert newline"),
-            Line::from("↵ to send message    ctrl + c to quit"),
+            Line::from("! for shell commands ctrl + k to search commands, files and sessions"),
+            Line::from("↵ to send message    ctrl + c to quit    ctrl + o to page full output"),
         ];
         let shortcuts_widget = Paragraph::new(shortcuts).style(Style::default().fg(Color::Cyan));
         f.render_widget(shortcuts_widget, area);
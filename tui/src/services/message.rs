@@ -1,14 +1,17 @@
+use crate::app::AppState;
 use crate::services::markdown::render_markdown_to_lines;
 use ratatui::style::Color;
-use ratatui::style::{Modifier, Style};
+use ratatui::style::Style;
 use ratatui::text::{Line, Span};
 use regex::Regex;
 use serde_json::Value;
 #[cfg(test)]
 use stakpak_shared::models::integrations::openai::FunctionCall;
 use stakpak_shared::models::integrations::openai::ToolCall;
+use std::collections::hash_map::DefaultHasher;
+use std::hash::{Hash, Hasher};
 use uuid::Uuid;
-#[derive(Clone)]
+#[derive(Clone, Hash)]
 pub struct BubbleColors {
     pub border_color: Color,
     pub title_color: Color,
@@ -16,14 +19,17 @@ pub struct BubbleColors {
     pub tool_type: String,
 }
 
+#[derive(Hash)]
 pub enum MessageContent {
     Plain(String, Style),
     Styled(Line<'static>),
     StyledBlock(Vec<Line<'static>>),
     Markdown(String),
+    /// Kept unwrapped, the bubble is drawn for the current width
     BashBubble {
         title: String,
-        content: Vec<String>,
+        bubble_title: String,
+        content: String,
         colors: BubbleColors,
         tool_type: String,
     },
@@ -111,20 +117,76 @@ pub fn get_wrapped_plain_lines<'a>(
     lines
 }
 
-pub fn get_wrapped_styled_lines<'a>(line: &Line<'a>, _width: usize) -> Vec<(Line<'a>, Style)> {
-    vec![
-        (line.clone(), Style::default()),
-        (Line::from(""), Style::default()),
-    ]
+/// Leading span drawn as a gutter, like the `└ ` of tool results or the `│ ` of triage
+fn is_gutter(text: &str) -> bool {
+    !text.is_empty()
+        && text
+            .chars()
+            .all(|c| c.is_whitespace() || matches!(c, '│' | '└'))
+}
+
+/// Wrap a styled line to `width` columns. A leading gutter span is repeated on the lines
+/// it wraps to so blocks keep their shape at any width.
+fn wrap_styled_line(line: &Line<'_>, width: usize) -> Vec<Line<'static>> {
+    let owned = into_owned_line(line.clone());
+    if width == 0 || owned.width() <= width {
+        return vec![owned];
+    }
+    let gutter = owned
+        .spans
+        .first()
+        .filter(|span| owned.spans.len() > 1 && is_gutter(&span.content))
+        .map(|span| Span::styled(span.content.replace('└', " "), span.style));
+    let gutter_width = gutter.as_ref().map(|gutter| gutter.width()).unwrap_or(0);
+    if gutter_width >= width {
+        return vec![owned];
+    }
+
+    let mut lines = Vec::new();
+    let mut current: Vec<Span<'static>> = Vec::new();
+    let mut current_width = 0;
+    for span in &owned.spans {
+        let mut text = String::new();
+        for c in span.content.chars() {
+            let char_width = unicode_width::UnicodeWidthChar::width(c).unwrap_or(0);
+            if current_width + char_width > width && current_width > gutter_width {
+                if !text.is_empty() {
+                    current.push(Span::styled(std::mem::take(&mut text), span.style));
+                }
+                lines.push(Line::from(std::mem::take(&mut current)).style(owned.style));
+                current_width = gutter_width;
+                current.extend(gutter.clone());
+            }
+            text.push(c);
+            current_width += char_width;
+        }
+        if !text.is_empty() {
+            current.push(Span::styled(text, span.style));
+        }
+    }
+    if !current.is_empty() {
+        lines.push(Line::from(current).style(owned.style));
+    }
+    lines
+}
+
+pub fn get_wrapped_styled_lines<'a>(line: &Line<'a>, width: usize) -> Vec<(Line<'a>, Style)> {
+    let mut lines: Vec<(Line<'a>, Style)> = wrap_styled_line(line, width)
+        .into_iter()
+        .map(|line| (line, Style::default()))
+        .collect();
+    lines.push((Line::from(""), Style::default()));
+    lines
 }
 
 pub fn get_wrapped_styled_block_lines<'a>(
     lines: &'a [Line<'a>],
-    _width: usize,
+    width: usize,
 ) -> Vec<(Line<'a>, Style)> {
     lines
         .iter()
-        .map(|l| (l.clone(), Style::default()))
+        .flat_map(|line| wrap_styled_line(line, width))
+        .map(|line| (line, Style::default()))
         .collect()
 }
 
@@ -138,22 +200,50 @@ pub fn get_wrapped_markdown_lines(markdown: &str, width: usize) -> Vec<(Line<'_>
     result
 }
 
-pub fn get_wrapped_bash_bubble_lines<'a>(
-    _title: &'a str,
-    content: &'a [String],
+/// Box drawn around `content` for a view `width` columns wide, `bubble_title` in its top
+/// border
+pub fn bubble_lines(content: &str, bubble_title: &str, width: usize) -> Vec<String> {
+    let inner_width = if width > 4 { width - 4 } else { 40 };
+    let horizontal_line = "─".repeat(inner_width + 2);
+    let bottom_border = format!("╰{}╯", horizontal_line);
+    let title_border = {
+        let title_width = bubble_title.chars().count();
+        if title_width <= inner_width {
+            let remaining_dashes = inner_width + 2 - title_width;
+            format!("╭{}{}", bubble_title, "─".repeat(remaining_dashes)) + "╮"
+        } else {
+            let truncated_title = bubble_title.chars().take(inner_width).collect::<String>();
+            format!("╭{}─╮", truncated_title)
+        }
+    };
+    let mut bubble_lines = vec![title_border];
+    for line in content.split('\n') {
+        let trimmed_line = line.trim_end();
+        if trimmed_line.is_empty() {
+            let padding = " ".repeat(inner_width);
+            bubble_lines.push(format!("│ {} │", padding));
+            continue;
+        }
+        for wrapped_line in wrap_text(trimmed_line, inner_width) {
+            let padding_needed = inner_width.saturating_sub(wrapped_line.chars().count());
+            let padding = " ".repeat(padding_needed);
+            bubble_lines.push(format!("│ {}{} │", wrapped_line, padding));
+        }
+    }
+    bubble_lines.push(bottom_border);
+    bubble_lines
+}
+
+pub fn get_wrapped_bash_bubble_lines(
+    bubble_title: &str,
+    content: &str,
     colors: &BubbleColors,
-) -> Vec<(Line<'a>, Style)> {
-    let _title_style = Style::default()
-        .fg(colors.title_color)
-        .add_modifier(Modifier::BOLD);
+    width: usize,
+) -> Vec<(Line<'static>, Style)> {
     let border_style = Style::default().fg(colors.border_color);
     let content_style = Style::default().fg(colors.content_color);
     let mut lines = Vec::new();
-    // lines.push((
-    //     Line::from(vec![Span::styled(title, title_style)]),
-    //     title_style,
-    // ));
-    for line in content.iter() {
+    for line in bubble_lines(content, bubble_title, width) {
         let chars: Vec<char> = line.chars().collect();
         if chars.len() > 2 && chars[0] == '│' && chars[chars.len() - 1] == '│' {
             let mut spans = Vec::new();
@@ -167,12 +257,12 @@ pub fn get_wrapped_bash_bubble_lines<'a>(
             lines.push((Line::from(spans), border_style));
         } else if line.starts_with('╭') || line.starts_with('╰') {
             lines.push((
-                Line::from(vec![Span::styled(line.clone(), border_style)]),
+                Line::from(vec![Span::styled(line, border_style)]),
                 border_style,
             ));
         } else {
             lines.push((
-                Line::from(vec![Span::styled(line.clone(), content_style)]),
+                Line::from(vec![Span::styled(line, content_style)]),
                 content_style,
             ));
         }
@@ -198,18 +288,110 @@ pub fn get_wrapped_message_lines(messages: &[Message], width: usize) -> Vec<(Lin
                 all_lines.extend(get_wrapped_markdown_lines(markdown, width));
             }
             MessageContent::BashBubble {
-                title,
+                bubble_title,
                 content,
                 colors,
-                tool_type: _,
+                ..
             } => {
-                all_lines.extend(get_wrapped_bash_bubble_lines(title, content, colors));
+                all_lines.extend(get_wrapped_bash_bubble_lines(
+                    bubble_title,
+                    content,
+                    colors,
+                    width,
+                ));
             }
         }
     }
     all_lines
 }
 
+/// Wrapped lines of the messages as of the last frame. Wrapping, markdown rendering
+/// included, is the bulk of a redraw, so it is only redone when the messages or the width
+/// change rather than on every spinner tick or keystroke.
+#[derive(Default)]
+pub struct WrappedLinesCache {
+    /// Fingerprint of the messages and the width the lines were wrapped for
+    key: Option<(u64, usize)>,
+    lines: Vec<(Line<'static>, Style)>,
+}
+
+fn messages_fingerprint(messages: &[Message]) -> u64 {
+    let mut hasher = DefaultHasher::new();
+    for message in messages {
+        message.id.hash(&mut hasher);
+        message.content.hash(&mut hasher);
+    }
+    hasher.finish()
+}
+
+fn into_owned_line(line: Line<'_>) -> Line<'static> {
+    Line {
+        spans: line
+            .spans
+            .into_iter()
+            .map(|span| Span::styled(span.content.into_owned(), span.style))
+            .collect(),
+        style: line.style,
+        alignment: line.alignment,
+    }
+}
+
+fn owned_message_lines(messages: &[Message], width: usize) -> Vec<(Line<'static>, Style)> {
+    get_wrapped_message_lines(messages, width)
+        .into_iter()
+        .map(|(line, style)| (into_owned_line(line), style))
+        .collect()
+}
+
+/// Run `f` on the wrapped message lines, re-wrapping only when the messages changed
+pub fn with_wrapped_message_lines<R>(
+    state: &AppState,
+    width: usize,
+    f: impl FnOnce(&[(Line<'static>, Style)]) -> R,
+) -> R {
+    let key = Some((messages_fingerprint(&state.messages), width));
+    match state.wrapped_lines_cache.lock() {
+        Ok(mut cache) => {
+            if cache.key != key {
+                cache.lines = owned_message_lines(&state.messages, width);
+                cache.key = key;
+            }
+            f(&cache.lines)
+        }
+        Err(_) => f(&owned_message_lines(&state.messages, width)),
+    }
+}
+
+pub fn wrapped_line_count(state: &AppState, width: usize) -> usize {
+    with_wrapped_message_lines(state, width, |lines| lines.len())
+}
+
+/// Scroll offset showing the same part of the history after the message area changed from
+/// `old_width` to `new_width` columns: the message at the top of the view stays there, the
+/// offset into it scaled to its new height
+pub fn reflow_scroll(
+    messages: &[Message],
+    old_width: usize,
+    new_width: usize,
+    scroll: usize,
+) -> usize {
+    let height = |message: &Message, width: usize| {
+        get_wrapped_message_lines(std::slice::from_ref(message), width).len()
+    };
+    let mut old_start = 0;
+    let mut new_start = 0;
+    for message in messages {
+        let old_height = height(message, old_width);
+        let new_height = height(message, new_width);
+        if scroll < old_start + old_height {
+            return new_start + (scroll - old_start) * new_height / old_height.max(1);
+        }
+        old_start += old_height;
+        new_start += new_height;
+    }
+    new_start
+}
+
 pub fn extract_truncated_command_arguments(tool_call: &ToolCall) -> String {
     let arguments = serde_json::from_str::<Value>(&tool_call.function.arguments);
     const MAX_PARAMS: usize = 3;
@@ -543,6 +725,7 @@ pub fn get_command_type_name(tool_call: &ToolCall) -> String {
         "run_command" => "Run command".to_string(),
         "read_file" => "Read file".to_string(),
         "delete_file" => "Delete file".to_string(),
+        "move_file" => "Move file".to_string(),
         "list_directory" => "List directory".to_string(),
         "search_files" => "Search files".to_string(),
         _ => {
@@ -601,4 +784,42 @@ mod tests {
             );
         }
     }
+
+    #[test]
+    fn test_wrapped_lines_cache() {
+        let mut state = AppState::new(vec![], None);
+        state.messages.push(Message::info("one", None));
+        let before = wrapped_line_count(&state, 80);
+
+        // Streaming grows the last message in place
+        if let Some(MessageContent::Plain(text, _)) = state
+            .messages
+            .last_mut()
+            .map(|message| &mut message.content)
+        {
+            text.push_str("\ntwo");
+        }
+        assert_eq!(wrapped_line_count(&state, 80), before + 1);
+        assert!(wrapped_line_count(&state, 20) > before);
+    }
+
+    #[test]
+    fn test_reflow_on_resize() {
+        let line = Line::from(vec![
+            Span::raw("  │ "),
+            Span::raw("a triage line that is too long for a narrow terminal"),
+        ]);
+        let wrapped = wrap_styled_line(&line, 20);
+        assert!(wrapped.len() > 1);
+        assert!(wrapped.iter().all(|line| line.width() <= 20));
+        assert!(wrapped.iter().all(|line| line.spans[0].content == "  │ "));
+
+        let messages = vec![
+            Message::info("x".repeat(100), None),
+            Message::info("second", None),
+        ];
+        // Top of the view on the second message stays on it at any width
+        assert_eq!(reflow_scroll(&messages, 100, 50, 2), 3);
+        assert_eq!(reflow_scroll(&messages, 50, 100, 3), 2);
+    }
 }
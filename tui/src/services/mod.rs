@@ -1,10 +1,19 @@
 pub mod bash_block;
+pub mod citations;
+pub mod command_palette;
 pub mod confirmation_dialog;
+pub mod file_history;
 pub mod helper_block;
 pub mod helper_dropdown;
 pub mod hint_helper;
 pub mod markdown;
 pub mod message;
 pub mod message_pattern;
+pub mod pager;
+pub mod path_completion;
+pub mod pins;
+pub mod question_form;
+pub mod session_env;
 pub mod sessions_dialog;
+pub mod shell_escape;
 pub mod update;
@@ -0,0 +1,76 @@
+use crate::app::AppState;
+use crate::services::helper_block::render_system_message;
+use regex::Regex;
+use stakpak_shared::command_result::CommandMetadata;
+use std::io;
+
+const DEFAULT_PAGER: &str = "less";
+
+/// Extract the file a truncated command output was saved to, if any
+pub fn extract_saved_output_path(result: &str) -> Option<String> {
+    if let Some(output_file) = CommandMetadata::parse(result).and_then(|m| m.output_file) {
+        return Some(output_file);
+    }
+    let regex = Regex::new(r"Full output saved to (\S+)").ok()?;
+    regex
+        .captures(result)
+        .and_then(|cap| cap.get(1))
+        .map(|path| path.as_str().to_string())
+}
+
+/// Remember the saved output of a truncated result and suggest opening it in the pager
+pub fn remember_saved_output(state: &mut AppState, result: &str) {
+    if let Some(path) = extract_saved_output_path(result) {
+        render_system_message(
+            state,
+            &format!(
+                "Output was truncated, press ctrl + o to open the full output ({}) in your pager",
+                path
+            ),
+        );
+        state.saved_output_path = Some(path);
+    }
+}
+
+/// Open `path` in `$PAGER` (less by default) and wait for it to exit. The caller is
+/// responsible for handing the terminal over to the pager.
+pub async fn open_in_pager(path: &str) -> io::Result<()> {
+    let pager = std::env::var("PAGER")
+        .ok()
+        .filter(|pager| !pager.trim().is_empty())
+        .unwrap_or_else(|| DEFAULT_PAGER.to_string());
+    let mut parts = pager.split_whitespace();
+    let program = parts.next().unwrap_or(DEFAULT_PAGER);
+
+    let status = tokio::process::Command::new(program)
+        .args(parts)
+        .arg(path)
+        .status()
+        .await?;
+    if !status.success() {
+        return Err(io::Error::other(format!(
+            "{} exited with {}",
+            program, status
+        )));
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_extract_saved_output_path() {
+        let result = "Showing the last 300 / 1200 output lines. Full output saved to .stakpak/session/command.output.00ab12.txt\n...\nlast line";
+        assert_eq!(
+            extract_saved_output_path(result),
+            Some(".stakpak/session/command.output.00ab12.txt".to_string())
+        );
+    }
+
+    #[test]
+    fn test_extract_saved_output_path_without_truncation() {
+        assert_eq!(extract_saved_output_path("total 0\nfile.txt"), None);
+    }
+}
@@ -0,0 +1,148 @@
+use crate::app::AppState;
+use stakpak_shared::workspace::{ROOT_URI_PREFIX, WorkspaceRoot, workspace_roots};
+use std::path::{Path, PathBuf};
+
+fn common_prefix(candidates: &[String]) -> String {
+    let Some(first) = candidates.first() else {
+        return String::new();
+    };
+    let mut prefix = first.as_str();
+    for candidate in &candidates[1..] {
+        while !candidate.starts_with(prefix) {
+            let mut chars = prefix.chars();
+            chars.next_back();
+            prefix = chars.as_str();
+        }
+    }
+    prefix.to_string()
+}
+
+/// Entries of `dir` starting with `prefix`, directories with a trailing `/`
+fn matching_entries(dir: &Path, prefix: &str) -> Vec<String> {
+    let Ok(entries) = std::fs::read_dir(dir) else {
+        return Vec::new();
+    };
+    let mut names: Vec<String> = entries
+        .flatten()
+        .filter_map(|entry| {
+            let name = entry.file_name().to_string_lossy().to_string();
+            // Hidden files only when asked for
+            if !name.starts_with(prefix) || (name.starts_with('.') && !prefix.starts_with('.')) {
+                return None;
+            }
+            let is_dir = entry.file_type().map(|ft| ft.is_dir()).unwrap_or(false);
+            Some(if is_dir { format!("{}/", name) } else { name })
+        })
+        .collect();
+    names.sort();
+    names
+}
+
+/// Longest unambiguous completion of `word` as a path, `root://` paths completing root
+/// names first and then files under the root
+fn complete_path_in(word: &str, roots: &[WorkspaceRoot]) -> Option<String> {
+    if let Some(rest) = word.strip_prefix(ROOT_URI_PREFIX) {
+        if !rest.contains('/') {
+            let names: Vec<String> = roots
+                .iter()
+                .filter(|root| root.name.starts_with(rest))
+                .map(|root| format!("{}/", root.name))
+                .collect();
+            let completed = format!("{}{}", ROOT_URI_PREFIX, common_prefix(&names));
+            return (completed.len() > word.len()).then_some(completed);
+        }
+    }
+
+    let (dir, prefix) = match word.rfind('/') {
+        Some(index) => (&word[..=index], &word[index + 1..]),
+        None => ("", word),
+    };
+    let dir_path = if dir.is_empty() {
+        PathBuf::from(".")
+    } else if let Some(rest) = dir.strip_prefix(ROOT_URI_PREFIX) {
+        let (name, relative) = rest.split_once('/').unwrap_or((rest, ""));
+        roots
+            .iter()
+            .find(|root| root.name == name)?
+            .path
+            .join(relative)
+    } else {
+        PathBuf::from(dir)
+    };
+    let completed = format!(
+        "{}{}",
+        dir,
+        common_prefix(&matching_entries(&dir_path, prefix))
+    );
+    (completed.len() > word.len()).then_some(completed)
+}
+
+/// Complete the path under the cursor on tab, like a shell does
+pub fn complete_input_path(state: &mut AppState) {
+    let pos = state.cursor_position.min(state.input.len());
+    let start = state.input[..pos]
+        .char_indices()
+        .rev()
+        .find(|(_, c)| c.is_whitespace())
+        .map(|(index, c)| index + c.len_utf8())
+        .unwrap_or(0);
+    // Slash commands own the first word
+    if start == 0 && state.input.starts_with('/') {
+        return;
+    }
+    let word = &state.input[start..pos];
+    if word.is_empty() {
+        return;
+    }
+    if let Some(completed) = complete_path_in(word, &workspace_roots()) {
+        state.input.replace_range(start..pos, &completed);
+        state.cursor_position = start + completed.len();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use stakpak_shared::workspace::RootAccess;
+
+    #[test]
+    fn test_complete_path_in() {
+        let dir =
+            std::env::temp_dir().join(format!("stakpak-path-completion-{}", uuid::Uuid::new_v4()));
+        std::fs::create_dir_all(dir.join("modules/vpc")).unwrap();
+        std::fs::write(dir.join("main.tf"), "").unwrap();
+        std::fs::write(dir.join("modules/vpc/main.tf"), "").unwrap();
+        let roots = vec![
+            WorkspaceRoot {
+                name: "infra".to_string(),
+                path: dir.clone(),
+                access: RootAccess::ReadOnly,
+            },
+            WorkspaceRoot {
+                name: "app".to_string(),
+                path: dir.join("modules"),
+                access: RootAccess::ReadWrite,
+            },
+        ];
+
+        assert_eq!(
+            complete_path_in("root://in", &roots),
+            Some("root://infra/".to_string())
+        );
+        assert_eq!(
+            complete_path_in("root://infra/mo", &roots),
+            Some("root://infra/modules/".to_string())
+        );
+        assert_eq!(
+            complete_path_in("root://app/vpc/m", &roots),
+            Some("root://app/vpc/main.tf".to_string())
+        );
+        assert_eq!(complete_path_in("root://infra/x", &roots), None);
+        let local = format!("{}/ma", dir.display());
+        assert_eq!(
+            complete_path_in(&local, &roots),
+            Some(format!("{}/main.tf", dir.display()))
+        );
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
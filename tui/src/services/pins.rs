@@ -0,0 +1,225 @@
+use crate::app::{AppState, OutputEvent};
+use crate::services::helper_block::render_system_message;
+use crate::services::message::{Message, MessageContent};
+use ratatui::style::{Color, Modifier, Style};
+use ratatui::text::{Line, Span};
+use tokio::sync::mpsc::Sender;
+use uuid::Uuid;
+
+const PIN_USAGE: &str = "Usage: /pin, /pins, /unpin <number>";
+
+/// Shown before pinned messages in the conversation
+const PIN_INDICATOR: &str = "📌 ";
+
+/// Characters of each pinned message shown by `/pins`
+const PIN_PREVIEW_CHARS: usize = 80;
+
+#[derive(Debug, PartialEq)]
+pub enum PinCommand {
+    /// Pin the latest message sent to the agent
+    Pin,
+    List,
+    /// Unpin by the 1-based number `/pins` shows
+    Unpin(usize),
+}
+
+/// Parse `/pin`, `/pins` or `/unpin <number>`
+pub fn parse_pin_command(input: &str) -> Result<PinCommand, String> {
+    let mut parts = input.split_whitespace();
+    let command = match (parts.next(), parts.next()) {
+        (Some("/pin"), None) => PinCommand::Pin,
+        (Some("/pins"), None) => PinCommand::List,
+        (Some("/unpin"), Some(number)) => match number.parse::<usize>() {
+            Ok(number) if number > 0 => PinCommand::Unpin(number),
+            _ => return Err(PIN_USAGE.to_string()),
+        },
+        _ => return Err(PIN_USAGE.to_string()),
+    };
+    if parts.next().is_some() {
+        return Err(PIN_USAGE.to_string());
+    }
+    Ok(command)
+}
+
+/// Text of a message the user sent to the agent, slash commands and `!commands` excluded
+fn user_message_text(message: &Message) -> Option<&str> {
+    let MessageContent::Plain(text, _) = &message.content else {
+        return None;
+    };
+    let text = text.strip_prefix(PIN_INDICATOR).unwrap_or(text);
+    let text = text.strip_prefix("> ")?;
+    if text.starts_with('/') || text.starts_with('!') {
+        return None;
+    }
+    Some(text)
+}
+
+/// Ask to pin the latest message, it is marked once the agent confirms with `mark_pinned`
+fn pin_latest_message(state: &mut AppState, output_tx: &Sender<OutputEvent>) {
+    let latest = state
+        .messages
+        .iter()
+        .rev()
+        .find_map(|message| user_message_text(message).map(str::to_string));
+    let Some(text) = latest else {
+        render_system_message(state, "No message to pin yet, send one first");
+        return;
+    };
+    if state.pinned_messages.contains(&text) {
+        render_system_message(state, "The latest message is already pinned");
+        return;
+    }
+    let _ = output_tx.try_send(OutputEvent::PinMessage(text));
+}
+
+/// Mark a message the agent pinned and list it under `/pins`
+pub fn mark_pinned(state: &mut AppState, text: String) {
+    if state.pinned_messages.contains(&text) {
+        return;
+    }
+    let sent = format!("> {}", text);
+    if let Some(message) = state.messages.iter_mut().rev().find(
+        |message| matches!(&message.content, MessageContent::Plain(content, _) if *content == sent),
+    ) {
+        if let MessageContent::Plain(content, _) = &mut message.content {
+            content.insert_str(0, PIN_INDICATOR);
+        }
+    }
+    state.pinned_messages.push(text);
+}
+
+fn unpin_message(state: &mut AppState, number: usize, output_tx: &Sender<OutputEvent>) {
+    if number > state.pinned_messages.len() {
+        render_system_message(
+            state,
+            &format!(
+                "There is no pin {}, /pins lists the {} pinned messages",
+                number,
+                state.pinned_messages.len()
+            ),
+        );
+        return;
+    }
+    let text = state.pinned_messages.remove(number - 1);
+    let pinned = format!("{}> {}", PIN_INDICATOR, text);
+    for message in state.messages.iter_mut() {
+        if let MessageContent::Plain(content, _) = &mut message.content {
+            if *content == pinned {
+                content.replace_range(..PIN_INDICATOR.len(), "");
+            }
+        }
+    }
+    let _ = output_tx.try_send(OutputEvent::UnpinMessage(text));
+}
+
+fn render_pins(state: &mut AppState) {
+    let mut lines = vec![
+        Line::from(Span::styled(
+            format!("{}Pinned messages", PIN_INDICATOR),
+            Style::default()
+                .fg(Color::Cyan)
+                .add_modifier(Modifier::BOLD),
+        )),
+        Line::from(""),
+    ];
+    if state.pinned_messages.is_empty() {
+        lines.push(Line::from(Span::styled(
+            "  No pinned messages, /pin keeps your latest message when the history is compacted",
+            Style::default().fg(Color::DarkGray),
+        )));
+    }
+    for (index, text) in state.pinned_messages.iter().enumerate() {
+        let mut preview: String = text
+            .lines()
+            .next()
+            .unwrap_or_default()
+            .chars()
+            .take(PIN_PREVIEW_CHARS)
+            .collect();
+        if preview.len() < text.len() {
+            preview.push('…');
+        }
+        lines.push(Line::from(vec![
+            Span::styled(
+                format!("  {}. ", index + 1),
+                Style::default().fg(Color::Yellow),
+            ),
+            Span::raw(preview),
+        ]));
+    }
+    lines.push(Line::from(""));
+    lines.push(Line::from(Span::styled(
+        format!("  {}", PIN_USAGE),
+        Style::default().fg(Color::DarkGray),
+    )));
+    lines.push(Line::from(""));
+    state.messages.push(Message {
+        id: Uuid::new_v4(),
+        content: MessageContent::StyledBlock(lines),
+    });
+}
+
+/// Run a parsed `/pin`, `/pins` or `/unpin` command
+pub fn run_pin_command(state: &mut AppState, command: PinCommand, output_tx: &Sender<OutputEvent>) {
+    match command {
+        PinCommand::Pin => pin_latest_message(state, output_tx),
+        PinCommand::List => render_pins(state),
+        PinCommand::Unpin(number) => unpin_message(state, number, output_tx),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_pin_command() {
+        assert_eq!(parse_pin_command("/pin"), Ok(PinCommand::Pin));
+        assert_eq!(parse_pin_command(" /pins "), Ok(PinCommand::List));
+        assert_eq!(parse_pin_command("/unpin 2"), Ok(PinCommand::Unpin(2)));
+        assert!(parse_pin_command("/unpin").is_err());
+        assert!(parse_pin_command("/unpin 0").is_err());
+        assert!(parse_pin_command("/pin everything").is_err());
+    }
+
+    #[test]
+    fn test_messages_are_marked_once_pinned() {
+        let (output_tx, mut output_rx) = tokio::sync::mpsc::channel(10);
+        let mut state = AppState::new(Vec::new(), None);
+        state
+            .messages
+            .push(Message::user("> migrate the api to eks", None));
+
+        run_pin_command(&mut state, PinCommand::Pin, &output_tx);
+        assert!(matches!(
+            output_rx.try_recv(),
+            Ok(OutputEvent::PinMessage(text)) if text == "migrate the api to eks"
+        ));
+        // Nothing is marked until the agent confirms the pin
+        assert!(state.pinned_messages.is_empty());
+
+        mark_pinned(&mut state, "migrate the api to eks".to_string());
+        assert_eq!(state.pinned_messages, vec!["migrate the api to eks"]);
+        assert!(matches!(
+            &state.messages.last().unwrap().content,
+            MessageContent::Plain(content, _) if content == "📌 > migrate the api to eks"
+        ));
+
+        run_pin_command(&mut state, PinCommand::Unpin(1), &output_tx);
+        assert!(state.pinned_messages.is_empty());
+        assert!(matches!(
+            &state.messages.last().unwrap().content,
+            MessageContent::Plain(content, _) if content == "> migrate the api to eks"
+        ));
+    }
+
+    #[test]
+    fn test_only_sent_messages_can_be_pinned() {
+        let message = Message::user("> migrate the api to eks", None);
+        assert_eq!(user_message_text(&message), Some("migrate the api to eks"));
+        let pinned = Message::user("📌 > migrate the api to eks", None);
+        assert_eq!(user_message_text(&pinned), Some("migrate the api to eks"));
+        assert_eq!(user_message_text(&Message::user("> /usage", None)), None);
+        assert_eq!(user_message_text(&Message::info("System", None)), None);
+    }
+}
@@ -0,0 +1,368 @@
+use crate::app::{AppState, OutputEvent};
+use crate::services::message::{Message, MessageContent, wrap_text};
+use ratatui::{
+    Frame,
+    layout::Rect,
+    style::{Color, Modifier, Style},
+    text::{Line, Span},
+    widgets::{Block, Borders, Paragraph, Wrap},
+};
+use serde::{Deserialize, Serialize};
+use stakpak_shared::models::integrations::openai::ToolCall;
+use tokio::sync::mpsc::Sender;
+use uuid::Uuid;
+
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum QuestionArg {
+    Text(String),
+    Detailed {
+        question: String,
+        #[serde(default)]
+        options: Vec<String>,
+        #[serde(default = "default_required")]
+        required: bool,
+    },
+}
+
+fn default_required() -> bool {
+    true
+}
+
+#[derive(Deserialize)]
+struct AskUserArgs {
+    questions: Vec<QuestionArg>,
+}
+
+#[derive(Debug, Clone)]
+pub struct FormField {
+    pub question: String,
+    pub options: Vec<String>,
+    pub required: bool,
+    pub answer: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct QuestionAnswer {
+    pub question: String,
+    pub answer: String,
+}
+
+#[derive(Debug, Clone)]
+pub struct QuestionForm {
+    pub tool_call: ToolCall,
+    pub fields: Vec<FormField>,
+    pub selected: usize,
+    pub error: Option<String>,
+}
+
+impl QuestionForm {
+    pub fn from_tool_call(tool_call: &ToolCall) -> Result<Self, String> {
+        let args: AskUserArgs = serde_json::from_str(&tool_call.function.arguments)
+            .map_err(|e| format!("Invalid questions from the agent: {}", e))?;
+        if args.questions.is_empty() {
+            return Err("The agent asked no questions".to_string());
+        }
+
+        let fields = args
+            .questions
+            .into_iter()
+            .map(|q| match q {
+                QuestionArg::Text(question) => FormField {
+                    question,
+                    options: Vec::new(),
+                    required: true,
+                    answer: String::new(),
+                },
+                QuestionArg::Detailed {
+                    question,
+                    options,
+                    required,
+                } => FormField {
+                    question,
+                    options,
+                    required,
+                    answer: String::new(),
+                },
+            })
+            .collect();
+
+        Ok(Self {
+            tool_call: tool_call.clone(),
+            fields,
+            selected: 0,
+            error: None,
+        })
+    }
+
+    pub fn next_field(&mut self) {
+        self.selected = (self.selected + 1) % self.fields.len();
+    }
+
+    pub fn previous_field(&mut self) {
+        self.selected = (self.selected + self.fields.len() - 1) % self.fields.len();
+    }
+
+    pub fn insert_str(&mut self, text: &str) {
+        if let Some(field) = self.fields.get_mut(self.selected) {
+            field.answer.push_str(text);
+        }
+        self.error = None;
+    }
+
+    pub fn backspace(&mut self) {
+        if let Some(field) = self.fields.get_mut(self.selected) {
+            field.answer.pop();
+        }
+        self.error = None;
+    }
+
+    /// Validate every field, focusing the first invalid one. Options can be answered
+    /// by their value or their 1-based number.
+    pub fn validate(&mut self) -> Result<Vec<QuestionAnswer>, String> {
+        let mut answers = Vec::new();
+        for (i, field) in self.fields.iter().enumerate() {
+            let answer = field.answer.trim();
+            let answer = if field.options.is_empty() || answer.is_empty() {
+                Some(answer.to_string())
+            } else {
+                answer
+                    .parse::<usize>()
+                    .ok()
+                    .and_then(|n| field.options.get(n.wrapping_sub(1)).cloned())
+                    .or_else(|| {
+                        field
+                            .options
+                            .iter()
+                            .find(|o| o.eq_ignore_ascii_case(answer))
+                            .cloned()
+                    })
+            };
+
+            let error = match &answer {
+                Some(a) if a.is_empty() && field.required => Some("An answer is required"),
+                None => Some("Pick one of the listed options"),
+                _ => None,
+            };
+            if let Some(error) = error {
+                self.selected = i;
+                return Err(error.to_string());
+            }
+
+            answers.push(QuestionAnswer {
+                question: field.question.clone(),
+                answer: answer.unwrap_or_default(),
+            });
+        }
+        Ok(answers)
+    }
+
+    pub fn height(&self) -> u16 {
+        let field_lines: usize = self
+            .fields
+            .iter()
+            .map(|f| if f.options.is_empty() { 2 } else { 3 })
+            .sum();
+        // borders and the help/error line
+        (field_lines + 3) as u16
+    }
+}
+
+pub fn open_question_form(state: &mut AppState, tool_call: &ToolCall) -> Result<(), String> {
+    state.question_form = Some(QuestionForm::from_tool_call(tool_call)?);
+    state.input.clear();
+    state.cursor_position = 0;
+    state.loading = false;
+    Ok(())
+}
+
+/// Submit the form when the last field is focused, otherwise move to the next field
+pub fn handle_form_submit(state: &mut AppState, output_tx: &Sender<OutputEvent>) {
+    let Some(form) = state.question_form.as_mut() else {
+        return;
+    };
+    if form.selected + 1 < form.fields.len() {
+        form.next_field();
+        return;
+    }
+
+    match form.validate() {
+        Ok(answers) => {
+            let tool_call = form.tool_call.clone();
+            state.question_form = None;
+            render_answers_block(state, &answers);
+            let _ = output_tx.try_send(OutputEvent::SubmitAnswers(tool_call, answers));
+            state.loading = true;
+            state.spinner_frame = 0;
+        }
+        Err(error) => form.error = Some(error),
+    }
+}
+
+pub fn handle_form_cancel(state: &mut AppState, output_tx: &Sender<OutputEvent>) {
+    if let Some(form) = state.question_form.take() {
+        let _ = output_tx.try_send(OutputEvent::RejectTool(form.tool_call));
+        state.messages.push(Message::info(
+            "Questions dismissed",
+            Some(Style::default().fg(Color::DarkGray)),
+        ));
+    }
+}
+
+fn render_answers_block(state: &mut AppState, answers: &[QuestionAnswer]) {
+    let mut lines = vec![Line::from(vec![
+        Span::styled(
+            "● ",
+            Style::default()
+                .fg(Color::LightBlue)
+                .add_modifier(Modifier::BOLD),
+        ),
+        Span::styled(
+            "Answers",
+            Style::default()
+                .fg(Color::White)
+                .add_modifier(Modifier::BOLD),
+        ),
+    ])];
+    for answer in answers {
+        for wrapped in wrap_text(&answer.question, 100) {
+            lines.push(Line::from(vec![
+                Span::styled("  │ ", Style::default().fg(Color::Blue)),
+                Span::styled(wrapped, Style::default().fg(Color::Gray)),
+            ]));
+        }
+        let text = if answer.answer.is_empty() {
+            "(no answer)".to_string()
+        } else {
+            answer.answer.clone()
+        };
+        lines.push(Line::from(vec![
+            Span::styled("  │ ", Style::default().fg(Color::Blue)),
+            Span::styled(format!("→ {}", text), Style::default().fg(Color::LightBlue)),
+        ]));
+    }
+    lines.push(Line::from(""));
+
+    state.messages.push(Message {
+        id: Uuid::new_v4(),
+        content: MessageContent::StyledBlock(lines),
+    });
+}
+
+pub fn render_question_form(f: &mut Frame, state: &AppState, area: Rect) {
+    let Some(form) = &state.question_form else {
+        return;
+    };
+
+    let mut lines = Vec::new();
+    for (i, field) in form.fields.iter().enumerate() {
+        let focused = i == form.selected;
+        let marker = if focused { "› " } else { "  " };
+        let question_style = if focused {
+            Style::default()
+                .fg(Color::White)
+                .add_modifier(Modifier::BOLD)
+        } else {
+            Style::default().fg(Color::Gray)
+        };
+        lines.push(Line::from(vec![
+            Span::styled(marker, Style::default().fg(Color::LightYellow)),
+            Span::styled(format!("{}. {}", i + 1, field.question), question_style),
+            Span::styled(
+                if field.required { "" } else { " (optional)" },
+                Style::default().fg(Color::DarkGray),
+            ),
+        ]));
+        if !field.options.is_empty() {
+            let options = field
+                .options
+                .iter()
+                .enumerate()
+                .map(|(n, o)| format!("[{}] {}", n + 1, o))
+                .collect::<Vec<_>>()
+                .join("  ");
+            lines.push(Line::from(Span::styled(
+                format!("    {}", options),
+                Style::default().fg(Color::DarkGray),
+            )));
+        }
+        let cursor = if focused && state.cursor_visible {
+            "▏"
+        } else {
+            ""
+        };
+        lines.push(Line::from(vec![
+            Span::styled("    > ", Style::default().fg(Color::Cyan)),
+            Span::styled(
+                format!("{}{}", field.answer, cursor),
+                Style::default().fg(Color::Cyan),
+            ),
+        ]));
+    }
+
+    lines.push(match &form.error {
+        Some(error) => Line::from(Span::styled(
+            error.clone(),
+            Style::default().fg(Color::LightRed),
+        )),
+        None => Line::from(Span::styled(
+            "Tab/↓ next field · Shift+Tab/↑ previous · Enter on the last field submits · Esc dismisses",
+            Style::default().fg(Color::DarkGray),
+        )),
+    });
+
+    let form_widget = Paragraph::new(lines).wrap(Wrap { trim: false }).block(
+        Block::default()
+            .borders(Borders::ALL)
+            .border_style(Style::default().fg(Color::LightYellow))
+            .title("Stakpak has some questions"),
+    );
+    f.render_widget(ratatui::widgets::Clear, area);
+    f.render_widget(form_widget, area);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use stakpak_shared::models::integrations::openai::FunctionCall;
+
+    fn ask_user_call(arguments: &str) -> ToolCall {
+        ToolCall {
+            id: "call_1".to_string(),
+            r#type: "function".to_string(),
+            function: FunctionCall {
+                name: "ask_user".to_string(),
+                arguments: arguments.to_string(),
+            },
+        }
+    }
+
+    #[test]
+    fn test_validate_answers() {
+        let mut form = QuestionForm::from_tool_call(&ask_user_call(
+            r#"{"questions": ["Cluster name?", {"question": "Environment?", "options": ["staging", "production"]}, {"question": "Notes?", "required": false}]}"#,
+        ))
+        .unwrap_or_else(|e| panic!("{}", e));
+        assert_eq!(form.fields.len(), 3);
+
+        // Missing required answer focuses the first field
+        form.selected = 2;
+        assert!(form.validate().is_err());
+        assert_eq!(form.selected, 0);
+
+        form.insert_str("prod-eu");
+        form.next_field();
+        form.insert_str("dev");
+        assert!(form.validate().is_err());
+        assert_eq!(form.selected, 1);
+
+        form.backspace();
+        form.backspace();
+        form.backspace();
+        form.insert_str("2");
+        let answers = form.validate().unwrap_or_else(|e| panic!("{}", e));
+        assert_eq!(answers[0].answer, "prod-eu");
+        assert_eq!(answers[1].answer, "production");
+        assert_eq!(answers[2].answer, "");
+    }
+}
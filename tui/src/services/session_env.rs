@@ -0,0 +1,169 @@
+use crate::app::{AppState, OutputEvent};
+use crate::services::helper_block::render_system_message;
+use crate::services::message::{Message, MessageContent};
+use ratatui::style::{Color, Modifier, Style};
+use ratatui::text::{Line, Span};
+use stakpak_shared::session_env::{load_session_env, validate_env_name};
+use tokio::sync::mpsc::Sender;
+use uuid::Uuid;
+
+const ENV_USAGE: &str = "Usage: /env, /env set NAME=value, /env secret NAME, /env unset NAME";
+
+#[derive(Debug, PartialEq)]
+pub enum EnvCommand {
+    Show,
+    Set {
+        name: String,
+        value: String,
+    },
+    /// Ask for the value with a masked input
+    Secret(String),
+    Unset(String),
+}
+
+/// Parse `/env [set NAME=value | secret NAME | unset NAME]`
+pub fn parse_env_command(input: &str) -> Result<EnvCommand, String> {
+    let rest = input
+        .trim()
+        .strip_prefix("/env")
+        .ok_or_else(|| ENV_USAGE.to_string())?
+        .trim();
+    if rest.is_empty() {
+        return Ok(EnvCommand::Show);
+    }
+    let (action, argument) = rest.split_once(' ').unwrap_or((rest, ""));
+    let argument = argument.trim();
+    let command = match action {
+        "set" => {
+            let (name, value) = argument
+                .split_once('=')
+                .ok_or_else(|| ENV_USAGE.to_string())?;
+            EnvCommand::Set {
+                name: name.trim().to_string(),
+                value: value.trim().trim_matches('"').to_string(),
+            }
+        }
+        "secret" => EnvCommand::Secret(argument.to_string()),
+        "unset" => EnvCommand::Unset(argument.to_string()),
+        _ => return Err(ENV_USAGE.to_string()),
+    };
+    let (EnvCommand::Set { name, .. } | EnvCommand::Secret(name) | EnvCommand::Unset(name)) =
+        &command
+    else {
+        return Ok(command);
+    };
+    validate_env_name(name)?;
+    Ok(command)
+}
+
+fn render_session_env(state: &mut AppState) {
+    let mut lines = vec![
+        Line::from(Span::styled(
+            "🌱 Session environment",
+            Style::default()
+                .fg(Color::Cyan)
+                .add_modifier(Modifier::BOLD),
+        )),
+        Line::from(""),
+    ];
+    let env = load_session_env();
+    if env.is_empty() {
+        lines.push(Line::from(Span::styled(
+            "  No variables set",
+            Style::default().fg(Color::DarkGray),
+        )));
+    }
+    for (name, var) in env {
+        lines.push(Line::from(vec![
+            Span::styled(format!("  {}", name), Style::default().fg(Color::Yellow)),
+            Span::raw(format!("={}", var.value)),
+            Span::styled(
+                if var.secret { "  (secret)" } else { "" },
+                Style::default().fg(Color::DarkGray),
+            ),
+        ]));
+    }
+    lines.push(Line::from(""));
+    lines.push(Line::from(Span::styled(
+        format!("  {}", ENV_USAGE),
+        Style::default().fg(Color::DarkGray),
+    )));
+    lines.push(Line::from(""));
+    state.messages.push(Message {
+        id: Uuid::new_v4(),
+        content: MessageContent::StyledBlock(lines),
+    });
+}
+
+/// Run a parsed `/env` command
+pub fn run_env_command(state: &mut AppState, command: EnvCommand, output_tx: &Sender<OutputEvent>) {
+    match command {
+        EnvCommand::Show => render_session_env(state),
+        EnvCommand::Set { name, value } => {
+            let _ = output_tx.try_send(OutputEvent::SetSessionEnv {
+                name,
+                value,
+                secret: false,
+            });
+        }
+        EnvCommand::Secret(name) => {
+            render_system_message(
+                state,
+                &format!(
+                    "Type the value of {} and press enter, it stays hidden and the agent only sees a placeholder. Esc to cancel.",
+                    name
+                ),
+            );
+            state.secret_env_name = Some(name);
+        }
+        EnvCommand::Unset(name) => {
+            let _ = output_tx.try_send(OutputEvent::UnsetSessionEnv(name));
+        }
+    }
+}
+
+/// Send the typed secret value of the variable `/env secret` asked for
+pub fn submit_secret_env(state: &mut AppState, output_tx: &Sender<OutputEvent>) {
+    let Some(name) = state.secret_env_name.take() else {
+        return;
+    };
+    let value = std::mem::take(&mut state.input);
+    state.cursor_position = 0;
+    if value.is_empty() {
+        render_system_message(state, &format!("No value given, {} was not set", name));
+        return;
+    }
+    let _ = output_tx.try_send(OutputEvent::SetSessionEnv {
+        name,
+        value,
+        secret: true,
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_env_command() {
+        assert_eq!(parse_env_command("/env"), Ok(EnvCommand::Show));
+        assert_eq!(
+            parse_env_command("/env set AWS_PROFILE=staging"),
+            Ok(EnvCommand::Set {
+                name: "AWS_PROFILE".to_string(),
+                value: "staging".to_string(),
+            })
+        );
+        assert_eq!(
+            parse_env_command("/env secret DB_PASSWORD"),
+            Ok(EnvCommand::Secret("DB_PASSWORD".to_string()))
+        );
+        assert_eq!(
+            parse_env_command("/env unset AWS_PROFILE"),
+            Ok(EnvCommand::Unset("AWS_PROFILE".to_string()))
+        );
+        assert!(parse_env_command("/env set NOVALUE").is_err());
+        assert!(parse_env_command("/env secret 1BAD").is_err());
+        assert!(parse_env_command("/env list").is_err());
+    }
+}
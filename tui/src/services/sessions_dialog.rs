@@ -1,5 +1,5 @@
 use crate::app::AppState;
-use crate::services::message::get_wrapped_message_lines;
+use crate::services::message::wrapped_line_count;
 use ratatui::{
     Frame,
     layout::{Alignment, Rect},
@@ -12,8 +12,7 @@ pub fn render_sessions_dialog(f: &mut Frame, state: &AppState) {
     let screen = f.area();
     let dialog_height = 12;
 
-    let message_lines = get_wrapped_message_lines(&state.messages, screen.width as usize);
-    let mut last_message_y = message_lines.len() as u16 + 1; // +1 for a gap
+    let mut last_message_y = wrapped_line_count(state, screen.width as usize) as u16 + 1; // +1 for a gap
     if last_message_y + dialog_height > screen.height {
         last_message_y = screen.height.saturating_sub(dialog_height + 1);
     }
@@ -0,0 +1,187 @@
+use crate::app::{AppState, OutputEvent, ShellCommandResult};
+use crate::services::bash_block::render_styled_block;
+use crate::services::helper_block::render_system_message;
+use crate::services::message::{BubbleColors, Message};
+use ratatui::style::Color;
+use tokio::sync::mpsc::Sender;
+use uuid::Uuid;
+
+/// Output lines kept in the shell block, older lines scroll out
+const MAX_SHELL_LINES: usize = 200;
+
+/// Extract the command out of `!<command>`
+pub fn parse_shell_escape(input: &str) -> Option<String> {
+    let command = input.trim().strip_prefix('!')?.trim();
+    if command.is_empty() {
+        return None;
+    }
+    Some(command.to_string())
+}
+
+fn shell_colors() -> BubbleColors {
+    BubbleColors {
+        border_color: Color::Magenta,
+        title_color: Color::White,
+        content_color: Color::Gray,
+        tool_type: "shell".to_string(),
+    }
+}
+
+fn render_shell_block(state: &mut AppState, id: Uuid, title: &str) {
+    let content = state.shell_outputs.get(&id).cloned().unwrap_or_default();
+    state.messages.retain(|m| m.id != id);
+    render_styled_block(
+        &content,
+        "Shell",
+        title,
+        Some(shell_colors()),
+        state,
+        "shell",
+        Some(id),
+    );
+}
+
+/// Run `command` on the host, its output is never sent to the model unless shared
+pub fn start_shell_escape(state: &mut AppState, command: String, output_tx: &Sender<OutputEvent>) {
+    let id = Uuid::new_v4();
+    state
+        .messages
+        .push(Message::user(format!("! {}", command), None));
+    state.shell_outputs.insert(id, String::new());
+    let _ = output_tx.try_send(OutputEvent::RunShellCommand(id, command));
+}
+
+pub fn handle_shell_output(state: &mut AppState, id: Uuid, line: String) {
+    let Some(buffer) = state.shell_outputs.get_mut(&id) else {
+        return;
+    };
+    buffer.push_str(&line);
+    buffer.push('\n');
+    let lines: Vec<&str> = buffer.lines().collect();
+    if lines.len() > MAX_SHELL_LINES {
+        *buffer = lines[lines.len() - MAX_SHELL_LINES..].join("\n") + "\n";
+    }
+    render_shell_block(state, id, "Running");
+}
+
+pub fn handle_shell_finished(state: &mut AppState, id: Uuid, result: ShellCommandResult) {
+    let title = match result.exit_code {
+        Some(0) => "Done".to_string(),
+        Some(code) => format!("Exited with code {}", code),
+        None => "Terminated".to_string(),
+    };
+    render_shell_block(state, id, &title);
+    state.shell_outputs.remove(&id);
+
+    render_system_message(
+        state,
+        &format!(
+            "Share the output of `{}` with the agent? Press y to share or n to dismiss",
+            result.command
+        ),
+    );
+    state.pending_shell_share = Some(result);
+}
+
+/// Answer the share prompt of the last shell escape, only y and n on an empty input
+/// are taken so typing a message is never swallowed
+pub fn handle_shell_share_key(
+    state: &mut AppState,
+    c: char,
+    output_tx: &Sender<OutputEvent>,
+) -> bool {
+    if state.pending_shell_share.is_none() || !state.input.is_empty() {
+        return false;
+    }
+    match c {
+        'y' | 'Y' => {
+            if let Some(result) = state.pending_shell_share.take() {
+                render_system_message(
+                    state,
+                    &format!("Shared the output of `{}` with the agent", result.command),
+                );
+                let _ = output_tx.try_send(OutputEvent::ShareShellOutput(result));
+            }
+            true
+        }
+        'n' | 'N' => {
+            state.pending_shell_share = None;
+            true
+        }
+        _ => false,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn result(exit_code: Option<i32>) -> ShellCommandResult {
+        ShellCommandResult {
+            command: "git status".to_string(),
+            output: "nothing to commit".to_string(),
+            exit_code,
+        }
+    }
+
+    #[test]
+    fn test_parse_shell_escape() {
+        assert_eq!(
+            parse_shell_escape(" ! git status "),
+            Some("git status".to_string())
+        );
+        assert_eq!(parse_shell_escape("!"), None);
+        assert_eq!(parse_shell_escape("git status"), None);
+    }
+
+    #[test]
+    fn test_shell_output_keeps_the_last_lines() {
+        let mut state = AppState::new(Vec::new(), None);
+        let (output_tx, mut output_rx) = tokio::sync::mpsc::channel(8);
+        start_shell_escape(&mut state, "seq 300".to_string(), &output_tx);
+        let Ok(OutputEvent::RunShellCommand(id, command)) = output_rx.try_recv() else {
+            panic!("the command was not sent");
+        };
+        assert_eq!(command, "seq 300");
+
+        for line in 1..=MAX_SHELL_LINES + 50 {
+            handle_shell_output(&mut state, id, line.to_string());
+        }
+        let buffer = &state.shell_outputs[&id];
+        assert_eq!(buffer.lines().count(), MAX_SHELL_LINES);
+        assert_eq!(buffer.lines().next(), Some("51"));
+        // One shell block, re-rendered in place
+        assert_eq!(state.messages.iter().filter(|m| m.id == id).count(), 1);
+
+        // Output of an unknown command is dropped
+        handle_shell_output(&mut state, Uuid::new_v4(), "stray".to_string());
+        assert_eq!(state.shell_outputs.len(), 1);
+
+        handle_shell_finished(&mut state, id, result(Some(0)));
+        assert!(state.shell_outputs.is_empty());
+        assert!(state.pending_shell_share.is_some());
+    }
+
+    #[test]
+    fn test_share_prompt() {
+        let mut state = AppState::new(Vec::new(), None);
+        let (output_tx, mut output_rx) = tokio::sync::mpsc::channel(8);
+        assert!(!handle_shell_share_key(&mut state, 'y', &output_tx));
+
+        state.pending_shell_share = Some(result(Some(1)));
+        state.input = "y".to_string();
+        assert!(!handle_shell_share_key(&mut state, 'y', &output_tx));
+        state.input.clear();
+        assert!(!handle_shell_share_key(&mut state, 'x', &output_tx));
+        assert!(handle_shell_share_key(&mut state, 'y', &output_tx));
+        assert!(matches!(
+            output_rx.try_recv(),
+            Ok(OutputEvent::ShareShellOutput(shared)) if shared == result(Some(1))
+        ));
+
+        state.pending_shell_share = Some(result(None));
+        assert!(handle_shell_share_key(&mut state, 'N', &output_tx));
+        assert!(state.pending_shell_share.is_none());
+        assert!(output_rx.try_recv().is_err());
+    }
+}
@@ -1,13 +1,32 @@
 use crate::app::{AppState, InputEvent, LoadingType, OutputEvent};
 use crate::services::bash_block::{
-    render_bash_block, render_bash_block_rejected, render_styled_block,
+    render_bash_block, render_bash_block_rejected, render_styled_block, render_triage_block,
+};
+use crate::services::citations::push_citations;
+use crate::services::command_palette::{PaletteAction, open_command_palette};
+use crate::services::confirmation_dialog::{
+    assess_tool_call, is_allowed_by_workspace_policy, is_confirmation_satisfied, remember_approval,
+    start_confirmation_countdown,
+};
+use crate::services::file_history::{
+    close_file_history, open_blame, open_file_history, parse_blame_path, parse_file_history_path,
+    step_file_history,
 };
 use crate::services::helper_block::{
-    push_error_message, push_help_message, push_status_message, render_system_message,
+    push_error_message, push_help_message, push_status_message, push_usage_message,
+    render_system_message,
 };
-use crate::services::message::{Message, MessageContent, get_wrapped_message_lines};
-use ratatui::layout::Size;
-use stakpak_shared::models::integrations::openai::ToolCallResultProgress;
+use crate::services::message::{Message, MessageContent, reflow_scroll, wrapped_line_count};
+use crate::services::path_completion::complete_input_path;
+use crate::services::pins::{mark_pinned, parse_pin_command, run_pin_command};
+use crate::services::question_form::{handle_form_cancel, handle_form_submit, open_question_form};
+use crate::services::session_env::{parse_env_command, run_env_command, submit_secret_env};
+use crate::services::shell_escape::{
+    handle_shell_finished, handle_shell_output, handle_shell_share_key, parse_shell_escape,
+    start_shell_escape,
+};
+use stakpak_shared::models::integrations::openai::{ToolCall, ToolCallResultProgress};
+use stakpak_shared::policy_rules::PolicyAction;
 use tokio::sync::mpsc::Sender;
 use uuid::Uuid;
 
@@ -19,9 +38,14 @@ pub fn update(
     message_area_height: usize,
     message_area_width: usize,
     output_tx: &Sender<OutputEvent>,
-    terminal_size: Size,
 ) {
     state.scroll = state.scroll.max(0);
+    if handle_question_form_input(state, &event, output_tx)
+        || handle_command_palette_input(state, &event, message_area_height, output_tx)
+    {
+        adjust_scroll(state, message_area_height, message_area_width);
+        return;
+    }
     match event {
         InputEvent::Up => {
             if state.show_sessions_dialog {
@@ -53,7 +77,11 @@ pub fn update(
         }
         InputEvent::DropdownUp => handle_dropdown_up(state),
         InputEvent::DropdownDown => handle_dropdown_down(state),
-        InputEvent::InputChanged(c) => handle_input_changed(state, c),
+        InputEvent::InputChanged(c) => {
+            if !handle_shell_share_key(state, c, output_tx) {
+                handle_input_changed(state, c)
+            }
+        }
         InputEvent::InputBackspace => handle_input_backspace(state),
         InputEvent::InputSubmitted => {
             if !state.is_pasting {
@@ -67,8 +95,15 @@ pub fn update(
         InputEvent::StreamAssistantMessage(id, s) => {
             handle_stream_message(state, id, s, message_area_height)
         }
-        InputEvent::StreamToolResult(progress) => {
-            handle_stream_tool_result(state, progress, terminal_size)
+        InputEvent::StreamToolResult(progress) => handle_stream_tool_result(state, progress),
+        InputEvent::ShellOutput(id, line) => handle_shell_output(state, id, line),
+        InputEvent::ShellFinished(id, result) => handle_shell_finished(state, id, result),
+        InputEvent::ErrorTriage(triage) => render_triage_block(&triage, state),
+        InputEvent::AskUser(tool_call) => {
+            if let Err(error) = open_question_form(state, &tool_call) {
+                push_error_message(state, &error);
+                let _ = output_tx.try_send(OutputEvent::RejectTool(tool_call));
+            }
         }
         InputEvent::ScrollUp => handle_scroll_up(state),
         InputEvent::ScrollDown => {
@@ -77,6 +112,12 @@ pub fn update(
         InputEvent::PageUp => handle_page_up(state, message_area_height),
         InputEvent::PageDown => handle_page_down(state, message_area_height, message_area_width),
         InputEvent::Quit => {}
+        InputEvent::CursorLeft if state.file_history.is_some() && state.input.is_empty() => {
+            step_file_history(state, -1)
+        }
+        InputEvent::CursorRight if state.file_history.is_some() && state.input.is_empty() => {
+            step_file_history(state, 1)
+        }
         InputEvent::CursorLeft => {
             if state.cursor_position > 0 {
                 let prev = state.input[..state.cursor_position]
@@ -98,23 +139,64 @@ pub fn update(
             }
         }
         InputEvent::ToggleCursorVisible => state.cursor_visible = !state.cursor_visible,
-        InputEvent::ShowConfirmationDialog(tool_call) => {
-            state.is_dialog_open = true;
-            state.dialog_command = Some(tool_call.clone());
+        InputEvent::ShowConfirmationDialog(tool_call)
+            if is_allowed_by_workspace_policy(&tool_call) =>
+        {
             let full_command = extract_full_command_arguments(&tool_call);
-            let message_id =
-                render_bash_block(&tool_call, &full_command, false, state, terminal_size);
-            state.pending_bash_message_id = Some(message_id);
+            render_bash_block(&tool_call, &full_command, true, state);
+            render_system_message(state, "Allowed by the workspace policy");
+            let _ = output_tx.try_send(OutputEvent::AcceptTool(tool_call));
+        }
+        InputEvent::ShowConfirmationDialog(tool_call) => {
+            open_confirmation_dialog(state, tool_call);
         }
+        InputEvent::ApplyToolPolicy(tool_call, decision) => match decision.action {
+            PolicyAction::Allow => {
+                let full_command = extract_full_command_arguments(&tool_call);
+                render_bash_block(&tool_call, &full_command, true, state);
+                render_system_message(
+                    state,
+                    &format!("Allowed by policy rule '{}'", decision.rule),
+                );
+                let _ = output_tx.try_send(OutputEvent::AcceptTool(tool_call));
+            }
+            PolicyAction::Prompt => open_confirmation_dialog(state, tool_call),
+            PolicyAction::Deny => {
+                render_bash_block_rejected(&extract_truncated_command_arguments(&tool_call), state);
+                render_system_message(state, &format!("Denied by policy rule '{}'", decision.rule));
+                let _ = output_tx.try_send(OutputEvent::RejectTool(tool_call));
+            }
+        },
 
+        InputEvent::Resized(..) => {
+            // Keep the same part of the history in view, re-wrapped for the new width
+            if !state.stay_at_bottom
+                && state.message_area_width != 0
+                && state.message_area_width != message_area_width
+            {
+                state.scroll = reflow_scroll(
+                    &state.messages,
+                    state.message_area_width,
+                    message_area_width,
+                    state.scroll,
+                );
+            }
+        }
         InputEvent::Loading(is_loading) => {
             state.loading = is_loading;
+            if !is_loading {
+                state.stream_metrics = None;
+            }
         }
+        InputEvent::StreamMetrics(metrics) => state.stream_metrics = Some(metrics),
+        InputEvent::Citations(citations) => push_citations(state, citations),
         InputEvent::HandleEsc => handle_esc(state, output_tx),
 
         InputEvent::GetStatus(account_info) => {
             state.account_info = account_info;
         }
+        InputEvent::UsageReport(report) => state.usage_report = report,
+        InputEvent::MessagePinned(text) => mark_pinned(state, text),
         InputEvent::Tab => handle_tab(state),
         InputEvent::SetSessions(sessions) => {
             state.sessions = sessions;
@@ -123,6 +205,9 @@ pub fn update(
             state.loading_type = LoadingType::Llm;
             state.show_sessions_dialog = true;
         }
+        InputEvent::SystemMessage(message) => {
+            render_system_message(state, &message);
+        }
         InputEvent::Error(error) => {
             push_error_message(state, &error);
         }
@@ -137,7 +222,98 @@ pub fn update(
     adjust_scroll(state, message_area_height, message_area_width);
 }
 
-fn handle_tab(_state: &mut AppState) {}
+fn handle_tab(state: &mut AppState) {
+    complete_input_path(state);
+}
+
+/// Route keyboard input to the question form while it is open
+fn handle_question_form_input(
+    state: &mut AppState,
+    event: &InputEvent,
+    output_tx: &Sender<OutputEvent>,
+) -> bool {
+    let Some(form) = state.question_form.as_mut() else {
+        return false;
+    };
+    match event {
+        InputEvent::InputChanged(c) => form.insert_str(&c.to_string()),
+        InputEvent::HandlePaste(text) => form.insert_str(text),
+        InputEvent::InputBackspace => form.backspace(),
+        InputEvent::Tab | InputEvent::Down => form.next_field(),
+        InputEvent::BackTab | InputEvent::Up => form.previous_field(),
+        InputEvent::InputChangedNewline => {}
+        InputEvent::InputSubmitted => handle_form_submit(state, output_tx),
+        InputEvent::HandleEsc => handle_form_cancel(state, output_tx),
+        _ => return false,
+    }
+    true
+}
+
+/// Route keyboard input to the command palette while it is open
+fn handle_command_palette_input(
+    state: &mut AppState,
+    event: &InputEvent,
+    message_area_height: usize,
+    output_tx: &Sender<OutputEvent>,
+) -> bool {
+    if let InputEvent::OpenCommandPalette = event {
+        // The confirmation dialog and question form keep the keyboard until answered
+        if !state.is_dialog_open && state.question_form.is_none() {
+            open_command_palette(state);
+        }
+        return true;
+    }
+    let Some(palette) = state.command_palette.as_mut() else {
+        return false;
+    };
+    match event {
+        InputEvent::InputChanged(c) => palette.insert_str(&c.to_string()),
+        InputEvent::HandlePaste(text) => palette.insert_str(text),
+        InputEvent::InputBackspace => palette.backspace(),
+        InputEvent::Down | InputEvent::Tab => palette.next(),
+        InputEvent::Up | InputEvent::BackTab => palette.previous(),
+        InputEvent::HandleEsc => state.command_palette = None,
+        InputEvent::InputSubmitted => {
+            let action = palette.selected_action();
+            state.command_palette = None;
+            if let Some(action) = action {
+                run_palette_action(state, action, message_area_height, output_tx);
+            }
+        }
+        _ => return false,
+    }
+    true
+}
+
+fn run_palette_action(
+    state: &mut AppState,
+    action: PaletteAction,
+    message_area_height: usize,
+    output_tx: &Sender<OutputEvent>,
+) {
+    match action {
+        PaletteAction::Command(command) => {
+            // Same path as picking the command from the helper dropdown
+            state.input = command.to_string();
+            state.cursor_position = state.input.len();
+            state.show_helper_dropdown = true;
+            state.filtered_helpers = vec![command];
+            state.helper_selected = 0;
+            handle_input_submitted(state, message_area_height, output_tx);
+        }
+        PaletteAction::OpenFileHistory(path) => {
+            if let Err(error) = open_file_history(state, &path) {
+                push_error_message(state, &error);
+            }
+        }
+        PaletteAction::SwitchSession { id, title } => {
+            let _ = output_tx.try_send(OutputEvent::SwitchToSession(id));
+            state.messages.clear();
+            state.pinned_messages.clear();
+            render_system_message(state, &format!("Switching to session . {}", title));
+        }
+    }
+}
 
 fn handle_dropdown_up(state: &mut AppState) {
     if state.show_helper_dropdown
@@ -165,6 +341,11 @@ fn handle_input_changed(state: &mut AppState, c: char) {
         return;
     }
 
+    // Someone is at the keyboard, leave the decision to them
+    if state.is_dialog_open {
+        state.dialog_deadline = None;
+    }
+
     let pos = state.cursor_position.min(state.input.len());
     state.input.insert(pos, c);
     state.cursor_position = pos + c.len_utf8();
@@ -221,11 +402,26 @@ fn handle_input_backspace(state: &mut AppState) {
     }
 }
 
+fn open_confirmation_dialog(state: &mut AppState, tool_call: ToolCall) {
+    state.command_palette = None;
+    state.is_dialog_open = true;
+    state.dialog_risk = Some(assess_tool_call(&tool_call));
+    state.dialog_command = Some(tool_call.clone());
+    start_confirmation_countdown(state);
+    let full_command = extract_full_command_arguments(&tool_call);
+    let message_id = render_bash_block(&tool_call, &full_command, false, state);
+    state.pending_bash_message_id = Some(message_id);
+}
+
 fn handle_esc(state: &mut AppState, output_tx: &Sender<OutputEvent>) {
     if state.show_sessions_dialog {
         state.show_sessions_dialog = false;
     } else if state.show_helper_dropdown {
         state.show_helper_dropdown = false;
+    } else if state.file_history.is_some() {
+        close_file_history(state);
+    } else if state.secret_env_name.take().is_some() {
+        render_system_message(state, "Secret variable not set");
     } else if state.is_dialog_open {
         let tool_call_opt = state.dialog_command.clone();
         if let Some(tool_call) = &tool_call_opt {
@@ -235,6 +431,8 @@ fn handle_esc(state: &mut AppState, output_tx: &Sender<OutputEvent>) {
         }
         state.is_dialog_open = false;
         state.dialog_command = None;
+        state.dialog_risk = None;
+        state.dialog_deadline = None;
     }
 
     state.input.clear();
@@ -247,20 +445,31 @@ fn handle_input_submitted(
     output_tx: &Sender<OutputEvent>,
 ) {
     let input_height = 3;
-    if state.show_sessions_dialog {
+    if state.secret_env_name.is_some() {
+        // The typed value is a secret, it never becomes a message
+        submit_secret_env(state, output_tx);
+    } else if state.show_sessions_dialog {
         let selected = &state.sessions[state.session_selected];
         let _ = output_tx.try_send(OutputEvent::SwitchToSession(selected.id.to_string()));
         state.messages.clear();
+        state.pinned_messages.clear();
         render_system_message(state, &format!("Switching to session . {}", selected.title));
         state.show_sessions_dialog = false;
     } else if state.is_dialog_open {
+        // High risk tool calls are only accepted once the confirmation has been typed
+        if state.dialog_selected == 0 && !is_confirmation_satisfied(state) {
+            state.input.clear();
+            state.cursor_position = 0;
+            return;
+        }
         state.is_dialog_open = false;
         state.input.clear();
         state.cursor_position = 0;
 
         if state.dialog_selected == 0 {
-            if let Some(tool_call) = &state.dialog_command {
+            if let Some(tool_call) = state.dialog_command.clone() {
                 let _ = output_tx.try_send(OutputEvent::AcceptTool(tool_call.clone()));
+                remember_approval(state, &tool_call);
             }
         } else {
             // Clone dialog_command before mutating state
@@ -272,6 +481,8 @@ fn handle_input_submitted(
         }
 
         state.dialog_command = None;
+        state.dialog_risk = None;
+        state.dialog_deadline = None;
     } else if state.show_helper_dropdown && !state.filtered_helpers.is_empty() {
         let selected = state.filtered_helpers[state.helper_selected];
 
@@ -299,6 +510,59 @@ fn handle_input_submitted(
                 state.show_helper_dropdown = false;
                 return;
             }
+            "/usage" => {
+                push_usage_message(state);
+                state.input.clear();
+                state.cursor_position = 0;
+                state.show_helper_dropdown = false;
+                return;
+            }
+            "/checkpoint" => {
+                state.input = "/checkpoint label ".to_string();
+                state.cursor_position = state.input.len();
+                state.show_helper_dropdown = false;
+                return;
+            }
+            "/filehistory" => {
+                state.input = "/filehistory ".to_string();
+                state.cursor_position = state.input.len();
+                state.show_helper_dropdown = false;
+                return;
+            }
+            "/blame" => {
+                state.input = "/blame ".to_string();
+                state.cursor_position = state.input.len();
+                state.show_helper_dropdown = false;
+                return;
+            }
+            "/env" => {
+                state.input = "/env ".to_string();
+                state.cursor_position = state.input.len();
+                state.show_helper_dropdown = false;
+                return;
+            }
+            "/compact" => {
+                let _ = output_tx.try_send(OutputEvent::CompactHistory);
+                state.input.clear();
+                state.cursor_position = 0;
+                state.show_helper_dropdown = false;
+                return;
+            }
+            "/pin" | "/pins" => {
+                if let Ok(command) = parse_pin_command(selected) {
+                    run_pin_command(state, command, output_tx);
+                }
+                state.input.clear();
+                state.cursor_position = 0;
+                state.show_helper_dropdown = false;
+                return;
+            }
+            "/unpin" => {
+                state.input = "/unpin ".to_string();
+                state.cursor_position = state.input.len();
+                state.show_helper_dropdown = false;
+                return;
+            }
             "/quit" => {
                 state.show_helper_dropdown = false;
                 state.input.clear();
@@ -329,6 +593,61 @@ fn handle_input_submitted(
         }
         state.loading = true;
         state.spinner_frame = 0;
+    } else if state.input.trim().starts_with("/checkpoint") {
+        match parse_checkpoint_label(&state.input) {
+            Some(label) => {
+                let _ = output_tx.try_send(OutputEvent::LabelCheckpoint(label));
+            }
+            None => push_error_message(state, "Usage: /checkpoint label <name>"),
+        }
+        state.input.clear();
+        state.cursor_position = 0;
+        state.show_helper_dropdown = false;
+    } else if state.input.trim().starts_with('!') {
+        match parse_shell_escape(&state.input) {
+            Some(command) => start_shell_escape(state, command, output_tx),
+            None => push_error_message(state, "Usage: !<command>"),
+        }
+        state.input.clear();
+        state.cursor_position = 0;
+    } else if state.input.trim().starts_with("/filehistory") {
+        let result = match parse_file_history_path(&state.input) {
+            Some(path) => open_file_history(state, &path),
+            None => Err("Usage: /filehistory <path>".to_string()),
+        };
+        if let Err(error) = result {
+            push_error_message(state, &error);
+        }
+        state.input.clear();
+        state.cursor_position = 0;
+        state.show_helper_dropdown = false;
+    } else if state.input.trim().starts_with("/blame") {
+        let result = match parse_blame_path(&state.input) {
+            Some(path) => open_blame(state, &path),
+            None => Err("Usage: /blame <path>".to_string()),
+        };
+        if let Err(error) = result {
+            push_error_message(state, &error);
+        }
+        state.input.clear();
+        state.cursor_position = 0;
+        state.show_helper_dropdown = false;
+    } else if state.input.trim().starts_with("/pin") || state.input.trim().starts_with("/unpin") {
+        match parse_pin_command(&state.input) {
+            Ok(command) => run_pin_command(state, command, output_tx),
+            Err(error) => push_error_message(state, &error),
+        }
+        state.input.clear();
+        state.cursor_position = 0;
+        state.show_helper_dropdown = false;
+    } else if state.input.trim().starts_with("/env") {
+        match parse_env_command(&state.input) {
+            Ok(command) => run_env_command(state, command, output_tx),
+            Err(error) => push_error_message(state, &error),
+        }
+        state.input.clear();
+        state.cursor_position = 0;
+        state.show_helper_dropdown = false;
     } else if !state.input.trim().is_empty() && !state.input.trim().starts_with('/') {
         let total_lines = state.messages.len() * 2;
         let max_visible_lines = std::cmp::max(1, message_area_height.saturating_sub(input_height));
@@ -351,6 +670,20 @@ fn handle_input_submitted(
     }
 }
 
+/// Extract the label out of `/checkpoint label <name>`, the name may be quoted
+fn parse_checkpoint_label(input: &str) -> Option<String> {
+    let mut parts = input.trim().strip_prefix("/checkpoint")?.split_whitespace();
+    if parts.next()? != "label" {
+        return None;
+    }
+    let label = parts.collect::<Vec<_>>().join(" ");
+    let label = label.trim_matches('"').trim();
+    if label.is_empty() {
+        return None;
+    }
+    Some(label.to_string())
+}
+
 fn handle_input_submitted_with(state: &mut AppState, s: String, message_area_height: usize) {
     let input_height = 3;
     let total_lines = state.messages.len() * 2;
@@ -399,11 +732,7 @@ fn handle_stream_message(state: &mut AppState, id: Uuid, s: String, message_area
     }
 }
 
-fn handle_stream_tool_result(
-    state: &mut AppState,
-    progress: ToolCallResultProgress,
-    terminal_size: Size,
-) {
+fn handle_stream_tool_result(state: &mut AppState, progress: ToolCallResultProgress) {
     let tool_call_id = progress.id;
     state.streaming_tool_result_id = Some(tool_call_id);
     // 1. Update the buffer for this tool_call_id
@@ -430,7 +759,6 @@ fn handle_stream_tool_result(
         "Result",
         None,
         state,
-        terminal_size,
         "Streaming",
         Some(tool_call_id),
     );
@@ -444,8 +772,7 @@ fn handle_scroll_up(state: &mut AppState) {
 }
 
 fn handle_scroll_down(state: &mut AppState, message_area_height: usize, message_area_width: usize) {
-    let all_lines = get_wrapped_message_lines(&state.messages, message_area_width);
-    let total_lines = all_lines.len();
+    let total_lines = wrapped_line_count(state, message_area_width);
     let max_scroll = total_lines.saturating_sub(message_area_height);
     if state.scroll < max_scroll {
         state.scroll += 1;
@@ -468,8 +795,7 @@ fn handle_page_up(state: &mut AppState, message_area_height: usize) {
 }
 
 fn handle_page_down(state: &mut AppState, message_area_height: usize, message_area_width: usize) {
-    let all_lines = get_wrapped_message_lines(&state.messages, message_area_width);
-    let total_lines = all_lines.len();
+    let total_lines = wrapped_line_count(state, message_area_width);
     let max_scroll = total_lines.saturating_sub(message_area_height);
     let page = std::cmp::max(1, message_area_height);
     if state.scroll < max_scroll {
@@ -483,8 +809,8 @@ fn handle_page_down(state: &mut AppState, message_area_height: usize, message_ar
 }
 
 fn adjust_scroll(state: &mut AppState, message_area_height: usize, message_area_width: usize) {
-    let all_lines = get_wrapped_message_lines(&state.messages, message_area_width);
-    let total_lines = all_lines.len();
+    state.message_area_width = message_area_width;
+    let total_lines = wrapped_line_count(state, message_area_width);
     let max_scroll = total_lines.saturating_sub(message_area_height);
     if state.stay_at_bottom {
         state.scroll = max_scroll;
@@ -1,13 +1,18 @@
 use crate::app::AppState;
-use crate::services::confirmation_dialog::render_confirmation_dialog;
+use crate::services::citations::hyperlink_citations;
+use crate::services::command_palette::render_command_palette;
+use crate::services::confirmation_dialog::{
+    confirmation_dialog_height, render_confirmation_dialog,
+};
 use crate::services::helper_block::render_loading_spinner;
 use crate::services::helper_dropdown::render_helper_dropdown;
 use crate::services::hint_helper::render_hint_or_shortcuts;
-use crate::services::message::get_wrapped_message_lines;
+use crate::services::message::with_wrapped_message_lines;
 use crate::services::message_pattern::{
     process_agent_mode_patterns, process_checkpoint_patterns, process_section_title_patterns,
     spans_to_string,
 };
+use crate::services::question_form::render_question_form;
 use crate::services::sessions_dialog::render_sessions_dialog;
 use ratatui::{
     Frame,
@@ -38,14 +43,17 @@ pub fn view(f: &mut Frame, state: &AppState) {
     };
     let hint_height = if dropdown_showing { 0 } else { margin_height };
 
-    let dialog_height = if state.show_sessions_dialog {
+    let dialog_height = if let Some(form) = &state.question_form {
+        form.height()
+    } else if state.show_sessions_dialog {
         11
     } else if state.is_dialog_open {
-        3
+        confirmation_dialog_height(state)
     } else {
         0
     };
-    let dialog_margin = if state.is_dialog_open || state.show_sessions_dialog {
+    let form_open = state.question_form.is_some();
+    let dialog_margin = if state.is_dialog_open || state.show_sessions_dialog || form_open {
         1
     } else {
         0
@@ -57,7 +65,7 @@ pub fn view(f: &mut Frame, state: &AppState) {
         Constraint::Length(dialog_margin),
         Constraint::Length(dialog_height),
     ];
-    if !state.show_sessions_dialog {
+    if !state.show_sessions_dialog && !form_open {
         constraints.push(Constraint::Length(input_height));
         constraints.push(Constraint::Length(dropdown_height));
         constraints.push(Constraint::Length(hint_height));
@@ -86,7 +94,7 @@ pub fn view(f: &mut Frame, state: &AppState) {
         width: 0,
         height: 0,
     };
-    if !state.show_sessions_dialog {
+    if !state.show_sessions_dialog && !form_open {
         input_area = chunks[3];
         dropdown_area = chunks.get(4).copied().unwrap_or(input_area);
         hint_area = chunks.get(5).copied().unwrap_or(input_area);
@@ -106,8 +114,12 @@ pub fn view(f: &mut Frame, state: &AppState) {
         render_confirmation_dialog(f, state);
     }
 
-    // Only render input, dropdown, and hint if dialog is not open and sessions dialog is not open
-    if !state.is_dialog_open && !state.show_sessions_dialog {
+    if form_open {
+        render_question_form(f, state, chunks[2]);
+    }
+
+    // Only render input, dropdown, and hint if no dialog or form is open
+    if !state.is_dialog_open && !state.show_sessions_dialog && !form_open {
         render_multiline_input(f, state, input_area);
         render_helper_dropdown(f, state, dropdown_area);
         if !dropdown_showing {
@@ -117,6 +129,8 @@ pub fn view(f: &mut Frame, state: &AppState) {
     if state.show_sessions_dialog {
         render_sessions_dialog(f, state);
     }
+    // Drawn last so it floats above everything else
+    render_command_palette(f, state);
 }
 
 // Calculate how many lines the input will take up when wrapped
@@ -177,12 +191,31 @@ fn calculate_input_lines(input: &str, width: usize) -> usize {
 
 fn render_messages(f: &mut Frame, state: &AppState, area: Rect, width: usize, height: usize) {
     f.render_widget(ratatui::widgets::Clear, area);
-    let mut all_lines: Vec<(Line, Style)> = get_wrapped_message_lines(&state.messages, width);
-    if state.loading {
-        let loading_line = render_loading_spinner(state);
-        all_lines.push((loading_line, Style::default()));
-    }
-    let total_lines = all_lines.len();
+    let screen_width = f.area().width as usize;
+    let visible_lines = with_wrapped_message_lines(state, width, |message_lines| {
+        visible_message_lines(state, message_lines, screen_width, height)
+    });
+    let message_widget = Paragraph::new(visible_lines).wrap(ratatui::widgets::Wrap { trim: false });
+    f.render_widget(message_widget, area);
+    hyperlink_citations(f.buffer_mut(), area, &state.citation_links);
+}
+
+/// Lines of the scrolled to part of the transcript, the loading spinner last
+fn visible_message_lines<'a>(
+    state: &'a AppState,
+    message_lines: &[(Line<'static>, Style)],
+    screen_width: usize,
+    height: usize,
+) -> Vec<Line<'a>> {
+    let loading_line = state.loading.then(|| render_loading_spinner(state));
+    let line_at = |index: usize| -> Option<Line<'a>> {
+        match message_lines.get(index) {
+            Some((line, _)) => Some(line.clone()),
+            None if index == message_lines.len() => loading_line.clone(),
+            None => None,
+        }
+    };
+    let total_lines = message_lines.len() + usize::from(loading_line.is_some());
     let max_scroll = total_lines.saturating_sub(height);
 
     let scroll = if state.stay_at_bottom {
@@ -200,14 +233,12 @@ fn render_messages(f: &mut Frame, state: &AppState, area: Rect, width: usize, he
             break; // Prevent overflow
         }
 
-        if let Some((line, _)) = all_lines.get(scroll + i) {
-            let line_text = spans_to_string(line);
+        if let Some(line) = line_at(scroll + i) {
+            let line_text = spans_to_string(&line);
 
             if line_text.contains("<checkpoint_id>") {
-                let processed = process_checkpoint_patterns(
-                    &[(line.clone(), Style::default())],
-                    f.area().width as usize,
-                );
+                let processed =
+                    process_checkpoint_patterns(&[(line.clone(), Style::default())], screen_width);
                 // Add processed lines but respect height limit
                 for (processed_line, _) in processed {
                     if lines_added < height {
@@ -263,7 +294,7 @@ fn render_messages(f: &mut Frame, state: &AppState, area: Rect, width: usize, he
                 }
 
                 if !found && lines_added < height {
-                    visible_lines.push(line.clone());
+                    visible_lines.push(line);
                     lines_added += 1;
                 }
             }
@@ -275,18 +306,24 @@ fn render_messages(f: &mut Frame, state: &AppState, area: Rect, width: usize, he
 
     // Ensure we don't exceed the allocated height
     visible_lines.truncate(height);
-
-    let message_widget = Paragraph::new(visible_lines).wrap(ratatui::widgets::Wrap { trim: false });
-    f.render_widget(message_widget, area);
+    visible_lines
 }
 
 fn render_multiline_input(f: &mut Frame, state: &AppState, area: Rect) {
-    // Make a copy of input to avoid borrowing issues
-    let input = state.input.clone();
+    // Make a copy of input to avoid borrowing issues, masking secret values
+    let (input, cursor_pos) = if state.secret_env_name.is_some() {
+        let masked_cursor = state
+            .input
+            .get(..state.cursor_position)
+            .map_or(0, |before| before.chars().count());
+        ("*".repeat(state.input.chars().count()), masked_cursor)
+    } else {
+        (state.input.clone(), state.cursor_position)
+    };
     let available_width = area.width.saturating_sub(4) as usize; // -4 for borders and padding
 
     // Ensure the cursor position is valid
-    let cursor_pos = state.cursor_position.min(input.len());
+    let cursor_pos = cursor_pos.min(input.len());
 
     // Split the input by newlines first
     let line_segments: Vec<&str> = input.split('\n').collect();